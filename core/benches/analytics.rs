@@ -0,0 +1,71 @@
+//! 分析聚合基准：10 万笔以上交易，单线程与默认线程池对比
+//!
+//! 运行：`cargo bench -p asset-manager-core --bench analytics`
+
+use asset_manager_core::analytics::{
+    attribution, sum_deltas_by_asset, DailyBalances, DateRange, GroupBy,
+};
+use asset_manager_core::asset::{Asset, AssetTransaction, AssetType, TransactionType};
+use chrono::{Duration, Utc};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use rayon::ThreadPool;
+
+const ASSETS: usize = 200;
+const TRANSACTIONS: usize = 120_000;
+
+fn fixture() -> (Vec<Asset>, Vec<AssetTransaction>) {
+    let start = Utc::now() - Duration::days(3 * 365);
+    let types = [AssetType::Stock, AssetType::Fund, AssetType::BankDeposit];
+    let assets: Vec<Asset> = (0..ASSETS)
+        .map(|i| Asset::new(format!("资产{}", i), types[i % types.len()].clone(), 0.0))
+        .collect();
+    let transactions = (0..TRANSACTIONS)
+        .map(|i| {
+            let asset = &assets[i % ASSETS];
+            let kind = match i % 4 {
+                0 => TransactionType::Income,
+                1 => TransactionType::Expense,
+                _ => TransactionType::Buy,
+            };
+            let before = (i / ASSETS) as f64 * 10.0;
+            AssetTransaction::new(asset.id, kind, before, before + 10.0)
+                .with_timestamp(start + Duration::minutes(i as i64 * 10))
+        })
+        .collect();
+    (assets, transactions)
+}
+
+fn pools() -> Vec<(&'static str, ThreadPool)> {
+    let single = rayon::ThreadPoolBuilder::new()
+        .num_threads(1)
+        .build()
+        .unwrap();
+    let default = rayon::ThreadPoolBuilder::new().build().unwrap();
+    vec![("sequential", single), ("parallel", default)]
+}
+
+fn bench_analytics(c: &mut Criterion) {
+    let (assets, transactions) = fixture();
+    let range = DateRange::new(Utc::now() - Duration::days(365), Utc::now());
+    let pools = pools();
+
+    let mut group = c.benchmark_group("analytics");
+    group.sample_size(20);
+    for (name, pool) in &pools {
+        group.bench_function(BenchmarkId::new("attribution", name), |b| {
+            b.iter(|| {
+                pool.install(|| attribution(&assets, &transactions, &range, GroupBy::AssetType))
+            })
+        });
+        group.bench_function(BenchmarkId::new("summary", name), |b| {
+            b.iter(|| pool.install(|| sum_deltas_by_asset(&transactions, |t| t.delta() > 0.0)))
+        });
+        group.bench_function(BenchmarkId::new("daily_balances", name), |b| {
+            b.iter(|| pool.install(|| DailyBalances::build(&assets, &transactions)))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_analytics);
+criterion_main!(benches);
@@ -0,0 +1,206 @@
+//! 收益归因：将资产增长拆分为资金投入/取出与市场收益
+
+use super::{transactions_by_asset, value_history, DateRange, ValuePoint};
+use crate::asset::{Asset, AssetTransaction, TransactionType};
+use chrono::{DateTime, Utc};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// 未设置标签的资产归入的分组
+pub const UNTAGGED_GROUP: &str = "untagged";
+
+/// 分组方式
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum GroupBy {
+    /// 按资产类型
+    AssetType,
+    /// 按标签（多标签资产会计入每个标签）
+    Tag,
+}
+
+/// 单个分组的归因结果
+///
+/// `end_value - start_value = contributions - withdrawals + income + market_gain`
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct AttributionGroup {
+    pub key: String,
+    /// 期初价值
+    pub start_value: f64,
+    /// 期末价值
+    pub end_value: f64,
+    /// 资金投入（买入、转入、期间新建资产的初始价值）
+    pub contributions: f64,
+    /// 资金取出（卖出、转出）
+    pub withdrawals: f64,
+    /// 收益净额（利息、分红减去手续费）
+    pub income: f64,
+    /// 市场涨跌（价值变动及未记录交易的修改）
+    pub market_gain: f64,
+}
+
+impl AttributionGroup {
+    fn add(&mut self, other: &AttributionGroup) {
+        self.start_value += other.start_value;
+        self.end_value += other.end_value;
+        self.contributions += other.contributions;
+        self.withdrawals += other.withdrawals;
+        self.income += other.income;
+        self.market_gain += other.market_gain;
+    }
+}
+
+/// 归因报告
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Attribution {
+    pub range: DateRange,
+    pub group_by: GroupBy,
+    pub groups: Vec<AttributionGroup>,
+    /// 全部资产合计（按资产计算，不会因多标签重复计入）
+    pub total: AttributionGroup,
+}
+
+/// 计算收益归因
+///
+/// 各资产并行计算，再按资产顺序合并，结果与线程数无关。
+pub fn attribution(
+    assets: &[Asset],
+    transactions: &[AssetTransaction],
+    range: &DateRange,
+    group_by: GroupBy,
+) -> Attribution {
+    let by_asset = transactions_by_asset(transactions);
+    let results: Vec<Option<AttributionGroup>> = assets
+        .par_iter()
+        .map(|asset| {
+            let txns = by_asset
+                .get(&asset.id)
+                .map(Vec::as_slice)
+                .unwrap_or_default();
+            asset_attribution(asset, txns, range)
+        })
+        .collect();
+
+    let mut groups: BTreeMap<String, AttributionGroup> = BTreeMap::new();
+    let mut total = AttributionGroup::default();
+
+    for (asset, result) in assets.iter().zip(results) {
+        let Some(result) = result else {
+            continue;
+        };
+        total.add(&result);
+
+        let keys = match group_by {
+            GroupBy::AssetType => vec![asset.asset_type.as_str().to_string()],
+            GroupBy::Tag if asset.tags.is_empty() => vec![UNTAGGED_GROUP.to_string()],
+            GroupBy::Tag => asset.tags.clone(),
+        };
+        for key in keys {
+            groups
+                .entry(key.clone())
+                .or_insert_with(|| AttributionGroup {
+                    key,
+                    ..Default::default()
+                })
+                .add(&result);
+        }
+    }
+
+    Attribution {
+        range: *range,
+        group_by,
+        groups: groups.into_values().collect(),
+        total,
+    }
+}
+
+fn asset_attribution(
+    asset: &Asset,
+    transactions: &[AssetTransaction],
+    range: &DateRange,
+) -> Option<AttributionGroup> {
+    let history = value_history(asset, transactions);
+    let first = history.first()?;
+    if first.timestamp > range.end {
+        return None;
+    }
+
+    let mut result = AttributionGroup {
+        start_value: value_at(&history, range.start),
+        end_value: value_at(&history, range.end),
+        ..Default::default()
+    };
+
+    // 期间内新建的资产，初始价值视为投入
+    if range.contains(first.timestamp) {
+        result.contributions += first.value;
+    }
+
+    for txn in transactions
+        .iter()
+        .filter(|t| t.asset_id == asset.id && !t.scheduled && range.contains(t.timestamp))
+    {
+        let delta = txn.delta();
+        match txn.transaction_type {
+            TransactionType::Buy | TransactionType::Sell | TransactionType::Transfer => {
+                if delta >= 0.0 {
+                    result.contributions += delta;
+                } else {
+                    result.withdrawals -= delta;
+                }
+            }
+            TransactionType::Income | TransactionType::Expense => result.income += delta,
+            TransactionType::ValueChange => {}
+        }
+    }
+
+    result.market_gain = result.end_value - result.start_value - result.contributions
+        + result.withdrawals
+        - result.income;
+    Some(result)
+}
+
+fn value_at(history: &[ValuePoint], t: DateTime<Utc>) -> f64 {
+    let idx = history.partition_point(|p| p.timestamp <= t);
+    if idx == 0 {
+        0.0
+    } else {
+        history[idx - 1].value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asset::AssetType;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_attribution() {
+        let day = |d: u32| Utc.with_ymd_and_hms(2024, 1, d, 0, 0, 0).unwrap();
+        let mut fund =
+            Asset::new("基金", AssetType::Fund, 1300.0).with_tags(vec!["投资".to_string()]);
+        fund.created_at = day(1);
+        fund.updated_at = day(20);
+        let txns = vec![
+            AssetTransaction::new(fund.id, TransactionType::Buy, 1000.0, 1200.0)
+                .with_timestamp(day(10)),
+            AssetTransaction::new(fund.id, TransactionType::ValueChange, 1200.0, 1250.0)
+                .with_timestamp(day(15)),
+            AssetTransaction::new(fund.id, TransactionType::Income, 1250.0, 1300.0)
+                .with_timestamp(day(20)),
+        ];
+
+        let range = DateRange::new(day(5), day(25));
+        let report = attribution(&[fund], &txns, &range, GroupBy::Tag);
+        assert_eq!(report.groups.len(), 1);
+        let group = &report.groups[0];
+        assert_eq!(group.key, "投资");
+        assert_eq!(group.start_value, 1000.0);
+        assert_eq!(group.end_value, 1300.0);
+        assert_eq!(group.contributions, 200.0);
+        assert_eq!(group.income, 50.0);
+        assert_eq!(group.market_gain, 50.0);
+    }
+}
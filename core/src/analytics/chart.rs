@@ -0,0 +1,185 @@
+//! 图表数据：把价值序列降采样到目标点数，多年的每日数据也只传给界面几百个点
+
+use super::{InterpolationMethod, Interval, ValuePoint};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// 目标点数的下限（保留首尾两点之外至少还有一个点）
+pub const MIN_CHART_POINTS: usize = 3;
+
+/// 降采样方式
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DownsampleMethod {
+    /// Largest-Triangle-Three-Buckets：每个分桶保留与前后点构成三角形面积最大的点，折线形状最接近原序列
+    #[default]
+    Lttb,
+    /// 每个分桶保留最小值与最大值，不会丢掉尖峰
+    MinMax,
+}
+
+/// 图表序列的数据来源
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ChartSource {
+    /// 资产的每日余额
+    DailyBalances { asset_id: Uuid },
+    /// 资产的估值历史
+    Valuations { asset_id: Uuid },
+    /// 资产价值的等间隔序列
+    ValueHistory {
+        asset_id: Uuid,
+        interval: Interval,
+        #[serde(default)]
+        method: InterpolationMethod,
+    },
+    /// 以本位币计价的净资产序列
+    NetWorth { interval: Interval },
+}
+
+/// 降采样后的序列
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct ChartSeries {
+    /// 按时间升序的点
+    pub points: Vec<ValuePoint>,
+    /// 降采样前的点数
+    pub source_points: usize,
+}
+
+impl ChartSeries {
+    /// 把按时间升序的序列降采样到不超过 `target_points` 个点
+    pub fn new(points: &[ValuePoint], target_points: usize, method: DownsampleMethod) -> Self {
+        Self {
+            points: downsample(points, target_points, method),
+            source_points: points.len(),
+        }
+    }
+}
+
+/// 降采样按时间升序的序列，点数不超过目标时原样返回；首尾两点总是保留
+pub fn downsample(
+    points: &[ValuePoint],
+    target_points: usize,
+    method: DownsampleMethod,
+) -> Vec<ValuePoint> {
+    let target = target_points.max(MIN_CHART_POINTS);
+    if points.len() <= target {
+        return points.to_vec();
+    }
+    match method {
+        DownsampleMethod::Lttb => lttb(points, target),
+        DownsampleMethod::MinMax => min_max(points, target),
+    }
+}
+
+/// 时间轴坐标（秒）
+fn x(point: &ValuePoint) -> f64 {
+    point.timestamp.timestamp() as f64
+}
+
+/// 第 `i` 个分桶在原序列中的范围（不含首尾两点）
+fn bucket(len: usize, buckets: usize, i: usize) -> std::ops::Range<usize> {
+    let inner = len - 2;
+    1 + i * inner / buckets..1 + (i + 1) * inner / buckets
+}
+
+fn lttb(points: &[ValuePoint], target: usize) -> Vec<ValuePoint> {
+    let buckets = target - 2;
+    let mut sampled = Vec::with_capacity(target);
+    sampled.push(points[0]);
+    let mut prev = points[0];
+    for i in 0..buckets {
+        // 下一个分桶的平均点（最后一个分桶之后是末尾的点）
+        let next = if i + 1 < buckets {
+            &points[bucket(points.len(), buckets, i + 1)]
+        } else {
+            &points[points.len() - 1..]
+        };
+        let avg_x = next.iter().map(x).sum::<f64>() / next.len() as f64;
+        let avg_y = next.iter().map(|p| p.value).sum::<f64>() / next.len() as f64;
+
+        let chosen = points[bucket(points.len(), buckets, i)]
+            .iter()
+            .max_by(|a, b| {
+                let area = |p: &ValuePoint| {
+                    ((x(&prev) - avg_x) * (p.value - prev.value)
+                        - (x(&prev) - x(p)) * (avg_y - prev.value))
+                        .abs()
+                };
+                area(a).total_cmp(&area(b))
+            })
+            .copied()
+            .unwrap_or(prev);
+        sampled.push(chosen);
+        prev = chosen;
+    }
+    sampled.push(points[points.len() - 1]);
+    sampled
+}
+
+fn min_max(points: &[ValuePoint], target: usize) -> Vec<ValuePoint> {
+    let buckets = (target - 2) / 2;
+    let mut sampled = Vec::with_capacity(target);
+    sampled.push(points[0]);
+    for i in 0..buckets {
+        let slice = &points[bucket(points.len(), buckets, i)];
+        let min = (0..slice.len()).min_by(|&a, &b| slice[a].value.total_cmp(&slice[b].value));
+        let max = (0..slice.len()).max_by(|&a, &b| slice[a].value.total_cmp(&slice[b].value));
+        let (Some(min), Some(max)) = (min, max) else {
+            continue;
+        };
+        // 按时间顺序放入，最小值与最大值是同一个点时只放一次
+        sampled.push(slice[min.min(max)]);
+        if min != max {
+            sampled.push(slice[min.max(max)]);
+        }
+    }
+    sampled.push(points[points.len() - 1]);
+    sampled
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone, Utc};
+
+    #[test]
+    fn test_downsample() {
+        let start = Utc.with_ymd_and_hms(2020, 1, 1, 0, 0, 0).unwrap();
+        // 五年的每日数据，中间有一个尖峰
+        let points: Vec<ValuePoint> = (0..1826)
+            .map(|d| ValuePoint {
+                timestamp: start + Duration::days(d),
+                value: if d == 1000 { 5000.0 } else { d as f64 },
+            })
+            .collect();
+
+        for method in [DownsampleMethod::Lttb, DownsampleMethod::MinMax] {
+            let series = ChartSeries::new(&points, 200, method);
+            assert_eq!(series.source_points, 1826);
+            assert!(series.points.len() <= 200, "{:?}", method);
+            assert_eq!(series.points.first(), points.first());
+            assert_eq!(series.points.last(), points.last());
+            assert!(series
+                .points
+                .windows(2)
+                .all(|w| w[0].timestamp < w[1].timestamp));
+            // 尖峰保留下来
+            assert!(
+                series.points.iter().any(|p| p.value == 5000.0),
+                "{:?}",
+                method
+            );
+        }
+
+        // 点数不超过目标时原样返回，目标过小时按下限处理
+        assert_eq!(
+            downsample(&points[..50], 200, DownsampleMethod::Lttb),
+            &points[..50]
+        );
+        assert_eq!(
+            downsample(&points, 0, DownsampleMethod::Lttb).len(),
+            MIN_CHART_POINTS
+        );
+    }
+}
@@ -0,0 +1,153 @@
+//! 每日余额：按资产物化的日终价值
+//!
+//! 只记录发生变化的日期，查询时沿用前一个日终值，因此绘制历史图表无需再回放交易。
+//! 资产或其交易变化时只重算该资产。
+
+use super::{transactions_by_asset, value_history, DateRange};
+use crate::asset::{Asset, AssetTransaction};
+use chrono::{Duration, NaiveDate};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use uuid::Uuid;
+
+/// 某资产某日的日终价值
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct DailyBalance {
+    pub asset_id: Uuid,
+    pub date: NaiveDate,
+    pub value: f64,
+}
+
+/// 资产发生变化的各日的日终价值
+pub fn end_of_day_values(
+    asset: &Asset,
+    transactions: &[AssetTransaction],
+) -> BTreeMap<NaiveDate, f64> {
+    value_history(asset, transactions)
+        .into_iter()
+        .map(|p| (p.timestamp.date_naive(), p.value))
+        .collect()
+}
+
+/// 各资产的日终价值表
+#[derive(Debug, Clone, Default)]
+pub struct DailyBalances {
+    balances: HashMap<Uuid, BTreeMap<NaiveDate, f64>>,
+}
+
+impl DailyBalances {
+    /// 由全部资产与交易构建（各资产并行计算）
+    pub fn build(assets: &[Asset], transactions: &[AssetTransaction]) -> Self {
+        let by_asset = transactions_by_asset(transactions);
+        let balances = assets
+            .par_iter()
+            .map(|asset| {
+                let txns = by_asset
+                    .get(&asset.id)
+                    .map(Vec::as_slice)
+                    .unwrap_or_default();
+                (asset.id, end_of_day_values(asset, txns))
+            })
+            .collect();
+        Self { balances }
+    }
+
+    /// 重算单个资产的日终价值
+    pub fn refresh(&mut self, asset: &Asset, transactions: &[AssetTransaction]) {
+        self.balances
+            .insert(asset.id, end_of_day_values(asset, transactions));
+    }
+
+    /// 写入某日的日终价值
+    pub fn insert(&mut self, balance: DailyBalance) {
+        self.balances
+            .entry(balance.asset_id)
+            .or_default()
+            .insert(balance.date, balance.value);
+    }
+
+    /// 移除资产
+    pub fn remove(&mut self, asset_id: Uuid) {
+        self.balances.remove(&asset_id);
+    }
+
+    /// 有资产发生变化的所有日期
+    pub fn change_dates(&self) -> BTreeSet<NaiveDate> {
+        self.balances
+            .values()
+            .flat_map(|days| days.keys().copied())
+            .collect()
+    }
+
+    /// 某日的日终价值（资产在该日之前没有记录时为 None）
+    pub fn value_on(&self, asset_id: Uuid, date: NaiveDate) -> Option<f64> {
+        self.balances
+            .get(&asset_id)?
+            .range(..=date)
+            .next_back()
+            .map(|(_, value)| *value)
+    }
+
+    /// 时间范围内每天的日终价值，跳过资产尚无记录的日期
+    pub fn series(&self, asset_id: Uuid, range: &DateRange) -> Vec<DailyBalance> {
+        let Some(days) = self.balances.get(&asset_id) else {
+            return Vec::new();
+        };
+        let (start, end) = (range.start.date_naive(), range.end.date_naive());
+        let mut value = days.range(..start).next_back().map(|(_, v)| *v);
+        let mut changes = days.range(start..=end).peekable();
+
+        let mut series = Vec::new();
+        let mut date = start;
+        while date <= end {
+            if let Some((_, v)) = changes.next_if(|(d, _)| **d == date) {
+                value = Some(*v);
+            }
+            if let Some(value) = value {
+                series.push(DailyBalance {
+                    asset_id,
+                    date,
+                    value,
+                });
+            }
+            date += Duration::days(1);
+        }
+        series
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asset::{AssetType, TransactionType};
+    use chrono::{TimeZone, Utc};
+
+    #[test]
+    fn test_daily_balances() {
+        let day = |d: u32, h: u32| Utc.with_ymd_and_hms(2024, 3, d, h, 0, 0).unwrap();
+        let mut asset = Asset::new("活期", AssetType::BankDeposit, 80.0);
+        asset.created_at = day(2, 9);
+        asset.updated_at = day(4, 18);
+        let txns = vec![
+            AssetTransaction::new(asset.id, TransactionType::Income, 0.0, 100.0)
+                .with_timestamp(day(2, 10)),
+            AssetTransaction::new(asset.id, TransactionType::Expense, 100.0, 90.0)
+                .with_timestamp(day(4, 8)),
+            AssetTransaction::new(asset.id, TransactionType::Expense, 90.0, 80.0)
+                .with_timestamp(day(4, 18)),
+        ];
+        let balances = DailyBalances::build(std::slice::from_ref(&asset), &txns);
+
+        let series = balances.series(asset.id, &DateRange::new(day(1, 0), day(5, 0)));
+        let values: Vec<f64> = series.iter().map(|b| b.value).collect();
+        // 3 月 1 日尚无记录；同一天多笔交易取日终值
+        assert_eq!(values, vec![100.0, 100.0, 80.0, 80.0]);
+        assert_eq!(series[0].date, day(2, 0).date_naive());
+        assert_eq!(balances.value_on(asset.id, day(1, 0).date_naive()), None);
+        assert_eq!(
+            balances.value_on(asset.id, day(3, 0).date_naive()),
+            Some(100.0)
+        );
+    }
+}
@@ -0,0 +1,37 @@
+//! 统计分析：历史序列、收益归因等
+
+mod attribution;
+mod chart;
+mod daily;
+mod parallel;
+mod series;
+
+pub use attribution::*;
+pub use chart::*;
+pub use daily::*;
+pub use parallel::*;
+pub use series::*;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// 时间范围（闭区间）
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct DateRange {
+    /// 开始时间
+    pub start: DateTime<Utc>,
+    /// 结束时间
+    pub end: DateTime<Utc>,
+}
+
+impl DateRange {
+    /// 创建时间范围
+    pub fn new(start: DateTime<Utc>, end: DateTime<Utc>) -> Self {
+        Self { start, end }
+    }
+
+    /// 是否包含某一时刻
+    pub fn contains(&self, t: DateTime<Utc>) -> bool {
+        t >= self.start && t <= self.end
+    }
+}
@@ -0,0 +1,117 @@
+//! 并行聚合：按块并行处理交易，再按块顺序合并
+//!
+//! 合并顺序固定，浮点累加的顺序与线程数无关，因此结果在多次运行间完全一致。
+
+use crate::asset::AssetTransaction;
+use rayon::prelude::*;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// 每个并行任务处理的交易条数
+pub const CHUNK_SIZE: usize = 4096;
+
+/// 按资产分组交易，组内保持原有顺序
+pub fn transactions_by_asset(
+    transactions: &[AssetTransaction],
+) -> HashMap<Uuid, Vec<AssetTransaction>> {
+    let chunks: Vec<HashMap<Uuid, Vec<&AssetTransaction>>> = transactions
+        .par_chunks(CHUNK_SIZE)
+        .map(|chunk| {
+            let mut groups: HashMap<Uuid, Vec<&AssetTransaction>> = HashMap::new();
+            for t in chunk {
+                groups.entry(t.asset_id).or_default().push(t);
+            }
+            groups
+        })
+        .collect();
+
+    let mut groups: HashMap<Uuid, Vec<AssetTransaction>> = HashMap::new();
+    for chunk in chunks {
+        for (asset_id, txns) in chunk {
+            groups
+                .entry(asset_id)
+                .or_default()
+                .extend(txns.into_iter().cloned());
+        }
+    }
+    groups
+}
+
+/// 按资产合计满足条件的交易变动
+pub fn sum_deltas_by_asset<F>(transactions: &[AssetTransaction], filter: F) -> HashMap<Uuid, f64>
+where
+    F: Fn(&AssetTransaction) -> bool + Sync,
+{
+    let chunks: Vec<HashMap<Uuid, f64>> = transactions
+        .par_chunks(CHUNK_SIZE)
+        .map(|chunk| {
+            let mut sums: HashMap<Uuid, f64> = HashMap::new();
+            for t in chunk.iter().filter(|t| filter(t)) {
+                *sums.entry(t.asset_id).or_insert(0.0) += t.delta();
+            }
+            sums
+        })
+        .collect();
+
+    let mut sums: HashMap<Uuid, f64> = HashMap::new();
+    for chunk in chunks {
+        for (asset_id, sum) in chunk {
+            *sums.entry(asset_id).or_insert(0.0) += sum;
+        }
+    }
+    sums
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analytics::{attribution, DateRange, GroupBy};
+    use crate::asset::{Asset, AssetType, TransactionType};
+    use chrono::{Duration, Utc};
+
+    #[test]
+    fn test_parallel_results_are_deterministic() {
+        let start = Utc::now() - Duration::days(400);
+        let assets: Vec<Asset> = (0..8)
+            .map(|i| Asset::new(format!("资产{}", i), AssetType::Fund, 0.0))
+            .collect();
+        let transactions: Vec<AssetTransaction> = (0..3 * CHUNK_SIZE)
+            .map(|i| {
+                let asset = &assets[i % assets.len()];
+                let kind = if i % 3 == 0 {
+                    TransactionType::Income
+                } else {
+                    TransactionType::Buy
+                };
+                AssetTransaction::new(asset.id, kind, i as f64 * 0.1, i as f64 * 0.1 + 0.3)
+                    .with_timestamp(start + Duration::minutes(i as i64))
+            })
+            .collect();
+        let range = DateRange::new(start, Utc::now());
+
+        let single = rayon::ThreadPoolBuilder::new()
+            .num_threads(1)
+            .build()
+            .unwrap();
+        let sequential = single.install(|| {
+            (
+                sum_deltas_by_asset(&transactions, |_| true),
+                attribution(&assets, &transactions, &range, GroupBy::AssetType),
+            )
+        });
+        let parallel = (
+            sum_deltas_by_asset(&transactions, |_| true),
+            attribution(&assets, &transactions, &range, GroupBy::AssetType),
+        );
+        assert_eq!(sequential.0, parallel.0);
+        assert_eq!(
+            serde_json::to_value(&sequential.1).unwrap(),
+            serde_json::to_value(&parallel.1).unwrap()
+        );
+
+        let groups = transactions_by_asset(&transactions);
+        let first = &groups[&assets[0].id];
+        assert_eq!(first.len(), transactions.len() / assets.len());
+        assert!(first.windows(2).all(|w| w[0].timestamp < w[1].timestamp));
+    }
+}
@@ -0,0 +1,203 @@
+//! 资产价值历史序列
+
+use super::DateRange;
+use crate::asset::{Asset, AssetTransaction};
+use chrono::{DateTime, Duration, Months, Utc};
+use serde::{Deserialize, Serialize};
+
+/// 序列采样间隔
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Interval {
+    Day,
+    Week,
+    Month,
+}
+
+impl Interval {
+    /// 从 `start` 起第 `n` 个采样点（为负时向前），超出日期范围时为 None
+    ///
+    /// 每个点都由 `start` 直接推算，按月采样时日期不会逐月漂移（1 月 31 日之后为 2 月 29 日、3 月 31 日）。
+    pub fn nth(&self, start: DateTime<Utc>, n: i32) -> Option<DateTime<Utc>> {
+        let months = Months::new(n.unsigned_abs());
+        match self {
+            Interval::Day => start.checked_add_signed(Duration::days(n.into())),
+            Interval::Week => start.checked_add_signed(Duration::weeks(n.into())),
+            Interval::Month if n >= 0 => start.checked_add_months(months),
+            Interval::Month => start.checked_sub_months(months),
+        }
+    }
+}
+
+/// 缺失点的填充方式
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum InterpolationMethod {
+    /// 沿用上一次的值
+    #[default]
+    CarryForward,
+    /// 在前后两个快照之间线性插值
+    Linear,
+}
+
+/// 价值快照
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct ValuePoint {
+    pub timestamp: DateTime<Utc>,
+    pub value: f64,
+}
+
+/// 序列中的一个点
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct SeriesPoint {
+    pub timestamp: DateTime<Utc>,
+    pub value: f64,
+    /// 该采样区间内没有实际快照，值由填充得到
+    pub interpolated: bool,
+}
+
+/// 由交易记录还原资产价值快照（按时间升序）
+pub fn value_history(asset: &Asset, transactions: &[AssetTransaction]) -> Vec<ValuePoint> {
+    let mut txns: Vec<&AssetTransaction> = transactions
+        .iter()
+        .filter(|t| t.asset_id == asset.id && !t.scheduled)
+        .collect();
+    txns.sort_by_key(|t| t.timestamp);
+
+    let mut history = Vec::with_capacity(txns.len() + 2);
+    let Some(first) = txns.first() else {
+        history.push(ValuePoint {
+            timestamp: asset.created_at,
+            value: asset.value,
+        });
+        return history;
+    };
+
+    if asset.created_at < first.timestamp {
+        history.push(ValuePoint {
+            timestamp: asset.created_at,
+            value: first.amount_before,
+        });
+    }
+    history.extend(txns.iter().map(|t| ValuePoint {
+        timestamp: t.timestamp,
+        value: t.amount_after,
+    }));
+
+    // 未记录交易的直接修改
+    let last = history[history.len() - 1];
+    if asset.updated_at > last.timestamp && asset.value != last.value {
+        history.push(ValuePoint {
+            timestamp: asset.updated_at,
+            value: asset.value,
+        });
+    }
+
+    history
+}
+
+/// 按固定间隔采样并填充缺失点
+///
+/// 第一个快照之前的采样点没有数据，不会出现在结果中；最后一个快照之后的点总是沿用最后的值。
+pub fn interpolate_series(
+    history: &[ValuePoint],
+    range: &DateRange,
+    interval: Interval,
+    method: InterpolationMethod,
+) -> Vec<SeriesPoint> {
+    let mut points = Vec::new();
+    let mut prev = interval.nth(range.start, -1).unwrap_or(range.start);
+    let mut t = range.start;
+    let mut n = 0;
+
+    while t <= range.end {
+        // 最近一个不晚于 t 的快照
+        let idx = history.partition_point(|p| p.timestamp <= t);
+        if idx > 0 {
+            let before = history[idx - 1];
+            let actual = before.timestamp > prev;
+            let value = match (actual, method, history.get(idx)) {
+                (false, InterpolationMethod::Linear, Some(after)) => {
+                    let span = (after.timestamp - before.timestamp).num_seconds() as f64;
+                    let elapsed = (t - before.timestamp).num_seconds() as f64;
+                    before.value + (after.value - before.value) * elapsed / span
+                }
+                _ => before.value,
+            };
+            points.push(SeriesPoint {
+                timestamp: t,
+                value,
+                interpolated: !actual,
+            });
+        }
+
+        n += 1;
+        let Some(next) = interval.nth(range.start, n).filter(|next| *next > t) else {
+            break;
+        };
+        prev = t;
+        t = next;
+    }
+
+    points
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asset::{AssetType, TransactionType};
+    use chrono::{Datelike, TimeZone};
+
+    #[test]
+    fn test_interpolation() {
+        let day = |d: u32| Utc.with_ymd_and_hms(2024, 1, d, 0, 0, 0).unwrap();
+        let mut asset = Asset::new("基金", AssetType::Fund, 200.0);
+        asset.created_at = day(1);
+        asset.updated_at = day(5);
+        let txns = vec![
+            AssetTransaction::new(asset.id, TransactionType::ValueChange, 100.0, 100.0)
+                .with_timestamp(day(1)),
+            AssetTransaction::new(asset.id, TransactionType::ValueChange, 100.0, 200.0)
+                .with_timestamp(day(5)),
+        ];
+        let history = value_history(&asset, &txns);
+        let range = DateRange::new(day(1), day(6));
+
+        let carried = interpolate_series(
+            &history,
+            &range,
+            Interval::Day,
+            InterpolationMethod::CarryForward,
+        );
+        assert_eq!(carried.len(), 6);
+        assert!(!carried[0].interpolated);
+        assert_eq!(carried[2].value, 100.0);
+        assert!(carried[2].interpolated);
+        assert!(!carried[4].interpolated);
+
+        let linear =
+            interpolate_series(&history, &range, Interval::Day, InterpolationMethod::Linear);
+        assert_eq!(linear[2].value, 150.0);
+        assert_eq!(linear[5].value, 200.0);
+        assert!(linear[5].interpolated);
+    }
+
+    #[test]
+    fn test_monthly_points_keep_day() {
+        let start = Utc.with_ymd_and_hms(2024, 1, 31, 0, 0, 0).unwrap();
+        let end = Utc.with_ymd_and_hms(2024, 5, 31, 0, 0, 0).unwrap();
+        let history = [ValuePoint {
+            timestamp: start,
+            value: 1.0,
+        }];
+        let points = interpolate_series(
+            &history,
+            &DateRange::new(start, end),
+            Interval::Month,
+            InterpolationMethod::CarryForward,
+        );
+        let days: Vec<u32> = points.iter().map(|p| p.timestamp.day()).collect();
+        assert_eq!(days, [31, 29, 31, 30, 31]);
+        assert_eq!(Interval::Month.nth(start, -2).unwrap().day(), 30);
+    }
+}
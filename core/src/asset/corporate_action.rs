@@ -0,0 +1,219 @@
+//! 公司行动：拆股/合股、代码变更与现金分红
+//!
+//! 持仓数量保存在元数据中（股票为 `shares`，基金与加密货币为 `units`），按批次跟踪时
+//! 另有 `lots: [{ "shares": 100, "price": 10.5 }]`。拆股按比例调整数量与每批买入价，
+//! 总成本不变；分红按持仓数量计算金额。
+
+use super::{Asset, AssetType};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+
+/// 公司行动
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum CorporateAction {
+    /// 拆股或合股：每 `from` 股变为 `to` 股（如 1 拆 2、10 合 1）
+    Split { from: u32, to: u32 },
+    /// 代码变更（如更名、合并后换股）
+    SymbolChange {
+        symbol: String,
+        #[serde(default)]
+        exchange: Option<String>,
+    },
+    /// 现金分红：每股金额，可记入另一资产（如证券账户的现金）
+    Dividend {
+        per_share: f64,
+        #[serde(default)]
+        pay_to: Option<Uuid>,
+    },
+}
+
+/// 数据源提供的一次分红
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct DividendEvent {
+    /// 除息日
+    pub date: NaiveDate,
+    /// 每股金额
+    pub per_share: f64,
+}
+
+impl DividendEvent {
+    /// 自动录入时交易的外部标识，用于跳过已录入的分红
+    pub fn external_id(&self, asset_id: Uuid) -> String {
+        format!("dividend:{}:{}", asset_id, self.date)
+    }
+}
+
+/// 持仓数量的元数据字段
+pub fn quantity_key(asset_type: &AssetType) -> &'static str {
+    match asset_type {
+        AssetType::Fund | AssetType::Crypto => "units",
+        _ => "shares",
+    }
+}
+
+/// 证券代码的元数据字段
+pub fn symbol_key(asset_type: &AssetType) -> &'static str {
+    match asset_type {
+        AssetType::Fund => "code",
+        AssetType::Crypto => "symbol",
+        _ => "ticker",
+    }
+}
+
+/// 持仓数量：优先取数量字段，否则为各批次数量之和
+pub fn holding_quantity(asset: &Asset) -> Option<f64> {
+    let key = quantity_key(&asset.asset_type);
+    if let Some(quantity) = asset.metadata.get(key).and_then(Value::as_f64) {
+        return Some(quantity);
+    }
+    let lots = asset.metadata.get("lots")?.as_array()?;
+    Some(
+        lots.iter()
+            .filter_map(|lot| lot.get(key).and_then(Value::as_f64))
+            .sum(),
+    )
+}
+
+/// 证券代码
+pub fn holding_symbol(asset: &Asset) -> Option<&str> {
+    asset
+        .metadata
+        .get(symbol_key(&asset.asset_type))
+        .and_then(Value::as_str)
+        .filter(|s| !s.trim().is_empty())
+}
+
+/// 按比例调整数量字段
+fn scale(object: &mut Value, key: &str, factor: f64) {
+    if let Some(value) = object.get_mut(key) {
+        if let Some(n) = value.as_f64() {
+            *value = (n * factor).into();
+        }
+    }
+}
+
+impl CorporateAction {
+    /// 检查是否有效
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            CorporateAction::Split { from, to } => {
+                if *from == 0 || *to == 0 || from == to {
+                    return Err(format!("Invalid split ratio {}:{}", from, to));
+                }
+            }
+            CorporateAction::SymbolChange { symbol, .. } => {
+                if symbol.trim().is_empty() {
+                    return Err("New symbol is required".to_string());
+                }
+            }
+            CorporateAction::Dividend { per_share, .. } => {
+                if !(per_share.is_finite() && *per_share > 0.0) {
+                    return Err(format!("Invalid dividend per share: {}", per_share));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// 作用于资产：拆股与代码变更修改元数据，返回说明与分红金额
+    pub fn apply(&self, asset: &mut Asset) -> Result<(String, f64), String> {
+        self.validate()?;
+        let quantity = holding_quantity(asset);
+        match self {
+            CorporateAction::Split { from, to } => {
+                let quantity = quantity.ok_or_else(|| no_quantity(asset))?;
+                let factor = *to as f64 / *from as f64;
+                let key = quantity_key(&asset.asset_type);
+                scale(&mut asset.metadata, key, factor);
+                if let Some(lots) = asset.metadata.get_mut("lots").and_then(Value::as_array_mut) {
+                    for lot in lots {
+                        scale(lot, key, factor);
+                        scale(lot, "price", 1.0 / factor);
+                    }
+                }
+                let label = if to > from { "拆股" } else { "合股" };
+                Ok((
+                    format!(
+                        "{} {}:{}（{} → {}）",
+                        label,
+                        from,
+                        to,
+                        quantity,
+                        quantity * factor
+                    ),
+                    0.0,
+                ))
+            }
+            CorporateAction::SymbolChange { symbol, exchange } => {
+                let symbol = symbol.trim().to_string();
+                let old = holding_symbol(asset).unwrap_or("-").to_string();
+                let Value::Object(fields) = &mut asset.metadata else {
+                    return Err(format!("{} has invalid metadata", asset.name));
+                };
+                fields.insert(symbol_key(&asset.asset_type).into(), symbol.clone().into());
+                if let Some(exchange) = exchange {
+                    fields.insert("exchange".into(), exchange.clone().into());
+                }
+                Ok((format!("代码变更 {} → {}", old, symbol), 0.0))
+            }
+            CorporateAction::Dividend { per_share, .. } => {
+                let quantity = quantity.ok_or_else(|| no_quantity(asset))?;
+                Ok((
+                    format!("{} 分红，每股 {}，持有 {}", asset.name, per_share, quantity),
+                    per_share * quantity,
+                ))
+            }
+        }
+    }
+}
+
+fn no_quantity(asset: &Asset) -> String {
+    format!("{} has no holding quantity", asset.name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_corporate_actions() {
+        let mut stock = Asset::new("苹果", AssetType::Stock, 2000.0).with_metadata(json!({
+            "ticker": "AAPL",
+            "lots": [{ "shares": 10, "price": 120.0 }, { "shares": 6, "price": 150.0 }]
+        }));
+        assert_eq!(holding_quantity(&stock), Some(16.0));
+
+        let split = CorporateAction::Split { from: 1, to: 4 };
+        let (note, cash) = split.apply(&mut stock).unwrap();
+        assert_eq!((note.as_str(), cash), ("拆股 1:4（16 → 64）", 0.0));
+        assert_eq!(
+            stock.metadata["lots"][0],
+            json!({ "shares": 40.0, "price": 30.0 })
+        );
+        assert_eq!(holding_quantity(&stock), Some(64.0));
+
+        let rename = CorporateAction::SymbolChange {
+            symbol: "AAPL2".into(),
+            exchange: Some("NYSE".into()),
+        };
+        rename.apply(&mut stock).unwrap();
+        assert_eq!(holding_symbol(&stock), Some("AAPL2"));
+        assert_eq!(stock.metadata["exchange"], "NYSE");
+
+        let dividend = CorporateAction::Dividend {
+            per_share: 0.25,
+            pay_to: None,
+        };
+        assert_eq!(dividend.apply(&mut stock).unwrap().1, 16.0);
+
+        let mut cash = Asset::new("现金", AssetType::Cash, 100.0);
+        assert!(dividend.apply(&mut cash).is_err());
+        assert!(CorporateAction::Split { from: 2, to: 2 }
+            .validate()
+            .is_err());
+    }
+}
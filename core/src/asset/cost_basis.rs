@@ -0,0 +1,233 @@
+//! 成本重算：手动修改批次或交易后买入成本可能与历史不符，按买入/卖出历史重放得到成本
+//!
+//! 交易只记录金额，重放时把持仓折算为份额：单位净值为交易前价值除以持有份额，
+//! 金额增加视为按净值买入、成本增加同样金额，金额减少视为按净值卖出、按所选方法结转成本。
+//! 更正分录同样按变动方向处理；首笔交易前已有的价值作为期初批次，按当时价值计入成本。
+
+use super::{Asset, AssetTransaction, TransactionType};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use uuid::Uuid;
+
+/// 小于此值的份额与金额视为零
+const EPSILON: f64 = 1e-9;
+
+/// 卖出时结转成本的方法
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CostMethod {
+    /// 加权平均：卖出份额按全部持仓的平均成本结转
+    #[default]
+    WeightedAverage,
+    /// 先进先出：卖出份额依次结转最早买入批次的成本
+    Fifo,
+}
+
+/// 重放中的一步
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CostAdjustment {
+    /// 重放的交易（期初批次为空）
+    pub transaction_id: Option<Uuid>,
+    pub timestamp: DateTime<Utc>,
+    /// 交易的金额变动（期初批次为当时的价值）
+    pub amount: f64,
+    /// 成本变动
+    pub cost_change: f64,
+    /// 这一步之后的成本
+    pub cost_after: f64,
+    /// 卖出实现的盈亏
+    pub realized_gain: f64,
+}
+
+/// 成本重算结果
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CostBasisReport {
+    pub asset_id: Uuid,
+    pub method: CostMethod,
+    /// 重算前的成本
+    pub previous: Option<f64>,
+    /// 重算后的成本
+    pub cost_basis: f64,
+    /// 与重算前成本的差额（原先未设置成本时按 0 计）
+    pub difference: f64,
+    /// 累计实现盈亏
+    pub realized_gain: f64,
+    /// 按时间顺序的各步成本变动
+    pub adjustments: Vec<CostAdjustment>,
+}
+
+/// 一个买入批次
+#[derive(Debug, Clone, Copy)]
+struct Lot {
+    units: f64,
+    cost: f64,
+}
+
+/// 按买入/卖出历史重放资产的成本，跳过其他类型的交易与未入账的计划交易
+pub fn replay_cost_basis(
+    asset: &Asset,
+    transactions: &[AssetTransaction],
+    method: CostMethod,
+) -> CostBasisReport {
+    let mut replayed: Vec<&AssetTransaction> = transactions
+        .iter()
+        .filter(|t| {
+            t.asset_id == asset.id
+                && !t.scheduled
+                && matches!(
+                    t.transaction_type,
+                    TransactionType::Buy | TransactionType::Sell
+                )
+        })
+        .collect();
+    replayed.sort_by_key(|t| t.timestamp);
+
+    let mut lots: VecDeque<Lot> = VecDeque::new();
+    let mut adjustments = Vec::new();
+    let mut realized_gain = 0.0;
+    for t in replayed {
+        let units: f64 = lots.iter().map(|lot| lot.units).sum();
+        if units <= EPSILON && t.amount_before > EPSILON {
+            lots.clear();
+            lots.push_back(Lot {
+                units: t.amount_before,
+                cost: t.amount_before,
+            });
+            adjustments.push(CostAdjustment {
+                transaction_id: None,
+                timestamp: t.timestamp,
+                amount: t.amount_before,
+                cost_change: t.amount_before,
+                cost_after: total_cost(&lots),
+                realized_gain: 0.0,
+            });
+        }
+        let units: f64 = lots.iter().map(|lot| lot.units).sum();
+        let delta = t.delta();
+        let (cost_change, gain) = if delta > 0.0 {
+            let price = if units > EPSILON && t.amount_before > EPSILON {
+                t.amount_before / units
+            } else {
+                1.0
+            };
+            let lot = Lot {
+                units: delta / price,
+                cost: delta,
+            };
+            match (method, lots.back_mut()) {
+                (CostMethod::WeightedAverage, Some(pool)) => {
+                    pool.units += lot.units;
+                    pool.cost += lot.cost;
+                }
+                _ => lots.push_back(lot),
+            }
+            (delta, 0.0)
+        } else {
+            let sold = if t.amount_before > EPSILON {
+                units * (-delta / t.amount_before).min(1.0)
+            } else {
+                0.0
+            };
+            let cost = dispose(&mut lots, sold);
+            (-cost, -delta - cost)
+        };
+        realized_gain += gain;
+        adjustments.push(CostAdjustment {
+            transaction_id: Some(t.id),
+            timestamp: t.timestamp,
+            amount: delta,
+            cost_change,
+            cost_after: total_cost(&lots),
+            realized_gain: gain,
+        });
+    }
+
+    let cost_basis = total_cost(&lots);
+    CostBasisReport {
+        asset_id: asset.id,
+        method,
+        previous: asset.cost_basis,
+        cost_basis,
+        difference: cost_basis - asset.cost_basis.unwrap_or(0.0),
+        realized_gain,
+        adjustments,
+    }
+}
+
+fn total_cost(lots: &VecDeque<Lot>) -> f64 {
+    lots.iter().map(|lot| lot.cost).sum()
+}
+
+/// 从最早的批次起卖出份额，返回结转的成本
+fn dispose(lots: &mut VecDeque<Lot>, mut units: f64) -> f64 {
+    let mut cost = 0.0;
+    while units > EPSILON {
+        let Some(lot) = lots.front_mut() else {
+            break;
+        };
+        if lot.units <= units + EPSILON {
+            units -= lot.units;
+            cost += lot.cost;
+            lots.pop_front();
+        } else {
+            let share = lot.cost * units / lot.units;
+            lot.units -= units;
+            lot.cost -= share;
+            cost += share;
+            units = 0.0;
+        }
+    }
+    cost
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asset::AssetType;
+    use chrono::{Duration, TimeZone};
+
+    #[test]
+    fn test_replay_cost_basis() {
+        let stock = Asset::new("腾讯", AssetType::Stock, 1500.0).with_cost_basis(999.0);
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let txn = |kind, before, after, days| {
+            AssetTransaction::new(stock.id, kind, before, after)
+                .with_timestamp(start + Duration::days(days))
+        };
+        // 1000 买入 1000 份（净值 1），涨到 2000 后再买 1000（500 份，净值 2），
+        // 涨到 4500 后卖出 1500（500 份，净值 3）
+        let transactions = vec![
+            txn(TransactionType::Sell, 4500.0, 3000.0, 20),
+            txn(TransactionType::Buy, 0.0, 1000.0, 0),
+            txn(TransactionType::ValueChange, 1000.0, 2000.0, 5),
+            txn(TransactionType::Buy, 2000.0, 3000.0, 10),
+            txn(TransactionType::ValueChange, 3000.0, 4500.0, 15),
+        ];
+
+        let average = replay_cost_basis(&stock, &transactions, CostMethod::WeightedAverage);
+        assert_eq!(average.previous, Some(999.0));
+        assert_eq!(average.adjustments.len(), 3);
+        // 平均成本 2000 / 1500 份，卖出 500 份结转 666.67
+        assert!((average.cost_basis - 4000.0 / 3.0).abs() < 1e-6);
+        assert!((average.realized_gain - (1500.0 - 2000.0 / 3.0)).abs() < 1e-6);
+        assert!((average.difference - (4000.0 / 3.0 - 999.0)).abs() < 1e-6);
+
+        // 先进先出：卖出的 500 份来自第一批，成本 500
+        let fifo = replay_cost_basis(&stock, &transactions, CostMethod::Fifo);
+        assert!((fifo.cost_basis - 1500.0).abs() < 1e-6);
+        assert!((fifo.realized_gain - 1000.0).abs() < 1e-6);
+        assert_eq!(
+            fifo.adjustments.last().unwrap().transaction_id,
+            Some(transactions[0].id)
+        );
+
+        // 没有买入交易时，首笔交易前的价值作为期初批次
+        let cash = Asset::new("现金", AssetType::Cash, 600.0);
+        let sale = AssetTransaction::new(cash.id, TransactionType::Sell, 800.0, 600.0);
+        let report = replay_cost_basis(&cash, &[sale], CostMethod::Fifo);
+        assert_eq!(report.adjustments[0].transaction_id, None);
+        assert!((report.cost_basis - 600.0).abs() < 1e-6);
+        assert_eq!(report.previous, None);
+    }
+}
@@ -0,0 +1,156 @@
+//! 显示样式：资产类型、分类、标签与单个资产的颜色、图标与表情
+//!
+//! 样式保存在数据文件中，随同步与备份一起迁移，各设备显示一致。资产自身的样式优先，
+//! 未设置的部分依次取第一个有样式的标签、资产类型的样式。
+
+use super::{Asset, TransactionType};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+/// 显示样式
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct DisplayMeta {
+    /// 颜色（`#RGB`、`#RRGGBB` 或 `#RRGGBBAA`）
+    #[serde(default)]
+    pub color: Option<String>,
+    /// 图标键（如 `bank`、`mdi:chart-line`）
+    #[serde(default)]
+    pub icon: Option<String>,
+    /// 表情
+    #[serde(default)]
+    pub emoji: Option<String>,
+}
+
+impl DisplayMeta {
+    /// 是否未设置任何样式
+    pub fn is_empty(&self) -> bool {
+        self.color.is_none() && self.icon.is_none() && self.emoji.is_none()
+    }
+
+    /// 未设置的部分取 `fallback` 中的值
+    pub fn or(&self, fallback: &DisplayMeta) -> DisplayMeta {
+        DisplayMeta {
+            color: self.color.clone().or_else(|| fallback.color.clone()),
+            icon: self.icon.clone().or_else(|| fallback.icon.clone()),
+            emoji: self.emoji.clone().or_else(|| fallback.emoji.clone()),
+        }
+    }
+
+    /// 检查样式是否有效
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(color) = &self.color {
+            let hex = color.strip_prefix('#').unwrap_or("");
+            if ![3, 6, 8].contains(&hex.len()) || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+                return Err(format!("Invalid color: {}", color));
+            }
+        }
+        if let Some(icon) = &self.icon {
+            let valid = !icon.is_empty()
+                && icon.len() <= 64
+                && icon
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | ':' | '.'));
+            if !valid {
+                return Err(format!("Invalid icon key: {}", icon));
+            }
+        }
+        if let Some(emoji) = &self.emoji {
+            if emoji.trim().is_empty() || emoji.chars().count() > 8 {
+                return Err(format!("Invalid emoji: {}", emoji));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 设置样式的对象
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum DisplayTarget {
+    /// 资产类型（类型代码，自定义类型为其名称）
+    AssetType { code: String },
+    /// 交易分类
+    Category { category: TransactionType },
+    /// 标签
+    Tag { tag: String },
+    /// 单个资产
+    Asset { id: Uuid },
+}
+
+/// 资产类型、分类与标签的样式
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct DisplayCatalog {
+    /// 资产类型代码 → 样式
+    #[serde(default)]
+    pub asset_types: BTreeMap<String, DisplayMeta>,
+    /// 交易分类 → 样式
+    #[serde(default)]
+    pub categories: BTreeMap<String, DisplayMeta>,
+    /// 标签 → 样式
+    #[serde(default)]
+    pub tags: BTreeMap<String, DisplayMeta>,
+}
+
+/// 交易分类的键
+pub fn category_key(category: &TransactionType) -> String {
+    serde_json::to_value(category)
+        .ok()
+        .and_then(|v| v.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+impl DisplayCatalog {
+    /// 资产最终显示的样式
+    pub fn resolve(&self, asset: &Asset) -> DisplayMeta {
+        let tag = asset
+            .tags
+            .iter()
+            .find_map(|tag| self.tags.get(tag))
+            .cloned()
+            .unwrap_or_default();
+        let asset_type = self
+            .asset_types
+            .get(asset.asset_type.code())
+            .cloned()
+            .unwrap_or_default();
+        asset.display.or(&tag).or(&asset_type)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asset::AssetType;
+
+    #[test]
+    fn test_display_resolve() {
+        let meta = |color: Option<&str>, emoji: Option<&str>| DisplayMeta {
+            color: color.map(str::to_string),
+            icon: None,
+            emoji: emoji.map(str::to_string),
+        };
+        let mut catalog = DisplayCatalog::default();
+        catalog
+            .asset_types
+            .insert("stock".into(), meta(Some("#336699"), Some("📈")));
+        catalog.tags.insert("A股".into(), meta(Some("#e33"), None));
+        assert_eq!(category_key(&TransactionType::ValueChange), "value_change");
+
+        let mut stock =
+            Asset::new("招商银行", AssetType::Stock, 800.0).with_tags(vec!["A股".into()]);
+        assert_eq!(catalog.resolve(&stock), meta(Some("#e33"), Some("📈")));
+        stock.display = meta(None, Some("🏦"));
+        assert_eq!(catalog.resolve(&stock), meta(Some("#e33"), Some("🏦")));
+
+        assert!(meta(Some("#12345g"), None).validate().is_err());
+        assert!(meta(Some("336699"), None).validate().is_err());
+        assert!(DisplayMeta {
+            icon: Some("mdi:bank icon".into()),
+            ..DisplayMeta::default()
+        }
+        .validate()
+        .is_err());
+        assert!(meta(Some("#336699ff"), Some("🏦")).validate().is_ok());
+    }
+}
@@ -0,0 +1,86 @@
+//! 信封预算模型
+
+use super::{Asset, Currency};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// 现金或存款资产内的虚拟分配（如把储蓄卡分为房租、伙食、娱乐）
+///
+/// 信封只划分资产价值的用途，不改变资产价值本身。
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Envelope {
+    /// 唯一标识符
+    pub id: Uuid,
+    /// 所属资产ID
+    pub asset_id: Uuid,
+    /// 信封名称
+    pub name: String,
+    /// 已分配金额
+    pub balance: f64,
+    /// 创建时间
+    pub created_at: DateTime<Utc>,
+}
+
+impl Envelope {
+    /// 创建空信封
+    pub fn new(asset_id: Uuid, name: impl Into<String>) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            asset_id,
+            name: name.into(),
+            balance: 0.0,
+            created_at: Utc::now(),
+        }
+    }
+}
+
+/// 单个资产的信封分配情况
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EnvelopeSummary {
+    pub asset_id: Uuid,
+    pub asset_name: String,
+    pub currency: Currency,
+    pub envelopes: Vec<Envelope>,
+    /// 未分配到任何信封的金额（为负表示超额分配）
+    pub unallocated: f64,
+}
+
+impl EnvelopeSummary {
+    /// 汇总资产下的信封
+    pub fn new(asset: &Asset, envelopes: &[Envelope]) -> Self {
+        let envelopes: Vec<Envelope> = envelopes
+            .iter()
+            .filter(|e| e.asset_id == asset.id)
+            .cloned()
+            .collect();
+        let allocated: f64 = envelopes.iter().map(|e| e.balance).sum();
+        Self {
+            asset_id: asset.id,
+            asset_name: asset.name.clone(),
+            currency: asset.currency.clone(),
+            envelopes,
+            unallocated: asset.value - allocated,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asset::AssetType;
+
+    #[test]
+    fn test_envelope_summary() {
+        let checking = Asset::new("储蓄卡", AssetType::BankDeposit, 5000.0);
+        let mut rent = Envelope::new(checking.id, "房租");
+        rent.balance = 3000.0;
+        let mut food = Envelope::new(checking.id, "伙食");
+        food.balance = 1500.0;
+        let other = Envelope::new(Uuid::new_v4(), "其他");
+
+        let summary = EnvelopeSummary::new(&checking, &[rent, food, other]);
+        assert_eq!(summary.envelopes.len(), 2);
+        assert_eq!(summary.unallocated, 500.0);
+    }
+}
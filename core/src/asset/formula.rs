@@ -0,0 +1,136 @@
+//! 公式计算的虚拟资产
+
+use super::{Asset, AssetType};
+use crate::fx::FxSettings;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// 聚合方式
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Aggregate {
+    /// 求和
+    #[default]
+    Sum,
+    /// 平均值
+    Average,
+    /// 最小值
+    Min,
+    /// 最大值
+    Max,
+    /// 计数
+    Count,
+}
+
+/// 虚拟资产的计算公式（如"盈透账户合计 = 标签为 IBKR 的股票资产之和"）
+///
+/// 筛选条件同时满足才计入；其他虚拟资产始终不计入，避免循环引用。
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct AssetFormula {
+    /// 聚合方式
+    #[serde(default)]
+    pub aggregate: Aggregate,
+    /// 限定资产类型
+    #[serde(default)]
+    pub asset_type: Option<AssetType>,
+    /// 须包含的全部标签
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// 限定资产ID（为空表示不限）
+    #[serde(default)]
+    pub asset_ids: Vec<Uuid>,
+    /// 是否计入摘要总额；默认不计入，避免与来源资产重复统计
+    #[serde(default)]
+    pub include_in_summary: bool,
+}
+
+impl AssetFormula {
+    /// 创建公式
+    pub fn new(aggregate: Aggregate) -> Self {
+        Self {
+            aggregate,
+            ..Default::default()
+        }
+    }
+
+    /// 限定资产类型
+    pub fn with_asset_type(mut self, asset_type: AssetType) -> Self {
+        self.asset_type = Some(asset_type);
+        self
+    }
+
+    /// 限定标签
+    pub fn with_tags(mut self, tags: Vec<String>) -> Self {
+        self.tags = tags;
+        self
+    }
+
+    /// 资产是否满足筛选条件
+    pub fn matches(&self, asset: &Asset) -> bool {
+        asset.formula.is_none()
+            && self
+                .asset_type
+                .as_ref()
+                .is_none_or(|t| t.as_str() == asset.asset_type.as_str())
+            && self.tags.iter().all(|tag| asset.tags.contains(tag))
+            && (self.asset_ids.is_empty() || self.asset_ids.contains(&asset.id))
+    }
+
+    /// 计算虚拟资产的价值，来源资产按汇率设置折算为 `target` 的货币
+    ///
+    /// 缺少汇率的来源资产不计入。
+    pub fn evaluate(&self, target: &Asset, assets: &[Asset], fx: &FxSettings) -> f64 {
+        let values: Vec<f64> = assets
+            .iter()
+            .filter(|a| a.id != target.id && self.matches(a))
+            .filter_map(|a| {
+                if a.currency == target.currency {
+                    return Some(a.value);
+                }
+                let base = fx.convert(a.value, &a.currency)?;
+                Some(base / fx.rate(&target.currency)?)
+            })
+            .collect();
+
+        match self.aggregate {
+            Aggregate::Sum => values.iter().sum(),
+            Aggregate::Average if values.is_empty() => 0.0,
+            Aggregate::Average => values.iter().sum::<f64>() / values.len() as f64,
+            Aggregate::Min => values.iter().copied().reduce(f64::min).unwrap_or(0.0),
+            Aggregate::Max => values.iter().copied().reduce(f64::max).unwrap_or(0.0),
+            Aggregate::Count => values.len() as f64,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asset::Currency;
+
+    #[test]
+    fn test_formula_evaluate() {
+        let ibkr = vec!["IBKR".to_string()];
+        let a = Asset::new("苹果", AssetType::Stock, 1000.0).with_tags(ibkr.clone());
+        let b = Asset::new("特斯拉", AssetType::Stock, 100.0)
+            .with_currency(Currency::USD)
+            .with_tags(ibkr.clone());
+        let c = Asset::new("茅台", AssetType::Stock, 5000.0);
+        let total = Asset::new("盈透合计", AssetType::Stock, 0.0).with_formula(
+            AssetFormula::new(Aggregate::Sum)
+                .with_asset_type(AssetType::Stock)
+                .with_tags(ibkr),
+        );
+
+        let mut fx = FxSettings::default();
+        let assets = vec![a, b, c, total.clone()];
+        let formula = total.formula.as_ref().unwrap();
+        assert_eq!(formula.evaluate(&total, &assets, &fx), 1000.0);
+
+        fx.rates.insert("USD".to_string(), 7.0);
+        assert_eq!(formula.evaluate(&total, &assets, &fx), 1700.0);
+
+        let count = AssetFormula::new(Aggregate::Count).with_asset_type(AssetType::Stock);
+        assert_eq!(count.evaluate(&total, &assets, &fx), 3.0);
+    }
+}
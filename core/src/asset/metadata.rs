@@ -0,0 +1,122 @@
+//! 元数据字段路径，用于按自定义字段筛选资产
+//!
+//! 路径采用 SQLite JSON1 的写法（如 `$.broker`、`$.lots[0].price`），也接受省略 `$`
+//! 或带 `metadata.` 前缀的写法（如 `broker`、`metadata.broker`）。
+
+use serde_json::Value;
+use std::fmt;
+
+/// 路径中的一段
+#[derive(Debug, Clone, PartialEq)]
+pub enum PathSegment {
+    /// 对象字段
+    Key(String),
+    /// 数组下标
+    Index(usize),
+}
+
+/// 元数据字段路径
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetadataPath {
+    segments: Vec<PathSegment>,
+}
+
+impl MetadataPath {
+    /// 解析路径
+    pub fn parse(path: &str) -> Result<Self, String> {
+        let invalid = || format!("Invalid metadata path: {}", path);
+        let trimmed = path.trim();
+        let rest = match trimmed.strip_prefix('$') {
+            Some(rest) => rest.strip_prefix('.').unwrap_or(rest),
+            None => trimmed.strip_prefix("metadata.").unwrap_or(trimmed),
+        };
+
+        let mut segments = Vec::new();
+        for part in rest.split('.') {
+            let (key, mut indexes) = part.split_at(part.find('[').unwrap_or(part.len()));
+            if key.is_empty() && (indexes.is_empty() || !segments.is_empty()) {
+                return Err(invalid());
+            }
+            if !key.is_empty() {
+                segments.push(PathSegment::Key(key.to_string()));
+            }
+            while let Some(after) = indexes.strip_prefix('[') {
+                let end = after.find(']').ok_or_else(invalid)?;
+                let index = after[..end].trim().parse().map_err(|_| invalid())?;
+                segments.push(PathSegment::Index(index));
+                indexes = &after[end + 1..];
+            }
+            if !indexes.is_empty() {
+                return Err(invalid());
+            }
+        }
+        if segments.is_empty() {
+            return Err(invalid());
+        }
+        Ok(Self { segments })
+    }
+
+    /// 取出路径处的值
+    pub fn lookup<'a>(&self, value: &'a Value) -> Option<&'a Value> {
+        self.segments
+            .iter()
+            .try_fold(value, |value, segment| match segment {
+                PathSegment::Key(key) => value.get(key),
+                PathSegment::Index(index) => value.get(index),
+            })
+    }
+
+    /// 路径处的值是否等于给定值（数字按数值比较）
+    pub fn matches(&self, metadata: &Value, expected: &Value) -> bool {
+        match (self.lookup(metadata), expected) {
+            (Some(Value::Number(a)), Value::Number(b)) => a.as_f64() == b.as_f64(),
+            (Some(found), expected) => found == expected,
+            (None, _) => false,
+        }
+    }
+}
+
+impl fmt::Display for MetadataPath {
+    /// 输出 SQLite JSON1 路径，如 `$."broker"`、`$."lots"[0]`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "$")?;
+        for segment in &self.segments {
+            match segment {
+                PathSegment::Key(key) => write!(f, ".\"{}\"", key.replace('"', "\\\""))?,
+                PathSegment::Index(index) => write!(f, "[{}]", index)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_metadata_path() {
+        let metadata = json!({ "broker": "IBKR", "lots": [{ "price": 10 }, { "price": 12.5 }] });
+
+        for path in ["$.broker", "broker", "metadata.broker"] {
+            let path = MetadataPath::parse(path).unwrap();
+            assert!(path.matches(&metadata, &json!("IBKR")));
+            assert_eq!(path.to_string(), "$.\"broker\"");
+        }
+
+        let price = MetadataPath::parse("$.lots[0].price").unwrap();
+        assert_eq!(price.to_string(), "$.\"lots\"[0].\"price\"");
+        assert!(price.matches(&metadata, &json!(10.0)));
+        assert!(!MetadataPath::parse("lots[1].price")
+            .unwrap()
+            .matches(&metadata, &json!(10)));
+        assert!(!MetadataPath::parse("missing")
+            .unwrap()
+            .matches(&metadata, &json!(null)));
+
+        for invalid in ["", "$", "a..b", "a[x]", "a[1"] {
+            assert!(MetadataPath::parse(invalid).is_err(), "{}", invalid);
+        }
+    }
+}
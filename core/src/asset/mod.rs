@@ -1,5 +1,35 @@
 //! 资产模型定义
 
+mod corporate_action;
+mod cost_basis;
+mod display;
+mod envelope;
+mod formula;
+mod metadata;
 mod models;
+mod period;
+mod relation;
+mod reminder;
+mod sale;
+mod schedule;
+mod schema;
+mod sip;
+mod target;
+mod template;
 
+pub use corporate_action::*;
+pub use cost_basis::*;
+pub use display::*;
+pub use envelope::*;
+pub use formula::*;
+pub use metadata::*;
 pub use models::*;
+pub use period::*;
+pub use relation::*;
+pub use reminder::*;
+pub use sale::*;
+pub use schedule::*;
+pub use schema::*;
+pub use sip::*;
+pub use target::*;
+pub use template::*;
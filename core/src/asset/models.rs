@@ -1,6 +1,7 @@
 //! 资产数据模型
 
-use chrono::{DateTime, Utc};
+use super::{AssetFormula, DisplayMeta, EnvelopeSummary, SaleCharge};
+use chrono::{DateTime, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
@@ -45,6 +46,30 @@ impl AssetType {
             AssetType::Other(_) => "other",
         }
     }
+
+    /// 类型代码（自定义类型返回其名称）
+    pub fn code(&self) -> &str {
+        match self {
+            AssetType::Other(name) => name,
+            other => other.as_str(),
+        }
+    }
+
+    /// 从类型代码（或常用别名，如 `bank`、`gold`）解析，未知代码视为自定义类型
+    pub fn parse(s: &str) -> AssetType {
+        match s.trim().to_lowercase().as_str() {
+            "cash" => AssetType::Cash,
+            "bank_deposit" | "bank" => AssetType::BankDeposit,
+            "stock" => AssetType::Stock,
+            "fund" => AssetType::Fund,
+            "bond" => AssetType::Bond,
+            "real_estate" | "property" => AssetType::RealEstate,
+            "vehicle" | "car" => AssetType::Vehicle,
+            "crypto" | "cryptocurrency" => AssetType::Crypto,
+            "precious_metal" | "gold" | "silver" => AssetType::PreciousMetal,
+            _ => AssetType::Other(s.trim().to_string()),
+        }
+    }
 }
 
 /// 货币类型
@@ -60,6 +85,34 @@ pub enum Currency {
     Other(String),
 }
 
+impl Currency {
+    /// 货币代码
+    pub fn code(&self) -> &str {
+        match self {
+            Currency::CNY => "CNY",
+            Currency::USD => "USD",
+            Currency::EUR => "EUR",
+            Currency::GBP => "GBP",
+            Currency::JPY => "JPY",
+            Currency::HKD => "HKD",
+            Currency::Other(code) => code,
+        }
+    }
+
+    /// 从货币代码解析
+    pub fn from_code(s: &str) -> Currency {
+        match s.trim().to_uppercase().as_str() {
+            "CNY" | "RMB" => Currency::CNY,
+            "USD" => Currency::USD,
+            "EUR" => Currency::EUR,
+            "GBP" => Currency::GBP,
+            "JPY" => Currency::JPY,
+            "HKD" => Currency::HKD,
+            other => Currency::Other(other.to_string()),
+        }
+    }
+}
+
 /// 资产记录
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Asset {
@@ -83,6 +136,49 @@ pub struct Asset {
     pub created_at: DateTime<Utc>,
     /// 更新时间
     pub updated_at: DateTime<Utc>,
+    /// 虚拟资产的计算公式，价值在读取时由其他资产算出
+    #[serde(default)]
+    pub formula: Option<AssetFormula>,
+    /// 买入成本
+    #[serde(default)]
+    pub cost_basis: Option<f64>,
+    /// 目标价（止盈目标或止损线）
+    #[serde(default)]
+    pub target_price: Option<f64>,
+    /// 已就当前这次达到目标发送过通知
+    #[serde(default)]
+    pub target_notified: bool,
+    /// 创建该资产的导入批次
+    #[serde(default)]
+    pub import_batch: Option<Uuid>,
+    /// 外部系统中的标识（如券商账户号），全局唯一
+    #[serde(default)]
+    pub external_id: Option<String>,
+    /// 显示样式（未设置的部分取标签与资产类型的样式）
+    #[serde(default)]
+    pub display: DisplayMeta,
+    /// 同时显示的货币（为空时使用本位币）
+    #[serde(default)]
+    pub display_currency: Option<Currency>,
+    /// 移入回收站的时间（仅回收站中的资产有值）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deleted_at: Option<DateTime<Utc>>,
+    /// 折算为显示货币的价值（读取时计算，与显示货币相同时为空）
+    #[serde(default, skip_deserializing, skip_serializing_if = "Option::is_none")]
+    pub conversion: Option<CurrencyConversion>,
+}
+
+/// 资产价值折算为另一种货币的结果
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CurrencyConversion {
+    /// 折算后的货币
+    pub currency: Currency,
+    /// 折算后的价值
+    pub converted_value: f64,
+    /// 1 单位资产货币折合多少折算后的货币
+    pub rate: f64,
+    /// 所用历史汇率的日期（使用手动设置的汇率时为空）
+    pub rate_date: Option<NaiveDate>,
 }
 
 impl Asset {
@@ -100,6 +196,16 @@ impl Asset {
             metadata: serde_json::json!({}),
             created_at: now,
             updated_at: now,
+            formula: None,
+            cost_basis: None,
+            target_price: None,
+            target_notified: false,
+            import_batch: None,
+            external_id: None,
+            display: DisplayMeta::default(),
+            display_currency: None,
+            deleted_at: None,
+            conversion: None,
         }
     }
 
@@ -127,6 +233,47 @@ impl Asset {
         self
     }
 
+    /// 设置显示样式
+    pub fn with_display(mut self, display: DisplayMeta) -> Self {
+        self.display = display;
+        self
+    }
+
+    /// 设置同时显示的货币
+    pub fn with_display_currency(mut self, currency: Currency) -> Self {
+        self.display_currency = Some(currency);
+        self
+    }
+
+    /// 设为由公式计算的虚拟资产
+    pub fn with_formula(mut self, formula: AssetFormula) -> Self {
+        self.formula = Some(formula);
+        self
+    }
+
+    /// 设置买入成本
+    pub fn with_cost_basis(mut self, cost_basis: f64) -> Self {
+        self.cost_basis = Some(cost_basis);
+        self
+    }
+
+    /// 设置目标价
+    pub fn with_target_price(mut self, target_price: f64) -> Self {
+        self.target_price = Some(target_price);
+        self
+    }
+
+    /// 设置外部标识
+    pub fn with_external_id(mut self, external_id: impl Into<String>) -> Self {
+        self.external_id = Some(external_id.into());
+        self
+    }
+
+    /// 是否为虚拟资产
+    pub fn is_virtual(&self) -> bool {
+        self.formula.is_some()
+    }
+
     /// 更新资产价值
     pub fn update_value(&mut self, value: f64) {
         self.value = value;
@@ -151,6 +298,103 @@ pub struct AssetTransaction {
     pub note: Option<String>,
     /// 交易时间
     pub timestamp: DateTime<Utc>,
+    /// 更正分录所更正的原交易ID
+    #[serde(default)]
+    pub corrects: Option<Uuid>,
+    /// 原始金额（交易币种与资产币种不同时，如用人民币卡支付美元）
+    #[serde(default)]
+    pub original_amount: Option<f64>,
+    /// 原始金额的币种
+    #[serde(default)]
+    pub original_currency: Option<Currency>,
+    /// 实际使用的汇率：1 单位原币折合多少资产币种
+    #[serde(default)]
+    pub fx_rate: Option<f64>,
+    /// 清算状态（旧数据视为已清算）
+    #[serde(default)]
+    pub status: TransactionStatus,
+    /// 未来日期的计划交易，到期入账前不计入资产价值
+    #[serde(default)]
+    pub scheduled: bool,
+    /// 对账单导入时的流水指纹，用于跳过重复导入
+    #[serde(default)]
+    pub import_hash: Option<String>,
+    /// 导入该交易的批次
+    #[serde(default)]
+    pub import_batch: Option<Uuid>,
+    /// 外部系统中的标识（如银行流水号），在所属资产内唯一
+    #[serde(default)]
+    pub external_id: Option<String>,
+    /// 卖出时的手续费或税费：所属的卖出交易与费用金额
+    #[serde(default)]
+    pub charge: Option<SaleCharge>,
+}
+
+impl AssetTransaction {
+    /// 创建新交易记录
+    pub fn new(
+        asset_id: Uuid,
+        transaction_type: TransactionType,
+        amount_before: f64,
+        amount_after: f64,
+    ) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            asset_id,
+            transaction_type,
+            amount_before,
+            amount_after,
+            note: None,
+            timestamp: Utc::now(),
+            corrects: None,
+            original_amount: None,
+            original_currency: None,
+            fx_rate: None,
+            status: TransactionStatus::default(),
+            scheduled: false,
+            import_hash: None,
+            import_batch: None,
+            external_id: None,
+            charge: None,
+        }
+    }
+
+    /// 设置备注
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.note = Some(note.into());
+        self
+    }
+
+    /// 设置交易时间
+    pub fn with_timestamp(mut self, timestamp: DateTime<Utc>) -> Self {
+        self.timestamp = timestamp;
+        self
+    }
+
+    /// 记录原币金额与实际汇率
+    pub fn with_original_amount(mut self, amount: f64, currency: Currency, fx_rate: f64) -> Self {
+        self.original_amount = Some(amount);
+        self.original_currency = Some(currency);
+        self.fx_rate = Some(fx_rate);
+        self
+    }
+
+    /// 设置清算状态
+    pub fn with_status(mut self, status: TransactionStatus) -> Self {
+        self.status = status;
+        self
+    }
+
+    /// 设置外部标识
+    pub fn with_external_id(mut self, external_id: impl Into<String>) -> Self {
+        self.external_id = Some(external_id.into());
+        self
+    }
+
+    /// 变动金额
+    pub fn delta(&self) -> f64 {
+        self.amount_after - self.amount_before
+    }
 }
 
 /// 交易类型
@@ -171,6 +415,29 @@ pub enum TransactionType {
     Transfer,
 }
 
+/// 交易清算状态
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum TransactionStatus {
+    /// 待入账（如未结算的刷卡）
+    Pending,
+    /// 已入账
+    #[default]
+    Cleared,
+    /// 已与对账单核对
+    Reconciled,
+}
+
+impl TransactionStatus {
+    /// 是否允许转换到目标状态；已对账的交易只能退回已入账
+    pub fn can_transition_to(self, to: TransactionStatus) -> bool {
+        !matches!(
+            (self, to),
+            (TransactionStatus::Reconciled, TransactionStatus::Pending)
+        )
+    }
+}
+
 /// 资产统计摘要
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct AssetSummary {
@@ -182,6 +449,12 @@ pub struct AssetSummary {
     pub by_currency: std::collections::HashMap<String, f64>,
     /// 资产数量
     pub asset_count: usize,
+    /// 各资产的信封分配（仅含设置了信封的资产）
+    #[serde(default)]
+    pub envelopes: Vec<EnvelopeSummary>,
+    /// 插件通过 `contribute_summary` 提供的附加指标
+    #[serde(default)]
+    pub plugin_metrics: std::collections::HashMap<String, serde_json::Value>,
 }
 
 #[cfg(test)]
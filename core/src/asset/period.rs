@@ -0,0 +1,38 @@
+//! 会计期间模型
+
+use chrono::{DateTime, Datelike, Utc};
+use serde::{Deserialize, Serialize};
+
+/// 已结账的月度期间
+///
+/// 结账后，该月内的交易不可新增、修改或删除，需先显式解锁；调整通过当前期间的更正分录完成。
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ClosedPeriod {
+    /// 年份
+    pub year: i32,
+    /// 月份（1~12）
+    pub month: u32,
+    /// 结账时间
+    pub closed_at: DateTime<Utc>,
+}
+
+impl ClosedPeriod {
+    /// 创建结账期间
+    pub fn new(year: i32, month: u32) -> Self {
+        Self {
+            year,
+            month,
+            closed_at: Utc::now(),
+        }
+    }
+
+    /// 时间是否落在该期间内
+    pub fn contains(&self, t: DateTime<Utc>) -> bool {
+        t.year() == self.year && t.month() == self.month
+    }
+
+    /// 期间标识，如 "2024-03"
+    pub fn label(&self) -> String {
+        format!("{:04}-{:02}", self.year, self.month)
+    }
+}
@@ -0,0 +1,103 @@
+//! 资产关系模型
+
+use super::Asset;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// 关系类型
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RelationKind {
+    /// 以...作抵押（如贷款以房产抵押）
+    SecuredBy,
+    /// 包含（如证券账户包含股票持仓）
+    Contains,
+    /// 保障（如保险保障车辆）
+    Covers,
+    /// 其他关系
+    Other(String),
+}
+
+/// 资产之间的关系（有向边：from -> to）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AssetRelation {
+    /// 唯一标识符
+    pub id: Uuid,
+    /// 起点资产ID
+    pub from_id: Uuid,
+    /// 终点资产ID
+    pub to_id: Uuid,
+    /// 关系类型
+    pub kind: RelationKind,
+    /// 备注
+    pub note: Option<String>,
+    /// 创建时间
+    pub created_at: DateTime<Utc>,
+}
+
+impl AssetRelation {
+    /// 创建新关系
+    pub fn new(from_id: Uuid, to_id: Uuid, kind: RelationKind) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            from_id,
+            to_id,
+            kind,
+            note: None,
+            created_at: Utc::now(),
+        }
+    }
+
+    /// 设置备注
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.note = Some(note.into());
+        self
+    }
+}
+
+/// 关系方向（相对于查询的资产）
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RelationDirection {
+    /// 查询的资产是起点
+    Outgoing,
+    /// 查询的资产是终点
+    Incoming,
+}
+
+/// 关联资产
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RelatedAsset {
+    /// 关系
+    pub relation: AssetRelation,
+    /// 关系方向
+    pub direction: RelationDirection,
+    /// 另一端的资产
+    pub asset: Asset,
+}
+
+/// 关系图节点
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GraphNode {
+    pub id: Uuid,
+    pub name: String,
+    pub asset_type: String,
+    pub value: f64,
+}
+
+/// 关系图边
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GraphEdge {
+    pub id: Uuid,
+    pub source: Uuid,
+    pub target: Uuid,
+    pub kind: RelationKind,
+}
+
+/// 资产关系图（用于可视化）
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct RelationGraph {
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<GraphEdge>,
+}
@@ -0,0 +1,149 @@
+//! 复查提醒模型
+
+use chrono::{DateTime, Duration, Months, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// 提醒重复周期
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Recurrence {
+    /// 每天
+    Daily,
+    /// 每周
+    Weekly,
+    /// 每月
+    Monthly,
+    /// 每季度
+    Quarterly,
+    /// 每年
+    Yearly,
+}
+
+impl Recurrence {
+    /// 计算下一次到期时间
+    pub fn advance(&self, from: DateTime<Utc>) -> DateTime<Utc> {
+        match self {
+            Recurrence::Daily => from + Duration::days(1),
+            Recurrence::Weekly => from + Duration::weeks(1),
+            Recurrence::Monthly => from.checked_add_months(Months::new(1)).unwrap_or(from),
+            Recurrence::Quarterly => from.checked_add_months(Months::new(3)).unwrap_or(from),
+            Recurrence::Yearly => from.checked_add_months(Months::new(12)).unwrap_or(from),
+        }
+    }
+}
+
+/// 复查提醒（如"每年重新估价房产"、"每季度再平衡"）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Reminder {
+    /// 唯一标识符
+    pub id: Uuid,
+    /// 关联的资产ID（为空表示针对整个资产组合）
+    pub asset_id: Option<Uuid>,
+    /// 提醒标题
+    pub title: String,
+    /// 重复周期
+    pub recurrence: Recurrence,
+    /// 下一次到期时间
+    pub next_due: DateTime<Utc>,
+    /// 推迟到的时间
+    pub snoozed_until: Option<DateTime<Utc>>,
+    /// 历次完成时间
+    #[serde(default)]
+    pub completions: Vec<DateTime<Utc>>,
+    /// 已发送通知对应的到期时间，避免重复通知
+    #[serde(default)]
+    pub notified_due: Option<DateTime<Utc>>,
+    /// 创建时间
+    pub created_at: DateTime<Utc>,
+}
+
+impl Reminder {
+    /// 创建新提醒
+    pub fn new(title: impl Into<String>, recurrence: Recurrence, first_due: DateTime<Utc>) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            asset_id: None,
+            title: title.into(),
+            recurrence,
+            next_due: first_due,
+            snoozed_until: None,
+            completions: Vec::new(),
+            notified_due: None,
+            created_at: Utc::now(),
+        }
+    }
+
+    /// 关联到资产
+    pub fn for_asset(mut self, asset_id: Uuid) -> Self {
+        self.asset_id = Some(asset_id);
+        self
+    }
+
+    /// 是否已到期（推迟期间不算到期）
+    pub fn is_due(&self, now: DateTime<Utc>) -> bool {
+        if let Some(until) = self.snoozed_until {
+            if until > now {
+                return false;
+            }
+        }
+        self.next_due <= now
+    }
+
+    /// 标记完成，并推进到下一个周期
+    pub fn complete(&mut self, now: DateTime<Utc>) {
+        self.completions.push(now);
+        self.snoozed_until = None;
+        // 至少推进一个周期（提前完成时从到期时间推进），再跳过已错过的周期
+        loop {
+            let next = self.recurrence.advance(self.next_due);
+            if next == self.next_due {
+                break;
+            }
+            self.next_due = next;
+            if self.next_due > now {
+                break;
+            }
+        }
+    }
+
+    /// 推迟提醒
+    pub fn snooze(&mut self, until: DateTime<Utc>) {
+        self.snoozed_until = Some(until);
+        // 推迟结束后允许再次通知
+        self.notified_due = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reminder_lifecycle() {
+        let now = Utc::now();
+        let mut reminder = Reminder::new("重新估价房产", Recurrence::Yearly, now - Duration::days(1));
+        assert!(reminder.is_due(now));
+
+        reminder.snooze(now + Duration::days(7));
+        assert!(!reminder.is_due(now));
+        assert!(reminder.is_due(now + Duration::days(8)));
+
+        reminder.complete(now);
+        assert_eq!(reminder.completions.len(), 1);
+        assert!(reminder.next_due > now);
+        assert!(!reminder.is_due(now));
+    }
+
+    #[test]
+    fn test_complete_early() {
+        let now = Utc::now();
+        let due = now + Duration::days(3);
+        let mut reminder = Reminder::new("每月记账", Recurrence::Monthly, due);
+
+        // 到期前完成，推进到到期时间之后的下一个周期
+        reminder.complete(now);
+        assert_eq!(reminder.next_due, Recurrence::Monthly.advance(due));
+        assert!(!reminder.is_due(due + Duration::days(1)));
+    }
+}
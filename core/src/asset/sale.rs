@@ -0,0 +1,241 @@
+//! 卖出：部分卖出持仓并记下手续费与税费，供已实现盈亏与税费汇总使用
+//!
+//! 卖出金额（扣费前）从所卖资产中扣除。指定收款资产时卖出金额转入收款资产，手续费与税费作为
+//! 关联的支出交易从收款资产扣除，实际到账为净额；未指定时卖出款不在账内，手续费与税费记为
+//! 所卖资产上金额为零的说明交易。汇总时从关联交易读取费用，不需要另外录入。
+
+use super::{replay_cost_basis, Asset, AssetTransaction, CostMethod, TransactionType};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// 卖出费用的类型
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum ChargeKind {
+    /// 手续费（佣金、过户费等）
+    Fee,
+    /// 税费（印花税、资本利得税等）
+    Tax,
+}
+
+/// 卖出费用交易所关联的卖出
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct SaleCharge {
+    /// 所属的卖出交易
+    pub sale_id: Uuid,
+    pub kind: ChargeKind,
+    /// 费用金额（未记入收款资产时交易的变动为零，金额以此为准）
+    pub amount: f64,
+}
+
+/// 卖出指令
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SellOrder {
+    pub asset_id: Uuid,
+    /// 卖出金额（扣除费用前）
+    pub gross: f64,
+    #[serde(default)]
+    pub fee: f64,
+    #[serde(default)]
+    pub tax: f64,
+    /// 收款资产（如证券账户的现金）
+    #[serde(default)]
+    pub pay_to: Option<Uuid>,
+    #[serde(default)]
+    pub note: Option<String>,
+}
+
+impl SellOrder {
+    /// 检查金额是否有效
+    pub fn validate(&self) -> Result<(), String> {
+        if !(self.gross.is_finite() && self.gross > 0.0) {
+            return Err(format!("Invalid sell amount: {}", self.gross));
+        }
+        for (label, amount) in [("fee", self.fee), ("tax", self.tax)] {
+            if !(amount.is_finite() && amount >= 0.0) {
+                return Err(format!("Invalid {}: {}", label, amount));
+            }
+        }
+        if self.fee + self.tax > self.gross {
+            return Err("Fee and tax exceed the sell amount".to_string());
+        }
+        if self.pay_to == Some(self.asset_id) {
+            return Err("Cannot pay proceeds to the sold asset".to_string());
+        }
+        Ok(())
+    }
+
+    /// 扣除手续费与税费后的实际到账金额
+    pub fn net_proceeds(&self) -> f64 {
+        self.gross - self.fee - self.tax
+    }
+
+    /// 需要记录的费用（金额为零的跳过）
+    pub fn charges(&self) -> impl Iterator<Item = (ChargeKind, f64)> {
+        [(ChargeKind::Fee, self.fee), (ChargeKind::Tax, self.tax)]
+            .into_iter()
+            .filter(|(_, amount)| *amount > 0.0)
+    }
+}
+
+/// 一次卖出记录的交易
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SaleRecord {
+    /// 所卖资产上的卖出交易
+    pub sale: AssetTransaction,
+    /// 收款资产上的转入交易
+    pub proceeds: Option<AssetTransaction>,
+    /// 关联的手续费与税费交易
+    pub charges: Vec<AssetTransaction>,
+    pub net_proceeds: f64,
+}
+
+/// 一笔卖出的已实现盈亏
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RealizedSale {
+    pub sale_id: Uuid,
+    pub asset_id: Uuid,
+    pub asset_name: String,
+    pub timestamp: DateTime<Utc>,
+    /// 卖出金额（扣除费用前）
+    pub gross: f64,
+    pub fee: f64,
+    pub tax: f64,
+    /// 结转的成本
+    pub cost: f64,
+    /// 扣除手续费后的税前盈亏
+    pub gain: f64,
+    /// 税后盈亏
+    pub net_gain: f64,
+}
+
+/// 已实现盈亏与税费汇总
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RealizedGainsReport {
+    pub method: CostMethod,
+    /// 按时间排序的卖出
+    pub sales: Vec<RealizedSale>,
+    pub total_gain: f64,
+    pub total_fees: f64,
+    pub total_taxes: f64,
+    pub total_net_gain: f64,
+}
+
+impl RealizedGainsReport {
+    pub fn new(method: CostMethod, mut sales: Vec<RealizedSale>) -> Self {
+        sales.sort_by_key(|s| s.timestamp);
+        Self {
+            method,
+            total_gain: sales.iter().map(|s| s.gain).sum(),
+            total_fees: sales.iter().map(|s| s.fee).sum(),
+            total_taxes: sales.iter().map(|s| s.tax).sum(),
+            total_net_gain: sales.iter().map(|s| s.net_gain).sum(),
+            sales,
+        }
+    }
+}
+
+/// 资产各笔卖出的已实现盈亏：成本按所选方法重放，费用取自关联到卖出交易的费用交易
+///
+/// `transactions` 为资产自身的交易，`charges` 可包含收款资产上的费用交易。
+pub fn realized_sales(
+    asset: &Asset,
+    transactions: &[AssetTransaction],
+    charges: &[AssetTransaction],
+    method: CostMethod,
+) -> Vec<RealizedSale> {
+    let mut fees: HashMap<(Uuid, ChargeKind), f64> = HashMap::new();
+    for charge in charges.iter().filter_map(|t| t.charge) {
+        *fees.entry((charge.sale_id, charge.kind)).or_default() += charge.amount;
+    }
+    let sales: HashMap<Uuid, &AssetTransaction> = transactions
+        .iter()
+        .filter(|t| t.transaction_type == TransactionType::Sell && t.delta() < 0.0)
+        .map(|t| (t.id, t))
+        .collect();
+
+    replay_cost_basis(asset, transactions, method)
+        .adjustments
+        .into_iter()
+        .filter_map(|step| {
+            let sale = sales.get(&step.transaction_id?)?;
+            let charged = |kind| fees.get(&(sale.id, kind)).copied().unwrap_or(0.0);
+            let (fee, tax) = (charged(ChargeKind::Fee), charged(ChargeKind::Tax));
+            let gain = step.realized_gain - fee;
+            Some(RealizedSale {
+                sale_id: sale.id,
+                asset_id: asset.id,
+                asset_name: asset.name.clone(),
+                timestamp: sale.timestamp,
+                gross: -sale.delta(),
+                fee,
+                tax,
+                cost: -step.cost_change,
+                gain,
+                net_gain: gain - tax,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asset::AssetType;
+    use chrono::Duration;
+
+    #[test]
+    fn test_realized_sales() {
+        let mut order = SellOrder {
+            asset_id: Uuid::new_v4(),
+            gross: 1000.0,
+            fee: 5.0,
+            tax: 1.0,
+            pay_to: None,
+            note: None,
+        };
+        assert!(order.validate().is_ok());
+        assert_eq!(order.net_proceeds(), 994.0);
+        order.tax = 0.0;
+        assert_eq!(order.charges().count(), 1);
+        order.fee = 2000.0;
+        assert!(order.validate().is_err());
+        order.fee = -1.0;
+        assert!(order.validate().is_err());
+
+        let stock = Asset::new("茅台", AssetType::Stock, 1000.0);
+        let start = Utc::now() - Duration::days(10);
+        let buy = AssetTransaction::new(stock.id, TransactionType::Buy, 0.0, 1000.0)
+            .with_timestamp(start);
+        let sale = AssetTransaction::new(stock.id, TransactionType::Sell, 2000.0, 1000.0);
+        let cash = Uuid::new_v4();
+        let charge = |kind, amount: f64| {
+            let mut t = AssetTransaction::new(cash, TransactionType::Expense, 0.0, -amount);
+            t.charge = Some(SaleCharge {
+                sale_id: sale.id,
+                kind,
+                amount,
+            });
+            t
+        };
+        let charges = [charge(ChargeKind::Fee, 5.0), charge(ChargeKind::Tax, 1.0)];
+
+        // 1000 买入，涨到 2000 后卖出一半：结转成本 500
+        let sales = realized_sales(
+            &stock,
+            &[buy, sale.clone()],
+            &charges,
+            CostMethod::WeightedAverage,
+        );
+        assert_eq!(sales.len(), 1);
+        assert_eq!(sales[0].sale_id, sale.id);
+        assert_eq!((sales[0].gross, sales[0].cost), (1000.0, 500.0));
+        assert_eq!((sales[0].gain, sales[0].net_gain), (495.0, 494.0));
+
+        let report = RealizedGainsReport::new(CostMethod::WeightedAverage, sales);
+        assert_eq!((report.total_fees, report.total_taxes), (5.0, 1.0));
+        assert_eq!(report.total_net_gain, 494.0);
+    }
+}
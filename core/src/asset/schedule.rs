@@ -0,0 +1,94 @@
+//! 计划交易与预计余额
+
+use super::{Asset, AssetTransaction};
+use crate::assumptions::Assumptions;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// 单个资产的预计余额
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProjectedBalance {
+    pub asset_id: Uuid,
+    pub name: String,
+    /// 当前价值（不含计划交易）
+    pub current_value: f64,
+    /// 截止日前计划交易的变动合计
+    pub scheduled_delta: f64,
+    /// 按利率假设计算的当前价值到截止日的利息
+    pub interest: f64,
+    /// 预计余额
+    pub projected_value: f64,
+    /// 预计余额按预期通胀折算为今天的购买力
+    pub real_value: f64,
+}
+
+/// 计算各资产截至 `until`（含）的预计余额，利息与通胀按利率环境假设计算
+pub fn projected_balances(
+    assets: &[Asset],
+    transactions: &[AssetTransaction],
+    now: DateTime<Utc>,
+    until: DateTime<Utc>,
+    assumptions: &Assumptions,
+) -> Vec<ProjectedBalance> {
+    let days = (until - now).num_days();
+    assets
+        .iter()
+        .map(|asset| {
+            let scheduled_delta: f64 = transactions
+                .iter()
+                .filter(|t| t.scheduled && t.asset_id == asset.id && t.timestamp <= until)
+                .map(|t| t.delta())
+                .sum();
+            let interest = assumptions.accrued_interest(asset, days);
+            let projected_value = asset.value + scheduled_delta + interest;
+            ProjectedBalance {
+                asset_id: asset.id,
+                name: asset.name.clone(),
+                current_value: asset.value,
+                scheduled_delta,
+                interest,
+                projected_value,
+                real_value: assumptions.real_value(projected_value, days),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asset::{AssetType, TransactionType};
+    use chrono::Duration;
+
+    #[test]
+    fn test_projected_balances() {
+        let now = Utc::now();
+        let card = Asset::new("储蓄卡", AssetType::BankDeposit, 5000.0);
+        let mut rent = AssetTransaction::new(card.id, TransactionType::Expense, 5000.0, 2000.0);
+        rent.timestamp = now + Duration::days(5);
+        rent.scheduled = true;
+        let mut salary = AssetTransaction::new(card.id, TransactionType::Income, 2000.0, 12000.0);
+        salary.timestamp = now + Duration::days(40);
+        salary.scheduled = true;
+        let posted = AssetTransaction::new(card.id, TransactionType::Income, 4000.0, 5000.0);
+
+        let assets = [card];
+        let txns = [rent, salary, posted];
+        let none = Assumptions::default();
+        let month = projected_balances(&assets, &txns, now, now + Duration::days(30), &none);
+        assert_eq!(month[0].projected_value, 2000.0);
+        let later = projected_balances(&assets, &txns, now, now + Duration::days(60), &none);
+        assert_eq!(later[0].projected_value, 12000.0);
+
+        // 存款利息按当前价值计算
+        let rates = Assumptions {
+            deposit_rate: 0.02,
+            ..none
+        };
+        let year = projected_balances(&assets, &txns, now, now + Duration::days(365), &rates);
+        assert!((year[0].interest - 100.0).abs() < 1e-9);
+        assert!((year[0].projected_value - 12100.0).abs() < 1e-9);
+        assert_eq!(year[0].real_value, year[0].projected_value);
+    }
+}
@@ -0,0 +1,177 @@
+//! 资产元数据的软约束：按资产类型以 JSON Schema 校验 `metadata`
+//!
+//! 内置常见类型的 Schema，插件可为任意类型代码（包括自定义类型）提供 Schema 并覆盖
+//! 内置定义。Schema 只约束已出现字段的类型与取值，未声明的字段不受限制；没有
+//! Schema 的类型不做校验。
+
+use super::AssetType;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::fmt;
+
+/// 元数据校验错误
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SchemaViolation {
+    /// 出错字段的 JSON Pointer（如 "/shares"），为空表示元数据本身
+    pub path: String,
+    /// 错误说明
+    pub message: String,
+}
+
+impl fmt::Display for SchemaViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.path.is_empty() {
+            write!(f, "{}", self.message)
+        } else {
+            write!(f, "{}: {}", self.path, self.message)
+        }
+    }
+}
+
+/// 各资产类型的元数据 Schema
+#[derive(Debug, Clone)]
+pub struct MetadataSchemas {
+    schemas: HashMap<String, Value>,
+}
+
+impl MetadataSchemas {
+    /// 仅包含内置 Schema
+    pub fn builtin() -> Self {
+        let schemas = [
+            (
+                "stock",
+                json!({
+                    "type": "object",
+                    "properties": {
+                        "ticker": { "type": "string", "minLength": 1 },
+                        "exchange": { "type": "string" },
+                        "broker": { "type": "string" },
+                        "shares": { "type": "number", "minimum": 0 }
+                    }
+                }),
+            ),
+            (
+                "fund",
+                json!({
+                    "type": "object",
+                    "properties": {
+                        "code": { "type": "string", "minLength": 1 },
+                        "broker": { "type": "string" },
+                        "units": { "type": "number", "minimum": 0 },
+                        "nav": { "type": "number", "exclusiveMinimum": 0 }
+                    }
+                }),
+            ),
+            (
+                "bond",
+                json!({
+                    "type": "object",
+                    "properties": {
+                        "issuer": { "type": "string" },
+                        "coupon_rate": { "type": "number", "minimum": 0 },
+                        "maturity": { "type": "string", "pattern": "^\\d{4}-\\d{2}-\\d{2}$" }
+                    }
+                }),
+            ),
+            (
+                "real_estate",
+                json!({
+                    "type": "object",
+                    "properties": {
+                        "address": { "type": "string" },
+                        "area": { "type": "number", "exclusiveMinimum": 0 }
+                    }
+                }),
+            ),
+            (
+                "crypto",
+                json!({
+                    "type": "object",
+                    "properties": {
+                        "symbol": { "type": "string", "minLength": 1 },
+                        "wallet": { "type": "string" },
+                        "units": { "type": "number", "minimum": 0 }
+                    }
+                }),
+            ),
+        ];
+        Self {
+            schemas: schemas
+                .into_iter()
+                .map(|(code, schema)| (code.to_string(), schema))
+                .collect(),
+        }
+    }
+
+    /// 注册类型的 Schema（覆盖已有定义），Schema 本身无效时返回错误
+    pub fn register(&mut self, type_code: &str, schema: Value) -> Result<(), String> {
+        jsonschema::validator_for(&schema)
+            .map_err(|e| format!("Invalid schema for {}: {}", type_code, e))?;
+        self.schemas.insert(type_code.to_string(), schema);
+        Ok(())
+    }
+
+    /// 获取类型的 Schema
+    pub fn get(&self, type_code: &str) -> Option<&Value> {
+        self.schemas.get(type_code)
+    }
+
+    /// 所有 Schema（按类型代码）
+    pub fn all(&self) -> &HashMap<String, Value> {
+        &self.schemas
+    }
+
+    /// 校验元数据，返回所有错误（无 Schema 时总是通过）
+    pub fn validate(&self, asset_type: &AssetType, metadata: &Value) -> Vec<SchemaViolation> {
+        let Some(schema) = self.schemas.get(asset_type.code()) else {
+            return Vec::new();
+        };
+        // 注册时已校验过 Schema
+        let Ok(validator) = jsonschema::validator_for(schema) else {
+            return Vec::new();
+        };
+        validator
+            .iter_errors(metadata)
+            .map(|e| SchemaViolation {
+                path: e.instance_path.to_string(),
+                message: e.to_string(),
+            })
+            .collect()
+    }
+}
+
+impl Default for MetadataSchemas {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_metadata() {
+        let mut schemas = MetadataSchemas::builtin();
+        assert!(schemas
+            .validate(&AssetType::Stock, &json!({ "ticker": "AAPL", "note": 1 }))
+            .is_empty());
+
+        let errors = schemas.validate(&AssetType::Stock, &json!({ "shares": "many" }));
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].path, "/shares");
+
+        // 没有 Schema 的类型不校验
+        assert!(schemas
+            .validate(&AssetType::Cash, &json!({ "shares": "many" }))
+            .is_empty());
+
+        let watch = AssetType::Other("watch".to_string());
+        schemas
+            .register("watch", json!({ "type": "object", "required": ["brand"] }))
+            .unwrap();
+        assert_eq!(schemas.validate(&watch, &json!({})).len(), 1);
+        assert!(schemas.register("bad", json!({ "type": 42 })).is_err());
+    }
+}
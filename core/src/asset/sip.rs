@@ -0,0 +1,315 @@
+//! 基金定投：定投计划、每期记录、平均成本与定投收益率
+//!
+//! 每期按扣款金额减去手续费、除以当期净值得到确认份额；定投收益率为按各期扣款日期与
+//! 当前市值计算的年化内部收益率（XIRR）。
+
+use super::{Asset, Recurrence};
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+
+/// 定投计划
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SipPlan {
+    /// 唯一标识符
+    pub id: Uuid,
+    /// 定投的基金资产
+    pub asset_id: Uuid,
+    /// 扣款资产（如银行卡），为空时不记录扣款
+    #[serde(default)]
+    pub pay_from: Option<Uuid>,
+    /// 每期扣款金额
+    pub amount: f64,
+    /// 定投周期
+    pub recurrence: Recurrence,
+    /// 下一期扣款时间
+    pub next_run: DateTime<Utc>,
+    /// 结束时间（为空表示长期定投）
+    #[serde(default)]
+    pub end: Option<DateTime<Utc>>,
+    /// 是否启用
+    pub active: bool,
+    /// 已执行的各期记录（按日期排序）
+    #[serde(default)]
+    pub installments: Vec<SipInstallment>,
+    /// 创建时间
+    pub created_at: DateTime<Utc>,
+}
+
+/// 一期定投
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SipInstallment {
+    /// 扣款日期
+    pub date: DateTime<Utc>,
+    /// 扣款金额
+    pub amount: f64,
+    /// 手续费
+    #[serde(default)]
+    pub fee: f64,
+    /// 确认净值
+    pub nav: f64,
+    /// 确认份额
+    pub units: f64,
+    /// 基金资产上记录的买入交易
+    #[serde(default)]
+    pub transaction_id: Option<Uuid>,
+}
+
+/// 净值历史中的一点
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct NavPoint {
+    pub date: NaiveDate,
+    pub nav: f64,
+}
+
+/// 定投报告
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SipReport {
+    pub plan_id: Uuid,
+    pub asset_id: Uuid,
+    /// 基金名称
+    pub name: String,
+    /// 已执行期数
+    pub installments: usize,
+    /// 累计投入（含手续费）
+    pub total_invested: f64,
+    /// 累计手续费
+    pub total_fees: f64,
+    /// 累计份额
+    pub total_units: f64,
+    /// 平均成本（每份）
+    pub average_cost: Option<f64>,
+    /// 当前净值
+    pub current_nav: Option<f64>,
+    /// 当前市值
+    pub market_value: f64,
+    /// 浮动盈亏
+    pub profit: f64,
+    /// 累计收益率
+    pub return_rate: Option<f64>,
+    /// 年化内部收益率
+    pub irr: Option<f64>,
+    /// 各期确认净值
+    pub nav_history: Vec<NavPoint>,
+}
+
+impl SipPlan {
+    /// 创建新计划
+    pub fn new(
+        asset_id: Uuid,
+        amount: f64,
+        recurrence: Recurrence,
+        first_run: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            asset_id,
+            pay_from: None,
+            amount,
+            recurrence,
+            next_run: first_run,
+            end: None,
+            active: true,
+            installments: Vec::new(),
+            created_at: Utc::now(),
+        }
+    }
+
+    /// 从指定资产扣款
+    pub fn with_pay_from(mut self, pay_from: Uuid) -> Self {
+        self.pay_from = Some(pay_from);
+        self
+    }
+
+    /// 检查计划是否有效
+    pub fn validate(&self) -> Result<(), String> {
+        if !(self.amount.is_finite() && self.amount > 0.0) {
+            return Err(format!("Invalid installment amount: {}", self.amount));
+        }
+        if self.pay_from == Some(self.asset_id) {
+            return Err("A plan cannot pay from the fund itself".to_string());
+        }
+        if matches!(self.end, Some(end) if end < self.next_run) {
+            return Err("Plan ends before the next installment".to_string());
+        }
+        Ok(())
+    }
+
+    /// 是否到了扣款时间
+    pub fn is_due(&self, now: DateTime<Utc>) -> bool {
+        self.active && self.next_run <= now && !matches!(self.end, Some(end) if end < self.next_run)
+    }
+
+    /// 记入一期，下一期扣款时间推进到该期之后
+    pub fn record(&mut self, installment: SipInstallment) {
+        while self.next_run <= installment.date {
+            let next = self.recurrence.advance(self.next_run);
+            if next == self.next_run {
+                break;
+            }
+            self.next_run = next;
+        }
+        let pos = self
+            .installments
+            .partition_point(|i| i.date <= installment.date);
+        self.installments.insert(pos, installment);
+    }
+}
+
+impl SipInstallment {
+    /// 按扣款金额、手续费与净值计算确认份额
+    pub fn new(date: DateTime<Utc>, amount: f64, fee: f64, nav: f64) -> Result<Self, String> {
+        if !(amount.is_finite() && amount > 0.0) {
+            return Err(format!("Invalid installment amount: {}", amount));
+        }
+        if !(fee.is_finite() && (0.0..amount).contains(&fee)) {
+            return Err(format!("Invalid installment fee: {}", fee));
+        }
+        if !(nav.is_finite() && nav > 0.0) {
+            return Err(format!("Invalid NAV: {}", nav));
+        }
+        Ok(Self {
+            date,
+            amount,
+            fee,
+            nav,
+            units: (amount - fee) / nav,
+            transaction_id: None,
+        })
+    }
+}
+
+/// 基金的最新净值：元数据中的 `nav`，否则为最近一期的确认净值
+pub fn current_nav(asset: &Asset, plan: &SipPlan) -> Option<f64> {
+    asset
+        .metadata
+        .get("nav")
+        .and_then(Value::as_f64)
+        .filter(|nav| *nav > 0.0)
+        .or_else(|| plan.installments.last().map(|i| i.nav))
+}
+
+/// 生成定投报告，收益率以 `now` 时的市值计算
+pub fn sip_report(plan: &SipPlan, asset: &Asset, now: DateTime<Utc>) -> SipReport {
+    let total_invested: f64 = plan.installments.iter().map(|i| i.amount).sum();
+    let total_fees: f64 = plan.installments.iter().map(|i| i.fee).sum();
+    let total_units: f64 = plan.installments.iter().map(|i| i.units).sum();
+    let nav = current_nav(asset, plan);
+    let market_value = nav.map_or(0.0, |nav| nav * total_units);
+
+    let mut flows: Vec<(NaiveDate, f64)> = plan
+        .installments
+        .iter()
+        .map(|i| (i.date.date_naive(), -i.amount))
+        .collect();
+    flows.push((now.date_naive(), market_value));
+
+    SipReport {
+        plan_id: plan.id,
+        asset_id: plan.asset_id,
+        name: asset.name.clone(),
+        installments: plan.installments.len(),
+        total_invested,
+        total_fees,
+        total_units,
+        average_cost: (total_units > 0.0).then(|| total_invested / total_units),
+        current_nav: nav,
+        market_value,
+        profit: market_value - total_invested,
+        return_rate: (total_invested > 0.0).then(|| market_value / total_invested - 1.0),
+        irr: xirr(&flows),
+        nav_history: plan
+            .installments
+            .iter()
+            .map(|i| NavPoint {
+                date: i.date.date_naive(),
+                nav: i.nav,
+            })
+            .collect(),
+    }
+}
+
+/// 不定期现金流的年化内部收益率（投入为负、收回为正），无解时为 None
+pub fn xirr(flows: &[(NaiveDate, f64)]) -> Option<f64> {
+    let start = flows.iter().map(|(date, _)| *date).min()?;
+    let years: Vec<(f64, f64)> = flows
+        .iter()
+        .map(|(date, amount)| ((*date - start).num_days() as f64 / 365.0, *amount))
+        .collect();
+    let npv = |rate: f64| -> f64 {
+        years
+            .iter()
+            .map(|(t, amount)| amount / (1.0 + rate).powf(*t))
+            .sum()
+    };
+
+    // 先投入后收回时净现值随收益率单调递减，二分查找零点
+    let (mut low, mut high) = (-0.9999, 1000.0);
+    if npv(low) < 0.0 || npv(high) > 0.0 {
+        return None;
+    }
+    for _ in 0..200 {
+        let mid = (low + high) / 2.0;
+        if npv(mid) > 0.0 {
+            low = mid;
+        } else {
+            high = mid;
+        }
+        if high - low < 1e-10 {
+            break;
+        }
+    }
+    Some((low + high) / 2.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asset::AssetType;
+    use chrono::TimeZone;
+    use serde_json::json;
+
+    fn day(y: i32, m: u32, d: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(y, m, d, 0, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn test_xirr() {
+        let flows = [
+            (day(2023, 1, 1).date_naive(), -1000.0),
+            (day(2024, 1, 1).date_naive(), 1100.0),
+        ];
+        assert!((xirr(&flows).unwrap() - 0.1).abs() < 1e-6);
+        assert!(xirr(&flows[..1]).is_none());
+        assert!(xirr(&[]).is_none());
+    }
+
+    #[test]
+    fn test_sip_report() {
+        let mut fund = Asset::new("沪深300指数基金", AssetType::Fund, 0.0);
+        let mut plan = SipPlan::new(fund.id, 1000.0, Recurrence::Monthly, day(2024, 1, 10));
+        assert!(plan.validate().is_ok());
+        assert!(plan.is_due(day(2024, 1, 10)));
+
+        for (date, nav) in [(day(2024, 1, 10), 1.0), (day(2024, 2, 10), 0.8)] {
+            plan.record(SipInstallment::new(date, 1000.0, 0.0, nav).unwrap());
+        }
+        assert_eq!(plan.next_run, day(2024, 3, 10));
+        assert!(!plan.is_due(day(2024, 3, 1)));
+
+        // 低位多买份额，平均成本低于两期净值的平均值
+        fund.metadata = json!({ "nav": 1.0 });
+        let report = sip_report(&plan, &fund, day(2024, 3, 10));
+        assert_eq!(report.total_units, 2250.0);
+        assert!((report.average_cost.unwrap() - 2000.0 / 2250.0).abs() < 1e-9);
+        assert_eq!(report.profit, 250.0);
+        assert!(report.irr.unwrap() > report.return_rate.unwrap());
+        assert_eq!(report.nav_history.len(), 2);
+
+        assert!(SipInstallment::new(day(2024, 1, 1), 100.0, 100.0, 1.0).is_err());
+        assert!(SipInstallment::new(day(2024, 1, 1), 100.0, 1.0, 0.0).is_err());
+        plan.amount = 0.0;
+        assert!(plan.validate().is_err());
+    }
+}
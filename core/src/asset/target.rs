@@ -0,0 +1,98 @@
+//! 成本与目标价跟踪
+
+use super::Asset;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// 单个资产的浮动盈亏与目标进度
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AssetPerformance {
+    pub asset_id: Uuid,
+    pub name: String,
+    /// 当前价值
+    pub value: f64,
+    /// 买入成本
+    pub cost_basis: Option<f64>,
+    /// 浮动盈亏（当前价值 - 成本）
+    pub unrealized_gain: Option<f64>,
+    /// 浮动盈亏比例（0.1 表示 10%）
+    pub unrealized_gain_pct: Option<f64>,
+    /// 目标价
+    pub target_price: Option<f64>,
+    /// 距目标还差多少（目标价 - 当前价值）
+    pub distance_to_target: Option<f64>,
+    /// 距目标的比例（相对当前价值）
+    pub distance_pct: Option<f64>,
+    /// 是否已达到目标
+    pub target_reached: bool,
+}
+
+impl AssetPerformance {
+    /// 由资产计算
+    pub fn new(asset: &Asset) -> Self {
+        let unrealized_gain = asset.cost_basis.map(|cost| asset.value - cost);
+        let distance_to_target = asset.target_price.map(|target| target - asset.value);
+        Self {
+            asset_id: asset.id,
+            name: asset.name.clone(),
+            value: asset.value,
+            cost_basis: asset.cost_basis,
+            unrealized_gain,
+            unrealized_gain_pct: asset
+                .cost_basis
+                .filter(|cost| *cost != 0.0)
+                .zip(unrealized_gain)
+                .map(|(cost, gain)| gain / cost.abs()),
+            target_price: asset.target_price,
+            distance_to_target,
+            distance_pct: distance_to_target
+                .filter(|_| asset.value != 0.0)
+                .map(|d| d / asset.value.abs()),
+            target_reached: asset.target_reached(),
+        }
+    }
+}
+
+impl Asset {
+    /// 是否已达到目标价
+    ///
+    /// 目标价不低于成本（或未设置成本）时视为止盈目标，价值涨到目标即达到；
+    /// 目标价低于成本时视为止损线，价值跌到目标即达到。
+    pub fn target_reached(&self) -> bool {
+        let Some(target) = self.target_price else {
+            return false;
+        };
+        match self.cost_basis {
+            Some(cost) if target < cost => self.value <= target,
+            _ => self.value >= target,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asset::AssetType;
+
+    #[test]
+    fn test_performance() {
+        let mut stock = Asset::new("茅台", AssetType::Stock, 12000.0);
+        stock.cost_basis = Some(10000.0);
+        stock.target_price = Some(15000.0);
+
+        let perf = AssetPerformance::new(&stock);
+        assert_eq!(perf.unrealized_gain, Some(2000.0));
+        assert_eq!(perf.unrealized_gain_pct, Some(0.2));
+        assert_eq!(perf.distance_to_target, Some(3000.0));
+        assert!(!perf.target_reached);
+
+        stock.value = 15500.0;
+        assert!(stock.target_reached());
+
+        // 止损线
+        stock.target_price = Some(8000.0);
+        assert!(!stock.target_reached());
+        stock.value = 7900.0;
+        assert!(stock.target_reached());
+    }
+}
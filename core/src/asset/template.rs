@@ -0,0 +1,73 @@
+//! 交易模板模型
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// 交易模板（如"每月从储蓄卡还房贷 ¥5,000"）
+///
+/// - 同时设置转出与转入资产：记录为一笔转账
+/// - 仅设置转出资产：记录为支出
+/// - 仅设置转入资产：记录为收益
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransactionTemplate {
+    /// 唯一标识符
+    pub id: Uuid,
+    /// 模板名称
+    pub name: String,
+    /// 转出资产ID
+    pub from_asset_id: Option<Uuid>,
+    /// 转入资产ID
+    pub to_asset_id: Option<Uuid>,
+    /// 默认金额（为空表示每次应用时输入）
+    pub amount: Option<f64>,
+    /// 输入金额时的提示文字
+    pub amount_prompt: Option<String>,
+    /// 交易备注
+    pub note: Option<String>,
+    /// 创建时间
+    pub created_at: DateTime<Utc>,
+}
+
+impl TransactionTemplate {
+    /// 创建新模板
+    pub fn new(
+        name: impl Into<String>,
+        from_asset_id: Option<Uuid>,
+        to_asset_id: Option<Uuid>,
+    ) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            name: name.into(),
+            from_asset_id,
+            to_asset_id,
+            amount: None,
+            amount_prompt: None,
+            note: None,
+            created_at: Utc::now(),
+        }
+    }
+
+    /// 设置默认金额
+    pub fn with_amount(mut self, amount: f64) -> Self {
+        self.amount = Some(amount);
+        self
+    }
+
+    /// 设置金额输入提示
+    pub fn with_amount_prompt(mut self, prompt: impl Into<String>) -> Self {
+        self.amount_prompt = Some(prompt.into());
+        self
+    }
+
+    /// 设置备注
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.note = Some(note.into());
+        self
+    }
+
+    /// 应用模板时是否需要输入金额
+    pub fn requires_amount(&self) -> bool {
+        self.amount.is_none()
+    }
+}
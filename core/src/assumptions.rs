@@ -0,0 +1,103 @@
+//! 利率环境假设：存款利率、房贷利率与预期通胀率
+//!
+//! 统一保存在设置中，预计余额等推算都从这里读取利率与通胀率，修改一处即全部生效。
+//! 利率均为年利率（如 0.015 表示 1.5%），按日复利折算；未设置时不计息、不折算通胀。
+
+use crate::asset::{Asset, AssetType};
+use crate::settings::Settings;
+use serde::{Deserialize, Serialize};
+
+/// 设置项键名
+pub const ASSUMPTIONS_SETTING_KEY: &str = "assumptions";
+
+/// 利率环境假设设置项
+pub const ASSUMPTIONS_SETTINGS: Settings<Assumptions> =
+    Settings::new(ASSUMPTIONS_SETTING_KEY, Assumptions::default);
+
+/// 利率环境假设
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+pub struct Assumptions {
+    /// 存款年利率（现金与银行存款）
+    #[serde(default)]
+    pub deposit_rate: f64,
+    /// 房贷年利率（价值为负的负债）
+    #[serde(default)]
+    pub mortgage_rate: f64,
+    /// 预期年通胀率
+    #[serde(default)]
+    pub inflation: f64,
+}
+
+impl Assumptions {
+    /// 检查假设是否有效
+    pub fn validate(&self) -> Result<(), String> {
+        for (name, rate) in [
+            ("deposit rate", self.deposit_rate),
+            ("mortgage rate", self.mortgage_rate),
+            ("inflation", self.inflation),
+        ] {
+            if !(rate.is_finite() && rate > -1.0 && rate < 1.0) {
+                return Err(format!("Invalid {}: {}", name, rate));
+            }
+        }
+        Ok(())
+    }
+
+    /// 资产适用的年利率：负债按房贷利率，现金与银行存款按存款利率，其他资产不计息
+    pub fn annual_rate(&self, asset: &Asset) -> f64 {
+        if asset.value < 0.0 {
+            self.mortgage_rate
+        } else if matches!(asset.asset_type, AssetType::Cash | AssetType::BankDeposit) {
+            self.deposit_rate
+        } else {
+            0.0
+        }
+    }
+
+    /// 资产当前价值在 `days` 天内产生的利息（负债为负）
+    pub fn accrued_interest(&self, asset: &Asset, days: i64) -> f64 {
+        asset.value * (growth(self.annual_rate(asset), days) - 1.0)
+    }
+
+    /// 把 `days` 天后的金额按预期通胀折算为今天的购买力
+    pub fn real_value(&self, value: f64, days: i64) -> f64 {
+        value / growth(self.inflation, days)
+    }
+}
+
+/// 年利率按日复利 `days` 天的增长倍数
+fn growth(rate: f64, days: i64) -> f64 {
+    (1.0 + rate).powf(days.max(0) as f64 / 365.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_assumptions() {
+        let assumptions = Assumptions {
+            deposit_rate: 0.02,
+            mortgage_rate: 0.04,
+            inflation: 0.03,
+        };
+        let deposit = Asset::new("储蓄卡", AssetType::BankDeposit, 10000.0);
+        let loan = Asset::new("房贷", AssetType::Other("loan".into()), -100000.0);
+        let stock = Asset::new("股票", AssetType::Stock, 5000.0);
+
+        assert!((assumptions.accrued_interest(&deposit, 365) - 200.0).abs() < 1e-9);
+        assert!((assumptions.accrued_interest(&loan, 365) + 4000.0).abs() < 1e-9);
+        assert_eq!(assumptions.accrued_interest(&stock, 365), 0.0);
+        assert_eq!(assumptions.accrued_interest(&deposit, -30), 0.0);
+        assert!((assumptions.real_value(10300.0, 365) - 10000.0).abs() < 1e-9);
+
+        // 默认不计息
+        assert_eq!(Assumptions::default().accrued_interest(&deposit, 365), 0.0);
+        assert!(Assumptions {
+            inflation: f64::NAN,
+            ..assumptions
+        }
+        .validate()
+        .is_err());
+    }
+}
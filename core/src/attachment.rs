@@ -0,0 +1,167 @@
+//! 附件：资产的收据、合同与照片
+//!
+//! 文件内容按 SHA-256 指纹存放在数据文件旁的 `attachments` 目录中（`<指纹前两位>/<指纹>`），
+//! 相同内容只存一份；附件记录（所属资产、文件名、大小与指纹）保存在数据文件中。不再被引用的文件
+//! 在删除附件时一并删除。指纹必须是固定长度的小写十六进制，读取数据与拼接路径时都会检查，
+//! 避免被修改的数据指向附件目录之外。
+
+use crate::storage::StorageError;
+use chrono::{DateTime, Utc};
+use serde::{de, Deserialize, Deserializer, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+/// 数据文件旁的附件目录名
+pub const ATTACHMENTS_DIR: &str = "attachments";
+
+/// 单个附件的大小上限（字节）
+pub const MAX_ATTACHMENT_BYTES: u64 = 50 * 1024 * 1024;
+
+/// SHA-256 指纹的长度（十六进制字符数）
+pub const HASH_LEN: usize = 64;
+
+/// 附件记录
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Attachment {
+    pub id: Uuid,
+    pub asset_id: Uuid,
+    /// 原文件名
+    pub file_name: String,
+    /// 文件大小（字节）
+    pub size: u64,
+    /// 内容指纹，也是附件目录中的文件名
+    #[serde(deserialize_with = "deserialize_hash")]
+    pub hash: String,
+    #[serde(default)]
+    pub note: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Attachment {
+    /// 由文件内容创建附件记录
+    pub fn new(asset_id: Uuid, file_name: impl Into<String>, content: &[u8]) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            asset_id,
+            file_name: file_name.into(),
+            size: content.len() as u64,
+            hash: content_hash(content),
+            note: None,
+            created_at: Utc::now(),
+        }
+    }
+
+    pub fn with_note(mut self, note: Option<String>) -> Self {
+        self.note = note.filter(|n| !n.trim().is_empty());
+        self
+    }
+
+    /// 附件文件在附件目录中的位置
+    pub fn path(&self, dir: &Path) -> Result<PathBuf, StorageError> {
+        content_path(dir, &self.hash)
+    }
+}
+
+/// 文件内容的 SHA-256 指纹（小写十六进制）
+pub fn content_hash(content: &[u8]) -> String {
+    Sha256::digest(content)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// 指纹是否为 SHA-256 的小写十六进制
+pub fn is_valid_hash(hash: &str) -> bool {
+    hash.len() == HASH_LEN
+        && hash.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+}
+
+fn deserialize_hash<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    let hash = String::deserialize(deserializer)?;
+    if !is_valid_hash(&hash) {
+        return Err(de::Error::custom(format!(
+            "invalid attachment hash: {}",
+            hash
+        )));
+    }
+    Ok(hash)
+}
+
+/// 数据文件旁的附件目录
+pub fn attachments_dir(db_path: &Path) -> PathBuf {
+    db_path
+        .parent()
+        .unwrap_or_else(|| Path::new("."))
+        .join(ATTACHMENTS_DIR)
+}
+
+/// 指纹对应的文件位置，拒绝不合法的指纹
+pub fn content_path(dir: &Path, hash: &str) -> Result<PathBuf, StorageError> {
+    if !is_valid_hash(hash) {
+        return Err(StorageError::InvalidData(format!(
+            "Invalid attachment hash: {}",
+            hash
+        )));
+    }
+    Ok(dir.join(&hash[..2]).join(hash))
+}
+
+/// 读取要添加的文件，超过大小上限时拒绝
+pub fn read_source(path: &Path) -> Result<Vec<u8>, StorageError> {
+    let size = fs::metadata(path)?.len();
+    if size > MAX_ATTACHMENT_BYTES {
+        return Err(StorageError::InvalidData(format!(
+            "Attachment is larger than {} MB",
+            MAX_ATTACHMENT_BYTES / 1024 / 1024
+        )));
+    }
+    Ok(fs::read(path)?)
+}
+
+/// 写入文件内容（先写临时文件再替换）；已有相同内容时不重复写入，指纹相同而内容不同时拒绝
+pub fn store_content(dir: &Path, hash: &str, content: &[u8]) -> Result<PathBuf, StorageError> {
+    let path = content_path(dir, hash)?;
+    if path.exists() {
+        if fs::read(&path)? != content {
+            return Err(StorageError::InvalidData(format!(
+                "Attachment {} conflicts with an existing file",
+                hash
+            )));
+        }
+        return Ok(path);
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, content)?;
+    fs::rename(&tmp, &path)?;
+    Ok(path)
+}
+
+/// 删除附件目录中不在 `used` 中的文件，返回删除的数量
+pub fn remove_unused(dir: &Path, used: &[&str]) -> Result<usize, StorageError> {
+    let read_dir = match fs::read_dir(dir) {
+        Ok(read_dir) => read_dir,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(e.into()),
+    };
+    let mut removed = 0;
+    for entry in read_dir {
+        let shard = entry?.path();
+        if !shard.is_dir() {
+            continue;
+        }
+        for file in fs::read_dir(&shard)? {
+            let path = file?.path();
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            if !used.contains(&name.as_ref()) {
+                fs::remove_file(&path)?;
+                removed += 1;
+            }
+        }
+    }
+    Ok(removed)
+}
@@ -0,0 +1,46 @@
+//! 二进制序列化：大列表通过 IPC 传输时使用 MessagePack 代替 JSON
+//!
+//! 结构体按字段名编码为映射，UUID、时间等按与 JSON 相同的字符串形式编码，
+//! 前端解码后得到的对象与 JSON 接口一致。
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// 支持的二进制格式名称（供前端检测）
+pub const BINARY_FORMAT: &str = "msgpack";
+
+/// 编码为 MessagePack
+pub fn to_msgpack<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, rmp_serde::encode::Error> {
+    let mut buf = Vec::new();
+    let mut serializer = rmp_serde::Serializer::new(&mut buf)
+        .with_struct_map()
+        .with_human_readable();
+    value.serialize(&mut serializer)?;
+    Ok(buf)
+}
+
+/// 从 MessagePack 解码
+pub fn from_msgpack<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, rmp_serde::decode::Error> {
+    let mut deserializer = rmp_serde::Deserializer::new(bytes).with_human_readable();
+    T::deserialize(&mut deserializer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asset::{Asset, AssetType};
+
+    #[test]
+    fn test_msgpack_round_trip() {
+        let assets = vec![Asset::new("现金", AssetType::Cash, 12.5)];
+        let bytes = to_msgpack(&assets).unwrap();
+        let decoded: Vec<Asset> = from_msgpack(&bytes).unwrap();
+        assert_eq!(decoded[0].id, assets[0].id);
+        assert_eq!(decoded[0].value, 12.5);
+
+        // UUID 按字符串编码，与 JSON 一致
+        let value: serde_json::Value = from_msgpack(&bytes).unwrap();
+        assert_eq!(value[0]["id"], assets[0].id.to_string());
+        assert!(bytes.len() < serde_json::to_vec(&assets).unwrap().len());
+    }
+}
@@ -0,0 +1,321 @@
+//! 表格批量编辑：导出带稳定 ID 的 CSV，在 Excel 中修改后导回
+//!
+//! 导回时与当前数据逐字段比对，可先预览再应用。`id` 为空的行视为新资产，
+//! 表格中缺失的资产不会被删除。导出的数值保留原始精度，不做显示舍入，以免导回时丢失精度。
+
+use crate::asset::{Asset, AssetType, Currency};
+use crate::csv;
+use crate::storage::{Database, SortBy, SortOrder, StorageError};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// 导出的列
+pub const EDIT_COLUMNS: [&str; 7] = [
+    "id",
+    "name",
+    "asset_type",
+    "value",
+    "currency",
+    "description",
+    "tags",
+];
+
+/// 标签在单元格内的分隔符
+const TAG_SEPARATOR: char = '|';
+
+/// 导出筛选条件
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EditFilter {
+    /// 资产类型代码
+    pub asset_type: Option<String>,
+    /// 包含的标签
+    pub tag: Option<String>,
+}
+
+impl EditFilter {
+    fn matches(&self, asset: &Asset) -> bool {
+        let type_ok = self
+            .asset_type
+            .as_ref()
+            .map(|t| AssetType::parse(t) == asset.asset_type)
+            .unwrap_or(true);
+        let tag_ok = self
+            .tag
+            .as_ref()
+            .map(|t| asset.tags.iter().any(|tag| tag == t))
+            .unwrap_or(true);
+        type_ok && tag_ok
+    }
+}
+
+/// 编辑动作
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum EditAction {
+    /// 新建资产
+    Create,
+    /// 修改已有资产
+    Update,
+}
+
+/// 字段变更
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FieldChange {
+    pub field: String,
+    pub old: String,
+    pub new: String,
+}
+
+/// 单个资产的编辑
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AssetEdit {
+    /// 表格中的行号（从 1 开始，含表头）
+    pub row: usize,
+    /// 资产ID
+    pub id: Uuid,
+    /// 资产名称
+    pub name: String,
+    /// 编辑动作
+    pub action: EditAction,
+    /// 字段变更
+    pub changes: Vec<FieldChange>,
+}
+
+/// 行错误
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RowError {
+    pub row: usize,
+    pub message: String,
+}
+
+/// 编辑预览
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EditPreview {
+    /// 有变更的资产
+    pub edits: Vec<AssetEdit>,
+    /// 无变更的行数
+    pub unchanged: usize,
+    /// 无法处理的行
+    pub errors: Vec<RowError>,
+}
+
+/// 导出用于编辑的 CSV
+pub fn export_for_edit(db: &Database, filter: &EditFilter) -> Result<String, StorageError> {
+    let mut out = String::new();
+    out.push(csv::UTF8_BOM);
+    out.push_str(&csv::write_row(&EDIT_COLUMNS, ','));
+    out.push_str("\r\n");
+
+    for asset in db
+        .list_assets(SortBy::default(), SortOrder::default())?
+        .iter()
+        .filter(|a| filter.matches(a))
+    {
+        out.push_str(&csv::write_row(&asset_to_row(asset), ','));
+        out.push_str("\r\n");
+    }
+
+    Ok(out)
+}
+
+/// 预览导回的修改
+pub fn preview_edits(db: &Database, content: &str) -> Result<EditPreview, StorageError> {
+    plan_edits(db, content).map(|(preview, _)| preview)
+}
+
+/// 应用导回的修改，返回实际应用的变更
+pub fn apply_edits(db: &mut Database, content: &str) -> Result<EditPreview, StorageError> {
+    let (preview, planned) = plan_edits(db, content)?;
+
+    for (action, asset) in planned {
+        match action {
+            EditAction::Create => db.create_asset(&asset)?,
+            EditAction::Update => db.update_asset(&asset)?,
+        }
+    }
+
+    Ok(preview)
+}
+
+fn asset_to_row(asset: &Asset) -> Vec<String> {
+    vec![
+        asset.id.to_string(),
+        asset.name.clone(),
+        asset.asset_type.code().to_string(),
+        asset.value.to_string(),
+        asset.currency.code().to_string(),
+        asset.description.clone().unwrap_or_default(),
+        asset.tags.join(&TAG_SEPARATOR.to_string()),
+    ]
+}
+
+fn plan_edits(
+    db: &Database,
+    content: &str,
+) -> Result<(EditPreview, Vec<(EditAction, Asset)>), StorageError> {
+    let mut rows = csv::parse(content, ',').into_iter();
+    let header = rows
+        .next()
+        .ok_or_else(|| StorageError::InvalidData("Empty file".to_string()))?;
+
+    // 按表头定位列，允许用户调整列顺序
+    let mut columns = Vec::with_capacity(EDIT_COLUMNS.len());
+    for name in EDIT_COLUMNS {
+        let index = header
+            .iter()
+            .position(|h| h.trim() == name)
+            .ok_or_else(|| StorageError::InvalidData(format!("Missing column: {}", name)))?;
+        columns.push(index);
+    }
+
+    let mut preview = EditPreview::default();
+    let mut planned = Vec::new();
+
+    for (i, row) in rows.enumerate() {
+        let row_number = i + 2;
+        let cell = |col: usize| {
+            row.get(columns[col])
+                .map(|s| s.trim().to_string())
+                .unwrap_or_default()
+        };
+
+        match plan_row(db, &cell) {
+            Ok(Some((action, asset, changes))) => {
+                preview.edits.push(AssetEdit {
+                    row: row_number,
+                    id: asset.id,
+                    name: asset.name.clone(),
+                    action,
+                    changes,
+                });
+                planned.push((action, asset));
+            }
+            Ok(None) => preview.unchanged += 1,
+            Err(message) => preview.errors.push(RowError {
+                row: row_number,
+                message,
+            }),
+        }
+    }
+
+    Ok((preview, planned))
+}
+
+type PlannedRow = (EditAction, Asset, Vec<FieldChange>);
+
+fn plan_row(db: &Database, cell: &dyn Fn(usize) -> String) -> Result<Option<PlannedRow>, String> {
+    let name = cell(1);
+    if name.is_empty() {
+        return Err("Name is required".to_string());
+    }
+    let asset_type = AssetType::parse(&cell(2));
+    let value: f64 = cell(3)
+        .parse()
+        .map_err(|_| format!("Invalid value: {}", cell(3)))?;
+    let currency = Currency::from_code(&cell(4));
+    let description = Some(cell(5)).filter(|d| !d.is_empty());
+    let tags: Vec<String> = cell(6)
+        .split(TAG_SEPARATOR)
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty())
+        .collect();
+
+    let id = cell(0);
+    if id.is_empty() {
+        let mut asset = Asset::new(name, asset_type, value)
+            .with_currency(currency)
+            .with_tags(tags);
+        asset.description = description;
+        let changes = EDIT_COLUMNS[1..]
+            .iter()
+            .zip(asset_to_row(&asset).into_iter().skip(1))
+            .filter(|(_, new)| !new.is_empty())
+            .map(|(field, new)| FieldChange {
+                field: field.to_string(),
+                old: String::new(),
+                new,
+            })
+            .collect();
+        return Ok(Some((EditAction::Create, asset, changes)));
+    }
+
+    let id = Uuid::parse_str(&id).map_err(|_| format!("Invalid id: {}", id))?;
+    let current = db
+        .get_asset(id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Asset not found: {}", id))?;
+
+    let mut updated = current.clone();
+    updated.name = name;
+    updated.asset_type = asset_type;
+    updated.value = value;
+    updated.currency = currency;
+    updated.description = description;
+    updated.tags = tags;
+
+    let changes: Vec<FieldChange> = EDIT_COLUMNS
+        .iter()
+        .zip(
+            asset_to_row(&current)
+                .into_iter()
+                .zip(asset_to_row(&updated)),
+        )
+        .filter(|(_, (old, new))| old != new)
+        .map(|(field, (old, new))| FieldChange {
+            field: field.to_string(),
+            old,
+            new,
+        })
+        .collect();
+
+    if changes.is_empty() {
+        return Ok(None);
+    }
+
+    updated.updated_at = Utc::now();
+    Ok(Some((EditAction::Update, updated, changes)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_edit_round_trip() {
+        let mut db = Database::open_in_memory().unwrap();
+        let stock = Asset::new("股票A", AssetType::Stock, 5000.0)
+            .with_tags(vec!["投资".to_string(), "A股".to_string()]);
+        let cash = Asset::new("现金", AssetType::Cash, 3000.0);
+        db.create_asset(&stock).unwrap();
+        db.create_asset(&cash).unwrap();
+
+        let exported = export_for_edit(&db, &EditFilter::default()).unwrap();
+        assert_eq!(csv::parse(&exported, ',').len(), 3);
+
+        // 修改股票价值，新增一行，并加入一行错误数据
+        let row = format!("{},股票A,stock,5000,", stock.id);
+        assert!(exported.contains(&row));
+        let edited = exported.replace(&row, &format!("{},股票A,stock,6500,", stock.id))
+            + ",新基金,fund,1000,CNY,,\r\n"
+            + &format!("{},坏数据,cash,abc,CNY,,\r\n", cash.id);
+
+        let preview = preview_edits(&db, &edited).unwrap();
+        assert_eq!(preview.edits.len(), 2);
+        assert_eq!(preview.unchanged, 1);
+        assert_eq!(preview.errors.len(), 1);
+        // 预览不修改数据
+        assert_eq!(db.get_asset(stock.id).unwrap().unwrap().value, 5000.0);
+
+        apply_edits(&mut db, &edited).unwrap();
+        let reloaded = db.get_asset(stock.id).unwrap().unwrap();
+        assert_eq!(reloaded.value, 6500.0);
+        assert_eq!(reloaded.tags, stock.tags);
+        assert_eq!(
+            db.list_assets(SortBy::default(), SortOrder::default())
+                .unwrap()
+                .len(),
+            3
+        );
+    }
+}
@@ -0,0 +1,112 @@
+//! 财务日记：每日或每周的打卡记录
+//!
+//! 记录当时的心情与想法（如"大跌没有恐慌卖出"），可附带当时的资产快照，与数字一起显示在时间线上。
+
+use crate::asset::AssetSummary;
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// 打卡周期
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckInPeriod {
+    #[default]
+    Daily,
+    Weekly,
+}
+
+/// 心情
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Mood {
+    Terrible,
+    Bad,
+    Neutral,
+    Good,
+    Great,
+}
+
+/// 打卡时的资产快照
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CheckInSnapshot {
+    /// 快照时间
+    pub taken_at: DateTime<Utc>,
+    /// 总资产价值
+    pub total_value: f64,
+    /// 资产数量
+    pub asset_count: usize,
+}
+
+impl CheckInSnapshot {
+    /// 由资产摘要生成快照
+    pub fn from_summary(summary: &AssetSummary) -> Self {
+        Self {
+            taken_at: Utc::now(),
+            total_value: summary.total_value,
+            asset_count: summary.asset_count,
+        }
+    }
+}
+
+/// 日记条目
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CheckIn {
+    /// 唯一标识符
+    pub id: Uuid,
+    /// 日期（每周打卡为该周内的任意一天）
+    pub date: NaiveDate,
+    /// 周期
+    #[serde(default)]
+    pub period: CheckInPeriod,
+    /// 心情
+    #[serde(default)]
+    pub mood: Option<Mood>,
+    /// 记录
+    #[serde(default)]
+    pub notes: String,
+    /// 资产快照
+    #[serde(default)]
+    pub snapshot: Option<CheckInSnapshot>,
+    /// 创建时间
+    pub created_at: DateTime<Utc>,
+    /// 更新时间
+    pub updated_at: DateTime<Utc>,
+}
+
+impl CheckIn {
+    /// 创建日记条目
+    pub fn new(date: NaiveDate, notes: impl Into<String>) -> Self {
+        let now = Utc::now();
+        Self {
+            id: Uuid::new_v4(),
+            date,
+            period: CheckInPeriod::default(),
+            mood: None,
+            notes: notes.into(),
+            snapshot: None,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    /// 设置心情
+    pub fn with_mood(mut self, mood: Mood) -> Self {
+        self.mood = Some(mood);
+        self
+    }
+
+    /// 设置周期
+    pub fn with_period(mut self, period: CheckInPeriod) -> Self {
+        self.period = period;
+        self
+    }
+
+    /// 检查条目是否有效
+    pub fn validate(&self) -> Result<(), String> {
+        if self.mood.is_none() && self.notes.trim().is_empty() {
+            return Err("Check-in needs a mood or notes".to_string());
+        }
+        Ok(())
+    }
+}
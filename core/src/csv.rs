@@ -0,0 +1,152 @@
+//! CSV 读写工具
+
+use std::collections::HashMap;
+
+/// UTF-8 BOM，便于 Excel 正确识别中文
+pub const UTF8_BOM: char = '\u{feff}';
+
+/// 自动识别时考虑的分隔符
+pub const DELIMITERS: [char; 4] = [',', ';', '\t', '|'];
+
+/// 自动识别时采样的行数
+const SNIFF_LINES: usize = 20;
+
+/// 将一行字段编码为 CSV 文本（不含换行符）
+pub fn write_row<S: AsRef<str>>(fields: &[S], delimiter: char) -> String {
+    fields
+        .iter()
+        .map(|f| escape_field(f.as_ref(), delimiter))
+        .collect::<Vec<_>>()
+        .join(&delimiter.to_string())
+}
+
+fn escape_field(field: &str, delimiter: char) -> String {
+    let needs_quotes = field.contains(delimiter)
+        || field.contains('"')
+        || field.contains('\n')
+        || field.contains('\r');
+    if needs_quotes {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// 解析 CSV 文本，跳过空行
+pub fn parse(content: &str, delimiter: char) -> Vec<Vec<String>> {
+    let content = content.strip_prefix(UTF8_BOM).unwrap_or(content);
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = content.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            if c == '"' {
+                if chars.peek() == Some(&'"') {
+                    field.push('"');
+                    chars.next();
+                } else {
+                    in_quotes = false;
+                }
+            } else {
+                field.push(c);
+            }
+            continue;
+        }
+
+        match c {
+            '"' => in_quotes = true,
+            '\r' => {}
+            '\n' => {
+                row.push(std::mem::take(&mut field));
+                push_row(&mut rows, std::mem::take(&mut row));
+            }
+            c if c == delimiter => row.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        push_row(&mut rows, row);
+    }
+
+    rows
+}
+
+/// 根据前几行推断分隔符，无法判断时为逗号
+///
+/// 取各候选分隔符在每行（引号外）出现次数的众数，出现该次数的行最多者胜出，
+/// 因此银行导出开头的说明行不会影响结果。
+pub fn detect_delimiter(content: &str) -> char {
+    let content = content.strip_prefix(UTF8_BOM).unwrap_or(content);
+    let lines: Vec<&str> = content
+        .lines()
+        .filter(|l| !l.trim().is_empty())
+        .take(SNIFF_LINES)
+        .collect();
+
+    let mut best = (',', 0, 0);
+    for delimiter in DELIMITERS {
+        let mut frequency: HashMap<usize, usize> = HashMap::new();
+        for line in &lines {
+            let count = count_unquoted(line, delimiter);
+            if count > 0 {
+                *frequency.entry(count).or_insert(0) += 1;
+            }
+        }
+        // (行数, 每行字段分隔数)，先比较行数
+        if let Some((count, rows)) = frequency.into_iter().max_by_key(|&(c, r)| (r, c)) {
+            if (rows, count) > (best.1, best.2) {
+                best = (delimiter, rows, count);
+            }
+        }
+    }
+    best.0
+}
+
+fn count_unquoted(line: &str, delimiter: char) -> usize {
+    let mut in_quotes = false;
+    let mut count = 0;
+    for c in line.chars() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            c if c == delimiter && !in_quotes => count += 1,
+            _ => {}
+        }
+    }
+    count
+}
+
+fn push_row(rows: &mut Vec<Vec<String>>, row: Vec<String>) {
+    if row.iter().any(|f| !f.trim().is_empty()) {
+        rows.push(row);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let fields = ["id", "含,逗号", "含\"引号\"", "多\n行"];
+        let line = write_row(&fields, ',');
+        let parsed = parse(&format!("{}{}\r\n\r\n", UTF8_BOM, line), ',');
+        assert_eq!(parsed.len(), 1);
+        assert_eq!(parsed[0], fields);
+    }
+
+    #[test]
+    fn test_detect_delimiter() {
+        assert_eq!(
+            detect_delimiter("账户明细\n日期;摘要;金额\n2024-01-05;\"a;b\";1,5\n"),
+            ';'
+        );
+        assert_eq!(detect_delimiter("a\tb\tc\n1\t2\t3\n"), '\t');
+        assert_eq!(detect_delimiter("date,amount\n2024-01-05,1\n"), ',');
+        assert_eq!(detect_delimiter("no delimiter"), ',');
+    }
+}
@@ -0,0 +1,194 @@
+//! 深度链接：`assetmgr://` 链接打开应用并跳转到对应位置
+//!
+//! 支持 `assetmgr://asset/<资产ID>`（打开资产详情）与 `assetmgr://add?amount=...`（打开添加表单并预填，
+//! 可选参数 `asset`、`name`、`note`）。链接来自其他程序，解析时校验每个参数，有不合法的部分时整个拒绝。
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// 链接协议名
+pub const DEEP_LINK_SCHEME: &str = "assetmgr";
+
+/// 链接的长度上限
+const MAX_LINK_LEN: usize = 2048;
+
+/// 解析后的链接
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "route", rename_all = "snake_case")]
+pub enum DeepLink {
+    /// 打开资产详情
+    Asset { asset_id: Uuid },
+    /// 打开添加表单：指定资产时为该资产记一笔交易，否则新建资产
+    Add {
+        asset_id: Option<Uuid>,
+        amount: Option<f64>,
+        name: Option<String>,
+        note: Option<String>,
+    },
+}
+
+impl DeepLink {
+    /// 解析并校验链接
+    pub fn parse(link: &str) -> Result<Self, String> {
+        if link.len() > MAX_LINK_LEN {
+            return Err("Link is too long".to_string());
+        }
+        if !is_deep_link(link) {
+            return Err(format!("Not a {} link: {}", DEEP_LINK_SCHEME, link));
+        }
+        let rest = &link[DEEP_LINK_SCHEME.len() + 1..];
+        let rest = rest.trim_start_matches('/');
+        let rest = rest.split('#').next().unwrap_or_default();
+        let (path, query) = rest.split_once('?').unwrap_or((rest, ""));
+        let segments: Vec<&str> = path.trim_end_matches('/').split('/').collect();
+        match segments.as_slice() {
+            ["asset", id] => {
+                ensure_no_params(query)?;
+                Ok(DeepLink::Asset {
+                    asset_id: parse_uuid(id)?,
+                })
+            }
+            ["add"] => parse_add(query),
+            _ => Err(format!("Unknown link: {}", link)),
+        }
+    }
+
+    /// 链接指向的资产
+    pub fn asset_id(&self) -> Option<Uuid> {
+        match self {
+            DeepLink::Asset { asset_id } => Some(*asset_id),
+            DeepLink::Add { asset_id, .. } => *asset_id,
+        }
+    }
+}
+
+/// 是否为 `assetmgr:` 链接（协议名不区分大小写）
+pub fn is_deep_link(arg: &str) -> bool {
+    arg.get(..DEEP_LINK_SCHEME.len() + 1)
+        .is_some_and(|prefix| prefix.eq_ignore_ascii_case(&format!("{}:", DEEP_LINK_SCHEME)))
+}
+
+/// 解析添加表单的参数
+fn parse_add(query: &str) -> Result<DeepLink, String> {
+    let (mut asset_id, mut amount, mut name, mut note) = (None, None, None, None);
+    for (key, value) in query_params(query)? {
+        match key.as_str() {
+            "asset" => asset_id = Some(parse_uuid(&value)?),
+            "amount" => {
+                let parsed: f64 = value
+                    .parse()
+                    .map_err(|_| format!("Invalid amount: {}", value))?;
+                if !parsed.is_finite() {
+                    return Err(format!("Invalid amount: {}", value));
+                }
+                amount = Some(parsed);
+            }
+            "name" => name = Some(value.trim().to_string()).filter(|v| !v.is_empty()),
+            "note" => note = Some(value.trim().to_string()).filter(|v| !v.is_empty()),
+            _ => return Err(format!("Unknown parameter: {}", key)),
+        }
+    }
+    Ok(DeepLink::Add {
+        asset_id,
+        amount,
+        name,
+        note,
+    })
+}
+
+fn ensure_no_params(query: &str) -> Result<(), String> {
+    match query_params(query)?.first() {
+        Some((key, _)) => Err(format!("Unknown parameter: {}", key)),
+        None => Ok(()),
+    }
+}
+
+fn parse_uuid(value: &str) -> Result<Uuid, String> {
+    Uuid::parse_str(value).map_err(|_| format!("Invalid asset ID: {}", value))
+}
+
+/// 拆分并解码查询参数
+fn query_params(query: &str) -> Result<Vec<(String, String)>, String> {
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            Ok((percent_decode(key)?, percent_decode(value)?))
+        })
+        .collect()
+}
+
+/// 解码 `%XX` 转义（`+` 视为空格）
+fn percent_decode(s: &str) -> Result<String, String> {
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => decoded.push(b' '),
+            b'%' => {
+                let byte = s
+                    .get(i + 1..i + 3)
+                    .filter(|hex| hex.bytes().all(|b| b.is_ascii_hexdigit()))
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                    .ok_or_else(|| format!("Invalid escape in {}", s))?;
+                decoded.push(byte);
+                i += 2;
+            }
+            b => decoded.push(b),
+        }
+        i += 1;
+    }
+    String::from_utf8(decoded).map_err(|_| format!("Invalid text in {}", s))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_deep_links() {
+        let id = Uuid::new_v4();
+        assert_eq!(
+            DeepLink::parse(&format!("assetmgr://asset/{}", id)).unwrap(),
+            DeepLink::Asset { asset_id: id }
+        );
+        assert_eq!(
+            DeepLink::parse(&format!("ASSETMGR://asset/{}/", id))
+                .unwrap()
+                .asset_id(),
+            Some(id)
+        );
+
+        let link = format!(
+            "assetmgr://add?amount=56.5&asset={}&note=%E5%8D%88%E9%A5%AD+AA",
+            id
+        );
+        assert_eq!(
+            DeepLink::parse(&link).unwrap(),
+            DeepLink::Add {
+                asset_id: Some(id),
+                amount: Some(56.5),
+                name: None,
+                note: Some("午饭 AA".to_string()),
+            }
+        );
+        assert!(DeepLink::parse("assetmgr://add").is_ok());
+
+        // 不合法的链接整个拒绝
+        for link in [
+            "https://example.com/asset/1",
+            "assetmgr://asset/not-a-uuid",
+            "assetmgr://delete/all",
+            "assetmgr://add?amount=abc",
+            "assetmgr://add?amount=inf",
+            "assetmgr://add?note=%E5%8D",
+            "assetmgr://add?note=%zz",
+            "assetmgr://add?script=1",
+        ] {
+            assert!(DeepLink::parse(link).is_err(), "{}", link);
+        }
+        assert!(!is_deep_link("--safe-mode"));
+    }
+}
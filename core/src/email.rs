@@ -0,0 +1,260 @@
+//! 邮件发送（SMTP）与月度报告邮件
+//!
+//! SMTP 服务器等配置保存在设置中，登录密码通过 [`crate::secrets`] 单独保存。
+//! 启用月度报告后，每月 `send_day` 日起自动发送上月报告，同一月份只发送一次。
+
+use crate::format::Formatter;
+use crate::report::MonthlyReport;
+use crate::secrets::SMTP_PASSWORD;
+use crate::settings::Settings;
+use crate::storage::{Database, StorageError};
+use chrono::{DateTime, Datelike, Utc};
+use lettre::message::{header::ContentType, Mailbox};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{Message, SmtpTransport, Transport};
+use serde::{Deserialize, Serialize};
+
+/// SMTP 设置项键名
+pub const SMTP_SETTING_KEY: &str = "smtp";
+
+/// 月度报告邮件设置项键名
+pub const REPORT_EMAIL_SETTING_KEY: &str = "report_email";
+
+/// SMTP 设置项
+pub const SMTP_SETTINGS: Settings<SmtpSettings> =
+    Settings::new(SMTP_SETTING_KEY, SmtpSettings::default);
+
+/// 月度报告邮件设置项
+pub const REPORT_EMAIL_SETTINGS: Settings<ReportEmailSettings> =
+    Settings::new(REPORT_EMAIL_SETTING_KEY, ReportEmailSettings::default);
+
+/// 检查是否需要发送月度报告的间隔（秒）
+pub const REPORT_CHECK_INTERVAL_SECS: u64 = 3600;
+
+/// 邮件发送错误
+#[derive(Debug, thiserror::Error)]
+pub enum EmailError {
+    #[error("SMTP is not configured")]
+    NotConfigured,
+
+    #[error("Invalid address: {0}")]
+    InvalidAddress(String),
+
+    #[error("Failed to build message: {0}")]
+    Message(String),
+
+    #[error("SMTP error: {0}")]
+    Smtp(String),
+}
+
+/// 连接加密方式
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SmtpSecurity {
+    /// 明文连接后升级为 TLS（通常为 587 端口）
+    #[default]
+    StartTls,
+    /// 直接使用 TLS（通常为 465 端口）
+    Tls,
+    /// 不加密（仅用于本地中继）
+    None,
+}
+
+fn default_port() -> u16 {
+    587
+}
+
+/// SMTP 服务器设置（不含密码）
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct SmtpSettings {
+    /// 服务器地址
+    pub host: String,
+    /// 端口
+    #[serde(default = "default_port")]
+    pub port: u16,
+    /// 登录用户名，为空表示无需认证
+    #[serde(default)]
+    pub username: String,
+    /// 发件人地址
+    pub from: String,
+    /// 加密方式
+    #[serde(default)]
+    pub security: SmtpSecurity,
+}
+
+impl SmtpSettings {
+    /// 是否已配置服务器与发件人
+    pub fn is_configured(&self) -> bool {
+        !self.host.trim().is_empty() && !self.from.trim().is_empty()
+    }
+
+    /// 发送 HTML 邮件
+    pub fn send_html(
+        &self,
+        password: Option<&str>,
+        recipients: &[String],
+        subject: &str,
+        html: String,
+    ) -> Result<(), EmailError> {
+        if !self.is_configured() || recipients.is_empty() {
+            return Err(EmailError::NotConfigured);
+        }
+
+        let mut builder = Message::builder()
+            .from(parse_mailbox(&self.from)?)
+            .subject(subject)
+            .header(ContentType::TEXT_HTML);
+        for recipient in recipients {
+            builder = builder.to(parse_mailbox(recipient)?);
+        }
+        let message = builder
+            .body(html)
+            .map_err(|e| EmailError::Message(e.to_string()))?;
+
+        let mut transport = match self.security {
+            SmtpSecurity::StartTls => SmtpTransport::starttls_relay(&self.host),
+            SmtpSecurity::Tls => SmtpTransport::relay(&self.host),
+            SmtpSecurity::None => Ok(SmtpTransport::builder_dangerous(&self.host)),
+        }
+        .map_err(|e| EmailError::Smtp(e.to_string()))?
+        .port(self.port);
+        if !self.username.is_empty() {
+            transport = transport.credentials(Credentials::new(
+                self.username.clone(),
+                password.unwrap_or_default().to_string(),
+            ));
+        }
+
+        transport
+            .build()
+            .send(&message)
+            .map_err(|e| EmailError::Smtp(e.to_string()))?;
+        Ok(())
+    }
+}
+
+fn parse_mailbox(address: &str) -> Result<Mailbox, EmailError> {
+    address
+        .trim()
+        .parse()
+        .map_err(|_| EmailError::InvalidAddress(address.to_string()))
+}
+
+fn default_send_day() -> u32 {
+    1
+}
+
+/// 月度报告邮件设置
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ReportEmailSettings {
+    /// 是否启用自动发送
+    #[serde(default)]
+    pub enabled: bool,
+    /// 收件人
+    #[serde(default)]
+    pub recipients: Vec<String>,
+    /// 每月几日发送上月报告（1~28）
+    #[serde(default = "default_send_day")]
+    pub send_day: u32,
+    /// 最近一次发送的报告月份，如 "2024-03"
+    #[serde(default)]
+    pub last_sent: Option<String>,
+}
+
+impl Default for ReportEmailSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            recipients: Vec::new(),
+            send_day: default_send_day(),
+            last_sent: None,
+        }
+    }
+}
+
+impl ReportEmailSettings {
+    /// 当前应发送的报告月份（上月），未到发送日或已发送时返回 None
+    pub fn due_period(&self, now: DateTime<Utc>) -> Option<(i32, u32)> {
+        if !self.enabled || self.recipients.is_empty() || now.day() < self.send_day.clamp(1, 28) {
+            return None;
+        }
+        let (year, month) = if now.month() == 1 {
+            (now.year() - 1, 12)
+        } else {
+            (now.year(), now.month() - 1)
+        };
+        let label = format!("{:04}-{:02}", year, month);
+        (self.last_sent.as_deref() != Some(label.as_str())).then_some((year, month))
+    }
+}
+
+/// 待发送的报告邮件（含密码，不实现 Debug）
+#[derive(Clone)]
+pub struct ReportEmail {
+    pub smtp: SmtpSettings,
+    pub password: Option<String>,
+    pub recipients: Vec<String>,
+    pub report: MonthlyReport,
+    /// 报告中数值的格式
+    pub formatter: Formatter,
+}
+
+impl ReportEmail {
+    /// 为指定月份准备报告邮件
+    pub fn prepare(
+        db: &Database,
+        year: i32,
+        month: u32,
+        recipients: Vec<String>,
+    ) -> Result<Self, StorageError> {
+        Ok(Self {
+            smtp: db.get_smtp_settings()?,
+            password: db.get_secret(SMTP_PASSWORD)?,
+            recipients,
+            report: db.get_monthly_report(year, month)?,
+            formatter: db.formatter()?,
+        })
+    }
+
+    /// 准备到期的月度报告邮件（若有）
+    pub fn due(db: &Database, now: DateTime<Utc>) -> Result<Option<Self>, StorageError> {
+        let settings = db.get_report_email_settings()?;
+        match settings.due_period(now) {
+            Some((year, month)) => Self::prepare(db, year, month, settings.recipients).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// 发送邮件（网络请求，调用方不应持有数据库锁）
+    pub fn send(&self) -> Result<(), EmailError> {
+        self.smtp.send_html(
+            self.password.as_deref(),
+            &self.recipients,
+            &self.report.subject(),
+            self.report.to_html_with(&self.formatter),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_due_period() {
+        let mut settings = ReportEmailSettings {
+            enabled: true,
+            recipients: vec!["me@example.com".to_string()],
+            send_day: 3,
+            last_sent: None,
+        };
+        let jan2 = Utc.with_ymd_and_hms(2024, 1, 2, 8, 0, 0).unwrap();
+        let jan5 = Utc.with_ymd_and_hms(2024, 1, 5, 8, 0, 0).unwrap();
+        assert_eq!(settings.due_period(jan2), None);
+        assert_eq!(settings.due_period(jan5), Some((2023, 12)));
+
+        settings.last_sent = Some("2023-12".to_string());
+        assert_eq!(settings.due_period(jan5), None);
+    }
+}
@@ -0,0 +1,144 @@
+//! 首次运行：检测全新安装，并按用户选择的位置、本位币与语言创建数据
+//!
+//! 配置文件最后写入，作为完成的标志：中途失败时下次启动仍视为首次运行，重新选择即可，
+//! 已创建的数据文件会被沿用。便携模式下数据位置固定在程序旁，选择的目录不生效。
+
+use crate::asset::Currency;
+use crate::portable::AppPaths;
+use crate::settings::Settings;
+use crate::storage::{Database, StorageError};
+use crate::AppConfig;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// 配置文件路径
+pub const CONFIG_FILE: &str = "config.json";
+
+/// 数据目录中的数据文件名
+pub const DATA_FILE_NAME: &str = "assets.json";
+
+/// 语言设置项键名
+pub const LOCALE_SETTING_KEY: &str = "locale";
+
+/// 默认语言
+pub const DEFAULT_LOCALE: &str = "zh-CN";
+
+/// 语言设置项
+pub const LOCALE_SETTINGS: Settings<String> = Settings::new(LOCALE_SETTING_KEY, default_locale);
+
+fn default_locale() -> String {
+    DEFAULT_LOCALE.to_string()
+}
+
+/// 首次运行状态（供引导界面使用）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FirstRunState {
+    /// 是否为全新安装（没有配置文件，默认位置也没有数据）
+    pub first_run: bool,
+    /// 是否为便携模式（数据目录固定，不能选择）
+    pub portable: bool,
+    /// 建议的数据目录
+    pub default_data_dir: String,
+    /// 建议的本位币
+    pub base_currency: Currency,
+    /// 建议的语言
+    pub locale: String,
+}
+
+impl FirstRunState {
+    /// 检测首次运行状态
+    pub fn detect(paths: &AppPaths) -> Self {
+        let defaults = paths.default_config();
+        let default_data_dir = Path::new(&defaults.db_path)
+            .parent()
+            .map(|dir| dir.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        Self {
+            first_run: !paths.config_file.exists() && !Path::new(&defaults.db_path).exists(),
+            portable: paths.is_portable(),
+            default_data_dir,
+            base_currency: Currency::default(),
+            locale: default_locale(),
+        }
+    }
+}
+
+/// 首次运行时用户的选择
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FirstRunChoices {
+    /// 数据目录
+    pub data_dir: String,
+    /// 本位币
+    pub base_currency: Currency,
+    /// 语言
+    pub locale: String,
+}
+
+/// 按选择创建数据文件与设置，最后写入配置文件，返回新配置
+pub fn complete(paths: &AppPaths, choices: &FirstRunChoices) -> Result<AppConfig, StorageError> {
+    if choices.data_dir.trim().is_empty() {
+        return Err(StorageError::InvalidData(
+            "Data directory is required".to_string(),
+        ));
+    }
+    if choices.locale.trim().is_empty() {
+        return Err(StorageError::InvalidData("Locale is required".to_string()));
+    }
+
+    let db_path: PathBuf = Path::new(&choices.data_dir).join(DATA_FILE_NAME);
+    let config = paths.enforce(AppConfig {
+        db_path: db_path.to_string_lossy().into_owned(),
+        ..paths.default_config()
+    });
+    {
+        let mut db = Database::open(&config.db_path)?;
+        let mut fx = db.get_fx_settings()?;
+        fx.base_currency = choices.base_currency.clone();
+        db.set_fx_settings(&fx)?;
+        db.set_settings(LOCALE_SETTINGS, &choices.locale)?;
+        db.save_now()?;
+    }
+    config.save(&paths.config_file)?;
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    #[test]
+    fn test_first_run_setup() {
+        let dir = std::env::temp_dir().join(format!("asset-manager-first-{}", Uuid::new_v4()));
+        let config_path = dir.join(CONFIG_FILE);
+        let paths = AppPaths {
+            config_file: config_path.clone(),
+            portable_root: None,
+            profile_dir: None,
+        };
+        assert!(FirstRunState::detect(&paths).first_run);
+
+        let mut choices = FirstRunChoices {
+            data_dir: String::new(),
+            base_currency: Currency::USD,
+            locale: "en-US".to_string(),
+        };
+        assert!(complete(&paths, &choices).is_err());
+        assert!(!config_path.exists());
+
+        choices.data_dir = dir.join("data").to_string_lossy().into_owned();
+        let config = complete(&paths, &choices).unwrap();
+        assert!(!FirstRunState::detect(&paths).first_run);
+        assert_eq!(
+            AppConfig::load(&config_path).unwrap().unwrap().db_path,
+            config.db_path
+        );
+
+        let db = Database::open(&config.db_path).unwrap();
+        assert_eq!(db.get_fx_settings().unwrap().base_currency, Currency::USD);
+        assert_eq!(db.get_settings(LOCALE_SETTINGS).unwrap(), "en-US");
+
+        drop(db);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,247 @@
+//! 数字与日期格式化：界面、报告模板与报告邮件使用同一套设置，各处显示一致
+//!
+//! 语言来自首次运行时选择的 `locale` 设置（`zh` 开头的按中文习惯，其余按英文习惯），
+//! 小数位数与舍入方式来自精度设置，数字分组与日期样式来自本模块的格式设置。
+
+use crate::asset::Currency;
+use crate::first_run::DEFAULT_LOCALE;
+use crate::precision::PrecisionSettings;
+use crate::settings::Settings;
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+
+/// 设置项键名
+pub const FORMAT_SETTING_KEY: &str = "format";
+
+/// 格式设置项
+pub const FORMAT_SETTINGS: Settings<FormatSettings> =
+    Settings::new(FORMAT_SETTING_KEY, FormatSettings::default);
+
+/// 整数部分的分组方式
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum NumberGrouping {
+    /// 不分组：1234567.00
+    #[default]
+    None,
+    /// 每三位分组：1,234,567.00
+    Thousands,
+    /// 按万、亿每四位分组：123,4567.00
+    Myriad,
+}
+
+/// 日期样式
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DateStyle {
+    /// 2024-03-05
+    #[default]
+    Iso,
+    /// 按语言：2024年3月5日 / Mar 5, 2024
+    Locale,
+}
+
+/// 格式设置
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct FormatSettings {
+    #[serde(default)]
+    pub grouping: NumberGrouping,
+    #[serde(default)]
+    pub date_style: DateStyle,
+}
+
+/// 要格式化的值（供界面批量请求）
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum FormatRequest {
+    /// 数字，未指定小数位时使用默认小数位
+    Number {
+        value: f64,
+        #[serde(default)]
+        decimals: Option<u32>,
+    },
+    /// 金额，按货币的小数位
+    Money {
+        value: f64,
+        currency: String,
+    },
+    /// 紧凑写法：1.23万 / 1.23M
+    Compact {
+        value: f64,
+    },
+    Date {
+        date: NaiveDate,
+    },
+    DateTime {
+        timestamp: DateTime<Utc>,
+    },
+}
+
+/// 格式化器
+#[derive(Debug, Clone)]
+pub struct Formatter {
+    chinese: bool,
+    settings: FormatSettings,
+    precision: PrecisionSettings,
+}
+
+impl Default for Formatter {
+    fn default() -> Self {
+        Self::new(
+            DEFAULT_LOCALE,
+            FormatSettings::default(),
+            PrecisionSettings::default(),
+        )
+    }
+}
+
+impl Formatter {
+    pub fn new(locale: &str, settings: FormatSettings, precision: PrecisionSettings) -> Self {
+        Self {
+            chinese: locale.to_ascii_lowercase().starts_with("zh"),
+            settings,
+            precision,
+        }
+    }
+
+    /// 按指定小数位舍入并分组
+    pub fn number(&self, value: f64, decimals: u32) -> String {
+        let rounded = self.precision.rounding_mode.round(value, decimals);
+        let text = format!("{:.*}", decimals as usize, rounded.abs());
+        let (int, frac) = text.split_once('.').unwrap_or((text.as_str(), ""));
+        let group = match self.settings.grouping {
+            NumberGrouping::None => int.len(),
+            NumberGrouping::Thousands => 3,
+            NumberGrouping::Myriad => 4,
+        };
+        let mut out = String::with_capacity(text.len() + int.len() / group + 1);
+        if rounded < 0.0 {
+            out.push('-');
+        }
+        for (i, c) in int.chars().enumerate() {
+            if i > 0 && (int.len() - i) % group == 0 {
+                out.push(',');
+            }
+            out.push(c);
+        }
+        if !frac.is_empty() {
+            out.push('.');
+            out.push_str(frac);
+        }
+        out
+    }
+
+    /// 按默认小数位格式化
+    pub fn number_default(&self, value: f64) -> String {
+        self.number(value, self.precision.default_decimals)
+    }
+
+    /// 按货币的小数位格式化金额
+    pub fn money(&self, value: f64, currency: &Currency) -> String {
+        self.number(value, self.precision.decimals_for(currency))
+    }
+
+    /// 紧凑写法：中文用万、亿，其余用 K、M、B（最多两位小数），较小的值按默认小数位
+    pub fn compact(&self, value: f64) -> String {
+        let units: &[(f64, &str)] = if self.chinese {
+            &[(1e8, "亿"), (1e4, "万")]
+        } else {
+            &[(1e9, "B"), (1e6, "M"), (1e3, "K")]
+        };
+        let Some((scale, unit)) = units.iter().find(|(scale, _)| value.abs() >= *scale) else {
+            return self.number_default(value);
+        };
+        let scaled = self.number(value / scale, 2);
+        let scaled = scaled.trim_end_matches('0').trim_end_matches('.');
+        format!("{}{}", scaled, unit)
+    }
+
+    /// 格式化日期
+    pub fn date(&self, date: NaiveDate) -> String {
+        date.format(self.date_pattern()).to_string()
+    }
+
+    /// 格式化时间（UTC）
+    pub fn datetime(&self, timestamp: DateTime<Utc>) -> String {
+        format!(
+            "{} {}",
+            timestamp.format(self.date_pattern()),
+            timestamp.format("%H:%M UTC")
+        )
+    }
+
+    fn date_pattern(&self) -> &'static str {
+        match (self.settings.date_style, self.chinese) {
+            (DateStyle::Iso, _) => "%Y-%m-%d",
+            (DateStyle::Locale, true) => "%Y年%-m月%-d日",
+            (DateStyle::Locale, false) => "%b %-d, %Y",
+        }
+    }
+
+    /// 格式化界面请求的值
+    pub fn format(&self, request: &FormatRequest) -> String {
+        match request {
+            FormatRequest::Number { value, decimals } => match decimals {
+                Some(decimals) => self.number(*value, *decimals),
+                None => self.number_default(*value),
+            },
+            FormatRequest::Money { value, currency } => {
+                self.money(*value, &Currency::from_code(currency))
+            }
+            FormatRequest::Compact { value } => self.compact(*value),
+            FormatRequest::Date { date } => self.date(*date),
+            FormatRequest::DateTime { timestamp } => self.datetime(*timestamp),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_formatter() {
+        let plain = Formatter::default();
+        assert_eq!(plain.number_default(1234567.891), "1234567.89");
+        assert_eq!(plain.money(-1234.5, &Currency::JPY), "-1235");
+        assert_eq!(plain.number(-0.001, 2), "0.00");
+
+        let settings = FormatSettings {
+            grouping: NumberGrouping::Thousands,
+            date_style: DateStyle::Locale,
+        };
+        let en = Formatter::new("en-US", settings.clone(), PrecisionSettings::default());
+        assert_eq!(en.number_default(1234567.891), "1,234,567.89");
+        assert_eq!(en.number(999.0, 0), "999");
+        assert_eq!(en.compact(1_530_000.0), "1.53M");
+        assert_eq!(en.compact(-2000.0), "-2K");
+        assert_eq!(en.compact(12.5), "12.50");
+
+        let zh = Formatter::new(
+            "zh-CN",
+            FormatSettings {
+                grouping: NumberGrouping::Myriad,
+                ..settings
+            },
+            PrecisionSettings::default(),
+        );
+        assert_eq!(zh.number(123456789.0, 0), "1,2345,6789");
+        assert_eq!(zh.compact(123456789.0), "1.23亿");
+        assert_eq!(zh.compact(50000.0), "5万");
+
+        let date = NaiveDate::from_ymd_opt(2024, 3, 5).unwrap();
+        assert_eq!(plain.date(date), "2024-03-05");
+        assert_eq!(zh.date(date), "2024年3月5日");
+        assert_eq!(en.date(date), "Mar 5, 2024");
+        let timestamp = Utc.with_ymd_and_hms(2024, 3, 5, 8, 30, 0).unwrap();
+        assert_eq!(plain.datetime(timestamp), "2024-03-05 08:30 UTC");
+        assert_eq!(
+            zh.format(&FormatRequest::Money {
+                value: 12345.678,
+                currency: "CNY".to_string(),
+            }),
+            "1,2345.68"
+        );
+    }
+}
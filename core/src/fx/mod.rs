@@ -0,0 +1,260 @@
+//! 汇率换算与外汇敞口
+
+mod provider;
+
+pub use provider::*;
+
+use crate::asset::{Asset, AssetTransaction, Currency};
+use crate::settings::Settings;
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use uuid::Uuid;
+
+/// 设置项键名
+pub const FX_SETTING_KEY: &str = "fx";
+
+/// 汇率设置项
+pub const FX_SETTINGS: Settings<FxSettings> = Settings::new(FX_SETTING_KEY, FxSettings::default);
+
+/// 敏感度分析使用的汇率变动幅度
+pub const SENSITIVITY_SHOCK: f64 = 0.10;
+
+/// 汇率设置
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct FxSettings {
+    /// 本位币
+    #[serde(default)]
+    pub base_currency: Currency,
+    /// 汇率：1 单位外币折合多少本位币（按货币代码）
+    #[serde(default)]
+    pub rates: HashMap<String, f64>,
+}
+
+impl FxSettings {
+    /// 获取折算为本位币的汇率
+    pub fn rate(&self, currency: &Currency) -> Option<f64> {
+        if *currency == self.base_currency {
+            return Some(1.0);
+        }
+        self.rates.get(currency.code()).copied()
+    }
+
+    /// 折算为本位币，缺少汇率时返回 `None`
+    pub fn convert(&self, value: f64, currency: &Currency) -> Option<f64> {
+        self.rate(currency).map(|rate| value * rate)
+    }
+}
+
+/// 某日的汇率：1 单位 `currency` 折合 `rate` 单位 `base_currency`
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FxRate {
+    /// 货币代码
+    pub currency: String,
+    /// 本位币代码
+    pub base_currency: String,
+    /// 生效日期
+    pub date: NaiveDate,
+    pub rate: f64,
+}
+
+impl FxRate {
+    /// 创建汇率记录
+    pub fn new(currency: &Currency, base_currency: &Currency, date: NaiveDate, rate: f64) -> Self {
+        Self {
+            currency: currency.code().to_string(),
+            base_currency: base_currency.code().to_string(),
+            date,
+            rate,
+        }
+    }
+
+    /// 是否为同一货币对、同一日期的记录
+    pub fn same_key(&self, other: &FxRate) -> bool {
+        self.currency == other.currency
+            && self.base_currency == other.base_currency
+            && self.date == other.date
+    }
+}
+
+/// 单一货币的敞口
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CurrencyExposure {
+    pub currency: Currency,
+    /// 原币价值
+    pub value: f64,
+    /// 折合本位币价值
+    pub base_value: f64,
+    /// 占组合比例（0~1）
+    pub percentage: f64,
+    /// 该货币升值 10% 时净资产的变化（本位币）
+    pub sensitivity_up: f64,
+    /// 该货币贬值 10% 时净资产的变化（本位币）
+    pub sensitivity_down: f64,
+}
+
+/// 外汇敞口报告
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FxExposure {
+    pub base_currency: Currency,
+    /// 折合本位币的总价值
+    pub total_base_value: f64,
+    /// 各货币敞口（按本位币价值降序）
+    pub exposures: Vec<CurrencyExposure>,
+    /// 缺少汇率、未计入的货币代码
+    pub missing_rates: Vec<String>,
+}
+
+/// 计算外汇敞口
+pub fn fx_exposure(assets: &[Asset], settings: &FxSettings) -> FxExposure {
+    let mut by_currency: BTreeMap<String, (Currency, f64)> = BTreeMap::new();
+    for asset in assets {
+        by_currency
+            .entry(asset.currency.code().to_string())
+            .or_insert_with(|| (asset.currency.clone(), 0.0))
+            .1 += asset.value;
+    }
+
+    let mut exposures = Vec::new();
+    let mut missing_rates = Vec::new();
+    for (code, (currency, value)) in by_currency {
+        let Some(base_value) = settings.convert(value, &currency) else {
+            missing_rates.push(code);
+            continue;
+        };
+        let shock = if currency == settings.base_currency {
+            0.0
+        } else {
+            base_value * SENSITIVITY_SHOCK
+        };
+        exposures.push(CurrencyExposure {
+            currency,
+            value,
+            base_value,
+            percentage: 0.0,
+            sensitivity_up: shock,
+            sensitivity_down: -shock,
+        });
+    }
+
+    let total_base_value: f64 = exposures.iter().map(|e| e.base_value).sum();
+    if total_base_value != 0.0 {
+        for exposure in &mut exposures {
+            exposure.percentage = exposure.base_value / total_base_value;
+        }
+    }
+    exposures.sort_by(|a, b| b.base_value.total_cmp(&a.base_value));
+
+    FxExposure {
+        base_currency: settings.base_currency.clone(),
+        total_base_value,
+        exposures,
+        missing_rates,
+    }
+}
+
+/// 外币交易的汇兑损益
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FxGainLoss {
+    pub transaction_id: Uuid,
+    pub asset_id: Uuid,
+    pub timestamp: DateTime<Utc>,
+    /// 原币金额
+    pub original_amount: f64,
+    pub original_currency: Currency,
+    /// 资产（账户）币种
+    pub account_currency: Currency,
+    /// 实际使用的汇率
+    pub applied_rate: f64,
+    /// 当日参考汇率
+    pub reference_rate: f64,
+    /// 汇兑损益（资产币种，正数为收益）
+    pub gain_loss: f64,
+    /// 汇兑损益折合本位币，缺少汇率时为空
+    pub base_gain_loss: Option<f64>,
+}
+
+/// 汇兑损益报告
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FxGainLossReport {
+    pub base_currency: Currency,
+    pub items: Vec<FxGainLoss>,
+    /// 折合本位币的合计
+    pub total_base_gain_loss: f64,
+    /// 缺少参考汇率、未计入的交易
+    pub missing_rates: Vec<Uuid>,
+}
+
+/// 按参考汇率计算一笔外币交易的汇兑损益
+///
+/// 资金流入时实际汇率高于参考汇率为收益；流出（支付）时实际汇率高于参考汇率为损失。
+/// 非外币交易返回 `None`。
+pub fn fx_gain_loss(
+    transaction: &AssetTransaction,
+    account_currency: &Currency,
+    reference_rate: f64,
+) -> Option<FxGainLoss> {
+    let original_amount = transaction.original_amount?;
+    let original_currency = transaction.original_currency.clone()?;
+    let applied_rate = transaction.fx_rate?;
+    let sign = if transaction.delta() < 0.0 { -1.0 } else { 1.0 };
+
+    Some(FxGainLoss {
+        transaction_id: transaction.id,
+        asset_id: transaction.asset_id,
+        timestamp: transaction.timestamp,
+        original_amount,
+        original_currency,
+        account_currency: account_currency.clone(),
+        applied_rate,
+        reference_rate,
+        gain_loss: sign * (applied_rate - reference_rate) * original_amount.abs(),
+        base_gain_loss: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asset::{AssetType, TransactionType};
+
+    #[test]
+    fn test_fx_exposure() {
+        let mut settings = FxSettings::default();
+        settings.rates.insert("USD".to_string(), 7.0);
+
+        let assets = vec![
+            Asset::new("现金", AssetType::Cash, 3000.0),
+            Asset::new("美股", AssetType::Stock, 1000.0).with_currency(Currency::USD),
+            Asset::new("日元", AssetType::Cash, 5000.0).with_currency(Currency::JPY),
+        ];
+
+        let report = fx_exposure(&assets, &settings);
+        assert_eq!(report.total_base_value, 10000.0);
+        assert_eq!(report.missing_rates, vec!["JPY".to_string()]);
+
+        let usd = &report.exposures[0];
+        assert_eq!(usd.currency, Currency::USD);
+        assert_eq!(usd.percentage, 0.7);
+        assert_eq!(usd.sensitivity_up, 700.0);
+        assert_eq!(report.exposures[1].sensitivity_down, 0.0);
+    }
+
+    #[test]
+    fn test_fx_gain_loss() {
+        let card = Asset::new("信用卡", AssetType::Cash, 10000.0);
+        // 支付 100 美元，实际扣款 720 元，参考汇率 7.1
+        let payment = AssetTransaction::new(card.id, TransactionType::Expense, 10000.0, 9280.0)
+            .with_original_amount(100.0, Currency::USD, 7.2);
+        let result = fx_gain_loss(&payment, &Currency::CNY, 7.1).unwrap();
+        assert!((result.gain_loss + 10.0).abs() < 1e-9);
+
+        let refund = AssetTransaction::new(card.id, TransactionType::Income, 9280.0, 9995.0)
+            .with_original_amount(100.0, Currency::USD, 7.15);
+        let result = fx_gain_loss(&refund, &Currency::CNY, 7.1).unwrap();
+        assert!((result.gain_loss - 5.0).abs() < 1e-9);
+
+        let plain = AssetTransaction::new(card.id, TransactionType::Income, 0.0, 1.0);
+        assert!(fx_gain_loss(&plain, &Currency::CNY, 7.1).is_none());
+    }
+}
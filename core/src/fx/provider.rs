@@ -0,0 +1,201 @@
+//! 历史汇率数据源
+
+use super::FxRate;
+use crate::asset::Currency;
+use chrono::{Datelike, NaiveDate};
+
+/// 汇率数据源错误
+#[derive(Debug, thiserror::Error)]
+pub enum FxError {
+    #[error("Request failed: {0}")]
+    Request(String),
+
+    #[error("Invalid response: {0}")]
+    InvalidResponse(String),
+}
+
+/// 历史汇率数据源
+pub trait ExchangeRateProvider: Send + Sync {
+    /// 数据源名称
+    fn name(&self) -> &str;
+
+    /// 获取日期范围内（含首尾）`currency` 对 `base` 的每日汇率
+    fn fetch_history(
+        &self,
+        currency: &Currency,
+        base: &Currency,
+        start: NaiveDate,
+        end: NaiveDate,
+    ) -> Result<Vec<FxRate>, FxError>;
+}
+
+/// 依次拉取多个货币的历史汇率
+pub fn backfill(
+    provider: &dyn ExchangeRateProvider,
+    currencies: &[Currency],
+    base: &Currency,
+    start: NaiveDate,
+    end: NaiveDate,
+) -> Result<Vec<FxRate>, FxError> {
+    let mut rates = Vec::new();
+    for currency in currencies.iter().filter(|c| *c != base) {
+        rates.extend(provider.fetch_history(currency, base, start, end)?);
+    }
+    Ok(rates)
+}
+
+/// Frankfurter（欧洲央行参考汇率，免费且无需密钥）
+pub struct FrankfurterProvider {
+    base_url: String,
+}
+
+impl FrankfurterProvider {
+    pub const DEFAULT_URL: &'static str = "https://api.frankfurter.app";
+
+    /// 使用默认地址
+    pub fn new() -> Self {
+        Self::with_base_url(Self::DEFAULT_URL)
+    }
+
+    /// 使用自定义地址（如自建实例）
+    pub fn with_base_url(base_url: impl Into<String>) -> Self {
+        Self {
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+        }
+    }
+}
+
+impl Default for FrankfurterProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ExchangeRateProvider for FrankfurterProvider {
+    fn name(&self) -> &str {
+        "frankfurter"
+    }
+
+    fn fetch_history(
+        &self,
+        currency: &Currency,
+        base: &Currency,
+        start: NaiveDate,
+        end: NaiveDate,
+    ) -> Result<Vec<FxRate>, FxError> {
+        let url = format!("{}/{}..{}", self.base_url, start, end);
+        let body = ureq::get(&url)
+            .query("from", currency.code())
+            .query("to", base.code())
+            .call()
+            .map_err(|e| FxError::Request(e.to_string()))?
+            .into_string()
+            .map_err(|e| FxError::Request(e.to_string()))?;
+        parse_frankfurter(&body, currency, base)
+    }
+}
+
+/// 模拟汇率数据源：不联网，按内置参考汇率加上随日期变化的固定波动生成（同一日期结果相同），
+/// 用于开发、测试与离线演示
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MockExchangeRateProvider;
+
+impl MockExchangeRateProvider {
+    /// 1 美元可兑换的该货币数量（未知货币按 1 计）
+    fn per_usd(currency: &Currency) -> f64 {
+        match currency {
+            Currency::CNY => 7.2,
+            Currency::USD => 1.0,
+            Currency::EUR => 0.92,
+            Currency::GBP => 0.79,
+            Currency::JPY => 150.0,
+            Currency::HKD => 7.8,
+            Currency::Other(_) => 1.0,
+        }
+    }
+
+    /// 某日 `currency` 对 `base` 的汇率，按星期在 ±1% 内波动
+    pub fn rate(currency: &Currency, base: &Currency, date: NaiveDate) -> f64 {
+        if currency == base {
+            return 1.0;
+        }
+        let swing = (date.num_days_from_ce() % 7 - 3) as f64 / 300.0;
+        Self::per_usd(base) / Self::per_usd(currency) * (1.0 + swing)
+    }
+}
+
+impl ExchangeRateProvider for MockExchangeRateProvider {
+    fn name(&self) -> &str {
+        "mock"
+    }
+
+    fn fetch_history(
+        &self,
+        currency: &Currency,
+        base: &Currency,
+        start: NaiveDate,
+        end: NaiveDate,
+    ) -> Result<Vec<FxRate>, FxError> {
+        Ok(start
+            .iter_days()
+            .take_while(|date| *date <= end)
+            .map(|date| FxRate::new(currency, base, date, Self::rate(currency, base, date)))
+            .collect())
+    }
+}
+
+/// 解析 `{"rates": {"2024-01-02": {"CNY": 7.1}}}` 格式的响应
+fn parse_frankfurter(
+    body: &str,
+    currency: &Currency,
+    base: &Currency,
+) -> Result<Vec<FxRate>, FxError> {
+    let json: serde_json::Value =
+        serde_json::from_str(body).map_err(|e| FxError::InvalidResponse(e.to_string()))?;
+    let rates = json
+        .get("rates")
+        .and_then(|r| r.as_object())
+        .ok_or_else(|| FxError::InvalidResponse("missing rates".to_string()))?;
+
+    let mut result = Vec::with_capacity(rates.len());
+    for (date, values) in rates {
+        let date = NaiveDate::parse_from_str(date, "%Y-%m-%d")
+            .map_err(|e| FxError::InvalidResponse(e.to_string()))?;
+        if let Some(rate) = values.get(base.code()).and_then(|v| v.as_f64()) {
+            result.push(FxRate::new(currency, base, date, rate));
+        }
+    }
+    result.sort_by_key(|r| r.date);
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_frankfurter() {
+        let body = r#"{"amount":1.0,"base":"USD","rates":{"2024-01-03":{"CNY":7.14},"2024-01-02":{"CNY":7.1}}}"#;
+        let rates = parse_frankfurter(body, &Currency::USD, &Currency::CNY).unwrap();
+        assert_eq!(rates.len(), 2);
+        assert_eq!(rates[0].date, NaiveDate::from_ymd_opt(2024, 1, 2).unwrap());
+        assert_eq!(rates[1].rate, 7.14);
+        assert!(parse_frankfurter("{}", &Currency::USD, &Currency::CNY).is_err());
+    }
+
+    #[test]
+    fn test_mock_provider() {
+        let start = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let end = NaiveDate::from_ymd_opt(2024, 1, 10).unwrap();
+        let rates = MockExchangeRateProvider
+            .fetch_history(&Currency::USD, &Currency::CNY, start, end)
+            .unwrap();
+        assert_eq!(rates.len(), 10);
+        assert!(rates.iter().all(|r| (r.rate - 7.2).abs() <= 0.072 + 1e-9));
+        // 结果可重复
+        let again = MockExchangeRateProvider
+            .fetch_history(&Currency::USD, &Currency::CNY, start, end)
+            .unwrap();
+        assert_eq!(rates[3].rate, again[3].rate);
+    }
+}
@@ -0,0 +1,628 @@
+//! GnuCash 账簿导入导出
+//!
+//! 支持 GnuCash 的 XML 格式（含 gzip 压缩的默认存档）。导入时资产类账户映射为资产，
+//! 拆分（split）映射为交易；导出时以复式账簿为基础生成 GnuCash 科目表与交易。
+//! 以货币计价的账户按拆分的数量（账户货币）计算余额，交易货币与账户货币不同时也不会算错。
+//! SQLite 格式的账簿暂不支持，需先在 GnuCash 中另存为 XML。
+
+mod xml;
+
+use crate::asset::{Asset, AssetTransaction, AssetType, Currency, TransactionType};
+use crate::import::{ImportBatch, ImportSource};
+use crate::ledger::AccountKind;
+use crate::storage::{Database, SortBy, SortOrder, StorageError};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::io::Read;
+use uuid::Uuid;
+
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const SQLITE_MAGIC: &[u8] = b"SQLite format 3\0";
+const DATE_FORMAT: &str = "%Y-%m-%d %H:%M:%S %z";
+
+/// 导入结果
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GnuCashImport {
+    /// 导入的资产数量
+    pub assets: usize,
+    /// 导入的交易数量
+    pub transactions: usize,
+    /// 跳过的占位账户（完整路径）
+    pub skipped_accounts: Vec<String>,
+    /// 导入批次，可用于撤销本次导入
+    #[serde(default)]
+    pub batch_id: Option<Uuid>,
+}
+
+/// 读取账簿文件内容，自动解压 gzip
+pub fn read_book(bytes: &[u8]) -> Result<String, StorageError> {
+    if bytes.starts_with(SQLITE_MAGIC) {
+        return Err(StorageError::InvalidData(
+            "GnuCash SQLite books are not supported, save the book as XML first".to_string(),
+        ));
+    }
+    if bytes.starts_with(GZIP_MAGIC) {
+        let mut content = String::new();
+        flate2::read::GzDecoder::new(bytes).read_to_string(&mut content)?;
+        return Ok(content);
+    }
+    String::from_utf8(bytes.to_vec()).map_err(|e| StorageError::InvalidData(e.to_string()))
+}
+
+// ============ 导入 ============
+
+struct GncAccount {
+    id: String,
+    name: String,
+    kind: String,
+    parent: Option<String>,
+    currency: Option<Currency>,
+    placeholder: bool,
+}
+
+struct GncSplit {
+    account: String,
+    /// 以交易货币计的金额
+    value: f64,
+    /// 以账户商品（货币或份额）计的数量
+    quantity: Option<f64>,
+}
+
+struct GncTransaction {
+    date: DateTime<Utc>,
+    description: String,
+    currency: Option<Currency>,
+    splits: Vec<GncSplit>,
+}
+
+/// 导入 GnuCash XML 账簿
+pub fn import_book(db: &mut Database, content: &str) -> Result<GnuCashImport, StorageError> {
+    let root = xml::parse(content).map_err(StorageError::InvalidData)?;
+    if root.name != "gnc-v2" {
+        return Err(StorageError::InvalidData(format!(
+            "Not a GnuCash book: <{}>",
+            root.name
+        )));
+    }
+    let book = root.child("gnc:book").unwrap_or(&root);
+
+    let accounts: HashMap<String, GncAccount> = book
+        .children_named("gnc:account")
+        .filter_map(parse_account)
+        .map(|a| (a.id.clone(), a))
+        .collect();
+    let mut transactions: Vec<GncTransaction> = book
+        .children_named("gnc:transaction")
+        .map(parse_transaction)
+        .collect::<Result<_, _>>()?;
+    transactions.sort_by_key(|t| t.date);
+
+    let batch = ImportBatch::new(ImportSource::GnuCash);
+    let mut result = GnuCashImport::default();
+    let mut assets: HashMap<String, Asset> = HashMap::new();
+    for account in accounts.values() {
+        let Some(asset_type) = asset_type_for(&account.kind) else {
+            continue;
+        };
+        let first_txn = transactions
+            .iter()
+            .find(|t| t.splits.iter().any(|s| s.account == account.id));
+        if account.placeholder && first_txn.is_none() {
+            result.skipped_accounts.push(full_name(&accounts, account));
+            continue;
+        }
+
+        let mut asset = Asset::new(account.name.clone(), asset_type, 0.0)
+            .with_description(full_name(&accounts, account));
+        if let Some(currency) = account
+            .currency
+            .clone()
+            .or_else(|| first_txn.and_then(|t| t.currency.clone()))
+        {
+            asset = asset.with_currency(currency);
+        }
+        if let Some(txn) = first_txn {
+            asset.created_at = txn.date;
+        }
+        asset.import_batch = Some(batch.id);
+        // 尽量沿用 GnuCash 的 GUID，便于重复导入时识别
+        if let Ok(id) = Uuid::parse_str(&account.id) {
+            if db.get_asset(id)?.is_none() {
+                asset.id = id;
+            }
+        }
+        assets.insert(account.id.clone(), asset);
+    }
+
+    // 按时间顺序回放拆分，得到每笔交易前后的余额
+    let mut balances: HashMap<String, f64> = HashMap::new();
+    let mut records = Vec::new();
+    for txn in &transactions {
+        let transaction_type = transaction_type_for(txn, &accounts);
+        for split in &txn.splits {
+            let Some(asset) = assets.get(&split.account) else {
+                continue;
+            };
+            // 货币账户的余额以账户货币计，其他商品（如股票份额）按交易货币的金额计价
+            let amount = match accounts.get(&split.account) {
+                Some(account) if account.currency.is_some() => {
+                    split.quantity.unwrap_or(split.value)
+                }
+                _ => split.value,
+            };
+            let balance = balances.entry(split.account.clone()).or_insert(0.0);
+            let before = *balance;
+            *balance += amount;
+            let mut record =
+                AssetTransaction::new(asset.id, transaction_type.clone(), before, *balance)
+                    .with_timestamp(txn.date);
+            if !txn.description.is_empty() {
+                record = record.with_note(txn.description.clone());
+            }
+            record.import_batch = Some(batch.id);
+            records.push(record);
+        }
+    }
+
+    // 先以零值创建资产，写入交易后再设置最终余额，保证复式账簿不重复计入
+    for asset in assets.values() {
+        db.create_asset(asset)?;
+    }
+    db.add_transactions(&records)?;
+    for (account_id, asset) in &mut assets {
+        asset.update_value(balances.get(account_id).copied().unwrap_or(0.0));
+        db.update_asset(asset)?;
+    }
+
+    if !assets.is_empty() {
+        db.add_import_batch(&batch)?;
+        result.batch_id = Some(batch.id);
+    }
+    result.assets = assets.len();
+    result.transactions = records.len();
+    Ok(result)
+}
+
+fn parse_account(element: &xml::Element) -> Option<GncAccount> {
+    let placeholder = element
+        .child("act:slots")
+        .map(|slots| {
+            slots.children_named("slot").any(|slot| {
+                slot.child_text("slot:key") == Some("placeholder")
+                    && slot.child_text("slot:value") == Some("true")
+            })
+        })
+        .unwrap_or(false);
+
+    Some(GncAccount {
+        id: element.child_text("act:id")?.to_string(),
+        name: element.child_text("act:name")?.to_string(),
+        kind: element.child_text("act:type")?.to_string(),
+        parent: element.child_text("act:parent").map(str::to_string),
+        currency: element.child("act:commodity").and_then(parse_commodity),
+        placeholder,
+    })
+}
+
+fn parse_transaction(element: &xml::Element) -> Result<GncTransaction, StorageError> {
+    let date = element
+        .child("trn:date-posted")
+        .and_then(|d| d.child_text("ts:date"))
+        .ok_or_else(|| StorageError::InvalidData("Transaction without date".to_string()))?;
+    let date = DateTime::parse_from_str(date, DATE_FORMAT)
+        .map_err(|e| StorageError::InvalidData(format!("Invalid date {}: {}", date, e)))?
+        .with_timezone(&Utc);
+
+    let splits = element
+        .child("trn:splits")
+        .map(|splits| {
+            splits
+                .children_named("trn:split")
+                .filter_map(|split| {
+                    Some(GncSplit {
+                        account: split.child_text("split:account")?.to_string(),
+                        value: parse_rational(split.child_text("split:value")?)?,
+                        quantity: split.child_text("split:quantity").and_then(parse_rational),
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    Ok(GncTransaction {
+        date,
+        description: element
+            .child_text("trn:description")
+            .unwrap_or_default()
+            .to_string(),
+        currency: element.child("trn:currency").and_then(parse_commodity),
+        splits,
+    })
+}
+
+/// 仅识别货币类商品
+fn parse_commodity(element: &xml::Element) -> Option<Currency> {
+    match element.child_text("cmdty:space")? {
+        "CURRENCY" | "ISO4217" => Some(Currency::from_code(element.child_text("cmdty:id")?)),
+        _ => None,
+    }
+}
+
+/// 解析 "12345/100" 形式的有理数
+fn parse_rational(s: &str) -> Option<f64> {
+    match s.split_once('/') {
+        Some((num, denom)) => {
+            let denom: f64 = denom.trim().parse().ok()?;
+            (denom != 0.0).then_some(num.trim().parse::<f64>().ok()? / denom)
+        }
+        None => s.trim().parse().ok(),
+    }
+}
+
+fn asset_type_for(kind: &str) -> Option<AssetType> {
+    match kind {
+        "CASH" => Some(AssetType::Cash),
+        "BANK" => Some(AssetType::BankDeposit),
+        "STOCK" => Some(AssetType::Stock),
+        "MUTUAL" => Some(AssetType::Fund),
+        "ASSET" | "CURRENCY" => Some(AssetType::Other("asset".to_string())),
+        "RECEIVABLE" => Some(AssetType::Other("receivable".to_string())),
+        "CREDIT" | "LIABILITY" | "PAYABLE" => Some(AssetType::Other("liability".to_string())),
+        _ => None,
+    }
+}
+
+fn transaction_type_for(
+    txn: &GncTransaction,
+    accounts: &HashMap<String, GncAccount>,
+) -> TransactionType {
+    let kinds: Vec<&str> = txn
+        .splits
+        .iter()
+        .filter_map(|s| accounts.get(&s.account))
+        .map(|a| a.kind.as_str())
+        .collect();
+    if kinds.contains(&"INCOME") {
+        TransactionType::Income
+    } else if kinds.contains(&"EXPENSE") {
+        TransactionType::Expense
+    } else if kinds.len() > 1 {
+        TransactionType::Transfer
+    } else {
+        TransactionType::ValueChange
+    }
+}
+
+/// 账户完整路径，如 "Assets:Current Assets:Checking"
+fn full_name(accounts: &HashMap<String, GncAccount>, account: &GncAccount) -> String {
+    let mut parts = vec![account.name.as_str()];
+    let mut parent = account.parent.as_ref();
+    while let Some(p) = parent.and_then(|id| accounts.get(id)) {
+        if p.kind == "ROOT" {
+            break;
+        }
+        parts.push(&p.name);
+        parent = p.parent.as_ref();
+    }
+    parts.reverse();
+    parts.join(":")
+}
+
+// ============ 导出 ============
+
+/// 导出为 GnuCash XML 账簿
+pub fn export_book(db: &Database) -> Result<String, StorageError> {
+    let ledger = db.build_ledger();
+    let base = db.get_fx_settings()?.base_currency;
+    let assets: HashMap<Uuid, Asset> = db
+        .list_assets(SortBy::default(), SortOrder::default())?
+        .into_iter()
+        .map(|a| (a.id, a))
+        .collect();
+
+    let account_currency = |asset_id: Option<Uuid>| -> Currency {
+        asset_id
+            .and_then(|id| assets.get(&id))
+            .map(|a| a.currency.clone())
+            .unwrap_or_else(|| base.clone())
+    };
+
+    let root_id = Uuid::new_v4();
+    let kinds = [
+        (AccountKind::Asset, "Assets", "ASSET"),
+        (AccountKind::Liability, "Liabilities", "LIABILITY"),
+        (AccountKind::Equity, "Equity", "EQUITY"),
+        (AccountKind::Income, "Income", "INCOME"),
+        (AccountKind::Expense, "Expenses", "EXPENSE"),
+    ];
+    let parents: Vec<(AccountKind, Uuid, &str, &str)> = kinds
+        .iter()
+        .filter(|(kind, _, _)| ledger.accounts.iter().any(|a| a.kind == *kind))
+        .map(|(kind, name, gnc_type)| (*kind, Uuid::new_v4(), *name, *gnc_type))
+        .collect();
+
+    let mut accounts = String::new();
+    write_account(
+        &mut accounts,
+        root_id,
+        "Root Account",
+        "ROOT",
+        None,
+        None,
+        false,
+    );
+    for (_, id, name, gnc_type) in &parents {
+        write_account(
+            &mut accounts,
+            *id,
+            name,
+            gnc_type,
+            Some(&base),
+            Some(root_id),
+            true,
+        );
+    }
+    for account in &ledger.accounts {
+        let parent = parents.iter().find(|p| p.0 == account.kind).map(|p| p.1);
+        let gnc_type = match account.asset_id.and_then(|id| assets.get(&id)) {
+            Some(asset) => match asset.asset_type {
+                AssetType::Cash => "CASH",
+                AssetType::BankDeposit => "BANK",
+                _ => "ASSET",
+            },
+            None => kinds
+                .iter()
+                .find(|k| k.0 == account.kind)
+                .map(|k| k.2)
+                .unwrap_or("ASSET"),
+        };
+        let currency = account_currency(account.asset_id);
+        write_account(
+            &mut accounts,
+            account.id,
+            &account.name,
+            gnc_type,
+            Some(&currency),
+            parent,
+            false,
+        );
+    }
+
+    let mut transactions = String::new();
+    for entry in &ledger.entries {
+        let currency = entry
+            .postings
+            .iter()
+            .find_map(|p| ledger.account(p.account_id).and_then(|a| a.asset_id))
+            .map(|id| account_currency(Some(id)))
+            .unwrap_or_else(|| base.clone());
+        let date = entry.timestamp.format(DATE_FORMAT);
+
+        let _ = writeln!(transactions, "<gnc:transaction version=\"2.0.0\">");
+        let _ = writeln!(
+            transactions,
+            "  <trn:id type=\"guid\">{}</trn:id>",
+            entry.id.simple()
+        );
+        write_commodity(&mut transactions, "  ", "trn:currency", &currency);
+        let _ = writeln!(
+            transactions,
+            "  <trn:date-posted>\n    <ts:date>{}</ts:date>\n  </trn:date-posted>",
+            date
+        );
+        let _ = writeln!(
+            transactions,
+            "  <trn:date-entered>\n    <ts:date>{}</ts:date>\n  </trn:date-entered>",
+            date
+        );
+        let _ = writeln!(
+            transactions,
+            "  <trn:description>{}</trn:description>",
+            xml::escape(&entry.description)
+        );
+        let _ = writeln!(transactions, "  <trn:splits>");
+        for posting in &entry.postings {
+            let amount = format_rational(posting.amount);
+            let _ = writeln!(
+                transactions,
+                "    <trn:split>\n      <split:id type=\"guid\">{}</split:id>\n      <split:reconciled-state>n</split:reconciled-state>\n      <split:value>{}</split:value>\n      <split:quantity>{}</split:quantity>\n      <split:account type=\"guid\">{}</split:account>\n    </trn:split>",
+                Uuid::new_v4().simple(),
+                amount,
+                amount,
+                posting.account_id.simple()
+            );
+        }
+        let _ = writeln!(transactions, "  </trn:splits>");
+        let _ = writeln!(transactions, "</gnc:transaction>");
+    }
+
+    let mut out = String::new();
+    out.push_str("<?xml version=\"1.0\" encoding=\"utf-8\" ?>\n");
+    out.push_str("<gnc-v2\n");
+    for ns in [
+        "gnc", "act", "book", "cd", "cmdty", "slot", "split", "trn", "ts",
+    ] {
+        let _ = writeln!(
+            out,
+            "     xmlns:{}=\"http://www.gnucash.org/XML/{}\"",
+            ns, ns
+        );
+    }
+    out.push_str(">\n");
+    out.push_str("<gnc:count-data cd:type=\"book\">1</gnc:count-data>\n");
+    out.push_str("<gnc:book version=\"2.0.0\">\n");
+    let _ = writeln!(
+        out,
+        "<book:id type=\"guid\">{}</book:id>",
+        Uuid::new_v4().simple()
+    );
+    let _ = writeln!(
+        out,
+        "<gnc:count-data cd:type=\"account\">{}</gnc:count-data>",
+        ledger.accounts.len() + parents.len() + 1
+    );
+    let _ = writeln!(
+        out,
+        "<gnc:count-data cd:type=\"transaction\">{}</gnc:count-data>",
+        ledger.entries.len()
+    );
+    out.push_str(&accounts);
+    out.push_str(&transactions);
+    out.push_str("</gnc:book>\n</gnc-v2>\n");
+    Ok(out)
+}
+
+fn write_account(
+    out: &mut String,
+    id: Uuid,
+    name: &str,
+    gnc_type: &str,
+    currency: Option<&Currency>,
+    parent: Option<Uuid>,
+    placeholder: bool,
+) {
+    let _ = writeln!(out, "<gnc:account version=\"2.0.0\">");
+    let _ = writeln!(out, "  <act:name>{}</act:name>", xml::escape(name));
+    let _ = writeln!(out, "  <act:id type=\"guid\">{}</act:id>", id.simple());
+    let _ = writeln!(out, "  <act:type>{}</act:type>", gnc_type);
+    if let Some(currency) = currency {
+        write_commodity(out, "  ", "act:commodity", currency);
+        out.push_str("  <act:commodity-scu>100</act:commodity-scu>\n");
+    }
+    if placeholder {
+        out.push_str("  <act:slots>\n    <slot>\n      <slot:key>placeholder</slot:key>\n      <slot:value type=\"string\">true</slot:value>\n    </slot>\n  </act:slots>\n");
+    }
+    if let Some(parent) = parent {
+        let _ = writeln!(
+            out,
+            "  <act:parent type=\"guid\">{}</act:parent>",
+            parent.simple()
+        );
+    }
+    let _ = writeln!(out, "</gnc:account>");
+}
+
+fn write_commodity(out: &mut String, indent: &str, tag: &str, currency: &Currency) {
+    let _ = writeln!(
+        out,
+        "{indent}<{tag}>\n{indent}  <cmdty:space>CURRENCY</cmdty:space>\n{indent}  <cmdty:id>{}</cmdty:id>\n{indent}</{tag}>",
+        xml::escape(currency.code())
+    );
+}
+
+/// 以分为单位输出有理数，如 12.34 → "1234/100"
+fn format_rational(amount: f64) -> String {
+    format!("{}/100", (amount * 100.0).round() as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let mut db = Database::open_in_memory().unwrap();
+        let cash = Asset::new("现金 & 零钱", AssetType::Cash, 1000.0);
+        db.create_asset(&cash).unwrap();
+        db.adjust_asset_value(
+            cash.id,
+            250.5,
+            TransactionType::Income,
+            Some("工资".to_string()),
+        )
+        .unwrap();
+        let usd =
+            Asset::new("美元账户", AssetType::BankDeposit, 300.0).with_currency(Currency::USD);
+        db.create_asset(&usd).unwrap();
+
+        let exported = export_book(&db).unwrap();
+        let content = read_book(exported.as_bytes()).unwrap();
+
+        let mut imported = Database::open_in_memory().unwrap();
+        let result = import_book(&mut imported, &content).unwrap();
+        assert_eq!(result.assets, 2);
+
+        let assets = imported
+            .list_assets(SortBy::default(), SortOrder::default())
+            .unwrap();
+        let cash_copy = assets.iter().find(|a| a.name == cash.name).unwrap();
+        assert_eq!(cash_copy.value, 1250.5);
+        assert_eq!(cash_copy.asset_type, AssetType::Cash);
+        let txns = imported
+            .get_transactions(cash_copy.id, &Default::default())
+            .unwrap();
+        assert!(txns
+            .iter()
+            .any(|t| t.transaction_type == TransactionType::Income && t.delta() == 250.5));
+
+        let usd_copy = assets.iter().find(|a| a.name == usd.name).unwrap();
+        assert_eq!(usd_copy.currency, Currency::USD);
+        assert_eq!(usd_copy.value, 300.0);
+
+        let undo = imported.undo_import(result.batch_id.unwrap()).unwrap();
+        assert_eq!(undo.assets, 2);
+        assert!(imported
+            .list_assets(SortBy::default(), SortOrder::default())
+            .unwrap()
+            .is_empty());
+
+        assert!(read_book(b"SQLite format 3\0...").is_err());
+    }
+
+    #[test]
+    fn test_import_split_quantity() {
+        // 从人民币账户转入美元账户：交易货币为人民币，美元账户的数量为 100 美元
+        let content = r#"<?xml version="1.0" encoding="utf-8" ?>
+<gnc-v2>
+<gnc:book>
+<gnc:account>
+  <act:name>Root Account</act:name>
+  <act:id>root</act:id>
+  <act:type>ROOT</act:type>
+</gnc:account>
+<gnc:account>
+  <act:name>Checking</act:name>
+  <act:id>cny</act:id>
+  <act:type>BANK</act:type>
+  <act:commodity><cmdty:space>CURRENCY</cmdty:space><cmdty:id>CNY</cmdty:id></act:commodity>
+  <act:parent>root</act:parent>
+</gnc:account>
+<gnc:account>
+  <act:name>Dollars</act:name>
+  <act:id>usd</act:id>
+  <act:type>BANK</act:type>
+  <act:commodity><cmdty:space>CURRENCY</cmdty:space><cmdty:id>USD</cmdty:id></act:commodity>
+  <act:parent>root</act:parent>
+</gnc:account>
+<gnc:transaction>
+  <trn:currency><cmdty:space>CURRENCY</cmdty:space><cmdty:id>CNY</cmdty:id></trn:currency>
+  <trn:date-posted><ts:date>2024-03-01 10:00:00 +0800</ts:date></trn:date-posted>
+  <trn:description>换汇</trn:description>
+  <trn:splits>
+    <trn:split>
+      <split:value>-70000/100</split:value>
+      <split:quantity>-70000/100</split:quantity>
+      <split:account>cny</split:account>
+    </trn:split>
+    <trn:split>
+      <split:value>70000/100</split:value>
+      <split:quantity>10000/100</split:quantity>
+      <split:account>usd</split:account>
+    </trn:split>
+  </trn:splits>
+</gnc:transaction>
+</gnc:book>
+</gnc-v2>"#;
+        let mut db = Database::open_in_memory().unwrap();
+        import_book(&mut db, content).unwrap();
+        let assets = db
+            .list_assets(SortBy::default(), SortOrder::default())
+            .unwrap();
+        let usd = assets.iter().find(|a| a.name == "Dollars").unwrap();
+        assert_eq!((usd.currency.clone(), usd.value), (Currency::USD, 100.0));
+        let cny = assets.iter().find(|a| a.name == "Checking").unwrap();
+        assert_eq!(cny.value, -700.0);
+    }
+}
@@ -0,0 +1,223 @@
+//! 极简 XML 解析与转义，仅覆盖 GnuCash 文件用到的子集
+
+/// XML 元素
+#[derive(Debug, Clone, Default)]
+pub struct Element {
+    /// 带命名空间前缀的标签名，如 `act:name`
+    pub name: String,
+    pub attrs: Vec<(String, String)>,
+    pub children: Vec<Element>,
+    /// 直接包含的文本（已解码）
+    pub text: String,
+}
+
+impl Element {
+    /// 第一个指定名称的子元素
+    pub fn child(&self, name: &str) -> Option<&Element> {
+        self.children.iter().find(|c| c.name == name)
+    }
+
+    /// 所有指定名称的子元素
+    pub fn children_named<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Element> {
+        self.children.iter().filter(move |c| c.name == name)
+    }
+
+    /// 子元素的文本（去除首尾空白）
+    pub fn child_text(&self, name: &str) -> Option<&str> {
+        self.child(name).map(|c| c.text.trim())
+    }
+}
+
+/// 解析 XML 文本，返回根元素
+pub fn parse(input: &str) -> Result<Element, String> {
+    let mut stack: Vec<Element> = Vec::new();
+    let mut root = None;
+    let mut rest = input;
+
+    while let Some(start) = rest.find('<') {
+        if let Some(top) = stack.last_mut() {
+            top.text.push_str(&decode(&rest[..start]));
+        }
+        rest = &rest[start..];
+
+        if let Some(body) = rest.strip_prefix("<?") {
+            rest = skip_past(body, "?>")?;
+        } else if let Some(body) = rest.strip_prefix("<!--") {
+            rest = skip_past(body, "-->")?;
+        } else if let Some(body) = rest.strip_prefix("<![CDATA[") {
+            let end = body.find("]]>").ok_or("Unterminated CDATA")?;
+            if let Some(top) = stack.last_mut() {
+                top.text.push_str(&body[..end]);
+            }
+            rest = &body[end + 3..];
+        } else if let Some(body) = rest.strip_prefix("<!") {
+            rest = skip_past(body, ">")?;
+        } else if let Some(body) = rest.strip_prefix("</") {
+            let end = body.find('>').ok_or("Unterminated end tag")?;
+            let name = body[..end].trim();
+            let element = stack
+                .pop()
+                .ok_or_else(|| format!("Unexpected </{}>", name))?;
+            if element.name != name {
+                return Err(format!("Expected </{}>, found </{}>", element.name, name));
+            }
+            attach(&mut stack, &mut root, element);
+            rest = &body[end + 1..];
+        } else {
+            let end = tag_end(rest).ok_or("Unterminated start tag")?;
+            let inner = &rest[1..end];
+            let (inner, self_closing) = match inner.strip_suffix('/') {
+                Some(inner) => (inner, true),
+                None => (inner, false),
+            };
+            let element = parse_start_tag(inner)?;
+            if self_closing {
+                attach(&mut stack, &mut root, element);
+            } else {
+                stack.push(element);
+            }
+            rest = &rest[end + 1..];
+        }
+    }
+
+    if let Some(open) = stack.last() {
+        return Err(format!("Unclosed element <{}>", open.name));
+    }
+    root.ok_or_else(|| "Empty document".to_string())
+}
+
+/// 转义文本或属性值
+pub fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '&' => out.push_str("&amp;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+fn attach(stack: &mut [Element], root: &mut Option<Element>, element: Element) {
+    match stack.last_mut() {
+        Some(parent) => parent.children.push(element),
+        None => *root = Some(element),
+    }
+}
+
+fn skip_past<'a>(s: &'a str, pattern: &str) -> Result<&'a str, String> {
+    s.find(pattern)
+        .map(|i| &s[i + pattern.len()..])
+        .ok_or_else(|| format!("Missing {}", pattern))
+}
+
+/// 查找开始标签的结束位置（忽略引号内的 `>`）
+fn tag_end(s: &str) -> Option<usize> {
+    let mut quote = None;
+    for (i, c) in s.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), c) if c == q => quote = None,
+            (None, '>') => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+fn parse_start_tag(inner: &str) -> Result<Element, String> {
+    let inner = inner.trim();
+    let name_end = inner.find(char::is_whitespace).unwrap_or(inner.len());
+    let mut element = Element {
+        name: inner[..name_end].to_string(),
+        ..Default::default()
+    };
+
+    let mut rest = inner[name_end..].trim_start();
+    while !rest.is_empty() {
+        let eq = rest.find('=').ok_or("Malformed attribute")?;
+        let key = rest[..eq].trim().to_string();
+        let value_part = rest[eq + 1..].trim_start();
+        let quote = value_part.chars().next().ok_or("Malformed attribute")?;
+        if quote != '"' && quote != '\'' {
+            return Err(format!("Unquoted attribute: {}", key));
+        }
+        let close = value_part[1..]
+            .find(quote)
+            .ok_or("Unterminated attribute")?;
+        element.attrs.push((key, decode(&value_part[1..close + 1])));
+        rest = value_part[close + 2..].trim_start();
+    }
+
+    Ok(element)
+}
+
+fn decode(s: &str) -> String {
+    if !s.contains('&') {
+        return s.to_string();
+    }
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(i) = rest.find('&') {
+        out.push_str(&rest[..i]);
+        rest = &rest[i..];
+        let Some(end) = rest.find(';') else {
+            break;
+        };
+        let entity = &rest[1..end];
+        let decoded = match entity {
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "amp" => Some('&'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => entity
+                .strip_prefix("#x")
+                .map(|hex| u32::from_str_radix(hex, 16).ok())
+                .unwrap_or_else(|| entity.strip_prefix('#').and_then(|d| d.parse().ok()))
+                .and_then(char::from_u32),
+        };
+        match decoded {
+            Some(c) => {
+                out.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let doc = r#"<?xml version="1.0"?>
+<!-- comment -->
+<gnc-v2 xmlns:act="x">
+  <act:name>A &amp; B</act:name>
+  <act:id type="guid">abc</act:id>
+  <empty/>
+</gnc-v2>"#;
+        let root = parse(doc).unwrap();
+        assert_eq!(root.name, "gnc-v2");
+        assert_eq!(root.child_text("act:name"), Some("A & B"));
+        assert_eq!(
+            root.child("act:id").unwrap().attrs,
+            vec![("type".to_string(), "guid".to_string())]
+        );
+        assert!(root.child("empty").is_some());
+        assert!(parse("<a><b></a>").is_err());
+        assert_eq!(decode(&escape("<\"&'>")), "<\"&'>");
+    }
+}
@@ -0,0 +1,76 @@
+//! 导入批次：每次导入产生的资产与交易都带有批次ID，可整体撤销
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// 导入来源
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportSource {
+    /// 银行对账单（CSV/OFX）
+    Statement,
+    /// GnuCash 账簿
+    GnuCash,
+    /// 插件导入器
+    Plugin,
+    /// 券商成交单
+    Trades,
+}
+
+/// 导入批次
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ImportBatch {
+    /// 唯一标识符
+    pub id: Uuid,
+    /// 导入来源
+    pub source: ImportSource,
+    /// 来源文件名
+    pub file_name: Option<String>,
+    /// 导入时间
+    pub created_at: DateTime<Utc>,
+}
+
+impl ImportBatch {
+    /// 创建导入批次
+    pub fn new(source: ImportSource) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            source,
+            file_name: None,
+            created_at: Utc::now(),
+        }
+    }
+
+    /// 设置来源文件名
+    pub fn with_file_name(mut self, file_name: impl Into<String>) -> Self {
+        self.file_name = Some(file_name.into());
+        self
+    }
+}
+
+/// 导入批次及其统计
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportBatchStats {
+    #[serde(flatten)]
+    pub batch: ImportBatch,
+    /// 该批次新建的资产数量
+    pub assets: usize,
+    /// 该批次导入的交易数量
+    pub transactions: usize,
+    /// 交易变动合计
+    pub total_amount: f64,
+    /// 最早的交易时间
+    pub first_date: Option<DateTime<Utc>>,
+    /// 最晚的交易时间
+    pub last_date: Option<DateTime<Utc>>,
+}
+
+/// 撤销导入的结果
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UndoImport {
+    /// 删除的资产数量
+    pub assets: usize,
+    /// 删除的交易数量
+    pub transactions: usize,
+}
@@ -0,0 +1,89 @@
+//! 导入去重：为每笔流水与整个文件计算内容指纹
+//!
+//! 流水指纹由资产、记账日、金额（分）、摘要和流水号计算。同一文件中完全相同的流水
+//! （如同日两笔相同金额的消费）按出现次序区分，因此重复导入同一对账单或导入日期
+//! 重叠的对账单时，已导入的流水会被跳过，而真实的重复消费不会被误判。
+
+use super::StatementLine;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// FNV-1a 64 位哈希
+pub fn fnv1a64(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(FNV_OFFSET, |hash, b| {
+        (hash ^ u64::from(*b)).wrapping_mul(FNV_PRIME)
+    })
+}
+
+/// 文件内容指纹
+pub fn file_hash(bytes: &[u8]) -> String {
+    format!("{:016x}", fnv1a64(bytes))
+}
+
+/// 计算每笔流水的指纹（与输入顺序一致）
+pub fn line_hashes(asset_id: Uuid, lines: &[StatementLine]) -> Vec<String> {
+    let mut occurrences: HashMap<String, usize> = HashMap::new();
+    lines
+        .iter()
+        .map(|line| {
+            let key = format!(
+                "{}|{}|{}|{}|{}",
+                asset_id,
+                line.date.format("%Y-%m-%d"),
+                (line.amount * 100.0).round() as i64,
+                line.description.as_deref().unwrap_or("").trim(),
+                line.reference.as_deref().unwrap_or("").trim(),
+            );
+            let occurrence = occurrences.entry(key.clone()).or_insert(0);
+            *occurrence += 1;
+            file_hash(format!("{}#{}", key, occurrence).as_bytes())
+        })
+        .collect()
+}
+
+/// 已导入文件的记录
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ImportedFile {
+    /// 文件内容指纹
+    pub hash: String,
+    /// 导入的资产
+    pub asset_id: Uuid,
+    /// 文件名
+    pub file_name: String,
+    /// 导入时间
+    pub imported_at: chrono::DateTime<chrono::Utc>,
+    /// 导入批次（撤销该批次后文件可重新导入）
+    #[serde(default)]
+    pub batch_id: Option<Uuid>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_line_hashes() {
+        let line = StatementLine {
+            date: chrono::Utc.with_ymd_and_hms(2024, 1, 5, 9, 0, 0).unwrap(),
+            amount: -12.5,
+            description: Some("咖啡".to_string()),
+            reference: None,
+        };
+        let asset = Uuid::new_v4();
+        let hashes = line_hashes(asset, &[line.clone(), line.clone()]);
+        // 同一文件中的两笔相同消费互不相同
+        assert_ne!(hashes[0], hashes[1]);
+        // 再次导入时指纹不变
+        assert_eq!(
+            line_hashes(asset, std::slice::from_ref(&line))[0],
+            hashes[0]
+        );
+        assert_ne!(line_hashes(Uuid::new_v4(), &[line])[0], hashes[0]);
+        assert_eq!(file_hash(b""), "cbf29ce484222325");
+    }
+}
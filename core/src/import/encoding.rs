@@ -0,0 +1,90 @@
+//! 对账单文件编码识别与解码
+//!
+//! 依次按 BOM、UTF-16 零字节特征、UTF-8 合法性判断，其余情况按 GB18030（兼容 GBK）
+//! 解码，覆盖国内银行常见的导出格式。
+
+use encoding_rs::{Encoding, GB18030, UTF_16BE, UTF_16LE, UTF_8};
+
+/// 判断 UTF-16 时采样的字节数
+const SNIFF_BYTES: usize = 512;
+
+/// 解码后的文本
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodedText {
+    pub text: String,
+    /// 实际使用的编码名称（如 "UTF-8"、"gb18030"）
+    pub encoding: String,
+}
+
+/// 解码文件内容；`label` 为 None 时自动识别编码
+pub fn decode(bytes: &[u8], label: Option<&str>) -> Result<DecodedText, String> {
+    let encoding = match label.map(str::trim).filter(|l| !l.is_empty()) {
+        Some(label) => Encoding::for_label(label.as_bytes())
+            .ok_or_else(|| format!("Unknown encoding: {}", label))?,
+        None => detect(bytes),
+    };
+    let (text, _) = encoding.decode_with_bom_removal(bytes);
+    Ok(DecodedText {
+        text: text.into_owned(),
+        encoding: encoding.name().to_string(),
+    })
+}
+
+/// 识别编码
+pub fn detect(bytes: &[u8]) -> &'static Encoding {
+    if let Some((encoding, _)) = Encoding::for_bom(bytes) {
+        return encoding;
+    }
+
+    let sample = &bytes[..bytes.len().min(SNIFF_BYTES) & !1];
+    if sample.len() >= 4 {
+        let zeros = |offset: usize| {
+            sample
+                .iter()
+                .skip(offset)
+                .step_by(2)
+                .filter(|b| **b == 0)
+                .count()
+        };
+        let half = sample.len() / 2;
+        // ASCII 为主的 UTF-16 文本，每两个字节中约有一个为零
+        if zeros(1) * 3 > half && zeros(0) * 10 < half {
+            return UTF_16LE;
+        }
+        if zeros(0) * 3 > half && zeros(1) * 10 < half {
+            return UTF_16BE;
+        }
+    }
+
+    if std::str::from_utf8(bytes).is_ok() {
+        UTF_8
+    } else {
+        GB18030
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_encodings() {
+        let text = "日期,金额\n2024-01-05,100\n";
+
+        let (gbk, _, _) = GB18030.encode(text);
+        let decoded = decode(&gbk, None).unwrap();
+        assert_eq!(decoded.text, text);
+        assert_eq!(decoded.encoding, "gb18030");
+
+        let utf16: Vec<u8> = text.encode_utf16().flat_map(|u| u.to_le_bytes()).collect();
+        assert_eq!(decode(&utf16, None).unwrap().text, text);
+
+        let mut bom = vec![0xFE, 0xFF];
+        bom.extend(text.encode_utf16().flat_map(|u| u.to_be_bytes()));
+        assert_eq!(decode(&bom, None).unwrap().text, text);
+
+        assert_eq!(decode(text.as_bytes(), None).unwrap().encoding, "UTF-8");
+        assert_eq!(decode(&gbk, Some("GBK")).unwrap().text, text);
+        assert!(decode(&gbk, Some("klingon")).is_err());
+    }
+}
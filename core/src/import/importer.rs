@@ -0,0 +1,221 @@
+//! 导入器：内置的 CSV/OFX 与插件声明的导入器
+//!
+//! 插件导入器按扩展名接管内置格式不支持的文件，解析函数返回的流水行与内置格式一样
+//! 经过去重、按批次入库，可整体撤销。
+
+use super::{ParsedStatement, StatementFormat, StatementLine};
+use crate::plugin::{PluginImporter, PluginManager};
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// 可用的导入器
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ImporterInfo {
+    /// 导入器名称
+    pub name: String,
+    /// 处理的文件扩展名（不含点）
+    pub extensions: Vec<String>,
+    /// 提供导入器的插件（内置导入器为空）
+    pub plugin: Option<String>,
+}
+
+/// 文件使用的导入器
+#[derive(Debug, Clone, PartialEq)]
+pub enum Importer {
+    Builtin(StatementFormat),
+    Plugin(PluginImporter),
+}
+
+/// 内置导入器
+pub fn builtin_importers() -> Vec<ImporterInfo> {
+    [("csv", &["csv", "txt"]), ("ofx", &["ofx", "qfx"])]
+        .into_iter()
+        .map(|(name, extensions)| ImporterInfo {
+            name: name.to_string(),
+            extensions: extensions.iter().map(|e| e.to_string()).collect(),
+            plugin: None,
+        })
+        .collect()
+}
+
+/// 全部可用的导入器：内置导入器在前
+pub fn list_importers(plugins: &PluginManager) -> Vec<ImporterInfo> {
+    let mut importers = builtin_importers();
+    importers.extend(
+        plugins
+            .importers()
+            .into_iter()
+            .map(|importer| ImporterInfo {
+                name: importer.name,
+                extensions: importer.extensions,
+                plugin: Some(importer.plugin),
+            }),
+    );
+    importers
+}
+
+/// 按扩展名选择导入器：内置格式优先，其次为先声明该扩展名的插件导入器
+pub fn find_importer(path: &Path, plugins: Option<&PluginManager>) -> Option<Importer> {
+    if let Some(format) = StatementFormat::from_path(path) {
+        return Some(Importer::Builtin(format));
+    }
+    let ext = path.extension()?.to_str()?.to_lowercase();
+    plugins?
+        .importers()
+        .into_iter()
+        .find(|importer| {
+            importer
+                .extensions
+                .iter()
+                .any(|e| e.trim_start_matches('.').eq_ignore_ascii_case(&ext))
+        })
+        .map(Importer::Plugin)
+}
+
+/// 插件解析函数返回的一行流水
+#[derive(Debug, Deserialize)]
+struct PluginRow {
+    /// `YYYY-MM-DD` 或 RFC 3339 时间
+    date: String,
+    amount: f64,
+    #[serde(default)]
+    description: Option<String>,
+    #[serde(default)]
+    reference: Option<String>,
+}
+
+/// 转换插件解析函数的返回值（流水行列表），无法识别的行记为错误
+pub fn parse_rows(value: serde_json::Value) -> ParsedStatement {
+    let mut parsed = ParsedStatement::default();
+    let rows = match value {
+        serde_json::Value::Array(rows) => rows,
+        // 空的 Lua 表转换为空对象
+        serde_json::Value::Object(map) if map.is_empty() => Vec::new(),
+        serde_json::Value::Null => Vec::new(),
+        other => {
+            parsed
+                .errors
+                .push(format!("Importer returned a non-list value: {}", other));
+            return parsed;
+        }
+    };
+    for (i, row) in rows.into_iter().enumerate() {
+        let line = serde_json::from_value::<PluginRow>(row)
+            .map_err(|e| e.to_string())
+            .and_then(|row| {
+                let date =
+                    parse_date(&row.date).ok_or_else(|| format!("invalid date '{}'", row.date))?;
+                if !row.amount.is_finite() {
+                    return Err(format!("invalid amount {}", row.amount));
+                }
+                Ok(StatementLine {
+                    date,
+                    amount: row.amount,
+                    description: row.description.filter(|d| !d.is_empty()),
+                    reference: row.reference.filter(|r| !r.is_empty()),
+                })
+            });
+        match line {
+            Ok(line) => parsed.lines.push(line),
+            Err(e) => parsed.errors.push(format!("Row {}: {}", i + 1, e)),
+        }
+    }
+    parsed
+}
+
+fn parse_date(text: &str) -> Option<DateTime<Utc>> {
+    let text = text.trim();
+    DateTime::parse_from_rfc3339(text)
+        .map(|dt| dt.with_timezone(&Utc))
+        .ok()
+        .or_else(|| {
+            NaiveDate::parse_from_str(text, "%Y-%m-%d")
+                .ok()
+                .map(|d| d.and_time(chrono::NaiveTime::MIN).and_utc())
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asset::{Asset, AssetType};
+    use crate::import::{commit_file, read_file, ImportSource, MappingProfile};
+    use crate::storage::Database;
+    use serde_json::json;
+    use uuid::Uuid;
+
+    #[test]
+    fn test_parse_rows() {
+        let parsed = parse_rows(json!([
+            { "date": "2024-01-05", "amount": -20.5, "description": "午餐" },
+            { "date": "2024-01-06T08:00:00+08:00", "amount": 100, "reference": "R1" },
+            { "date": "01/07/2024", "amount": 1 },
+            { "amount": 1 },
+        ]));
+        assert_eq!(parsed.lines.len(), 2);
+        assert_eq!(parsed.lines[0].description.as_deref(), Some("午餐"));
+        assert_eq!(
+            parsed.lines[1].date.to_rfc3339(),
+            "2024-01-06T00:00:00+00:00"
+        );
+        assert_eq!(parsed.errors.len(), 2);
+        assert!(parsed.errors[0].starts_with("Row 3"));
+
+        assert!(parse_rows(json!({})).lines.is_empty());
+        assert_eq!(parse_rows(json!("oops")).errors.len(), 1);
+    }
+
+    #[test]
+    fn test_plugin_importer() {
+        let dir = std::env::temp_dir().join(format!("asset-manager-importer-{}", Uuid::new_v4()));
+        let plugin_dir = dir.join("plugins").join("qif");
+        std::fs::create_dir_all(&plugin_dir).unwrap();
+        std::fs::write(
+            plugin_dir.join("init.lua"),
+            r#"
+                local plugin = { name = "qif" }
+                plugin.importers = {
+                    { name = "qif", extensions = { "qif" }, handler = "parse_qif" },
+                }
+                function plugin.parse_qif(file)
+                    local rows = {}
+                    for date, amount in file.content:gmatch("D(%S+)\nT(%S+)") do
+                        table.insert(rows, { date = date, amount = tonumber(amount) })
+                    end
+                    return rows
+                end
+                return plugin
+            "#,
+        )
+        .unwrap();
+        let mut plugins = PluginManager::new(dir.join("plugins"));
+        plugins.load_all().unwrap();
+
+        let importers = list_importers(&plugins);
+        assert_eq!(importers.len(), 3);
+        assert_eq!(importers[2].plugin.as_deref(), Some("qif"));
+
+        let mut db = Database::open_in_memory().unwrap();
+        let asset = Asset::new("钱包", AssetType::Cash, 0.0);
+        db.create_asset(&asset).unwrap();
+        let profile = MappingProfile::new("qif", asset.id, "0", "1");
+        let path = dir.join("wallet.QIF");
+        std::fs::write(&path, "D2024-01-05\nT-20.5\nD2024-01-06\nT100\n").unwrap();
+
+        // 预览不入库，确认后按批次导入，可整体撤销
+        let file = read_file(&path, &profile, Some(&plugins)).unwrap();
+        assert_eq!(file.importer.as_deref(), Some("qif"));
+        assert_eq!(file.parsed.lines.len(), 2);
+        assert!(read_file(&path, &profile, None).is_err());
+        let result = commit_file(&mut db, file, &profile).unwrap();
+        assert_eq!(result.imported, 2);
+        let batches = db.list_import_batches().unwrap();
+        assert_eq!(batches[0].batch.source, ImportSource::Plugin);
+        let undo = db.undo_import(result.batch_id.unwrap()).unwrap();
+        assert_eq!(undo.transactions, 2);
+        assert_eq!(db.get_asset(asset.id).unwrap().unwrap().value, 0.0);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
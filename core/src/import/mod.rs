@@ -0,0 +1,334 @@
+//! 对账单导入（CSV/OFX）
+//!
+//! CSV 按绑定的映射配置解析列；OFX/QFX 使用标准字段，只借用映射配置中的目标资产。
+//! 其他扩展名交给插件声明的导入器解析，同样借用映射配置中的目标资产与编码。
+//! 监视目录中出现的新文件会被自动导入并归档，结果以通知形式发出。
+//! 券商成交单按成交单映射配置解析，见 [`TradeProfile`]。
+
+mod batch;
+mod dedup;
+mod encoding;
+mod importer;
+mod ofx;
+mod profile;
+mod trades;
+mod watch;
+
+pub use batch::*;
+pub use dedup::*;
+pub use encoding::{decode, DecodedText};
+pub use importer::*;
+pub use ofx::parse_ofx;
+pub use profile::*;
+pub use trades::*;
+pub use watch::*;
+
+use crate::asset::AssetTransaction;
+use crate::plugin::PluginManager;
+use crate::storage::{Database, StorageError};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+/// 对账单中的一笔流水
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct StatementLine {
+    /// 记账日期
+    pub date: DateTime<Utc>,
+    /// 金额（流入为正，流出为负）
+    pub amount: f64,
+    /// 摘要
+    pub description: Option<String>,
+    /// 银行流水号（如 OFX 的 FITID）
+    pub reference: Option<String>,
+}
+
+/// 对账单格式
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum StatementFormat {
+    Csv,
+    Ofx,
+}
+
+impl StatementFormat {
+    /// 按扩展名识别格式
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_str()?.to_lowercase();
+        match ext.as_str() {
+            "csv" | "txt" => Some(StatementFormat::Csv),
+            "ofx" | "qfx" => Some(StatementFormat::Ofx),
+            _ => None,
+        }
+    }
+}
+
+/// 解析结果：成功的流水与出错的行
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ParsedStatement {
+    pub lines: Vec<StatementLine>,
+    /// 无法解析的行（含行号）
+    pub errors: Vec<String>,
+}
+
+/// 导入结果
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ImportResult {
+    /// 来源文件
+    pub file: Option<String>,
+    /// 导入的资产
+    pub asset_id: Option<Uuid>,
+    /// 导入的交易数量
+    pub imported: usize,
+    /// 因已导入而跳过的流水数量
+    #[serde(default)]
+    pub duplicates: usize,
+    /// 整个文件此前已导入过
+    #[serde(default)]
+    pub duplicate_file: bool,
+    /// 导入批次，可用于撤销本次导入
+    #[serde(default)]
+    pub batch_id: Option<Uuid>,
+    /// 无法解析的行
+    pub errors: Vec<String>,
+    /// 归档后的文件路径
+    pub archived_to: Option<String>,
+    /// 识别或指定的文件编码
+    #[serde(default)]
+    pub encoding: Option<String>,
+}
+
+/// 对账单入库结果
+#[derive(Debug, Clone, Default)]
+pub struct StatementImport {
+    /// 新增的交易
+    pub transactions: Vec<AssetTransaction>,
+    /// 跳过的重复流水数量
+    pub duplicates: usize,
+}
+
+/// 文件格式识别结果，用于建立映射配置前预览
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StatementSniff {
+    pub format: StatementFormat,
+    pub encoding: String,
+    /// 识别出的分隔符（仅 CSV）
+    pub delimiter: Option<char>,
+    /// 前几行内容（仅 CSV）
+    pub rows: Vec<Vec<String>>,
+}
+
+/// 预览时返回的行数
+const SNIFF_ROWS: usize = 5;
+
+/// 识别对账单文件的格式、编码与分隔符
+pub fn sniff_file(path: &Path, encoding: Option<&str>) -> Result<StatementSniff, StorageError> {
+    let format = format_of(path)?;
+    let decoded = decode(&std::fs::read(path)?, encoding).map_err(StorageError::InvalidData)?;
+    let (delimiter, rows) = match format {
+        StatementFormat::Csv => {
+            let delimiter = crate::csv::detect_delimiter(&decoded.text);
+            let mut rows = crate::csv::parse(&decoded.text, delimiter);
+            rows.truncate(SNIFF_ROWS);
+            (Some(delimiter), rows)
+        }
+        StatementFormat::Ofx => (None, Vec::new()),
+    };
+    Ok(StatementSniff {
+        format,
+        encoding: decoded.encoding,
+        delimiter,
+        rows,
+    })
+}
+
+fn format_of(path: &Path) -> Result<StatementFormat, StorageError> {
+    StatementFormat::from_path(path).ok_or_else(|| {
+        StorageError::InvalidData(format!("Unsupported statement file: {}", path.display()))
+    })
+}
+
+/// 按格式解析对账单
+pub fn parse_statement(
+    content: &str,
+    format: StatementFormat,
+    profile: &MappingProfile,
+) -> ParsedStatement {
+    match format {
+        StatementFormat::Csv => profile.parse_csv(content),
+        StatementFormat::Ofx => parse_ofx(content),
+    }
+}
+
+/// 已读取并解析、尚未入库的对账单文件，可先预览再导入
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StatementFile {
+    pub path: PathBuf,
+    /// 文件内容指纹
+    pub hash: String,
+    /// 识别或指定的文件编码
+    pub encoding: String,
+    /// 解析文件的插件导入器（内置格式为空）
+    pub importer: Option<String>,
+    pub parsed: ParsedStatement,
+}
+
+/// 读取并解析对账单文件：内置格式按映射配置解析，其他扩展名交给插件导入器
+pub fn read_file(
+    path: &Path,
+    profile: &MappingProfile,
+    plugins: Option<&PluginManager>,
+) -> Result<StatementFile, StorageError> {
+    let importer = find_importer(path, plugins).ok_or_else(|| {
+        StorageError::InvalidData(format!("Unsupported statement file: {}", path.display()))
+    })?;
+    let bytes = std::fs::read(path)?;
+    let decoded = decode(&bytes, profile.encoding.as_deref()).map_err(StorageError::InvalidData)?;
+    let (parsed, importer) = match importer {
+        Importer::Builtin(format) => (parse_statement(&decoded.text, format, profile), None),
+        Importer::Plugin(importer) => {
+            let plugins = plugins.expect("plugin importers come from the plugin manager");
+            let value = plugins
+                .run_importer(&importer, &decoded.text, &file_name(path))
+                .map_err(|e| StorageError::InvalidData(e.to_string()))?;
+            (parse_rows(value), Some(importer.name))
+        }
+    };
+    Ok(StatementFile {
+        path: path.to_path_buf(),
+        hash: file_hash(&bytes),
+        encoding: decoded.encoding,
+        importer,
+        parsed,
+    })
+}
+
+/// 按映射配置导入一个内置格式的对账单文件
+///
+/// 已导入过的文件和流水会被跳过，跳过的数量记录在结果中。
+pub fn import_file(
+    db: &mut Database,
+    path: &Path,
+    profile: &MappingProfile,
+) -> Result<ImportResult, StorageError> {
+    let file = read_file(path, profile, None)?;
+    commit_file(db, file, profile)
+}
+
+/// 把解析后的对账单导入映射配置的目标资产，产生的交易属于同一批次，可整体撤销
+pub fn commit_file(
+    db: &mut Database,
+    file: StatementFile,
+    profile: &MappingProfile,
+) -> Result<ImportResult, StorageError> {
+    let StatementFile {
+        path,
+        hash,
+        encoding,
+        importer,
+        parsed,
+    } = file;
+    let mut result = ImportResult {
+        file: Some(path.display().to_string()),
+        asset_id: Some(profile.asset_id),
+        errors: parsed.errors,
+        encoding: Some(encoding),
+        ..Default::default()
+    };
+
+    if db.is_file_imported(profile.asset_id, &hash) {
+        result.duplicates = parsed.lines.len();
+        result.duplicate_file = true;
+        return Ok(result);
+    }
+
+    let file_name = file_name(&path);
+    let source = match importer {
+        Some(_) => ImportSource::Plugin,
+        None => ImportSource::Statement,
+    };
+    let batch = ImportBatch::new(source).with_file_name(file_name.clone());
+    let outcome = db.import_statement(profile.asset_id, &parsed.lines, &batch)?;
+    result.imported = outcome.transactions.len();
+    result.duplicates = outcome.duplicates;
+    result.batch_id = (result.imported > 0).then_some(batch.id);
+    db.record_imported_file(ImportedFile {
+        hash,
+        asset_id: profile.asset_id,
+        file_name,
+        imported_at: chrono::Utc::now(),
+        batch_id: result.batch_id,
+    })?;
+    Ok(result)
+}
+
+/// 按成交单映射配置导入券商成交单文件，解析出错的行一并记入结果
+pub fn import_trade_file(
+    db: &mut Database,
+    path: &Path,
+    profile: &TradeProfile,
+) -> Result<TradeImport, StorageError> {
+    let bytes = std::fs::read(path)?;
+    let decoded = decode(&bytes, profile.encoding.as_deref()).map_err(StorageError::InvalidData)?;
+    let parsed = profile.parse_csv(&decoded.text);
+    let batch = ImportBatch::new(ImportSource::Trades).with_file_name(file_name(path));
+    let mut result = db.import_trades(profile, &parsed.trades, &batch)?;
+    result.errors.splice(0..0, parsed.errors);
+    Ok(result)
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string()
+}
+
+/// 解析金额，支持千分位、货币符号和会计格式的括号负数
+pub(crate) fn parse_amount(s: &str) -> Option<f64> {
+    let s = s.trim();
+    let (s, negative) = match s.strip_prefix('(').and_then(|s| s.strip_suffix(')')) {
+        Some(inner) => (inner, true),
+        None => (s, false),
+    };
+    let cleaned: String = s
+        .chars()
+        .filter(|c| !matches!(c, ',' | ' ' | '¥' | '￥' | '$' | '€' | '£'))
+        .collect();
+    if cleaned.is_empty() {
+        return None;
+    }
+    let value: f64 = cleaned.parse().ok()?;
+    Some(if negative { -value } else { value })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_amount() {
+        assert_eq!(parse_amount("1,234.50"), Some(1234.5));
+        assert_eq!(parse_amount("¥-20"), Some(-20.0));
+        assert_eq!(parse_amount("(12.00)"), Some(-12.0));
+        assert_eq!(parse_amount(" "), None);
+        assert_eq!(
+            StatementFormat::from_path(Path::new("a/b.QFX")),
+            Some(StatementFormat::Ofx)
+        );
+    }
+
+    #[test]
+    fn test_sniff_gbk_semicolon_file() {
+        let path = std::env::temp_dir().join(format!("asset-manager-{}.csv", Uuid::new_v4()));
+        let (bytes, _, _) = encoding_rs::GBK.encode("交易日期;摘要;金额\n2024-01-05;工资;8000\n");
+        std::fs::write(&path, &bytes).unwrap();
+
+        let sniff = sniff_file(&path, None).unwrap();
+        assert_eq!(sniff.encoding, "gb18030");
+        assert_eq!(sniff.delimiter, Some(';'));
+        assert_eq!(sniff.rows[0], vec!["交易日期", "摘要", "金额"]);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,96 @@
+//! OFX/QFX 对账单解析
+//!
+//! 同时兼容 SGML 格式的 OFX 1.x（元素无结束标签）和 XML 格式的 OFX 2.x，
+//! 只读取 `<STMTTRN>` 交易块。
+
+use super::{parse_amount, ParsedStatement, StatementLine};
+use chrono::NaiveDate;
+
+/// 解析 OFX 对账单
+pub fn parse_ofx(content: &str) -> ParsedStatement {
+    let mut result = ParsedStatement::default();
+    let mut rest = content;
+    let mut index = 0;
+
+    while let Some(start) = find_tag(rest, "<STMTTRN>") {
+        let body = &rest[start + "<STMTTRN>".len()..];
+        let end = find_tag(body, "</STMTTRN>").unwrap_or(body.len());
+        index += 1;
+        match parse_transaction(&body[..end]) {
+            Ok(line) => result.lines.push(line),
+            Err(e) => result.errors.push(format!("Transaction {}: {}", index, e)),
+        }
+        rest = &body[end..];
+    }
+    result
+}
+
+fn parse_transaction(block: &str) -> Result<StatementLine, String> {
+    let posted = element(block, "DTPOSTED").ok_or("missing DTPOSTED")?;
+    let date = posted
+        .get(..8)
+        .and_then(|d| NaiveDate::parse_from_str(d, "%Y%m%d").ok())
+        .and_then(|d| d.and_hms_opt(0, 0, 0))
+        .ok_or_else(|| format!("invalid DTPOSTED: {}", posted))?
+        .and_utc();
+    let amount_text = element(block, "TRNAMT").ok_or("missing TRNAMT")?;
+    let amount =
+        parse_amount(&amount_text).ok_or_else(|| format!("invalid TRNAMT: {}", amount_text))?;
+
+    let description = match (element(block, "NAME"), element(block, "MEMO")) {
+        (Some(name), Some(memo)) if name != memo => Some(format!("{} {}", name, memo)),
+        (Some(name), _) => Some(name),
+        (None, memo) => memo,
+    };
+
+    Ok(StatementLine {
+        date,
+        amount,
+        description,
+        reference: element(block, "FITID"),
+    })
+}
+
+/// 读取元素值：取开始标签之后、下一个标签或换行之前的文本
+fn element(block: &str, name: &str) -> Option<String> {
+    let open = format!("<{}>", name);
+    let start = find_tag(block, &open)? + open.len();
+    let value = &block[start..];
+    let end = value.find(['<', '\n', '\r']).unwrap_or(value.len());
+    let value = value[..end].trim();
+    (!value.is_empty()).then(|| decode(value))
+}
+
+/// 不区分大小写地查找标签
+fn find_tag(haystack: &str, tag: &str) -> Option<usize> {
+    haystack.to_ascii_uppercase().find(tag)
+}
+
+fn decode(s: &str) -> String {
+    s.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_sgml_and_xml() {
+        let sgml = "OFXHEADER:100\n<OFX><BANKTRANLIST>\n<STMTTRN>\n<TRNTYPE>DEBIT\n<DTPOSTED>20240105120000[-5:EST]\n<TRNAMT>-42.10\n<FITID>A1\n<NAME>Coffee &amp; Co\n</STMTTRN>\n<STMTTRN>\n<DTPOSTED>bad\n<TRNAMT>1\n</STMTTRN>\n</BANKTRANLIST></OFX>";
+        let parsed = parse_ofx(sgml);
+        assert_eq!(parsed.lines.len(), 1);
+        assert_eq!(parsed.lines[0].amount, -42.1);
+        assert_eq!(parsed.lines[0].reference.as_deref(), Some("A1"));
+        assert_eq!(parsed.lines[0].description.as_deref(), Some("Coffee & Co"));
+        assert_eq!(parsed.errors.len(), 1);
+
+        let xml = "<OFX><stmttrn><DTPOSTED>20240201</DTPOSTED><TRNAMT>100.00</TRNAMT><MEMO>Salary</MEMO></stmttrn></OFX>";
+        let parsed = parse_ofx(xml);
+        assert_eq!(parsed.lines.len(), 1);
+        assert_eq!(parsed.lines[0].description.as_deref(), Some("Salary"));
+    }
+}
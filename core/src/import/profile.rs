@@ -0,0 +1,208 @@
+//! CSV 列映射配置
+
+use super::{parse_amount, ParsedStatement, StatementLine};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+pub(super) fn default_true() -> bool {
+    true
+}
+
+pub(super) fn default_date_format() -> String {
+    "%Y-%m-%d".to_string()
+}
+
+/// CSV 映射配置：说明对账单各列的含义及导入到哪个资产
+///
+/// 列可用表头名称或从 0 开始的列号指定。金额可为单列（带符号），
+/// 也可拆成支出、收入两列。
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MappingProfile {
+    /// 唯一标识符（新建时可省略）
+    #[serde(default = "Uuid::new_v4")]
+    pub id: Uuid,
+    /// 配置名称（如"招商银行储蓄卡"）
+    pub name: String,
+    /// 导入的目标资产
+    pub asset_id: Uuid,
+    /// 分隔符，为空时自动识别
+    #[serde(default)]
+    pub delimiter: Option<char>,
+    /// 文件编码（如 "GBK"、"UTF-16LE"），为空时自动识别
+    #[serde(default)]
+    pub encoding: Option<String>,
+    /// 首行是否为表头
+    #[serde(default = "default_true")]
+    pub has_header: bool,
+    /// 表头之前需要跳过的行数（银行导出常带说明行）
+    #[serde(default)]
+    pub skip_rows: usize,
+    /// 日期列
+    pub date_column: String,
+    /// 日期格式（chrono 格式串）
+    #[serde(default = "default_date_format")]
+    pub date_format: String,
+    /// 带符号的金额列
+    #[serde(default)]
+    pub amount_column: Option<String>,
+    /// 支出列（正数表示流出）
+    #[serde(default)]
+    pub debit_column: Option<String>,
+    /// 收入列
+    #[serde(default)]
+    pub credit_column: Option<String>,
+    /// 摘要列
+    #[serde(default)]
+    pub description_column: Option<String>,
+    /// 流水号列
+    #[serde(default)]
+    pub reference_column: Option<String>,
+    /// 金额取反（如信用卡账单以消费为正）
+    #[serde(default)]
+    pub negate_amounts: bool,
+}
+
+impl MappingProfile {
+    /// 创建使用单一金额列的配置
+    pub fn new(
+        name: impl Into<String>,
+        asset_id: Uuid,
+        date_column: impl Into<String>,
+        amount_column: impl Into<String>,
+    ) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            name: name.into(),
+            asset_id,
+            delimiter: None,
+            encoding: None,
+            has_header: true,
+            skip_rows: 0,
+            date_column: date_column.into(),
+            date_format: default_date_format(),
+            amount_column: Some(amount_column.into()),
+            debit_column: None,
+            credit_column: None,
+            description_column: None,
+            reference_column: None,
+            negate_amounts: false,
+        }
+    }
+
+    /// 校验配置
+    pub fn validate(&self) -> Result<(), String> {
+        if self.amount_column.is_none()
+            && self.debit_column.is_none()
+            && self.credit_column.is_none()
+        {
+            return Err("An amount, debit or credit column is required".to_string());
+        }
+        Ok(())
+    }
+
+    /// 按配置解析 CSV 对账单
+    pub fn parse_csv(&self, content: &str) -> ParsedStatement {
+        let delimiter = self
+            .delimiter
+            .unwrap_or_else(|| crate::csv::detect_delimiter(content));
+        let mut rows = crate::csv::parse(content, delimiter)
+            .into_iter()
+            .enumerate()
+            .skip(self.skip_rows);
+        let headers: Vec<String> = if self.has_header {
+            rows.next()
+                .map(|(_, row)| row.iter().map(|h| h.trim().to_lowercase()).collect())
+                .unwrap_or_default()
+        } else {
+            Vec::new()
+        };
+
+        let mut result = ParsedStatement::default();
+        for (index, row) in rows {
+            match self.parse_row(&headers, &row) {
+                Ok(line) => result.lines.push(line),
+                Err(e) => result.errors.push(format!("Line {}: {}", index + 1, e)),
+            }
+        }
+        result
+    }
+
+    fn parse_row(&self, headers: &[String], row: &[String]) -> Result<StatementLine, String> {
+        let cell = |column: Option<&str>| -> Option<&str> {
+            let index = column_index(headers, column?)?;
+            row.get(index).map(|s| s.trim()).filter(|s| !s.is_empty())
+        };
+
+        let date_text = cell(Some(&self.date_column)).ok_or("missing date")?;
+        let date = parse_date(date_text, &self.date_format)
+            .ok_or_else(|| format!("invalid date: {}", date_text))?;
+
+        let mut amount = match cell(self.amount_column.as_deref()) {
+            Some(text) => parse_amount(text).ok_or_else(|| format!("invalid amount: {}", text))?,
+            None => {
+                let debit = cell(self.debit_column.as_deref()).and_then(parse_amount);
+                let credit = cell(self.credit_column.as_deref()).and_then(parse_amount);
+                if debit.is_none() && credit.is_none() {
+                    return Err("missing amount".to_string());
+                }
+                credit.unwrap_or(0.0) - debit.unwrap_or(0.0).abs()
+            }
+        };
+        if self.negate_amounts {
+            amount = -amount;
+        }
+
+        Ok(StatementLine {
+            date,
+            amount,
+            description: cell(self.description_column.as_deref()).map(str::to_string),
+            reference: cell(self.reference_column.as_deref()).map(str::to_string),
+        })
+    }
+}
+
+/// 按表头名称（不区分大小写）或列号查找列
+pub(super) fn column_index(headers: &[String], column: &str) -> Option<usize> {
+    let key = column.trim().to_lowercase();
+    headers
+        .iter()
+        .position(|h| *h == key)
+        .or_else(|| key.parse().ok())
+}
+
+/// 解析日期，格式不含时间时取当日零点（UTC）
+pub(super) fn parse_date(text: &str, format: &str) -> Option<DateTime<Utc>> {
+    if let Ok(dt) = NaiveDateTime::parse_from_str(text, format) {
+        return Some(dt.and_utc());
+    }
+    NaiveDate::parse_from_str(text, format)
+        .ok()
+        .and_then(|d| d.and_hms_opt(0, 0, 0))
+        .map(|dt| dt.and_utc())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_csv() {
+        let mut profile = MappingProfile::new("银行", Uuid::new_v4(), "交易日期", "");
+        profile.amount_column = None;
+        profile.debit_column = Some("支出".to_string());
+        profile.credit_column = Some("收入".to_string());
+        profile.description_column = Some("摘要".to_string());
+        profile.date_format = "%Y/%m/%d".to_string();
+        profile.skip_rows = 1;
+
+        let content = "账户明细\n交易日期,摘要,支出,收入\n2024/01/05,工资,,\"8,000.00\"\n2024/01/06,超市,120.5,\nbad,坏行,1,\n";
+        let parsed = profile.parse_csv(content);
+        assert_eq!(parsed.lines.len(), 2);
+        assert_eq!(parsed.lines[0].amount, 8000.0);
+        assert_eq!(parsed.lines[1].amount, -120.5);
+        assert_eq!(parsed.lines[1].description.as_deref(), Some("超市"));
+        assert_eq!(parsed.errors.len(), 1);
+        assert!(parsed.errors[0].starts_with("Line 5"));
+    }
+}
@@ -0,0 +1,444 @@
+//! 券商成交单导入：一次导入建立或更新按批次跟踪的持仓，并记录买入、卖出交易
+//!
+//! 成交按代码匹配资产：先按配置中的匹配规则（代码相同，或以 `*` 结尾的前缀），再找证券代码
+//! 相同的已有资产，都没有时按配置的资产类型新建。买入追加一批 `{ 数量, price }`，卖出按
+//! 先进先出减少批次（资产记录的是数量字段时直接增减数量）。资产价值按成交金额增减，
+//! 指定资金账户时成交金额从中划转，手续费记为关联到成交的支出。
+
+use super::profile::{column_index, default_date_format, default_true, parse_date};
+use super::{fnv1a64, parse_amount};
+use crate::asset::{holding_quantity, holding_symbol, quantity_key, symbol_key, Asset, AssetType};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// 小于此值的数量视为零
+const QUANTITY_EPSILON: f64 = 1e-9;
+
+fn default_asset_type() -> AssetType {
+    AssetType::Stock
+}
+
+/// 买卖方向
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TradeSide {
+    Buy,
+    Sell,
+}
+
+impl TradeSide {
+    /// 识别成交单中的方向（如 `Buy`、`S`、`买入`、`证券卖出`）
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim().to_lowercase();
+        match text.as_str() {
+            "buy" | "b" | "bot" => Some(TradeSide::Buy),
+            "sell" | "s" | "sld" => Some(TradeSide::Sell),
+            _ if text.contains('买') => Some(TradeSide::Buy),
+            _ if text.contains('卖') => Some(TradeSide::Sell),
+            _ => None,
+        }
+    }
+}
+
+/// 一笔成交
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TradeLine {
+    pub date: DateTime<Utc>,
+    pub symbol: String,
+    pub side: TradeSide,
+    /// 成交数量（正数）
+    pub quantity: f64,
+    /// 成交价
+    pub price: f64,
+    #[serde(default)]
+    pub fee: f64,
+    /// 成交编号
+    #[serde(default)]
+    pub reference: Option<String>,
+}
+
+impl TradeLine {
+    /// 成交金额（不含费用）
+    pub fn amount(&self) -> f64 {
+        self.quantity * self.price
+    }
+}
+
+/// 代码到资产的匹配规则
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SymbolRule {
+    /// 代码（不区分大小写），以 `*` 结尾时按前缀匹配
+    pub pattern: String,
+    pub asset_id: Uuid,
+}
+
+impl SymbolRule {
+    pub fn matches(&self, symbol: &str) -> bool {
+        let pattern = self.pattern.trim().to_uppercase();
+        let symbol = symbol.trim().to_uppercase();
+        match pattern.strip_suffix('*') {
+            Some(prefix) => symbol.starts_with(prefix),
+            None => symbol == pattern,
+        }
+    }
+}
+
+/// 成交单映射配置：说明各列的含义、新建资产的类型与资金账户
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TradeProfile {
+    /// 唯一标识符（新建时可省略）
+    #[serde(default = "Uuid::new_v4")]
+    pub id: Uuid,
+    /// 配置名称（如"华泰证券成交单"）
+    pub name: String,
+    /// 分隔符，为空时自动识别
+    #[serde(default)]
+    pub delimiter: Option<char>,
+    /// 文件编码，为空时自动识别
+    #[serde(default)]
+    pub encoding: Option<String>,
+    /// 首行是否为表头
+    #[serde(default = "default_true")]
+    pub has_header: bool,
+    /// 表头之前需要跳过的行数
+    #[serde(default)]
+    pub skip_rows: usize,
+    pub date_column: String,
+    #[serde(default = "default_date_format")]
+    pub date_format: String,
+    pub symbol_column: String,
+    /// 方向列，为空时按数量的正负区分买卖
+    #[serde(default)]
+    pub side_column: Option<String>,
+    pub quantity_column: String,
+    pub price_column: String,
+    #[serde(default)]
+    pub fee_column: Option<String>,
+    /// 成交编号列
+    #[serde(default)]
+    pub reference_column: Option<String>,
+    /// 未匹配到资产时新建的资产类型
+    #[serde(default = "default_asset_type")]
+    pub asset_type: AssetType,
+    /// 资金账户（如证券账户的现金），为空时成交金额不在账内划转
+    #[serde(default)]
+    pub cash_account: Option<Uuid>,
+    /// 代码匹配规则，按顺序取第一条匹配的
+    #[serde(default)]
+    pub rules: Vec<SymbolRule>,
+}
+
+/// 解析结果：成功的成交与出错的行
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ParsedTrades {
+    pub trades: Vec<TradeLine>,
+    pub errors: Vec<String>,
+}
+
+/// 成交单导入结果
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TradeImport {
+    /// 新建的资产
+    pub created_assets: Vec<Uuid>,
+    /// 更新了持仓的资产（含新建的）
+    pub updated_assets: Vec<Uuid>,
+    /// 导入的成交数量
+    pub imported: usize,
+    /// 因已导入而跳过的成交数量
+    pub duplicates: usize,
+    /// 无法解析或无法入账的行
+    pub errors: Vec<String>,
+    /// 导入批次，可用于撤销本次导入
+    pub batch_id: Option<Uuid>,
+}
+
+impl TradeProfile {
+    /// 创建配置
+    pub fn new(
+        name: impl Into<String>,
+        date_column: impl Into<String>,
+        symbol_column: impl Into<String>,
+        quantity_column: impl Into<String>,
+        price_column: impl Into<String>,
+    ) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            name: name.into(),
+            delimiter: None,
+            encoding: None,
+            has_header: true,
+            skip_rows: 0,
+            date_column: date_column.into(),
+            date_format: default_date_format(),
+            symbol_column: symbol_column.into(),
+            side_column: None,
+            quantity_column: quantity_column.into(),
+            price_column: price_column.into(),
+            fee_column: None,
+            reference_column: None,
+            asset_type: default_asset_type(),
+            cash_account: None,
+            rules: Vec::new(),
+        }
+    }
+
+    /// 校验配置
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("Profile name is required".to_string());
+        }
+        if let Some(rule) = self.rules.iter().find(|r| r.pattern.trim().is_empty()) {
+            return Err(format!("Empty symbol pattern for asset {}", rule.asset_id));
+        }
+        Ok(())
+    }
+
+    /// 匹配规则指定的资产
+    pub fn rule_for(&self, symbol: &str) -> Option<Uuid> {
+        self.rules
+            .iter()
+            .find(|r| r.matches(symbol))
+            .map(|r| r.asset_id)
+    }
+
+    /// 按配置解析 CSV 成交单
+    pub fn parse_csv(&self, content: &str) -> ParsedTrades {
+        let delimiter = self
+            .delimiter
+            .unwrap_or_else(|| crate::csv::detect_delimiter(content));
+        let mut rows = crate::csv::parse(content, delimiter)
+            .into_iter()
+            .enumerate()
+            .skip(self.skip_rows);
+        let headers: Vec<String> = if self.has_header {
+            rows.next()
+                .map(|(_, row)| row.iter().map(|h| h.trim().to_lowercase()).collect())
+                .unwrap_or_default()
+        } else {
+            Vec::new()
+        };
+
+        let mut result = ParsedTrades::default();
+        for (index, row) in rows {
+            if row.iter().all(|cell| cell.trim().is_empty()) {
+                continue;
+            }
+            match self.parse_row(&headers, &row) {
+                Ok(trade) => result.trades.push(trade),
+                Err(e) => result.errors.push(format!("Line {}: {}", index + 1, e)),
+            }
+        }
+        result
+    }
+
+    fn parse_row(&self, headers: &[String], row: &[String]) -> Result<TradeLine, String> {
+        let cell = |column: Option<&str>| -> Option<&str> {
+            let index = column_index(headers, column?)?;
+            row.get(index).map(|s| s.trim()).filter(|s| !s.is_empty())
+        };
+        let number = |column: Option<&str>, label: &str| -> Result<Option<f64>, String> {
+            cell(column)
+                .map(|text| {
+                    parse_amount(text).ok_or_else(|| format!("invalid {}: {}", label, text))
+                })
+                .transpose()
+        };
+
+        let date_text = cell(Some(&self.date_column)).ok_or("missing date")?;
+        let date = parse_date(date_text, &self.date_format)
+            .ok_or_else(|| format!("invalid date: {}", date_text))?;
+        let symbol = cell(Some(&self.symbol_column))
+            .ok_or("missing symbol")?
+            .to_uppercase();
+        let quantity =
+            number(Some(&self.quantity_column), "quantity")?.ok_or("missing quantity")?;
+        let price = number(Some(&self.price_column), "price")?.ok_or("missing price")?;
+        let side = match cell(self.side_column.as_deref()) {
+            Some(text) => {
+                TradeSide::parse(text).ok_or_else(|| format!("invalid side: {}", text))?
+            }
+            None if self.side_column.is_some() => return Err("missing side".to_string()),
+            None if quantity < 0.0 => TradeSide::Sell,
+            None => TradeSide::Buy,
+        };
+        if quantity == 0.0 || !(price.is_finite() && price >= 0.0) {
+            return Err(format!("invalid trade: {} @ {}", quantity, price));
+        }
+
+        Ok(TradeLine {
+            date,
+            symbol,
+            side,
+            quantity: quantity.abs(),
+            price,
+            fee: number(self.fee_column.as_deref(), "fee")?
+                .unwrap_or(0.0)
+                .abs(),
+            reference: cell(self.reference_column.as_deref()).map(str::to_string),
+        })
+    }
+}
+
+/// 计算每笔成交的指纹（与输入顺序一致），完全相同的成交按出现次序区分
+pub fn trade_hashes(trades: &[TradeLine]) -> Vec<String> {
+    let mut occurrences: HashMap<String, usize> = HashMap::new();
+    trades
+        .iter()
+        .map(|trade| {
+            let key = format!(
+                "trade|{}|{}|{:?}|{}|{}|{}",
+                trade.date.format("%Y-%m-%d"),
+                trade.symbol,
+                trade.side,
+                trade.quantity,
+                trade.price,
+                trade.reference.as_deref().unwrap_or("").trim(),
+            );
+            let occurrence = occurrences.entry(key.clone()).or_insert(0);
+            *occurrence += 1;
+            format!(
+                "{:016x}",
+                fnv1a64(format!("{}#{}", key, occurrence).as_bytes())
+            )
+        })
+        .collect()
+}
+
+/// 证券代码相同的已有资产（不区分大小写）
+pub fn find_holding<'a>(assets: impl IntoIterator<Item = &'a Asset>, symbol: &str) -> Option<Uuid> {
+    assets
+        .into_iter()
+        .find(|a| holding_symbol(a).is_some_and(|s| s.trim().eq_ignore_ascii_case(symbol.trim())))
+        .map(|a| a.id)
+}
+
+/// 为未匹配到的代码新建按批次跟踪的资产
+pub fn new_holding(symbol: &str, asset_type: &AssetType) -> Asset {
+    Asset::new(symbol, asset_type.clone(), 0.0)
+        .with_metadata(json!({ symbol_key(asset_type): symbol, "lots": [] }))
+}
+
+/// 按成交更新持仓数量：买入追加一批，卖出按先进先出减少批次；卖出超过持仓时拒绝
+pub fn apply_trade(asset: &mut Asset, trade: &TradeLine) -> Result<(), String> {
+    let key = quantity_key(&asset.asset_type);
+    if trade.side == TradeSide::Sell {
+        let held = holding_quantity(asset).unwrap_or(0.0);
+        if trade.quantity > held + QUANTITY_EPSILON {
+            return Err(format!(
+                "{}: sell quantity {} exceeds holding {}",
+                trade.symbol, trade.quantity, held
+            ));
+        }
+    }
+    if !asset.metadata.is_object() {
+        asset.metadata = json!({});
+    }
+    // 记录的是数量字段时直接增减
+    if let Some(quantity) = asset.metadata.get(key).and_then(Value::as_f64) {
+        let change = match trade.side {
+            TradeSide::Buy => trade.quantity,
+            TradeSide::Sell => -trade.quantity,
+        };
+        asset.metadata[key] = (quantity + change).max(0.0).into();
+        return Ok(());
+    }
+
+    if !asset.metadata.get("lots").is_some_and(Value::is_array) {
+        asset.metadata["lots"] = json!([]);
+    }
+    let lots = asset.metadata["lots"]
+        .as_array_mut()
+        .expect("lots is an array");
+    match trade.side {
+        TradeSide::Buy => lots.push(json!({ key: trade.quantity, "price": trade.price })),
+        TradeSide::Sell => {
+            let mut remaining = trade.quantity;
+            lots.retain_mut(|lot| {
+                let held = lot.get(key).and_then(Value::as_f64).unwrap_or(0.0);
+                if remaining <= QUANTITY_EPSILON {
+                    return true;
+                }
+                if held <= remaining + QUANTITY_EPSILON {
+                    remaining -= held;
+                    return false;
+                }
+                lot[key] = (held - remaining).into();
+                remaining = 0.0;
+                true
+            });
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_trades() {
+        let mut profile = TradeProfile::new("券商", "成交日期", "证券代码", "成交数量", "成交价格");
+        profile.side_column = Some("买卖标志".to_string());
+        profile.fee_column = Some("佣金".to_string());
+        profile.reference_column = Some("成交编号".to_string());
+
+        let content = "成交日期,证券代码,买卖标志,成交数量,成交价格,佣金,成交编号\n\
+                       2024-03-01,aapl,买入,10,170.5,1.2,T1\n\
+                       2024-03-05,AAPL,证券卖出,4,180,1,T2\n\
+                       2024-03-06,AAPL,撤单,4,180,1,T3\n";
+        let parsed = profile.parse_csv(content);
+        assert_eq!(parsed.trades.len(), 2);
+        assert_eq!(parsed.trades[0].symbol, "AAPL");
+        assert_eq!(parsed.trades[0].side, TradeSide::Buy);
+        assert_eq!(parsed.trades[1].side, TradeSide::Sell);
+        assert_eq!(parsed.trades[1].amount(), 720.0);
+        assert_eq!(parsed.errors.len(), 1);
+        assert!(parsed.errors[0].starts_with("Line 4"));
+
+        // 重复的成交按出现次序区分
+        let hashes = trade_hashes(&[parsed.trades[0].clone(), parsed.trades[0].clone()]);
+        assert_ne!(hashes[0], hashes[1]);
+        assert_eq!(hashes[0], trade_hashes(&parsed.trades)[0]);
+
+        profile.rules.push(SymbolRule {
+            pattern: "00*".to_string(),
+            asset_id: Uuid::nil(),
+        });
+        assert_eq!(profile.rule_for("000001"), Some(Uuid::nil()));
+        assert_eq!(profile.rule_for("600000"), None);
+    }
+
+    #[test]
+    fn test_apply_trade() {
+        let mut stock = new_holding("AAPL", &AssetType::Stock);
+        assert_eq!(find_holding([&stock], "aapl"), Some(stock.id));
+        let trade = |side, quantity, price| TradeLine {
+            date: Utc::now(),
+            symbol: "AAPL".to_string(),
+            side,
+            quantity,
+            price,
+            fee: 0.0,
+            reference: None,
+        };
+
+        apply_trade(&mut stock, &trade(TradeSide::Buy, 10.0, 100.0)).unwrap();
+        apply_trade(&mut stock, &trade(TradeSide::Buy, 5.0, 120.0)).unwrap();
+        apply_trade(&mut stock, &trade(TradeSide::Sell, 12.0, 130.0)).unwrap();
+        // 先卖出第一批，第二批剩 3 股
+        assert_eq!(
+            stock.metadata["lots"],
+            json!([{ "shares": 3.0, "price": 120.0 }])
+        );
+        assert!(apply_trade(&mut stock, &trade(TradeSide::Sell, 5.0, 130.0)).is_err());
+
+        // 记录数量字段的资产直接增减
+        let mut fund =
+            Asset::new("基金", AssetType::Fund, 0.0).with_metadata(json!({ "units": 100 }));
+        apply_trade(&mut fund, &trade(TradeSide::Sell, 40.0, 1.0)).unwrap();
+        assert_eq!(fund.metadata["units"], 60.0);
+    }
+}
@@ -0,0 +1,211 @@
+//! 监视目录：自动导入放入的对账单并归档
+
+use super::{import_file, ImportResult, StatementFormat};
+use crate::notification::{Notification, NotificationKind};
+use crate::storage::{Database, StorageError};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+/// 监视目录的默认轮询间隔（秒）
+pub const WATCH_INTERVAL_SECS: u64 = 60;
+
+fn default_true() -> bool {
+    true
+}
+
+/// 监视目录配置
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct WatchFolder {
+    /// 唯一标识符
+    pub id: Uuid,
+    /// 监视的目录
+    pub path: PathBuf,
+    /// 绑定的映射配置
+    pub profile_id: Uuid,
+    /// 归档目录，默认为监视目录下的 `archive`
+    #[serde(default)]
+    pub archive_dir: Option<PathBuf>,
+    /// 是否启用
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+}
+
+impl WatchFolder {
+    /// 创建监视目录配置
+    pub fn new(path: impl Into<PathBuf>, profile_id: Uuid) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            path: path.into(),
+            profile_id,
+            archive_dir: None,
+            enabled: true,
+        }
+    }
+
+    /// 归档目录
+    pub fn archive_path(&self) -> PathBuf {
+        self.archive_dir
+            .clone()
+            .unwrap_or_else(|| self.path.join("archive"))
+    }
+
+    /// 目录中待导入的对账单（不含子目录，按文件名排序）
+    pub fn pending_files(&self) -> std::io::Result<Vec<PathBuf>> {
+        let mut files: Vec<PathBuf> = fs::read_dir(&self.path)?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.is_file() && StatementFormat::from_path(path).is_some())
+            .collect();
+        files.sort();
+        Ok(files)
+    }
+
+    /// 将文件移入归档目录，重名时加上时间前缀
+    pub fn archive(&self, file: &Path) -> std::io::Result<PathBuf> {
+        let dir = self.archive_path();
+        fs::create_dir_all(&dir)?;
+        let name = file.file_name().unwrap_or_default().to_string_lossy();
+        let mut target = dir.join(name.as_ref());
+        if target.exists() {
+            let stamp = Utc::now().format("%Y%m%d%H%M%S");
+            target = dir.join(format!("{}_{}", stamp, name));
+        }
+        fs::rename(file, &target)?;
+        Ok(target)
+    }
+}
+
+/// 扫描所有启用的监视目录，导入新文件、归档并发出通知
+///
+/// 导入失败的文件保留在原处，下次扫描时重试，失败原因同样以通知发出。
+pub fn scan_watch_folders(db: &mut Database) -> Result<Vec<ImportResult>, StorageError> {
+    let mut results = Vec::new();
+
+    for folder in db.list_watch_folders()? {
+        if !folder.enabled {
+            continue;
+        }
+        let Some(profile) = db.get_import_profile(folder.profile_id)? else {
+            warn!("Watch folder {:?} has no mapping profile", folder.path);
+            continue;
+        };
+        let files = match folder.pending_files() {
+            Ok(files) => files,
+            Err(e) => {
+                warn!("Failed to read watch folder {:?}: {}", folder.path, e);
+                continue;
+            }
+        };
+
+        for file in files {
+            let name = file
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string();
+            let notification = match import_file(db, &file, &profile) {
+                Ok(mut result) => {
+                    match folder.archive(&file) {
+                        Ok(target) => result.archived_to = Some(target.display().to_string()),
+                        Err(e) => warn!("Failed to archive {:?}: {}", file, e),
+                    }
+                    info!("Imported {} transactions from {:?}", result.imported, file);
+                    let mut message = if result.duplicate_file {
+                        "该文件此前已导入，已跳过".to_string()
+                    } else {
+                        format!("导入 {} 笔交易", result.imported)
+                    };
+                    if result.duplicates > 0 && !result.duplicate_file {
+                        message.push_str(&format!("，跳过 {} 笔重复流水", result.duplicates));
+                    }
+                    if !result.errors.is_empty() {
+                        message.push_str(&format!("，{} 行无法解析", result.errors.len()));
+                    }
+                    results.push(result);
+                    Notification::new(NotificationKind::Import, format!("已导入 {}", name))
+                        .with_message(message)
+                        .with_asset(Some(profile.asset_id))
+                }
+                Err(e) => {
+                    warn!("Failed to import {:?}: {}", file, e);
+                    Notification::new(NotificationKind::Import, format!("导入 {} 失败", name))
+                        .with_message(e.to_string())
+                        .with_asset(Some(profile.asset_id))
+                }
+            };
+            db.add_notification(&notification)?;
+        }
+    }
+
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asset::{Asset, AssetType};
+    use crate::import::MappingProfile;
+
+    #[test]
+    fn test_scan_watch_folder() {
+        let dir = std::env::temp_dir().join(format!("asset-manager-watch-{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("jan.csv"),
+            "date,amount,memo\n2024-01-05,8000,工资\n2024-01-06,-120.5,超市\n",
+        )
+        .unwrap();
+        fs::write(dir.join("notes.md"), "ignored").unwrap();
+
+        let mut db = Database::open_in_memory().unwrap();
+        let card = Asset::new("储蓄卡", AssetType::BankDeposit, 1000.0);
+        db.create_asset(&card).unwrap();
+        let mut profile = MappingProfile::new("银行", card.id, "date", "amount");
+        profile.description_column = Some("memo".to_string());
+        db.save_import_profile(&profile).unwrap();
+        db.add_watch_folder(&WatchFolder::new(&dir, profile.id))
+            .unwrap();
+
+        let results = scan_watch_folders(&mut db).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].imported, 2);
+        assert_eq!(db.get_asset(card.id).unwrap().unwrap().value, 8879.5);
+        assert!(dir.join("archive").join("jan.csv").exists());
+        assert!(!dir.join("jan.csv").exists());
+        assert_eq!(db.list_notifications(true).unwrap().len(), 1);
+
+        assert!(scan_watch_folders(&mut db).unwrap().is_empty());
+
+        // 同一文件再次放入，以及日期重叠的新对账单
+        fs::copy(
+            dir.join("archive").join("jan.csv"),
+            dir.join("jan-again.csv"),
+        )
+        .unwrap();
+        fs::write(
+            dir.join("overlap.csv"),
+            "date,amount,memo\n2024-01-06,-120.5,超市\n2024-01-07,-30,地铁\n",
+        )
+        .unwrap();
+        let results = scan_watch_folders(&mut db).unwrap();
+        assert!(results[0].duplicate_file);
+        assert_eq!((results[1].imported, results[1].duplicates), (1, 1));
+        assert_eq!(db.get_asset(card.id).unwrap().unwrap().value, 8849.5);
+
+        // 撤销最近一次导入后，同一文件可重新导入
+        let batches = db.list_import_batches().unwrap();
+        assert_eq!(batches.len(), 2);
+        assert_eq!(batches[0].batch.id, results[1].batch_id.unwrap());
+        assert_eq!(batches[0].transactions, 1);
+        let undo = db.undo_import(batches[0].batch.id).unwrap();
+        assert_eq!(undo.transactions, 1);
+        assert_eq!(db.get_asset(card.id).unwrap().unwrap().value, 8879.5);
+        let archived = dir.join("archive").join("overlap.csv");
+        fs::rename(&archived, dir.join("overlap.csv")).unwrap();
+        assert_eq!(scan_watch_folders(&mut db).unwrap()[0].imported, 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,91 @@
+//! 由单式记录迁移到复式账簿
+
+use super::{AccountKind, Ledger, LedgerEntry, BALANCE_EPSILON, OPENING_BALANCE_ACCOUNT};
+use crate::asset::{Asset, AssetTransaction};
+
+/// 由现有资产和交易生成账簿
+///
+/// - 第一笔交易前的价值记为期初余额
+/// - 每笔交易按类型生成对应分录
+/// - 未记录交易的直接修改记为估值变动，保证资产账户余额与当前价值一致
+pub fn migrate(assets: &[Asset], transactions: &[AssetTransaction]) -> Ledger {
+    let mut ledger = Ledger::default();
+
+    for asset in assets.iter().filter(|a| !a.is_virtual()) {
+        let account = ledger.asset_account(asset);
+        let mut txns: Vec<&AssetTransaction> = transactions
+            .iter()
+            .filter(|t| t.asset_id == asset.id && !t.scheduled)
+            .collect();
+        txns.sort_by_key(|t| t.timestamp);
+
+        let opening = txns.first().map(|t| t.amount_before).unwrap_or(asset.value);
+        if opening.abs() > BALANCE_EPSILON {
+            let equity = ledger.named_account(OPENING_BALANCE_ACCOUNT, AccountKind::Equity);
+            ledger.entries.push(
+                LedgerEntry::new(OPENING_BALANCE_ACCOUNT)
+                    .with_timestamp(asset.created_at)
+                    .with_posting(account, opening)
+                    .with_posting(equity, -opening),
+            );
+        }
+
+        for txn in txns {
+            ledger.record_transaction(txn, asset);
+        }
+
+        ledger.sync_asset(asset);
+    }
+
+    ledger
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asset::{AssetType, TransactionType};
+    use crate::ledger::{LedgerAccount, LedgerError};
+
+    #[test]
+    fn test_migrate_and_validate() {
+        let cash = Asset::new("现金", AssetType::Cash, 1200.0);
+        let mut stock = Asset::new("股票", AssetType::Stock, 5000.0);
+        stock.value = 5500.0;
+        let txns = vec![
+            AssetTransaction::new(cash.id, TransactionType::Income, 1000.0, 1300.0),
+            AssetTransaction::new(cash.id, TransactionType::Expense, 1300.0, 1200.0),
+        ];
+
+        let mut ledger = migrate(&[cash.clone(), stock.clone()], &txns);
+        let trial = ledger.trial_balance();
+        assert!(trial.balanced);
+        assert_eq!(
+            ledger.balance(ledger.account_for_asset(cash.id).unwrap().id),
+            1200.0
+        );
+        assert_eq!(
+            ledger.balance(ledger.account_for_asset(stock.id).unwrap().id),
+            5500.0
+        );
+
+        let cash_account = ledger.account_for_asset(cash.id).unwrap().id;
+        let rent = LedgerAccount::new("房租", AccountKind::Expense);
+        let rent_id = rent.id;
+        ledger.add_account(rent).unwrap();
+
+        let unbalanced = LedgerEntry::new("房租")
+            .with_posting(rent_id, 500.0)
+            .with_posting(cash_account, -400.0);
+        assert!(matches!(
+            ledger.post(unbalanced),
+            Err(LedgerError::Unbalanced(_))
+        ));
+
+        let entry = LedgerEntry::new("房租")
+            .with_posting(rent_id, 500.0)
+            .with_posting(cash_account, -500.0);
+        ledger.post(entry).unwrap();
+        assert!(ledger.trial_balance().balanced);
+        assert_eq!(ledger.balance(cash_account), 700.0);
+    }
+}
@@ -0,0 +1,345 @@
+//! 复式记账（可选）
+//!
+//! 启用后每笔交易都记录为借贷平衡的分录：借方为正数，贷方为负数，所有分录行之和必须为零。
+//! 资产账户与资产一一对应，单式交易会自动镜像为分录。
+
+mod migrate;
+
+pub use migrate::*;
+
+use crate::asset::{Asset, AssetTransaction, TransactionType};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// 借贷平衡的容差
+pub const BALANCE_EPSILON: f64 = 1e-6;
+
+/// 期初余额账户
+pub const OPENING_BALANCE_ACCOUNT: &str = "期初余额";
+/// 资金投入/取出（买入、卖出、转账）的对方账户
+pub const CONTRIBUTION_ACCOUNT: &str = "资金往来";
+/// 收益的对方账户
+pub const INCOME_ACCOUNT: &str = "收入";
+/// 支出的对方账户
+pub const EXPENSE_ACCOUNT: &str = "支出";
+/// 价值变动的对方账户
+pub const VALUATION_ACCOUNT: &str = "估值变动";
+
+/// 账户类别
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AccountKind {
+    Asset,
+    Liability,
+    Equity,
+    Income,
+    Expense,
+}
+
+/// 账户
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LedgerAccount {
+    /// 唯一标识符
+    pub id: Uuid,
+    /// 账户名称
+    pub name: String,
+    /// 账户类别
+    pub kind: AccountKind,
+    /// 对应的资产ID
+    pub asset_id: Option<Uuid>,
+}
+
+impl LedgerAccount {
+    /// 创建账户
+    pub fn new(name: impl Into<String>, kind: AccountKind) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            name: name.into(),
+            kind,
+            asset_id: None,
+        }
+    }
+
+    /// 创建与资产对应的账户
+    pub fn for_asset(asset: &Asset) -> Self {
+        Self {
+            asset_id: Some(asset.id),
+            ..Self::new(asset.name.clone(), AccountKind::Asset)
+        }
+    }
+}
+
+/// 分录行
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Posting {
+    pub account_id: Uuid,
+    /// 金额（借方为正，贷方为负）
+    pub amount: f64,
+}
+
+/// 分录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LedgerEntry {
+    /// 唯一标识符
+    pub id: Uuid,
+    /// 记账时间
+    pub timestamp: DateTime<Utc>,
+    /// 摘要
+    pub description: String,
+    /// 分录行
+    pub postings: Vec<Posting>,
+    /// 由单式交易镜像而来时，对应的交易ID
+    #[serde(default)]
+    pub source_transaction_id: Option<Uuid>,
+}
+
+impl LedgerEntry {
+    /// 创建分录
+    pub fn new(description: impl Into<String>) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            timestamp: Utc::now(),
+            description: description.into(),
+            postings: Vec::new(),
+            source_transaction_id: None,
+        }
+    }
+
+    /// 添加分录行
+    pub fn with_posting(mut self, account_id: Uuid, amount: f64) -> Self {
+        self.postings.push(Posting { account_id, amount });
+        self
+    }
+
+    /// 设置记账时间
+    pub fn with_timestamp(mut self, timestamp: DateTime<Utc>) -> Self {
+        self.timestamp = timestamp;
+        self
+    }
+
+    /// 借贷差额
+    pub fn imbalance(&self) -> f64 {
+        self.postings.iter().map(|p| p.amount).sum()
+    }
+}
+
+/// 复式记账错误
+#[derive(Debug, thiserror::Error)]
+pub enum LedgerError {
+    #[error("Entry does not balance (off by {0})")]
+    Unbalanced(f64),
+
+    #[error("Entry needs at least two postings")]
+    TooFewPostings,
+
+    #[error("Unknown account: {0}")]
+    UnknownAccount(Uuid),
+
+    #[error("Account already exists: {0}")]
+    DuplicateAccount(String),
+}
+
+/// 试算平衡表中的一行
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrialBalanceRow {
+    pub account_id: Uuid,
+    pub name: String,
+    pub kind: AccountKind,
+    /// 借方余额
+    pub debit: f64,
+    /// 贷方余额
+    pub credit: f64,
+}
+
+/// 试算平衡表
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrialBalance {
+    pub rows: Vec<TrialBalanceRow>,
+    pub total_debit: f64,
+    pub total_credit: f64,
+    /// 借贷合计是否相等
+    pub balanced: bool,
+}
+
+/// 账簿
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Ledger {
+    pub accounts: Vec<LedgerAccount>,
+    pub entries: Vec<LedgerEntry>,
+}
+
+impl Ledger {
+    /// 添加账户（名称不可重复）
+    pub fn add_account(&mut self, account: LedgerAccount) -> Result<(), LedgerError> {
+        if self.accounts.iter().any(|a| a.name == account.name) {
+            return Err(LedgerError::DuplicateAccount(account.name));
+        }
+        self.accounts.push(account);
+        Ok(())
+    }
+
+    /// 获取账户
+    pub fn account(&self, id: Uuid) -> Option<&LedgerAccount> {
+        self.accounts.iter().find(|a| a.id == id)
+    }
+
+    /// 获取资产对应的账户
+    pub fn account_for_asset(&self, asset_id: Uuid) -> Option<&LedgerAccount> {
+        self.accounts.iter().find(|a| a.asset_id == Some(asset_id))
+    }
+
+    /// 校验分录：至少两行、账户存在、借贷平衡
+    pub fn validate(&self, entry: &LedgerEntry) -> Result<(), LedgerError> {
+        if entry.postings.len() < 2 {
+            return Err(LedgerError::TooFewPostings);
+        }
+        if let Some(p) = entry
+            .postings
+            .iter()
+            .find(|p| self.account(p.account_id).is_none())
+        {
+            return Err(LedgerError::UnknownAccount(p.account_id));
+        }
+        let imbalance = entry.imbalance();
+        if imbalance.abs() > BALANCE_EPSILON {
+            return Err(LedgerError::Unbalanced(imbalance));
+        }
+        Ok(())
+    }
+
+    /// 校验并记账
+    pub fn post(&mut self, entry: LedgerEntry) -> Result<(), LedgerError> {
+        self.validate(&entry)?;
+        self.entries.push(entry);
+        Ok(())
+    }
+
+    /// 账户余额（借方为正）
+    pub fn balance(&self, account_id: Uuid) -> f64 {
+        self.entries
+            .iter()
+            .flat_map(|e| &e.postings)
+            .filter(|p| p.account_id == account_id)
+            .map(|p| p.amount)
+            .sum()
+    }
+
+    /// 试算平衡表
+    pub fn trial_balance(&self) -> TrialBalance {
+        let rows: Vec<TrialBalanceRow> = self
+            .accounts
+            .iter()
+            .map(|account| {
+                let balance = self.balance(account.id);
+                TrialBalanceRow {
+                    account_id: account.id,
+                    name: account.name.clone(),
+                    kind: account.kind,
+                    debit: balance.max(0.0),
+                    credit: (-balance).max(0.0),
+                }
+            })
+            .collect();
+        let total_debit: f64 = rows.iter().map(|r| r.debit).sum();
+        let total_credit: f64 = rows.iter().map(|r| r.credit).sum();
+
+        TrialBalance {
+            rows,
+            total_debit,
+            total_credit,
+            balanced: (total_debit - total_credit).abs() <= BALANCE_EPSILON,
+        }
+    }
+
+    /// 将单式交易镜像为分录，变动为零时不记账
+    pub fn record_transaction(&mut self, txn: &AssetTransaction, asset: &Asset) {
+        let delta = txn.delta();
+        if delta.abs() <= BALANCE_EPSILON {
+            return;
+        }
+
+        let asset_account = self.asset_account(asset);
+        let counter = match txn.transaction_type {
+            TransactionType::Income => self.named_account(INCOME_ACCOUNT, AccountKind::Income),
+            TransactionType::Expense => self.named_account(EXPENSE_ACCOUNT, AccountKind::Expense),
+            TransactionType::ValueChange => {
+                self.named_account(VALUATION_ACCOUNT, AccountKind::Income)
+            }
+            TransactionType::Buy | TransactionType::Sell | TransactionType::Transfer => {
+                self.named_account(CONTRIBUTION_ACCOUNT, AccountKind::Equity)
+            }
+        };
+
+        let mut entry = LedgerEntry::new(txn.note.clone().unwrap_or_else(|| asset.name.clone()))
+            .with_timestamp(txn.timestamp)
+            .with_posting(asset_account, delta)
+            .with_posting(counter, -delta);
+        entry.source_transaction_id = Some(txn.id);
+        self.entries.push(entry);
+    }
+
+    /// 使资产账户余额与资产当前价值一致
+    ///
+    /// 新账户的差额记为期初余额，已有账户的差额记为估值变动；虚拟资产不建立账户。
+    pub fn sync_asset(&mut self, asset: &Asset) {
+        if asset.is_virtual() {
+            return;
+        }
+        let is_new = self.account_for_asset(asset.id).is_none();
+        let account = self.asset_account(asset);
+        let drift = asset.value - self.balance(account);
+        if drift.abs() <= BALANCE_EPSILON {
+            return;
+        }
+
+        let (name, kind) = if is_new {
+            (OPENING_BALANCE_ACCOUNT, AccountKind::Equity)
+        } else {
+            (VALUATION_ACCOUNT, AccountKind::Income)
+        };
+        let counter = self.named_account(name, kind);
+        self.entries.push(
+            LedgerEntry::new(name)
+                .with_timestamp(asset.updated_at)
+                .with_posting(account, drift)
+                .with_posting(counter, -drift),
+        );
+    }
+
+    /// 获取或创建资产对应的账户
+    pub(crate) fn asset_account(&mut self, asset: &Asset) -> Uuid {
+        if let Some(account) = self.account_for_asset(asset.id) {
+            return account.id;
+        }
+        let account = LedgerAccount::for_asset(asset);
+        let id = account.id;
+        self.accounts.push(account);
+        id
+    }
+
+    /// 获取或创建指定名称的账户
+    pub(crate) fn named_account(&mut self, name: &str, kind: AccountKind) -> Uuid {
+        if let Some(account) = self
+            .accounts
+            .iter()
+            .find(|a| a.name == name && a.asset_id.is_none())
+        {
+            return account.id;
+        }
+        let account = LedgerAccount::new(name, kind);
+        let id = account.id;
+        self.accounts.push(account);
+        id
+    }
+
+    /// 删除资产对应的账户及涉及该账户的分录
+    pub fn remove_asset(&mut self, asset_id: Uuid) {
+        let Some(account_id) = self.account_for_asset(asset_id).map(|a| a.id) else {
+            return;
+        };
+        self.entries
+            .retain(|e| e.postings.iter().all(|p| p.account_id != account_id));
+        self.accounts.retain(|a| a.id != account_id);
+    }
+}
@@ -5,11 +5,44 @@
 //! - Lua 插件系统
 //! - JSON 本地存储
 
+pub mod analytics;
 pub mod asset;
+pub mod assumptions;
+pub mod attachment;
+pub mod binary;
+pub mod bulk_edit;
+pub mod checkin;
+pub mod csv;
+pub mod deep_link;
+pub mod email;
+pub mod first_run;
+pub mod format;
+pub mod fx;
+pub mod gnucash;
+pub mod import;
+pub mod ledger;
+pub mod milestone;
+pub mod notification;
 pub mod plugin;
+pub mod portable;
+pub mod precision;
+pub mod profile;
+pub mod quick_entry;
+pub mod report;
+pub mod rules;
+pub mod search;
+pub mod secrets;
+pub mod settings;
+pub mod split;
 pub mod storage;
+pub mod symbol;
+pub mod tags;
+pub mod timeline;
+pub mod usage;
+pub mod valuation;
 
 pub use asset::*;
+pub use notification::{Notification, NotificationKind};
 pub use plugin::PluginManager;
 pub use storage::Database;
 
@@ -20,8 +53,39 @@ pub struct AppConfig {
     pub db_path: String,
     /// 插件目录路径
     pub plugins_dir: String,
+    /// 自定义报告模板目录
+    pub templates_dir: String,
+    /// 备份目录
+    #[serde(default = "default_backups_dir")]
+    pub backups_dir: String,
     /// 是否启用调试模式
     pub debug: bool,
+    /// 使用模拟的汇率与证券数据源（不联网，用于开发、测试与离线演示）
+    #[serde(default)]
+    pub mock_providers: bool,
+}
+
+impl AppConfig {
+    /// 读取配置文件，文件不存在时为 None
+    pub fn load(path: impl AsRef<std::path::Path>) -> Result<Option<Self>, storage::StorageError> {
+        match std::fs::read_to_string(path) {
+            Ok(content) => Ok(Some(serde_json::from_str(&content)?)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// 写入配置文件（先写临时文件再替换）
+    pub fn save(&self, path: impl AsRef<std::path::Path>) -> Result<(), storage::StorageError> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, serde_json::to_string_pretty(self)?)?;
+        std::fs::rename(&tmp, path)?;
+        Ok(())
+    }
 }
 
 impl Default for AppConfig {
@@ -29,7 +93,14 @@ impl Default for AppConfig {
         Self {
             db_path: "data/assets.json".to_string(),
             plugins_dir: "plugins".to_string(),
+            templates_dir: "templates".to_string(),
+            backups_dir: default_backups_dir(),
             debug: false,
+            mock_providers: false,
         }
     }
 }
+
+fn default_backups_dir() -> String {
+    "backups".to_string()
+}
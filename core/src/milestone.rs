@@ -0,0 +1,138 @@
+//! 净资产里程碑：首次达到某个净资产、还清全部负债等
+//!
+//! 里程碑从每日净资产历史中检测，记录达成日期，每个里程碑只记录一次。
+
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// 达成时间在此天数以内的里程碑才发送通知，更早的（如首次检测到的历史里程碑）只记录
+pub const NOTIFY_WITHIN_DAYS: i64 = 7;
+
+/// 检测的净资产门槛（本位币）
+pub const NET_WORTH_THRESHOLDS: [f64; 6] = [
+    10_000.0,
+    100_000.0,
+    500_000.0,
+    1_000_000.0,
+    5_000_000.0,
+    10_000_000.0,
+];
+
+/// 里程碑类型
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum MilestoneKind {
+    /// 净资产首次达到门槛
+    NetWorth { threshold: f64 },
+    /// 有过负债后首次还清全部负债
+    DebtFree,
+}
+
+impl MilestoneKind {
+    /// 里程碑标题
+    pub fn title(&self, currency: &str) -> String {
+        match self {
+            MilestoneKind::NetWorth { threshold } => {
+                format!("净资产首次达到 {}万 {}", threshold / 10_000.0, currency)
+            }
+            MilestoneKind::DebtFree => "还清全部负债".to_string(),
+        }
+    }
+}
+
+/// 已达成的里程碑
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Milestone {
+    /// 唯一标识符
+    pub id: Uuid,
+    /// 类型
+    pub kind: MilestoneKind,
+    /// 标题
+    pub title: String,
+    /// 达成日期
+    pub achieved_on: NaiveDate,
+    /// 达成当日的净资产（本位币）
+    pub net_worth: f64,
+    /// 检测到的时间
+    pub recorded_at: DateTime<Utc>,
+}
+
+/// 某日的净资产与负债（本位币，负债为正数）
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NetWorthDay {
+    pub date: NaiveDate,
+    pub net_worth: f64,
+    pub debt: f64,
+}
+
+/// 从按日期升序的历史中检测尚未记录的里程碑
+pub fn detect_milestones(
+    history: &[NetWorthDay],
+    recorded: &[Milestone],
+    currency: &str,
+) -> Vec<Milestone> {
+    let is_recorded = |kind: &MilestoneKind| recorded.iter().any(|m| m.kind == *kind);
+    let milestone = |kind: MilestoneKind, day: &NetWorthDay| Milestone {
+        id: Uuid::new_v4(),
+        kind,
+        title: kind.title(currency),
+        achieved_on: day.date,
+        net_worth: day.net_worth,
+        recorded_at: Utc::now(),
+    };
+
+    let mut found = Vec::new();
+    for threshold in NET_WORTH_THRESHOLDS {
+        let kind = MilestoneKind::NetWorth { threshold };
+        if is_recorded(&kind) {
+            continue;
+        }
+        if let Some(day) = history.iter().find(|d| d.net_worth >= threshold) {
+            found.push(milestone(kind, day));
+        }
+    }
+
+    if !is_recorded(&MilestoneKind::DebtFree) {
+        let cleared = history
+            .iter()
+            .skip_while(|d| d.debt <= 0.0)
+            .find(|d| d.debt <= 0.0);
+        if let Some(day) = cleared {
+            found.push(milestone(MilestoneKind::DebtFree, day));
+        }
+    }
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_milestones() {
+        let day = |d: u32, net_worth: f64, debt: f64| NetWorthDay {
+            date: NaiveDate::from_ymd_opt(2024, 1, d).unwrap(),
+            net_worth,
+            debt,
+        };
+        let history = [
+            day(1, 5_000.0, 0.0),
+            day(2, 20_000.0, 50_000.0),
+            day(3, 90_000.0, 10_000.0),
+            day(4, 120_000.0, 0.0),
+        ];
+        let found = detect_milestones(&history, &[], "CNY");
+        assert_eq!(found.len(), 3);
+        assert_eq!(found[0].achieved_on, history[1].date);
+        assert_eq!(found[1].title, "净资产首次达到 10万 CNY");
+        assert_eq!(found[1].achieved_on, history[3].date);
+        assert_eq!(found[2].kind, MilestoneKind::DebtFree);
+        assert_eq!(found[2].achieved_on, history[3].date);
+
+        // 已记录的不再重复检测
+        assert!(detect_milestones(&history, &found, "CNY").is_empty());
+        // 从未有过负债不算还清
+        assert!(detect_milestones(&history[..1], &[], "CNY").is_empty());
+    }
+}
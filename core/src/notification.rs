@@ -0,0 +1,78 @@
+//! 应用内通知（提醒、告警等）
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// 通知来源
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationKind {
+    /// 复查提醒到期
+    Reminder,
+    /// 资产达到目标价
+    TargetReached,
+    /// 对账单自动导入
+    Import,
+    /// 自动化规则
+    Rule,
+    /// 达成净资产里程碑
+    Milestone,
+    /// 其他通知
+    Info,
+}
+
+/// 通知
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Notification {
+    /// 唯一标识符
+    pub id: Uuid,
+    /// 通知来源
+    pub kind: NotificationKind,
+    /// 标题
+    pub title: String,
+    /// 内容
+    pub message: Option<String>,
+    /// 关联的资产ID
+    pub asset_id: Option<Uuid>,
+    /// 关联的对象ID（如提醒ID）
+    pub source_id: Option<Uuid>,
+    /// 是否已读
+    pub read: bool,
+    /// 创建时间
+    pub created_at: DateTime<Utc>,
+}
+
+impl Notification {
+    /// 创建新通知
+    pub fn new(kind: NotificationKind, title: impl Into<String>) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            kind,
+            title: title.into(),
+            message: None,
+            asset_id: None,
+            source_id: None,
+            read: false,
+            created_at: Utc::now(),
+        }
+    }
+
+    /// 设置内容
+    pub fn with_message(mut self, message: impl Into<String>) -> Self {
+        self.message = Some(message.into());
+        self
+    }
+
+    /// 关联资产
+    pub fn with_asset(mut self, asset_id: Option<Uuid>) -> Self {
+        self.asset_id = asset_id;
+        self
+    }
+
+    /// 关联来源对象
+    pub fn with_source(mut self, source_id: Uuid) -> Self {
+        self.source_id = Some(source_id);
+        self
+    }
+}
@@ -1,11 +1,36 @@
 //! 插件加载器
 
-use super::{PluginError, PluginInfo};
-use mlua::{Lua, Result as LuaResult, Table, Value};
+use super::{PluginError, PluginInfo, SharedAppContext, READ_SETTINGS_PERMISSION};
+use mlua::{
+    ChunkMode, Function, Lua, LuaOptions, LuaSerdeExt, Result as LuaResult, StdLib, Table, Value,
+};
+use serde::Serialize;
 use std::fs;
 use std::path::Path;
 use tracing::{debug, info, warn};
 
+/// 插件返回的模块表在注册表中的键名
+const PLUGIN_TABLE_KEY: &str = "plugin";
+
+/// 插件声明的权限在注册表中的键名（插件无法修改注册表）
+const PERMISSIONS_KEY: &str = "permissions";
+
+/// 插件可用的标准库（不含 `package`、`io`、`os` 与 `debug`）
+fn sandbox_libs() -> StdLib {
+    StdLib::COROUTINE | StdLib::TABLE | StdLib::STRING | StdLib::UTF8 | StdLib::MATH
+}
+
+/// 插件 Lua 虚拟机的内存上限（字节）
+const SANDBOX_MEMORY_LIMIT: usize = 64 * 1024 * 1024;
+
+/// `string.rep` 生成字符串的最大长度（字节）
+const MAX_REP_LEN: usize = 16 * 1024 * 1024;
+
+/// 沙箱中移除的全局变量
+const REMOVED_GLOBALS: [&str; 7] = [
+    "os", "io", "debug", "package", "require", "loadfile", "dofile",
+];
+
 /// 插件加载器
 pub struct PluginLoader {
     lua: Lua,
@@ -14,8 +39,9 @@ pub struct PluginLoader {
 impl PluginLoader {
     /// 创建新的插件加载器
     pub fn new() -> LuaResult<Self> {
-        let lua = Lua::new();
-        
+        let lua = Lua::new_with(sandbox_libs(), LuaOptions::default())?;
+        lua.set_memory_limit(SANDBOX_MEMORY_LIMIT)?;
+
         // 设置安全的 Lua 环境
         Self::setup_sandbox(&lua)?;
         
@@ -24,13 +50,44 @@ impl PluginLoader {
 
     /// 设置沙箱环境，限制危险操作
     fn setup_sandbox(lua: &Lua) -> LuaResult<()> {
-        // 移除危险函数
+        // 移除危险函数（只加载了白名单中的标准库，这里再清除一遍以防万一）
         let globals = lua.globals();
-        globals.set("os", Value::Nil)?;  // 移除 os 库（安全考虑）
-        globals.set("io", Value::Nil)?;  // 移除 io 库（安全考虑）
-        globals.set("loadfile", Value::Nil)?;
-        globals.set("dofile", Value::Nil)?;
-        
+        for name in REMOVED_GLOBALS {
+            globals.set(name, Value::Nil)?;
+        }
+
+        // 只允许加载文本代码：字节码可绕过校验读写虚拟机内存
+        let string: Table = globals.get("string")?;
+        string.set("dump", Value::Nil)?;
+        let raw_load: Function = globals.get("load")?;
+        let load_fn = lua.create_function(move |lua, mut args: mlua::Variadic<Value>| {
+            // 只替换 mode 参数：显式传入 nil 的 env 与未传入含义不同
+            if args.len() < 3 {
+                args.resize(3, Value::Nil);
+            }
+            args[2] = Value::String(lua.create_string("t")?);
+            raw_load.call::<mlua::MultiValue>(args)
+        })?;
+        globals.set("load", load_fn)?;
+
+        // 限制 string.rep 的结果长度，避免一次分配耗尽内存
+        let raw_rep: Function = string.get("rep")?;
+        let rep_fn = lua.create_function(
+            move |_, (text, count, sep): (mlua::String, i64, Option<mlua::String>)| {
+                let count = count.max(0) as usize;
+                let sep_len = sep.as_ref().map_or(0, |s| s.as_bytes().len());
+                let len = (text.as_bytes().len() + sep_len).saturating_mul(count);
+                if len > MAX_REP_LEN {
+                    return Err(mlua::Error::runtime(format!(
+                        "string.rep result too large ({} bytes, limit {})",
+                        len, MAX_REP_LEN
+                    )));
+                }
+                raw_rep.call::<mlua::String>((text, count, sep))
+            },
+        )?;
+        string.set("rep", rep_fn)?;
+
         // 添加安全的日志函数
         let log_fn = lua.create_function(|_, msg: String| {
             info!(target: "plugin", "{}", msg);
@@ -52,6 +109,44 @@ impl PluginLoader {
         Ok(())
     }
 
+    /// 提供只读的 `app` 表：`app.base_currency()` 返回本位币代码，
+    /// `app.get_setting(key)` 返回设置项的值（未设置时为 nil，需声明 `read_settings` 权限）
+    pub fn set_app_context(&self, context: SharedAppContext) -> LuaResult<()> {
+        let app = self.lua.create_table()?;
+
+        let base = context.clone();
+        let base_currency = self.lua.create_function(move |_, ()| {
+            let context = base
+                .read()
+                .map_err(|e| mlua::Error::runtime(e.to_string()))?;
+            Ok(context.base_currency().code().to_string())
+        })?;
+        app.set("base_currency", base_currency)?;
+
+        let get_setting = self.lua.create_function(move |lua, key: String| {
+            let permissions: Vec<String> = lua
+                .named_registry_value::<Option<Vec<String>>>(PERMISSIONS_KEY)?
+                .unwrap_or_default();
+            if !permissions.iter().any(|p| p == READ_SETTINGS_PERMISSION) {
+                return Err(mlua::Error::runtime(format!(
+                    "Permission denied: app.get_setting requires '{}'",
+                    READ_SETTINGS_PERMISSION
+                )));
+            }
+            let value = context
+                .read()
+                .map_err(|e| mlua::Error::runtime(e.to_string()))?
+                .setting(&key);
+            match value {
+                Some(value) => lua.to_value(&value),
+                None => Ok(Value::Nil),
+            }
+        })?;
+        app.set("get_setting", get_setting)?;
+
+        self.lua.globals().set("app", app)
+    }
+
     /// 从目录加载插件
     pub fn load_from_dir(&self, plugin_dir: &Path) -> Result<PluginInfo, PluginError> {
         let init_file = plugin_dir.join("init.lua");
@@ -71,9 +166,12 @@ impl PluginLoader {
     /// 加载插件代码
     fn load_plugin_code(&self, code: &str, plugin_dir: &Path) -> Result<PluginInfo, PluginError> {
         // 执行插件代码
-        let plugin_table: Table = self.lua.load(code).eval().map_err(|e| {
-            PluginError::LoadError(format!("Failed to load plugin: {}", e))
-        })?;
+        let plugin_table: Table = self
+            .lua
+            .load(code)
+            .set_mode(ChunkMode::Text)
+            .eval()
+            .map_err(|e| PluginError::LoadError(format!("Failed to load plugin: {}", e)))?;
 
         // 读取插件元信息
         let name: String = plugin_table
@@ -86,9 +184,20 @@ impl PluginLoader {
         
         let author: Option<String> = plugin_table.get("author").ok();
         let description: Option<String> = plugin_table.get("description").ok();
+        let permissions: Vec<String> = plugin_table
+            .get::<Option<Vec<String>>>("permissions")
+            .ok()
+            .flatten()
+            .unwrap_or_default();
 
         info!("Loaded plugin: {} v{}", name, version);
 
+        // 保存模块表，事件处理函数从中查找
+        self.lua
+            .set_named_registry_value(PLUGIN_TABLE_KEY, plugin_table)?;
+        self.lua
+            .set_named_registry_value(PERMISSIONS_KEY, permissions.clone())?;
+
         Ok(PluginInfo {
             name,
             version,
@@ -96,6 +205,7 @@ impl PluginLoader {
             description,
             path: plugin_dir.to_path_buf(),
             enabled: true,
+            permissions,
         })
     }
 
@@ -105,9 +215,7 @@ impl PluginLoader {
         A: mlua::IntoLuaMulti,
         R: mlua::FromLuaMulti + 'a,
     {
-        let globals = self.lua.globals();
-        
-        if let Ok(func) = globals.get::<mlua::Function>(func_name) {
+        if let Some(func) = self.find_function(func_name) {
             Ok(func.call(args)?)
         } else {
             warn!("Function '{}' not found", func_name);
@@ -115,6 +223,39 @@ impl PluginLoader {
         }
     }
 
+    /// 以 Lua 表的形式传入数据调用插件函数，并将返回值转换为 JSON
+    pub fn call_with_value<T: Serialize + ?Sized>(
+        &self,
+        func_name: &str,
+        data: &T,
+    ) -> Result<serde_json::Value, PluginError> {
+        let func = self
+            .find_function(func_name)
+            .ok_or_else(|| PluginError::NotFound(func_name.to_string()))?;
+        let result: Value = func.call(self.lua.to_value(data)?)?;
+        Ok(self.lua.from_value(result)?)
+    }
+
+    /// 读取插件模块表中的字段并转换为 JSON（字段不存在时返回 None）
+    pub fn table_value(&self, key: &str) -> Result<Option<serde_json::Value>, PluginError> {
+        let Ok(table) = self.lua.named_registry_value::<Table>(PLUGIN_TABLE_KEY) else {
+            return Ok(None);
+        };
+        match table.get::<Value>(key)? {
+            Value::Nil => Ok(None),
+            value => Ok(Some(self.lua.from_value(value)?)),
+        }
+    }
+
+    /// 查找插件函数：优先取插件返回的模块表，其次取全局函数
+    fn find_function(&self, func_name: &str) -> Option<Function> {
+        self.lua
+            .named_registry_value::<Table>(PLUGIN_TABLE_KEY)
+            .ok()
+            .and_then(|table| table.get::<Function>(func_name).ok())
+            .or_else(|| self.lua.globals().get::<Function>(func_name).ok())
+    }
+
     /// 获取 Lua 实例引用
     pub fn lua(&self) -> &Lua {
         &self.lua
@@ -142,6 +283,8 @@ impl Default for PluginLoader {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::plugin::AppContext;
+    use std::sync::{Arc, RwLock};
 
     #[test]
     fn test_plugin_loader_creation() {
@@ -155,4 +298,133 @@ mod tests {
         let result: i32 = loader.lua().load("return 1 + 1").eval().unwrap();
         assert_eq!(result, 2);
     }
+
+    #[test]
+    fn test_sandbox_escapes() {
+        let loader = PluginLoader::new().unwrap();
+        let eval = |code: &str| loader.lua().load(code).eval::<Value>();
+
+        // 模块加载、系统库与调试库均不可用
+        for name in REMOVED_GLOBALS {
+            let value = eval(&format!("return {}", name)).unwrap();
+            assert!(value.is_nil(), "{} should be removed", name);
+        }
+        assert!(eval(r#"return require("os")"#).is_err());
+        assert!(eval(r#"return package.loadlib("libc.so.6", "system")"#).is_err());
+
+        // 不能生成或加载字节码，文本代码照常加载
+        assert!(eval("return string.dump(print)").is_err());
+        let loaded: Value = eval(r#"return load("\27Lua\84\0", "chunk", "b")"#).unwrap();
+        assert!(loaded.is_nil());
+        let sum: i64 = loader
+            .lua()
+            .load(r#"return load("return math.max(1, 2) + 1", "chunk", "b")()"#)
+            .eval()
+            .unwrap();
+        assert_eq!(sum, 3);
+
+        // 超长的 string.rep 与不断翻倍的字符串都会失败
+        assert!(eval(r#"return string.rep("x", 1e9)"#).is_err());
+        assert!(eval(r#"return ("x"):rep(1 << 40)"#).is_err());
+        assert!(eval(r#"local s = "x" for i = 1, 40 do s = s .. s end return #s"#).is_err());
+        let short: String = loader
+            .lua()
+            .load(r#"return string.rep("ab", 3, "-")"#)
+            .eval()
+            .unwrap();
+        assert_eq!(short, "ab-ab-ab");
+        // 内存超限后虚拟机仍可使用
+        let ok: i64 = loader
+            .lua()
+            .load("return #string.rep('x', 10)")
+            .eval()
+            .unwrap();
+        assert_eq!(ok, 10);
+    }
+
+    #[test]
+    fn test_plugin_table_functions() {
+        let loader = PluginLoader::new().unwrap();
+        let code = r#"
+            local plugin = { name = "test" }
+            function plugin.contribute_summary(summary)
+                return { doubled = summary.total_value * 2 }
+            end
+            return plugin
+        "#;
+        loader.load_plugin_code(code, Path::new(".")).unwrap();
+
+        let result = loader
+            .call_with_value(
+                "contribute_summary",
+                &serde_json::json!({ "total_value": 21.0 }),
+            )
+            .unwrap();
+        assert_eq!(result["doubled"], 42.0);
+        assert!(matches!(
+            loader.call_with_value("missing", &()),
+            Err(PluginError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_plugin_table_value() {
+        let loader = PluginLoader::new().unwrap();
+        let code = r#"
+            return {
+                name = "broker",
+                metadata_schemas = {
+                    stock = { type = "object", required = { "broker" } },
+                },
+            }
+        "#;
+        loader.load_plugin_code(code, Path::new(".")).unwrap();
+
+        let schemas = loader.table_value("metadata_schemas").unwrap().unwrap();
+        assert_eq!(schemas["stock"]["required"][0], "broker");
+        assert!(loader.table_value("missing").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_app_context() {
+        let mut context = AppContext::default();
+        context.update("locale", Some(r#""en-US""#));
+        context.update("fx", Some(r#"{"base_currency":"USD"}"#));
+        let context: SharedAppContext = Arc::new(RwLock::new(context));
+
+        let code = r#"
+            local plugin = { name = "locale", permissions = { "read_settings" } }
+            function plugin.describe()
+                return { currency = app.base_currency(), locale = app.get_setting("locale"),
+                         missing = app.get_setting("missing") == nil }
+            end
+            return plugin
+        "#;
+        let loader = PluginLoader::new().unwrap();
+        loader.set_app_context(context.clone()).unwrap();
+        let info = loader.load_plugin_code(code, Path::new(".")).unwrap();
+        assert!(info.has_permission(READ_SETTINGS_PERMISSION));
+        let result = loader.call_with_value("describe", &()).unwrap();
+        assert_eq!(result["currency"], "USD");
+        assert_eq!(result["locale"], "en-US");
+        assert_eq!(result["missing"], true);
+
+        // 设置变化后读取到新值
+        context.write().unwrap().update("fx", None);
+        let result = loader.call_with_value("describe", &()).unwrap();
+        assert_eq!(result["currency"], "CNY");
+
+        // 未声明权限时只能读取本位币
+        let code = r#"
+            local plugin = { name = "currency" }
+            function plugin.currency() return app.base_currency() end
+            function plugin.locale() return app.get_setting("locale") end
+            return plugin
+        "#;
+        let loader = PluginLoader::new().unwrap();
+        loader.set_app_context(context).unwrap();
+        loader.load_plugin_code(code, Path::new(".")).unwrap();
+        assert_eq!(loader.call_with_value("currency", &()).unwrap(), "CNY");
+        assert!(loader.call_with_value("locale", &()).is_err());
+    }
 }
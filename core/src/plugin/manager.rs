@@ -1,9 +1,16 @@
 //! 插件管理器
 
-use super::{PluginError, PluginEvent, PluginInfo, PluginLoader};
+use super::{
+    PluginError, PluginEvent, PluginHealth, PluginImporter, PluginInfo, PluginLoader,
+    PluginMetrics, SharedAppContext,
+};
+use crate::asset::AssetSummary;
+use chrono::Utc;
 use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::Instant;
 use tracing::{error, info, warn};
 
 /// 插件管理器
@@ -12,6 +19,10 @@ pub struct PluginManager {
     plugins_dir: PathBuf,
     /// 已加载的插件
     plugins: HashMap<String, (PluginInfo, PluginLoader)>,
+    /// 插件通过 `app` 表读取的应用信息
+    app_context: SharedAppContext,
+    /// 各插件的执行统计（调用时只持有管理器的共享引用）
+    metrics: Mutex<HashMap<String, PluginMetrics>>,
 }
 
 impl PluginManager {
@@ -20,9 +31,17 @@ impl PluginManager {
         Self {
             plugins_dir: plugins_dir.into(),
             plugins: HashMap::new(),
+            app_context: SharedAppContext::default(),
+            metrics: Mutex::default(),
         }
     }
 
+    /// 使用宿主持有的应用信息（需在加载插件前设置）
+    pub fn with_app_context(mut self, app_context: SharedAppContext) -> Self {
+        self.app_context = app_context;
+        self
+    }
+
     /// 扫描并加载所有插件
     pub fn load_all(&mut self) -> Result<Vec<PluginInfo>, PluginError> {
         let mut loaded = Vec::new();
@@ -57,6 +76,7 @@ impl PluginManager {
     /// 加载单个插件
     pub fn load_plugin(&mut self, plugin_dir: &Path) -> Result<PluginInfo, PluginError> {
         let loader = PluginLoader::new()?;
+        loader.set_app_context(Arc::clone(&self.app_context))?;
         let info = loader.load_from_dir(plugin_dir)?;
 
         // 调用插件的 on_load 函数（如果存在）
@@ -65,6 +85,7 @@ impl PluginManager {
         }
 
         let name = info.name.clone();
+        self.metrics_mut().remove(&name);
         self.plugins.insert(name, (info.clone(), loader));
 
         Ok(info)
@@ -73,6 +94,7 @@ impl PluginManager {
     /// 卸载插件
     pub fn unload_plugin(&mut self, name: &str) -> Result<(), PluginError> {
         if let Some((info, loader)) = self.plugins.remove(name) {
+            self.metrics_mut().remove(name);
             // 调用 on_unload
             let _ = self.call_plugin_lifecycle(&loader, "on_unload", ());
             info!("Unloaded plugin: {}", info.name);
@@ -92,6 +114,11 @@ impl PluginManager {
         self.plugins.get(name).map(|(info, _)| info)
     }
 
+    /// 插件自加载以来的执行统计
+    pub fn metrics(&self, name: &str) -> PluginMetrics {
+        self.metrics_mut().get(name).cloned().unwrap_or_default()
+    }
+
     /// 启用/禁用插件
     pub fn set_plugin_enabled(&mut self, name: &str, enabled: bool) -> Result<(), PluginError> {
         if let Some((info, _)) = self.plugins.get_mut(name) {
@@ -107,6 +134,23 @@ impl PluginManager {
         }
     }
 
+    /// 调用指定插件的处理函数：以 Lua 表传入参数，返回值转换为 JSON（供命令行等无界面场景使用）
+    pub fn run_handler(
+        &self,
+        name: &str,
+        handler: &str,
+        args: &serde_json::Value,
+    ) -> Result<serde_json::Value, PluginError> {
+        let (info, loader) = self
+            .plugins
+            .get(name)
+            .ok_or_else(|| PluginError::NotFound(name.to_string()))?;
+        if !info.enabled {
+            return Err(PluginError::Disabled(name.to_string()));
+        }
+        self.timed(name, || loader.call_with_value(handler, args))
+    }
+
     /// 广播事件到所有插件
     pub fn broadcast_event(&self, event: &PluginEvent) {
         for (info, loader) in self.plugins.values() {
@@ -114,7 +158,7 @@ impl PluginManager {
                 continue;
             }
 
-            let result = match event {
+            let result = self.timed(&info.name, || match event {
                 PluginEvent::AssetCreated(asset) => {
                     self.call_plugin_with_json(loader, "on_asset_created", asset)
                 }
@@ -130,10 +174,13 @@ impl PluginManager {
                 PluginEvent::AppClosing => {
                     self.call_plugin_lifecycle(loader, "on_app_closing", ())
                 }
+                PluginEvent::SettingChanged(key) => {
+                    self.call_plugin_with_json(loader, "on_setting_changed", key)
+                }
                 PluginEvent::Custom(event_name, data) => {
                     self.call_plugin_custom(loader, event_name, data)
                 }
-            };
+            });
 
             if let Err(e) = result {
                 // 只在函数存在但执行失败时警告
@@ -144,6 +191,151 @@ impl PluginManager {
         }
     }
 
+    /// 收集插件通过 `contribute_summary(summary)` 返回的附加指标并合并到摘要
+    ///
+    /// 钩子接收摘要表，返回 `{ 指标名 = 值 }`；返回 nil 表示不提供指标。
+    /// 同名指标以后执行的插件为准。
+    pub fn contribute_summary(&self, summary: &mut AssetSummary) {
+        let mut plugins: Vec<&(PluginInfo, PluginLoader)> = self
+            .plugins
+            .values()
+            .filter(|(info, _)| info.enabled)
+            .collect();
+        plugins.sort_by(|a, b| a.0.name.cmp(&b.0.name));
+
+        for (info, loader) in plugins {
+            let result = self.timed(&info.name, || {
+                loader.call_with_value("contribute_summary", &*summary)
+            });
+            match result {
+                Ok(serde_json::Value::Object(metrics)) => {
+                    summary.plugin_metrics.extend(metrics);
+                }
+                Ok(serde_json::Value::Null) => {}
+                Ok(serde_json::Value::Array(items)) if items.is_empty() => {}
+                Ok(other) => {
+                    warn!(
+                        "Plugin {} contribute_summary returned non-table value: {}",
+                        info.name, other
+                    );
+                }
+                Err(PluginError::NotFound(_)) => {}
+                Err(e) => error!("Plugin {} contribute_summary error: {}", info.name, e),
+            }
+        }
+    }
+
+    /// 收集插件在模块表 `metadata_schemas` 字段中声明的元数据 Schema
+    ///
+    /// 字段格式为 `{ 类型代码 = Schema }`；同一类型以后加载的插件为准。
+    pub fn metadata_schemas(&self) -> Vec<(String, serde_json::Value)> {
+        let mut plugins: Vec<&(PluginInfo, PluginLoader)> = self
+            .plugins
+            .values()
+            .filter(|(info, _)| info.enabled)
+            .collect();
+        plugins.sort_by(|a, b| a.0.name.cmp(&b.0.name));
+
+        let mut schemas = Vec::new();
+        for (info, loader) in plugins {
+            match loader.table_value("metadata_schemas") {
+                Ok(Some(serde_json::Value::Object(map))) => schemas.extend(map),
+                Ok(None) => {}
+                Ok(Some(serde_json::Value::Array(items))) if items.is_empty() => {}
+                Ok(Some(other)) => {
+                    warn!(
+                        "Plugin {} metadata_schemas is not a table: {}",
+                        info.name, other
+                    );
+                }
+                Err(e) => error!("Plugin {} metadata_schemas error: {}", info.name, e),
+            }
+        }
+        schemas
+    }
+
+    /// 收集插件在模块表 `importers` 字段中声明的对账单导入器
+    ///
+    /// 字段格式为 `{ { name = 名称, extensions = { 扩展名 }, handler = 解析函数名 } }`，
+    /// 格式不正确的插件被跳过。
+    pub fn importers(&self) -> Vec<PluginImporter> {
+        let mut plugins: Vec<&(PluginInfo, PluginLoader)> = self
+            .plugins
+            .values()
+            .filter(|(info, _)| info.enabled)
+            .collect();
+        plugins.sort_by(|a, b| a.0.name.cmp(&b.0.name));
+
+        let mut importers = Vec::new();
+        for (info, loader) in plugins {
+            let declared = match loader.table_value("importers") {
+                Ok(Some(value)) => serde_json::from_value::<Vec<PluginImporter>>(value),
+                Ok(None) => continue,
+                Err(e) => {
+                    error!("Plugin {} importers error: {}", info.name, e);
+                    continue;
+                }
+            };
+            match declared {
+                Ok(declared) => {
+                    importers.extend(declared.into_iter().map(|importer| PluginImporter {
+                        plugin: info.name.clone(),
+                        ..importer
+                    }))
+                }
+                Err(e) => warn!("Plugin {} importers are invalid: {}", info.name, e),
+            }
+        }
+        importers
+    }
+
+    /// 调用插件导入器解析文件内容，返回解析函数的结果
+    pub fn run_importer(
+        &self,
+        importer: &PluginImporter,
+        content: &str,
+        file_name: &str,
+    ) -> Result<serde_json::Value, PluginError> {
+        self.run_handler(
+            &importer.plugin,
+            &importer.handler,
+            &serde_json::json!({ "content": content, "file_name": file_name }),
+        )
+    }
+
+    /// 调用插件并记入执行统计（函数未定义时不计），插件变为不健康时警告
+    fn timed<T>(
+        &self,
+        name: &str,
+        call: impl FnOnce() -> Result<T, PluginError>,
+    ) -> Result<T, PluginError> {
+        let started = Instant::now();
+        let result = call();
+        if matches!(result, Err(PluginError::NotFound(_))) {
+            return result;
+        }
+        let mut metrics = self.metrics_mut();
+        let entry = metrics.entry(name.to_string()).or_default();
+        let before = entry.health();
+        entry.record(started.elapsed(), result.as_ref().err(), Utc::now());
+        let after = entry.health();
+        if after != before && after != PluginHealth::Healthy {
+            warn!(
+                "Plugin {} is unhealthy ({:?}): {} calls, {} errors, {:.1} ms average",
+                name,
+                after,
+                entry.calls,
+                entry.errors,
+                entry.average_ms()
+            );
+        }
+        result
+    }
+
+    fn metrics_mut(&self) -> MutexGuard<'_, HashMap<String, PluginMetrics>> {
+        self.metrics.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
     /// 调用插件生命周期函数
     fn call_plugin_lifecycle<A>(
         &self,
@@ -186,3 +378,55 @@ impl Default for PluginManager {
         Self::new("plugins")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plugin::HEALTH_MIN_CALLS;
+    use uuid::Uuid;
+
+    #[test]
+    fn test_plugin_metrics() {
+        let dir = std::env::temp_dir().join(format!("asset-manager-metrics-{}", Uuid::new_v4()));
+        let plugin_dir = dir.join("flaky");
+        fs::create_dir_all(&plugin_dir).unwrap();
+        fs::write(
+            plugin_dir.join("init.lua"),
+            r#"
+                local plugin = { name = "flaky" }
+                function plugin.ok() return 1 end
+                function plugin.fail() error("boom") end
+                return plugin
+            "#,
+        )
+        .unwrap();
+        let mut plugins = PluginManager::new(&dir);
+        plugins.load_all().unwrap();
+
+        // 未定义的钩子不计入统计
+        plugins.broadcast_event(&PluginEvent::AppStarted);
+        plugins
+            .run_handler("flaky", "ok", &serde_json::json!({}))
+            .unwrap();
+        assert_eq!(plugins.metrics("flaky").calls, 1);
+
+        for _ in 0..HEALTH_MIN_CALLS {
+            assert!(plugins
+                .run_handler("flaky", "fail", &serde_json::json!({}))
+                .is_err());
+        }
+        let metrics = plugins.metrics("flaky");
+        assert_eq!(
+            (metrics.calls, metrics.errors),
+            (HEALTH_MIN_CALLS + 1, HEALTH_MIN_CALLS)
+        );
+        assert!(metrics.last_error.unwrap().contains("boom"));
+        assert_eq!(plugins.metrics("flaky").health(), PluginHealth::Failing);
+
+        // 重新加载后从头统计
+        plugins.load_plugin(&plugin_dir).unwrap();
+        assert_eq!(plugins.metrics("flaky"), PluginMetrics::default());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
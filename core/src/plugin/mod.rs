@@ -6,8 +6,18 @@ mod manager;
 pub use loader::PluginLoader;
 pub use manager::PluginManager;
 
+use crate::asset::Currency;
+use crate::fx::{FX_SETTINGS, FX_SETTING_KEY};
+use crate::storage::{Database, StorageError};
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+/// 读取应用设置的权限，插件在模块表的 `permissions` 中声明后才能调用 `app.get_setting`
+pub const READ_SETTINGS_PERMISSION: &str = "read_settings";
 
 /// 插件元信息
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -24,6 +34,152 @@ pub struct PluginInfo {
     pub path: PathBuf,
     /// 是否启用
     pub enabled: bool,
+    /// 声明的权限
+    #[serde(default)]
+    pub permissions: Vec<String>,
+}
+
+impl PluginInfo {
+    /// 是否声明了权限
+    pub fn has_permission(&self, permission: &str) -> bool {
+        self.permissions.iter().any(|p| p == permission)
+    }
+}
+
+/// 应用关闭时等待插件处理 `on_app_closing` 的最长时间（秒）
+pub const APP_CLOSING_TIMEOUT_SECS: u64 = 5;
+
+/// 调用次数达到此值后才判断插件是否健康
+pub const HEALTH_MIN_CALLS: u64 = 5;
+
+/// 平均耗时超过此值（毫秒）的插件视为拖慢操作
+pub const SLOW_PLUGIN_MS: f64 = 100.0;
+
+/// 出错比例超过此值的插件视为经常出错
+pub const FAILING_ERROR_RATE: f64 = 0.5;
+
+/// 插件自加载以来的执行统计（不含未定义的钩子）
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct PluginMetrics {
+    /// 调用次数
+    pub calls: u64,
+    /// 出错次数
+    pub errors: u64,
+    /// 总耗时（毫秒）
+    pub total_ms: f64,
+    /// 最近一次错误
+    pub last_error: Option<String>,
+    pub last_error_at: Option<DateTime<Utc>>,
+}
+
+/// 插件健康状态
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PluginHealth {
+    Healthy,
+    /// 平均耗时过长，会拖慢保存资产等操作
+    Slow,
+    /// 经常出错
+    Failing,
+}
+
+impl PluginMetrics {
+    /// 记入一次调用
+    pub fn record(&mut self, elapsed: Duration, error: Option<&PluginError>, now: DateTime<Utc>) {
+        self.calls += 1;
+        self.total_ms += elapsed.as_secs_f64() * 1000.0;
+        if let Some(e) = error {
+            self.errors += 1;
+            self.last_error = Some(e.to_string());
+            self.last_error_at = Some(now);
+        }
+    }
+
+    /// 平均耗时（毫秒）
+    pub fn average_ms(&self) -> f64 {
+        if self.calls == 0 {
+            0.0
+        } else {
+            self.total_ms / self.calls as f64
+        }
+    }
+
+    /// 健康状态：调用次数不足时视为健康，经常出错优先于耗时过长
+    pub fn health(&self) -> PluginHealth {
+        if self.calls < HEALTH_MIN_CALLS {
+            PluginHealth::Healthy
+        } else if self.errors as f64 / self.calls as f64 > FAILING_ERROR_RATE {
+            PluginHealth::Failing
+        } else if self.average_ms() > SLOW_PLUGIN_MS {
+            PluginHealth::Slow
+        } else {
+            PluginHealth::Healthy
+        }
+    }
+}
+
+/// 插件在模块表 `importers` 字段中声明的对账单导入器
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PluginImporter {
+    /// 导入器名称
+    pub name: String,
+    /// 处理的文件扩展名（不含点）
+    #[serde(default)]
+    pub extensions: Vec<String>,
+    /// 解析函数名：接收 `{ content, file_name }`，返回流水行列表
+    pub handler: String,
+    /// 提供导入器的插件
+    #[serde(default)]
+    pub plugin: String,
+}
+
+/// 插件通过 `app` 表读取的应用信息
+///
+/// 由宿主持有并在设置变化时更新，插件调用时只读取这份副本，不会访问数据库。
+#[derive(Debug, Clone, Default)]
+pub struct AppContext {
+    /// 设置项（键名 → JSON 字符串）
+    settings: HashMap<String, String>,
+    /// 本位币
+    base_currency: Currency,
+}
+
+/// 各插件共享的应用信息
+pub type SharedAppContext = Arc<RwLock<AppContext>>;
+
+impl AppContext {
+    /// 从数据库当前的设置创建
+    pub fn from_database(db: &Database) -> Result<Self, StorageError> {
+        let mut context = Self::default();
+        for (key, raw) in db.list_settings()? {
+            context.update(&key, Some(&raw));
+        }
+        Ok(context)
+    }
+
+    /// 设置项变化时更新（值为空表示已删除）
+    pub fn update(&mut self, key: &str, raw: Option<&str>) {
+        match raw {
+            Some(raw) => self.settings.insert(key.to_string(), raw.to_string()),
+            None => self.settings.remove(key),
+        };
+        if key == FX_SETTING_KEY {
+            self.base_currency = FX_SETTINGS
+                .parse(raw)
+                .unwrap_or_else(|_| FX_SETTINGS.default_value())
+                .base_currency;
+        }
+    }
+
+    /// 设置项的值
+    pub fn setting(&self, key: &str) -> Option<serde_json::Value> {
+        serde_json::from_str(self.settings.get(key)?).ok()
+    }
+
+    /// 本位币
+    pub fn base_currency(&self) -> &Currency {
+        &self.base_currency
+    }
 }
 
 /// 插件事件
@@ -39,6 +195,8 @@ pub enum PluginEvent {
     AppStarted,
     /// 应用关闭
     AppClosing,
+    /// 设置项变化（键名）
+    SettingChanged(String),
     /// 自定义事件
     Custom(String, serde_json::Value),
 }
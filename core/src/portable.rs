@@ -0,0 +1,229 @@
+//! 便携模式：数据、插件、配置与备份都放在程序旁的目录中，适合从U盘运行
+//!
+//! 程序旁有 `portable.ini` 或以 `--portable` 启动时启用。便携模式下配置中的路径一律限定到
+//! 该目录，即使配置文件被手动修改或从其他电脑复制过来也不会写到本机的其他位置。
+//!
+//! 以命名档案启动时，配置与使用统计放在 `profiles/<档案名>/` 中，默认的数据文件与备份也在其中，
+//! 各档案互不影响（便携模式下整个档案目录都限定在便携数据目录中）。
+
+use crate::first_run::{CONFIG_FILE, DATA_FILE_NAME};
+use crate::profile::{ProfileManager, PROFILE_BACKUPS_DIR};
+use crate::usage::USAGE_FILE;
+use crate::AppConfig;
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+
+/// 程序旁的便携模式标记文件
+pub const PORTABLE_MARKER: &str = "portable.ini";
+
+/// 启用便携模式的命令行参数
+pub const PORTABLE_ARG: &str = "--portable";
+
+/// 便携模式下程序旁的数据目录
+pub const PORTABLE_DATA_DIR: &str = "data";
+
+/// 命名档案所在的目录（与默认配置文件在同一目录）
+pub const PROFILES_DIR: &str = "profiles";
+
+/// 应用文件的位置
+#[derive(Debug, Clone)]
+pub struct AppPaths {
+    /// 配置文件路径
+    pub config_file: PathBuf,
+    /// 便携模式的数据目录（非便携模式为空）
+    pub portable_root: Option<PathBuf>,
+    /// 命名档案的目录（未使用档案时为空）
+    pub profile_dir: Option<PathBuf>,
+}
+
+impl AppPaths {
+    /// 按当前程序位置与命令行参数确定文件位置
+    pub fn detect(args: impl IntoIterator<Item = String>) -> Self {
+        match std::env::current_exe() {
+            Ok(exe) => Self::for_exe(&exe, args),
+            Err(e) => {
+                tracing::warn!("Failed to locate executable: {}", e);
+                Self::installed()
+            }
+        }
+    }
+
+    /// 程序位于 `exe` 时的文件位置
+    pub fn for_exe(exe: &Path, args: impl IntoIterator<Item = String>) -> Self {
+        let exe_dir = exe.parent().unwrap_or(Path::new("."));
+        let portable = args.into_iter().any(|arg| arg == PORTABLE_ARG)
+            || exe_dir.join(PORTABLE_MARKER).exists();
+        if portable {
+            Self::portable(exe_dir.join(PORTABLE_DATA_DIR))
+        } else {
+            Self::installed()
+        }
+    }
+
+    /// 普通安装：相对于工作目录
+    pub fn installed() -> Self {
+        Self {
+            config_file: PathBuf::from(CONFIG_FILE),
+            portable_root: None,
+            profile_dir: None,
+        }
+    }
+
+    /// 便携模式：所有文件都在 `root` 中
+    pub fn portable(root: impl Into<PathBuf>) -> Self {
+        let root = root.into();
+        Self {
+            config_file: root.join(CONFIG_FILE),
+            portable_root: Some(root),
+            profile_dir: None,
+        }
+    }
+
+    /// 改用命名档案，档案名只能包含字母、数字、`-` 与 `_`
+    pub fn with_profile(self, name: &str) -> Result<Self, String> {
+        let dir = self.profiles().dir(name).map_err(|e| e.to_string())?;
+        if self.is_portable() {
+            return Ok(Self {
+                profile_dir: Some(dir.clone()),
+                ..Self::portable(dir)
+            });
+        }
+        Ok(Self {
+            config_file: dir.join(CONFIG_FILE),
+            portable_root: None,
+            profile_dir: Some(dir),
+        })
+    }
+
+    /// 档案所在的目录（以档案启动时为该档案的上一级目录）
+    pub fn profiles_root(&self) -> PathBuf {
+        match self.profile_dir.as_deref().and_then(Path::parent) {
+            Some(root) => root.to_path_buf(),
+            None => self
+                .config_file
+                .parent()
+                .unwrap_or(Path::new(""))
+                .join(PROFILES_DIR),
+        }
+    }
+
+    /// 管理档案目录中的档案
+    pub fn profiles(&self) -> ProfileManager {
+        ProfileManager::new(self.profiles_root())
+    }
+
+    /// 是否为便携模式
+    pub fn is_portable(&self) -> bool {
+        self.portable_root.is_some()
+    }
+
+    /// 本地使用统计文件（与配置文件在同一目录）
+    pub fn usage_file(&self) -> PathBuf {
+        self.config_file.with_file_name(USAGE_FILE)
+    }
+
+    /// 默认配置（使用档案时数据文件与备份在档案目录中）
+    pub fn default_config(&self) -> AppConfig {
+        let config = match &self.profile_dir {
+            Some(dir) => AppConfig {
+                db_path: dir.join(DATA_FILE_NAME).to_string_lossy().into_owned(),
+                backups_dir: dir.join(PROFILE_BACKUPS_DIR).to_string_lossy().into_owned(),
+                ..AppConfig::default()
+            },
+            None => AppConfig::default(),
+        };
+        self.enforce(config)
+    }
+
+    /// 便携模式下把配置中的路径限定到数据目录（保留数据文件名，档案的数据文件不变），否则原样返回
+    pub fn enforce(&self, config: AppConfig) -> AppConfig {
+        let Some(root) = &self.portable_root else {
+            return config;
+        };
+        let to_string = |path: PathBuf| path.to_string_lossy().into_owned();
+        let profiles = self.profiles();
+        let db_path = match profiles.find(Path::new(&config.db_path)) {
+            Some(_) => PathBuf::from(&config.db_path),
+            None => root.join(
+                Path::new(&config.db_path)
+                    .file_name()
+                    .unwrap_or(OsStr::new(DATA_FILE_NAME)),
+            ),
+        };
+        AppConfig {
+            db_path: to_string(db_path),
+            plugins_dir: to_string(root.join("plugins")),
+            templates_dir: to_string(root.join("templates")),
+            backups_dir: to_string(root.join("backups")),
+            ..config
+        }
+    }
+
+    /// 读取配置文件（不存在时使用默认配置），便携模式下限定路径
+    pub fn load_config(&self) -> AppConfig {
+        let config = AppConfig::load(&self.config_file)
+            .unwrap_or_else(|e| {
+                tracing::warn!("Failed to load config: {}", e);
+                None
+            })
+            .unwrap_or_else(|| self.default_config());
+        self.enforce(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    #[test]
+    fn test_portable_paths() {
+        let dir = std::env::temp_dir().join(format!("asset-manager-portable-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let exe = dir.join("asset-manager.exe");
+        let root = dir.join(PORTABLE_DATA_DIR);
+
+        assert!(!AppPaths::for_exe(&exe, Vec::new()).is_portable());
+        let paths = AppPaths::for_exe(&exe, vec![PORTABLE_ARG.to_string()]);
+        assert_eq!(paths.portable_root.as_deref(), Some(root.as_path()));
+
+        std::fs::write(dir.join(PORTABLE_MARKER), "").unwrap();
+        let paths = AppPaths::for_exe(&exe, Vec::new());
+        assert_eq!(paths.config_file, root.join(CONFIG_FILE));
+        assert_eq!(paths.usage_file(), root.join(USAGE_FILE));
+
+        // 配置中指向其他位置的路径被限定到数据目录
+        let config = paths.enforce(AppConfig {
+            db_path: "/elsewhere/books.json".to_string(),
+            plugins_dir: "/elsewhere/plugins".to_string(),
+            ..AppConfig::default()
+        });
+        assert_eq!(Path::new(&config.db_path), root.join("books.json"));
+        assert_eq!(Path::new(&config.plugins_dir), root.join("plugins"));
+        assert_eq!(Path::new(&config.backups_dir), root.join("backups"));
+        assert!(Path::new(&paths.default_config().templates_dir).starts_with(&root));
+
+        // 命名档案的配置与默认数据文件在档案目录中
+        let profile = root.join(PROFILES_DIR).join("work");
+        let paths = paths.with_profile("work").unwrap();
+        assert_eq!(paths.config_file, profile.join(CONFIG_FILE));
+        assert!(Path::new(&paths.load_config().db_path).starts_with(&profile));
+        let installed = AppPaths::installed().with_profile("test-1").unwrap();
+        assert_eq!(
+            Path::new(&installed.default_config().db_path),
+            Path::new(PROFILES_DIR).join("test-1").join(DATA_FILE_NAME)
+        );
+        assert!(AppPaths::installed().with_profile("../other").is_err());
+
+        // 切换到其他档案的数据文件时不限定到档案目录
+        assert_eq!(paths.profiles_root(), root.join(PROFILES_DIR));
+        let personal = paths.profiles().db_path("personal").unwrap();
+        let config = paths.enforce(AppConfig {
+            db_path: personal.to_string_lossy().into_owned(),
+            ..AppConfig::default()
+        });
+        assert_eq!(Path::new(&config.db_path), personal);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,156 @@
+//! 金额舍入与显示精度
+//!
+//! 存储中始终保留原始精度，仅在摘要、报表等展示环节按货币舍入。
+
+use crate::asset::{AssetSummary, Currency};
+use crate::settings::Settings;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// 设置项键名
+pub const PRECISION_SETTING_KEY: &str = "precision";
+
+/// 精度设置项
+pub const PRECISION_SETTINGS: Settings<PrecisionSettings> =
+    Settings::new(PRECISION_SETTING_KEY, PrecisionSettings::default);
+
+/// 舍入模式
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RoundingMode {
+    /// 四舍五入（远离零）
+    #[default]
+    HalfUp,
+    /// 银行家舍入（四舍六入五成双）
+    HalfEven,
+    /// 向零截断
+    Down,
+    /// 远离零进位
+    Up,
+}
+
+impl RoundingMode {
+    /// 按指定小数位舍入
+    pub fn round(&self, value: f64, decimals: u32) -> f64 {
+        let scale = 10f64.powi(decimals as i32);
+        // 先消除 1.005 * 100 = 100.49999... 这类浮点误差
+        let scaled = (value * scale * 1e6).round() / 1e6;
+        let rounded = match self {
+            RoundingMode::HalfUp => scaled.round(),
+            RoundingMode::HalfEven => {
+                let floor = scaled.floor();
+                let diff = scaled - floor;
+                if (diff - 0.5).abs() < 1e-9 {
+                    if floor % 2.0 == 0.0 {
+                        floor
+                    } else {
+                        floor + 1.0
+                    }
+                } else {
+                    scaled.round()
+                }
+            }
+            RoundingMode::Down => scaled.trunc(),
+            RoundingMode::Up => scaled.signum() * scaled.abs().ceil(),
+        };
+        rounded / scale
+    }
+}
+
+/// 精度设置
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PrecisionSettings {
+    /// 舍入模式
+    #[serde(default)]
+    pub rounding_mode: RoundingMode,
+    /// 未单独配置的货币使用的小数位数
+    #[serde(default = "default_decimals")]
+    pub default_decimals: u32,
+    /// 各货币的小数位数（按货币代码）
+    #[serde(default)]
+    pub currency_decimals: HashMap<String, u32>,
+}
+
+fn default_decimals() -> u32 {
+    2
+}
+
+impl Default for PrecisionSettings {
+    fn default() -> Self {
+        let mut currency_decimals = HashMap::new();
+        currency_decimals.insert(Currency::JPY.code().to_string(), 0);
+        Self {
+            rounding_mode: RoundingMode::default(),
+            default_decimals: default_decimals(),
+            currency_decimals,
+        }
+    }
+}
+
+impl PrecisionSettings {
+    /// 货币的显示小数位数
+    pub fn decimals_for(&self, currency: &Currency) -> u32 {
+        self.currency_decimals
+            .get(currency.code())
+            .copied()
+            .unwrap_or(self.default_decimals)
+    }
+
+    /// 按货币舍入金额
+    pub fn round(&self, value: f64, currency: &Currency) -> f64 {
+        self.rounding_mode.round(value, self.decimals_for(currency))
+    }
+
+    /// 按默认小数位舍入（用于跨货币的合计）
+    pub fn round_default(&self, value: f64) -> f64 {
+        self.rounding_mode.round(value, self.default_decimals)
+    }
+
+    /// 格式化为固定小数位的字符串
+    pub fn format(&self, value: f64, currency: &Currency) -> String {
+        let decimals = self.decimals_for(currency);
+        format!(
+            "{:.*}",
+            decimals as usize,
+            self.rounding_mode.round(value, decimals)
+        )
+    }
+
+    /// 舍入统计摘要
+    pub fn round_summary(&self, summary: &AssetSummary) -> AssetSummary {
+        let mut rounded = summary.clone();
+        rounded.total_value = self.round_default(summary.total_value);
+        for value in rounded.by_type.values_mut() {
+            *value = self.round_default(*value);
+        }
+        for (code, value) in rounded.by_currency.iter_mut() {
+            *value = self.round(*value, &Currency::from_code(code));
+        }
+        for summary in rounded.envelopes.iter_mut() {
+            summary.unallocated = self.round(summary.unallocated, &summary.currency);
+            for envelope in summary.envelopes.iter_mut() {
+                envelope.balance = self.round(envelope.balance, &summary.currency);
+            }
+        }
+        rounded
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rounding_modes() {
+        assert_eq!(RoundingMode::HalfUp.round(1.005, 2), 1.01);
+        assert_eq!(RoundingMode::HalfUp.round(-2.5, 0), -3.0);
+        assert_eq!(RoundingMode::HalfEven.round(2.5, 0), 2.0);
+        assert_eq!(RoundingMode::HalfEven.round(3.5, 0), 4.0);
+        assert_eq!(RoundingMode::Down.round(1.239, 2), 1.23);
+        assert_eq!(RoundingMode::Up.round(1.231, 2), 1.24);
+
+        let settings = PrecisionSettings::default();
+        assert_eq!(settings.format(1234.5, &Currency::JPY), "1235");
+        assert_eq!(settings.format(1234.5, &Currency::USD), "1234.50");
+    }
+}
@@ -0,0 +1,198 @@
+//! 账本档案：同一份配置下的多个命名数据文件（如“个人”与“公司”），桌面端运行时可切换
+//!
+//! 档案与 `--profile` 启动参数使用同一目录：`profiles/<档案名>/` 中的数据文件与备份目录。
+//! 各档案的数据文件同名，备份也放在档案目录中，避免不同档案的备份混在一起。
+
+use crate::first_run::DATA_FILE_NAME;
+use crate::storage::{Database, StorageError};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+/// 档案目录中的备份目录名
+pub const PROFILE_BACKUPS_DIR: &str = "backups";
+
+/// 档案信息
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ProfileInfo {
+    pub name: String,
+    pub db_path: String,
+    /// 数据文件大小（字节）
+    pub size: u64,
+    /// 数据文件的修改时间
+    pub modified: Option<DateTime<Utc>>,
+}
+
+/// 档案名只能包含字母、数字、`-` 与 `_`
+pub fn validate_profile_name(name: &str) -> Result<(), StorageError> {
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '-' || c == '_');
+    if !valid {
+        return Err(StorageError::InvalidData(format!(
+            "Invalid profile name: {}",
+            name
+        )));
+    }
+    Ok(())
+}
+
+/// 管理 `root` 目录下的档案
+#[derive(Debug, Clone)]
+pub struct ProfileManager {
+    root: PathBuf,
+}
+
+impl ProfileManager {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// 档案所在的目录
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// 档案目录
+    pub fn dir(&self, name: &str) -> Result<PathBuf, StorageError> {
+        validate_profile_name(name)?;
+        Ok(self.root.join(name))
+    }
+
+    /// 档案的数据文件
+    pub fn db_path(&self, name: &str) -> Result<PathBuf, StorageError> {
+        Ok(self.dir(name)?.join(DATA_FILE_NAME))
+    }
+
+    /// 档案的备份目录
+    pub fn backups_dir(&self, name: &str) -> Result<PathBuf, StorageError> {
+        Ok(self.dir(name)?.join(PROFILE_BACKUPS_DIR))
+    }
+
+    /// 已有数据文件的档案，按名称排序
+    pub fn list(&self) -> Result<Vec<ProfileInfo>, StorageError> {
+        let read_dir = match fs::read_dir(&self.root) {
+            Ok(read_dir) => read_dir,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+        let mut profiles = Vec::new();
+        for entry in read_dir {
+            let name = entry?.file_name().to_string_lossy().into_owned();
+            if validate_profile_name(&name).is_err() {
+                continue;
+            }
+            if let Some(info) = self.info(&name)? {
+                profiles.push(info);
+            }
+        }
+        profiles.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(profiles)
+    }
+
+    /// 档案信息，档案不存在时为 None
+    pub fn info(&self, name: &str) -> Result<Option<ProfileInfo>, StorageError> {
+        let db_path = self.db_path(name)?;
+        let metadata = match fs::metadata(&db_path) {
+            Ok(metadata) if metadata.is_file() => metadata,
+            Ok(_) => return Ok(None),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        Ok(Some(ProfileInfo {
+            name: name.to_string(),
+            db_path: db_path.to_string_lossy().into_owned(),
+            size: metadata.len(),
+            modified: metadata.modified().ok().map(DateTime::<Utc>::from),
+        }))
+    }
+
+    /// 新建档案及其空的数据文件，同名档案已存在时拒绝
+    pub fn create(&self, name: &str) -> Result<ProfileInfo, StorageError> {
+        if self.info(name)?.is_some() {
+            return Err(StorageError::InvalidData(format!(
+                "Profile already exists: {}",
+                name
+            )));
+        }
+        // 打开时写入空的数据文件，关闭时释放锁
+        drop(Database::open(self.db_path(name)?)?);
+        self.info(name)?
+            .ok_or_else(|| StorageError::NotFound(name.to_string()))
+    }
+
+    /// 打开档案的数据文件，档案不存在时不会新建
+    pub fn open(&self, name: &str) -> Result<Database, StorageError> {
+        let Some(info) = self.info(name)? else {
+            return Err(StorageError::NotFound(format!("Profile {}", name)));
+        };
+        Database::open(info.db_path)
+    }
+
+    /// 数据文件所属的档案
+    pub fn find(&self, db_path: &Path) -> Option<String> {
+        let name = db_path
+            .strip_prefix(&self.root)
+            .ok()?
+            .components()
+            .next()
+            .and_then(|c| match c {
+                Component::Normal(name) => name.to_str(),
+                _ => None,
+            })?;
+        (self.db_path(name).ok()? == db_path).then(|| name.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asset::{Asset, AssetType};
+    use uuid::Uuid;
+
+    #[test]
+    fn test_profiles() {
+        let root = std::env::temp_dir().join(format!("asset-manager-profiles-{}", Uuid::new_v4()));
+        let manager = ProfileManager::new(&root);
+        assert!(manager.list().unwrap().is_empty());
+
+        let business = manager.create("business").unwrap();
+        manager.create("个人").unwrap();
+        assert!(manager.create("business").is_err());
+        assert!(manager.create("../other").is_err());
+        // 没有数据文件的目录不算档案
+        fs::create_dir_all(root.join("empty")).unwrap();
+        let names: Vec<String> = manager
+            .list()
+            .unwrap()
+            .into_iter()
+            .map(|p| p.name)
+            .collect();
+        assert_eq!(names, ["business", "个人"]);
+
+        // 各档案的数据互不影响
+        let card = Asset::new("公司账户", AssetType::BankDeposit, 1000.0);
+        {
+            let mut db = manager.open("business").unwrap();
+            db.create_asset(&card).unwrap();
+        }
+        let db = manager.open("个人").unwrap();
+        assert!(db.get_asset(card.id).unwrap().is_none());
+        drop(db);
+        assert!(manager.open("missing").is_err());
+
+        assert_eq!(
+            manager.find(Path::new(&business.db_path)).as_deref(),
+            Some("business")
+        );
+        assert_eq!(
+            manager.find(&root.join("business").join("other.json")),
+            None
+        );
+        assert_eq!(manager.find(Path::new(DATA_FILE_NAME)), None);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
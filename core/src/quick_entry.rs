@@ -0,0 +1,345 @@
+//! 自然语言快速录入
+//!
+//! 将 "bought 10 shares of AAPL at 180 USD"、"花了 56 元 午饭" 这类文本解析为待确认的交易草稿。
+//! 语法规则可插拔：实现 [`GrammarRule`] 并通过 [`QuickEntryParser::add_rule`] 注册。
+
+use crate::asset::{Currency, TransactionType};
+use serde::{Deserialize, Serialize};
+
+/// 快速录入草稿（需用户确认后再落库）
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct QuickEntryDraft {
+    /// 交易类型
+    pub transaction_type: TransactionType,
+    /// 总金额
+    pub amount: f64,
+    /// 货币（未识别时为空）
+    pub currency: Option<Currency>,
+    /// 证券代码或资产名称
+    pub symbol: Option<String>,
+    /// 数量
+    pub quantity: Option<f64>,
+    /// 单价
+    pub price: Option<f64>,
+    /// 备注
+    pub note: Option<String>,
+    /// 匹配的规则名称
+    pub rule: String,
+}
+
+impl QuickEntryDraft {
+    fn new(rule: &str, transaction_type: TransactionType, amount: f64) -> Self {
+        Self {
+            transaction_type,
+            amount,
+            currency: None,
+            symbol: None,
+            quantity: None,
+            price: None,
+            note: None,
+            rule: rule.to_string(),
+        }
+    }
+}
+
+/// 语法规则
+pub trait GrammarRule: Send + Sync {
+    /// 规则名称
+    fn name(&self) -> &str;
+
+    /// 尝试解析文本，不匹配时返回 `None`
+    fn parse(&self, text: &str) -> Option<QuickEntryDraft>;
+}
+
+/// 快速录入解析器，按注册顺序依次尝试各规则
+pub struct QuickEntryParser {
+    rules: Vec<Box<dyn GrammarRule>>,
+}
+
+impl QuickEntryParser {
+    /// 创建包含内置规则的解析器
+    pub fn new() -> Self {
+        let mut parser = Self::empty();
+        parser.add_rule(Box::new(EnglishTradeRule));
+        parser.add_rule(Box::new(EnglishCashRule));
+        parser.add_rule(Box::new(ChineseCashRule));
+        parser
+    }
+
+    /// 创建不含任何规则的解析器
+    pub fn empty() -> Self {
+        Self { rules: Vec::new() }
+    }
+
+    /// 注册规则，排在已注册的规则之后尝试（按注册顺序，先匹配的规则生效）
+    pub fn add_rule(&mut self, rule: Box<dyn GrammarRule>) {
+        self.rules.push(rule);
+    }
+
+    /// 已注册的规则名称
+    pub fn rule_names(&self) -> Vec<&str> {
+        self.rules.iter().map(|r| r.name()).collect()
+    }
+
+    /// 解析文本
+    pub fn parse(&self, text: &str) -> Option<QuickEntryDraft> {
+        let text = text.trim();
+        if text.is_empty() {
+            return None;
+        }
+        self.rules.iter().find_map(|rule| rule.parse(text))
+    }
+}
+
+impl Default for QuickEntryParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// ============ 内置规则 ============
+
+/// 英文交易："bought 10 shares of AAPL at 180 USD"、"sell 5 TSLA @ 250"
+pub struct EnglishTradeRule;
+
+impl GrammarRule for EnglishTradeRule {
+    fn name(&self) -> &str {
+        "english_trade"
+    }
+
+    fn parse(&self, text: &str) -> Option<QuickEntryDraft> {
+        let tokens: Vec<&str> = text.split_whitespace().collect();
+        let verb = tokens.first()?.to_lowercase();
+        let transaction_type = match verb.as_str() {
+            "bought" | "buy" => TransactionType::Buy,
+            "sold" | "sell" => TransactionType::Sell,
+            _ => return None,
+        };
+
+        let quantity = parse_number(tokens.get(1)?)?;
+        let mut rest = tokens[2..].iter().copied().peekable();
+        while let Some(word) = rest.peek() {
+            match word.to_lowercase().as_str() {
+                "shares" | "share" | "units" | "unit" | "of" => {
+                    rest.next();
+                }
+                _ => break,
+            }
+        }
+        let symbol = rest.next()?.to_uppercase();
+        let at = rest.next()?;
+        if !matches!(at.to_lowercase().as_str(), "at" | "@") {
+            return None;
+        }
+        let (price, mut currency) = parse_amount_token(rest.next()?)?;
+        if let Some(word) = rest.next() {
+            currency = currency.or_else(|| parse_currency_word(word));
+        }
+
+        let mut draft = QuickEntryDraft::new(self.name(), transaction_type, quantity * price);
+        draft.symbol = Some(symbol);
+        draft.quantity = Some(quantity);
+        draft.price = Some(price);
+        draft.currency = currency;
+        Some(draft)
+    }
+}
+
+/// 英文收支："spent 12.5 USD on lunch"、"received 100 salary"
+pub struct EnglishCashRule;
+
+impl GrammarRule for EnglishCashRule {
+    fn name(&self) -> &str {
+        "english_cash"
+    }
+
+    fn parse(&self, text: &str) -> Option<QuickEntryDraft> {
+        let tokens: Vec<&str> = text.split_whitespace().collect();
+        let transaction_type = match tokens.first()?.to_lowercase().as_str() {
+            "spent" | "spend" | "paid" | "pay" => TransactionType::Expense,
+            "received" | "receive" | "earned" | "earn" | "got" => TransactionType::Income,
+            _ => return None,
+        };
+
+        let (amount, mut currency) = parse_amount_token(tokens.get(1)?)?;
+        let mut rest = &tokens[2..];
+        if let Some(c) = rest.first().and_then(|w| parse_currency_word(w)) {
+            currency = currency.or(Some(c));
+            rest = &rest[1..];
+        }
+        if let Some(first) = rest.first() {
+            if matches!(first.to_lowercase().as_str(), "on" | "for" | "from") {
+                rest = &rest[1..];
+            }
+        }
+
+        let mut draft = QuickEntryDraft::new(self.name(), transaction_type, amount);
+        draft.currency = currency;
+        draft.note = Some(rest.join(" ")).filter(|n| !n.is_empty());
+        Some(draft)
+    }
+}
+
+/// 中文收支："花了 56 元 午饭"、"午饭花了56块"、"收到工资 8000 元"
+pub struct ChineseCashRule;
+
+const EXPENSE_VERBS: [&str; 4] = ["花了", "花费", "支出", "付了"];
+const INCOME_VERBS: [&str; 5] = ["收到", "收入", "赚了", "进账", "到账"];
+/// 单字动词在两类多字动词都不匹配时才尝试，避免误配 "花呗" 这类词中的字
+const EXPENSE_FALLBACK: &str = "花";
+
+impl GrammarRule for ChineseCashRule {
+    fn name(&self) -> &str {
+        "chinese_cash"
+    }
+
+    fn parse(&self, text: &str) -> Option<QuickEntryDraft> {
+        let (transaction_type, verb) = EXPENSE_VERBS
+            .iter()
+            .map(|v| (TransactionType::Expense, *v))
+            .chain(INCOME_VERBS.iter().map(|v| (TransactionType::Income, *v)))
+            .chain([(TransactionType::Expense, EXPENSE_FALLBACK)])
+            .find(|(_, v)| text.contains(v))?;
+
+        // 找到第一个数字
+        let start = text.find(|c: char| c.is_ascii_digit())?;
+        let end = text[start..]
+            .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == ','))
+            .map(|i| start + i)
+            .unwrap_or(text.len());
+        let amount = parse_number(&text[start..end])?;
+
+        let mut after = text[end..].trim_start();
+        let mut currency = None;
+        for (unit, c) in CHINESE_UNITS {
+            if let Some(rest) = after.strip_prefix(unit) {
+                currency = Some(c.clone());
+                after = rest;
+                break;
+            }
+        }
+
+        let before = text[..start].replacen(verb, "", 1);
+        let before = before.trim_end_matches(['¥', '￥']);
+        let after = after.replacen(verb, "", 1);
+        let note = format!("{} {}", before.trim(), after.trim());
+
+        let mut draft = QuickEntryDraft::new(self.name(), transaction_type, amount);
+        draft.currency = currency.or_else(|| {
+            text[..start]
+                .trim_end()
+                .ends_with(['¥', '￥'])
+                .then_some(Currency::CNY)
+        });
+        draft.note = Some(note.trim().to_string()).filter(|n| !n.is_empty());
+        Some(draft)
+    }
+}
+
+const CHINESE_UNITS: [(&str, Currency); 9] = [
+    ("人民币", Currency::CNY),
+    ("美元", Currency::USD),
+    ("港币", Currency::HKD),
+    ("港元", Currency::HKD),
+    ("欧元", Currency::EUR),
+    ("日元", Currency::JPY),
+    ("英镑", Currency::GBP),
+    ("元", Currency::CNY),
+    ("块", Currency::CNY),
+];
+
+// ============ 辅助函数 ============
+
+fn parse_number(s: &str) -> Option<f64> {
+    s.replace(',', "")
+        .parse()
+        .ok()
+        .filter(|n: &f64| n.is_finite())
+}
+
+/// 解析金额，支持 "$180"、"¥56" 这类带符号的写法
+fn parse_amount_token(token: &str) -> Option<(f64, Option<Currency>)> {
+    let first = token.chars().next()?;
+    let currency = match first {
+        '$' => Some(Currency::USD),
+        '¥' | '￥' => Some(Currency::CNY),
+        '€' => Some(Currency::EUR),
+        '£' => Some(Currency::GBP),
+        _ => None,
+    };
+    let number = match currency {
+        Some(_) => &token[first.len_utf8()..],
+        None => token,
+    };
+    Some((parse_number(number)?, currency))
+}
+
+fn parse_currency_word(word: &str) -> Option<Currency> {
+    let is_code = word.len() == 3 && word.chars().all(|c| c.is_ascii_alphabetic());
+    match word.to_lowercase().as_str() {
+        "yuan" | "rmb" => Some(Currency::CNY),
+        "dollars" | "dollar" | "bucks" => Some(Currency::USD),
+        "euros" | "euro" => Some(Currency::EUR),
+        _ if is_code && word.chars().all(|c| c.is_ascii_uppercase()) => {
+            Some(Currency::from_code(word))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_rules() {
+        let parser = QuickEntryParser::new();
+
+        let draft = parser.parse("bought 10 shares of AAPL at 180 USD").unwrap();
+        assert_eq!(draft.transaction_type, TransactionType::Buy);
+        assert_eq!(draft.symbol.as_deref(), Some("AAPL"));
+        assert_eq!(draft.amount, 1800.0);
+        assert_eq!(draft.currency, Some(Currency::USD));
+
+        let draft = parser.parse("花了 56 元 午饭").unwrap();
+        assert_eq!(draft.transaction_type, TransactionType::Expense);
+        assert_eq!(draft.amount, 56.0);
+        assert_eq!(draft.currency, Some(Currency::CNY));
+        assert_eq!(draft.note.as_deref(), Some("午饭"));
+
+        let draft = parser.parse("收到花呗退款 200 元").unwrap();
+        assert_eq!(draft.transaction_type, TransactionType::Income);
+        assert_eq!(draft.note.as_deref(), Some("花呗退款"));
+        let draft = parser.parse("午饭花56块").unwrap();
+        assert_eq!(draft.transaction_type, TransactionType::Expense);
+
+        let draft = parser.parse("spent $12.5 on lunch").unwrap();
+        assert_eq!(draft.amount, 12.5);
+        assert_eq!(draft.note.as_deref(), Some("lunch"));
+
+        assert!(parser.parse("hello world").is_none());
+    }
+
+    #[test]
+    fn test_custom_rule() {
+        struct SalaryRule;
+        impl GrammarRule for SalaryRule {
+            fn name(&self) -> &str {
+                "salary"
+            }
+            fn parse(&self, text: &str) -> Option<QuickEntryDraft> {
+                let amount = parse_number(text.strip_prefix("salary ")?)?;
+                Some(QuickEntryDraft::new(
+                    self.name(),
+                    TransactionType::Income,
+                    amount,
+                ))
+            }
+        }
+
+        let mut parser = QuickEntryParser::empty();
+        parser.add_rule(Box::new(SalaryRule));
+        assert_eq!(parser.parse("salary 8000").unwrap().rule, "salary");
+        assert!(parser.parse("花了 56 元 午饭").is_none());
+    }
+}
@@ -0,0 +1,144 @@
+//! 报告：月度报告数据与基于模板的 HTML 渲染（用于打印、邮件或转换为 PDF）
+
+mod template;
+
+pub use template::*;
+
+use crate::analytics::{Attribution, DateRange};
+use crate::asset::AssetSummary;
+use crate::format::Formatter;
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+
+/// 月度报告
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MonthlyReport {
+    /// 年份
+    pub year: i32,
+    /// 月份（1~12）
+    pub month: u32,
+    /// 当前资产概况
+    pub summary: AssetSummary,
+    /// 当月收益归因（按资产类型）
+    pub attribution: Attribution,
+    /// 当月交易笔数
+    pub transaction_count: usize,
+    /// 生成时间
+    pub generated_at: DateTime<Utc>,
+}
+
+impl MonthlyReport {
+    /// 某月的时间范围（首日零点至月末最后一刻）
+    pub fn month_range(year: i32, month: u32) -> Option<DateRange> {
+        let start = NaiveDate::from_ymd_opt(year, month, 1)?;
+        let next = if month == 12 {
+            NaiveDate::from_ymd_opt(year + 1, 1, 1)?
+        } else {
+            NaiveDate::from_ymd_opt(year, month + 1, 1)?
+        };
+        let start = start.and_hms_opt(0, 0, 0)?.and_utc();
+        let end = next.and_hms_opt(0, 0, 0)?.and_utc() - Duration::milliseconds(1);
+        Some(DateRange::new(start, end))
+    }
+
+    /// 期间标识，如 "2024-03"
+    pub fn label(&self) -> String {
+        format!("{:04}-{:02}", self.year, self.month)
+    }
+
+    /// 邮件标题
+    pub fn subject(&self) -> String {
+        format!("资产月报 {}", self.label())
+    }
+
+    /// 模板上下文：`title`、`report`、按类型排序的 `allocation` 与 `generated_at`
+    pub fn context(&self) -> Value {
+        let mut allocation: Vec<(&String, &f64)> = self.summary.by_type.iter().collect();
+        allocation.sort_by(|a, b| b.1.total_cmp(a.1));
+        json!({
+            "title": self.subject(),
+            "report": self,
+            "allocation": allocation
+                .into_iter()
+                .map(|(key, value)| json!({ "key": key, "value": value }))
+                .collect::<Vec<_>>(),
+            "generated_at": self.generated_at.format("%Y-%m-%d %H:%M UTC").to_string(),
+        })
+    }
+
+    /// 使用内置 `monthly` 模板渲染为 HTML
+    pub fn to_html(&self) -> String {
+        self.to_html_with(&Formatter::default())
+    }
+
+    /// 使用内置 `monthly` 模板按指定格式渲染为 HTML
+    pub fn to_html_with(&self, formatter: &Formatter) -> String {
+        ReportRenderer::builtin()
+            .with_formatter(formatter.clone())
+            .render("monthly", &self.context())
+            .unwrap_or_default()
+    }
+}
+
+/// 报告渲染参数
+///
+/// 指定 `year` 与 `month` 时上下文中包含该月的 `report`；其余字段原样放入 `params`，
+/// 供自定义模板使用。
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ReportParams {
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub year: Option<i32>,
+    #[serde(default)]
+    pub month: Option<u32>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_month_range() {
+        let range = MonthlyReport::month_range(2024, 12).unwrap();
+        assert_eq!(range.start.to_rfc3339(), "2024-12-01T00:00:00+00:00");
+        assert!(
+            range.end
+                < NaiveDate::from_ymd_opt(2025, 1, 1)
+                    .unwrap()
+                    .and_hms_opt(0, 0, 0)
+                    .unwrap()
+                    .and_utc()
+        );
+        assert!(MonthlyReport::month_range(2024, 13).is_none());
+    }
+
+    #[test]
+    fn test_render_monthly_report() {
+        let mut db = crate::Database::open_in_memory().unwrap();
+        db.create_asset(&crate::Asset::new(
+            "储蓄卡",
+            crate::AssetType::BankDeposit,
+            1000.0,
+        ))
+        .unwrap();
+
+        let html = db.get_monthly_report(2024, 3).unwrap().to_html();
+        assert!(html.contains("资产月报 2024-03"));
+        assert!(html.contains("bank_deposit：1000.00"));
+
+        let params = ReportParams {
+            title: Some("清单".to_string()),
+            ..Default::default()
+        };
+        let context = db.report_context(&params).unwrap();
+        let html = ReportRenderer::builtin()
+            .render("assets", &context)
+            .unwrap();
+        assert!(html.contains("<title>清单</title>"));
+        assert!(html.contains("储蓄卡"));
+    }
+}
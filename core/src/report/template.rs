@@ -0,0 +1,235 @@
+//! 报告模板：基于 Handlebars 渲染可打印的 HTML
+//!
+//! 内置 `monthly`（月度报告）与 `assets`（资产清单）两个模板。用户可在模板目录中放置
+//! `<名称>.hbs` 文件新增模板，或覆盖同名的内置模板。模板中可用与界面相同的格式设置格式化数值：
+//! `{{money value}}`（可加货币代码 `{{money value currency}}`）、`{{compact value}}` 与 `{{date value}}`。
+
+use crate::asset::Currency;
+use crate::format::Formatter;
+use chrono::{DateTime, NaiveDate, Utc};
+use handlebars::{
+    Context, Handlebars, Helper, HelperDef, HelperResult, Output, RenderContext, RenderErrorReason,
+};
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+use tracing::warn;
+
+/// 模板文件扩展名
+pub const TEMPLATE_EXTENSION: &str = "hbs";
+
+/// 内置模板
+pub const BUILTIN_TEMPLATES: &[(&str, &str)] = &[
+    ("monthly", include_str!("templates/monthly.hbs")),
+    ("assets", include_str!("templates/assets.hbs")),
+];
+
+/// 模板错误
+#[derive(Debug, thiserror::Error)]
+pub enum TemplateError {
+    #[error("Template not found: {0}")]
+    NotFound(String),
+
+    #[error("Invalid template {0}: {1}")]
+    Invalid(String, String),
+
+    #[error("Render failed: {0}")]
+    Render(String),
+}
+
+/// 模板中的格式化助手
+#[derive(Clone, Copy)]
+enum FormatHelperKind {
+    Money,
+    Compact,
+    Date,
+}
+
+struct FormatHelper {
+    kind: FormatHelperKind,
+    formatter: Formatter,
+}
+
+impl HelperDef for FormatHelper {
+    fn call<'reg: 'rc, 'rc>(
+        &self,
+        h: &Helper<'rc>,
+        _: &'reg Handlebars<'reg>,
+        _: &'rc Context,
+        _: &mut RenderContext<'reg, 'rc>,
+        out: &mut dyn Output,
+    ) -> HelperResult {
+        let value = h
+            .param(0)
+            .map(|p| p.value())
+            .ok_or_else(|| RenderErrorReason::Other(format!("{} needs a value", h.name())))?;
+        let text = match self.kind {
+            FormatHelperKind::Date => match value.as_str() {
+                Some(s) => format_date(&self.formatter, s),
+                None => String::new(),
+            },
+            kind => {
+                let number = value.as_f64().ok_or_else(|| {
+                    RenderErrorReason::Other(format!("{} needs a number", h.name()))
+                })?;
+                match (kind, h.param(1).and_then(|p| p.value().as_str())) {
+                    (FormatHelperKind::Compact, _) => self.formatter.compact(number),
+                    (_, Some(code)) => self.formatter.money(number, &Currency::from_code(code)),
+                    _ => self.formatter.number_default(number),
+                }
+            }
+        };
+        out.write(&text)?;
+        Ok(())
+    }
+}
+
+/// 格式化日期或 RFC 3339 时间，无法解析时转义后原样输出
+fn format_date(formatter: &Formatter, s: &str) -> String {
+    if let Ok(date) = s.parse::<NaiveDate>() {
+        return formatter.date(date);
+    }
+    match DateTime::parse_from_rfc3339(s) {
+        Ok(t) => formatter.datetime(t.with_timezone(&Utc)),
+        Err(_) => handlebars::html_escape(s),
+    }
+}
+
+/// 报告渲染器
+pub struct ReportRenderer {
+    registry: Handlebars<'static>,
+}
+
+impl ReportRenderer {
+    /// 仅包含内置模板的渲染器（使用默认格式）
+    pub fn builtin() -> Self {
+        let mut registry = Handlebars::new();
+        for (name, source) in BUILTIN_TEMPLATES {
+            registry
+                .register_template_string(name, source)
+                .expect("builtin templates are valid");
+        }
+        Self { registry }.with_formatter(Formatter::default())
+    }
+
+    /// 模板中的格式化助手使用指定的格式
+    pub fn with_formatter(mut self, formatter: Formatter) -> Self {
+        for (name, kind) in [
+            ("money", FormatHelperKind::Money),
+            ("compact", FormatHelperKind::Compact),
+            ("date", FormatHelperKind::Date),
+        ] {
+            let helper = FormatHelper {
+                kind,
+                formatter: formatter.clone(),
+            };
+            self.registry.register_helper(name, Box::new(helper));
+        }
+        self
+    }
+
+    /// 加载模板目录中的用户模板（目录不存在时只使用内置模板）
+    ///
+    /// 无法解析的模板会被跳过并记录警告，不影响其他模板。
+    pub fn with_templates_dir(dir: impl AsRef<Path>) -> Self {
+        let mut renderer = Self::builtin();
+        let Ok(entries) = fs::read_dir(dir.as_ref()) else {
+            return renderer;
+        };
+        for path in entries.filter_map(|e| e.ok().map(|e| e.path())) {
+            if path.extension().and_then(|e| e.to_str()) != Some(TEMPLATE_EXTENSION) {
+                continue;
+            }
+            let Some(name) = path.file_stem().and_then(|s| s.to_str()) else {
+                continue;
+            };
+            if let Err(e) = renderer.register(name, &path) {
+                warn!("Skipping report template {:?}: {}", path, e);
+            }
+        }
+        renderer
+    }
+
+    fn register(&mut self, name: &str, path: &Path) -> Result<(), TemplateError> {
+        let source = fs::read_to_string(path)
+            .map_err(|e| TemplateError::Invalid(name.to_string(), e.to_string()))?;
+        self.registry
+            .register_template_string(name, source)
+            .map_err(|e| TemplateError::Invalid(name.to_string(), e.to_string()))
+    }
+
+    /// 可用的模板名称（已排序）
+    pub fn template_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.registry.get_templates().keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    /// 渲染模板
+    pub fn render<T: Serialize>(&self, name: &str, data: &T) -> Result<String, TemplateError> {
+        if !self.registry.has_template(name) {
+            return Err(TemplateError::NotFound(name.to_string()));
+        }
+        self.registry
+            .render(name, data)
+            .map_err(|e| TemplateError::Render(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_custom_template_overrides_builtin() {
+        let dir = std::env::temp_dir().join(format!("asset-manager-tpl-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("assets.hbs"), "<p>{{title}}: {{money total}}</p>").unwrap();
+        fs::write(dir.join("broken.hbs"), "{{#each}}").unwrap();
+        fs::write(dir.join("readme.txt"), "ignored").unwrap();
+
+        let renderer = ReportRenderer::with_templates_dir(&dir);
+        assert_eq!(renderer.template_names(), vec!["assets", "monthly"]);
+        let html = renderer
+            .render("assets", &json!({ "title": "<净资产>", "total": 1234.5 }))
+            .unwrap();
+        assert_eq!(html, "<p>&lt;净资产&gt;: 1234.50</p>");
+        assert!(matches!(
+            renderer.render("missing", &json!({})),
+            Err(TemplateError::NotFound(_))
+        ));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_format_helpers() {
+        use crate::format::{DateStyle, FormatSettings, NumberGrouping};
+        use crate::precision::PrecisionSettings;
+
+        let settings = FormatSettings {
+            grouping: NumberGrouping::Thousands,
+            date_style: DateStyle::Locale,
+        };
+        let mut renderer = ReportRenderer::builtin().with_formatter(Formatter::new(
+            "zh-CN",
+            settings,
+            PrecisionSettings::default(),
+        ));
+        renderer
+            .registry
+            .register_template_string(
+                "t",
+                "{{money a}}|{{money b \"JPY\"}}|{{compact a}}|{{date d}}|{{date \"n/a\"}}",
+            )
+            .unwrap();
+        let text = renderer
+            .render(
+                "t",
+                &json!({ "a": 1234567.5, "b": 1234.5, "d": "2024-03-05" }),
+            )
+            .unwrap();
+        assert_eq!(text, "1,234,567.50|1,235|123.46万|2024年3月5日|n/a");
+        assert!(renderer.render("t", &json!({ "a": "x" })).is_err());
+    }
+}
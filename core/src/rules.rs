@@ -0,0 +1,369 @@
+//! 自动化规则（"如果……就……"）
+//!
+//! 规则由触发方式、条件与动作组成，保存在数据库中：
+//! - 事件触发：资产新建、修改、删除或记录交易后，对相关资产求值；
+//! - 定时触发：按周期到期时执行；
+//! - 条件触发：定期检查，条件从不满足变为满足时执行一次。
+//!
+//! 条件涉及资产（类型、标签、价值等）时逐个资产求值，动作作用于满足条件的资产；
+//! 只涉及总资产时整体求值一次。规则动作产生的修改不会再触发其他规则，避免连锁执行。
+//! 调用插件的动作由调用方（桌面端或命令行）执行。
+
+use crate::asset::{Asset, AssetType, Recurrence, TransactionType};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// 定期检查定时与条件规则的间隔（秒）
+pub const RULES_CHECK_INTERVAL_SECS: u64 = 60;
+
+/// 触发规则的事件
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleEvent {
+    /// 资产新建
+    AssetCreated,
+    /// 资产修改
+    AssetUpdated,
+    /// 资产删除
+    AssetDeleted,
+    /// 记录交易
+    TransactionAdded,
+}
+
+/// 规则的触发方式
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum RuleTrigger {
+    /// 发生事件时
+    Event { event: RuleEvent },
+    /// 按周期定时
+    Schedule {
+        recurrence: Recurrence,
+        /// 下一次执行时间
+        next_run: DateTime<Utc>,
+    },
+    /// 条件从不满足变为满足时
+    Condition,
+}
+
+/// 比较方式
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Comparison {
+    Above,
+    AtLeast,
+    Below,
+    AtMost,
+    Equal,
+}
+
+impl Comparison {
+    /// `left` 与 `right` 是否满足比较
+    pub fn holds(&self, left: f64, right: f64) -> bool {
+        match self {
+            Comparison::Above => left > right,
+            Comparison::AtLeast => left >= right,
+            Comparison::Below => left < right,
+            Comparison::AtMost => left <= right,
+            Comparison::Equal => (left - right).abs() < 1e-9,
+        }
+    }
+}
+
+/// 规则条件
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum RuleCondition {
+    /// 资产类型
+    AssetType { asset_type: AssetType },
+    /// 资产带有标签
+    HasTag { tag: String },
+    /// 资产名称包含文本（不区分大小写）
+    NameContains { text: String },
+    /// 资产价值比较
+    AssetValue { op: Comparison, value: f64 },
+    /// 总资产比较
+    NetWorth { op: Comparison, value: f64 },
+    /// 全部满足
+    All { conditions: Vec<RuleCondition> },
+    /// 任一满足
+    Any { conditions: Vec<RuleCondition> },
+    /// 不满足
+    Not { condition: Box<RuleCondition> },
+}
+
+/// 求值时的数据
+#[derive(Debug, Clone, Copy)]
+pub struct RuleContext<'a> {
+    /// 当前求值的资产（整体求值或资产已删除时为空）
+    pub asset: Option<&'a Asset>,
+    /// 总资产
+    pub net_worth: f64,
+}
+
+impl RuleCondition {
+    /// 是否需要针对资产求值
+    pub fn needs_asset(&self) -> bool {
+        match self {
+            RuleCondition::NetWorth { .. } => false,
+            RuleCondition::All { conditions } | RuleCondition::Any { conditions } => {
+                conditions.iter().any(RuleCondition::needs_asset)
+            }
+            RuleCondition::Not { condition } => condition.needs_asset(),
+            _ => true,
+        }
+    }
+
+    /// 求值（涉及资产的条件在没有资产时不满足）
+    pub fn eval(&self, ctx: &RuleContext) -> bool {
+        match self {
+            RuleCondition::AssetType { asset_type } => {
+                ctx.asset.is_some_and(|a| &a.asset_type == asset_type)
+            }
+            RuleCondition::HasTag { tag } => ctx.asset.is_some_and(|a| a.tags.contains(tag)),
+            RuleCondition::NameContains { text } => ctx
+                .asset
+                .is_some_and(|a| a.name.to_lowercase().contains(&text.to_lowercase())),
+            RuleCondition::AssetValue { op, value } => {
+                ctx.asset.is_some_and(|a| op.holds(a.value, *value))
+            }
+            RuleCondition::NetWorth { op, value } => op.holds(ctx.net_worth, *value),
+            RuleCondition::All { conditions } => conditions.iter().all(|c| c.eval(ctx)),
+            RuleCondition::Any { conditions } => conditions.iter().any(|c| c.eval(ctx)),
+            RuleCondition::Not { condition } => !condition.eval(ctx),
+        }
+    }
+}
+
+/// 规则动作
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum RuleAction {
+    /// 给资产加标签
+    AddTag { tag: String },
+    /// 记录交易（未指定资产时作用于当前资产）
+    CreateTransaction {
+        #[serde(default)]
+        asset_id: Option<Uuid>,
+        delta: f64,
+        transaction_type: TransactionType,
+        #[serde(default)]
+        note: Option<String>,
+    },
+    /// 生成应用内通知，标题与内容中的 `{asset}` 替换为资产名称
+    Notify {
+        title: String,
+        #[serde(default)]
+        message: Option<String>,
+    },
+    /// 调用插件的处理函数
+    CallPlugin {
+        plugin: String,
+        handler: String,
+        #[serde(default)]
+        args: serde_json::Value,
+    },
+}
+
+/// 自动化规则
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Rule {
+    /// 唯一标识符
+    pub id: Uuid,
+    /// 名称
+    pub name: String,
+    /// 是否启用
+    pub enabled: bool,
+    /// 触发方式
+    pub trigger: RuleTrigger,
+    /// 条件（全部满足才执行，为空表示总是执行）
+    #[serde(default)]
+    pub conditions: Vec<RuleCondition>,
+    /// 动作（按顺序执行）
+    pub actions: Vec<RuleAction>,
+    /// 最近一次执行时间
+    #[serde(default)]
+    pub last_run: Option<DateTime<Utc>>,
+    /// 最近一次执行的错误
+    #[serde(default)]
+    pub last_error: Option<String>,
+    /// 条件触发：上次检查时满足条件的资产（整体求值时为空值）
+    #[serde(default)]
+    pub matched: Vec<Option<Uuid>>,
+    /// 创建时间
+    pub created_at: DateTime<Utc>,
+}
+
+impl Rule {
+    /// 创建规则，条件与动作为空
+    pub fn new(name: impl Into<String>, trigger: RuleTrigger) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            name: name.into(),
+            enabled: true,
+            trigger,
+            conditions: Vec::new(),
+            actions: Vec::new(),
+            last_run: None,
+            last_error: None,
+            matched: Vec::new(),
+            created_at: Utc::now(),
+        }
+    }
+
+    /// 添加条件
+    pub fn when(mut self, condition: RuleCondition) -> Self {
+        self.conditions.push(condition);
+        self
+    }
+
+    /// 添加动作
+    pub fn then(mut self, action: RuleAction) -> Self {
+        self.actions.push(action);
+        self
+    }
+
+    /// 是否逐个资产求值
+    pub fn per_asset(&self) -> bool {
+        self.conditions.iter().any(RuleCondition::needs_asset)
+    }
+
+    /// 条件是否全部满足
+    pub fn matches(&self, ctx: &RuleContext) -> bool {
+        self.conditions.iter().all(|c| c.eval(ctx))
+    }
+
+    /// 定时规则是否到期
+    pub fn is_due(&self, now: DateTime<Utc>) -> bool {
+        matches!(self.trigger, RuleTrigger::Schedule { next_run, .. } if next_run <= now)
+    }
+
+    /// 定时规则执行后推进到下一个周期（跳过已错过的周期）
+    pub fn advance_schedule(&mut self, now: DateTime<Utc>) {
+        if let RuleTrigger::Schedule {
+            recurrence,
+            next_run,
+        } = &mut self.trigger
+        {
+            while *next_run <= now {
+                let next = recurrence.advance(*next_run);
+                if next == *next_run {
+                    break;
+                }
+                *next_run = next;
+            }
+        }
+    }
+
+    /// 检查规则是否有效
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("Rule name is required".to_string());
+        }
+        if self.actions.is_empty() {
+            return Err("Rule needs at least one action".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// 驱动规则求值的信号
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RuleSignal {
+    /// 发生事件（关联的资产）
+    Event { event: RuleEvent, asset_id: Uuid },
+    /// 定期检查定时与条件规则
+    Tick,
+}
+
+/// 需要调用方执行的插件调用
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PluginCall {
+    pub plugin: String,
+    pub handler: String,
+    /// 参数；有当前资产时附加 `asset` 字段
+    pub args: serde_json::Value,
+}
+
+/// 规则的一次执行
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RuleRun {
+    /// 规则ID
+    pub rule_id: Uuid,
+    /// 执行时的资产
+    pub asset_id: Option<Uuid>,
+    /// 需要执行的插件调用
+    pub plugin_calls: Vec<PluginCall>,
+    /// 执行失败的原因（之前的动作已生效）
+    pub error: Option<String>,
+}
+
+/// 替换文本中的 `{asset}`
+pub fn render_text(text: &str, asset: Option<&Asset>) -> String {
+    match asset {
+        Some(asset) => text.replace("{asset}", &asset.name),
+        None => text.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn test_rule_conditions() {
+        let stock = Asset::new("招商银行", AssetType::Stock, 800.0).with_tags(vec!["A股".into()]);
+        let ctx = RuleContext {
+            asset: Some(&stock),
+            net_worth: 5000.0,
+        };
+        let rule = Rule::new("低价股票", RuleTrigger::Condition)
+            .when(RuleCondition::AssetType {
+                asset_type: AssetType::Stock,
+            })
+            .when(RuleCondition::AssetValue {
+                op: Comparison::Below,
+                value: 1000.0,
+            });
+        assert!(rule.per_asset());
+        assert!(rule.matches(&ctx));
+        assert!(!rule.matches(&RuleContext { asset: None, ..ctx }));
+
+        let portfolio = Rule::new("百万", RuleTrigger::Condition).when(RuleCondition::Any {
+            conditions: vec![RuleCondition::NetWorth {
+                op: Comparison::AtLeast,
+                value: 1_000_000.0,
+            }],
+        });
+        assert!(!portfolio.per_asset());
+        assert!(!portfolio.matches(&ctx));
+        assert!(RuleCondition::Not {
+            condition: Box::new(RuleCondition::HasTag {
+                tag: "港股".into()
+            })
+        }
+        .eval(&ctx));
+        assert_eq!(
+            render_text("{asset} 跌破 1000", Some(&stock)),
+            "招商银行 跌破 1000"
+        );
+    }
+
+    #[test]
+    fn test_schedule_advance() {
+        let now = Utc::now();
+        let mut rule = Rule::new(
+            "每周",
+            RuleTrigger::Schedule {
+                recurrence: Recurrence::Weekly,
+                next_run: now - Duration::days(15),
+            },
+        );
+        assert!(rule.is_due(now));
+        rule.advance_schedule(now);
+        assert!(!rule.is_due(now));
+        assert!(matches!(rule.trigger, RuleTrigger::Schedule { next_run, .. } if next_run > now));
+    }
+}
@@ -0,0 +1,163 @@
+//! 搜索匹配：子串、模糊与拼音匹配
+//!
+//! 除了普通的子串匹配，还支持模糊匹配（查询的字符按顺序出现即可，如 "zsyh" 匹配 "zhaoshang yinhang"）
+//! 与拼音匹配（全拼、首字母或混合，如 "zs"、"zhaoshang"、"zsyinhang" 都能找到 "招商银行"）。
+//! 多音字的每个读音都参与匹配。
+
+use crate::settings::Settings;
+use pinyin::ToPinyinMulti;
+use serde::{Deserialize, Serialize};
+
+/// 设置项键名
+pub const SEARCH_SETTING_KEY: &str = "search";
+
+/// 搜索设置项
+pub const SEARCH_SETTINGS: Settings<SearchSettings> =
+    Settings::new(SEARCH_SETTING_KEY, SearchSettings::default);
+
+/// 搜索设置
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SearchSettings {
+    /// 是否启用模糊匹配
+    #[serde(default = "enabled")]
+    pub fuzzy: bool,
+    /// 是否启用拼音匹配
+    #[serde(default = "enabled")]
+    pub pinyin: bool,
+}
+
+fn enabled() -> bool {
+    true
+}
+
+impl Default for SearchSettings {
+    fn default() -> Self {
+        Self {
+            fuzzy: enabled(),
+            pinyin: enabled(),
+        }
+    }
+}
+
+impl SearchSettings {
+    /// 文本是否匹配查询（不区分大小写，空查询匹配全部）
+    pub fn matches(&self, query: &str, text: &str) -> bool {
+        self.score(query, text).is_some()
+    }
+
+    /// 匹配程度，越大越相关，不匹配时为 None：完全相同 > 开头相同 > 包含 > 拼音 > 模糊
+    pub fn score(&self, query: &str, text: &str) -> Option<u32> {
+        let query = query.trim().to_lowercase();
+        let text = text.to_lowercase();
+        if query.is_empty() {
+            Some(0)
+        } else if text == query {
+            Some(5)
+        } else if text.starts_with(&query) {
+            Some(4)
+        } else if text.contains(&query) {
+            Some(3)
+        } else if self.pinyin && pinyin_match(&query, &text) {
+            Some(2)
+        } else if self.fuzzy && fuzzy_match(&query, &text) {
+            Some(1)
+        } else {
+            None
+        }
+    }
+}
+
+/// 查询中的非空白字符是否按顺序出现在文本中
+fn fuzzy_match(query: &str, text: &str) -> bool {
+    let mut chars = text.chars();
+    query
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .all(|q| chars.any(|c| c == q))
+}
+
+/// 每个字符的候选读音：汉字为各读音的拼音，其他字符为其本身
+fn readings(text: &str) -> Vec<Vec<String>> {
+    text.chars()
+        .filter(|c| !c.is_whitespace())
+        .map(|c| match c.to_pinyin_multi() {
+            Some(multi) => multi.into_iter().map(|p| p.plain().to_string()).collect(),
+            None => vec![c.to_string()],
+        })
+        .collect()
+}
+
+/// 查询（小写字母与数字）能否由文本中某段连续字符的拼音拼出
+fn pinyin_match(query: &str, text: &str) -> bool {
+    let query: String = query.chars().filter(|c| !c.is_whitespace()).collect();
+    if !query.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return false;
+    }
+    let readings = readings(text);
+    (0..readings.len()).any(|start| match_readings(&query, &readings[start..]))
+}
+
+/// 每个字符可取全拼或首字母，最后一个字符还可取全拼的前缀
+fn match_readings(query: &str, readings: &[Vec<String>]) -> bool {
+    if query.is_empty() {
+        return true;
+    }
+    let Some((first, rest)) = readings.split_first() else {
+        return false;
+    };
+    first.iter().any(|reading| {
+        if reading.starts_with(query) {
+            return true;
+        }
+        if let Some(tail) = query.strip_prefix(reading.as_str()) {
+            if match_readings(tail, rest) {
+                return true;
+            }
+        }
+        reading.len() > 1
+            && reading.chars().next() == query.chars().next()
+            && match_readings(&query[1..], rest)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search_matches() {
+        let settings = SearchSettings::default();
+        assert!(settings.matches("", "招商银行"));
+        assert!(settings.matches("银行", "招商银行"));
+        assert!(settings.matches("zs", "招商银行"));
+        assert!(settings.matches("ZSYH", "招商银行"));
+        assert!(settings.matches("zhaoshang", "招商银行"));
+        assert!(settings.matches("zsyinh", "招商银行"));
+        // 多音字：行 (xing/hang)
+        assert!(settings.matches("yinhang", "招商银行"));
+        assert!(settings.matches("yh", "中国银行 A股"));
+        assert!(!settings.matches("zy", "招商银行"));
+
+        assert!(settings.matches("stk aapl", "Apple Stock AAPL"));
+        assert!(settings.matches("apl", "Apple"));
+        assert!(!settings.matches("lpa", "Apple"));
+
+        let strict = SearchSettings {
+            fuzzy: false,
+            pinyin: false,
+        };
+        assert!(!strict.matches("zs", "招商银行"));
+        assert!(!strict.matches("apl", "Apple"));
+        assert!(strict.matches("APP", "Apple"));
+    }
+
+    #[test]
+    fn test_search_score() {
+        let settings = SearchSettings::default();
+        let score = |text| settings.score("银行", text);
+        assert!(score("银行") > score("银行卡"));
+        assert!(score("银行卡") > score("招商银行"));
+        assert!(settings.score("zs", "招商银行") > settings.score("apl", "Apple"));
+        assert_eq!(score("股票"), None);
+    }
+}
@@ -0,0 +1,70 @@
+//! 敏感凭据存储（如 SMTP 密码）
+//!
+//! 凭据与普通设置分开保存，只在后端使用，不会通过设置接口返回给前端，
+//! 调试输出中也只显示名称。
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+
+/// SMTP 登录密码
+pub const SMTP_PASSWORD: &str = "smtp_password";
+
+/// 凭据集合
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct SecretStore {
+    values: HashMap<String, String>,
+}
+
+impl SecretStore {
+    /// 保存凭据，值为空时删除
+    pub fn set(&mut self, name: &str, value: &str) {
+        if value.is_empty() {
+            self.values.remove(name);
+        } else {
+            self.values.insert(name.to_string(), value.to_string());
+        }
+    }
+
+    /// 读取凭据
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.values.get(name).map(String::as_str)
+    }
+
+    /// 是否已保存凭据
+    pub fn contains(&self, name: &str) -> bool {
+        self.values.contains_key(name)
+    }
+
+    /// 删除凭据
+    pub fn remove(&mut self, name: &str) -> bool {
+        self.values.remove(name).is_some()
+    }
+}
+
+impl fmt::Debug for SecretStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut names: Vec<&String> = self.values.keys().collect();
+        names.sort();
+        f.debug_struct("SecretStore")
+            .field("names", &names)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_secret_store() {
+        let mut secrets = SecretStore::default();
+        secrets.set(SMTP_PASSWORD, "hunter2");
+        assert_eq!(secrets.get(SMTP_PASSWORD), Some("hunter2"));
+        assert!(!format!("{:?}", secrets).contains("hunter2"));
+
+        secrets.set(SMTP_PASSWORD, "");
+        assert!(!secrets.contains(SMTP_PASSWORD));
+    }
+}
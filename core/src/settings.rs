@@ -0,0 +1,196 @@
+//! 类型化设置：按类型读写设置项，并在设置变化时通知订阅者
+//!
+//! 设置项以 JSON 字符串保存。[`Settings<T>`] 描述一个设置项的键名与默认值，读取时解析为 `T`，
+//! 未设置时使用默认值。订阅后，设置被修改（包括重新加载数据文件带来的变化）时回调收到新值，
+//! 各模块无需重启即可生效。[`SettingsKey`] 列出内置的设置项，供界面与插件按名称识别变化的设置。
+
+use crate::assumptions::ASSUMPTIONS_SETTING_KEY;
+use crate::email::{REPORT_EMAIL_SETTING_KEY, SMTP_SETTING_KEY};
+use crate::first_run::LOCALE_SETTING_KEY;
+use crate::format::FORMAT_SETTING_KEY;
+use crate::fx::FX_SETTING_KEY;
+use crate::precision::PRECISION_SETTING_KEY;
+use crate::search::SEARCH_SETTING_KEY;
+use crate::storage::{AUDIT_SETTING_KEY, BACKUP_SETTING_KEY, SAVE_SETTING_KEY};
+use crate::tags::TAG_SETTING_KEY;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use tracing::warn;
+
+/// 订阅ID，用于取消订阅
+pub type SubscriptionId = u64;
+
+/// 类型化的设置项：键名与默认值
+pub struct Settings<T> {
+    key: &'static str,
+    default: fn() -> T,
+}
+
+impl<T> Settings<T> {
+    /// 创建设置项
+    pub const fn new(key: &'static str, default: fn() -> T) -> Self {
+        Self { key, default }
+    }
+
+    /// 设置项键名
+    pub fn key(&self) -> &'static str {
+        self.key
+    }
+
+    /// 默认值
+    pub fn default_value(&self) -> T {
+        (self.default)()
+    }
+}
+
+impl<T: DeserializeOwned> Settings<T> {
+    /// 解析设置项的值，未设置时为默认值
+    pub fn parse(&self, raw: Option<&str>) -> Result<T, serde_json::Error> {
+        match raw {
+            Some(raw) => serde_json::from_str(raw),
+            None => Ok(self.default_value()),
+        }
+    }
+}
+
+impl<T> Clone for Settings<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Settings<T> {}
+
+/// 内置的设置项（序列化为键名）
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum SettingsKey {
+    Fx,
+    Precision,
+    Tags,
+    Search,
+    Save,
+    Backup,
+    Audit,
+    Smtp,
+    ReportEmail,
+    Assumptions,
+    Locale,
+    Format,
+}
+
+impl SettingsKey {
+    /// 全部内置设置项
+    pub const ALL: [SettingsKey; 12] = [
+        SettingsKey::Fx,
+        SettingsKey::Precision,
+        SettingsKey::Tags,
+        SettingsKey::Search,
+        SettingsKey::Save,
+        SettingsKey::Backup,
+        SettingsKey::Audit,
+        SettingsKey::Smtp,
+        SettingsKey::ReportEmail,
+        SettingsKey::Assumptions,
+        SettingsKey::Locale,
+        SettingsKey::Format,
+    ];
+
+    /// 设置项键名
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SettingsKey::Fx => FX_SETTING_KEY,
+            SettingsKey::Precision => PRECISION_SETTING_KEY,
+            SettingsKey::Tags => TAG_SETTING_KEY,
+            SettingsKey::Search => SEARCH_SETTING_KEY,
+            SettingsKey::Save => SAVE_SETTING_KEY,
+            SettingsKey::Backup => BACKUP_SETTING_KEY,
+            SettingsKey::Audit => AUDIT_SETTING_KEY,
+            SettingsKey::Smtp => SMTP_SETTING_KEY,
+            SettingsKey::ReportEmail => REPORT_EMAIL_SETTING_KEY,
+            SettingsKey::Assumptions => ASSUMPTIONS_SETTING_KEY,
+            SettingsKey::Locale => LOCALE_SETTING_KEY,
+            SettingsKey::Format => FORMAT_SETTING_KEY,
+        }
+    }
+
+    /// 由键名查找内置设置项（自定义设置项为 None）
+    pub fn from_key(key: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|k| k.as_str() == key)
+    }
+}
+
+impl AsRef<str> for SettingsKey {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl fmt::Display for SettingsKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// 设置变化的回调：参数为键名与新值（已删除时为空）
+type Callback = Box<dyn Fn(&str, Option<&str>) + Send>;
+
+/// 设置变化的订阅者
+///
+/// 回调在修改设置的线程中、持有数据库时执行，不得再访问数据库。
+#[derive(Default)]
+pub struct Subscribers {
+    next_id: SubscriptionId,
+    /// 订阅ID、订阅的键名（为空表示全部设置）与回调
+    callbacks: Vec<(SubscriptionId, Option<&'static str>, Callback)>,
+}
+
+impl Subscribers {
+    /// 订阅某一设置项的变化，回调收到解析后的新值（无法解析时跳过）
+    pub fn subscribe<T: DeserializeOwned + 'static>(
+        &mut self,
+        settings: Settings<T>,
+        callback: impl Fn(&T) + Send + 'static,
+    ) -> SubscriptionId {
+        let key = settings.key();
+        self.add(
+            Some(key),
+            Box::new(move |_, raw| match settings.parse(raw) {
+                Ok(value) => callback(&value),
+                Err(e) => warn!("Ignoring invalid setting {}: {}", key, e),
+            }),
+        )
+    }
+
+    /// 订阅全部设置的变化，回调收到变化的键名与新值（已删除时为空）
+    pub fn subscribe_all(
+        &mut self,
+        callback: impl Fn(&str, Option<&str>) + Send + 'static,
+    ) -> SubscriptionId {
+        self.add(None, Box::new(callback))
+    }
+
+    /// 取消订阅，返回订阅是否存在
+    pub fn unsubscribe(&mut self, id: SubscriptionId) -> bool {
+        let before = self.callbacks.len();
+        self.callbacks.retain(|(i, _, _)| *i != id);
+        self.callbacks.len() != before
+    }
+
+    /// 通知设置项的新值
+    pub fn notify(&self, key: &str, raw: Option<&str>) {
+        for (_, filter, callback) in &self.callbacks {
+            if filter.is_none_or(|k| k == key) {
+                callback(key, raw);
+            }
+        }
+    }
+
+    fn add(&mut self, key: Option<&'static str>, callback: Callback) -> SubscriptionId {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.callbacks.push((id, key, callback));
+        id
+    }
+}
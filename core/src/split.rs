@@ -0,0 +1,237 @@
+//! 分摊账单与结算（简易 AA 记账）
+//!
+//! 记录多人共同承担的支出，计算每个人的净欠款，并给出最少笔数的结算方案。
+//! 用户本人以 [`ME`] 表示，涉及本人的结算可同时记为资产交易。
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+/// 代表用户本人的参与者名称
+pub const ME: &str = "me";
+
+/// 金额比较的容差
+const EPSILON: f64 = 0.005;
+
+/// 某参与者应承担的份额
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Share {
+    pub participant: String,
+    pub amount: f64,
+}
+
+/// 一笔共同支出
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SharedExpense {
+    /// 唯一标识符
+    pub id: Uuid,
+    /// 说明（如"周末聚餐"）
+    pub description: String,
+    /// 垫付人
+    pub payer: String,
+    /// 总金额
+    pub amount: f64,
+    /// 各参与者份额，合计应等于总金额
+    pub shares: Vec<Share>,
+    /// 发生时间
+    pub date: DateTime<Utc>,
+}
+
+impl SharedExpense {
+    /// 创建支出，份额为空
+    pub fn new(description: impl Into<String>, payer: impl Into<String>, amount: f64) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            description: description.into(),
+            payer: payer.into(),
+            amount,
+            shares: Vec::new(),
+            date: Utc::now(),
+        }
+    }
+
+    /// 由参与者平均分摊（分摊余数计入第一位参与者）
+    pub fn split_evenly(mut self, participants: &[&str]) -> Self {
+        if participants.is_empty() {
+            return self;
+        }
+        let each = (self.amount / participants.len() as f64 * 100.0).floor() / 100.0;
+        let remainder = self.amount - each * participants.len() as f64;
+        self.shares = participants
+            .iter()
+            .enumerate()
+            .map(|(i, p)| Share {
+                participant: p.to_string(),
+                amount: if i == 0 { each + remainder } else { each },
+            })
+            .collect();
+        self
+    }
+
+    /// 添加指定金额的份额
+    pub fn with_share(mut self, participant: impl Into<String>, amount: f64) -> Self {
+        self.shares.push(Share {
+            participant: participant.into(),
+            amount,
+        });
+        self
+    }
+
+    /// 校验金额与份额
+    pub fn validate(&self) -> Result<(), String> {
+        if self.payer.trim().is_empty() {
+            return Err("Payer is required".to_string());
+        }
+        if self.amount <= 0.0 {
+            return Err("Amount must be positive".to_string());
+        }
+        if self.shares.iter().any(|s| s.amount < 0.0) {
+            return Err("Shares cannot be negative".to_string());
+        }
+        let total: f64 = self.shares.iter().map(|s| s.amount).sum();
+        if (total - self.amount).abs() > EPSILON {
+            return Err(format!(
+                "Shares add up to {}, expected {}",
+                total, self.amount
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// 一次已完成的还款
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Settlement {
+    /// 唯一标识符
+    pub id: Uuid,
+    /// 付款人
+    pub from: String,
+    /// 收款人
+    pub to: String,
+    pub amount: f64,
+    /// 结算时间
+    pub date: DateTime<Utc>,
+    /// 涉及本人时生成的资产交易ID
+    pub transaction_id: Option<Uuid>,
+}
+
+impl Settlement {
+    /// 创建结算记录
+    pub fn new(from: impl Into<String>, to: impl Into<String>, amount: f64) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            from: from.into(),
+            to: to.into(),
+            amount,
+            date: Utc::now(),
+            transaction_id: None,
+        }
+    }
+}
+
+/// 参与者的净余额：正数表示别人欠他，负数表示他欠别人
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ParticipantBalance {
+    pub participant: String,
+    pub balance: f64,
+}
+
+/// 建议的还款
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Debt {
+    pub from: String,
+    pub to: String,
+    pub amount: f64,
+}
+
+/// 计算各参与者的净余额（按名称排序，省略已结清的参与者）
+pub fn balances(expenses: &[SharedExpense], settlements: &[Settlement]) -> Vec<ParticipantBalance> {
+    let mut totals: BTreeMap<&str, f64> = BTreeMap::new();
+    for expense in expenses {
+        *totals.entry(&expense.payer).or_insert(0.0) += expense.amount;
+        for share in &expense.shares {
+            *totals.entry(&share.participant).or_insert(0.0) -= share.amount;
+        }
+    }
+    for settlement in settlements {
+        *totals.entry(&settlement.from).or_insert(0.0) += settlement.amount;
+        *totals.entry(&settlement.to).or_insert(0.0) -= settlement.amount;
+    }
+
+    totals
+        .into_iter()
+        .filter(|(_, balance)| balance.abs() > EPSILON)
+        .map(|(participant, balance)| ParticipantBalance {
+            participant: participant.to_string(),
+            balance,
+        })
+        .collect()
+}
+
+/// 由净余额生成还款方案：每次让欠得最多的人向被欠最多的人还款
+pub fn settle_up(balances: &[ParticipantBalance]) -> Vec<Debt> {
+    let mut debtors: Vec<(String, f64)> = Vec::new();
+    let mut creditors: Vec<(String, f64)> = Vec::new();
+    for b in balances {
+        if b.balance < -EPSILON {
+            debtors.push((b.participant.clone(), -b.balance));
+        } else if b.balance > EPSILON {
+            creditors.push((b.participant.clone(), b.balance));
+        }
+    }
+
+    let mut debts = Vec::new();
+    loop {
+        debtors.sort_by(|a, b| b.1.total_cmp(&a.1));
+        creditors.sort_by(|a, b| b.1.total_cmp(&a.1));
+        let (Some(debtor), Some(creditor)) = (debtors.first_mut(), creditors.first_mut()) else {
+            break;
+        };
+        if debtor.1 <= EPSILON || creditor.1 <= EPSILON {
+            break;
+        }
+        let amount = debtor.1.min(creditor.1);
+        debtor.1 -= amount;
+        creditor.1 -= amount;
+        debts.push(Debt {
+            from: debtor.0.clone(),
+            to: creditor.0.clone(),
+            amount,
+        });
+    }
+    debts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_balances_and_settle_up() {
+        let dinner = SharedExpense::new("聚餐", ME, 300.0).split_evenly(&[ME, "小王", "小李"]);
+        let taxi = SharedExpense::new("打车", "小王", 60.0)
+            .with_share("小王", 30.0)
+            .with_share("小李", 30.0);
+        assert!(dinner.validate().is_ok());
+        assert!(taxi.validate().is_ok());
+        assert!(SharedExpense::new("x", ME, 10.0)
+            .with_share("小王", 5.0)
+            .validate()
+            .is_err());
+
+        let result = balances(&[dinner.clone(), taxi.clone()], &[]);
+        let me = result.iter().find(|b| b.participant == ME).unwrap();
+        assert_eq!(me.balance, 200.0);
+
+        let debts = settle_up(&result);
+        assert_eq!(debts.len(), 2);
+        assert!(debts.iter().all(|d| d.to == ME));
+        let total: f64 = debts.iter().map(|d| d.amount).sum();
+        assert_eq!(total, 200.0);
+
+        let paid = Settlement::new("小李", ME, 130.0);
+        let result = balances(&[dinner, taxi], &[paid]);
+        assert_eq!(settle_up(&result).len(), 1);
+    }
+}
@@ -0,0 +1,373 @@
+//! 便携存档：把资产、交易、设置与插件文件打包为一个 JSON 文件，在设备与存储后端之间迁移数据
+//!
+//! 存档不含凭据（如 SMTP 密码）与可重建的派生数据（索引、每日余额、复式账簿余额），
+//! 导入后重新计算。插件文件只收录文本文件。
+
+use super::{Database, StorageError};
+use crate::asset::{Asset, AssetTransaction};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Component, Path};
+use tracing::warn;
+
+/// 存档格式标识
+pub const ARCHIVE_FORMAT: &str = "asset-manager-archive";
+
+/// 当前存档版本
+pub const ARCHIVE_VERSION: u32 = 1;
+
+/// 存档内容
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Archive {
+    pub format: String,
+    pub version: u32,
+    pub exported_at: DateTime<Utc>,
+    pub assets: Vec<Asset>,
+    pub transactions: Vec<AssetTransaction>,
+    /// 设置项（键名 → JSON 字符串）
+    #[serde(default)]
+    pub settings: BTreeMap<String, String>,
+    /// 插件文件：插件目录名 → 相对路径 → 内容
+    #[serde(default)]
+    pub plugins: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+/// 导入方式
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ArchiveMode {
+    /// 用存档替换现有的资产、交易与设置
+    Replace,
+    /// 只加入尚不存在的资产、交易、设置项与插件文件
+    Merge,
+}
+
+/// 导出或导入的数量
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct ArchiveSummary {
+    pub assets: usize,
+    pub transactions: usize,
+    pub settings: usize,
+    pub plugin_files: usize,
+    /// 已存在或所属资产不存在而跳过的记录数量
+    #[serde(default)]
+    pub skipped: usize,
+    /// 未通过校验（如外部标识已被使用）而跳过的记录及原因
+    #[serde(default)]
+    pub errors: Vec<String>,
+}
+
+impl Archive {
+    /// 创建当前版本的存档
+    pub fn new(
+        assets: Vec<Asset>,
+        transactions: Vec<AssetTransaction>,
+        settings: BTreeMap<String, String>,
+    ) -> Self {
+        Self {
+            format: ARCHIVE_FORMAT.to_string(),
+            version: ARCHIVE_VERSION,
+            exported_at: Utc::now(),
+            assets,
+            transactions,
+            settings,
+            plugins: BTreeMap::new(),
+        }
+    }
+
+    /// 读取存档文件，拒绝其他格式与更新版本的存档
+    pub fn read(path: &Path) -> Result<Self, StorageError> {
+        let archive: Archive = serde_json::from_str(&fs::read_to_string(path)?)?;
+        if archive.format != ARCHIVE_FORMAT {
+            return Err(StorageError::InvalidData(format!(
+                "Not an archive file: {}",
+                path.display()
+            )));
+        }
+        if archive.version > ARCHIVE_VERSION {
+            return Err(StorageError::InvalidData(format!(
+                "Archive version {} is newer than supported version {}",
+                archive.version, ARCHIVE_VERSION
+            )));
+        }
+        Ok(archive)
+    }
+
+    /// 写入存档文件（先写临时文件再替换）
+    pub fn write(&self, path: &Path) -> Result<(), StorageError> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, serde_json::to_string(self)?)?;
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    /// 收录插件目录中各插件的文本文件
+    pub fn add_plugins(&mut self, plugins_dir: &Path) -> Result<(), StorageError> {
+        if !plugins_dir.is_dir() {
+            return Ok(());
+        }
+        for entry in fs::read_dir(plugins_dir)? {
+            let path = entry?.path();
+            if !path.is_dir() {
+                continue;
+            }
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            let mut files = BTreeMap::new();
+            collect_files(&path, &path, &mut files)?;
+            self.plugins.insert(name.to_string(), files);
+        }
+        Ok(())
+    }
+
+    /// 检查插件文件的路径，都在插件目录之内才能写入
+    pub fn check_plugin_paths(&self) -> Result<(), StorageError> {
+        for (name, files) in &self.plugins {
+            safe_path(name)?;
+            for relative in files.keys() {
+                safe_path(relative)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 把插件文件写入插件目录，合并模式下不覆盖已有文件，返回写入的文件数量
+    pub fn restore_plugins(
+        &self,
+        plugins_dir: &Path,
+        mode: ArchiveMode,
+    ) -> Result<usize, StorageError> {
+        let mut written = 0;
+        for (name, files) in &self.plugins {
+            for (relative, content) in files {
+                let target = plugins_dir
+                    .join(safe_path(name)?)
+                    .join(safe_path(relative)?);
+                if mode == ArchiveMode::Merge && target.exists() {
+                    continue;
+                }
+                if let Some(parent) = target.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::write(&target, content)?;
+                written += 1;
+            }
+        }
+        Ok(written)
+    }
+
+    /// 插件文件数量
+    pub fn plugin_files(&self) -> usize {
+        self.plugins.values().map(BTreeMap::len).sum()
+    }
+}
+
+/// 把数据与插件目录（如有）导出为存档文件
+pub fn export_archive(
+    db: &Database,
+    path: &Path,
+    plugins_dir: Option<&Path>,
+) -> Result<ArchiveSummary, StorageError> {
+    let mut archive = db.to_archive()?;
+    if let Some(dir) = plugins_dir {
+        archive.add_plugins(dir)?;
+    }
+    archive.write(path)?;
+    Ok(ArchiveSummary {
+        assets: archive.assets.len(),
+        transactions: archive.transactions.len(),
+        settings: archive.settings.len(),
+        plugin_files: archive.plugin_files(),
+        ..Default::default()
+    })
+}
+
+/// 导入存档文件：数据写入数据库，指定插件目录时插件文件写入插件目录。
+/// 插件文件的路径在修改数据库之前检查，路径无效时不导入任何内容
+pub fn import_archive(
+    db: &mut Database,
+    path: &Path,
+    mode: ArchiveMode,
+    plugins_dir: Option<&Path>,
+) -> Result<ArchiveSummary, StorageError> {
+    let archive = Archive::read(path)?;
+    if plugins_dir.is_some() {
+        archive.check_plugin_paths()?;
+    }
+    let mut summary = db.apply_archive(&archive, mode)?;
+    if let Some(dir) = plugins_dir {
+        summary.plugin_files = archive.restore_plugins(dir, mode)?;
+    }
+    Ok(summary)
+}
+
+/// 递归收集目录中的文本文件，键为以 `/` 分隔的相对路径
+fn collect_files(
+    root: &Path,
+    dir: &Path,
+    files: &mut BTreeMap<String, String>,
+) -> Result<(), StorageError> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_files(root, &path, files)?;
+            continue;
+        }
+        let relative = path.strip_prefix(root).unwrap_or(&path);
+        let key = relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        match fs::read_to_string(&path) {
+            Ok(content) => {
+                files.insert(key, content);
+            }
+            Err(e) => warn!("Skipping plugin file {:?}: {}", path, e),
+        }
+    }
+    Ok(())
+}
+
+/// 存档中的相对路径，拒绝绝对路径与 `..`，避免写到插件目录之外
+fn safe_path(relative: &str) -> Result<&Path, StorageError> {
+    let path = Path::new(relative);
+    if relative.is_empty() || !path.components().all(|c| matches!(c, Component::Normal(_))) {
+        return Err(StorageError::InvalidData(format!(
+            "Invalid path in archive: {}",
+            relative
+        )));
+    }
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asset::{AssetType, TransactionType};
+    use crate::fx::{FxSettings, FX_SETTINGS};
+    use crate::Currency;
+    use chrono::TimeZone;
+    use uuid::Uuid;
+
+    #[test]
+    fn test_archive_round_trip() {
+        let dir = std::env::temp_dir().join(format!("asset-manager-archive-{}", Uuid::new_v4()));
+        let plugins = dir.join("plugins");
+        fs::create_dir_all(plugins.join("broker").join("lib")).unwrap();
+        fs::write(plugins.join("broker").join("init.lua"), "return {}").unwrap();
+        fs::write(
+            plugins.join("broker").join("lib").join("util.lua"),
+            "-- util",
+        )
+        .unwrap();
+
+        let mut db = Database::open_in_memory().unwrap();
+        let card = Asset::new("储蓄卡", AssetType::BankDeposit, 1000.0);
+        db.create_asset(&card).unwrap();
+        let txn = AssetTransaction::new(card.id, TransactionType::Income, 0.0, 1000.0);
+        db.add_transaction(&txn).unwrap();
+        let fx = FxSettings {
+            base_currency: Currency::USD,
+            ..Default::default()
+        };
+        db.set_settings(FX_SETTINGS, &fx).unwrap();
+
+        let path = dir.join("backup.json");
+        let exported = export_archive(&db, &path, Some(&plugins)).unwrap();
+        assert_eq!((exported.assets, exported.transactions), (1, 1));
+        assert_eq!(exported.plugin_files, 2);
+
+        // 导入到另一台设备的空数据库与插件目录
+        let mut other = Database::open_in_memory().unwrap();
+        let other_plugins = dir.join("other-plugins");
+        let imported =
+            import_archive(&mut other, &path, ArchiveMode::Merge, Some(&other_plugins)).unwrap();
+        assert_eq!((imported.assets, imported.transactions), (1, 1));
+        assert_eq!(other.get_settings(FX_SETTINGS).unwrap(), fx);
+        assert!(other_plugins.join("broker/lib/util.lua").exists());
+
+        // 再次合并时全部跳过；替换时清除存档中没有的资产
+        let imported = import_archive(&mut other, &path, ArchiveMode::Merge, None).unwrap();
+        assert_eq!((imported.assets, imported.skipped), (0, 2));
+        let extra = Asset::new("现金", AssetType::Cash, 50.0);
+        other.create_asset(&extra).unwrap();
+        import_archive(&mut other, &path, ArchiveMode::Replace, None).unwrap();
+        assert!(other.get_asset(extra.id).unwrap().is_none());
+        assert_eq!(other.get_asset(card.id).unwrap().unwrap().value, 1000.0);
+
+        // 拒绝写到插件目录之外的路径，替换模式下也不先清除现有数据
+        let mut archive = Archive::read(&path).unwrap();
+        archive.plugins.insert(
+            "../evil".to_string(),
+            BTreeMap::from([("x".into(), "".into())]),
+        );
+        assert!(archive
+            .restore_plugins(&other_plugins, ArchiveMode::Merge)
+            .is_err());
+        let evil = dir.join("evil.json");
+        fs::write(&evil, serde_json::to_string(&archive).unwrap()).unwrap();
+        other.create_asset(&extra).unwrap();
+        let result = import_archive(&mut other, &evil, ArchiveMode::Replace, Some(&other_plugins));
+        assert!(result.is_err());
+        assert!(other.get_asset(extra.id).unwrap().is_some());
+        assert!(!dir.join("evil").exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_import_closed_period() {
+        let dir = std::env::temp_dir().join(format!("asset-manager-archive-{}", Uuid::new_v4()));
+        let mut db = Database::open_in_memory().unwrap();
+        let card = Asset::new("储蓄卡", AssetType::BankDeposit, 1000.0);
+        db.create_asset(&card).unwrap();
+        let march = Utc.with_ymd_and_hms(2024, 3, 15, 0, 0, 0).unwrap();
+        let txn = AssetTransaction::new(card.id, TransactionType::Income, 0.0, 1000.0)
+            .with_timestamp(march);
+        db.add_transaction(&txn).unwrap();
+        let path = dir.join("backup.json");
+        export_archive(&db, &path, None).unwrap();
+        db.close_period(2024, 3).unwrap();
+
+        // 已有的交易会被跳过，不检查结账期间
+        let imported = import_archive(&mut db, &path, ArchiveMode::Merge, None).unwrap();
+        assert_eq!(imported.skipped, 2);
+
+        // 替换前先检查全部资产，任何一项不能删除时不删除任何资产
+        let cash = Asset::new("现金", AssetType::Cash, 50.0);
+        db.create_asset(&cash).unwrap();
+        let result = import_archive(&mut db, &path, ArchiveMode::Replace, None);
+        assert!(matches!(result, Err(StorageError::PeriodClosed(_))));
+        assert!(db.get_asset(card.id).unwrap().is_some());
+        assert!(db.get_asset(cash.id).unwrap().is_some());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_import_validates() {
+        let dir = std::env::temp_dir().join(format!("asset-manager-archive-{}", Uuid::new_v4()));
+        let mut db = Database::open_in_memory().unwrap();
+        let mut card = Asset::new("储蓄卡", AssetType::BankDeposit, 1000.0);
+        card.external_id = Some("X".to_string());
+        db.create_asset(&card).unwrap();
+        let path = dir.join("backup.json");
+        export_archive(&db, &path, None).unwrap();
+
+        // 外部标识已被其他资产使用的资产不导入
+        let mut other = Database::open_in_memory().unwrap();
+        let mut taken = Asset::new("工资卡", AssetType::BankDeposit, 500.0);
+        taken.external_id = Some("X".to_string());
+        other.create_asset(&taken).unwrap();
+        let imported = import_archive(&mut other, &path, ArchiveMode::Merge, None).unwrap();
+        assert_eq!((imported.assets, imported.errors.len()), (0, 1));
+        assert!(other.get_asset(card.id).unwrap().is_none());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
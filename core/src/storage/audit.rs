@@ -0,0 +1,487 @@
+//! 修改记录：存储层在每次保存时把资产与交易的每一处变化追加到主文件旁的 `<文件名>.audit`
+//! （操作者、操作、记录ID与各字段修改前后的值）
+//!
+//! 修改记录不随主文件压缩清空，可按条件查询，超过保留天数的记录在打开时清除。
+//! 资产的修改历史由该资产的记录按时间排列得到。
+
+use super::StorageError;
+use crate::settings::Settings;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::cmp::Reverse;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tracing::warn;
+use uuid::Uuid;
+
+/// 比较版本时忽略的字段
+const IGNORED_FIELDS: [&str; 4] = ["id", "created_at", "updated_at", "conversion"];
+
+/// 未指定时的操作者
+pub const DEFAULT_ACTOR: &str = "user";
+
+/// 设置项键名
+pub const AUDIT_SETTING_KEY: &str = "audit";
+
+/// 修改记录设置项
+pub const AUDIT_SETTINGS: Settings<AuditSettings> =
+    Settings::new(AUDIT_SETTING_KEY, AuditSettings::default);
+
+/// 修改历史中的操作（删除后再次新建视为恢复）
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditAction {
+    Created,
+    Updated,
+    Deleted,
+    Restored,
+}
+
+/// 一个字段的修改（字段不存在时为 null，元数据字段为 `metadata.<键>`）
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FieldChange {
+    pub field: String,
+    pub before: Value,
+    pub after: Value,
+}
+
+/// 相邻两个版本之间的修改
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AssetVersionDiff {
+    pub timestamp: DateTime<Utc>,
+    pub action: AuditAction,
+    /// 新建时为各字段的初始值，删除与恢复时为空
+    pub changes: Vec<FieldChange>,
+}
+
+/// 被修改的记录类型
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditEntity {
+    Asset,
+    Transaction,
+}
+
+/// 修改操作
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditOperation {
+    Create,
+    Update,
+    Delete,
+}
+
+/// 一次修改
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditRecord {
+    pub timestamp: DateTime<Utc>,
+    /// 操作者（如 `user`、`rule:<规则名>`）
+    pub actor: String,
+    pub operation: AuditOperation,
+    pub entity: AuditEntity,
+    pub entity_id: Uuid,
+    /// 各字段修改前后的值（新建时修改前为 null，删除时修改后为 null）
+    pub changes: Vec<FieldChange>,
+}
+
+impl AuditRecord {
+    /// 比较记录修改前后的内容，没有变化时为 None
+    pub fn compare<T: Serialize>(
+        actor: &str,
+        entity: AuditEntity,
+        entity_id: Uuid,
+        before: Option<&T>,
+        after: Option<&T>,
+        timestamp: DateTime<Utc>,
+    ) -> Result<Option<Self>, StorageError> {
+        let operation = match (before, after) {
+            (None, None) => return Ok(None),
+            (None, Some(_)) => AuditOperation::Create,
+            (Some(_), Some(_)) => AuditOperation::Update,
+            (Some(_), None) => AuditOperation::Delete,
+        };
+        let before = before.map(fields).transpose()?.unwrap_or_default();
+        let after = after.map(fields).transpose()?.unwrap_or_default();
+        let changes = diff_fields(&before, &after);
+        if operation == AuditOperation::Update && changes.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(Self {
+            timestamp,
+            actor: actor.to_string(),
+            operation,
+            entity,
+            entity_id,
+            changes,
+        }))
+    }
+}
+
+/// 修改记录的查询条件，结果按时间倒序
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AuditQuery {
+    #[serde(default)]
+    pub entity: Option<AuditEntity>,
+    #[serde(default)]
+    pub entity_id: Option<Uuid>,
+    #[serde(default)]
+    pub operation: Option<AuditOperation>,
+    #[serde(default)]
+    pub actor: Option<String>,
+    #[serde(default)]
+    pub since: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub until: Option<DateTime<Utc>>,
+    /// 最多返回的条数
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+impl AuditQuery {
+    /// 只查询一条记录的修改
+    pub fn for_entity(entity: AuditEntity, entity_id: Uuid) -> Self {
+        Self {
+            entity: Some(entity),
+            entity_id: Some(entity_id),
+            ..Self::default()
+        }
+    }
+
+    /// 记录是否满足条件（不含数量限制）
+    pub fn matches(&self, record: &AuditRecord) -> bool {
+        self.entity.is_none_or(|e| e == record.entity)
+            && self.entity_id.is_none_or(|id| id == record.entity_id)
+            && self.operation.is_none_or(|op| op == record.operation)
+            && self.actor.as_ref().is_none_or(|a| *a == record.actor)
+            && self.since.is_none_or(|since| record.timestamp >= since)
+            && self.until.is_none_or(|until| record.timestamp <= until)
+    }
+}
+
+/// 修改记录设置
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct AuditSettings {
+    /// 保留天数，为空时永久保留
+    #[serde(default = "default_retention_days")]
+    pub retention_days: Option<u32>,
+}
+
+fn default_retention_days() -> Option<u32> {
+    Some(365)
+}
+
+impl Default for AuditSettings {
+    fn default() -> Self {
+        Self {
+            retention_days: default_retention_days(),
+        }
+    }
+}
+
+impl AuditSettings {
+    /// 检查设置是否有效
+    pub fn validate(&self) -> Result<(), String> {
+        if self.retention_days == Some(0) {
+            return Err("Retention must be at least one day".to_string());
+        }
+        Ok(())
+    }
+
+    /// 早于此时间的记录应清除
+    pub fn cutoff(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        self.retention_days
+            .map(|days| now - Duration::days(i64::from(days)))
+    }
+}
+
+/// 数据文件的修改记录（内存数据库只保存在内存中）
+#[derive(Debug, Clone, Default)]
+pub struct AuditLog {
+    path: Option<PathBuf>,
+    /// 尚未写入文件的记录
+    pending: Vec<AuditRecord>,
+}
+
+impl AuditLog {
+    /// 主文件的修改记录（同目录下的 `<文件名>.audit`）
+    pub fn for_store(store_path: &Path) -> Self {
+        let mut path = store_path.as_os_str().to_owned();
+        path.push(".audit");
+        Self {
+            path: Some(PathBuf::from(path)),
+            pending: Vec::new(),
+        }
+    }
+
+    /// 记下一次修改，保存时写入
+    pub fn record(&mut self, record: AuditRecord) {
+        self.pending.push(record);
+    }
+
+    /// 把未写入的记录追加到文件
+    pub fn flush(&mut self) -> Result<(), StorageError> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        let mut content = String::new();
+        for record in &self.pending {
+            content.push_str(&serde_json::to_string(record)?);
+            content.push('\n');
+        }
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?
+            .write_all(content.as_bytes())?;
+        self.pending.clear();
+        Ok(())
+    }
+
+    /// 查询满足条件的记录（包括未写入的），按时间倒序
+    pub fn query(&self, query: &AuditQuery) -> Result<Vec<AuditRecord>, StorageError> {
+        let mut records: Vec<AuditRecord> = self
+            .read_file()?
+            .into_iter()
+            .chain(self.pending.iter().cloned())
+            .filter(|r| query.matches(r))
+            .collect();
+        // 时间相同的按写入顺序倒序
+        records.reverse();
+        records.sort_by_key(|record| Reverse(record.timestamp));
+        if let Some(limit) = query.limit {
+            records.truncate(limit);
+        }
+        Ok(records)
+    }
+
+    /// 清除早于 `cutoff` 的记录，返回清除的数量
+    pub fn prune(&mut self, cutoff: DateTime<Utc>) -> Result<usize, StorageError> {
+        let before = self.pending.len();
+        self.pending.retain(|r| r.timestamp >= cutoff);
+        let mut pruned = before - self.pending.len();
+
+        let records = self.read_file()?;
+        let kept: Vec<&AuditRecord> = records.iter().filter(|r| r.timestamp >= cutoff).collect();
+        if let Some(path) = self.path.as_ref().filter(|_| kept.len() < records.len()) {
+            let mut content = String::new();
+            for record in &kept {
+                content.push_str(&serde_json::to_string(record)?);
+                content.push('\n');
+            }
+            let tmp = path.with_extension("audit.tmp");
+            fs::write(&tmp, content)?;
+            fs::rename(&tmp, path)?;
+            pruned += records.len() - kept.len();
+        }
+        Ok(pruned)
+    }
+
+    /// 读取文件中的记录，无法解析的行跳过
+    fn read_file(&self) -> Result<Vec<AuditRecord>, StorageError> {
+        let mut records = Vec::new();
+        let Some(path) = self.path.as_ref().filter(|p| p.exists()) else {
+            return Ok(records);
+        };
+        for (i, line) in fs::read_to_string(path)?.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str::<AuditRecord>(line) {
+                Ok(record) => records.push(record),
+                Err(e) => warn!("Skipping audit log {:?} line {}: {}", path, i + 1, e),
+            }
+        }
+        Ok(records)
+    }
+}
+
+/// 按时间顺序列出资产各次修改的字段
+pub fn history_diff(records: &[AuditRecord]) -> Vec<AssetVersionDiff> {
+    let mut deleted = false;
+    records
+        .iter()
+        .map(|record| {
+            let (action, changes) = match record.operation {
+                AuditOperation::Create if deleted => (AuditAction::Restored, Vec::new()),
+                AuditOperation::Create => (
+                    AuditAction::Created,
+                    record
+                        .changes
+                        .iter()
+                        .filter(|c| !is_empty(&c.after))
+                        .cloned()
+                        .collect(),
+                ),
+                AuditOperation::Update => (AuditAction::Updated, record.changes.clone()),
+                AuditOperation::Delete => (AuditAction::Deleted, Vec::new()),
+            };
+            deleted = record.operation == AuditOperation::Delete;
+            AssetVersionDiff {
+                timestamp: record.timestamp,
+                action,
+                changes,
+            }
+        })
+        .collect()
+}
+
+/// 记录的可比较字段，元数据对象展开为 `metadata.<键>`
+fn fields<T: Serialize>(record: &T) -> Result<Map<String, Value>, StorageError> {
+    let Value::Object(mut fields) = serde_json::to_value(record)? else {
+        return Ok(Map::new());
+    };
+    for field in IGNORED_FIELDS {
+        fields.remove(field);
+    }
+    if let Some(Value::Object(metadata)) = fields.remove("metadata") {
+        for (key, value) in metadata {
+            fields.insert(format!("metadata.{}", key), value);
+        }
+    }
+    Ok(fields)
+}
+
+fn diff_fields(before: &Map<String, Value>, after: &Map<String, Value>) -> Vec<FieldChange> {
+    let mut keys: Vec<&String> = before.keys().chain(after.keys()).collect();
+    keys.sort();
+    keys.dedup();
+    keys.into_iter()
+        .filter_map(|key| {
+            let old = before.get(key).cloned().unwrap_or(Value::Null);
+            let new = after.get(key).cloned().unwrap_or(Value::Null);
+            (old != new).then(|| FieldChange {
+                field: key.clone(),
+                before: old,
+                after: new,
+            })
+        })
+        .collect()
+}
+
+fn is_empty(value: &Value) -> bool {
+    match value {
+        Value::Null | Value::Bool(false) => true,
+        Value::Array(items) => items.is_empty(),
+        Value::Object(fields) => fields.is_empty(),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asset::{Asset, AssetType};
+    use serde_json::json;
+
+    #[test]
+    fn test_history_diff() {
+        let mut log = AuditLog::default();
+        let original = Asset::new("存款", AssetType::BankDeposit, 10000.0)
+            .with_metadata(json!({ "bank": "招商银行" }));
+        let mut asset = original.clone();
+        asset.name = "定期存款".into();
+        asset.value = 12000.0;
+        asset.metadata = json!({ "bank": "招商银行", "term": "1y" });
+        let other = Asset::new("其他", AssetType::Cash, 1.0);
+        let now = Utc::now();
+        for (id, before, after) in [
+            (asset.id, None, Some(&original)),
+            (asset.id, Some(&original), Some(&asset)),
+            (asset.id, Some(&asset), None),
+            (other.id, None, Some(&other)),
+            (asset.id, None, Some(&asset)),
+        ] {
+            let record = AuditRecord::compare("user", AuditEntity::Asset, id, before, after, now);
+            log.record(record.unwrap().unwrap());
+        }
+
+        let mut records = log
+            .query(&AuditQuery::for_entity(AuditEntity::Asset, asset.id))
+            .unwrap();
+        records.reverse();
+        assert_eq!(records.len(), 4);
+        let diffs = history_diff(&records);
+        let created: Vec<&str> = diffs[0].changes.iter().map(|c| c.field.as_str()).collect();
+        assert!(created.contains(&"name") && created.contains(&"metadata.bank"));
+        assert!(!created.contains(&"tags"));
+
+        assert_eq!(
+            diffs[1].changes,
+            vec![
+                FieldChange {
+                    field: "metadata.term".into(),
+                    before: Value::Null,
+                    after: json!("1y"),
+                },
+                FieldChange {
+                    field: "name".into(),
+                    before: json!("存款"),
+                    after: json!("定期存款"),
+                },
+                FieldChange {
+                    field: "value".into(),
+                    before: json!(10000.0),
+                    after: json!(12000.0),
+                },
+            ]
+        );
+        assert_eq!(diffs[2].action, AuditAction::Deleted);
+        assert!(diffs[2].changes.is_empty());
+        // 删除后再次新建视为恢复
+        assert_eq!(diffs[3].action, AuditAction::Restored);
+    }
+
+    #[test]
+    fn test_audit_log() {
+        let dir = std::env::temp_dir().join(format!("asset-manager-audit-{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let mut log = AuditLog::for_store(&dir.join("assets.json"));
+        let now = Utc::now();
+        let asset = Asset::new("存款", AssetType::BankDeposit, 10000.0);
+        let mut updated = asset.clone();
+        updated.value = 12000.0;
+
+        let record = |before, after, at| {
+            AuditRecord::compare("user", AuditEntity::Asset, asset.id, before, after, at).unwrap()
+        };
+        let created = record(None, Some(&asset), now - Duration::days(400)).unwrap();
+        assert_eq!(created.operation, AuditOperation::Create);
+        log.record(created);
+        log.record(record(Some(&asset), Some(&updated), now).unwrap());
+        assert!(record(Some(&updated), Some(&updated), now).is_none());
+        log.flush().unwrap();
+        log.record(record(Some(&updated), None, now).unwrap());
+
+        let records = log
+            .query(&AuditQuery::for_entity(AuditEntity::Asset, asset.id))
+            .unwrap();
+        assert_eq!(records.len(), 3);
+        let update = records
+            .iter()
+            .find(|r| r.operation == AuditOperation::Update)
+            .unwrap();
+        assert_eq!(update.changes.len(), 1);
+        assert_eq!(update.changes[0].before, serde_json::json!(10000.0));
+
+        // 按保留天数清除文件与未写入的旧记录
+        let cutoff = AuditSettings::default().cutoff(now).unwrap();
+        assert_eq!(log.prune(cutoff).unwrap(), 1);
+        let query = AuditQuery {
+            operation: Some(AuditOperation::Create),
+            ..AuditQuery::default()
+        };
+        assert!(log.query(&query).unwrap().is_empty());
+        assert!(AuditSettings {
+            retention_days: Some(0)
+        }
+        .validate()
+        .is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,239 @@
+//! 数据备份：把当前数据写入备份目录，并读回校验，确认备份可用于恢复
+//!
+//! 按备份设置定时备份，只保留最新的若干份；恢复时旧格式的备份先迁移到当前格式。
+
+use super::json::JsonStore;
+use super::migration;
+use super::StorageError;
+use crate::settings::Settings;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::cmp::Reverse;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+/// 备份设置的设置项键名
+pub const BACKUP_SETTING_KEY: &str = "backup";
+
+/// 备份设置项
+pub const BACKUP_SETTINGS: Settings<BackupSettings> =
+    Settings::new(BACKUP_SETTING_KEY, BackupSettings::default);
+
+/// 检查是否需要定时备份的间隔（秒）
+pub const BACKUP_CHECK_INTERVAL_SECS: u64 = 600;
+
+/// 备份文件名中的时间格式
+const TIME_FORMAT: &str = "%Y%m%d-%H%M%S";
+
+/// 定时备份设置
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BackupSettings {
+    /// 是否启用定时备份
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// 备份间隔（小时）
+    #[serde(default = "default_interval_hours")]
+    pub interval_hours: u32,
+    /// 保留的备份份数
+    #[serde(default = "default_keep")]
+    pub keep: usize,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+fn default_interval_hours() -> u32 {
+    24
+}
+
+fn default_keep() -> usize {
+    7
+}
+
+impl Default for BackupSettings {
+    fn default() -> Self {
+        Self {
+            enabled: default_enabled(),
+            interval_hours: default_interval_hours(),
+            keep: default_keep(),
+        }
+    }
+}
+
+impl BackupSettings {
+    /// 备份间隔
+    pub fn interval(&self) -> chrono::Duration {
+        chrono::Duration::hours(self.interval_hours as i64)
+    }
+
+    /// 检查设置是否有效
+    pub fn validate(&self) -> Result<(), String> {
+        if self.interval_hours == 0 {
+            return Err("Backup interval must be at least one hour".to_string());
+        }
+        if self.keep == 0 {
+            return Err("At least one backup must be kept".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// 一份已校验的备份
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupInfo {
+    /// 备份文件路径
+    pub path: String,
+    /// 创建时间
+    pub created_at: DateTime<Utc>,
+    /// 文件大小（字节）
+    pub size: u64,
+    /// 数据格式版本
+    pub schema_version: u32,
+    /// 资产数
+    pub assets: usize,
+    /// 交易数
+    pub transactions: usize,
+}
+
+/// 数据文件的备份文件名前缀（数据文件名，内存数据库为 `assets`）
+pub(super) fn name_for(store_path: Option<&Path>) -> String {
+    store_path.and_then(Path::file_stem).map_or_else(
+        || "assets".into(),
+        |stem| stem.to_string_lossy().into_owned(),
+    )
+}
+
+/// 备份文件名：`<数据文件名>-<时间>.json`
+fn backup_path(dir: &Path, name: &str, at: DateTime<Utc>) -> PathBuf {
+    dir.join(format!("{}-{}.json", name, at.format(TIME_FORMAT)))
+}
+
+/// 从备份文件名解析创建时间，不是该数据文件的备份时为 None
+fn backup_time(path: &Path, name: &str) -> Option<DateTime<Utc>> {
+    if path.extension()? != "json" {
+        return None;
+    }
+    let stamp = path
+        .file_stem()?
+        .to_str()?
+        .strip_prefix(name)?
+        .strip_prefix('-')?;
+    NaiveDateTime::parse_from_str(stamp, TIME_FORMAT)
+        .ok()
+        .map(|t| t.and_utc())
+}
+
+/// 写入备份（先写临时文件再替换），读回后确认内容一致且能完整解析
+pub(super) fn write_verified(
+    store: &JsonStore,
+    dir: &Path,
+    name: &str,
+    created_at: DateTime<Utc>,
+) -> Result<BackupInfo, StorageError> {
+    fs::create_dir_all(dir)?;
+    let path = backup_path(dir, name, created_at);
+    let content = serde_json::to_string_pretty(store)?;
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, &content)?;
+    fs::rename(&tmp, &path)?;
+
+    let invalid =
+        |reason: &str| StorageError::InvalidData(format!("Backup {} {}", path.display(), reason));
+    let written = fs::read_to_string(&path)?;
+    if written != content {
+        return Err(invalid("does not match the data"));
+    }
+    let restored: JsonStore =
+        serde_json::from_str(&written).map_err(|_| invalid("cannot be read back"))?;
+    if restored.assets.len() != store.assets.len()
+        || restored.transactions.len() != store.transactions.len()
+    {
+        return Err(invalid("is incomplete"));
+    }
+
+    Ok(BackupInfo {
+        path: path.to_string_lossy().into_owned(),
+        created_at,
+        size: written.len() as u64,
+        schema_version: restored.schema_version,
+        assets: restored.assets.len(),
+        transactions: restored.transactions.len(),
+    })
+}
+
+/// 目录中该数据文件的备份文件与创建时间，按时间倒序
+pub(super) fn entries(
+    dir: &Path,
+    name: &str,
+) -> Result<Vec<(DateTime<Utc>, PathBuf)>, StorageError> {
+    let read_dir = match fs::read_dir(dir) {
+        Ok(read_dir) => read_dir,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    let mut entries = Vec::new();
+    for entry in read_dir {
+        let path = entry?.path();
+        if let Some(created_at) = backup_time(&path, name) {
+            entries.push((created_at, path));
+        }
+    }
+    entries.sort_by_key(|(created_at, _)| Reverse(*created_at));
+    Ok(entries)
+}
+
+/// 列出该数据文件的备份，按时间倒序（无法读取的文件跳过）
+pub(super) fn list(dir: &Path, name: &str) -> Result<Vec<BackupInfo>, StorageError> {
+    let mut backups = Vec::new();
+    for (created_at, path) in entries(dir, name)? {
+        match inspect(&path, created_at) {
+            Ok(info) => backups.push(info),
+            Err(e) => warn!("Skipping unreadable backup {:?}: {}", path, e),
+        }
+    }
+    Ok(backups)
+}
+
+/// 读取备份的格式版本与数量（不要求为当前格式）
+fn inspect(path: &Path, created_at: DateTime<Utc>) -> Result<BackupInfo, StorageError> {
+    let content = fs::read_to_string(path)?;
+    let value: Value = serde_json::from_str(&content)?;
+    let count = |field: &str| {
+        value
+            .get(field)
+            .and_then(Value::as_array)
+            .map_or(0, Vec::len)
+    };
+    Ok(BackupInfo {
+        path: path.to_string_lossy().into_owned(),
+        created_at,
+        size: content.len() as u64,
+        schema_version: migration::version_of(&value),
+        assets: count("assets"),
+        transactions: count("transactions"),
+    })
+}
+
+/// 只保留最新的 `keep` 份备份，返回删除的份数
+pub(super) fn rotate(dir: &Path, name: &str, keep: usize) -> Result<usize, StorageError> {
+    let stale: Vec<_> = entries(dir, name)?.into_iter().skip(keep.max(1)).collect();
+    for (_, path) in &stale {
+        fs::remove_file(path)?;
+    }
+    Ok(stale.len())
+}
+
+/// 读取备份用于恢复：在临时副本上迁移到当前格式，不修改备份本身
+pub(super) fn read(path: &Path) -> Result<JsonStore, StorageError> {
+    let tmp = path.with_extension("restore");
+    fs::copy(path, &tmp)?;
+    let store = migration::migrate_file(&tmp, |_| {})
+        .and_then(|_| Ok(serde_json::from_str(&fs::read_to_string(&tmp)?)?));
+    if let Err(e) = fs::remove_file(&tmp) {
+        warn!("Failed to remove {:?}: {}", tmp, e);
+    }
+    store
+}
@@ -0,0 +1,119 @@
+//! 追加日志：只改动资产、交易或估值时把变更追加到主文件旁的日志，而不是重写整个文件
+//!
+//! 日志每行一条 JSON 变更。打开数据库时先读主文件再按顺序重放日志；日志过长或其他数据
+//! 变化时重写主文件（压缩）并清空日志。写入中断导致的不完整末行会被忽略。
+
+use super::StorageError;
+use crate::asset::{Asset, AssetTransaction};
+use crate::valuation::Valuation;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tracing::warn;
+use uuid::Uuid;
+
+/// 日志条数超过该值时重写主文件
+pub const COMPACT_THRESHOLD: usize = 1000;
+
+/// 表中一行的变更
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum Change<T> {
+    /// 写入（新增或替换）
+    Put { row: Box<T> },
+    /// 删除
+    Delete { id: Uuid },
+}
+
+/// 日志中的一条记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "table", content = "change", rename_all = "snake_case")]
+pub enum JournalEntry {
+    Assets(Change<Asset>),
+    Transactions(Change<AssetTransaction>),
+    Valuations(Change<Valuation>),
+}
+
+/// 主文件对应的追加日志
+#[derive(Debug)]
+pub struct Journal {
+    path: PathBuf,
+    /// 日志中已有的条数
+    len: usize,
+}
+
+impl Journal {
+    /// 主文件的日志（同目录下的 `<文件名>.journal`）
+    pub fn for_store(store_path: &Path) -> Self {
+        let mut path = store_path.as_os_str().to_owned();
+        path.push(".journal");
+        Self {
+            path: PathBuf::from(path),
+            len: 0,
+        }
+    }
+
+    /// 日志文件路径
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// 再追加 `pending` 条后是否应重写主文件
+    pub fn needs_compaction(&self, pending: usize) -> bool {
+        self.len + pending > COMPACT_THRESHOLD
+    }
+
+    /// 读取日志中的全部条目
+    pub fn read(&mut self) -> Result<Vec<JournalEntry>, StorageError> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+        let content = fs::read_to_string(&self.path)?;
+        let mut entries = Vec::new();
+        for (i, line) in content.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str(line) {
+                Ok(entry) => entries.push(entry),
+                Err(e) => {
+                    warn!(
+                        "Ignoring journal {:?} from line {}: {}",
+                        self.path,
+                        i + 1,
+                        e
+                    );
+                    break;
+                }
+            }
+        }
+        self.len = entries.len();
+        Ok(entries)
+    }
+
+    /// 追加条目
+    pub fn append(&mut self, entries: &[JournalEntry]) -> Result<(), StorageError> {
+        let mut content = String::new();
+        for entry in entries {
+            content.push_str(&serde_json::to_string(entry)?);
+            content.push('\n');
+        }
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?
+            .write_all(content.as_bytes())?;
+        self.len += entries.len();
+        Ok(())
+    }
+
+    /// 清空日志（主文件已包含全部数据后调用）
+    pub fn clear(&mut self) -> Result<(), StorageError> {
+        if self.path.exists() {
+            fs::remove_file(&self.path)?;
+        }
+        self.len = 0;
+        Ok(())
+    }
+}
@@ -1,29 +1,344 @@
 //! JSON 文件存储实现
 
-use super::StorageError;
-use crate::asset::{Asset, AssetSummary, AssetTransaction, AssetType};
+use super::archive::{Archive, ArchiveMode, ArchiveSummary};
+use super::audit::{
+    history_diff, AssetVersionDiff, AuditEntity, AuditLog, AuditQuery, AuditRecord, AuditSettings,
+    AUDIT_SETTINGS, DEFAULT_ACTOR,
+};
+use super::backup::{self, BackupInfo, BackupSettings, BACKUP_SETTINGS};
+use super::journal::{Journal, JournalEntry};
+use super::merge::{MergeChoice, MergeConflict, MergeReport, MergeStrategy};
+use super::migration::{self, SCHEMA_VERSION};
+use super::sync::{
+    ConflictResolution, FileState, LockAttempt, LockInfo, ReloadOutcome, StoreConflict, StoreLock,
+};
+use super::table::{Record, Table};
+use super::{
+    sort_assets, AssetFilter, AssetUpsert, BulkResult, CompactReport, RebuildReport, SaveMode,
+    SaveSettings, SaveStatus, Snapshot, SortBy, SortOrder, StorageError, TransactionCursor,
+    TransactionPage, TransactionQuery, AUTOSAVE_CHECK_INTERVAL_SECS, DEBOUNCE_CHECK_INTERVAL_MS,
+    SAVE_SETTINGS, SAVE_SETTING_KEY,
+};
+use crate::analytics::{
+    attribution, interpolate_series, sum_deltas_by_asset, transactions_by_asset, value_history,
+    Attribution, ChartSeries, ChartSource, DailyBalance, DailyBalances, DateRange,
+    DownsampleMethod, GroupBy, InterpolationMethod, Interval, SeriesPoint, ValuePoint,
+};
+use crate::asset::{
+    category_key, holding_quantity, projected_balances, quantity_key, realized_sales,
+    replay_cost_basis, sip_report, Asset, AssetPerformance, AssetRelation, AssetSummary,
+    AssetTransaction, AssetType, ChargeKind, ClosedPeriod, CorporateAction, CostBasisReport,
+    CostMethod, Currency, CurrencyConversion, DisplayCatalog, DisplayMeta, DisplayTarget,
+    DividendEvent, Envelope, EnvelopeSummary, GraphEdge, GraphNode, MetadataPath, MetadataSchemas,
+    ProjectedBalance, RealizedGainsReport, RelatedAsset, RelationDirection, RelationGraph,
+    Reminder, SaleCharge, SaleRecord, SchemaViolation, SellOrder, SipInstallment, SipPlan,
+    SipReport, TransactionStatus, TransactionTemplate, TransactionType,
+};
+use crate::assumptions::{Assumptions, ASSUMPTIONS_SETTINGS};
+use crate::attachment::{self, Attachment};
+use crate::checkin::{CheckIn, CheckInSnapshot};
+use crate::email::{ReportEmailSettings, SmtpSettings, REPORT_EMAIL_SETTINGS, SMTP_SETTINGS};
+use crate::first_run::LOCALE_SETTINGS;
+use crate::format::{FormatSettings, Formatter, FORMAT_SETTINGS};
+use crate::fx::{
+    fx_exposure, fx_gain_loss, FxExposure, FxGainLossReport, FxRate, FxSettings, FX_SETTINGS,
+};
+use crate::import::{
+    apply_trade, find_holding, line_hashes, new_holding, trade_hashes, ImportBatch,
+    ImportBatchStats, ImportSource, ImportedFile, MappingProfile, StatementImport, StatementLine,
+    TradeImport, TradeLine, TradeProfile, TradeSide, UndoImport, WatchFolder,
+};
+use crate::ledger::{self, AccountKind, Ledger, LedgerAccount, LedgerEntry, TrialBalance};
+use crate::milestone::{detect_milestones, Milestone, NetWorthDay, NOTIFY_WITHIN_DAYS};
+use crate::notification::{Notification, NotificationKind};
+use crate::precision::{PrecisionSettings, PRECISION_SETTINGS};
+use crate::report::{MonthlyReport, ReportParams};
+use crate::rules::{
+    render_text, PluginCall, Rule, RuleAction, RuleContext, RuleSignal, RuleRun, RuleTrigger,
+};
+use crate::search::{SearchSettings, SEARCH_SETTINGS};
+use crate::secrets::SecretStore;
+use crate::settings::{Settings, Subscribers, SubscriptionId};
+use crate::split::{self, Debt, ParticipantBalance, Settlement, SharedExpense};
+use crate::tags::{TagSettings, TAG_SETTINGS};
+use crate::timeline::{start_of_day, TimelineEvent, TimelineFilter, TimelineKind};
+use crate::valuation::Valuation;
+use chrono::{DateTime, NaiveDate, Utc};
+use rayon::prelude::*;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::ops::Bound;
 use std::path::{Path, PathBuf};
-use tracing::info;
+use std::sync::Arc;
+use tracing::{info, warn};
 use uuid::Uuid;
 
 /// JSON 存储的数据结构
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct JsonStore {
-    /// 资产与负债
-    pub assets: Vec<Asset>,
-    /// 交易记录
-    pub transactions: Vec<AssetTransaction>,
+    /// 数据格式版本（引入版本号之前的文件为 0）
+    #[serde(default)]
+    pub schema_version: u32,
+    /// 资产与负债（按创建时间索引）
+    pub assets: Table<Asset>,
+    /// 交易记录（按交易时间索引）
+    pub transactions: Table<AssetTransaction>,
+    /// 估值历史（按记录时间索引）
+    #[serde(default)]
+    pub valuations: Table<Valuation>,
     /// 应用设置
     pub settings: HashMap<String, String>,
+    /// 复查提醒
+    #[serde(default)]
+    pub reminders: Vec<Reminder>,
+    /// 应用内通知
+    #[serde(default)]
+    pub notifications: Vec<Notification>,
+    /// 资产关系
+    #[serde(default)]
+    pub relations: Vec<AssetRelation>,
+    /// 交易模板
+    #[serde(default)]
+    pub templates: Vec<TransactionTemplate>,
+    /// 历史汇率
+    #[serde(default)]
+    pub fx_rates: Vec<FxRate>,
+    /// 已结账的会计期间
+    #[serde(default)]
+    pub closed_periods: Vec<ClosedPeriod>,
+    /// 复式账簿（为空表示未启用复式记账）
+    #[serde(default)]
+    pub ledger: Option<Ledger>,
+    /// 信封预算
+    #[serde(default)]
+    pub envelopes: Vec<Envelope>,
+    /// 分摊账单
+    #[serde(default)]
+    pub shared_expenses: Vec<SharedExpense>,
+    /// 分摊账单的结算记录
+    #[serde(default)]
+    pub settlements: Vec<Settlement>,
+    /// 对账单导入的映射配置
+    #[serde(default)]
+    pub import_profiles: Vec<MappingProfile>,
+    /// 券商成交单的映射配置
+    #[serde(default)]
+    pub trade_profiles: Vec<TradeProfile>,
+    /// 自动导入的监视目录
+    #[serde(default)]
+    pub watch_folders: Vec<WatchFolder>,
+    /// 已导入的对账单文件
+    #[serde(default)]
+    pub imported_files: Vec<ImportedFile>,
+    /// 导入批次
+    #[serde(default)]
+    pub import_batches: Vec<ImportBatch>,
+    /// 敏感凭据（如 SMTP 密码）
+    #[serde(default)]
+    pub secrets: SecretStore,
+    /// 自动化规则
+    #[serde(default)]
+    pub rules: Vec<Rule>,
+    /// 资产类型、分类与标签的显示样式
+    #[serde(default)]
+    pub display: DisplayCatalog,
+    /// 已达成的里程碑
+    #[serde(default)]
+    pub milestones: Vec<Milestone>,
+    /// 财务日记
+    #[serde(default)]
+    pub check_ins: Vec<CheckIn>,
+    /// 基金定投计划
+    #[serde(default)]
+    pub sip_plans: Vec<SipPlan>,
+    /// 附件记录
+    #[serde(default)]
+    pub attachments: Vec<Attachment>,
+    /// 回收站
+    #[serde(default)]
+    pub trash: Vec<TrashedAsset>,
+}
+
+/// 回收站中的资产及删除时一并移出的关联记录，恢复时原样放回
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrashedAsset {
+    pub asset: Asset,
+    #[serde(default)]
+    pub transactions: Vec<AssetTransaction>,
+    #[serde(default)]
+    pub reminders: Vec<Reminder>,
+    #[serde(default)]
+    pub envelopes: Vec<Envelope>,
+    #[serde(default)]
+    pub relations: Vec<AssetRelation>,
+    #[serde(default)]
+    pub templates: Vec<TransactionTemplate>,
+    #[serde(default)]
+    pub sip_plans: Vec<SipPlan>,
+    #[serde(default)]
+    pub import_profiles: Vec<MappingProfile>,
+    #[serde(default)]
+    pub watch_folders: Vec<WatchFolder>,
+    #[serde(default)]
+    pub imported_files: Vec<ImportedFile>,
+    #[serde(default)]
+    pub attachments: Vec<Attachment>,
+    #[serde(default)]
+    pub valuations: Vec<Valuation>,
+}
+
+impl JsonStore {
+    /// 当前格式版本的空数据
+    fn empty() -> Self {
+        Self {
+            schema_version: SCHEMA_VERSION,
+            ..Self::default()
+        }
+    }
+
+    /// 取出资产、交易与估值自上次写入以来的变更
+    fn take_changes(&mut self) -> Vec<JournalEntry> {
+        let assets = self.assets.take_changes().into_iter();
+        let transactions = self.transactions.take_changes().into_iter();
+        let valuations = self.valuations.take_changes().into_iter();
+        assets
+            .map(JournalEntry::Assets)
+            .chain(transactions.map(JournalEntry::Transactions))
+            .chain(valuations.map(JournalEntry::Valuations))
+            .collect()
+    }
+
+    /// 丢弃资产、交易与估值记录的变更
+    fn clear_changes(&mut self) {
+        self.assets.clear_changes();
+        self.transactions.clear_changes();
+        self.valuations.clear_changes();
+    }
+
+    /// 重放一条日志
+    fn apply(&mut self, entry: JournalEntry) {
+        match entry {
+            JournalEntry::Assets(change) => self.assets.apply(change),
+            JournalEntry::Transactions(change) => self.transactions.apply(change),
+            JournalEntry::Valuations(change) => self.valuations.apply(change),
+        }
+    }
+
+    /// 资产、交易与估值以外数据的指纹，用于判断保存时能否只追加日志
+    fn fingerprint(&mut self) -> Result<u64, StorageError> {
+        let assets = std::mem::take(&mut self.assets);
+        let transactions = std::mem::take(&mut self.transactions);
+        let valuations = std::mem::take(&mut self.valuations);
+        let content = serde_json::to_vec(self);
+        self.assets = assets;
+        self.transactions = transactions;
+        self.valuations = valuations;
+
+        let mut hasher = DefaultHasher::new();
+        content?.hash(&mut hasher);
+        Ok(hasher.finish())
+    }
+}
+
+/// 把表中记录修改前后的内容记入修改记录
+fn record_changes<T: Record + Serialize>(
+    audit: &mut AuditLog,
+    actor: &str,
+    entity: AuditEntity,
+    changes: Vec<(Option<T>, Option<T>)>,
+    now: DateTime<Utc>,
+) -> Result<(), StorageError> {
+    for (before, after) in changes {
+        let Some(id) = before.as_ref().or(after.as_ref()).map(Record::id) else {
+            continue;
+        };
+        let record = AuditRecord::compare(actor, entity, id, before.as_ref(), after.as_ref(), now)?;
+        if let Some(record) = record {
+            audit.record(record);
+        }
+    }
+    Ok(())
+}
+
+/// 去掉序列点的插值标记
+fn series_values(points: Vec<SeriesPoint>) -> Vec<ValuePoint> {
+    points
+        .into_iter()
+        .map(|p| ValuePoint {
+            timestamp: p.timestamp,
+            value: p.value,
+        })
+        .collect()
+}
+
+/// 移出并返回满足条件的元素
+fn take_where<T>(items: &mut Vec<T>, mut f: impl FnMut(&T) -> bool) -> Vec<T> {
+    let (taken, kept) = std::mem::take(items).into_iter().partition(|item| f(item));
+    *items = kept;
+    taken
+}
+
+/// 读取主文件并重放其日志
+fn load_store(path: &Path, journal: &mut Journal) -> Result<JsonStore, StorageError> {
+    let content = fs::read_to_string(path)?;
+    let mut store = if content.trim().is_empty() {
+        JsonStore::empty()
+    } else {
+        serde_json::from_str(&content)?
+    };
+    for entry in journal.read()? {
+        store.apply(entry);
+    }
+    store.take_changes();
+    Ok(store)
 }
 
 /// JSON 文件数据库
+///
+/// 只改动资产或交易时把变更追加到日志，其他数据变化或日志过长时才重写主文件。
+/// 非立即保存模式下修改只标记为未保存，由定时保存或 `save_now` 写入。
 pub struct Database {
     path: Option<PathBuf>,
-    store: JsonStore,
+    /// 数据（与快照共享，修改时若有快照在使用则先复制）
+    store: Arc<JsonStore>,
+    /// 主文件的追加日志（内存数据库为空）
+    journal: Option<Journal>,
+    /// 资产与交易的修改记录
+    audit: AuditLog,
+    /// 记入修改记录的操作者
+    actor: String,
+    /// 主文件中资产与交易以外数据的指纹，为空时下次保存须重写主文件
+    fingerprint: Option<u64>,
+    /// 保存设置（打开时从设置项读取）
+    save_settings: SaveSettings,
+    /// 最早一次未保存修改的时间
+    unsaved_since: Option<DateTime<Utc>>,
+    /// 最近一次未保存修改的时间
+    last_change: Option<DateTime<Utc>>,
+    /// 最近一次写入文件的时间
+    last_saved: Option<DateTime<Utc>>,
+    /// 本实例持有的锁文件
+    lock: Option<StoreLock>,
+    /// 持有锁的其他实例
+    lock_holder: Option<LockInfo>,
+    /// 本实例最近一次读写后主文件的状态
+    main_state: Option<FileState>,
+    /// 本实例最近一次读写后日志的状态
+    journal_state: Option<FileState>,
+    /// 未解决的冲突：发现时间与磁盘上的版本
+    conflict: Option<(DateTime<Utc>, Box<JsonStore>)>,
+    /// 元数据 Schema（内置与插件提供，不持久化）
+    metadata_schemas: MetadataSchemas,
+    /// 各资产的日终价值（打开时构建，随资产与交易的修改更新）
+    daily_balances: Arc<DailyBalances>,
+    /// 设置变化的订阅者（不持久化）
+    subscribers: Subscribers,
+    /// 只读模式下打开时的数据与日终价值，修改被拒绝时恢复
+    read_only: Option<(Arc<JsonStore>, Arc<DailyBalances>)>,
 }
 
 impl Database {
@@ -36,261 +351,7237 @@ impl Database {
             fs::create_dir_all(parent)?;
         }
 
-        let store = if path.exists() {
-            let content = fs::read_to_string(&path)?;
-            if content.trim().is_empty() {
-                JsonStore::default()
-            } else {
-                serde_json::from_str(&content)?
-            }
-        } else {
-            let store = JsonStore::default();
-            let content = serde_json::to_string_pretty(&store)?;
+        if !path.exists() {
+            let content = serde_json::to_string_pretty(&JsonStore::empty())?;
             fs::write(&path, content)?;
-            store
+        }
+
+        // 桌面端升级后会先执行迁移并显示进度，这里补上其他入口打开旧文件的情况
+        let migrated = migration::migrate_file(&path, |progress| {
+            info!("Migrating {:?}: {}", path, progress.description);
+        })?;
+        if migrated > 0 {
+            info!("Migrated {:?} to format {}", path, SCHEMA_VERSION);
+        }
+
+        // 重放上次关闭前追加的日志
+        let mut journal = Journal::for_store(&path);
+        let store = load_store(&path, &mut journal)?;
+        let replay = journal.path().exists();
+
+        let (lock, lock_holder) = match StoreLock::acquire(&path)? {
+            LockAttempt::Acquired(lock) => (Some(lock), None),
+            LockAttempt::Held(holder) => {
+                warn!(
+                    "Data file is in use by {} (pid {})",
+                    holder.host, holder.pid
+                );
+                (None, Some(holder))
+            }
         };
 
         info!("JSON database opened: {:?}", path);
 
-        Ok(Self {
+        let mut db = Self {
+            daily_balances: Arc::new(DailyBalances::build(&store.assets, &store.transactions)),
+            store: Arc::new(store),
+            subscribers: Subscribers::default(),
+            main_state: FileState::read(&path)?,
+            journal_state: FileState::read(journal.path())?,
+            audit: AuditLog::for_store(&path),
+            actor: DEFAULT_ACTOR.to_string(),
             path: Some(path),
-            store,
-        })
+            journal: Some(journal),
+            fingerprint: None,
+            save_settings: SaveSettings::default(),
+            unsaved_since: None,
+            last_change: None,
+            last_saved: None,
+            lock,
+            lock_holder,
+            conflict: None,
+            metadata_schemas: MetadataSchemas::builtin(),
+            read_only: None,
+        };
+        db.save_settings = db.get_save_settings()?;
+        db.fingerprint = Some(db.store_mut().fingerprint()?);
+        if let Err(e) = db.prune_audit_log(Utc::now()) {
+            warn!("Failed to prune audit log of {:?}: {}", db.path, e);
+        }
+        // 日志已并入内存，重写主文件后删除（包括只有不完整条目的日志）
+        if replay {
+            info!("Compacting journal of {:?}", db.path);
+            db.write_snapshot()?;
+        }
+        Ok(db)
     }
 
     /// 创建内存数据库（用于测试）
     pub fn open_in_memory() -> Result<Self, StorageError> {
         Ok(Self {
             path: None,
-            store: JsonStore::default(),
+            store: Arc::new(JsonStore::empty()),
+            journal: None,
+            audit: AuditLog::default(),
+            actor: DEFAULT_ACTOR.to_string(),
+            fingerprint: None,
+            save_settings: SaveSettings::default(),
+            unsaved_since: None,
+            last_change: None,
+            last_saved: None,
+            lock: None,
+            lock_holder: None,
+            main_state: None,
+            journal_state: None,
+            conflict: None,
+            metadata_schemas: MetadataSchemas::builtin(),
+            daily_balances: Arc::default(),
+            subscribers: Subscribers::default(),
+            read_only: None,
+        })
+    }
+
+    /// 以只读方式打开数据文件（安全模式、查看备份或共享的文件）：不创建、不获取锁文件，
+    /// 也不改动原文件（旧格式的文件在临时目录的副本上迁移）；修改返回 `ReadOnly` 错误，数据保持打开时的状态
+    pub fn open_read_only(path: impl AsRef<Path>) -> Result<Self, StorageError> {
+        let path = path.as_ref().to_path_buf();
+        if !path.exists() {
+            return Err(StorageError::NotFound(path.display().to_string()));
+        }
+        let mut journal = Journal::for_store(&path);
+        let store = if migration::pending_migrations(&path)?.is_empty() {
+            load_store(&path, &mut journal)?
+        } else {
+            let tmp = std::env::temp_dir().join(format!("asset-manager-{}.json", Uuid::new_v4()));
+            fs::copy(&path, &tmp)?;
+            let store =
+                migration::migrate_file(&tmp, |_| {}).and_then(|_| load_store(&tmp, &mut journal));
+            if let Err(e) = fs::remove_file(&tmp) {
+                warn!("Failed to remove {:?}: {}", tmp, e);
+            }
+            store?
+        };
+        info!("JSON database opened read-only: {:?}", path);
+
+        let mut db = Self::open_in_memory()?;
+        db.daily_balances = Arc::new(DailyBalances::build(&store.assets, &store.transactions));
+        db.store = Arc::new(store);
+        // 读取文件已有的修改记录，只读时不会写入
+        db.audit = AuditLog::for_store(&path);
+        db.path = Some(path);
+        db.save_settings = db.get_save_settings()?;
+        db.read_only = Some((Arc::clone(&db.store), Arc::clone(&db.daily_balances)));
+        Ok(db)
+    }
+
+    /// 是否以只读方式打开
+    pub fn is_read_only(&self) -> bool {
+        self.read_only.is_some()
+    }
+
+    /// 只读模式下恢复打开时的数据并拒绝修改
+    fn check_writable(&mut self) -> Result<(), StorageError> {
+        if let Some((store, daily_balances)) = &self.read_only {
+            self.store = Arc::clone(store);
+            self.daily_balances = Arc::clone(daily_balances);
+            return Err(StorageError::ReadOnly);
+        }
+        Ok(())
+    }
+
+    /// 数据文件路径（内存数据库为空）
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// 备份文件名前缀（数据文件名，内存数据库为 `assets`）
+    fn backup_name(&self) -> String {
+        backup::name_for(self.path.as_deref())
+    }
+
+    /// 把当前数据（包括未保存的修改）备份到目录中，并读回校验
+    pub fn backup_to(&self, dir: impl AsRef<Path>) -> Result<BackupInfo, StorageError> {
+        backup::write_verified(&self.store, dir.as_ref(), &self.backup_name(), Utc::now())
+    }
+
+    /// 列出目录中本数据文件的备份，按时间倒序
+    pub fn list_backups(&self, dir: impl AsRef<Path>) -> Result<Vec<BackupInfo>, StorageError> {
+        backup::list(dir.as_ref(), &self.backup_name())
+    }
+
+    /// 定时备份：启用且距最新一份备份已超过间隔时备份，并只保留最新的若干份，返回新备份
+    pub fn scheduled_backup(
+        &self,
+        dir: impl AsRef<Path>,
+        now: DateTime<Utc>,
+    ) -> Result<Option<BackupInfo>, StorageError> {
+        let settings = self.get_backup_settings()?;
+        if !settings.enabled {
+            return Ok(None);
+        }
+        let (dir, name) = (dir.as_ref(), self.backup_name());
+        let latest = backup::entries(dir, &name)?.first().map(|(at, _)| *at);
+        if latest.is_some_and(|at| now - at < settings.interval()) {
+            return Ok(None);
+        }
+        let created = backup::write_verified(&self.store, dir, &name, now)?;
+        let removed = backup::rotate(dir, &name, settings.keep)?;
+        if removed > 0 {
+            info!("Removed {} old backups from {:?}", removed, dir);
+        }
+        Ok(Some(created))
+    }
+
+    /// 从目录中的一份备份恢复，并写入数据文件
+    ///
+    /// 恢复前先备份当前数据（包括未保存的修改），返回这份备份以便撤销。
+    pub fn restore_backup(
+        &mut self,
+        dir: impl AsRef<Path>,
+        path: impl AsRef<Path>,
+    ) -> Result<BackupInfo, StorageError> {
+        self.check_writable()?;
+        let (dir, path) = (dir.as_ref(), path.as_ref());
+        let listed = backup::entries(dir, &self.backup_name())?;
+        if !listed.iter().any(|(_, p)| p == path) {
+            return Err(StorageError::NotFound(path.display().to_string()));
+        }
+        let store = backup::read(path)?;
+        let current = self.backup_to(dir)?;
+        info!("Restoring {:?} from backup {:?}", self.path, path);
+        self.replace_store(store)?;
+        self.conflict = None;
+        self.write_snapshot()?;
+        self.unsaved_since = None;
+        self.last_saved = Some(Utc::now());
+        Ok(current)
+    }
+
+    /// 创建只读快照（与数据库共享数据，不写入文件）
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot::new(Self {
+            path: None,
+            store: Arc::clone(&self.store),
+            journal: None,
+            audit: self.audit.clone(),
+            actor: self.actor.clone(),
+            fingerprint: None,
+            save_settings: self.save_settings,
+            unsaved_since: None,
+            last_change: None,
+            last_saved: self.last_saved,
+            lock: None,
+            lock_holder: None,
+            main_state: None,
+            journal_state: None,
+            conflict: None,
+            metadata_schemas: self.metadata_schemas.clone(),
+            daily_balances: Arc::clone(&self.daily_balances),
+            subscribers: Subscribers::default(),
+            read_only: None,
         })
     }
 
-    /// 将数据写入文件
-    fn save(&self) -> Result<(), StorageError> {
-        if let Some(ref path) = self.path {
-            let content = serde_json::to_string_pretty(&self.store)?;
-            fs::write(path, content)?;
+    /// 修改数据前取得可写引用（有快照共享数据时先复制一份）
+    fn store_mut(&mut self) -> &mut JsonStore {
+        Arc::make_mut(&mut self.store)
+    }
+
+    /// 修改后调用：记下修改记录，立即保存模式下写入文件，否则只标记为未保存
+    fn save(&mut self) -> Result<(), StorageError> {
+        self.check_writable()?;
+        self.record_mutations()?;
+        if self.save_settings.mode == SaveMode::Immediate {
+            return self.save_now();
+        }
+        let now = Utc::now();
+        self.unsaved_since.get_or_insert(now);
+        self.last_change = Some(now);
+        Ok(())
+    }
+
+    /// 立即写入所有修改：只有资产或交易变化时追加日志，否则重写主文件
+    pub fn save_now(&mut self) -> Result<(), StorageError> {
+        self.check_writable()?;
+        match self.write_changes() {
+            Ok(()) => {
+                self.unsaved_since = None;
+                self.last_change = None;
+                self.last_saved = Some(Utc::now());
+                Ok(())
+            }
+            Err(e) => {
+                // 已取出的变更可能未写入日志，下次保存时重写主文件
+                self.fingerprint = None;
+                self.unsaved_since.get_or_insert_with(Utc::now);
+                Err(e)
+            }
+        }
+    }
+
+    /// 把资产与交易自上次记录以来的修改记入修改记录
+    fn record_mutations(&mut self) -> Result<(), StorageError> {
+        let now = Utc::now();
+        let store = self.store_mut();
+        let assets = store.assets.take_originals();
+        let transactions = store.transactions.take_originals();
+        let (audit, actor) = (&mut self.audit, self.actor.as_str());
+        record_changes(audit, actor, AuditEntity::Asset, assets, now)?;
+        record_changes(audit, actor, AuditEntity::Transaction, transactions, now)
+    }
+
+    fn write_changes(&mut self) -> Result<(), StorageError> {
+        if self.journal.is_none() {
+            self.store_mut().clear_changes();
+            return Ok(());
+        }
+        self.check_external_changes()?;
+        let changes = self.store_mut().take_changes();
+        let fingerprint = self.store_mut().fingerprint()?;
+        let Some(journal) = self.journal.as_mut() else {
+            return Ok(());
+        };
+        if Some(fingerprint) != self.fingerprint || journal.needs_compaction(changes.len()) {
+            self.write_snapshot()?;
+        } else if !changes.is_empty() {
+            journal.append(&changes)?;
+            self.journal_state = FileState::read(journal.path())?;
+        }
+        self.audit.flush()
+    }
+
+    /// 写入前检查主文件与日志是否被其他实例或同步工具修改，被修改时记录冲突而不覆盖
+    fn check_external_changes(&mut self) -> Result<(), StorageError> {
+        let (Some(path), Some(_)) = (&self.path, &self.journal) else {
+            return Ok(());
+        };
+        let conflict = || StorageError::Conflict(path.display().to_string());
+        if self.conflict.is_some() {
+            return Err(conflict());
+        }
+        if !self.files_changed()? {
+            return Ok(());
+        }
+        warn!("Data file {:?} was modified externally", path);
+        let theirs = load_store(path, &mut Journal::for_store(path))?;
+        let err = conflict();
+        self.conflict = Some((Utc::now(), Box::new(theirs)));
+        Err(err)
+    }
+
+    /// 主文件或日志是否在本实例最近一次读写后被修改
+    fn files_changed(&self) -> Result<bool, StorageError> {
+        let (Some(path), Some(journal)) = (&self.path, &self.journal) else {
+            return Ok(false);
+        };
+        Ok(FileState::changed(self.main_state.as_ref(), path)?
+            || FileState::changed(self.journal_state.as_ref(), journal.path())?)
+    }
+
+    /// 数据文件被外部修改（命令行编辑或其他设备同步）时重新加载
+    ///
+    /// 本实例有未保存的修改时不覆盖内存中的数据，而是记为冲突；已有未解决的冲突时不再检查。
+    pub fn reload_if_changed(&mut self) -> Result<ReloadOutcome, StorageError> {
+        if self.conflict.is_some() || !self.files_changed()? {
+            return Ok(ReloadOutcome::Unchanged);
+        }
+        let Some(path) = self.path.clone() else {
+            return Ok(ReloadOutcome::Unchanged);
+        };
+        let mut journal = Journal::for_store(&path);
+        let theirs = load_store(&path, &mut journal)?;
+        if self.unsaved_since.is_some() {
+            warn!(
+                "Data file {:?} changed while there are unsaved changes",
+                path
+            );
+            self.conflict = Some((Utc::now(), Box::new(theirs)));
+            return Ok(ReloadOutcome::Conflict);
+        }
+
+        info!("Reloading externally modified data file {:?}", path);
+        self.replace_store(theirs)?;
+        self.main_state = FileState::read(&path)?;
+        self.journal_state = FileState::read(journal.path())?;
+        self.journal = Some(journal);
+        self.fingerprint = Some(self.store_mut().fingerprint()?);
+        Ok(ReloadOutcome::Reloaded)
+    }
+
+    /// 以另一份数据替换内存中的数据
+    fn replace_store(&mut self, mut store: JsonStore) -> Result<(), StorageError> {
+        store.clear_changes();
+        let (old, new) = (&self.store.settings, &store.settings);
+        let changed: HashSet<String> = old
+            .keys()
+            .chain(new.keys())
+            .filter(|key| old.get(*key) != new.get(*key))
+            .cloned()
+            .collect();
+        self.daily_balances = Arc::new(DailyBalances::build(&store.assets, &store.transactions));
+        self.store = Arc::new(store);
+        self.save_settings = self.get_save_settings()?;
+        for key in changed {
+            self.subscribers
+                .notify(&key, self.store.settings.get(&key).map(String::as_str));
+        }
+        Ok(())
+    }
+
+    /// 数据文件被外部修改后尚未解决的冲突（包含两个版本的差异）
+    pub fn store_conflict(&self) -> Option<StoreConflict> {
+        let (detected_at, theirs) = self.conflict.as_ref()?;
+        Some(StoreConflict::compare(
+            *detected_at,
+            (&self.store.assets, &self.store.transactions),
+            (&theirs.assets, &theirs.transactions),
+        ))
+    }
+
+    /// 解决冲突：保留本实例的版本覆盖磁盘，或采用磁盘上的版本并放弃未保存的修改
+    pub fn resolve_store_conflict(
+        &mut self,
+        resolution: ConflictResolution,
+    ) -> Result<(), StorageError> {
+        let Some((_, theirs)) = self.conflict.take() else {
+            return Err(StorageError::InvalidData(
+                "No conflict to resolve".to_string(),
+            ));
+        };
+        match resolution {
+            ConflictResolution::KeepTheirs => self.replace_store(*theirs)?,
+            ConflictResolution::KeepMine => self.store_mut().clear_changes(),
+        }
+        self.write_snapshot()?;
+        self.unsaved_since = None;
+        self.last_saved = Some(Utc::now());
+        Ok(())
+    }
+
+    /// 同时打开数据文件的其他实例
+    pub fn lock_holder(&self) -> Option<&LockInfo> {
+        self.lock_holder.as_ref()
+    }
+
+    /// 释放锁文件（退出前调用）
+    pub fn release_lock(&mut self) {
+        self.lock = None;
+    }
+
+    /// 刷新锁文件心跳；未持有锁时尝试重新获取（另一实例已退出或失效）
+    pub fn refresh_lock(&mut self) -> Result<(), StorageError> {
+        let (Some(path), None) = (&self.path, &self.read_only) else {
+            return Ok(());
+        };
+        match self.lock.as_mut() {
+            Some(lock) => {
+                if let Some(holder) = lock.refresh()? {
+                    warn!(
+                        "Data file was taken over by {} (pid {})",
+                        holder.host, holder.pid
+                    );
+                    self.lock = None;
+                    self.lock_holder = Some(holder);
+                }
+            }
+            None => match StoreLock::acquire(path)? {
+                LockAttempt::Acquired(lock) => {
+                    self.lock = Some(lock);
+                    self.lock_holder = None;
+                }
+                LockAttempt::Held(holder) => self.lock_holder = Some(holder),
+            },
         }
         Ok(())
     }
 
+    /// 重写主文件（先写临时文件再替换）并清空日志
+    fn write_snapshot(&mut self) -> Result<(), StorageError> {
+        let (Some(path), Some(journal)) = (&self.path, self.journal.as_mut()) else {
+            return Ok(());
+        };
+        let content = serde_json::to_string_pretty(&*self.store)?;
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, &content)?;
+        fs::rename(&tmp, path)?;
+        journal.clear()?;
+        self.main_state = Some(FileState::written(path, content.as_bytes())?);
+        self.journal_state = None;
+        self.fingerprint = Some(self.store_mut().fingerprint()?);
+        Ok(())
+    }
+
     // ============ 资产操作 ============
 
     /// 创建资产
     pub fn create_asset(&mut self, asset: &Asset) -> Result<(), StorageError> {
-        self.store.assets.push(asset.clone());
+        self.insert_asset(asset)?;
+        self.refresh_daily_balances([asset.id]);
         self.save()
     }
 
+    /// 批量创建资产，校验失败或ID已存在的跳过，其余一次性保存
+    pub fn create_assets_bulk(&mut self, assets: &[Asset]) -> Result<BulkResult, StorageError> {
+        let mut result = BulkResult::default();
+        for (index, asset) in assets.iter().enumerate() {
+            let inserted = self.insert_asset(asset);
+            result.push(index, asset.id, inserted);
+        }
+        if result.inserted > 0 {
+            self.refresh_daily_balances(assets.iter().map(|a| a.id));
+            self.save()?;
+        }
+        Ok(result)
+    }
+
+    /// 校验并写入新资产（不保存），ID已存在时报错
+    fn insert_asset(&mut self, asset: &Asset) -> Result<(), StorageError> {
+        if self.store.assets.contains(asset.id) {
+            return Err(StorageError::InvalidData(format!(
+                "Asset already exists: {}",
+                asset.id
+            )));
+        }
+        self.put_asset(asset)
+    }
+
+    /// 校验并写入资产（不保存），同步复式账簿
+    fn put_asset(&mut self, asset: &Asset) -> Result<(), StorageError> {
+        let asset = &self.prepare_asset(asset)?;
+        asset
+            .display
+            .validate()
+            .map_err(StorageError::InvalidData)?;
+        self.ensure_metadata_valid(asset)?;
+        self.ensure_asset_external_id_free(asset)?;
+        self.store_mut().assets.insert(asset.clone());
+        if let Some(ledger) = self.store_mut().ledger.as_mut() {
+            ledger.sync_asset(asset);
+        }
+        Ok(())
+    }
+
     /// 获取资产
     pub fn get_asset(&self, id: Uuid) -> Result<Option<Asset>, StorageError> {
-        let asset = self.store.assets.iter().find(|a| a.id == id).cloned();
+        let mut asset = self.store.assets.get(id).cloned();
+        if let Some(asset) = asset.as_mut() {
+            self.compute_derived(std::slice::from_mut(asset))?;
+        }
         Ok(asset)
     }
 
-    /// 获取所有资产
-    pub fn list_assets(&self) -> Result<Vec<Asset>, StorageError> {
-        let mut assets = self.store.assets.clone();
-        assets.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+    /// 获取所有资产，按指定字段排序（默认创建时间倒序）
+    pub fn list_assets(
+        &self,
+        sort_by: SortBy,
+        order: SortOrder,
+    ) -> Result<Vec<Asset>, StorageError> {
+        let mut assets: Vec<Asset> = self.store.assets.newest_first(..).cloned().collect();
+        self.compute_derived(&mut assets)?;
+        sort_assets(&mut assets, sort_by, order);
         Ok(assets)
     }
 
+    /// 逐个读取资产（创建时间倒序），不一次复制全部资产，供导出与报告处理大量资产
+    ///
+    /// 派生字段在取出每个资产时计算；迭代期间持有数据库的借用。
+    pub fn iter_assets(&self) -> Result<impl Iterator<Item = Asset> + '_, StorageError> {
+        let fx = self.get_fx_settings()?;
+        let today = Utc::now().date_naive();
+        Ok(self.store.assets.newest_first(..).map(move |asset| {
+            let mut asset = asset.clone();
+            self.derive(&fx, &mut asset, today);
+            asset
+        }))
+    }
+
     /// 按类型获取资产
     pub fn list_assets_by_type(&self, asset_type: &AssetType) -> Result<Vec<Asset>, StorageError> {
+        self.query_assets(&AssetFilter::default().with_type(asset_type.clone()))
+    }
+
+    /// 按类型、货币、标签、价值与时间范围筛选资产（价值为计算公式后的值）
+    pub fn query_assets(&self, filter: &AssetFilter) -> Result<Vec<Asset>, StorageError> {
+        let mut assets: Vec<Asset> = self.store.assets.newest_first(..).cloned().collect();
+        self.compute_derived(&mut assets)?;
+        assets.retain(|a| filter.matches(a));
+        Ok(assets)
+    }
+
+    /// 按元数据字段筛选资产（如 `$.broker` 等于 "IBKR"）
+    pub fn query_assets_by_metadata(
+        &self,
+        json_path: &str,
+        value: &serde_json::Value,
+    ) -> Result<Vec<Asset>, StorageError> {
+        let path = MetadataPath::parse(json_path).map_err(StorageError::InvalidData)?;
         let mut assets: Vec<Asset> = self
             .store
             .assets
-            .iter()
-            .filter(|a| a.asset_type.as_str() == asset_type.as_str())
+            .newest_first(..)
+            .filter(|a| path.matches(&a.metadata, value))
             .cloned()
             .collect();
-        assets.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+        self.compute_derived(&mut assets)?;
         Ok(assets)
     }
 
     /// 更新资产
     pub fn update_asset(&mut self, asset: &Asset) -> Result<(), StorageError> {
-        let pos = self
-            .store
-            .assets
-            .iter()
-            .position(|a| a.id == asset.id)
-            .ok_or_else(|| StorageError::NotFound(asset.id.to_string()))?;
-
-        self.store.assets[pos] = asset.clone();
+        if !self.store.assets.contains(asset.id) {
+            return Err(StorageError::NotFound(asset.id.to_string()));
+        }
+        self.put_asset(asset)?;
+        self.refresh_daily_balances([asset.id]);
         self.save()
     }
 
     /// 删除资产
     pub fn delete_asset(&mut self, id: Uuid) -> Result<(), StorageError> {
-        let pos = self
-            .store
-            .assets
-            .iter()
-            .position(|a| a.id == id)
-            .ok_or_else(|| StorageError::NotFound(id.to_string()))?;
+        let mut trashed = self.detach_asset(id)?;
+        trashed.asset.deleted_at = Some(Utc::now());
+        self.store_mut().trash.push(trashed);
+        self.save()
+    }
 
-        self.store.assets.remove(pos);
-        // 同时删除关联的交易记录
-        self.store.transactions.retain(|t| t.asset_id != id);
+    /// 永久删除资产及其关联记录（包括回收站中的资产）
+    pub fn delete_asset_permanently(&mut self, id: Uuid) -> Result<(), StorageError> {
+        if let Some(pos) = self.store.trash.iter().position(|t| t.asset.id == id) {
+            self.store_mut().trash.remove(pos);
+        } else {
+            self.detach_asset(id)?;
+        }
         self.save()
     }
 
-    /// 搜索资产
-    pub fn search_assets(&self, query: &str) -> Result<Vec<Asset>, StorageError> {
-        let query_lower = query.to_lowercase();
-        let mut assets: Vec<Asset> = self
-            .store
-            .assets
-            .iter()
-            .filter(|a| {
-                a.name.to_lowercase().contains(&query_lower)
-                    || a.description
-                        .as_ref()
-                        .map(|d| d.to_lowercase().contains(&query_lower))
-                        .unwrap_or(false)
-                    || a.tags
-                        .iter()
-                        .any(|t| t.to_lowercase().contains(&query_lower))
-            })
-            .cloned()
-            .collect();
-        assets.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+    /// 回收站中的资产，最近删除的在前
+    pub fn list_trashed(&self) -> Result<Vec<Asset>, StorageError> {
+        let mut assets: Vec<Asset> = self.store.trash.iter().map(|t| t.asset.clone()).collect();
+        assets.sort_by_key(|a| std::cmp::Reverse(a.deleted_at));
         Ok(assets)
     }
 
-    // ============ 统计功能 ============
-
-    /// 获取资产统计摘要
-    pub fn get_summary(&self) -> Result<AssetSummary, StorageError> {
-        let assets = self.list_assets()?;
-        let mut summary = AssetSummary::default();
-        summary.asset_count = assets.len();
-
-        for asset in &assets {
-            summary.total_value += asset.value;
+    /// 从回收站恢复资产及其关联记录；复式账簿中按恢复时的价值重新记入期初余额
+    pub fn restore_asset(&mut self, id: Uuid) -> Result<Asset, StorageError> {
+        let Some(pos) = self.store.trash.iter().position(|t| t.asset.id == id) else {
+            return Err(StorageError::NotFound(id.to_string()));
+        };
+        if self.store.assets.contains(id) {
+            return Err(StorageError::InvalidData(format!(
+                "Asset {} already exists",
+                id
+            )));
+        }
+        let mut trashed = self.store_mut().trash.remove(pos);
+        trashed.asset.deleted_at = None;
+        let asset = trashed.asset.clone();
 
-            // 按类型统计
-            let type_key = asset.asset_type.as_str().to_string();
-            *summary.by_type.entry(type_key).or_insert(0.0) += asset.value;
+        // 另一端已删除的关系、模板与定投计划不再恢复
+        let exists =
+            |other: Option<Uuid>| other.is_none_or(|o| o == id || self.store.assets.contains(o));
+        let relations: Vec<AssetRelation> = trashed
+            .relations
+            .into_iter()
+            .filter(|r| exists(Some(r.from_id)) && exists(Some(r.to_id)))
+            .collect();
+        let templates: Vec<TransactionTemplate> = trashed
+            .templates
+            .into_iter()
+            .filter(|t| exists(t.from_asset_id) && exists(t.to_asset_id))
+            .collect();
+        let sip_plans: Vec<SipPlan> = trashed
+            .sip_plans
+            .into_iter()
+            .filter(|p| exists(Some(p.asset_id)) && exists(p.pay_from))
+            .collect();
 
-            // 按货币统计
-            let currency_key = format!("{:?}", asset.currency);
-            *summary.by_currency.entry(currency_key).or_insert(0.0) += asset.value;
+        let store = self.store_mut();
+        store.assets.insert(trashed.asset);
+        for txn in trashed.transactions {
+            store.transactions.insert(txn);
+        }
+        store.relations.extend(relations);
+        store.templates.extend(templates);
+        store.sip_plans.extend(sip_plans);
+        store.reminders.extend(trashed.reminders);
+        store.envelopes.extend(trashed.envelopes);
+        store.import_profiles.extend(trashed.import_profiles);
+        store.watch_folders.extend(trashed.watch_folders);
+        store.imported_files.extend(trashed.imported_files);
+        store.attachments.extend(trashed.attachments);
+        for valuation in trashed.valuations {
+            store.valuations.insert(valuation);
+        }
+        if let Some(ledger) = store.ledger.as_mut() {
+            ledger.sync_asset(&asset);
         }
 
-        Ok(summary)
+        self.refresh_daily_balances([id]);
+        self.save()?;
+        Ok(asset)
     }
 
-    // ============ 交易记录 ============
-
-    /// 记录交易
-    pub fn add_transaction(&mut self, transaction: &AssetTransaction) -> Result<(), StorageError> {
-        self.store.transactions.push(transaction.clone());
-        self.save()
+    /// 永久删除 `older_than` 及之前移入回收站的资产，返回删除的数量
+    pub fn purge_trash(&mut self, older_than: DateTime<Utc>) -> Result<usize, StorageError> {
+        let before = self.store.trash.len();
+        self.store_mut()
+            .trash
+            .retain(|t| t.asset.deleted_at.is_none_or(|at| at > older_than));
+        let purged = before - self.store.trash.len();
+        if purged > 0 {
+            self.save()?;
+        }
+        Ok(purged)
     }
 
-    /// 获取资产的交易历史
-    pub fn get_transactions(&self, asset_id: Uuid) -> Result<Vec<AssetTransaction>, StorageError> {
-        let mut txns: Vec<AssetTransaction> = self
-            .store
-            .transactions
+    /// 从数据中移出资产及其关联的交易记录、提醒、关系、模板、定投计划和导入配置
+    fn detach_asset(&mut self, id: Uuid) -> Result<TrashedAsset, StorageError> {
+        let Some(asset) = self.store.assets.get(id).cloned() else {
+            return Err(StorageError::NotFound(id.to_string()));
+        };
+        for txn in self.store.transactions.iter().filter(|t| t.asset_id == id) {
+            self.ensure_period_open(txn.timestamp)?;
+        }
+
+        Arc::make_mut(&mut self.daily_balances).remove(id);
+        let store = self.store_mut();
+        store.assets.remove(id);
+        if let Some(ledger) = store.ledger.as_mut() {
+            ledger.remove_asset(id);
+        }
+        let profiles: Vec<Uuid> = store
+            .import_profiles
             .iter()
-            .filter(|t| t.asset_id == asset_id)
-            .cloned()
+            .filter(|p| p.asset_id == id)
+            .map(|p| p.id)
             .collect();
-        txns.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
-        Ok(txns)
+        Ok(TrashedAsset {
+            asset,
+            transactions: store.transactions.remove_where(|t| t.asset_id == id),
+            reminders: take_where(&mut store.reminders, |r| r.asset_id == Some(id)),
+            envelopes: take_where(&mut store.envelopes, |e| e.asset_id == id),
+            relations: take_where(&mut store.relations, |r| r.from_id == id || r.to_id == id),
+            templates: take_where(&mut store.templates, |t| {
+                t.from_asset_id == Some(id) || t.to_asset_id == Some(id)
+            }),
+            sip_plans: take_where(&mut store.sip_plans, |p| {
+                p.asset_id == id || p.pay_from == Some(id)
+            }),
+            import_profiles: take_where(&mut store.import_profiles, |p| p.asset_id == id),
+            watch_folders: take_where(&mut store.watch_folders, |f| {
+                profiles.contains(&f.profile_id)
+            }),
+            imported_files: take_where(&mut store.imported_files, |f| f.asset_id == id),
+            attachments: take_where(&mut store.attachments, |a| a.asset_id == id),
+            valuations: store.valuations.remove_where(|v| v.asset_id == id),
+        })
     }
 
-    // ============ 设置 ============
+    /// 设置之后的修改记入修改记录时的操作者
+    pub fn set_actor(&mut self, actor: impl Into<String>) {
+        self.actor = actor.into();
+    }
 
-    /// 保存设置
-    pub fn set_setting(&mut self, key: &str, value: &str) -> Result<(), StorageError> {
-        self.store
-            .settings
-            .insert(key.to_string(), value.to_string());
-        self.save()
+    /// 查询资产与交易的修改记录，按时间倒序（包括尚未保存的修改）
+    pub fn query_audit_log(&self, query: &AuditQuery) -> Result<Vec<AuditRecord>, StorageError> {
+        self.audit.query(query)
     }
 
-    /// 获取设置
-    pub fn get_setting(&self, key: &str) -> Result<Option<String>, StorageError> {
-        Ok(self.store.settings.get(key).cloned())
+    /// 获取修改记录设置
+    pub fn get_audit_settings(&self) -> Result<AuditSettings, StorageError> {
+        self.get_settings(AUDIT_SETTINGS)
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::asset::{Asset, AssetType};
+    /// 保存修改记录设置，并按新的保留天数清除旧记录
+    pub fn set_audit_settings(&mut self, settings: &AuditSettings) -> Result<(), StorageError> {
+        settings.validate().map_err(StorageError::InvalidData)?;
+        self.set_settings(AUDIT_SETTINGS, settings)?;
+        self.prune_audit_log(Utc::now())?;
+        Ok(())
+    }
 
-    #[test]
-    fn test_json_database_operations() {
-        let mut db = Database::open_in_memory().unwrap();
+    /// 清除超过保留天数的修改记录，返回清除的数量
+    pub fn prune_audit_log(&mut self, now: DateTime<Utc>) -> Result<usize, StorageError> {
+        self.check_writable()?;
+        match self.get_audit_settings()?.cutoff(now) {
+            Some(cutoff) => self.audit.prune(cutoff),
+            None => Ok(0),
+        }
+    }
 
-        // 创建资产
-        let asset = Asset::new("测试股票", AssetType::Stock, 10000.0);
-        db.create_asset(&asset).unwrap();
+    /// 资产的修改历史：按时间列出修改记录中该资产每次修改的字段（资产删除后仍可查看）
+    pub fn get_asset_history_diff(
+        &self,
+        asset_id: Uuid,
+    ) -> Result<Vec<AssetVersionDiff>, StorageError> {
+        let mut records = self
+            .audit
+            .query(&AuditQuery::for_entity(AuditEntity::Asset, asset_id))?;
+        records.reverse();
+        Ok(history_diff(&records))
+    }
 
-        // 获取资产
-        let loaded = db.get_asset(asset.id).unwrap().unwrap();
-        assert_eq!(loaded.name, "测试股票");
-        assert_eq!(loaded.value, 10000.0);
+    /// 新建或更新资产
+    ///
+    /// 设置了外部标识时按外部标识匹配，否则按名称与类型匹配。匹配到已有资产时沿用其ID、
+    /// 创建时间与导入批次，其余字段以传入的资产为准。
+    pub fn upsert_asset(&mut self, asset: &Asset) -> Result<AssetUpsert, StorageError> {
+        let asset = self.prepare_asset(asset)?;
+        let existing = match asset.external_id.as_deref() {
+            Some(external_id) => self
+                .store
+                .assets
+                .iter()
+                .find(|a| a.external_id.as_deref() == Some(external_id)),
+            None => self
+                .store
+                .assets
+                .iter()
+                .find(|a| a.name == asset.name && a.asset_type == asset.asset_type),
+        };
 
-        // 列出资产
-        let assets = db.list_assets().unwrap();
-        assert_eq!(assets.len(), 1);
+        let Some(existing) = existing else {
+            self.create_asset(&asset)?;
+            return Ok(AssetUpsert {
+                asset,
+                created: true,
+            });
+        };
+        let mut updated = asset;
+        updated.id = existing.id;
+        updated.created_at = existing.created_at;
+        updated.import_batch = existing.import_batch;
+        updated.updated_at = Utc::now();
+        self.update_asset(&updated)?;
+        Ok(AssetUpsert {
+            asset: updated,
+            created: false,
+        })
+    }
+
+    /// 按外部标识获取资产
+    pub fn get_asset_by_external_id(
+        &self,
+        external_id: &str,
+    ) -> Result<Option<Asset>, StorageError> {
+        match self
+            .store
+            .assets
+            .iter()
+            .find(|a| a.external_id.as_deref() == Some(external_id))
+        {
+            Some(asset) => self.get_asset(asset.id),
+            None => Ok(None),
+        }
+    }
+
+    /// 设置插件提供的元数据 Schema（覆盖同类型的内置定义，替换之前设置的插件 Schema）
+    ///
+    /// 无效的 Schema 会被跳过并记录警告。
+    pub fn set_plugin_metadata_schemas(&mut self, schemas: Vec<(String, serde_json::Value)>) {
+        self.metadata_schemas = MetadataSchemas::builtin();
+        for (type_code, schema) in schemas {
+            if let Err(e) = self.metadata_schemas.register(&type_code, schema) {
+                warn!("Skipping metadata schema: {}", e);
+            }
+        }
+    }
+
+    /// 所有元数据 Schema（按类型代码）
+    pub fn metadata_schemas(&self) -> &HashMap<String, serde_json::Value> {
+        self.metadata_schemas.all()
+    }
+
+    /// 按资产类型的 Schema 校验元数据
+    pub fn validate_metadata(
+        &self,
+        asset_type: &AssetType,
+        metadata: &serde_json::Value,
+    ) -> Vec<SchemaViolation> {
+        self.metadata_schemas.validate(asset_type, metadata)
+    }
+
+    fn ensure_metadata_valid(&self, asset: &Asset) -> Result<(), StorageError> {
+        let violations = self.validate_metadata(&asset.asset_type, &asset.metadata);
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(StorageError::InvalidMetadata(violations))
+        }
+    }
+
+    /// 写入前的资产：按标签设置规范化标签（大小写统一为其他资产已有的写法），
+    /// 去掉读取时计算的字段
+    fn prepare_asset(&self, asset: &Asset) -> Result<Asset, StorageError> {
+        let settings = self.get_tag_settings()?;
+        let known = self
+            .store
+            .assets
+            .iter()
+            .filter(|a| a.id != asset.id)
+            .flat_map(|a| a.tags.iter());
+        let mut asset = asset.clone();
+        asset.tags = settings.normalize(&asset.tags, known.map(String::as_str));
+        asset.conversion = None;
+        Ok(asset)
+    }
+
+    /// 规范化单个标签（空白标签为空）
+    fn normalize_tag(&self, tag: &str) -> Result<Option<String>, StorageError> {
+        let settings = self.get_tag_settings()?;
+        let known = self.store.assets.iter().flat_map(|a| a.tags.iter());
+        Ok(settings
+            .normalize(&[tag.to_string()], known.map(String::as_str))
+            .pop())
+    }
+
+    /// 外部标识须全局唯一
+    fn ensure_asset_external_id_free(&self, asset: &Asset) -> Result<(), StorageError> {
+        let Some(external_id) = asset.external_id.as_deref() else {
+            return Ok(());
+        };
+        if self
+            .store
+            .assets
+            .iter()
+            .any(|a| a.id != asset.id && a.external_id.as_deref() == Some(external_id))
+        {
+            return Err(StorageError::InvalidData(format!(
+                "External ID already in use: {}",
+                external_id
+            )));
+        }
+        Ok(())
+    }
+
+    /// 搜索资产
+    pub fn search_assets(&self, query: &str) -> Result<Vec<Asset>, StorageError> {
+        let settings = self.get_search_settings()?;
+        let mut assets: Vec<Asset> = self
+            .store
+            .assets
+            .newest_first(..)
+            .filter(|a| {
+                settings.matches(query, &a.name)
+                    || a.description
+                        .as_ref()
+                        .is_some_and(|d| settings.matches(query, d))
+                    || a.tags.iter().any(|t| settings.matches(query, t))
+            })
+            .cloned()
+            .collect();
+        self.compute_derived(&mut assets)?;
+        Ok(assets)
+    }
+
+    /// 按相关度搜索资产：名称、标签与描述的匹配程度加权求和（名称最高），相关度相同时新的在前
+    pub fn search_assets_ranked(&self, query: &str) -> Result<Vec<Asset>, StorageError> {
+        let settings = self.get_search_settings()?;
+        let mut ranked: Vec<(u32, Asset)> = self
+            .store
+            .assets
+            .newest_first(..)
+            .filter_map(|a| {
+                let name = settings.score(query, &a.name).map(|s| s * 3);
+                let tags = a.tags.iter().filter_map(|t| settings.score(query, t)).max();
+                let description = a
+                    .description
+                    .as_ref()
+                    .and_then(|d| settings.score(query, d));
+                let scores = [name, tags.map(|s| s * 2), description];
+                scores
+                    .iter()
+                    .any(Option::is_some)
+                    .then(|| (scores.iter().flatten().sum(), a.clone()))
+            })
+            .collect();
+        ranked.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+        let mut assets: Vec<Asset> = ranked.into_iter().map(|(_, a)| a).collect();
+        self.compute_derived(&mut assets)?;
+        Ok(assets)
+    }
+
+    /// 各资产的浮动盈亏与目标进度
+    pub fn get_asset_performance(&self) -> Result<Vec<AssetPerformance>, StorageError> {
+        Ok(self
+            .list_assets(SortBy::default(), SortOrder::default())?
+            .iter()
+            .map(AssetPerformance::new)
+            .collect())
+    }
+
+    /// 计算读取时的派生字段：虚拟资产按公式计算的价值、折算为显示货币的价值
+    fn compute_derived(&self, assets: &mut [Asset]) -> Result<(), StorageError> {
+        let fx = self.get_fx_settings()?;
+        let today = Utc::now().date_naive();
+        for asset in assets.iter_mut() {
+            self.derive(&fx, asset, today);
+        }
+        Ok(())
+    }
+
+    /// 计算单个资产的派生字段
+    fn derive(&self, fx: &FxSettings, asset: &mut Asset, today: NaiveDate) {
+        if let Some(formula) = &asset.formula {
+            let value = formula.evaluate(asset, &self.store.assets, fx);
+            asset.value = value;
+        }
+        asset.conversion = self.conversion(fx, asset, today);
+    }
+
+    /// 资产价值折算为显示货币（默认本位币），货币相同或缺少汇率时为空
+    fn conversion(
+        &self,
+        fx: &FxSettings,
+        asset: &Asset,
+        date: NaiveDate,
+    ) -> Option<CurrencyConversion> {
+        let currency = asset.display_currency.as_ref().unwrap_or(&fx.base_currency);
+        if *currency == asset.currency {
+            return None;
+        }
+        let (from, from_date) = self.rate_with_date(fx, &asset.currency, date)?;
+        let (to, to_date) = self.rate_with_date(fx, currency, date)?;
+        if to == 0.0 {
+            return None;
+        }
+        let rate = from / to;
+        Some(CurrencyConversion {
+            currency: currency.clone(),
+            converted_value: asset.value * rate,
+            rate,
+            // 两个汇率日期不同时取较早的，反映汇率的新旧程度
+            rate_date: from_date.into_iter().chain(to_date).min(),
+        })
+    }
+
+    // ============ 统计功能 ============
+
+    /// 获取资产统计摘要
+    pub fn get_summary(&self) -> Result<AssetSummary, StorageError> {
+        self.get_summary_with_pending(true)
+    }
+
+    /// 获取资产统计摘要，`include_pending` 为否时扣除待入账交易的金额
+    pub fn get_summary_with_pending(
+        &self,
+        include_pending: bool,
+    ) -> Result<AssetSummary, StorageError> {
+        self.get_summary_filtered(&AssetFilter::default(), include_pending)
+    }
+
+    /// 只统计符合筛选条件（如创建时间范围、标签）的资产
+    pub fn get_summary_filtered(
+        &self,
+        filter: &AssetFilter,
+        include_pending: bool,
+    ) -> Result<AssetSummary, StorageError> {
+        let mut summary = AssetSummary::default();
+        // 待入账交易的变动按资产合计（并行分块计算）
+        let pending = if include_pending {
+            HashMap::new()
+        } else {
+            sum_deltas_by_asset(&self.store.transactions, |t| {
+                !t.scheduled && t.status == TransactionStatus::Pending
+            })
+        };
+
+        // 虚拟资产默认不计入，避免与来源资产重复统计
+        let assets = self
+            .iter_assets()?
+            .filter(|a| a.formula.as_ref().is_none_or(|f| f.include_in_summary))
+            .filter(|a| filter.matches(a));
+        for asset in assets {
+            let value = asset.value - pending.get(&asset.id).copied().unwrap_or(0.0);
+            summary.asset_count += 1;
+            summary.total_value += value;
+
+            // 按类型统计
+            let type_key = asset.asset_type.as_str().to_string();
+            *summary.by_type.entry(type_key).or_insert(0.0) += value;
+
+            // 按货币统计
+            let currency_key = format!("{:?}", asset.currency);
+            *summary.by_currency.entry(currency_key).or_insert(0.0) += value;
+
+            if self.store.envelopes.iter().any(|e| e.asset_id == asset.id) {
+                summary
+                    .envelopes
+                    .push(EnvelopeSummary::new(&asset, &self.store.envelopes));
+            }
+        }
+
+        Ok(summary)
+    }
+
+    /// 获取资产在时间范围内每天的日终价值（读取物化的每日余额，不回放交易）
+    pub fn get_daily_balances(
+        &self,
+        asset_id: Uuid,
+        range: &DateRange,
+    ) -> Result<Vec<DailyBalance>, StorageError> {
+        if !self.store.assets.contains(asset_id) {
+            return Err(StorageError::NotFound(asset_id.to_string()));
+        }
+        Ok(self.daily_balances.series(asset_id, range))
+    }
+
+    /// 重算资产的日终价值（资产已删除时移除），价值变化时记下估值
+    fn refresh_daily_balances(&mut self, asset_ids: impl IntoIterator<Item = Uuid>) {
+        let now = Utc::now();
+        for id in asset_ids {
+            let Some(asset) = self.store.assets.get(id) else {
+                Arc::make_mut(&mut self.daily_balances).remove(id);
+                continue;
+            };
+            Arc::make_mut(&mut self.daily_balances).refresh(asset, &self.store.transactions);
+            let value = asset.value;
+            let last = self
+                .store
+                .valuations
+                .newest_first(..)
+                .find(|v| v.asset_id == id)
+                .map(|v| v.value);
+            if last != Some(value) {
+                self.store_mut()
+                    .valuations
+                    .insert(Valuation::new(id, value, now));
+            }
+        }
+    }
+
+    /// 资产在时间范围内的估值历史，按时间升序
+    pub fn get_valuation_history(
+        &self,
+        asset_id: Uuid,
+        range: &DateRange,
+    ) -> Result<Vec<Valuation>, StorageError> {
+        if !self.store.assets.contains(asset_id) {
+            return Err(StorageError::NotFound(asset_id.to_string()));
+        }
+        let mut history: Vec<Valuation> = self
+            .store
+            .valuations
+            .newest_first(range.start..=range.end)
+            .filter(|v| v.asset_id == asset_id)
+            .cloned()
+            .collect();
+        history.reverse();
+        Ok(history)
+    }
+
+    /// 获取资产价值的等间隔序列，缺失点按指定方式填充
+    pub fn get_interpolated_series(
+        &self,
+        asset_id: Uuid,
+        range: &DateRange,
+        interval: Interval,
+        method: InterpolationMethod,
+    ) -> Result<Vec<SeriesPoint>, StorageError> {
+        let asset = self
+            .get_asset(asset_id)?
+            .ok_or_else(|| StorageError::NotFound(asset_id.to_string()))?;
+        let history = value_history(&asset, &self.store.transactions);
+        Ok(interpolate_series(&history, range, interval, method))
+    }
+
+    /// 获取降采样到目标点数的图表序列
+    pub fn get_chart_series(
+        &self,
+        source: &ChartSource,
+        range: &DateRange,
+        target_points: usize,
+        method: DownsampleMethod,
+    ) -> Result<ChartSeries, StorageError> {
+        let points: Vec<ValuePoint> = match source {
+            ChartSource::DailyBalances { asset_id } => self
+                .get_daily_balances(*asset_id, range)?
+                .into_iter()
+                .map(|b| ValuePoint {
+                    timestamp: b.date.and_time(chrono::NaiveTime::MIN).and_utc(),
+                    value: b.value,
+                })
+                .collect(),
+            ChartSource::Valuations { asset_id } => self
+                .get_valuation_history(*asset_id, range)?
+                .into_iter()
+                .map(|v| ValuePoint {
+                    timestamp: v.recorded_at,
+                    value: v.value,
+                })
+                .collect(),
+            ChartSource::ValueHistory {
+                asset_id,
+                interval,
+                method,
+            } => series_values(self.get_interpolated_series(*asset_id, range, *interval, *method)?),
+            ChartSource::NetWorth { interval } => {
+                series_values(self.get_net_worth_series(range, *interval)?)
+            }
+        };
+        Ok(ChartSeries::new(&points, target_points, method))
+    }
+
+    /// 获取各货币的外汇敞口
+    pub fn get_fx_exposure(&self) -> Result<FxExposure, StorageError> {
+        let settings = self.get_fx_settings()?;
+        let assets: Vec<Asset> = self
+            .store
+            .assets
+            .iter()
+            .filter(|a| !a.is_virtual())
+            .cloned()
+            .collect();
+        Ok(fx_exposure(&assets, &settings))
+    }
+
+    /// 将期间内的资产增长拆分为资金投入/取出与收益
+    pub fn get_attribution(
+        &self,
+        range: &DateRange,
+        group_by: GroupBy,
+    ) -> Result<Attribution, StorageError> {
+        let assets: Vec<Asset> = self
+            .store
+            .assets
+            .iter()
+            .filter(|a| !a.is_virtual())
+            .cloned()
+            .collect();
+        Ok(attribution(
+            &assets,
+            &self.store.transactions,
+            range,
+            group_by,
+        ))
+    }
+
+    // ============ 交易记录 ============
+
+    /// 记录交易
+    pub fn add_transaction(&mut self, transaction: &AssetTransaction) -> Result<(), StorageError> {
+        self.insert_transaction(transaction)?;
+        self.refresh_daily_balances([transaction.asset_id]);
+        self.save()
+    }
+
+    /// 批量记录交易，校验失败或ID已存在的跳过，其余一次性保存
+    ///
+    /// 与 `add_transactions` 不同，一项出错不影响其他项。
+    pub fn add_transactions_bulk(
+        &mut self,
+        transactions: &[AssetTransaction],
+    ) -> Result<BulkResult, StorageError> {
+        let mut result = BulkResult::default();
+        for (index, transaction) in transactions.iter().enumerate() {
+            let inserted = self.insert_transaction(transaction);
+            result.push(index, transaction.id, inserted);
+        }
+        if result.inserted > 0 {
+            let asset_ids: HashSet<Uuid> = transactions.iter().map(|t| t.asset_id).collect();
+            self.refresh_daily_balances(asset_ids);
+            self.save()?;
+        }
+        Ok(result)
+    }
+
+    /// 校验并写入新交易（不保存），ID已存在时报错
+    fn insert_transaction(&mut self, transaction: &AssetTransaction) -> Result<(), StorageError> {
+        self.ensure_transaction_new(transaction.id)?;
+        self.ensure_period_open(transaction.timestamp)?;
+        self.ensure_transaction_external_ids_free(std::slice::from_ref(transaction))?;
+        self.store_mut().transactions.insert(transaction.clone());
+        self.mirror_transaction(transaction);
+        Ok(())
+    }
+
+    /// 交易ID尚未使用
+    fn ensure_transaction_new(&self, id: Uuid) -> Result<(), StorageError> {
+        if self.store.transactions.contains(id) {
+            return Err(StorageError::InvalidData(format!(
+                "Transaction already exists: {}",
+                id
+            )));
+        }
+        Ok(())
+    }
+
+    /// 批量记录交易，全部校验通过后一次性写入
+    pub fn add_transactions(
+        &mut self,
+        transactions: &[AssetTransaction],
+    ) -> Result<(), StorageError> {
+        let mut ids = HashSet::new();
+        for transaction in transactions {
+            self.ensure_transaction_new(transaction.id)?;
+            if !ids.insert(transaction.id) {
+                return Err(StorageError::InvalidData(format!(
+                    "Transaction already exists: {}",
+                    transaction.id
+                )));
+            }
+            self.ensure_period_open(transaction.timestamp)?;
+        }
+        self.ensure_transaction_external_ids_free(transactions)?;
+        for transaction in transactions {
+            self.store_mut().transactions.insert(transaction.clone());
+            self.mirror_transaction(transaction);
+        }
+        let asset_ids: HashSet<Uuid> = transactions.iter().map(|t| t.asset_id).collect();
+        self.refresh_daily_balances(asset_ids);
+        self.save()
+    }
+
+    /// 修改交易记录（原时间和新时间都不能位于已结账期间）
+    pub fn update_transaction(
+        &mut self,
+        transaction: &AssetTransaction,
+    ) -> Result<(), StorageError> {
+        let existing = self
+            .store
+            .transactions
+            .get(transaction.id)
+            .ok_or_else(|| StorageError::NotFound(transaction.id.to_string()))?;
+        self.ensure_period_open(existing.timestamp)?;
+        self.ensure_period_open(transaction.timestamp)?;
+        self.ensure_transaction_external_ids_free(std::slice::from_ref(transaction))?;
+
+        let previous = self
+            .store_mut()
+            .transactions
+            .insert(transaction.clone())
+            .expect("transaction exists");
+        self.unmirror_transaction(transaction.id);
+        self.mirror_transaction(transaction);
+        self.refresh_daily_balances([previous.asset_id, transaction.asset_id]);
+        self.save()
+    }
+
+    /// 按外部标识获取资产下的交易
+    pub fn get_transaction_by_external_id(
+        &self,
+        asset_id: Uuid,
+        external_id: &str,
+    ) -> Result<Option<AssetTransaction>, StorageError> {
+        Ok(self
+            .store
+            .transactions
+            .iter()
+            .find(|t| t.asset_id == asset_id && t.external_id.as_deref() == Some(external_id))
+            .cloned())
+    }
+
+    /// 交易的外部标识须在所属资产内唯一（包括同一批交易之间）
+    fn ensure_transaction_external_ids_free(
+        &self,
+        transactions: &[AssetTransaction],
+    ) -> Result<(), StorageError> {
+        let mut seen: HashSet<(Uuid, &str)> = HashSet::new();
+        for transaction in transactions {
+            let Some(external_id) = transaction.external_id.as_deref() else {
+                continue;
+            };
+            let taken = self.store.transactions.iter().any(|t| {
+                t.id != transaction.id
+                    && t.asset_id == transaction.asset_id
+                    && t.external_id.as_deref() == Some(external_id)
+            });
+            if taken || !seen.insert((transaction.asset_id, external_id)) {
+                return Err(StorageError::InvalidData(format!(
+                    "External ID already in use: {}",
+                    external_id
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// 删除交易记录
+    pub fn delete_transaction(&mut self, id: Uuid) -> Result<(), StorageError> {
+        let existing = self
+            .store
+            .transactions
+            .get(id)
+            .ok_or_else(|| StorageError::NotFound(id.to_string()))?;
+        self.ensure_period_open(existing.timestamp)?;
+
+        let removed = self
+            .store_mut()
+            .transactions
+            .remove(id)
+            .expect("transaction exists");
+        self.unmirror_transaction(id);
+        self.refresh_daily_balances([removed.asset_id]);
+        self.save()
+    }
+
+    /// 为交易添加更正分录
+    ///
+    /// `corrected_delta` 为原交易本应记录的变动金额，差额以当前时间记为一笔新交易并调整资产价值，
+    /// 原交易保持不变。
+    pub fn add_correction(
+        &mut self,
+        transaction_id: Uuid,
+        corrected_delta: f64,
+        note: Option<String>,
+    ) -> Result<AssetTransaction, StorageError> {
+        let original = self
+            .store
+            .transactions
+            .get(transaction_id)
+            .cloned()
+            .ok_or_else(|| StorageError::NotFound(transaction_id.to_string()))?;
+
+        let note = note
+            .unwrap_or_else(|| format!("更正 {} 的交易", original.timestamp.format("%Y-%m-%d")));
+        let mut correction = self.apply_delta(
+            original.asset_id,
+            corrected_delta - original.delta(),
+            original.transaction_type.clone(),
+            Some(note),
+        )?;
+        correction.corrects = Some(original.id);
+        self.store_mut()
+            .transactions
+            .update(correction.id, |t| t.corrects = Some(original.id));
+        self.save()?;
+        Ok(correction)
+    }
+
+    /// 获取资产的交易历史
+    pub fn get_transactions(
+        &self,
+        asset_id: Uuid,
+        query: &TransactionQuery,
+    ) -> Result<Vec<AssetTransaction>, StorageError> {
+        let since = query.since.map_or(Bound::Unbounded, Bound::Included);
+        let until = query.until.map_or(Bound::Unbounded, Bound::Included);
+        Ok(self
+            .store
+            .transactions
+            .newest_first((since, until))
+            .filter(|t| t.asset_id == asset_id && query.matches(t))
+            .skip(query.offset)
+            .take(query.limit.unwrap_or(usize::MAX))
+            .cloned()
+            .collect())
+    }
+
+    /// 分页获取资产的交易（按时间倒序），`cursor` 为上一页返回的游标
+    pub fn get_transactions_page(
+        &self,
+        asset_id: Uuid,
+        cursor: Option<&str>,
+        limit: usize,
+    ) -> Result<TransactionPage, StorageError> {
+        let cursor = cursor.map(str::parse::<TransactionCursor>).transpose()?;
+        let mut txns: Vec<&AssetTransaction> = self
+            .store
+            .transactions
+            .iter()
+            .filter(|t| t.asset_id == asset_id && cursor.is_none_or(|c| c.precedes(t)))
+            .collect();
+        txns.sort_by_key(|txn| Reverse((txn.timestamp, txn.id)));
+        let rows = txns.into_iter().take(limit + 1).cloned().collect();
+        Ok(TransactionPage::from_rows(rows, limit))
+    }
+
+    /// 所有资产最近的交易（按时间倒序），供动态列表使用；不含尚未发生的计划交易
+    pub fn list_recent_transactions(
+        &self,
+        limit: usize,
+    ) -> Result<Vec<AssetTransaction>, StorageError> {
+        Ok(self
+            .store
+            .transactions
+            .newest_first(..)
+            .filter(|t| !t.scheduled)
+            .take(limit)
+            .cloned()
+            .collect())
+    }
+
+    /// 按清算状态筛选交易（按时间倒序），可限定资产
+    pub fn get_transactions_by_status(
+        &self,
+        status: TransactionStatus,
+        asset_id: Option<Uuid>,
+    ) -> Result<Vec<AssetTransaction>, StorageError> {
+        Ok(self
+            .store
+            .transactions
+            .newest_first(..)
+            .filter(|t| t.status == status && asset_id.is_none_or(|id| t.asset_id == id))
+            .cloned()
+            .collect())
+    }
+
+    /// 更改交易的清算状态
+    pub fn set_transaction_status(
+        &mut self,
+        id: Uuid,
+        status: TransactionStatus,
+    ) -> Result<AssetTransaction, StorageError> {
+        let transaction = self
+            .store
+            .transactions
+            .get(id)
+            .ok_or_else(|| StorageError::NotFound(id.to_string()))?;
+        if !transaction.status.can_transition_to(status) {
+            return Err(StorageError::InvalidData(format!(
+                "Cannot change status from {:?} to {:?}",
+                transaction.status, status
+            )));
+        }
+        let transaction = self
+            .store_mut()
+            .transactions
+            .update(id, |t| {
+                t.status = status;
+                t.clone()
+            })
+            .expect("transaction exists");
+        self.save()?;
+        Ok(transaction)
+    }
+
+    /// 按变动金额调整资产价值，并记录对应的交易
+    pub fn adjust_asset_value(
+        &mut self,
+        asset_id: Uuid,
+        delta: f64,
+        transaction_type: TransactionType,
+        note: Option<String>,
+    ) -> Result<AssetTransaction, StorageError> {
+        let transaction = self.apply_delta(asset_id, delta, transaction_type, note)?;
+        self.save()?;
+        Ok(transaction)
+    }
+
+    /// 记录币种与资产不同的交易（如用人民币卡支付美元）
+    ///
+    /// `original_amount` 为带符号的原币金额，按 `fx_rate` 折算为资产币种后计入资产价值。
+    pub fn add_foreign_transaction(
+        &mut self,
+        asset_id: Uuid,
+        original_amount: f64,
+        original_currency: Currency,
+        fx_rate: f64,
+        transaction_type: TransactionType,
+        note: Option<String>,
+    ) -> Result<AssetTransaction, StorageError> {
+        if !(fx_rate.is_finite() && fx_rate > 0.0) {
+            return Err(StorageError::InvalidData(format!(
+                "Invalid exchange rate: {}",
+                fx_rate
+            )));
+        }
+        let transaction =
+            self.apply_delta(asset_id, original_amount * fx_rate, transaction_type, note)?;
+        let transaction =
+            transaction.with_original_amount(original_amount, original_currency, fx_rate);
+        self.store_mut().transactions.insert(transaction.clone());
+        self.save()?;
+        Ok(transaction)
+    }
+
+    /// 记录未来日期的计划交易（如即将到期的账单），到期前不计入资产价值
+    pub fn add_scheduled_transaction(
+        &mut self,
+        asset_id: Uuid,
+        delta: f64,
+        transaction_type: TransactionType,
+        date: DateTime<Utc>,
+        note: Option<String>,
+    ) -> Result<AssetTransaction, StorageError> {
+        if date <= Utc::now() {
+            return Err(StorageError::InvalidData(
+                "Scheduled date must be in the future".to_string(),
+            ));
+        }
+        let asset = self
+            .get_asset(asset_id)?
+            .ok_or_else(|| StorageError::NotFound(asset_id.to_string()))?;
+
+        let mut transaction =
+            AssetTransaction::new(asset_id, transaction_type, asset.value, asset.value + delta);
+        transaction.timestamp = date;
+        transaction.note = note;
+        transaction.scheduled = true;
+        self.store_mut().transactions.insert(transaction.clone());
+        self.save()?;
+        Ok(transaction)
+    }
+
+    /// 将已到期的计划交易计入资产价值，返回本次入账的交易
+    pub fn post_due_transactions(
+        &mut self,
+        now: DateTime<Utc>,
+    ) -> Result<Vec<AssetTransaction>, StorageError> {
+        let mut due: Vec<&AssetTransaction> = self
+            .store
+            .transactions
+            .iter()
+            .filter(|t| t.scheduled && t.timestamp <= now)
+            .collect();
+        due.sort_by_key(|t| t.timestamp);
+        let due: Vec<(Uuid, Uuid, f64)> =
+            due.iter().map(|t| (t.id, t.asset_id, t.delta())).collect();
+
+        let mut posted = Vec::with_capacity(due.len());
+        for (id, asset_id, delta) in due {
+            let Some(before) = self.store_mut().assets.update(asset_id, |asset| {
+                let before = asset.value;
+                asset.update_value(before + delta);
+                before
+            }) else {
+                continue;
+            };
+            let transaction = self.store_mut().transactions.update(id, |t| {
+                t.amount_before = before;
+                t.amount_after = before + delta;
+                t.scheduled = false;
+                t.clone()
+            });
+            posted.extend(transaction);
+        }
+
+        if !posted.is_empty() {
+            for transaction in &posted {
+                self.mirror_transaction(transaction);
+            }
+            let asset_ids: HashSet<Uuid> = posted.iter().map(|t| t.asset_id).collect();
+            self.refresh_daily_balances(asset_ids);
+            self.save()?;
+        }
+        Ok(posted)
+    }
+
+    /// 获取尚未到期的计划交易（按日期升序），可限定截止时间
+    pub fn get_upcoming_transactions(
+        &self,
+        until: Option<DateTime<Utc>>,
+    ) -> Result<Vec<AssetTransaction>, StorageError> {
+        let mut txns: Vec<AssetTransaction> = self
+            .store
+            .transactions
+            .iter()
+            .filter(|t| t.scheduled && until.is_none_or(|until| t.timestamp <= until))
+            .cloned()
+            .collect();
+        txns.sort_by_key(|t| t.timestamp);
+        Ok(txns)
+    }
+
+    /// 各资产截至某时间的预计余额（当前价值加上计划交易与按利率假设计算的利息）
+    pub fn get_projected_balances(
+        &self,
+        until: DateTime<Utc>,
+    ) -> Result<Vec<ProjectedBalance>, StorageError> {
+        Ok(projected_balances(
+            &self.store.assets,
+            &self.store.transactions,
+            Utc::now(),
+            until,
+            &self.get_assumptions()?,
+        ))
+    }
+
+    /// 将对账单流水按日期顺序记为交易，并更新资产价值
+    ///
+    /// 流水指纹或流水号（外部标识）已存在于该资产交易中的流水视为重复，跳过不导入。
+    /// 导入的交易带有批次ID，有新交易时同时记录该批次。
+    pub fn import_statement(
+        &mut self,
+        asset_id: Uuid,
+        lines: &[StatementLine],
+        batch: &ImportBatch,
+    ) -> Result<StatementImport, StorageError> {
+        let existing: HashSet<&str> = self
+            .store
+            .transactions
+            .iter()
+            .filter(|t| t.asset_id == asset_id)
+            .filter_map(|t| t.import_hash.as_deref())
+            .collect();
+        let existing_refs: HashSet<&str> = self
+            .store
+            .transactions
+            .iter()
+            .filter(|t| t.asset_id == asset_id)
+            .filter_map(|t| t.external_id.as_deref())
+            .collect();
+        let mut duplicates = 0;
+        let mut lines: Vec<(&StatementLine, String)> = lines
+            .iter()
+            .zip(line_hashes(asset_id, lines))
+            .filter(|(line, hash)| {
+                let duplicate = existing.contains(hash.as_str())
+                    || line
+                        .reference
+                        .as_deref()
+                        .is_some_and(|r| existing_refs.contains(r.trim()));
+                duplicates += usize::from(duplicate);
+                !duplicate
+            })
+            .collect();
+        lines.sort_by_key(|(l, _)| l.date);
+        for (line, _) in &lines {
+            self.ensure_period_open(line.date)?;
+        }
+
+        let asset = self
+            .store
+            .assets
+            .get(asset_id)
+            .ok_or_else(|| StorageError::NotFound(asset_id.to_string()))?;
+        if asset.is_virtual() {
+            return Err(StorageError::InvalidData(format!(
+                "{} is a computed asset",
+                asset.name
+            )));
+        }
+
+        let mut value = asset.value;
+        let mut transactions = Vec::with_capacity(lines.len());
+        let mut references: HashSet<&str> = HashSet::new();
+        for (line, hash) in lines {
+            let transaction_type = if line.amount >= 0.0 {
+                TransactionType::Income
+            } else {
+                TransactionType::Expense
+            };
+            let mut transaction =
+                AssetTransaction::new(asset_id, transaction_type, value, value + line.amount)
+                    .with_timestamp(line.date);
+            transaction.note = line.description.clone();
+            transaction.import_hash = Some(hash);
+            if let Some(reference) = line.reference.as_deref().map(str::trim) {
+                if !reference.is_empty() && references.insert(reference) {
+                    transaction.external_id = Some(reference.to_string());
+                }
+            }
+            transaction.import_batch = Some(batch.id);
+            value += line.amount;
+            transactions.push(transaction);
+        }
+        if transactions.is_empty() {
+            return Ok(StatementImport {
+                transactions,
+                duplicates,
+            });
+        }
+        self.store_mut()
+            .assets
+            .update(asset_id, |asset| asset.update_value(value));
+
+        for transaction in &transactions {
+            self.store_mut().transactions.insert(transaction.clone());
+            self.mirror_transaction(transaction);
+        }
+        self.store_mut().import_batches.push(batch.clone());
+        self.refresh_daily_balances([asset_id]);
+        self.save()?;
+        Ok(StatementImport {
+            transactions,
+            duplicates,
+        })
+    }
+
+    /// 对账单文件是否已导入到该资产
+    pub fn is_file_imported(&self, asset_id: Uuid, hash: &str) -> bool {
+        self.store
+            .imported_files
+            .iter()
+            .any(|f| f.asset_id == asset_id && f.hash == hash)
+    }
+
+    /// 记录已导入的对账单文件
+    pub fn record_imported_file(&mut self, file: ImportedFile) -> Result<(), StorageError> {
+        self.store_mut().imported_files.push(file);
+        self.save()
+    }
+
+    /// 记录导入批次
+    pub fn add_import_batch(&mut self, batch: &ImportBatch) -> Result<(), StorageError> {
+        self.store_mut().import_batches.push(batch.clone());
+        self.save()
+    }
+
+    /// 获取所有导入批次及统计（最新的在前）
+    pub fn list_import_batches(&self) -> Result<Vec<ImportBatchStats>, StorageError> {
+        let mut batches: Vec<ImportBatchStats> = self
+            .store
+            .import_batches
+            .iter()
+            .map(|batch| {
+                let transactions: Vec<&AssetTransaction> = self
+                    .store
+                    .transactions
+                    .iter()
+                    .filter(|t| t.import_batch == Some(batch.id))
+                    .collect();
+                ImportBatchStats {
+                    batch: batch.clone(),
+                    assets: self
+                        .store
+                        .assets
+                        .iter()
+                        .filter(|a| a.import_batch == Some(batch.id))
+                        .count(),
+                    transactions: transactions.len(),
+                    total_amount: transactions.iter().map(|t| t.delta()).sum(),
+                    first_date: transactions.iter().map(|t| t.timestamp).min(),
+                    last_date: transactions.iter().map(|t| t.timestamp).max(),
+                }
+            })
+            .collect();
+        batches.sort_by_key(|summary| Reverse(summary.batch.created_at));
+        Ok(batches)
+    }
+
+    /// 撤销一次导入：删除该批次新建的资产和导入的交易，并回退其余资产的价值
+    pub fn undo_import(&mut self, batch_id: Uuid) -> Result<UndoImport, StorageError> {
+        let pos = self
+            .store
+            .import_batches
+            .iter()
+            .position(|b| b.id == batch_id)
+            .ok_or_else(|| StorageError::NotFound(batch_id.to_string()))?;
+        let asset_ids: Vec<Uuid> = self
+            .store
+            .assets
+            .iter()
+            .filter(|a| a.import_batch == Some(batch_id))
+            .map(|a| a.id)
+            .collect();
+        for txn in self
+            .store
+            .transactions
+            .iter()
+            .filter(|t| t.import_batch == Some(batch_id) || asset_ids.contains(&t.asset_id))
+        {
+            self.ensure_period_open(txn.timestamp)?;
+        }
+
+        let mut result = UndoImport {
+            assets: asset_ids.len(),
+            ..Default::default()
+        };
+        let removed = self
+            .store_mut()
+            .transactions
+            .remove_where(|t| t.import_batch == Some(batch_id));
+        let mut deltas: HashMap<Uuid, f64> = HashMap::new();
+        for txn in &removed {
+            self.unmirror_transaction(txn.id);
+            *deltas.entry(txn.asset_id).or_insert(0.0) += txn.delta();
+        }
+        result.transactions = removed.len();
+
+        for (&asset_id, delta) in &deltas {
+            self.store_mut()
+                .assets
+                .update(asset_id, |asset| asset.update_value(asset.value - delta));
+        }
+        self.refresh_daily_balances(deltas.into_keys());
+        for id in asset_ids {
+            self.delete_asset_permanently(id)?;
+        }
+        self.store_mut()
+            .imported_files
+            .retain(|f| f.batch_id != Some(batch_id));
+        self.store_mut().import_batches.remove(pos);
+        self.save()?;
+        Ok(result)
+    }
+
+    /// 调整资产价值并记录交易（不写入文件）
+    fn apply_delta(
+        &mut self,
+        asset_id: Uuid,
+        delta: f64,
+        transaction_type: TransactionType,
+        note: Option<String>,
+    ) -> Result<AssetTransaction, StorageError> {
+        self.apply_delta_at(asset_id, delta, transaction_type, note, Utc::now())
+    }
+
+    /// 调整资产价值并按指定时间记录交易（不写入文件）
+    fn apply_delta_at(
+        &mut self,
+        asset_id: Uuid,
+        delta: f64,
+        transaction_type: TransactionType,
+        note: Option<String>,
+        timestamp: DateTime<Utc>,
+    ) -> Result<AssetTransaction, StorageError> {
+        self.ensure_period_open(timestamp)?;
+        let asset = self
+            .store
+            .assets
+            .get(asset_id)
+            .ok_or_else(|| StorageError::NotFound(asset_id.to_string()))?;
+        if asset.is_virtual() {
+            return Err(StorageError::InvalidData(format!(
+                "{} is a computed asset",
+                asset.name
+            )));
+        }
+
+        let (before, after) = self
+            .store_mut()
+            .assets
+            .update(asset_id, |asset| {
+                let before = asset.value;
+                asset.update_value(before + delta);
+                (before, asset.value)
+            })
+            .expect("asset exists");
+
+        let mut transaction = AssetTransaction::new(asset_id, transaction_type, before, after)
+            .with_timestamp(timestamp);
+        transaction.note = note;
+        self.store_mut().transactions.insert(transaction.clone());
+        self.mirror_transaction(&transaction);
+        self.refresh_daily_balances([asset_id]);
+        Ok(transaction)
+    }
+
+    /// 启用复式记账时，将交易镜像为分录
+    fn mirror_transaction(&mut self, transaction: &AssetTransaction) {
+        if transaction.scheduled {
+            return;
+        }
+        let store = self.store_mut();
+        let Some(ledger) = store.ledger.as_mut() else {
+            return;
+        };
+        if let Some(asset) = store
+            .assets
+            .iter()
+            .find(|a| a.id == transaction.asset_id)
+        {
+            ledger.record_transaction(transaction, asset);
+        }
+    }
+
+    /// 删除交易镜像的分录
+    fn unmirror_transaction(&mut self, transaction_id: Uuid) {
+        if let Some(ledger) = self.store_mut().ledger.as_mut() {
+            ledger
+                .entries
+                .retain(|e| e.source_transaction_id != Some(transaction_id));
+        }
+    }
+
+    // ============ 设置 ============
+
+    /// 保存设置
+    pub fn set_setting(&mut self, key: &str, value: &str) -> Result<(), StorageError> {
+        self.put_setting(key, value.to_string());
+        self.save()
+    }
+
+    /// 获取设置
+    pub fn get_setting(&self, key: &str) -> Result<Option<String>, StorageError> {
+        Ok(self.store.settings.get(key).cloned())
+    }
+
+    /// 按类型读取设置项（值为 JSON），未设置时为 None
+    pub fn get_setting_as<T: DeserializeOwned>(
+        &self,
+        key: impl AsRef<str>,
+    ) -> Result<Option<T>, StorageError> {
+        let raw = self.store.settings.get(key.as_ref());
+        Ok(raw.map(|raw| serde_json::from_str(raw)).transpose()?)
+    }
+
+    /// 按类型保存设置项（序列化为 JSON），值变化时通知订阅者
+    pub fn set_setting_as<T: Serialize>(
+        &mut self,
+        key: impl AsRef<str>,
+        value: &T,
+    ) -> Result<(), StorageError> {
+        let raw = serde_json::to_string(value)?;
+        self.set_setting(key.as_ref(), &raw)
+    }
+
+    /// 获取全部设置（键名与 JSON 字符串）
+    pub fn list_settings(&self) -> Result<Vec<(String, String)>, StorageError> {
+        Ok(self
+            .store
+            .settings
+            .iter()
+            .map(|(key, raw)| (key.clone(), raw.clone()))
+            .collect())
+    }
+
+    /// 读取类型化设置，未设置时为默认值
+    pub fn get_settings<T: DeserializeOwned>(
+        &self,
+        settings: Settings<T>,
+    ) -> Result<T, StorageError> {
+        let raw = self.store.settings.get(settings.key());
+        Ok(settings.parse(raw.map(String::as_str))?)
+    }
+
+    /// 保存类型化设置
+    pub fn set_settings<T: Serialize>(
+        &mut self,
+        settings: Settings<T>,
+        value: &T,
+    ) -> Result<(), StorageError> {
+        let raw = serde_json::to_string(value)?;
+        self.set_setting(settings.key(), &raw)
+    }
+
+    /// 订阅设置项的变化，回调收到新值（在持有数据库时执行，不得再访问数据库）
+    pub fn subscribe_settings<T: DeserializeOwned + 'static>(
+        &mut self,
+        settings: Settings<T>,
+        callback: impl Fn(&T) + Send + 'static,
+    ) -> SubscriptionId {
+        self.subscribers.subscribe(settings, callback)
+    }
+
+    /// 订阅全部设置的变化，回调收到变化的键名与新值（已删除时为空）
+    pub fn subscribe_all_settings(
+        &mut self,
+        callback: impl Fn(&str, Option<&str>) + Send + 'static,
+    ) -> SubscriptionId {
+        self.subscribers.subscribe_all(callback)
+    }
+
+    /// 取消订阅
+    pub fn unsubscribe_settings(&mut self, id: SubscriptionId) -> bool {
+        self.subscribers.unsubscribe(id)
+    }
+
+    /// 写入设置项，值变化时通知订阅者
+    fn put_setting(&mut self, key: &str, raw: String) {
+        if self.store.settings.get(key) == Some(&raw) {
+            return;
+        }
+        self.store_mut().settings.insert(key.to_string(), raw);
+        self.subscribers
+            .notify(key, self.store.settings.get(key).map(String::as_str));
+    }
+
+    /// 获取舍入与显示精度设置
+    pub fn get_precision_settings(&self) -> Result<PrecisionSettings, StorageError> {
+        self.get_settings(PRECISION_SETTINGS)
+    }
+
+    /// 保存舍入与显示精度设置
+    pub fn set_precision_settings(
+        &mut self,
+        settings: &PrecisionSettings,
+    ) -> Result<(), StorageError> {
+        self.set_settings(PRECISION_SETTINGS, settings)
+    }
+
+    /// 获取数字与日期格式设置
+    pub fn get_format_settings(&self) -> Result<FormatSettings, StorageError> {
+        self.get_settings(FORMAT_SETTINGS)
+    }
+
+    /// 保存数字与日期格式设置
+    pub fn set_format_settings(&mut self, settings: &FormatSettings) -> Result<(), StorageError> {
+        self.set_settings(FORMAT_SETTINGS, settings)
+    }
+
+    /// 按当前语言、格式与精度设置创建格式化器
+    pub fn formatter(&self) -> Result<Formatter, StorageError> {
+        Ok(Formatter::new(
+            &self.get_settings(LOCALE_SETTINGS)?,
+            self.get_format_settings()?,
+            self.get_precision_settings()?,
+        ))
+    }
+
+    /// 获取搜索设置
+    pub fn get_search_settings(&self) -> Result<SearchSettings, StorageError> {
+        self.get_settings(SEARCH_SETTINGS)
+    }
+
+    /// 保存搜索设置
+    pub fn set_search_settings(&mut self, settings: &SearchSettings) -> Result<(), StorageError> {
+        self.set_settings(SEARCH_SETTINGS, settings)
+    }
+
+    /// 获取利率环境假设
+    pub fn get_assumptions(&self) -> Result<Assumptions, StorageError> {
+        self.get_settings(ASSUMPTIONS_SETTINGS)
+    }
+
+    /// 保存利率环境假设，预计余额等推算随之更新
+    pub fn set_assumptions(&mut self, assumptions: &Assumptions) -> Result<(), StorageError> {
+        assumptions.validate().map_err(StorageError::InvalidData)?;
+        self.set_settings(ASSUMPTIONS_SETTINGS, assumptions)
+    }
+
+    /// 获取标签设置
+    pub fn get_tag_settings(&self) -> Result<TagSettings, StorageError> {
+        self.get_settings(TAG_SETTINGS)
+    }
+
+    /// 保存标签设置，并按新设置规范化已有资产的标签，返回标签有变化的资产数
+    pub fn set_tag_settings(&mut self, settings: &TagSettings) -> Result<usize, StorageError> {
+        self.set_settings(TAG_SETTINGS, settings)?;
+        self.normalize_all_tags()
+    }
+
+    /// 获取标签别名（别名 → 标准标签）
+    pub fn list_tag_aliases(&self) -> Result<BTreeMap<String, String>, StorageError> {
+        Ok(self.get_tag_settings()?.aliases)
+    }
+
+    /// 添加或修改标签别名，并替换已有资产上的别名，返回标签有变化的资产数
+    pub fn set_tag_alias(&mut self, alias: &str, tag: &str) -> Result<usize, StorageError> {
+        let mut settings = self.get_tag_settings()?;
+        settings
+            .set_alias(alias, tag)
+            .map_err(StorageError::InvalidData)?;
+        self.set_tag_settings(&settings)
+    }
+
+    /// 删除标签别名（已替换的标签不会恢复）
+    pub fn remove_tag_alias(&mut self, alias: &str) -> Result<(), StorageError> {
+        let mut settings = self.get_tag_settings()?;
+        if !settings.remove_alias(alias) {
+            return Err(StorageError::NotFound(alias.to_string()));
+        }
+        self.set_settings(TAG_SETTINGS, &settings)
+    }
+
+    /// 按当前标签设置规范化所有资产与标签样式中的标签，返回标签有变化的资产数
+    pub fn normalize_all_tags(&mut self) -> Result<usize, StorageError> {
+        let settings = self.get_tag_settings()?;
+        let (changed, tag_styles) = {
+            let known: Vec<&str> = self
+                .store
+                .assets
+                .iter()
+                .flat_map(|a| a.tags.iter().map(String::as_str))
+                .collect();
+            let changed: Vec<Asset> = self
+                .store
+                .assets
+                .iter()
+                .filter_map(|a| {
+                    let tags = settings.normalize(&a.tags, known.iter().copied());
+                    (tags != a.tags).then(|| Asset {
+                        tags,
+                        updated_at: Utc::now(),
+                        ..a.clone()
+                    })
+                })
+                .collect();
+            // 合并到同一标签的样式保留先出现的
+            let mut tag_styles = BTreeMap::new();
+            for (tag, meta) in &self.store.display.tags {
+                let tag = settings
+                    .normalize(std::slice::from_ref(tag), known.iter().copied())
+                    .pop()
+                    .unwrap_or_else(|| tag.clone());
+                tag_styles.entry(tag).or_insert_with(|| meta.clone());
+            }
+            (changed, tag_styles)
+        };
+        if changed.is_empty() && tag_styles == self.store.display.tags {
+            return Ok(0);
+        }
+        let count = changed.len();
+        for asset in changed {
+            self.store_mut().assets.insert(asset);
+        }
+        self.store_mut().display.tags = tag_styles;
+        self.save()?;
+        Ok(count)
+    }
+
+    /// 获取本位币与汇率设置
+    pub fn get_fx_settings(&self) -> Result<FxSettings, StorageError> {
+        self.get_settings(FX_SETTINGS)
+    }
+
+    /// 保存本位币与汇率设置
+    pub fn set_fx_settings(&mut self, settings: &FxSettings) -> Result<(), StorageError> {
+        self.set_settings(FX_SETTINGS, settings)
+    }
+
+    /// 获取保存设置
+    pub fn get_save_settings(&self) -> Result<SaveSettings, StorageError> {
+        self.get_settings(SAVE_SETTINGS)
+    }
+
+    /// 保存保存设置，并立即写入所有未保存的修改
+    pub fn set_save_settings(&mut self, settings: &SaveSettings) -> Result<(), StorageError> {
+        if settings.interval_secs == 0 {
+            return Err(StorageError::InvalidData(
+                "Autosave interval must be positive".to_string(),
+            ));
+        }
+        self.put_setting(SAVE_SETTING_KEY, serde_json::to_string(settings)?);
+        self.save_settings = *settings;
+        self.save_now()
+    }
+
+    /// 当前的保存状态
+    pub fn save_status(&self) -> SaveStatus {
+        SaveStatus {
+            mode: self.save_settings.mode,
+            unsaved_changes: self.unsaved_since.is_some(),
+            unsaved_since: self.unsaved_since,
+            last_saved: self.last_saved,
+            conflict: self.conflict.is_some(),
+            locked_by: self.lock_holder.clone(),
+            read_only: self.is_read_only(),
+        }
+    }
+
+    /// 是否有未保存的修改
+    pub fn has_unsaved_changes(&self) -> bool {
+        self.unsaved_since.is_some()
+    }
+
+    /// 定时保存模式下，最早的未保存修改超过间隔时写入；延迟保存模式下，最后一次修改后
+    /// 超过等待时间（或最早的修改超过定时保存间隔）时写入。返回是否写入
+    pub fn autosave(&mut self, now: DateTime<Utc>) -> Result<bool, StorageError> {
+        let (Some(since), Some(last_change)) = (self.unsaved_since, self.last_change) else {
+            return Ok(false);
+        };
+        let interval = chrono::Duration::seconds(self.save_settings.interval_secs as i64);
+        let debounce = chrono::Duration::milliseconds(self.save_settings.debounce_ms as i64);
+        let due = match self.save_settings.mode {
+            SaveMode::Interval => now - since >= interval,
+            SaveMode::Debounced => now - last_change >= debounce || now - since >= interval,
+            SaveMode::Immediate | SaveMode::Manual => false,
+        };
+        if due {
+            self.save_now()?;
+        }
+        Ok(due)
+    }
+
+    /// 后台检查是否需要定时保存的间隔
+    pub fn autosave_check_interval(&self) -> std::time::Duration {
+        match self.save_settings.mode {
+            SaveMode::Debounced => std::time::Duration::from_millis(DEBOUNCE_CHECK_INTERVAL_MS),
+            _ => std::time::Duration::from_secs(AUTOSAVE_CHECK_INTERVAL_SECS),
+        }
+    }
+
+    /// 立即写入未保存的修改（任何保存方式），返回是否写入
+    pub fn flush(&mut self) -> Result<bool, StorageError> {
+        if self.unsaved_since.is_none() {
+            return Ok(false);
+        }
+        self.save_now()?;
+        Ok(true)
+    }
+
+    /// 获取定时备份设置
+    pub fn get_backup_settings(&self) -> Result<BackupSettings, StorageError> {
+        self.get_settings(BACKUP_SETTINGS)
+    }
+
+    /// 保存定时备份设置
+    pub fn set_backup_settings(&mut self, settings: &BackupSettings) -> Result<(), StorageError> {
+        settings.validate().map_err(StorageError::InvalidData)?;
+        self.set_settings(BACKUP_SETTINGS, settings)
+    }
+
+    /// 获取 SMTP 设置
+    pub fn get_smtp_settings(&self) -> Result<SmtpSettings, StorageError> {
+        self.get_settings(SMTP_SETTINGS)
+    }
+
+    /// 保存 SMTP 设置
+    pub fn set_smtp_settings(&mut self, settings: &SmtpSettings) -> Result<(), StorageError> {
+        self.set_settings(SMTP_SETTINGS, settings)
+    }
+
+    /// 获取月度报告邮件设置
+    pub fn get_report_email_settings(&self) -> Result<ReportEmailSettings, StorageError> {
+        self.get_settings(REPORT_EMAIL_SETTINGS)
+    }
+
+    /// 保存月度报告邮件设置
+    pub fn set_report_email_settings(
+        &mut self,
+        settings: &ReportEmailSettings,
+    ) -> Result<(), StorageError> {
+        self.set_settings(REPORT_EMAIL_SETTINGS, settings)
+    }
+
+    /// 记录某月报告已发送
+    pub fn mark_report_sent(&mut self, year: i32, month: u32) -> Result<(), StorageError> {
+        let mut settings = self.get_report_email_settings()?;
+        settings.last_sent = Some(format!("{:04}-{:02}", year, month));
+        self.set_report_email_settings(&settings)
+    }
+
+    // ============ 凭据 ============
+
+    /// 保存凭据，值为空时删除
+    pub fn set_secret(&mut self, name: &str, value: &str) -> Result<(), StorageError> {
+        self.store_mut().secrets.set(name, value);
+        self.save()
+    }
+
+    /// 读取凭据
+    pub fn get_secret(&self, name: &str) -> Result<Option<String>, StorageError> {
+        Ok(self.store.secrets.get(name).map(str::to_string))
+    }
+
+    /// 是否已保存凭据
+    pub fn has_secret(&self, name: &str) -> bool {
+        self.store.secrets.contains(name)
+    }
+
+    // ============ 报告 ============
+
+    /// 生成某月的月度报告
+    pub fn get_monthly_report(&self, year: i32, month: u32) -> Result<MonthlyReport, StorageError> {
+        let range = MonthlyReport::month_range(year, month).ok_or_else(|| {
+            StorageError::InvalidData(format!("Invalid month: {}-{}", year, month))
+        })?;
+        let transaction_count = self
+            .store
+            .transactions
+            .iter()
+            .filter(|t| !t.scheduled && range.contains(t.timestamp))
+            .count();
+        Ok(MonthlyReport {
+            year,
+            month,
+            summary: self.get_summary()?,
+            attribution: self.get_attribution(&range, GroupBy::AssetType)?,
+            transaction_count,
+            generated_at: Utc::now(),
+        })
+    }
+
+    /// 构建报告模板的渲染上下文
+    ///
+    /// 始终包含 `summary`、`assets` 与 `params`；指定年月时再合并月度报告的上下文。
+    pub fn report_context(&self, params: &ReportParams) -> Result<serde_json::Value, StorageError> {
+        let summary = self.get_summary()?;
+        let assets: Vec<serde_json::Value> = self
+            .list_assets(SortBy::default(), SortOrder::default())?
+            .iter()
+            .map(|a| {
+                serde_json::json!({
+                    "id": a.id,
+                    "name": a.name,
+                    "asset_type": a.asset_type.code(),
+                    "currency": a.currency.code(),
+                    "value": a.value,
+                    "tags": a.tags,
+                    "description": a.description,
+                })
+            })
+            .collect();
+
+        let mut context = match (params.year, params.month) {
+            (Some(year), Some(month)) => self.get_monthly_report(year, month)?.context(),
+            _ => serde_json::json!({
+                "title": "资产清单",
+                "generated_at": Utc::now().format("%Y-%m-%d %H:%M UTC").to_string(),
+            }),
+        };
+        context["summary"] = serde_json::to_value(&summary)?;
+        context["assets"] = serde_json::Value::Array(assets);
+        context["params"] = serde_json::Value::Object(params.extra.clone());
+        if let Some(title) = &params.title {
+            context["title"] = serde_json::Value::String(title.clone());
+        }
+        Ok(context)
+    }
+
+    // ============ 复式记账 ============
+
+    /// 是否已启用复式记账
+    pub fn is_double_entry_enabled(&self) -> bool {
+        self.store.ledger.is_some()
+    }
+
+    /// 启用复式记账，由现有单式记录生成账簿
+    pub fn enable_double_entry(&mut self) -> Result<TrialBalance, StorageError> {
+        if self.store.ledger.is_none() {
+            self.store_mut().ledger = Some(ledger::migrate(
+                &self.store.assets,
+                &self.store.transactions,
+            ));
+            self.save()?;
+        }
+        self.get_trial_balance()
+    }
+
+    /// 停用复式记账（丢弃账簿，单式记录不受影响）
+    pub fn disable_double_entry(&mut self) -> Result<(), StorageError> {
+        self.store_mut().ledger = None;
+        self.save()
+    }
+
+    /// 获取账户列表
+    pub fn list_ledger_accounts(&self) -> Result<Vec<LedgerAccount>, StorageError> {
+        Ok(self.ledger()?.accounts.clone())
+    }
+
+    /// 添加账户（如负债、收入、支出科目）
+    pub fn add_ledger_account(&mut self, account: &LedgerAccount) -> Result<(), StorageError> {
+        self.ledger_mut()?
+            .add_account(account.clone())
+            .map_err(|e| StorageError::InvalidData(e.to_string()))?;
+        self.save()
+    }
+
+    /// 获取分录（按时间倒序）
+    pub fn list_ledger_entries(&self) -> Result<Vec<LedgerEntry>, StorageError> {
+        let mut entries = self.ledger()?.entries.clone();
+        entries.sort_by_key(|e| std::cmp::Reverse(e.timestamp));
+        Ok(entries)
+    }
+
+    /// 记一笔分录
+    ///
+    /// 分录必须借贷平衡；涉及资产账户的分录行会同步调整资产价值并记录对应的单式交易。
+    pub fn post_ledger_entry(&mut self, entry: &LedgerEntry) -> Result<(), StorageError> {
+        self.ensure_period_open(entry.timestamp)?;
+        let ledger = self.ledger()?;
+        ledger
+            .validate(entry)
+            .map_err(|e| StorageError::InvalidData(e.to_string()))?;
+
+        let kinds: Vec<AccountKind> = entry
+            .postings
+            .iter()
+            .filter_map(|p| ledger.account(p.account_id).map(|a| a.kind))
+            .collect();
+        let transaction_type = if kinds.contains(&AccountKind::Income) {
+            TransactionType::Income
+        } else if kinds.contains(&AccountKind::Expense) {
+            TransactionType::Expense
+        } else {
+            TransactionType::Transfer
+        };
+        let asset_postings: Vec<(Uuid, f64)> = entry
+            .postings
+            .iter()
+            .filter_map(|p| {
+                let asset_id = ledger.account(p.account_id)?.asset_id?;
+                Some((asset_id, p.amount))
+            })
+            .collect();
+
+        for &(asset_id, amount) in &asset_postings {
+            let Some((before, after)) = self.store_mut().assets.update(asset_id, |asset| {
+                let before = asset.value;
+                asset.update_value(before + amount);
+                (before, asset.value)
+            }) else {
+                continue;
+            };
+            let transaction =
+                AssetTransaction::new(asset_id, transaction_type.clone(), before, after)
+                    .with_note(entry.description.clone())
+                    .with_timestamp(entry.timestamp);
+            self.store_mut().transactions.insert(transaction);
+        }
+        self.refresh_daily_balances(asset_postings.into_iter().map(|(id, _)| id));
+
+        self.ledger_mut()?.entries.push(entry.clone());
+        self.save()
+    }
+
+    /// 获取账簿；未启用复式记账时由单式记录临时生成
+    pub fn build_ledger(&self) -> Ledger {
+        match &self.store.ledger {
+            Some(ledger) => ledger.clone(),
+            None => ledger::migrate(&self.store.assets, &self.store.transactions),
+        }
+    }
+
+    /// 获取试算平衡表
+    pub fn get_trial_balance(&self) -> Result<TrialBalance, StorageError> {
+        Ok(self.ledger()?.trial_balance())
+    }
+
+    fn ledger(&self) -> Result<&Ledger, StorageError> {
+        self.store
+            .ledger
+            .as_ref()
+            .ok_or_else(|| StorageError::InvalidData("Double-entry mode is disabled".to_string()))
+    }
+
+    fn ledger_mut(&mut self) -> Result<&mut Ledger, StorageError> {
+        self.store_mut()
+            .ledger
+            .as_mut()
+            .ok_or_else(|| StorageError::InvalidData("Double-entry mode is disabled".to_string()))
+    }
+
+    // ============ 会计期间 ============
+
+    /// 结账某月，已结账时返回原记录
+    pub fn close_period(&mut self, year: i32, month: u32) -> Result<ClosedPeriod, StorageError> {
+        if !(1..=12).contains(&month) {
+            return Err(StorageError::InvalidData(format!(
+                "Invalid month: {}",
+                month
+            )));
+        }
+        if let Some(existing) = self
+            .store
+            .closed_periods
+            .iter()
+            .find(|p| p.year == year && p.month == month)
+        {
+            return Ok(existing.clone());
+        }
+
+        let period = ClosedPeriod::new(year, month);
+        self.store_mut().closed_periods.push(period.clone());
+        self.save()?;
+        Ok(period)
+    }
+
+    /// 解锁已结账的期间
+    pub fn reopen_period(&mut self, year: i32, month: u32) -> Result<(), StorageError> {
+        let pos = self
+            .store
+            .closed_periods
+            .iter()
+            .position(|p| p.year == year && p.month == month)
+            .ok_or_else(|| StorageError::NotFound(format!("{:04}-{:02}", year, month)))?;
+        self.store_mut().closed_periods.remove(pos);
+        self.save()
+    }
+
+    /// 获取已结账的期间（按时间升序）
+    pub fn list_closed_periods(&self) -> Result<Vec<ClosedPeriod>, StorageError> {
+        let mut periods = self.store.closed_periods.clone();
+        periods.sort_by_key(|p| (p.year, p.month));
+        Ok(periods)
+    }
+
+    /// 时间所在期间已结账时返回错误
+    fn ensure_period_open(&self, t: DateTime<Utc>) -> Result<(), StorageError> {
+        match self.store.closed_periods.iter().find(|p| p.contains(t)) {
+            Some(period) => Err(StorageError::PeriodClosed(period.label())),
+            None => Ok(()),
+        }
+    }
+
+    // ============ 公司行动 ============
+
+    /// 对持仓执行公司行动（拆股、代码变更、分红），返回记录的说明交易
+    ///
+    /// 拆股与代码变更修改资产元数据，并记录一笔金额不变的价值变动；分红按持仓数量计为收益，
+    /// 可记入另一资产（如证券账户的现金）。
+    pub fn apply_corporate_action(
+        &mut self,
+        asset_id: Uuid,
+        action: &CorporateAction,
+    ) -> Result<AssetTransaction, StorageError> {
+        let transaction = self.record_corporate_action(asset_id, action, None)?;
+        self.save()?;
+        Ok(transaction)
+    }
+
+    /// 录入数据源提供的分红，跳过资产创建前与已录入的分红，返回新录入的交易
+    pub fn record_dividends(
+        &mut self,
+        asset_id: Uuid,
+        events: &[DividendEvent],
+        pay_to: Option<Uuid>,
+    ) -> Result<Vec<AssetTransaction>, StorageError> {
+        let held_since = self
+            .store
+            .assets
+            .get(asset_id)
+            .ok_or_else(|| StorageError::NotFound(asset_id.to_string()))?
+            .created_at
+            .date_naive();
+        let target = pay_to.unwrap_or(asset_id);
+        let mut recorded = Vec::new();
+        for event in events.iter().filter(|e| e.date >= held_since) {
+            let external_id = event.external_id(asset_id);
+            if self
+                .get_transaction_by_external_id(target, &external_id)?
+                .is_some()
+            {
+                continue;
+            }
+            let action = CorporateAction::Dividend {
+                per_share: event.per_share,
+                pay_to,
+            };
+            recorded.push(self.record_corporate_action(asset_id, &action, Some(external_id))?);
+        }
+        if !recorded.is_empty() {
+            self.save()?;
+        }
+        Ok(recorded)
+    }
+
+    fn record_corporate_action(
+        &mut self,
+        asset_id: Uuid,
+        action: &CorporateAction,
+        external_id: Option<String>,
+    ) -> Result<AssetTransaction, StorageError> {
+        self.ensure_period_open(Utc::now())?;
+        let mut asset = self
+            .store
+            .assets
+            .get(asset_id)
+            .cloned()
+            .ok_or_else(|| StorageError::NotFound(asset_id.to_string()))?;
+        let (note, cash) = action
+            .apply(&mut asset)
+            .map_err(StorageError::InvalidData)?;
+        let mut transaction = match action {
+            CorporateAction::Dividend { pay_to, .. } => self.apply_delta(
+                pay_to.unwrap_or(asset_id),
+                cash,
+                TransactionType::Income,
+                Some(note),
+            )?,
+            _ => {
+                self.ensure_metadata_valid(&asset)?;
+                self.store_mut()
+                    .assets
+                    .update(asset_id, |a| a.metadata = asset.metadata);
+                self.apply_delta(asset_id, 0.0, TransactionType::ValueChange, Some(note))?
+            }
+        };
+        if external_id.is_some() {
+            transaction.external_id = external_id;
+            self.store_mut().transactions.insert(transaction.clone());
+        }
+        Ok(transaction)
+    }
+
+    // ============ 卖出 ============
+
+    /// 部分卖出持仓：卖出金额从资产扣除并转入收款资产，手续费与税费记为关联到卖出交易的支出
+    pub fn sell_asset(&mut self, order: &SellOrder) -> Result<SaleRecord, StorageError> {
+        order.validate().map_err(StorageError::InvalidData)?;
+        let asset = self
+            .store
+            .assets
+            .get(order.asset_id)
+            .ok_or_else(|| StorageError::NotFound(order.asset_id.to_string()))?;
+        if order.gross > asset.value {
+            return Err(StorageError::InvalidData(format!(
+                "Sell amount {} exceeds the value of {}",
+                order.gross, asset.name
+            )));
+        }
+        let note = order
+            .note
+            .clone()
+            .unwrap_or_else(|| format!("卖出 {}", asset.name));
+        // 先检查收款资产，避免卖出已记录后才失败
+        if let Some(pay_to) = order.pay_to {
+            match self.store.assets.get(pay_to) {
+                None => return Err(StorageError::NotFound(pay_to.to_string())),
+                Some(a) if a.is_virtual() => {
+                    return Err(StorageError::InvalidData(format!(
+                        "{} is a computed asset",
+                        a.name
+                    )))
+                }
+                Some(_) => {}
+            }
+        }
+
+        let sale = self.apply_delta(
+            order.asset_id,
+            -order.gross,
+            TransactionType::Sell,
+            Some(note.clone()),
+        )?;
+        let proceeds = order
+            .pay_to
+            .map(|pay_to| {
+                self.apply_delta(
+                    pay_to,
+                    order.gross,
+                    TransactionType::Transfer,
+                    Some(note.clone()),
+                )
+            })
+            .transpose()?;
+        let mut charges = Vec::new();
+        for (kind, amount) in order.charges() {
+            let label = match kind {
+                ChargeKind::Fee => "手续费",
+                ChargeKind::Tax => "税费",
+            };
+            // 卖出款不在账内时只记下费用，不改变资产价值
+            let (target, delta) = match order.pay_to {
+                Some(pay_to) => (pay_to, -amount),
+                None => (order.asset_id, 0.0),
+            };
+            let mut charge = self.apply_delta(
+                target,
+                delta,
+                TransactionType::Expense,
+                Some(format!("{}（{}）", note, label)),
+            )?;
+            charge.charge = Some(SaleCharge {
+                sale_id: sale.id,
+                kind,
+                amount,
+            });
+            self.store_mut().transactions.insert(charge.clone());
+            charges.push(charge);
+        }
+        self.save()?;
+        Ok(SaleRecord {
+            sale,
+            proceeds,
+            charges,
+            net_proceeds: order.net_proceeds(),
+        })
+    }
+
+    /// 时间范围内各笔卖出的已实现盈亏，以及手续费与税费合计
+    pub fn get_realized_gains(
+        &self,
+        range: &DateRange,
+        method: CostMethod,
+    ) -> Result<RealizedGainsReport, StorageError> {
+        let mut by_asset: HashMap<Uuid, Vec<AssetTransaction>> = HashMap::new();
+        for t in self.store.transactions.newest_first(..) {
+            by_asset.entry(t.asset_id).or_default().push(t.clone());
+        }
+        let charges: Vec<AssetTransaction> = self
+            .store
+            .transactions
+            .iter()
+            .filter(|t| t.charge.is_some())
+            .cloned()
+            .collect();
+
+        let mut sales = Vec::new();
+        for (asset_id, mut transactions) in by_asset {
+            let sold_in_range = transactions.iter().any(|t| {
+                t.transaction_type == TransactionType::Sell && range.contains(t.timestamp)
+            });
+            let Some(asset) = self.store.assets.get(asset_id).filter(|_| sold_in_range) else {
+                continue;
+            };
+            // 时间相同的交易按记录顺序重放
+            transactions.reverse();
+            sales.extend(
+                realized_sales(asset, &transactions, &charges, method)
+                    .into_iter()
+                    .filter(|s| range.contains(s.timestamp)),
+            );
+        }
+        Ok(RealizedGainsReport::new(method, sales))
+    }
+
+    // ============ 成本重算 ============
+
+    /// 按买入/卖出历史重算资产的买入成本，与原成本不同时保存，返回各步的成本变动
+    pub fn recalculate_cost_basis(
+        &mut self,
+        asset_id: Uuid,
+        method: CostMethod,
+    ) -> Result<CostBasisReport, StorageError> {
+        let asset = self
+            .store
+            .assets
+            .get(asset_id)
+            .ok_or_else(|| StorageError::NotFound(asset_id.to_string()))?;
+        let mut transactions: Vec<AssetTransaction> = self
+            .store
+            .transactions
+            .newest_first(..)
+            .filter(|t| t.asset_id == asset_id)
+            .cloned()
+            .collect();
+        // 时间相同的交易按记录顺序重放
+        transactions.reverse();
+        let report = replay_cost_basis(asset, &transactions, method);
+        if asset.cost_basis != Some(report.cost_basis) {
+            self.store_mut().assets.update(asset_id, |a| {
+                a.cost_basis = Some(report.cost_basis);
+                a.updated_at = Utc::now();
+            });
+            self.save()?;
+        }
+        Ok(report)
+    }
+
+    // ============ 基金定投 ============
+
+    /// 添加定投计划
+    pub fn add_sip_plan(&mut self, plan: &SipPlan) -> Result<(), StorageError> {
+        self.ensure_sip_plan_valid(plan)?;
+        self.store_mut().sip_plans.push(plan.clone());
+        self.save()
+    }
+
+    /// 更新定投计划的设置，保留已执行的各期记录
+    pub fn update_sip_plan(&mut self, plan: &SipPlan) -> Result<SipPlan, StorageError> {
+        self.ensure_sip_plan_valid(plan)?;
+        let existing = self.sip_plan_mut(plan.id)?;
+        let installments = std::mem::take(&mut existing.installments);
+        *existing = SipPlan {
+            installments,
+            ..plan.clone()
+        };
+        let updated = existing.clone();
+        self.save()?;
+        Ok(updated)
+    }
+
+    /// 获取定投计划（可按基金筛选，按下一期扣款时间排序）
+    pub fn list_sip_plans(&self, asset_id: Option<Uuid>) -> Result<Vec<SipPlan>, StorageError> {
+        let mut plans: Vec<SipPlan> = self
+            .store
+            .sip_plans
+            .iter()
+            .filter(|p| asset_id.is_none() || asset_id == Some(p.asset_id))
+            .cloned()
+            .collect();
+        plans.sort_by_key(|p| p.next_run);
+        Ok(plans)
+    }
+
+    /// 获取到期的定投计划
+    pub fn get_due_sip_plans(&self, now: DateTime<Utc>) -> Result<Vec<SipPlan>, StorageError> {
+        let mut plans = self.list_sip_plans(None)?;
+        plans.retain(|p| p.is_due(now));
+        Ok(plans)
+    }
+
+    /// 删除定投计划（已记录的交易保留）
+    pub fn delete_sip_plan(&mut self, id: Uuid) -> Result<(), StorageError> {
+        let pos = self
+            .store
+            .sip_plans
+            .iter()
+            .position(|p| p.id == id)
+            .ok_or_else(|| StorageError::NotFound(id.to_string()))?;
+        self.store_mut().sip_plans.remove(pos);
+        self.save()
+    }
+
+    /// 记录一期定投：基金价值增加扣除手续费后的金额，累加份额并更新净值；扣款资产减少扣款金额
+    pub fn record_sip_installment(
+        &mut self,
+        plan_id: Uuid,
+        date: DateTime<Utc>,
+        nav: f64,
+        fee: f64,
+    ) -> Result<SipInstallment, StorageError> {
+        self.ensure_period_open(date)?;
+        let plan = self.sip_plan(plan_id)?.clone();
+        let mut installment =
+            SipInstallment::new(date, plan.amount, fee, nav).map_err(StorageError::InvalidData)?;
+        let mut fund = self
+            .store
+            .assets
+            .get(plan.asset_id)
+            .cloned()
+            .ok_or_else(|| StorageError::NotFound(plan.asset_id.to_string()))?;
+
+        // 按批次跟踪时追加一批，否则累加数量字段
+        let key = quantity_key(&fund.asset_type);
+        if !fund.metadata.is_object() {
+            fund.metadata = serde_json::json!({});
+        }
+        let has_quantity = fund.metadata.get(key).is_some();
+        match fund
+            .metadata
+            .get_mut("lots")
+            .and_then(serde_json::Value::as_array_mut)
+        {
+            Some(lots) if !has_quantity => {
+                lots.push(serde_json::json!({ key: installment.units, "price": nav }))
+            }
+            _ => {
+                let units = fund.metadata.get(key).and_then(serde_json::Value::as_f64);
+                fund.metadata[key] = (units.unwrap_or(0.0) + installment.units).into();
+            }
+        }
+        fund.metadata["nav"] = nav.into();
+        self.ensure_metadata_valid(&fund)?;
+
+        let note = format!(
+            "定投 {}：净值 {}，确认份额 {:.2}",
+            fund.name, nav, installment.units
+        );
+        let mut buy = self.apply_delta(
+            plan.asset_id,
+            plan.amount - fee,
+            TransactionType::Buy,
+            Some(note.clone()),
+        )?;
+        self.store_mut()
+            .assets
+            .update(plan.asset_id, |a| a.metadata = fund.metadata);
+        buy.timestamp = date;
+        self.store_mut().transactions.insert(buy.clone());
+        if let Some(pay_from) = plan.pay_from {
+            let mut payment = self.apply_delta(
+                pay_from,
+                -plan.amount,
+                TransactionType::Transfer,
+                Some(note),
+            )?;
+            payment.timestamp = date;
+            self.store_mut().transactions.insert(payment);
+        }
+        // 交易日期改为扣款日期后重新计算每日余额
+        self.refresh_daily_balances([Some(plan.asset_id), plan.pay_from].into_iter().flatten());
+
+        installment.transaction_id = Some(buy.id);
+        self.sip_plan_mut(plan_id)?.record(installment.clone());
+        self.save()?;
+        Ok(installment)
+    }
+
+    /// 定投报告：累计投入、份额、平均成本、收益与年化内部收益率
+    pub fn get_sip_report(
+        &self,
+        plan_id: Uuid,
+        now: DateTime<Utc>,
+    ) -> Result<SipReport, StorageError> {
+        let plan = self.sip_plan(plan_id)?;
+        let fund = self
+            .store
+            .assets
+            .get(plan.asset_id)
+            .ok_or_else(|| StorageError::NotFound(plan.asset_id.to_string()))?;
+        Ok(sip_report(plan, fund, now))
+    }
+
+    fn ensure_sip_plan_valid(&self, plan: &SipPlan) -> Result<(), StorageError> {
+        plan.validate().map_err(StorageError::InvalidData)?;
+        let fund = self
+            .store
+            .assets
+            .get(plan.asset_id)
+            .ok_or_else(|| StorageError::NotFound(plan.asset_id.to_string()))?;
+        if fund.asset_type != AssetType::Fund {
+            return Err(StorageError::InvalidData(format!(
+                "{} is not a fund",
+                fund.name
+            )));
+        }
+        match plan.pay_from {
+            Some(id) if !self.store.assets.contains(id) => {
+                Err(StorageError::NotFound(id.to_string()))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn sip_plan(&self, id: Uuid) -> Result<&SipPlan, StorageError> {
+        self.store
+            .sip_plans
+            .iter()
+            .find(|p| p.id == id)
+            .ok_or_else(|| StorageError::NotFound(id.to_string()))
+    }
+
+    fn sip_plan_mut(&mut self, id: Uuid) -> Result<&mut SipPlan, StorageError> {
+        self.store_mut()
+            .sip_plans
+            .iter_mut()
+            .find(|p| p.id == id)
+            .ok_or_else(|| StorageError::NotFound(id.to_string()))
+    }
+
+    // ============ 汇率 ============
+
+    /// 保存历史汇率（同一货币对同一日期的记录会被覆盖），返回写入条数
+    pub fn upsert_fx_rates(&mut self, rates: &[FxRate]) -> Result<usize, StorageError> {
+        for rate in rates {
+            match self
+                .store_mut()
+                .fx_rates
+                .iter_mut()
+                .find(|r| r.same_key(rate))
+            {
+                Some(existing) => existing.rate = rate.rate,
+                None => self.store_mut().fx_rates.push(rate.clone()),
+            }
+        }
+        self.save()?;
+        Ok(rates.len())
+    }
+
+    /// 获取某货币对当前本位币的历史汇率（按日期升序）
+    pub fn list_fx_rates(&self, currency: &Currency) -> Result<Vec<FxRate>, StorageError> {
+        let base = self.get_fx_settings()?.base_currency;
+        let mut rates: Vec<FxRate> = self
+            .store
+            .fx_rates
+            .iter()
+            .filter(|r| r.currency == currency.code() && r.base_currency == base.code())
+            .cloned()
+            .collect();
+        rates.sort_by_key(|r| r.date);
+        Ok(rates)
+    }
+
+    /// 获取某日生效的汇率
+    ///
+    /// 取不晚于该日的最近一条历史汇率；没有历史记录时退回设置中的最新汇率。
+    pub fn get_rate_on(
+        &self,
+        currency: &Currency,
+        date: NaiveDate,
+    ) -> Result<Option<f64>, StorageError> {
+        let settings = self.get_fx_settings()?;
+        Ok(self.rate_on(&settings, currency, date))
+    }
+
+    /// 按某日生效的汇率折算为本位币
+    pub fn convert_on(
+        &self,
+        value: f64,
+        currency: &Currency,
+        date: NaiveDate,
+    ) -> Result<Option<f64>, StorageError> {
+        Ok(self.get_rate_on(currency, date)?.map(|rate| value * rate))
+    }
+
+    /// 外币交易的汇兑损益：实际汇率与当日参考汇率的差额
+    pub fn get_fx_gain_loss(
+        &self,
+        range: Option<&DateRange>,
+    ) -> Result<FxGainLossReport, StorageError> {
+        let settings = self.get_fx_settings()?;
+        let mut items = Vec::new();
+        let mut missing_rates = Vec::new();
+
+        for transaction in &self.store.transactions {
+            let Some(original_currency) = &transaction.original_currency else {
+                continue;
+            };
+            if range.is_some_and(|r| !r.contains(transaction.timestamp)) {
+                continue;
+            }
+            let Some(asset) = self
+                .store
+                .assets
+                .iter()
+                .find(|a| a.id == transaction.asset_id)
+            else {
+                continue;
+            };
+
+            let date = transaction.timestamp.date_naive();
+            let account_rate = self.rate_on(&settings, &asset.currency, date);
+            let reference_rate = self
+                .rate_on(&settings, original_currency, date)
+                .zip(account_rate)
+                .map(|(original, account)| original / account);
+            let Some(mut item) =
+                reference_rate.and_then(|rate| fx_gain_loss(transaction, &asset.currency, rate))
+            else {
+                missing_rates.push(transaction.id);
+                continue;
+            };
+            item.base_gain_loss = account_rate.map(|rate| item.gain_loss * rate);
+            items.push(item);
+        }
+
+        let total_base_gain_loss = items.iter().filter_map(|i| i.base_gain_loss).sum();
+        Ok(FxGainLossReport {
+            base_currency: settings.base_currency,
+            items,
+            total_base_gain_loss,
+            missing_rates,
+        })
+    }
+
+    /// 以本位币计价的净资产序列，各时点按当时生效的汇率折算
+    ///
+    /// 缺少汇率的资产不计入；任一资产的值为填充得到时，该点标记为插值。
+    pub fn get_net_worth_series(
+        &self,
+        range: &DateRange,
+        interval: Interval,
+    ) -> Result<Vec<SeriesPoint>, StorageError> {
+        let settings = self.get_fx_settings()?;
+        let mut totals: BTreeMap<DateTime<Utc>, (f64, bool)> = BTreeMap::new();
+
+        // 各资产的序列并行计算，按资产顺序合并
+        let by_asset = transactions_by_asset(&self.store.transactions);
+        let assets: Vec<&Asset> = self
+            .store
+            .assets
+            .iter()
+            .filter(|a| !a.is_virtual())
+            .collect();
+        let series: Vec<Vec<SeriesPoint>> = assets
+            .par_iter()
+            .map(|asset| {
+                let txns = by_asset
+                    .get(&asset.id)
+                    .map(Vec::as_slice)
+                    .unwrap_or_default();
+                let history = value_history(asset, txns);
+                interpolate_series(&history, range, interval, InterpolationMethod::CarryForward)
+            })
+            .collect();
+
+        for (asset, points) in assets.into_iter().zip(series) {
+            for point in points {
+                let date = point.timestamp.date_naive();
+                let Some(rate) = self.rate_on(&settings, &asset.currency, date) else {
+                    continue;
+                };
+                let entry = totals.entry(point.timestamp).or_insert((0.0, false));
+                entry.0 += point.value * rate;
+                entry.1 |= point.interpolated;
+            }
+        }
+
+        Ok(totals
+            .into_iter()
+            .map(|(timestamp, (value, interpolated))| SeriesPoint {
+                timestamp,
+                value,
+                interpolated,
+            })
+            .collect())
+    }
+
+    fn rate_on(&self, settings: &FxSettings, currency: &Currency, date: NaiveDate) -> Option<f64> {
+        self.rate_with_date(settings, currency, date)
+            .map(|(rate, _)| rate)
+    }
+
+    /// 折算为本位币的汇率及其日期（本位币与手动设置的汇率没有日期）
+    fn rate_with_date(
+        &self,
+        settings: &FxSettings,
+        currency: &Currency,
+        date: NaiveDate,
+    ) -> Option<(f64, Option<NaiveDate>)> {
+        if *currency == settings.base_currency {
+            return Some((1.0, None));
+        }
+        self.store
+            .fx_rates
+            .iter()
+            .filter(|r| {
+                r.currency == currency.code()
+                    && r.base_currency == settings.base_currency.code()
+                    && r.date <= date
+            })
+            .max_by_key(|r| r.date)
+            .map(|r| (r.rate, Some(r.date)))
+            .or_else(|| settings.rate(currency).map(|rate| (rate, None)))
+    }
+
+    // ============ 信封预算 ============
+
+    /// 在现金或存款资产内创建信封
+    pub fn create_envelope(&mut self, envelope: &Envelope) -> Result<(), StorageError> {
+        let asset = self
+            .get_asset(envelope.asset_id)?
+            .ok_or_else(|| StorageError::NotFound(envelope.asset_id.to_string()))?;
+        if !matches!(asset.asset_type, AssetType::Cash | AssetType::BankDeposit) {
+            return Err(StorageError::InvalidData(
+                "Envelopes are only available for cash and bank deposits".to_string(),
+            ));
+        }
+        if self
+            .store
+            .envelopes
+            .iter()
+            .any(|e| e.asset_id == envelope.asset_id && e.name == envelope.name)
+        {
+            return Err(StorageError::InvalidData(format!(
+                "Envelope already exists: {}",
+                envelope.name
+            )));
+        }
+        self.store_mut().envelopes.push(envelope.clone());
+        self.save()
+    }
+
+    /// 获取资产的信封分配情况
+    pub fn get_envelopes(&self, asset_id: Uuid) -> Result<EnvelopeSummary, StorageError> {
+        let asset = self
+            .get_asset(asset_id)?
+            .ok_or_else(|| StorageError::NotFound(asset_id.to_string()))?;
+        Ok(EnvelopeSummary::new(&asset, &self.store.envelopes))
+    }
+
+    /// 从未分配金额向信封分配（负数表示退回未分配）
+    pub fn allocate_to_envelope(
+        &mut self,
+        envelope_id: Uuid,
+        amount: f64,
+    ) -> Result<Envelope, StorageError> {
+        let asset_id = self.envelope_mut(envelope_id)?.asset_id;
+        let summary = self.get_envelopes(asset_id)?;
+        if amount > summary.unallocated {
+            return Err(StorageError::InvalidData(format!(
+                "Only {} is unallocated",
+                summary.unallocated
+            )));
+        }
+        let envelope = self.envelope_mut(envelope_id)?;
+        if envelope.balance + amount < 0.0 {
+            return Err(StorageError::InvalidData(format!(
+                "Envelope {} only holds {}",
+                envelope.name, envelope.balance
+            )));
+        }
+        envelope.balance += amount;
+        let envelope = envelope.clone();
+        self.save()?;
+        Ok(envelope)
+    }
+
+    /// 在同一资产的两个信封之间移动金额
+    pub fn move_between_envelopes(
+        &mut self,
+        from_id: Uuid,
+        to_id: Uuid,
+        amount: f64,
+    ) -> Result<(), StorageError> {
+        if amount <= 0.0 {
+            return Err(StorageError::InvalidData(
+                "Amount must be positive".to_string(),
+            ));
+        }
+        let to_asset = self.envelope_mut(to_id)?.asset_id;
+        let from = self.envelope_mut(from_id)?;
+        if from.asset_id != to_asset {
+            return Err(StorageError::InvalidData(
+                "Envelopes belong to different assets".to_string(),
+            ));
+        }
+        if from.balance < amount {
+            return Err(StorageError::InvalidData(format!(
+                "Envelope {} only holds {}",
+                from.name, from.balance
+            )));
+        }
+        from.balance -= amount;
+        self.envelope_mut(to_id)?.balance += amount;
+        self.save()
+    }
+
+    /// 删除信封，余额退回未分配
+    pub fn delete_envelope(&mut self, id: Uuid) -> Result<(), StorageError> {
+        let before = self.store.envelopes.len();
+        self.store_mut().envelopes.retain(|e| e.id != id);
+        if self.store.envelopes.len() == before {
+            return Err(StorageError::NotFound(id.to_string()));
+        }
+        self.save()
+    }
+
+    fn envelope_mut(&mut self, id: Uuid) -> Result<&mut Envelope, StorageError> {
+        self.store_mut()
+            .envelopes
+            .iter_mut()
+            .find(|e| e.id == id)
+            .ok_or_else(|| StorageError::NotFound(id.to_string()))
+    }
+
+    // ============ 分摊账单 ============
+
+    /// 记录共同支出
+    pub fn add_shared_expense(&mut self, expense: &SharedExpense) -> Result<(), StorageError> {
+        expense.validate().map_err(StorageError::InvalidData)?;
+        self.store_mut().shared_expenses.push(expense.clone());
+        self.save()
+    }
+
+    /// 获取所有共同支出（按时间倒序）
+    pub fn list_shared_expenses(&self) -> Result<Vec<SharedExpense>, StorageError> {
+        let mut expenses = self.store.shared_expenses.clone();
+        expenses.sort_by_key(|expense| Reverse(expense.date));
+        Ok(expenses)
+    }
+
+    /// 删除共同支出
+    pub fn delete_shared_expense(&mut self, id: Uuid) -> Result<(), StorageError> {
+        let before = self.store.shared_expenses.len();
+        self.store_mut().shared_expenses.retain(|e| e.id != id);
+        if self.store.shared_expenses.len() == before {
+            return Err(StorageError::NotFound(id.to_string()));
+        }
+        self.save()
+    }
+
+    /// 获取所有结算记录（按时间倒序）
+    pub fn list_settlements(&self) -> Result<Vec<Settlement>, StorageError> {
+        let mut settlements = self.store.settlements.clone();
+        settlements.sort_by_key(|settlement| Reverse(settlement.date));
+        Ok(settlements)
+    }
+
+    /// 各参与者的净余额
+    pub fn get_split_balances(&self) -> Result<Vec<ParticipantBalance>, StorageError> {
+        Ok(split::balances(
+            &self.store.shared_expenses,
+            &self.store.settlements,
+        ))
+    }
+
+    /// 结清所有欠款的建议还款方案
+    pub fn get_suggested_settlements(&self) -> Result<Vec<Debt>, StorageError> {
+        Ok(split::settle_up(&self.get_split_balances()?))
+    }
+
+    /// 记录一次还款
+    ///
+    /// 本人参与且指定了资产时，同时在该资产上记录收入（收款）或支出（付款）。
+    pub fn record_settlement(
+        &mut self,
+        mut settlement: Settlement,
+        asset_id: Option<Uuid>,
+    ) -> Result<Settlement, StorageError> {
+        if settlement.amount <= 0.0 {
+            return Err(StorageError::InvalidData(
+                "Amount must be positive".to_string(),
+            ));
+        }
+        if settlement.from == settlement.to {
+            return Err(StorageError::InvalidData(
+                "Cannot settle with oneself".to_string(),
+            ));
+        }
+
+        if let Some(asset_id) = asset_id {
+            let (delta, transaction_type, counterparty) = if settlement.to == split::ME {
+                (settlement.amount, TransactionType::Income, &settlement.from)
+            } else if settlement.from == split::ME {
+                (-settlement.amount, TransactionType::Expense, &settlement.to)
+            } else {
+                return Err(StorageError::InvalidData(
+                    "Only settlements involving yourself can be recorded on an asset".to_string(),
+                ));
+            };
+            let note = Some(format!("分摊结算：{}", counterparty));
+            let transaction = self.apply_delta(asset_id, delta, transaction_type, note)?;
+            settlement.transaction_id = Some(transaction.id);
+        }
+
+        self.store_mut().settlements.push(settlement.clone());
+        self.save()?;
+        Ok(settlement)
+    }
+
+    // ============ 对账单导入 ============
+
+    /// 保存映射配置（同 ID 的配置会被覆盖）
+    pub fn save_import_profile(&mut self, profile: &MappingProfile) -> Result<(), StorageError> {
+        profile.validate().map_err(StorageError::InvalidData)?;
+        if !self.store.assets.contains(profile.asset_id) {
+            return Err(StorageError::NotFound(profile.asset_id.to_string()));
+        }
+        match self
+            .store_mut()
+            .import_profiles
+            .iter_mut()
+            .find(|p| p.id == profile.id)
+        {
+            Some(existing) => *existing = profile.clone(),
+            None => self.store_mut().import_profiles.push(profile.clone()),
+        }
+        self.save()
+    }
+
+    /// 获取所有映射配置
+    pub fn list_import_profiles(&self) -> Result<Vec<MappingProfile>, StorageError> {
+        Ok(self.store.import_profiles.clone())
+    }
+
+    /// 获取映射配置
+    pub fn get_import_profile(&self, id: Uuid) -> Result<Option<MappingProfile>, StorageError> {
+        Ok(self
+            .store
+            .import_profiles
+            .iter()
+            .find(|p| p.id == id)
+            .cloned())
+    }
+
+    /// 删除映射配置，同时移除绑定它的监视目录
+    pub fn delete_import_profile(&mut self, id: Uuid) -> Result<(), StorageError> {
+        let before = self.store.import_profiles.len();
+        self.store_mut().import_profiles.retain(|p| p.id != id);
+        if self.store.import_profiles.len() == before {
+            return Err(StorageError::NotFound(id.to_string()));
+        }
+        self.store_mut()
+            .watch_folders
+            .retain(|f| f.profile_id != id);
+        self.save()
+    }
+
+    /// 添加监视目录
+    pub fn add_watch_folder(&mut self, folder: &WatchFolder) -> Result<(), StorageError> {
+        if !self
+            .store
+            .import_profiles
+            .iter()
+            .any(|p| p.id == folder.profile_id)
+        {
+            return Err(StorageError::NotFound(folder.profile_id.to_string()));
+        }
+        self.store_mut().watch_folders.push(folder.clone());
+        self.save()
+    }
+
+    /// 获取所有监视目录
+    pub fn list_watch_folders(&self) -> Result<Vec<WatchFolder>, StorageError> {
+        Ok(self.store.watch_folders.clone())
+    }
+
+    /// 删除监视目录
+    pub fn delete_watch_folder(&mut self, id: Uuid) -> Result<(), StorageError> {
+        let before = self.store.watch_folders.len();
+        self.store_mut().watch_folders.retain(|f| f.id != id);
+        if self.store.watch_folders.len() == before {
+            return Err(StorageError::NotFound(id.to_string()));
+        }
+        self.save()
+    }
+
+    // ============ 成交单导入 ============
+
+    /// 保存成交单映射配置（同 ID 的配置会被覆盖）
+    pub fn save_trade_profile(&mut self, profile: &TradeProfile) -> Result<(), StorageError> {
+        profile.validate().map_err(StorageError::InvalidData)?;
+        let referenced = profile.rules.iter().map(|r| r.asset_id);
+        if let Some(id) = referenced
+            .chain(profile.cash_account)
+            .find(|id| !self.store.assets.contains(*id))
+        {
+            return Err(StorageError::NotFound(id.to_string()));
+        }
+        match self
+            .store_mut()
+            .trade_profiles
+            .iter_mut()
+            .find(|p| p.id == profile.id)
+        {
+            Some(existing) => *existing = profile.clone(),
+            None => self.store_mut().trade_profiles.push(profile.clone()),
+        }
+        self.save()
+    }
+
+    /// 获取所有成交单映射配置
+    pub fn list_trade_profiles(&self) -> Result<Vec<TradeProfile>, StorageError> {
+        Ok(self.store.trade_profiles.clone())
+    }
+
+    /// 获取成交单映射配置
+    pub fn get_trade_profile(&self, id: Uuid) -> Result<Option<TradeProfile>, StorageError> {
+        Ok(self
+            .store
+            .trade_profiles
+            .iter()
+            .find(|p| p.id == id)
+            .cloned())
+    }
+
+    /// 删除成交单映射配置
+    pub fn delete_trade_profile(&mut self, id: Uuid) -> Result<(), StorageError> {
+        let before = self.store.trade_profiles.len();
+        self.store_mut().trade_profiles.retain(|p| p.id != id);
+        if self.store.trade_profiles.len() == before {
+            return Err(StorageError::NotFound(id.to_string()));
+        }
+        self.save()
+    }
+
+    /// 一次导入券商成交单：按代码匹配或新建持仓，按日期顺序记录买入、卖出及手续费
+    ///
+    /// 成交指纹已存在的成交视为重复，跳过不导入；找不到持仓或卖出超过持仓的成交记入错误。
+    /// 已有持仓在成交前按成交价重估，使成本重算能按成交价折算份额。新建的资产与导入的交易
+    /// 带有批次ID，有导入内容时同时记录该批次。
+    pub fn import_trades(
+        &mut self,
+        profile: &TradeProfile,
+        trades: &[TradeLine],
+        batch: &ImportBatch,
+    ) -> Result<TradeImport, StorageError> {
+        profile.validate().map_err(StorageError::InvalidData)?;
+        if let Some(cash) = profile.cash_account {
+            match self.store.assets.get(cash) {
+                None => return Err(StorageError::NotFound(cash.to_string())),
+                Some(a) if a.is_virtual() => {
+                    return Err(StorageError::InvalidData(format!(
+                        "{} is a computed asset",
+                        a.name
+                    )))
+                }
+                Some(_) => {}
+            }
+        }
+
+        let existing: HashSet<&str> = self
+            .store
+            .transactions
+            .iter()
+            .filter_map(|t| t.import_hash.as_deref())
+            .collect();
+        let mut result = TradeImport::default();
+        let mut trades: Vec<(&TradeLine, String)> = trades
+            .iter()
+            .zip(trade_hashes(trades))
+            .filter(|(_, hash)| {
+                let duplicate = existing.contains(hash.as_str());
+                result.duplicates += usize::from(duplicate);
+                !duplicate
+            })
+            .collect();
+        trades.sort_by_key(|(t, _)| t.date);
+        for (trade, _) in &trades {
+            self.ensure_period_open(trade.date)?;
+        }
+
+        let mut recorded = Vec::new();
+        for (trade, hash) in trades {
+            let matched = profile
+                .rule_for(&trade.symbol)
+                .or_else(|| find_holding(self.store.assets.iter(), &trade.symbol));
+            let asset_id = match matched {
+                Some(id) => id,
+                None if trade.side == TradeSide::Sell => {
+                    result
+                        .errors
+                        .push(format!("{}: no holding to sell", trade.symbol));
+                    continue;
+                }
+                None => {
+                    let mut asset = new_holding(&trade.symbol, &profile.asset_type);
+                    asset.import_batch = Some(batch.id);
+                    self.insert_asset(&asset)?;
+                    result.created_assets.push(asset.id);
+                    asset.id
+                }
+            };
+            let Some(mut asset) = self.store.assets.get(asset_id).cloned() else {
+                result
+                    .errors
+                    .push(format!("{}: asset {} not found", trade.symbol, asset_id));
+                continue;
+            };
+            if asset.is_virtual() {
+                result.errors.push(format!(
+                    "{}: {} is a computed asset",
+                    trade.symbol, asset.name
+                ));
+                continue;
+            }
+            let held = holding_quantity(&asset).unwrap_or(0.0);
+            if let Err(e) = apply_trade(&mut asset, trade) {
+                result.errors.push(e);
+                continue;
+            }
+            self.ensure_metadata_valid(&asset)?;
+            self.store_mut().assets.update(asset_id, |a| {
+                a.metadata = asset.metadata.clone();
+                a.updated_at = Utc::now();
+            });
+
+            // 成交前按成交价重估已有持仓
+            let revaluation = held * trade.price - asset.value;
+            if held > 0.0 && revaluation.abs() > 1e-9 {
+                recorded.push(self.apply_delta_at(
+                    asset_id,
+                    revaluation,
+                    TransactionType::ValueChange,
+                    Some(format!("按成交价重估 {}", trade.symbol)),
+                    trade.date,
+                )?);
+            }
+
+            let (label, transaction_type, delta) = match trade.side {
+                TradeSide::Buy => ("买入", TransactionType::Buy, trade.amount()),
+                TradeSide::Sell => ("卖出", TransactionType::Sell, -trade.amount()),
+            };
+            let note = format!(
+                "{} {} {} @ {}",
+                label, trade.symbol, trade.quantity, trade.price
+            );
+            let mut record = self.apply_delta_at(
+                asset_id,
+                delta,
+                transaction_type,
+                Some(note.clone()),
+                trade.date,
+            )?;
+            record.import_hash = Some(hash);
+            if let Some(cash) = profile.cash_account {
+                recorded.push(self.apply_delta_at(
+                    cash,
+                    -delta,
+                    TransactionType::Transfer,
+                    Some(note.clone()),
+                    trade.date,
+                )?);
+            }
+            if trade.fee > 0.0 {
+                // 成交金额不在账内时只记下费用，不改变资产价值
+                let (target, fee_delta) = match profile.cash_account {
+                    Some(cash) => (cash, -trade.fee),
+                    None => (asset_id, 0.0),
+                };
+                let mut fee = self.apply_delta_at(
+                    target,
+                    fee_delta,
+                    TransactionType::Expense,
+                    Some(format!("{}（手续费）", note)),
+                    trade.date,
+                )?;
+                if trade.side == TradeSide::Sell {
+                    fee.charge = Some(SaleCharge {
+                        sale_id: record.id,
+                        kind: ChargeKind::Fee,
+                        amount: trade.fee,
+                    });
+                }
+                recorded.push(fee);
+            }
+            recorded.push(record);
+            result.imported += 1;
+            if !result.updated_assets.contains(&asset_id) {
+                result.updated_assets.push(asset_id);
+            }
+        }
+        if recorded.is_empty() {
+            return Ok(result);
+        }
+
+        for mut transaction in recorded {
+            transaction.import_batch = Some(batch.id);
+            self.store_mut().transactions.insert(transaction);
+        }
+        self.store_mut().import_batches.push(batch.clone());
+        result.batch_id = Some(batch.id);
+        self.save()?;
+        Ok(result)
+    }
+
+    // ============ 资产关系 ============
+
+    /// 添加资产关系
+    pub fn add_relation(&mut self, relation: &AssetRelation) -> Result<(), StorageError> {
+        if relation.from_id == relation.to_id {
+            return Err(StorageError::InvalidData(
+                "An asset cannot be related to itself".to_string(),
+            ));
+        }
+        for id in [relation.from_id, relation.to_id] {
+            if !self.store.assets.contains(id) {
+                return Err(StorageError::NotFound(id.to_string()));
+            }
+        }
+        let duplicate = self.store.relations.iter().any(|r| {
+            r.from_id == relation.from_id && r.to_id == relation.to_id && r.kind == relation.kind
+        });
+        if duplicate {
+            return Err(StorageError::InvalidData("Relation already exists".to_string()));
+        }
+
+        self.store_mut().relations.push(relation.clone());
+        self.save()
+    }
+
+    /// 删除资产关系
+    pub fn remove_relation(&mut self, id: Uuid) -> Result<(), StorageError> {
+        let pos = self
+            .store
+            .relations
+            .iter()
+            .position(|r| r.id == id)
+            .ok_or_else(|| StorageError::NotFound(id.to_string()))?;
+
+        self.store_mut().relations.remove(pos);
+        self.save()
+    }
+
+    /// 获取与资产直接关联的资产
+    pub fn get_related_assets(&self, id: Uuid) -> Result<Vec<RelatedAsset>, StorageError> {
+        let related = self
+            .store
+            .relations
+            .iter()
+            .filter_map(|r| {
+                let (other, direction) = if r.from_id == id {
+                    (r.to_id, RelationDirection::Outgoing)
+                } else if r.to_id == id {
+                    (r.from_id, RelationDirection::Incoming)
+                } else {
+                    return None;
+                };
+                let asset = self.store.assets.get(other)?;
+                Some(RelatedAsset {
+                    relation: r.clone(),
+                    direction,
+                    asset: asset.clone(),
+                })
+            })
+            .collect();
+        Ok(related)
+    }
+
+    /// 导出资产关系图
+    pub fn export_relation_graph(&self) -> Result<RelationGraph, StorageError> {
+        let nodes = self
+            .store
+            .assets
+            .iter()
+            .map(|a| GraphNode {
+                id: a.id,
+                name: a.name.clone(),
+                asset_type: a.asset_type.as_str().to_string(),
+                value: a.value,
+            })
+            .collect();
+        let edges = self
+            .store
+            .relations
+            .iter()
+            .map(|r| GraphEdge {
+                id: r.id,
+                source: r.from_id,
+                target: r.to_id,
+                kind: r.kind.clone(),
+            })
+            .collect();
+        Ok(RelationGraph { nodes, edges })
+    }
+
+    // ============ 交易模板 ============
+
+    /// 添加交易模板
+    pub fn add_template(&mut self, template: &TransactionTemplate) -> Result<(), StorageError> {
+        if template.from_asset_id.is_none() && template.to_asset_id.is_none() {
+            return Err(StorageError::InvalidData(
+                "Template needs a source or target asset".to_string(),
+            ));
+        }
+        for id in template
+            .from_asset_id
+            .iter()
+            .chain(template.to_asset_id.iter())
+        {
+            if !self.store.assets.contains(*id) {
+                return Err(StorageError::NotFound(id.to_string()));
+            }
+        }
+
+        self.store_mut().templates.push(template.clone());
+        self.save()
+    }
+
+    /// 获取所有交易模板
+    pub fn list_templates(&self) -> Result<Vec<TransactionTemplate>, StorageError> {
+        let mut templates = self.store.templates.clone();
+        templates.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(templates)
+    }
+
+    /// 删除交易模板
+    pub fn delete_template(&mut self, id: Uuid) -> Result<(), StorageError> {
+        let pos = self
+            .store
+            .templates
+            .iter()
+            .position(|t| t.id == id)
+            .ok_or_else(|| StorageError::NotFound(id.to_string()))?;
+
+        self.store_mut().templates.remove(pos);
+        self.save()
+    }
+
+    /// 应用交易模板，返回生成的交易记录
+    ///
+    /// `amount` 会覆盖模板的默认金额；模板没有默认金额时必须提供。
+    pub fn apply_template(
+        &mut self,
+        id: Uuid,
+        amount: Option<f64>,
+    ) -> Result<Vec<AssetTransaction>, StorageError> {
+        let template = self
+            .store
+            .templates
+            .iter()
+            .find(|t| t.id == id)
+            .cloned()
+            .ok_or_else(|| StorageError::NotFound(id.to_string()))?;
+
+        let amount = amount.or(template.amount).ok_or_else(|| {
+            StorageError::InvalidData(format!("Template '{}' requires an amount", template.name))
+        })?;
+        let note = Some(
+            template
+                .note
+                .clone()
+                .unwrap_or_else(|| template.name.clone()),
+        );
+
+        let mut transactions = Vec::new();
+        match (template.from_asset_id, template.to_asset_id) {
+            (Some(from), Some(to)) => {
+                transactions.push(self.apply_delta(
+                    from,
+                    -amount,
+                    TransactionType::Transfer,
+                    note.clone(),
+                )?);
+                transactions.push(self.apply_delta(to, amount, TransactionType::Transfer, note)?);
+            }
+            (Some(from), None) => {
+                transactions.push(self.apply_delta(
+                    from,
+                    -amount,
+                    TransactionType::Expense,
+                    note,
+                )?);
+            }
+            (None, Some(to)) => {
+                transactions.push(self.apply_delta(to, amount, TransactionType::Income, note)?);
+            }
+            (None, None) => {
+                return Err(StorageError::InvalidData(
+                    "Template needs a source or target asset".to_string(),
+                ));
+            }
+        }
+
+        self.save()?;
+        Ok(transactions)
+    }
+
+    // ============ 复查提醒 ============
+
+    /// 添加提醒
+    pub fn add_reminder(&mut self, reminder: &Reminder) -> Result<(), StorageError> {
+        self.store_mut().reminders.push(reminder.clone());
+        self.save()
+    }
+
+    /// 获取所有提醒（按到期时间排序）
+    pub fn list_reminders(&self) -> Result<Vec<Reminder>, StorageError> {
+        let mut reminders = self.store.reminders.clone();
+        reminders.sort_by_key(|r| r.next_due);
+        Ok(reminders)
+    }
+
+    /// 获取已到期的提醒
+    pub fn get_due_reminders(&self, now: DateTime<Utc>) -> Result<Vec<Reminder>, StorageError> {
+        let mut reminders: Vec<Reminder> = self
+            .store
+            .reminders
+            .iter()
+            .filter(|r| r.is_due(now))
+            .cloned()
+            .collect();
+        reminders.sort_by_key(|r| r.next_due);
+        Ok(reminders)
+    }
+
+    /// 完成提醒，推进到下一个周期
+    pub fn complete_reminder(&mut self, id: Uuid, now: DateTime<Utc>) -> Result<Reminder, StorageError> {
+        let reminder = self.reminder_mut(id)?;
+        reminder.complete(now);
+        let reminder = reminder.clone();
+        self.save()?;
+        Ok(reminder)
+    }
+
+    /// 推迟提醒
+    pub fn snooze_reminder(&mut self, id: Uuid, until: DateTime<Utc>) -> Result<Reminder, StorageError> {
+        let reminder = self.reminder_mut(id)?;
+        reminder.snooze(until);
+        let reminder = reminder.clone();
+        self.save()?;
+        Ok(reminder)
+    }
+
+    /// 删除提醒
+    pub fn delete_reminder(&mut self, id: Uuid) -> Result<(), StorageError> {
+        let pos = self
+            .store
+            .reminders
+            .iter()
+            .position(|r| r.id == id)
+            .ok_or_else(|| StorageError::NotFound(id.to_string()))?;
+
+        self.store_mut().reminders.remove(pos);
+        self.save()
+    }
+
+    /// 为新到期的提醒生成通知，返回本次新增的通知
+    pub fn check_reminders(&mut self, now: DateTime<Utc>) -> Result<Vec<Notification>, StorageError> {
+        let mut created = Vec::new();
+
+        for reminder in self.store_mut().reminders.iter_mut() {
+            if !reminder.is_due(now) || reminder.notified_due == Some(reminder.next_due) {
+                continue;
+            }
+            reminder.notified_due = Some(reminder.next_due);
+            created.push(
+                Notification::new(NotificationKind::Reminder, reminder.title.clone())
+                    .with_asset(reminder.asset_id)
+                    .with_source(reminder.id),
+            );
+        }
+
+        if !created.is_empty() {
+            self.store_mut()
+                .notifications
+                .extend(created.iter().cloned());
+            self.save()?;
+        }
+        Ok(created)
+    }
+
+    /// 为新达到目标价的资产生成通知，返回本次新增的通知
+    ///
+    /// 同一次达到目标只通知一次；价值回落到目标之外后会重新计入。
+    pub fn check_targets(&mut self) -> Result<Vec<Notification>, StorageError> {
+        let mut created = Vec::new();
+        let changed: Vec<Uuid> = self
+            .store
+            .assets
+            .iter()
+            .filter(|a| !a.is_virtual() && a.target_reached() != a.target_notified)
+            .map(|a| a.id)
+            .collect();
+
+        for &id in &changed {
+            let Some(asset) = self.store_mut().assets.update(id, |asset| {
+                asset.target_notified = !asset.target_notified;
+                asset.clone()
+            }) else {
+                continue;
+            };
+            if !asset.target_notified {
+                continue;
+            }
+            let perf = AssetPerformance::new(&asset);
+            let mut notification = Notification::new(
+                NotificationKind::TargetReached,
+                format!("{} 已达到目标价", asset.name),
+            )
+            .with_asset(Some(asset.id));
+            if let (Some(target), Some(gain)) = (perf.target_price, perf.unrealized_gain) {
+                notification = notification.with_message(format!(
+                    "当前价值 {:.2}，目标价 {:.2}，浮动盈亏 {:.2}",
+                    asset.value, target, gain
+                ));
+            }
+            created.push(notification);
+        }
+
+        if !changed.is_empty() {
+            self.store_mut()
+                .notifications
+                .extend(created.iter().cloned());
+            self.save()?;
+        }
+        Ok(created)
+    }
+
+    // ============ 里程碑 ============
+
+    /// 获取已达成的里程碑（按达成日期）
+    pub fn list_milestones(&self) -> Result<Vec<Milestone>, StorageError> {
+        let mut milestones = self.store.milestones.clone();
+        milestones.sort_by_key(|m| m.achieved_on);
+        Ok(milestones)
+    }
+
+    /// 从每日净资产历史中检测新达成的里程碑，返回本次新增的通知
+    pub fn check_milestones(
+        &mut self,
+        now: DateTime<Utc>,
+    ) -> Result<Vec<Notification>, StorageError> {
+        let settings = self.get_fx_settings()?;
+        let history = self.net_worth_history(&settings);
+        let found = detect_milestones(
+            &history,
+            &self.store.milestones,
+            settings.base_currency.code(),
+        );
+        if found.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let recent = now.date_naive() - chrono::Duration::days(NOTIFY_WITHIN_DAYS);
+        let created: Vec<Notification> = found
+            .iter()
+            .filter(|m| m.achieved_on >= recent)
+            .map(|m| {
+                Notification::new(NotificationKind::Milestone, m.title.clone())
+                    .with_message(format!(
+                        "达成日期 {}，当日净资产 {:.2} {}",
+                        m.achieved_on,
+                        m.net_worth,
+                        settings.base_currency.code()
+                    ))
+                    .with_source(m.id)
+            })
+            .collect();
+        self.store_mut().milestones.extend(found);
+        self.store_mut()
+            .notifications
+            .extend(created.iter().cloned());
+        self.save()?;
+        Ok(created)
+    }
+
+    /// 有资产发生变化的各日的净资产与负债（本位币）
+    fn net_worth_history(&self, settings: &FxSettings) -> Vec<NetWorthDay> {
+        let assets: Vec<&Asset> = self
+            .store
+            .assets
+            .iter()
+            .filter(|a| !a.is_virtual())
+            .collect();
+        self.daily_balances
+            .change_dates()
+            .into_iter()
+            .map(|date| {
+                let mut day = NetWorthDay {
+                    date,
+                    net_worth: 0.0,
+                    debt: 0.0,
+                };
+                for asset in &assets {
+                    let value = self.daily_balances.value_on(asset.id, date);
+                    let rate = self.rate_on(settings, &asset.currency, date);
+                    if let (Some(value), Some(rate)) = (value, rate) {
+                        day.net_worth += value * rate;
+                        day.debt += (-value * rate).max(0.0);
+                    }
+                }
+                day
+            })
+            .collect()
+    }
+
+    // ============ 财务日记 ============
+
+    /// 保存日记条目（同ID的条目已存在时替换），`capture_snapshot` 为真时附带当前的资产快照
+    pub fn save_check_in(
+        &mut self,
+        entry: &CheckIn,
+        capture_snapshot: bool,
+    ) -> Result<CheckIn, StorageError> {
+        entry.validate().map_err(StorageError::InvalidData)?;
+        let mut entry = entry.clone();
+        if capture_snapshot {
+            entry.snapshot = Some(CheckInSnapshot::from_summary(&self.get_summary()?));
+        }
+        let check_ins = &mut self.store_mut().check_ins;
+        match check_ins.iter_mut().find(|c| c.id == entry.id) {
+            Some(existing) => {
+                entry.created_at = existing.created_at;
+                entry.updated_at = Utc::now();
+                *existing = entry.clone();
+            }
+            None => check_ins.push(entry.clone()),
+        }
+        self.save()?;
+        Ok(entry)
+    }
+
+    /// 获取日期范围内的日记条目（按日期倒序）
+    pub fn list_check_ins(
+        &self,
+        start: Option<NaiveDate>,
+        end: Option<NaiveDate>,
+    ) -> Result<Vec<CheckIn>, StorageError> {
+        let mut entries: Vec<CheckIn> = self
+            .store
+            .check_ins
+            .iter()
+            .filter(|c| start.is_none_or(|s| c.date >= s) && end.is_none_or(|e| c.date <= e))
+            .cloned()
+            .collect();
+        entries.sort_by_key(|c| std::cmp::Reverse((c.date, c.created_at)));
+        Ok(entries)
+    }
+
+    /// 删除日记条目
+    pub fn delete_check_in(&mut self, id: Uuid) -> Result<(), StorageError> {
+        let pos = self
+            .store
+            .check_ins
+            .iter()
+            .position(|c| c.id == id)
+            .ok_or_else(|| StorageError::NotFound(id.to_string()))?;
+
+        self.store_mut().check_ins.remove(pos);
+        self.save()
+    }
+
+    // ============ 附件 ============
+
+    /// 为资产添加附件：文件内容复制到附件目录 `dir`，返回附件记录
+    pub fn add_attachment(
+        &mut self,
+        dir: impl AsRef<Path>,
+        asset_id: Uuid,
+        source: impl AsRef<Path>,
+        note: Option<String>,
+    ) -> Result<Attachment, StorageError> {
+        self.check_writable()?;
+        if !self.store.assets.contains(asset_id) {
+            return Err(StorageError::NotFound(asset_id.to_string()));
+        }
+        let source = source.as_ref();
+        let content = attachment::read_source(source)?;
+        let file_name = source
+            .file_name()
+            .map_or_else(|| "attachment".into(), |n| n.to_string_lossy().into_owned());
+        let attachment = Attachment::new(asset_id, file_name, &content).with_note(note);
+        attachment::store_content(dir.as_ref(), &attachment.hash, &content)?;
+        self.store_mut().attachments.push(attachment.clone());
+        self.save()?;
+        Ok(attachment)
+    }
+
+    /// 资产的附件，按添加时间排序
+    pub fn list_attachments(&self, asset_id: Uuid) -> Result<Vec<Attachment>, StorageError> {
+        let mut attachments: Vec<Attachment> = self
+            .store
+            .attachments
+            .iter()
+            .filter(|a| a.asset_id == asset_id)
+            .cloned()
+            .collect();
+        attachments.sort_by_key(|a| a.created_at);
+        Ok(attachments)
+    }
+
+    /// 删除附件，文件内容不再被其他附件引用时一并删除
+    pub fn remove_attachment(
+        &mut self,
+        dir: impl AsRef<Path>,
+        id: Uuid,
+    ) -> Result<Attachment, StorageError> {
+        let pos = self
+            .store
+            .attachments
+            .iter()
+            .position(|a| a.id == id)
+            .ok_or_else(|| StorageError::NotFound(id.to_string()))?;
+        let removed = self.store_mut().attachments.remove(pos);
+        self.save()?;
+        if !self.attachment_hashes().contains(&removed.hash.as_str()) {
+            let path = removed.path(dir.as_ref())?;
+            if let Err(e) = fs::remove_file(&path) {
+                warn!("Failed to remove attachment file {:?}: {}", path, e);
+            }
+        }
+        Ok(removed)
+    }
+
+    /// 删除附件目录中不再被引用的文件（永久删除资产后），返回删除的数量
+    pub fn prune_attachment_files(&self, dir: impl AsRef<Path>) -> Result<usize, StorageError> {
+        if self.is_read_only() {
+            return Ok(0);
+        }
+        attachment::remove_unused(dir.as_ref(), &self.attachment_hashes())
+    }
+
+    /// 附件（包括回收站中资产的附件）引用的文件内容指纹
+    fn attachment_hashes(&self) -> Vec<&str> {
+        self.store
+            .attachments
+            .iter()
+            .chain(self.store.trash.iter().flat_map(|t| &t.attachments))
+            .map(|a| a.hash.as_str())
+            .collect()
+    }
+
+    // ============ 时间线 ============
+
+    /// 时间范围内的交易、价值变动、日记、通知、导入与里程碑，按时间倒序合并
+    pub fn get_timeline(
+        &self,
+        range: &DateRange,
+        filter: &TimelineFilter,
+    ) -> Result<Vec<TimelineEvent>, StorageError> {
+        let mut events = Vec::new();
+
+        let transactions = filter.includes(TimelineKind::Transaction)
+            || filter.includes(TimelineKind::ValueChange);
+        if transactions {
+            let txns = self
+                .store
+                .transactions
+                .newest_first(range.start..=range.end)
+                .filter(|t| !t.scheduled);
+            for txn in txns {
+                let kind = match txn.transaction_type {
+                    TransactionType::ValueChange => TimelineKind::ValueChange,
+                    _ => TimelineKind::Transaction,
+                };
+                let title = self
+                    .store
+                    .assets
+                    .get(txn.asset_id)
+                    .map_or_else(|| txn.asset_id.to_string(), |a| a.name.clone());
+                events.push(TimelineEvent {
+                    kind,
+                    timestamp: txn.timestamp,
+                    title,
+                    detail: txn.note.clone(),
+                    amount: Some(txn.delta()),
+                    asset_id: Some(txn.asset_id),
+                    source_id: txn.id,
+                });
+            }
+        }
+
+        for entry in &self.store.check_ins {
+            events.push(TimelineEvent {
+                kind: TimelineKind::CheckIn,
+                timestamp: start_of_day(entry.date),
+                title: "财务日记".to_string(),
+                detail: Some(entry.notes.clone()).filter(|n| !n.trim().is_empty()),
+                amount: entry.snapshot.as_ref().map(|s| s.total_value),
+                asset_id: None,
+                source_id: entry.id,
+            });
+        }
+
+        // 里程碑的通知不重复列出
+        let alerts = self
+            .store
+            .notifications
+            .iter()
+            .filter(|n| n.kind != NotificationKind::Milestone);
+        for notification in alerts {
+            events.push(TimelineEvent {
+                kind: TimelineKind::Alert,
+                timestamp: notification.created_at,
+                title: notification.title.clone(),
+                detail: notification.message.clone(),
+                amount: None,
+                asset_id: notification.asset_id,
+                source_id: notification.id,
+            });
+        }
+
+        for batch in &self.store.import_batches {
+            let source = match batch.source {
+                ImportSource::Statement => "对账单",
+                ImportSource::GnuCash => "GnuCash",
+                ImportSource::Plugin => "插件",
+                ImportSource::Trades => "成交单",
+            };
+            let count = self
+                .store
+                .transactions
+                .iter()
+                .filter(|t| t.import_batch == Some(batch.id))
+                .count();
+            events.push(TimelineEvent {
+                kind: TimelineKind::Import,
+                timestamp: batch.created_at,
+                title: format!("导入{}", batch.file_name.as_deref().unwrap_or(source)),
+                detail: Some(format!("{} 笔交易", count)),
+                amount: None,
+                asset_id: None,
+                source_id: batch.id,
+            });
+        }
+
+        for milestone in &self.store.milestones {
+            events.push(TimelineEvent {
+                kind: TimelineKind::Milestone,
+                timestamp: start_of_day(milestone.achieved_on),
+                title: milestone.title.clone(),
+                detail: None,
+                amount: Some(milestone.net_worth),
+                asset_id: None,
+                source_id: milestone.id,
+            });
+        }
+
+        events.retain(|e| range.contains(e.timestamp) && filter.accepts(e));
+        events.sort_by_key(|event| Reverse(event.timestamp));
+        if let Some(limit) = filter.limit {
+            events.truncate(limit);
+        }
+        Ok(events)
+    }
+
+    fn reminder_mut(&mut self, id: Uuid) -> Result<&mut Reminder, StorageError> {
+        self.store_mut()
+            .reminders
+            .iter_mut()
+            .find(|r| r.id == id)
+            .ok_or_else(|| StorageError::NotFound(id.to_string()))
+    }
+
+    // ============ 通知 ============
+
+    /// 添加通知
+    pub fn add_notification(&mut self, notification: &Notification) -> Result<(), StorageError> {
+        self.store_mut().notifications.push(notification.clone());
+        self.save()
+    }
+
+    /// 获取通知（最新的在前）
+    pub fn list_notifications(&self, unread_only: bool) -> Result<Vec<Notification>, StorageError> {
+        let mut notifications: Vec<Notification> = self
+            .store
+            .notifications
+            .iter()
+            .filter(|n| !unread_only || !n.read)
+            .cloned()
+            .collect();
+        notifications.sort_by_key(|n| std::cmp::Reverse(n.created_at));
+        Ok(notifications)
+    }
+
+    /// 标记通知为已读
+    pub fn mark_notification_read(&mut self, id: Uuid) -> Result<(), StorageError> {
+        let notification = self
+            .store_mut()
+            .notifications
+            .iter_mut()
+            .find(|n| n.id == id)
+            .ok_or_else(|| StorageError::NotFound(id.to_string()))?;
+
+        notification.read = true;
+        self.save()
+    }
+
+    // ============ 显示样式 ============
+
+    /// 获取资产类型、分类与标签的样式
+    pub fn get_display_catalog(&self) -> Result<DisplayCatalog, StorageError> {
+        Ok(self.store.display.clone())
+    }
+
+    /// 设置样式，样式为空时清除
+    pub fn set_display(
+        &mut self,
+        target: &DisplayTarget,
+        meta: &DisplayMeta,
+    ) -> Result<(), StorageError> {
+        meta.validate().map_err(StorageError::InvalidData)?;
+        let put = |styles: &mut BTreeMap<String, DisplayMeta>, key: String| {
+            if meta.is_empty() {
+                styles.remove(&key);
+            } else {
+                styles.insert(key, meta.clone());
+            }
+        };
+        match target {
+            DisplayTarget::AssetType { code } => {
+                let key = AssetType::parse(code).code().to_string();
+                put(&mut self.store_mut().display.asset_types, key);
+            }
+            DisplayTarget::Category { category } => {
+                put(
+                    &mut self.store_mut().display.categories,
+                    category_key(category),
+                );
+            }
+            DisplayTarget::Tag { tag } => {
+                // 样式按规范化后的标签保存
+                let key = self
+                    .normalize_tag(tag)?
+                    .ok_or_else(|| StorageError::InvalidData("Tag is required".to_string()))?;
+                put(&mut self.store_mut().display.tags, key);
+            }
+            DisplayTarget::Asset { id } => {
+                let mut asset = self
+                    .store
+                    .assets
+                    .get(*id)
+                    .cloned()
+                    .ok_or_else(|| StorageError::NotFound(id.to_string()))?;
+                asset.display = meta.clone();
+                asset.updated_at = Utc::now();
+                self.store_mut().assets.insert(asset);
+            }
+        }
+        self.save()
+    }
+
+    /// 资产最终显示的样式（自身、标签、资产类型依次补全）
+    pub fn resolve_display(&self, asset: &Asset) -> DisplayMeta {
+        self.store.display.resolve(asset)
+    }
+
+    // ============ 自动化规则 ============
+
+    /// 保存规则（同ID的规则已存在时替换）
+    pub fn save_rule(&mut self, rule: &Rule) -> Result<(), StorageError> {
+        rule.validate().map_err(StorageError::InvalidData)?;
+        let rules = &mut self.store_mut().rules;
+        match rules.iter_mut().find(|r| r.id == rule.id) {
+            Some(existing) => *existing = rule.clone(),
+            None => rules.push(rule.clone()),
+        }
+        self.save()
+    }
+
+    /// 获取所有规则
+    pub fn list_rules(&self) -> Result<Vec<Rule>, StorageError> {
+        Ok(self.store.rules.clone())
+    }
+
+    /// 删除规则
+    pub fn delete_rule(&mut self, id: Uuid) -> Result<(), StorageError> {
+        let pos = self
+            .store
+            .rules
+            .iter()
+            .position(|r| r.id == id)
+            .ok_or_else(|| StorageError::NotFound(id.to_string()))?;
+
+        self.store_mut().rules.remove(pos);
+        self.save()
+    }
+
+    /// 按信号执行启用的规则，返回本次的执行记录（其中的插件调用由调用方执行）
+    ///
+    /// 单条规则的动作失败时记录在执行结果与规则的 `last_error` 中，不影响其他规则。
+    pub fn run_rules(
+        &mut self,
+        signal: RuleSignal,
+        now: DateTime<Utc>,
+    ) -> Result<Vec<RuleRun>, StorageError> {
+        let net_worth = self.get_summary()?.total_value;
+        let mut runs = Vec::new();
+        let mut changed = false;
+
+        for index in 0..self.store.rules.len() {
+            let mut rule = self.store.rules[index].clone();
+            if !rule.enabled {
+                continue;
+            }
+            let trigger = rule.trigger.clone();
+            let targets = match (&trigger, signal) {
+                (RuleTrigger::Event { event }, RuleSignal::Event { event: fired, asset_id })
+                    if *event == fired =>
+                {
+                    let ctx = RuleContext {
+                        asset: self.store.assets.get(asset_id),
+                        net_worth,
+                    };
+                    if rule.matches(&ctx) {
+                        vec![Some(asset_id)]
+                    } else {
+                        Vec::new()
+                    }
+                }
+                (RuleTrigger::Schedule { .. }, RuleSignal::Tick) if rule.is_due(now) => {
+                    rule.advance_schedule(now);
+                    self.rule_subjects(&rule, net_worth)
+                }
+                (RuleTrigger::Condition, RuleSignal::Tick) => {
+                    let matched = self.rule_subjects(&rule, net_worth);
+                    let newly: Vec<Option<Uuid>> = matched
+                        .iter()
+                        .filter(|s| !rule.matched.contains(s))
+                        .copied()
+                        .collect();
+                    if matched == rule.matched {
+                        continue;
+                    }
+                    rule.matched = matched;
+                    newly
+                }
+                _ => continue,
+            };
+
+            for target in targets {
+                let run = self.execute_rule(&rule, target);
+                rule.last_run = Some(now);
+                rule.last_error = run.error.clone();
+                runs.push(run);
+            }
+            self.store_mut().rules[index] = rule;
+            changed = true;
+        }
+
+        if changed {
+            self.save()?;
+        }
+        Ok(runs)
+    }
+
+    /// 满足规则条件的资产；规则只涉及总资产时整体求值（以空值表示）
+    fn rule_subjects(&self, rule: &Rule, net_worth: f64) -> Vec<Option<Uuid>> {
+        if !rule.per_asset() {
+            let ctx = RuleContext {
+                asset: None,
+                net_worth,
+            };
+            return if rule.matches(&ctx) { vec![None] } else { Vec::new() };
+        }
+        self.store
+            .assets
+            .iter()
+            .filter(|asset| {
+                rule.matches(&RuleContext {
+                    asset: Some(asset),
+                    net_worth,
+                })
+            })
+            .map(|asset| Some(asset.id))
+            .collect()
+    }
+
+    /// 对资产依次执行规则的动作，遇到错误时停止
+    fn execute_rule(&mut self, rule: &Rule, target: Option<Uuid>) -> RuleRun {
+        let mut run = RuleRun {
+            rule_id: rule.id,
+            asset_id: target,
+            plugin_calls: Vec::new(),
+            error: None,
+        };
+        let actor = std::mem::replace(&mut self.actor, format!("rule:{}", rule.name));
+        for action in &rule.actions {
+            if let Err(e) = self.apply_rule_action(rule, action, target, &mut run.plugin_calls) {
+                warn!("Rule {} failed: {}", rule.name, e);
+                run.error = Some(e.to_string());
+                break;
+            }
+        }
+        self.actor = actor;
+        run
+    }
+
+    fn apply_rule_action(
+        &mut self,
+        rule: &Rule,
+        action: &RuleAction,
+        target: Option<Uuid>,
+        plugin_calls: &mut Vec<PluginCall>,
+    ) -> Result<(), StorageError> {
+        let asset = target.and_then(|id| self.store.assets.get(id)).cloned();
+        let needs_asset =
+            || StorageError::InvalidData(format!("Rule {} has no asset to act on", rule.name));
+        match action {
+            RuleAction::AddTag { tag } => {
+                let mut asset = asset.ok_or_else(needs_asset)?;
+                if !asset.tags.contains(tag) {
+                    asset.tags.push(tag.clone());
+                    self.update_asset(&asset)?;
+                }
+            }
+            RuleAction::CreateTransaction {
+                asset_id,
+                delta,
+                transaction_type,
+                note,
+            } => {
+                let id = asset_id
+                    .or(asset.as_ref().map(|a| a.id))
+                    .ok_or_else(needs_asset)?;
+                let note = note
+                    .as_deref()
+                    .map_or_else(|| rule.name.clone(), |n| render_text(n, asset.as_ref()));
+                self.adjust_asset_value(id, *delta, transaction_type.clone(), Some(note))?;
+            }
+            RuleAction::Notify { title, message } => {
+                let mut notification =
+                    Notification::new(NotificationKind::Rule, render_text(title, asset.as_ref()))
+                        .with_asset(target)
+                        .with_source(rule.id);
+                if let Some(message) = message {
+                    notification = notification.with_message(render_text(message, asset.as_ref()));
+                }
+                self.add_notification(&notification)?;
+            }
+            RuleAction::CallPlugin {
+                plugin,
+                handler,
+                args,
+            } => {
+                let mut args = args.clone();
+                if let Some(asset) = &asset {
+                    if args.is_null() {
+                        args = serde_json::json!({});
+                    }
+                    if let serde_json::Value::Object(map) = &mut args {
+                        map.insert("asset".to_string(), serde_json::to_value(asset)?);
+                    }
+                }
+                plugin_calls.push(PluginCall {
+                    plugin: plugin.clone(),
+                    handler: handler.clone(),
+                    args,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    // ============ 便携存档 ============
+
+    /// 导出资产、交易与设置（不含回收站与凭据）
+    pub fn to_archive(&self) -> Result<Archive, StorageError> {
+        Ok(Archive::new(
+            self.store.assets.iter().cloned().collect(),
+            self.store.transactions.iter().cloned().collect(),
+            self.store
+                .settings
+                .iter()
+                .map(|(key, raw)| (key.clone(), raw.clone()))
+                .collect(),
+        ))
+    }
+
+    /// 导入存档中的资产、交易与设置，派生数据随后重新计算
+    ///
+    /// 替换模式先永久删除现有资产（连同关联记录）与存档中没有的设置项；合并模式跳过ID已存在的
+    /// 资产与交易以及已有的设置项。所属资产不存在的交易同样跳过，未通过校验的记录跳过并列出原因。
+    pub fn apply_archive(
+        &mut self,
+        archive: &Archive,
+        mode: ArchiveMode,
+    ) -> Result<ArchiveSummary, StorageError> {
+        // 先完成全部校验再修改：只检查确实会写入的交易，替换模式下还要检查将删除的交易
+        let replace = mode == ArchiveMode::Replace;
+        let archived: HashSet<Uuid> = archive.assets.iter().map(|a| a.id).collect();
+        for txn in &archive.transactions {
+            let exists = !replace && self.store.transactions.contains(txn.id);
+            let owned = archived.contains(&txn.asset_id)
+                || (!replace && self.store.assets.contains(txn.asset_id));
+            if !exists && owned {
+                self.ensure_period_open(txn.timestamp)?;
+            }
+        }
+        if replace {
+            for txn in self.store.transactions.iter() {
+                if self.store.assets.contains(txn.asset_id) {
+                    self.ensure_period_open(txn.timestamp)?;
+                }
+            }
+            let ids: Vec<Uuid> = self.store.assets.iter().map(|a| a.id).collect();
+            for id in ids {
+                self.detach_asset(id)?;
+            }
+            let removed: Vec<String> = self
+                .store
+                .settings
+                .keys()
+                .filter(|key| !archive.settings.contains_key(*key))
+                .cloned()
+                .collect();
+            for key in removed {
+                self.store_mut().settings.remove(&key);
+                self.subscribers.notify(&key, None);
+            }
+        }
+
+        let mut summary = ArchiveSummary::default();
+        let mut imported = Vec::new();
+        let mut touched = HashSet::new();
+        for asset in &archive.assets {
+            if self.store.assets.contains(asset.id) {
+                summary.skipped += 1;
+                continue;
+            }
+            match self.insert_asset(asset) {
+                Ok(()) => imported.push(asset.id),
+                Err(e) => summary.errors.push(format!("{}: {}", asset.id, e)),
+            }
+        }
+        for txn in &archive.transactions {
+            if self.store.transactions.contains(txn.id) || !self.store.assets.contains(txn.asset_id)
+            {
+                summary.skipped += 1;
+                continue;
+            }
+            match self.insert_transaction(txn) {
+                Ok(()) => {
+                    touched.insert(txn.asset_id);
+                    summary.transactions += 1;
+                }
+                Err(e) => summary.errors.push(format!("{}: {}", txn.id, e)),
+            }
+        }
+        for (key, raw) in &archive.settings {
+            if mode == ArchiveMode::Merge && self.store.settings.contains_key(key) {
+                continue;
+            }
+            self.put_setting(key, raw.clone());
+            summary.settings += 1;
+        }
+
+        summary.assets = imported.len();
+        touched.extend(imported);
+        self.refresh_daily_balances(touched);
+        self.save_settings = self.get_save_settings()?;
+        self.save()?;
+        Ok(summary)
+    }
+
+    // ============ 合并数据库 ============
+
+    /// 合并另一个数据库的资产与交易，冲突按策略处理，派生数据随后重新计算
+    ///
+    /// 当前数据库中没有的资产直接加入（已在回收站中的跳过）；双方内容不同的资产按策略保留一方、
+    /// 两者都保留，或在逐个选择时列为冲突保持不变。交易按ID加入当前数据库中没有的，
+    /// 所属资产不存在的跳过。写入的记录与新建时一样校验（如外部标识唯一），未通过的跳过并列出原因。
+    pub fn merge_from(
+        &mut self,
+        other: &Database,
+        strategy: MergeStrategy,
+    ) -> Result<MergeReport, StorageError> {
+        for txn in other.store.transactions.iter() {
+            if !self.store.transactions.contains(txn.id) {
+                self.ensure_period_open(txn.timestamp)?;
+            }
+        }
+
+        let mut report = MergeReport::default();
+        let mut changed = Vec::new();
+        // 两个版本都保留的资产：另一份中的新交易记到副本上
+        let mut copies = HashMap::new();
+        for theirs in other.store.assets.iter() {
+            let Some(mine) = self.store.assets.get(theirs.id) else {
+                if self.store.trash.iter().any(|t| t.asset.id == theirs.id) {
+                    report.skipped += 1;
+                    continue;
+                }
+                match self.insert_asset(theirs) {
+                    Ok(()) => {
+                        changed.push(theirs.id);
+                        report.added += 1;
+                    }
+                    Err(e) => report.errors.push(format!("{}: {}", theirs.id, e)),
+                }
+                continue;
+            };
+            let Some(conflict) = MergeConflict::compare(mine, theirs)? else {
+                continue;
+            };
+            match strategy.choice(&conflict) {
+                None => report.conflicts.push(conflict),
+                Some(MergeChoice::KeepMine) => {}
+                Some(MergeChoice::KeepTheirs) => match self.put_asset(theirs) {
+                    Ok(()) => {
+                        changed.push(theirs.id);
+                        report.updated += 1;
+                    }
+                    Err(e) => report.errors.push(format!("{}: {}", theirs.id, e)),
+                },
+                Some(MergeChoice::KeepBoth) => {
+                    let copy = conflict.theirs_copy();
+                    copies.insert(theirs.id, copy.id);
+                    let exists = self.store.assets.contains(copy.id)
+                        || self.store.trash.iter().any(|t| t.asset.id == copy.id);
+                    if exists {
+                        continue;
+                    }
+                    match self.insert_asset(&copy) {
+                        Ok(()) => {
+                            changed.push(copy.id);
+                            report.duplicated += 1;
+                        }
+                        Err(e) => report.errors.push(format!("{}: {}", copy.id, e)),
+                    }
+                }
+            }
+        }
+
+        let mut touched: HashSet<Uuid> = changed.iter().copied().collect();
+        for txn in other.store.transactions.iter() {
+            if self.store.transactions.contains(txn.id) {
+                continue;
+            }
+            let mut txn = txn.clone();
+            if let Some(&copy) = copies.get(&txn.asset_id) {
+                txn.asset_id = copy;
+            }
+            if !self.store.assets.contains(txn.asset_id) {
+                report.skipped += 1;
+                continue;
+            }
+            match self.insert_transaction(&txn) {
+                Ok(()) => {
+                    touched.insert(txn.asset_id);
+                    report.transactions += 1;
+                }
+                Err(e) => report.errors.push(format!("{}: {}", txn.id, e)),
+            }
+        }
+
+        self.refresh_daily_balances(touched);
+        self.save()?;
+        Ok(report)
+    }
+
+    // ============ 维护 ============
+
+    /// 从资产与交易等原始记录重建派生数据：表索引（合并同ID的重复记录）、每日余额，
+    /// 以及复式账簿中与资产价值对齐的余额，并重写主文件。用于修复异常或手动编辑数据文件之后。
+    pub fn rebuild_derived_data(&mut self) -> Result<RebuildReport, StorageError> {
+        let store = self.store_mut();
+        let duplicates_removed =
+            store.assets.rebuild_indexes() + store.transactions.rebuild_indexes();
+        let mut ledger_adjustments = 0;
+        if let Some(ledger) = store.ledger.as_mut() {
+            let before = ledger.entries.len();
+            for asset in store.assets.iter() {
+                ledger.sync_asset(asset);
+            }
+            ledger_adjustments = ledger.entries.len() - before;
+        }
+        self.daily_balances = Arc::new(DailyBalances::build(
+            &self.store.assets,
+            &self.store.transactions,
+        ));
+        info!(
+            "Rebuilt derived data: {} duplicates removed, {} ledger adjustments",
+            duplicates_removed, ledger_adjustments
+        );
+
+        // 下次保存时重写主文件
+        self.fingerprint = None;
+        self.save()?;
+        Ok(RebuildReport {
+            assets: self.store.assets.len(),
+            transactions: self.store.transactions.len(),
+            duplicates_removed,
+            ledger_adjustments,
+        })
+    }
+
+    /// 压缩数据文件：去掉所属资产已不存在的记录，把日志并入主文件并重写，返回压缩前后的大小
+    pub fn compact(&mut self) -> Result<CompactReport, StorageError> {
+        self.check_writable()?;
+        let size_before = self.file_size()?;
+        let store = self.store_mut();
+        let assets = &store.assets;
+        let records_removed = store
+            .valuations
+            .remove_where(|v| !assets.contains(v.asset_id))
+            .len()
+            + take_where(&mut store.attachments, |a| !assets.contains(a.asset_id)).len();
+
+        // 重写主文件并清空日志
+        self.fingerprint = None;
+        self.save_now()?;
+        let size_after = self.file_size()?;
+        info!(
+            "Compacted {:?}: {} -> {} bytes, {} records removed",
+            self.path, size_before, size_after, records_removed
+        );
+        Ok(CompactReport {
+            size_before,
+            size_after,
+            records_removed,
+        })
+    }
+
+    /// 主文件与日志的大小（内存数据库为 0）
+    fn file_size(&self) -> Result<u64, StorageError> {
+        let Some(path) = &self.path else {
+            return Ok(0);
+        };
+        let mut size = 0;
+        for path in [path.as_path(), Journal::for_store(path).path()] {
+            match fs::metadata(path) {
+                Ok(meta) => size += meta.len(),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(size)
+    }
+}
+
+/// 关闭时写入未保存的修改（手动保存模式除外）
+impl Drop for Database {
+    fn drop(&mut self) {
+        if self.journal.is_none() || self.save_settings.mode == SaveMode::Manual {
+            return;
+        }
+        if let Err(e) = self.flush() {
+            warn!("Failed to save {:?} on close: {}", self.path, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asset::{Asset, AssetType};
+    use crate::checkin::{CheckInPeriod, Mood};
+    use crate::storage::AuditAction;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_json_database_operations() {
+        let mut db = Database::open_in_memory().unwrap();
+
+        // 创建资产
+        let asset = Asset::new("测试股票", AssetType::Stock, 10000.0);
+        db.create_asset(&asset).unwrap();
+
+        // 获取资产
+        let loaded = db.get_asset(asset.id).unwrap().unwrap();
+        assert_eq!(loaded.name, "测试股票");
+        assert_eq!(loaded.value, 10000.0);
+
+        // 列出资产
+        let assets = db
+            .list_assets(SortBy::default(), SortOrder::default())
+            .unwrap();
+        assert_eq!(assets.len(), 1);
 
         // 获取摘要
         let summary = db.get_summary().unwrap();
-        assert_eq!(summary.total_value, 10000.0);
-        assert_eq!(summary.asset_count, 1);
+        assert_eq!(summary.total_value, 10000.0);
+        assert_eq!(summary.asset_count, 1);
+
+        // 搜索
+        let results = db.search_assets("股票").unwrap();
+        assert_eq!(results.len(), 1);
+
+        let results = db.search_assets("不存在").unwrap();
+        assert_eq!(results.len(), 0);
+
+        // 更新
+        let mut updated = loaded.clone();
+        updated.update_value(20000.0);
+        db.update_asset(&updated).unwrap();
+        let reloaded = db.get_asset(asset.id).unwrap().unwrap();
+        assert_eq!(reloaded.value, 20000.0);
+
+        // 删除
+        db.delete_asset(asset.id).unwrap();
+        let deleted = db.get_asset(asset.id).unwrap();
+        assert!(deleted.is_none());
+    }
+
+    #[test]
+    fn test_search_pinyin() {
+        let mut db = Database::open_in_memory().unwrap();
+
+        // 拼音与模糊匹配可在设置中关闭
+        let bank = Asset::new("招商银行", AssetType::Cash, 500.0);
+        db.create_asset(&bank).unwrap();
+        assert_eq!(db.search_assets("zsyh").unwrap().len(), 1);
+        db.set_search_settings(&SearchSettings {
+            fuzzy: false,
+            pinyin: false,
+        })
+        .unwrap();
+        assert!(db.search_assets("zsyh").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_list_assets_sorted() {
+        let mut db = Database::open_in_memory().unwrap();
+        let now = Utc::now();
+        for (i, (name, asset_type, value)) in [
+            ("b", AssetType::Stock, 300.0),
+            ("c", AssetType::Cash, 100.0),
+            ("a", AssetType::Fund, 200.0),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let mut asset = Asset::new(name, asset_type, value);
+            asset.created_at = now - chrono::Duration::days(3 - i as i64);
+            db.create_asset(&asset).unwrap();
+        }
+
+        let names = |sort_by, order| -> Vec<String> {
+            db.list_assets(sort_by, order)
+                .unwrap()
+                .into_iter()
+                .map(|a| a.name)
+                .collect()
+        };
+        assert_eq!(names(SortBy::CreatedAt, SortOrder::Desc), ["a", "c", "b"]);
+        assert_eq!(names(SortBy::Value, SortOrder::Desc), ["b", "a", "c"]);
+        assert_eq!(names(SortBy::Name, SortOrder::Asc), ["a", "b", "c"]);
+        assert_eq!(names(SortBy::Type, SortOrder::Asc), ["c", "a", "b"]);
+        // 逐个读取的顺序与默认排序一致
+        let streamed: Vec<String> = db.iter_assets().unwrap().map(|a| a.name).collect();
+        assert_eq!(streamed, ["a", "c", "b"]);
+    }
+
+    #[test]
+    fn test_search_assets_ranked() {
+        let mut db = Database::open_in_memory().unwrap();
+        let described =
+            Asset::new("工资卡", AssetType::BankDeposit, 100.0).with_description("招商银行储蓄卡");
+        let tagged = Asset::new("信用卡", AssetType::Other("credit".into()), -50.0)
+            .with_tags(vec!["银行".into()]);
+        let named = Asset::new("银行理财", AssetType::Fund, 200.0);
+        for asset in [&named, &tagged, &described] {
+            db.create_asset(asset).unwrap();
+        }
+        db.create_asset(&Asset::new("现金", AssetType::Cash, 10.0))
+            .unwrap();
+
+        let ids: Vec<Uuid> = db
+            .search_assets_ranked("银行")
+            .unwrap()
+            .into_iter()
+            .map(|a| a.id)
+            .collect();
+        assert_eq!(ids, [named.id, tagged.id, described.id]);
+        assert_eq!(db.search_assets_ranked("").unwrap().len(), 4);
+    }
+
+    #[test]
+    fn test_settings() {
+        let mut db = Database::open_in_memory().unwrap();
+
+        db.set_setting("theme", "dark").unwrap();
+        let val = db.get_setting("theme").unwrap();
+        assert_eq!(val, Some("dark".to_string()));
+
+        let missing = db.get_setting("nonexistent").unwrap();
+        assert!(missing.is_none());
+    }
+
+    #[test]
+    fn test_list_by_type() {
+        let mut db = Database::open_in_memory().unwrap();
+
+        db.create_asset(&Asset::new("股票A", AssetType::Stock, 5000.0)).unwrap();
+        db.create_asset(&Asset::new("现金", AssetType::Cash, 3000.0)).unwrap();
+        db.create_asset(&Asset::new("股票B", AssetType::Stock, 8000.0)).unwrap();
+
+        let stocks = db.list_assets_by_type(&AssetType::Stock).unwrap();
+        assert_eq!(stocks.len(), 2);
+
+        let cash = db.list_assets_by_type(&AssetType::Cash).unwrap();
+        assert_eq!(cash.len(), 1);
+    }
+
+    #[test]
+    fn test_query_assets() {
+        let mut db = Database::open_in_memory().unwrap();
+        let usd = Asset::new("美股", AssetType::Stock, 8000.0)
+            .with_currency(Currency::USD)
+            .with_tags(vec!["长期".into(), "海外".into()]);
+        let mut old = Asset::new("股票A", AssetType::Stock, 5000.0).with_tags(vec!["长期".into()]);
+        old.created_at = Utc::now() - chrono::Duration::days(30);
+        db.create_asset(&usd).unwrap();
+        db.create_asset(&old).unwrap();
+        db.create_asset(&Asset::new("现金", AssetType::Cash, 3000.0))
+            .unwrap();
+
+        let ids = |filter: AssetFilter| -> Vec<Uuid> {
+            db.query_assets(&filter)
+                .unwrap()
+                .into_iter()
+                .map(|a| a.id)
+                .collect()
+        };
+        assert_eq!(ids(AssetFilter::default()).len(), 3);
+        assert_eq!(
+            ids(AssetFilter::default().with_tag("长期")),
+            [usd.id, old.id]
+        );
+        assert_eq!(
+            ids(AssetFilter::default().with_tag("长期").with_tag("海外")),
+            [usd.id]
+        );
+        assert_eq!(
+            ids(AssetFilter {
+                currency: Some(Currency::CNY),
+                ..AssetFilter::default().with_type(AssetType::Stock)
+            }),
+            [old.id]
+        );
+        assert_eq!(
+            ids(AssetFilter::default().with_value_range(Some(4000.0), Some(6000.0))),
+            [old.id]
+        );
+        assert_eq!(
+            ids(AssetFilter {
+                created_until: Some(Utc::now() - chrono::Duration::days(1)),
+                ..Default::default()
+            }),
+            [old.id]
+        );
+
+        // 摘要只统计符合条件的资产
+        let summary = db
+            .get_summary_filtered(&AssetFilter::default().with_tag("长期"), true)
+            .unwrap();
+        assert_eq!((summary.asset_count, summary.total_value), (2, 13000.0));
+        assert_eq!(summary.by_type["stock"], 13000.0);
+        assert_eq!(summary.by_currency["USD"], 8000.0);
+        let recent = AssetFilter::default()
+            .with_created_range(Some(Utc::now() - chrono::Duration::days(1)), None);
+        assert_eq!(
+            db.get_summary_filtered(&recent, true).unwrap().total_value,
+            11000.0
+        );
+    }
+
+    #[test]
+    fn test_asset_relations() {
+        use crate::asset::RelationKind;
+
+        let mut db = Database::open_in_memory().unwrap();
+        let house = Asset::new("房产", AssetType::RealEstate, 3_000_000.0);
+        let loan = Asset::new("房贷", AssetType::Other("loan".to_string()), -1_000_000.0);
+        db.create_asset(&house).unwrap();
+        db.create_asset(&loan).unwrap();
+
+        let relation = AssetRelation::new(loan.id, house.id, RelationKind::SecuredBy);
+        db.add_relation(&relation).unwrap();
+        assert!(db.add_relation(&relation).is_err());
+        assert!(db
+            .add_relation(&AssetRelation::new(house.id, house.id, RelationKind::Contains))
+            .is_err());
+
+        let related = db.get_related_assets(house.id).unwrap();
+        assert_eq!(related.len(), 1);
+        assert_eq!(related[0].asset.id, loan.id);
+        assert_eq!(related[0].direction, RelationDirection::Incoming);
+
+        let graph = db.export_relation_graph().unwrap();
+        assert_eq!(graph.nodes.len(), 2);
+        assert_eq!(graph.edges.len(), 1);
+
+        db.delete_asset(loan.id).unwrap();
+        assert!(db.get_related_assets(house.id).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_trash() {
+        use crate::asset::RelationKind;
+
+        let mut db = Database::open_in_memory().unwrap();
+        let house = Asset::new("房产", AssetType::RealEstate, 3_000_000.0);
+        let loan = Asset::new("房贷", AssetType::Other("loan".to_string()), -1_000_000.0);
+        db.create_asset(&house).unwrap();
+        db.create_asset(&loan).unwrap();
+        db.add_relation(&AssetRelation::new(
+            loan.id,
+            house.id,
+            RelationKind::SecuredBy,
+        ))
+        .unwrap();
+        db.add_transaction(&AssetTransaction::new(
+            loan.id,
+            TransactionType::Expense,
+            -1_000_000.0,
+            -990_000.0,
+        ))
+        .unwrap();
+
+        // 删除后移入回收站，关联记录一并移出
+        db.delete_asset(loan.id).unwrap();
+        assert!(db.get_asset(loan.id).unwrap().is_none());
+        assert!(db.get_related_assets(house.id).unwrap().is_empty());
+        let trashed = db.list_trashed().unwrap();
+        assert_eq!(trashed.len(), 1);
+        assert!(trashed[0].deleted_at.is_some());
+
+        // 恢复后原样放回
+        let restored = db.restore_asset(loan.id).unwrap();
+        assert!(restored.deleted_at.is_none());
+        assert!(db.get_asset(loan.id).unwrap().is_some());
+        assert_eq!(db.get_related_assets(house.id).unwrap().len(), 1);
+        let query = TransactionQuery::default();
+        assert_eq!(db.get_transactions(loan.id, &query).unwrap().len(), 1);
+        assert!(db.list_trashed().unwrap().is_empty());
+        assert!(matches!(
+            db.restore_asset(loan.id),
+            Err(StorageError::NotFound(_))
+        ));
+
+        // 只清除指定时间及之前删除的资产
+        db.delete_asset(loan.id).unwrap();
+        assert_eq!(
+            db.purge_trash(Utc::now() - chrono::Duration::days(1))
+                .unwrap(),
+            0
+        );
+        assert_eq!(db.purge_trash(Utc::now()).unwrap(), 1);
+        assert!(db.list_trashed().unwrap().is_empty());
+        assert!(db.get_transactions(loan.id, &query).unwrap().is_empty());
+
+        db.delete_asset_permanently(house.id).unwrap();
+        assert!(db.list_trashed().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_apply_template() {
+        let mut db = Database::open_in_memory().unwrap();
+        let checking = Asset::new("储蓄卡", AssetType::BankDeposit, 20_000.0);
+        let mortgage = Asset::new("房贷", AssetType::Other("loan".to_string()), -500_000.0);
+        db.create_asset(&checking).unwrap();
+        db.create_asset(&mortgage).unwrap();
+
+        let fixed = TransactionTemplate::new("月供", Some(checking.id), Some(mortgage.id))
+            .with_amount(5_000.0);
+        db.add_template(&fixed).unwrap();
+        let txns = db.apply_template(fixed.id, None).unwrap();
+        assert_eq!(txns.len(), 2);
+        assert_eq!(db.get_asset(checking.id).unwrap().unwrap().value, 15_000.0);
+        assert_eq!(
+            db.get_asset(mortgage.id).unwrap().unwrap().value,
+            -495_000.0
+        );
+
+        // 未设置金额的模板必须在应用时提供金额
+        let variable = TransactionTemplate::new("水电费", Some(checking.id), None);
+        db.add_template(&variable).unwrap();
+        assert!(db.apply_template(variable.id, None).is_err());
+        let txns = db.apply_template(variable.id, Some(300.0)).unwrap();
+        assert_eq!(txns[0].transaction_type, TransactionType::Expense);
+        assert_eq!(db.get_asset(checking.id).unwrap().unwrap().value, 14_700.0);
+        assert_eq!(
+            db.get_transactions(checking.id, &TransactionQuery::default())
+                .unwrap()
+                .len(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_double_entry_mode() {
+        let mut db = Database::open_in_memory().unwrap();
+        let cash = Asset::new("现金", AssetType::Cash, 1000.0);
+        db.create_asset(&cash).unwrap();
+        assert!(db.get_trial_balance().is_err());
+
+        assert!(db.enable_double_entry().unwrap().balanced);
+
+        // 单式操作自动镜像为分录
+        db.adjust_asset_value(cash.id, -200.0, TransactionType::Expense, None)
+            .unwrap();
+        let card = Asset::new("信用卡", AssetType::Cash, 0.0);
+        db.create_asset(&card).unwrap();
+
+        let accounts = db.list_ledger_accounts().unwrap();
+        let account = |id: Uuid| accounts.iter().find(|a| a.asset_id == Some(id)).unwrap().id;
+        let entry = LedgerEntry::new("还款")
+            .with_posting(account(card.id), 300.0)
+            .with_posting(account(cash.id), -300.0);
+        db.post_ledger_entry(&entry).unwrap();
+
+        assert_eq!(db.get_asset(cash.id).unwrap().unwrap().value, 500.0);
+        assert!(db.get_trial_balance().unwrap().balanced);
+    }
+
+    #[test]
+    fn test_closed_periods() {
+        let mut db = Database::open_in_memory().unwrap();
+        let asset = Asset::new("现金", AssetType::Cash, 1000.0);
+        db.create_asset(&asset).unwrap();
+
+        let march = Utc.with_ymd_and_hms(2024, 3, 15, 0, 0, 0).unwrap();
+        let txn = AssetTransaction::new(asset.id, TransactionType::Income, 900.0, 1000.0)
+            .with_timestamp(march);
+        db.add_transaction(&txn).unwrap();
+        db.close_period(2024, 3).unwrap();
+
+        // 已结账期间不可新增、修改或删除
+        let backdated = AssetTransaction::new(asset.id, TransactionType::Expense, 1000.0, 990.0)
+            .with_timestamp(march);
+        assert!(matches!(
+            db.add_transaction(&backdated),
+            Err(StorageError::PeriodClosed(_))
+        ));
+        assert!(db.delete_transaction(txn.id).is_err());
+        assert!(db.delete_asset(asset.id).is_err());
+
+        // 通过更正分录调整
+        let correction = db.add_correction(txn.id, 150.0, None).unwrap();
+        assert_eq!(correction.corrects, Some(txn.id));
+        assert_eq!(correction.delta(), 50.0);
+        assert_eq!(db.get_asset(asset.id).unwrap().unwrap().value, 1050.0);
+
+        db.reopen_period(2024, 3).unwrap();
+        db.delete_transaction(txn.id).unwrap();
+    }
+
+    #[test]
+    fn test_dated_fx_rates() {
+        let mut db = Database::open_in_memory().unwrap();
+        let date = |m: u32| NaiveDate::from_ymd_opt(2024, m, 1).unwrap();
+        db.upsert_fx_rates(&[
+            FxRate::new(&Currency::USD, &Currency::CNY, date(1), 7.1),
+            FxRate::new(&Currency::USD, &Currency::CNY, date(6), 7.2),
+        ])
+        .unwrap();
+        db.upsert_fx_rates(&[FxRate::new(&Currency::USD, &Currency::CNY, date(6), 7.25)])
+            .unwrap();
+
+        assert_eq!(db.list_fx_rates(&Currency::USD).unwrap().len(), 2);
+        assert_eq!(db.get_rate_on(&Currency::USD, date(3)).unwrap(), Some(7.1));
+        assert_eq!(db.get_rate_on(&Currency::USD, date(9)).unwrap(), Some(7.25));
+        assert_eq!(db.get_rate_on(&Currency::EUR, date(9)).unwrap(), None);
+        assert_eq!(
+            db.convert_on(10.0, &Currency::CNY, date(1)).unwrap(),
+            Some(10.0)
+        );
+    }
+
+    #[test]
+    fn test_foreign_transaction() {
+        let mut db = Database::open_in_memory().unwrap();
+        let card = Asset::new("信用卡", AssetType::Cash, 10_000.0);
+        db.create_asset(&card).unwrap();
+        let today = Utc::now().date_naive();
+        db.upsert_fx_rates(&[FxRate::new(&Currency::USD, &Currency::CNY, today, 7.1)])
+            .unwrap();
+
+        let txn = db
+            .add_foreign_transaction(
+                card.id,
+                -100.0,
+                Currency::USD,
+                7.2,
+                TransactionType::Expense,
+                None,
+            )
+            .unwrap();
+        assert_eq!(txn.original_currency, Some(Currency::USD));
+        assert_eq!(db.get_asset(card.id).unwrap().unwrap().value, 9280.0);
+
+        let report = db.get_fx_gain_loss(None).unwrap();
+        assert_eq!(report.items.len(), 1);
+        assert!((report.total_base_gain_loss + 10.0).abs() < 1e-9);
+        assert!(db
+            .add_foreign_transaction(
+                card.id,
+                1.0,
+                Currency::USD,
+                0.0,
+                TransactionType::Income,
+                None
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn test_transaction_status() {
+        let mut db = Database::open_in_memory().unwrap();
+        let card = Asset::new("储蓄卡", AssetType::BankDeposit, 1000.0);
+        db.create_asset(&card).unwrap();
+
+        let txn = db
+            .adjust_asset_value(card.id, -200.0, TransactionType::Expense, None)
+            .unwrap();
+        db.set_transaction_status(txn.id, TransactionStatus::Pending)
+            .unwrap();
+        assert_eq!(db.get_summary().unwrap().total_value, 800.0);
+        assert_eq!(
+            db.get_summary_with_pending(false).unwrap().total_value,
+            1000.0
+        );
+        assert_eq!(
+            db.get_transactions_by_status(TransactionStatus::Pending, Some(card.id))
+                .unwrap()
+                .len(),
+            1
+        );
+
+        db.set_transaction_status(txn.id, TransactionStatus::Reconciled)
+            .unwrap();
+        assert!(db
+            .set_transaction_status(txn.id, TransactionStatus::Pending)
+            .is_err());
+        assert_eq!(
+            db.get_summary_with_pending(false).unwrap().total_value,
+            800.0
+        );
+    }
+
+    #[test]
+    fn test_scheduled_transactions() {
+        use chrono::Duration;
+
+        let mut db = Database::open_in_memory().unwrap();
+        let card = Asset::new("储蓄卡", AssetType::BankDeposit, 5000.0);
+        db.create_asset(&card).unwrap();
+        let now = Utc::now();
+
+        let rent = db
+            .add_scheduled_transaction(
+                card.id,
+                -3000.0,
+                TransactionType::Expense,
+                now + Duration::days(3),
+                Some("房租".to_string()),
+            )
+            .unwrap();
+        assert_eq!(db.get_asset(card.id).unwrap().unwrap().value, 5000.0);
+        assert_eq!(db.get_upcoming_transactions(None).unwrap().len(), 1);
+        assert_eq!(
+            db.get_projected_balances(now + Duration::days(7)).unwrap()[0].projected_value,
+            2000.0
+        );
+        assert!(db
+            .add_scheduled_transaction(card.id, 1.0, TransactionType::Income, now, None)
+            .is_err());
+
+        db.adjust_asset_value(card.id, 500.0, TransactionType::Income, None)
+            .unwrap();
+        let posted = db.post_due_transactions(now + Duration::days(4)).unwrap();
+        assert_eq!(posted.len(), 1);
+        assert_eq!(posted[0].id, rent.id);
+        assert_eq!(posted[0].amount_before, 5500.0);
+        assert_eq!(db.get_asset(card.id).unwrap().unwrap().value, 2500.0);
+        assert!(db.get_upcoming_transactions(None).unwrap().is_empty());
+
+        // 修改利率假设后预计余额随之更新
+        let rates = Assumptions {
+            deposit_rate: 0.02,
+            ..Default::default()
+        };
+        db.set_assumptions(&rates).unwrap();
+        let year = db
+            .get_projected_balances(Utc::now() + Duration::days(366))
+            .unwrap();
+        assert!((year[0].interest - 50.0).abs() < 1e-9);
+        assert!(db
+            .set_assumptions(&Assumptions {
+                inflation: 2.0,
+                ..rates
+            })
+            .is_err());
+    }
+
+    #[test]
+    fn test_envelopes() {
+        let mut db = Database::open_in_memory().unwrap();
+        let checking = Asset::new("储蓄卡", AssetType::BankDeposit, 5000.0);
+        let stock = Asset::new("股票", AssetType::Stock, 1000.0);
+        db.create_asset(&checking).unwrap();
+        db.create_asset(&stock).unwrap();
+
+        let rent = Envelope::new(checking.id, "房租");
+        let food = Envelope::new(checking.id, "伙食");
+        db.create_envelope(&rent).unwrap();
+        db.create_envelope(&food).unwrap();
+        assert!(db
+            .create_envelope(&Envelope::new(stock.id, "房租"))
+            .is_err());
+
+        db.allocate_to_envelope(rent.id, 3000.0).unwrap();
+        db.allocate_to_envelope(food.id, 1500.0).unwrap();
+        assert!(db.allocate_to_envelope(food.id, 1000.0).is_err());
+        db.move_between_envelopes(rent.id, food.id, 200.0).unwrap();
+        assert!(db.move_between_envelopes(rent.id, food.id, 5000.0).is_err());
+
+        let summary = db.get_summary().unwrap();
+        assert_eq!(summary.envelopes.len(), 1);
+        assert_eq!(summary.envelopes[0].unallocated, 500.0);
+        assert_eq!(summary.envelopes[0].envelopes[1].balance, 1700.0);
+
+        db.delete_envelope(rent.id).unwrap();
+        assert_eq!(db.get_envelopes(checking.id).unwrap().unallocated, 3300.0);
+    }
+
+    #[test]
+    fn test_split_settlement() {
+        use crate::split::ME;
+
+        let mut db = Database::open_in_memory().unwrap();
+        let wallet = Asset::new("微信零钱", AssetType::Cash, 100.0);
+        db.create_asset(&wallet).unwrap();
+
+        let dinner = SharedExpense::new("聚餐", ME, 200.0).split_evenly(&[ME, "小王"]);
+        db.add_shared_expense(&dinner).unwrap();
+        assert!(db
+            .add_shared_expense(&SharedExpense::new("无份额", ME, 10.0))
+            .is_err());
+
+        let debts = db.get_suggested_settlements().unwrap();
+        assert_eq!(debts.len(), 1);
+        assert_eq!(debts[0].from, "小王");
+
+        let settlement = db
+            .record_settlement(Settlement::new("小王", ME, 100.0), Some(wallet.id))
+            .unwrap();
+        assert!(settlement.transaction_id.is_some());
+        assert_eq!(db.get_asset(wallet.id).unwrap().unwrap().value, 200.0);
+        assert!(db.get_split_balances().unwrap().is_empty());
+        assert!(db
+            .record_settlement(Settlement::new("小王", "小李", 10.0), Some(wallet.id))
+            .is_err());
+    }
+
+    #[test]
+    fn test_virtual_assets() {
+        use crate::asset::{Aggregate, AssetFormula};
+
+        let mut db = Database::open_in_memory().unwrap();
+        let ibkr = vec!["IBKR".to_string()];
+        let apple = Asset::new("苹果", AssetType::Stock, 1000.0).with_tags(ibkr.clone());
+        let msft = Asset::new("微软", AssetType::Stock, 2000.0).with_tags(ibkr.clone());
+        let total = Asset::new("盈透合计", AssetType::Stock, 0.0).with_formula(
+            AssetFormula::new(Aggregate::Sum)
+                .with_asset_type(AssetType::Stock)
+                .with_tags(ibkr),
+        );
+        db.create_asset(&apple).unwrap();
+        db.create_asset(&msft).unwrap();
+        db.create_asset(&total).unwrap();
+
+        assert_eq!(db.get_asset(total.id).unwrap().unwrap().value, 3000.0);
+        db.adjust_asset_value(apple.id, 500.0, TransactionType::ValueChange, None)
+            .unwrap();
+        assert_eq!(db.get_asset(total.id).unwrap().unwrap().value, 3500.0);
+
+        let summary = db.get_summary().unwrap();
+        assert_eq!(summary.total_value, 3500.0);
+        assert_eq!(summary.asset_count, 2);
+        assert!(db
+            .adjust_asset_value(total.id, 1.0, TransactionType::ValueChange, None)
+            .is_err());
+
+        // 归因同样不计入虚拟资产（即使保存过计算出的价值）
+        let saved = db.get_asset(total.id).unwrap().unwrap();
+        db.update_asset(&saved).unwrap();
+        let range = DateRange::new(apple.created_at - chrono::Duration::seconds(1), Utc::now());
+        let attribution = db.get_attribution(&range, GroupBy::AssetType).unwrap();
+        assert_eq!(attribution.total.end_value, summary.total_value);
+    }
+
+    #[test]
+    fn test_target_alerts() {
+        let mut db = Database::open_in_memory().unwrap();
+        let stock = Asset::new("茅台", AssetType::Stock, 12000.0)
+            .with_cost_basis(10000.0)
+            .with_target_price(15000.0);
+        db.create_asset(&stock).unwrap();
+
+        assert!(db.check_targets().unwrap().is_empty());
+        assert_eq!(
+            db.get_asset_performance().unwrap()[0].unrealized_gain,
+            Some(2000.0)
+        );
+
+        db.adjust_asset_value(stock.id, 3500.0, TransactionType::ValueChange, None)
+            .unwrap();
+        let alerts = db.check_targets().unwrap();
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].kind, NotificationKind::TargetReached);
+        assert!(db.check_targets().unwrap().is_empty());
+
+        // 回落后再次达到会重新通知
+        db.adjust_asset_value(stock.id, -1000.0, TransactionType::ValueChange, None)
+            .unwrap();
+        assert!(db.check_targets().unwrap().is_empty());
+        db.adjust_asset_value(stock.id, 1000.0, TransactionType::ValueChange, None)
+            .unwrap();
+        assert_eq!(db.check_targets().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_reminder_notifications() {
+        use crate::asset::Recurrence;
+        use chrono::Duration;
+
+        let mut db = Database::open_in_memory().unwrap();
+        let now = Utc::now();
+
+        let asset = Asset::new("房产", AssetType::RealEstate, 1_000_000.0);
+        db.create_asset(&asset).unwrap();
+        let reminder = Reminder::new("重新估价房产", Recurrence::Yearly, now - Duration::days(1))
+            .for_asset(asset.id);
+        db.add_reminder(&reminder).unwrap();
+
+        // 到期提醒只通知一次
+        assert_eq!(db.check_reminders(now).unwrap().len(), 1);
+        assert!(db.check_reminders(now).unwrap().is_empty());
+        assert_eq!(db.list_notifications(true).unwrap().len(), 1);
+
+        db.complete_reminder(reminder.id, now).unwrap();
+        assert!(db.get_due_reminders(now).unwrap().is_empty());
+
+        // 删除资产时一并删除提醒
+        db.delete_asset(asset.id).unwrap();
+        assert!(db.list_reminders().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_external_ids() {
+        let mut db = Database::open_in_memory().unwrap();
+        let account = Asset::new("证券账户", AssetType::Stock, 0.0).with_external_id("IB-001");
+        db.create_asset(&account).unwrap();
+        let duplicate = Asset::new("另一个", AssetType::Stock, 0.0).with_external_id("IB-001");
+        assert!(db.create_asset(&duplicate).is_err());
+        assert_eq!(
+            db.get_asset_by_external_id("IB-001").unwrap().unwrap().id,
+            account.id
+        );
+
+        let txn = AssetTransaction::new(account.id, TransactionType::Buy, 0.0, 100.0)
+            .with_external_id("T1");
+        db.add_transaction(&txn).unwrap();
+        let again = AssetTransaction::new(account.id, TransactionType::Buy, 100.0, 200.0)
+            .with_external_id("T1");
+        assert!(db.add_transaction(&again).is_err());
+        assert!(db
+            .get_transaction_by_external_id(account.id, "T1")
+            .unwrap()
+            .is_some());
+
+        // 对账单中流水号已存在的流水视为重复
+        let line = StatementLine {
+            date: Utc::now(),
+            amount: 50.0,
+            description: Some("不同摘要".to_string()),
+            reference: Some("T1".to_string()),
+        };
+        let batch = ImportBatch::new(crate::import::ImportSource::Statement);
+        let outcome = db.import_statement(account.id, &[line], &batch).unwrap();
+        assert_eq!(outcome.duplicates, 1);
+    }
+
+    #[test]
+    fn test_upsert_asset() {
+        let mut db = Database::open_in_memory().unwrap();
+        let first = db
+            .upsert_asset(&Asset::new("券商账户", AssetType::Stock, 100.0))
+            .unwrap();
+        assert!(first.created);
+
+        // 按名称与类型匹配，沿用原有ID
+        let second = db
+            .upsert_asset(&Asset::new("券商账户", AssetType::Stock, 150.0))
+            .unwrap();
+        assert!(!second.created);
+        assert_eq!(second.asset.id, first.asset.id);
+        assert_eq!(db.get_asset(first.asset.id).unwrap().unwrap().value, 150.0);
+
+        // 类型不同视为新资产
+        assert!(
+            db.upsert_asset(&Asset::new("券商账户", AssetType::Fund, 1.0))
+                .unwrap()
+                .created
+        );
+
+        // 设置外部标识时按外部标识匹配，可同时改名
+        let synced = Asset::new("A", AssetType::Cash, 1.0).with_external_id("acct-1");
+        let created = db.upsert_asset(&synced).unwrap();
+        let renamed = Asset::new("B", AssetType::Cash, 2.0).with_external_id("acct-1");
+        let updated = db.upsert_asset(&renamed).unwrap();
+        assert!(!updated.created);
+        assert_eq!(updated.asset.id, created.asset.id);
+        assert_eq!(
+            db.list_assets(SortBy::default(), SortOrder::default())
+                .unwrap()
+                .len(),
+            3
+        );
+    }
+
+    #[test]
+    fn test_metadata_schema_on_write() {
+        let mut db = Database::open_in_memory().unwrap();
+        let stock = Asset::new("苹果", AssetType::Stock, 100.0)
+            .with_metadata(serde_json::json!({ "ticker": "AAPL", "shares": -1 }));
+        assert!(matches!(
+            db.create_asset(&stock),
+            Err(StorageError::InvalidMetadata(ref v)) if v[0].path == "/shares"
+        ));
+
+        // 插件 Schema 覆盖内置定义
+        db.set_plugin_metadata_schemas(vec![(
+            "stock".to_string(),
+            serde_json::json!({ "type": "object", "required": ["broker"] }),
+        )]);
+        assert!(db.create_asset(&stock).is_err());
+        let stock = stock.with_metadata(serde_json::json!({ "broker": "IBKR", "shares": -1 }));
+        db.create_asset(&stock).unwrap();
+
+        // 更新时同样校验
+        let mut updated = stock.clone();
+        updated.metadata = serde_json::json!({});
+        assert!(db.update_asset(&updated).is_err());
+    }
+
+    #[test]
+    fn test_query_assets_by_metadata() {
+        let mut db = Database::open_in_memory().unwrap();
+        let ibkr = Asset::new("美股", AssetType::Stock, 100.0)
+            .with_metadata(serde_json::json!({ "broker": "IBKR", "shares": 10 }));
+        let futu = Asset::new("港股", AssetType::Stock, 50.0)
+            .with_metadata(serde_json::json!({ "broker": "Futu" }));
+        db.create_asset(&ibkr).unwrap();
+        db.create_asset(&futu).unwrap();
+
+        let found = db
+            .query_assets_by_metadata("metadata.broker", &serde_json::json!("IBKR"))
+            .unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].id, ibkr.id);
+        assert_eq!(
+            db.query_assets_by_metadata("$.shares", &serde_json::json!(10.0))
+                .unwrap()
+                .len(),
+            1
+        );
+        assert!(db
+            .query_assets_by_metadata("$..", &serde_json::json!(1))
+            .is_err());
+    }
+
+    #[test]
+    fn test_get_transactions_query() {
+        let mut db = Database::open_in_memory().unwrap();
+        let asset = Asset::new("活期", AssetType::BankDeposit, 0.0);
+        db.create_asset(&asset).unwrap();
+        let start = Utc::now() - chrono::Duration::days(10);
+        for day in 0..10 {
+            let mut txn = AssetTransaction::new(asset.id, TransactionType::Income, 0.0, 1.0);
+            txn.timestamp = start + chrono::Duration::days(day);
+            db.add_transaction(&txn).unwrap();
+        }
+
+        let recent = db
+            .get_transactions(asset.id, &TransactionQuery::default().with_limit(3))
+            .unwrap();
+        assert_eq!(recent.len(), 3);
+        assert_eq!(recent[0].timestamp, start + chrono::Duration::days(9));
+
+        let range = TransactionQuery::default().with_range(
+            Some(start + chrono::Duration::days(2)),
+            Some(start + chrono::Duration::days(4)),
+        );
+        assert_eq!(db.get_transactions(asset.id, &range).unwrap().len(), 3);
+        let page = range.with_offset(2).with_limit(5);
+        assert_eq!(db.get_transactions(asset.id, &page).unwrap().len(), 1);
+        let expenses = TransactionQuery::default().with_type(TransactionType::Expense);
+        assert!(db.get_transactions(asset.id, &expenses).unwrap().is_empty());
+
+        // 跨资产的最近交易
+        let cash = Asset::new("现金", AssetType::Cash, 0.0);
+        db.create_asset(&cash).unwrap();
+        let latest = AssetTransaction::new(cash.id, TransactionType::Income, 0.0, 5.0);
+        db.add_transaction(&latest).unwrap();
+        let feed = db.list_recent_transactions(2).unwrap();
+        assert_eq!(feed.len(), 2);
+        assert_eq!(feed[0].id, latest.id);
+        assert_eq!(feed[1].asset_id, asset.id);
+    }
+
+    #[test]
+    fn test_bulk_insert() {
+        let mut db = Database::open_in_memory().unwrap();
+        let existing = Asset::new("现金", AssetType::Cash, 10.0);
+        db.create_asset(&existing).unwrap();
+        let card = Asset::new("储蓄卡", AssetType::BankDeposit, 100.0);
+        let result = db
+            .create_assets_bulk(&[card.clone(), existing.clone()])
+            .unwrap();
+        assert_eq!(result.inserted, 1);
+        assert_eq!(
+            (result.errors[0].index, result.errors[0].id),
+            (1, existing.id)
+        );
+
+        // 已结账期间内的交易跳过，其余照常写入
+        db.close_period(2024, 3).unwrap();
+        let march = Utc.with_ymd_and_hms(2024, 3, 15, 0, 0, 0).unwrap();
+        let old = AssetTransaction::new(card.id, TransactionType::Income, 0.0, 50.0)
+            .with_timestamp(march);
+        let txn = AssetTransaction::new(card.id, TransactionType::Income, 50.0, 100.0);
+        let result = db.add_transactions_bulk(&[old, txn.clone(), txn]).unwrap();
+        assert_eq!(result.inserted, 1);
+        assert_eq!(
+            result.errors.iter().map(|e| e.index).collect::<Vec<_>>(),
+            vec![0, 2]
+        );
+        let all = db
+            .get_transactions(card.id, &TransactionQuery::default())
+            .unwrap();
+        assert_eq!(all.len(), 1);
+    }
+
+    #[test]
+    fn test_insert_existing_id() {
+        let mut db = Database::open_in_memory().unwrap();
+        let card = Asset::new("储蓄卡", AssetType::BankDeposit, 100.0);
+        db.create_asset(&card).unwrap();
+        let mut renamed = card.clone();
+        renamed.name = "信用卡".to_string();
+        assert!(db.create_asset(&renamed).is_err());
+        assert_eq!(db.get_asset(card.id).unwrap().unwrap().name, "储蓄卡");
+
+        // 已结账期间内的交易不能被同ID的新交易替换
+        let march = Utc.with_ymd_and_hms(2024, 3, 15, 0, 0, 0).unwrap();
+        let old = AssetTransaction::new(card.id, TransactionType::Income, 0.0, 50.0)
+            .with_timestamp(march);
+        db.add_transaction(&old).unwrap();
+        db.close_period(2024, 3).unwrap();
+        let mut replacement = AssetTransaction::new(card.id, TransactionType::Income, 0.0, 80.0);
+        replacement.id = old.id;
+        assert!(db.add_transaction(&replacement).is_err());
+        assert!(db.add_transactions(&[replacement]).is_err());
+
+        // 同一批中重复的ID整批拒绝
+        let txn = AssetTransaction::new(card.id, TransactionType::Income, 50.0, 100.0);
+        assert!(db.add_transactions(&[txn.clone(), txn]).is_err());
+        let all = db
+            .get_transactions(card.id, &TransactionQuery::default())
+            .unwrap();
+        assert_eq!((all.len(), all[0].timestamp), (1, march));
+    }
+
+    #[test]
+    fn test_get_transactions_page() {
+        let mut db = Database::open_in_memory().unwrap();
+        let asset = Asset::new("活期", AssetType::BankDeposit, 0.0);
+        db.create_asset(&asset).unwrap();
+        // 同一时刻的多笔交易按ID区分先后
+        let now = Utc::now();
+        for _ in 0..5 {
+            let mut txn = AssetTransaction::new(asset.id, TransactionType::Income, 0.0, 1.0);
+            txn.timestamp = now;
+            db.add_transaction(&txn).unwrap();
+        }
+
+        let first = db.get_transactions_page(asset.id, None, 2).unwrap();
+        assert_eq!(first.transactions.len(), 2);
+        let cursor = first.next_cursor.clone().unwrap();
+
+        // 游标之前插入的新交易不影响后续页
+        db.add_transaction(&AssetTransaction::new(
+            asset.id,
+            TransactionType::Expense,
+            1.0,
+            0.0,
+        ))
+        .unwrap();
+        let second = db
+            .get_transactions_page(asset.id, Some(&cursor), 2)
+            .unwrap();
+        let third = db
+            .get_transactions_page(asset.id, second.next_cursor.as_deref(), 2)
+            .unwrap();
+        assert_eq!(third.transactions.len(), 1);
+        assert!(third.next_cursor.is_none());
+
+        let mut ids: Vec<Uuid> = [first, second, third]
+            .iter()
+            .flat_map(|p| p.transactions.iter().map(|t| t.id))
+            .collect();
+        ids.dedup();
+        assert_eq!(ids.len(), 5);
+        assert!(db.get_transactions_page(asset.id, Some("bad"), 2).is_err());
+    }
+
+    #[test]
+    fn test_daily_balances_follow_mutations() {
+        let mut db = Database::open_in_memory().unwrap();
+        let mut asset = Asset::new("活期", AssetType::BankDeposit, 0.0);
+        asset.created_at = Utc::now() - chrono::Duration::days(3);
+        db.create_asset(&asset).unwrap();
+        let range = DateRange::new(asset.created_at, Utc::now());
+
+        let txn = AssetTransaction::new(asset.id, TransactionType::Income, 0.0, 100.0)
+            .with_timestamp(asset.created_at + chrono::Duration::days(1));
+        db.add_transaction(&txn).unwrap();
+        asset.update_value(100.0);
+        db.update_asset(&asset).unwrap();
+        let balances = db.get_daily_balances(asset.id, &range).unwrap();
+        assert_eq!(balances.len(), 4);
+        assert_eq!(balances[0].value, 0.0);
+        assert_eq!(balances[3].value, 100.0);
+
+        db.delete_transaction(txn.id).unwrap();
+        asset.update_value(0.0);
+        db.update_asset(&asset).unwrap();
+        let balances = db.get_daily_balances(asset.id, &range).unwrap();
+        assert!(balances.iter().all(|b| b.value == 0.0));
+
+        db.delete_asset(asset.id).unwrap();
+        assert!(db.get_daily_balances(asset.id, &range).is_err());
+    }
+
+    #[test]
+    fn test_chart_series() {
+        let mut db = Database::open_in_memory().unwrap();
+        let mut asset = Asset::new("基金", AssetType::Fund, 100.0);
+        asset.created_at = Utc::now() - chrono::Duration::days(1000);
+        db.create_asset(&asset).unwrap();
+        let range = DateRange::new(asset.created_at, Utc::now());
+
+        let source = ChartSource::ValueHistory {
+            asset_id: asset.id,
+            interval: Interval::Day,
+            method: InterpolationMethod::CarryForward,
+        };
+        let series = db
+            .get_chart_series(&source, &range, 100, DownsampleMethod::Lttb)
+            .unwrap();
+        assert!(series.source_points > 1000);
+        assert!(series.points.len() <= 100);
+        assert!(series.points.iter().all(|p| p.value == 100.0));
+
+        let source = ChartSource::Valuations {
+            asset_id: Uuid::new_v4(),
+        };
+        assert!(db
+            .get_chart_series(&source, &range, 100, DownsampleMethod::MinMax)
+            .is_err());
+    }
+
+    #[test]
+    fn test_valuation_history() {
+        let mut db = Database::open_in_memory().unwrap();
+        let start = Utc::now();
+        let mut stock = Asset::new("股票", AssetType::Stock, 1000.0);
+        db.create_asset(&stock).unwrap();
+
+        // 价值不变的修改不记录
+        stock.name = "股票账户".to_string();
+        db.update_asset(&stock).unwrap();
+        stock.update_value(1200.0);
+        db.update_asset(&stock).unwrap();
+        db.adjust_asset_value(stock.id, -200.0, TransactionType::ValueChange, None)
+            .unwrap();
+        let range = DateRange::new(start, Utc::now());
+        let values = |db: &Database| -> Vec<f64> {
+            let history = db.get_valuation_history(stock.id, &range).unwrap();
+            history.iter().map(|v| v.value).collect()
+        };
+        assert_eq!(values(&db), vec![1000.0, 1200.0, 1000.0]);
+
+        // 删除后估值随资产移入回收站，恢复时放回
+        db.delete_asset(stock.id).unwrap();
+        assert!(db.get_valuation_history(stock.id, &range).is_err());
+        db.restore_asset(stock.id).unwrap();
+        assert_eq!(values(&db).len(), 3);
+
+        let past = DateRange::new(
+            start - chrono::Duration::days(2),
+            start - chrono::Duration::days(1),
+        );
+        assert!(db
+            .get_valuation_history(stock.id, &past)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_journal_and_compaction() {
+        let dir = std::env::temp_dir().join(format!("asset-manager-db-{}", Uuid::new_v4()));
+        let path = dir.join("assets.json");
+        let journal = dir.join("assets.json.journal");
+
+        let mut db = Database::open(&path).unwrap();
+        let asset = Asset::new("活期", AssetType::BankDeposit, 0.0);
+        db.create_asset(&asset).unwrap();
+        let snapshot = fs::read_to_string(&path).unwrap();
+        let txn = AssetTransaction::new(asset.id, TransactionType::Income, 0.0, 50.0);
+        db.add_transaction(&txn).unwrap();
+        db.delete_transaction(txn.id).unwrap();
+        db.add_transaction(&txn).unwrap();
 
-        // 搜索
-        let results = db.search_assets("股票").unwrap();
-        assert_eq!(results.len(), 1);
+        // 只改动资产与交易时不重写主文件（创建资产时另有一条估值）
+        assert_eq!(fs::read_to_string(&path).unwrap(), snapshot);
+        assert_eq!(fs::read_to_string(&journal).unwrap().lines().count(), 5);
 
-        let results = db.search_assets("不存在").unwrap();
-        assert_eq!(results.len(), 0);
+        // 末尾不完整的条目被忽略，打开时重放日志并压缩
+        let mut file = fs::OpenOptions::new().append(true).open(&journal).unwrap();
+        std::io::Write::write_all(&mut file, b"{\"table\":\"assets\",").unwrap();
+        drop(db);
+        let mut db = Database::open(&path).unwrap();
+        assert!(!journal.exists());
+        assert_eq!(
+            db.get_transactions(asset.id, &Default::default())
+                .unwrap()
+                .len(),
+            1
+        );
 
-        // 更新
-        let mut updated = loaded.clone();
-        updated.update_value(20000.0);
-        db.update_asset(&updated).unwrap();
-        let reloaded = db.get_asset(asset.id).unwrap().unwrap();
-        assert_eq!(reloaded.value, 20000.0);
+        // 其他数据变化时重写主文件
+        db.add_transaction(&AssetTransaction::new(
+            asset.id,
+            TransactionType::Income,
+            50.0,
+            80.0,
+        ))
+        .unwrap();
+        assert!(journal.exists());
+        db.set_setting("theme", "dark").unwrap();
+        assert!(!journal.exists());
+        drop(db);
+        let db = Database::open(&path).unwrap();
+        assert_eq!(
+            db.get_transactions(asset.id, &Default::default())
+                .unwrap()
+                .len(),
+            2
+        );
+        assert_eq!(db.get_setting("theme").unwrap().as_deref(), Some("dark"));
 
-        // 删除
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_deferred_save_modes() {
+        let dir = std::env::temp_dir().join(format!("asset-manager-db-{}", Uuid::new_v4()));
+        let path = dir.join("assets.json");
+        let journal = dir.join("assets.json.journal");
+
+        let mut db = Database::open(&path).unwrap();
+        let settings = SaveSettings {
+            mode: SaveMode::Interval,
+            interval_secs: 60,
+            ..Default::default()
+        };
+        db.set_save_settings(&settings).unwrap();
+        assert!(!db.has_unsaved_changes());
+
+        let asset = Asset::new("活期", AssetType::BankDeposit, 0.0);
+        db.create_asset(&asset).unwrap();
+        let since = db.save_status().unsaved_since.unwrap();
+        assert!(!journal.exists());
+        assert!(!db.autosave(since + chrono::Duration::seconds(30)).unwrap());
+        assert!(db.autosave(since + chrono::Duration::seconds(60)).unwrap());
+        assert!(!db.has_unsaved_changes());
+        assert!(journal.exists());
+
+        // 手动模式只在显式保存时写入
+        db.set_save_settings(&SaveSettings {
+            mode: SaveMode::Manual,
+            ..settings
+        })
+        .unwrap();
+        db.set_setting("theme", "dark").unwrap();
+        assert!(!db.autosave(Utc::now() + chrono::Duration::days(1)).unwrap());
+        drop(db);
+        let mut db = Database::open(&path).unwrap();
+        assert_eq!(db.get_save_settings().unwrap().mode, SaveMode::Manual);
+        assert!(db.get_setting("theme").unwrap().is_none());
+        assert_eq!(
+            db.list_assets(SortBy::default(), SortOrder::default())
+                .unwrap()
+                .len(),
+            1
+        );
+
+        db.set_setting("theme", "dark").unwrap();
+        db.save_now().unwrap();
+        drop(db);
+        let mut db = Database::open(&path).unwrap();
+        assert_eq!(db.get_setting("theme").unwrap().as_deref(), Some("dark"));
+        assert!(db
+            .set_save_settings(&SaveSettings {
+                interval_secs: 0,
+                ..settings
+            })
+            .is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_asset_history_diff() {
+        let dir = std::env::temp_dir().join(format!("asset-manager-db-{}", Uuid::new_v4()));
+        let path = dir.join("assets.json");
+
+        let mut db = Database::open(&path).unwrap();
+        let mut asset = Asset::new("存款", AssetType::BankDeposit, 10000.0);
+        db.create_asset(&asset).unwrap();
+        asset.name = "定期存款".to_string();
+        asset.value = 12000.0;
+        db.update_asset(&asset).unwrap();
+        // 交易产生的价值变动同样列出
+        db.adjust_asset_value(asset.id, 100.0, TransactionType::Income, None)
+            .unwrap();
+        drop(db);
+
+        let mut db = Database::open(&path).unwrap();
         db.delete_asset(asset.id).unwrap();
-        let deleted = db.get_asset(asset.id).unwrap();
-        assert!(deleted.is_none());
+        let diffs = db.get_asset_history_diff(asset.id).unwrap();
+        assert_eq!(diffs.len(), 4);
+        assert_eq!(diffs[0].action, AuditAction::Created);
+        let fields: Vec<&str> = diffs[1].changes.iter().map(|c| c.field.as_str()).collect();
+        assert_eq!(fields, ["name", "value"]);
+        assert_eq!(diffs[1].changes[1].before, serde_json::json!(10000.0));
+        assert_eq!(diffs[2].changes[0].after, serde_json::json!(12100.0));
+        assert_eq!(diffs[3].action, AuditAction::Deleted);
+        db.restore_asset(asset.id).unwrap();
+        let diffs = db.get_asset_history_diff(asset.id).unwrap();
+        assert_eq!(diffs[4].action, AuditAction::Restored);
+        assert!(db
+            .get_asset_history_diff(Uuid::new_v4())
+            .unwrap()
+            .is_empty());
+
+        fs::remove_dir_all(&dir).unwrap();
     }
 
     #[test]
-    fn test_settings() {
+    fn test_audit_log() {
+        use crate::storage::AuditOperation;
+
+        let dir = std::env::temp_dir().join(format!("asset-manager-db-{}", Uuid::new_v4()));
+        let path = dir.join("assets.json");
+
+        let mut db = Database::open(&path).unwrap();
+        let mut asset = Asset::new("存款", AssetType::BankDeposit, 10000.0);
+        db.create_asset(&asset).unwrap();
+        asset.name = "定期存款".to_string();
+        db.update_asset(&asset).unwrap();
+        db.set_actor("import");
+        let txn = db
+            .adjust_asset_value(asset.id, 100.0, TransactionType::Income, None)
+            .unwrap();
+        drop(db);
+
+        // 修改记录随保存写入文件，重新打开后仍可查询
+        let mut db = Database::open(&path).unwrap();
+        let records = db
+            .query_audit_log(&AuditQuery::for_entity(AuditEntity::Asset, asset.id))
+            .unwrap();
+        let operations: Vec<AuditOperation> = records.iter().map(|r| r.operation).collect();
+        assert_eq!(
+            operations,
+            [
+                AuditOperation::Update,
+                AuditOperation::Update,
+                AuditOperation::Create
+            ]
+        );
+        assert_eq!(records[0].actor, "import");
+        assert_eq!(records[1].actor, DEFAULT_ACTOR);
+        assert_eq!(records[1].changes[0].after, serde_json::json!("定期存款"));
+        let query = AuditQuery {
+            actor: Some("import".to_string()),
+            ..AuditQuery::default()
+        };
+        let by_import = db.query_audit_log(&query).unwrap();
+        assert_eq!(by_import.len(), 2);
+        assert!(by_import.iter().any(|r| r.entity_id == txn.id));
+
+        db.delete_asset_permanently(asset.id).unwrap();
+        let query = AuditQuery {
+            operation: Some(AuditOperation::Delete),
+            limit: Some(1),
+            ..AuditQuery::default()
+        };
+        assert_eq!(db.query_audit_log(&query).unwrap().len(), 1);
+
+        // 清除超过保留天数的记录
+        let later = Utc::now() + chrono::Duration::days(400);
+        assert_eq!(db.prune_audit_log(later).unwrap(), 6);
+        assert!(db
+            .set_audit_settings(&AuditSettings {
+                retention_days: Some(0)
+            })
+            .is_err());
+
+        drop(db);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_rebuild_derived_data() {
         let mut db = Database::open_in_memory().unwrap();
+        let asset = Asset::new("活期", AssetType::BankDeposit, 100.0);
+        db.create_asset(&asset).unwrap();
+        db.enable_double_entry().unwrap();
+        db.adjust_asset_value(asset.id, 50.0, TransactionType::Income, None)
+            .unwrap();
+        let range = DateRange::new(asset.created_at, Utc::now());
+        assert_eq!(db.get_daily_balances(asset.id, &range).unwrap().len(), 1);
+
+        // 派生数据失效：每日余额丢失，资产价值被直接修改
+        db.daily_balances = Arc::default();
+        db.store_mut().assets.update(asset.id, |a| a.value = 200.0);
+        assert!(db.get_daily_balances(asset.id, &range).unwrap().is_empty());
+
+        let report = db.rebuild_derived_data().unwrap();
+        assert_eq!((report.assets, report.transactions), (1, 1));
+        assert_eq!(report.duplicates_removed, 0);
+        assert_eq!(report.ledger_adjustments, 1);
+        let balances = db.get_daily_balances(asset.id, &range).unwrap();
+        assert_eq!(balances.last().unwrap().value, 200.0);
+
+        // 再次重建没有需要修复的内容
+        let report = db.rebuild_derived_data().unwrap();
+        assert_eq!(report.ledger_adjustments, 0);
+    }
+
+    #[test]
+    fn test_compact() {
+        let dir = std::env::temp_dir().join(format!("asset-manager-db-{}", Uuid::new_v4()));
+        let path = dir.join("assets.json");
+        let journal = dir.join("assets.json.journal");
+        let mut db = Database::open(&path).unwrap();
+        let asset = Asset::new("活期", AssetType::BankDeposit, 0.0);
+        db.create_asset(&asset).unwrap();
+        for _ in 0..20 {
+            let txn = AssetTransaction::new(asset.id, TransactionType::Income, 0.0, 50.0);
+            db.add_transaction(&txn).unwrap();
+            db.delete_transaction(txn.id).unwrap();
+        }
+        // 所属资产已不存在的估值
+        db.store_mut()
+            .valuations
+            .insert(Valuation::new(Uuid::new_v4(), 1.0, Utc::now()));
+        db.save_now().unwrap();
+        assert!(journal.exists());
+
+        let report = db.compact().unwrap();
+        assert_eq!(report.records_removed, 1);
+        assert!(report.size_after < report.size_before);
+        assert!(!journal.exists());
+        assert_eq!(fs::metadata(&path).unwrap().len(), report.size_after);
+        drop(db);
+        let db = Database::open(&path).unwrap();
+        assert!(db.get_asset(asset.id).unwrap().is_some());
+        assert!(db.store.valuations.iter().all(|v| v.asset_id == asset.id));
+        drop(db);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_debounced_save() {
+        let dir = std::env::temp_dir().join(format!("asset-manager-db-{}", Uuid::new_v4()));
+        let path = dir.join("assets.json");
+        let journal = dir.join("assets.json.journal");
+
+        let mut db = Database::open(&path).unwrap();
+        db.set_save_settings(&SaveSettings {
+            mode: SaveMode::Debounced,
+            interval_secs: 60,
+            debounce_ms: 500,
+        })
+        .unwrap();
+
+        // 批量创建只在最后一次修改后等待片刻再写入一次
+        for i in 0..100 {
+            db.create_asset(&Asset::new(format!("资产{}", i), AssetType::Cash, 1.0))
+                .unwrap();
+        }
+        assert!(!journal.exists());
+        let last_change = db.last_change.unwrap();
+        assert!(!db
+            .autosave(last_change + chrono::Duration::milliseconds(100))
+            .unwrap());
+        assert!(db
+            .autosave(last_change + chrono::Duration::milliseconds(500))
+            .unwrap());
+        assert!(journal.exists());
+        assert!(!db.flush().unwrap());
 
+        // 持续修改时最迟在定时保存间隔后写入
         db.set_setting("theme", "dark").unwrap();
-        let val = db.get_setting("theme").unwrap();
-        assert_eq!(val, Some("dark".to_string()));
+        let since = db.save_status().unsaved_since.unwrap();
+        db.last_change = Some(since + chrono::Duration::seconds(59));
+        assert!(!db.autosave(since + chrono::Duration::seconds(59)).unwrap());
+        assert!(db.autosave(since + chrono::Duration::seconds(60)).unwrap());
 
-        let missing = db.get_setting("nonexistent").unwrap();
-        assert!(missing.is_none());
+        // 显式写入与关闭时写入
+        db.set_setting("theme", "light").unwrap();
+        assert!(db.flush().unwrap());
+        db.set_setting("currency", "USD").unwrap();
+        drop(db);
+        let db = Database::open(&path).unwrap();
+        assert_eq!(
+            db.list_assets(SortBy::default(), SortOrder::default())
+                .unwrap()
+                .len(),
+            100
+        );
+        assert_eq!(db.get_setting("theme").unwrap().as_deref(), Some("light"));
+        assert_eq!(db.get_setting("currency").unwrap().as_deref(), Some("USD"));
+
+        fs::remove_dir_all(&dir).unwrap();
     }
 
     #[test]
-    fn test_list_by_type() {
+    fn test_read_only() {
+        let dir = std::env::temp_dir().join(format!("asset-manager-db-{}", Uuid::new_v4()));
+        let path = dir.join("assets.json");
+        assert!(Database::open_read_only(&path).is_err());
+        let card = Asset::new("储蓄卡", AssetType::BankDeposit, 1000.0);
+        {
+            let mut db = Database::open(&path).unwrap();
+            db.create_asset(&card).unwrap();
+        }
+        let content = fs::read_to_string(&path).unwrap();
+
+        // 修改被拒绝，内存与文件中的数据都不变，也不占用锁文件
+        let mut db = Database::open_read_only(&path).unwrap();
+        assert!(db.is_read_only());
+        let cash = Asset::new("现金", AssetType::Cash, 50.0);
+        assert!(matches!(
+            db.create_asset(&cash),
+            Err(StorageError::ReadOnly)
+        ));
+        assert!(db.get_asset(cash.id).unwrap().is_none());
+        assert!(db.delete_asset(card.id).is_err());
+        assert_eq!(db.get_asset(card.id).unwrap().unwrap().name, "储蓄卡");
+        assert!(!dir.join("assets.json.lock").exists());
+
+        // 可查看文件中的修改历史，被拒绝的修改不留下记录
+        let mut renamed = card.clone();
+        renamed.name = "工资卡".to_string();
+        assert!(db.update_asset(&renamed).is_err());
+        let history = db.get_asset_history_diff(card.id).unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].action, AuditAction::Created);
+        let query = AuditQuery::for_entity(AuditEntity::Asset, card.id);
+        assert_eq!(db.query_audit_log(&query).unwrap().len(), 1);
+        drop(db);
+        assert_eq!(fs::read_to_string(&path).unwrap(), content);
+
+        // 旧格式的文件（如较早的备份）在副本上迁移，原文件不变
+        let old = dir.join("old.json");
+        let old_content = r#"{"assets": [], "transactions": [], "settings": {}}"#;
+        fs::write(&old, old_content).unwrap();
+        let db = Database::open_read_only(&old).unwrap();
+        assert!(db.save_status().read_only);
+        drop(db);
+        assert_eq!(fs::read_to_string(&old).unwrap(), old_content);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_external_modification_conflict() {
+        let dir = std::env::temp_dir().join(format!("asset-manager-db-{}", Uuid::new_v4()));
+        let path = dir.join("assets.json");
+
+        // 两个实例同时打开同一数据文件（如两台设备通过同步目录）
+        let mut mine = Database::open(&path).unwrap();
+        let mut theirs = Database::open(&path).unwrap();
+        assert!(mine.lock_holder().is_none());
+        assert!(theirs.save_status().locked_by.is_some());
+
+        let their_asset = Asset::new("对方", AssetType::Cash, 1.0);
+        theirs.create_asset(&their_asset).unwrap();
+        let my_asset = Asset::new("本机", AssetType::Cash, 2.0);
+        assert!(matches!(
+            mine.create_asset(&my_asset),
+            Err(StorageError::Conflict(_))
+        ));
+        assert!(mine.save_status().conflict);
+        assert!(mine.save_now().is_err());
+
+        let conflict = mine.store_conflict().unwrap();
+        assert_eq!(conflict.differences.len(), 2);
+        assert_eq!(
+            (conflict.mine.asset_count, conflict.theirs.asset_count),
+            (1, 1)
+        );
+
+        // 保留本机版本后，另一实例写入时同样发现冲突并可改用磁盘版本
+        mine.resolve_store_conflict(ConflictResolution::KeepMine)
+            .unwrap();
+        assert!(!mine.has_unsaved_changes());
+        assert!(theirs.set_setting("theme", "dark").is_err());
+        theirs
+            .resolve_store_conflict(ConflictResolution::KeepTheirs)
+            .unwrap();
+        let names: Vec<String> = theirs
+            .list_assets(SortBy::default(), SortOrder::default())
+            .unwrap()
+            .into_iter()
+            .map(|a| a.name)
+            .collect();
+        assert_eq!(names, vec!["本机"]);
+        assert!(theirs
+            .resolve_store_conflict(ConflictResolution::KeepMine)
+            .is_err());
+
+        drop((mine, theirs));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_reload_external_changes() {
+        let dir = std::env::temp_dir().join(format!("asset-manager-db-{}", Uuid::new_v4()));
+        let path = dir.join("assets.json");
+        let mut viewer = Database::open(&path).unwrap();
+        let mut editor = Database::open(&path).unwrap();
+        assert_eq!(
+            viewer.reload_if_changed().unwrap(),
+            ReloadOutcome::Unchanged
+        );
+
+        // 另一实例追加日志后，没有未保存修改的实例直接重新加载
+        let asset = Asset::new("活期", AssetType::BankDeposit, 10.0);
+        editor.create_asset(&asset).unwrap();
+        assert_eq!(viewer.reload_if_changed().unwrap(), ReloadOutcome::Reloaded);
+        assert_eq!(viewer.get_asset(asset.id).unwrap().unwrap().value, 10.0);
+        assert_eq!(
+            viewer.reload_if_changed().unwrap(),
+            ReloadOutcome::Unchanged
+        );
+
+        // 重新加载后本实例的写入不被视为冲突
+        let with_value = |value: f64| Asset {
+            value,
+            ..asset.clone()
+        };
+        viewer.update_asset(&with_value(20.0)).unwrap();
+        assert_eq!(editor.reload_if_changed().unwrap(), ReloadOutcome::Reloaded);
+        assert_eq!(editor.get_asset(asset.id).unwrap().unwrap().value, 20.0);
+
+        // 有未保存的修改时记为冲突，不覆盖内存中的数据（保存设置随数据文件同步）
+        let manual = SaveSettings {
+            mode: SaveMode::Manual,
+            interval_secs: 60,
+            ..Default::default()
+        };
+        viewer.set_save_settings(&manual).unwrap();
+        assert_eq!(editor.reload_if_changed().unwrap(), ReloadOutcome::Reloaded);
+        assert_eq!(editor.get_save_settings().unwrap().mode, SaveMode::Manual);
+        viewer.update_asset(&with_value(30.0)).unwrap();
+        editor.update_asset(&with_value(40.0)).unwrap();
+        editor.save_now().unwrap();
+        assert_eq!(viewer.reload_if_changed().unwrap(), ReloadOutcome::Conflict);
+        assert_eq!(viewer.get_asset(asset.id).unwrap().unwrap().value, 30.0);
+        assert!(viewer.store_conflict().is_some());
+        assert_eq!(
+            viewer.reload_if_changed().unwrap(),
+            ReloadOutcome::Unchanged
+        );
+
+        drop((viewer, editor));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_typed_settings_and_subscriptions() {
+        use std::sync::Mutex;
+
+        let dir = std::env::temp_dir().join(format!("asset-manager-db-{}", Uuid::new_v4()));
+        let path = dir.join("assets.json");
+        let mut db = Database::open(&path).unwrap();
+        let mut other = Database::open(&path).unwrap();
+        assert_eq!(db.get_settings(FX_SETTINGS).unwrap(), FxSettings::default());
+
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&seen);
+        let id = other.subscribe_settings(FX_SETTINGS, move |fx: &FxSettings| {
+            sink.lock()
+                .unwrap()
+                .push(fx.base_currency.code().to_string())
+        });
+        let keys = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&keys);
+        other.subscribe_all_settings(move |key, _| sink.lock().unwrap().push(key.to_string()));
+
+        // 本实例修改设置时立即通知，值未变化时不通知
+        let fx = FxSettings {
+            base_currency: Currency::USD,
+            ..Default::default()
+        };
+        other.set_settings(FX_SETTINGS, &fx).unwrap();
+        other.set_settings(FX_SETTINGS, &fx).unwrap();
+        assert_eq!(*seen.lock().unwrap(), vec!["USD"]);
+
+        // 重新加载数据文件带来的变化同样通知
+        db.reload_if_changed().unwrap();
+        db.set_setting("theme", "dark").unwrap();
+        db.set_settings(FX_SETTINGS, &FxSettings::default())
+            .unwrap();
+        assert_eq!(other.reload_if_changed().unwrap(), ReloadOutcome::Reloaded);
+        assert_eq!(*seen.lock().unwrap(), vec!["USD", "CNY"]);
+        let mut changed = keys.lock().unwrap().clone();
+        changed.sort();
+        assert_eq!(changed, vec!["fx", "fx", "theme"]);
+
+        assert!(other.unsubscribe_settings(id));
+        assert!(!other.unsubscribe_settings(id));
+        drop((db, other));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_setting_as() {
+        use crate::settings::SettingsKey;
+
         let mut db = Database::open_in_memory().unwrap();
+        assert_eq!(
+            db.get_setting_as::<FxSettings>(SettingsKey::Fx).unwrap(),
+            None
+        );
+        let fx = FxSettings {
+            base_currency: Currency::USD,
+            ..Default::default()
+        };
+        db.set_setting_as(SettingsKey::Fx, &fx).unwrap();
+        assert_eq!(db.get_settings(FX_SETTINGS).unwrap(), fx);
+        db.set_setting_as("window", &(1280, 720)).unwrap();
+        assert_eq!(
+            db.get_setting_as::<(u32, u32)>("window").unwrap(),
+            Some((1280, 720))
+        );
+        // 类型不符时报错
+        assert!(db.get_setting_as::<bool>("window").is_err());
 
-        db.create_asset(&Asset::new("股票A", AssetType::Stock, 5000.0)).unwrap();
-        db.create_asset(&Asset::new("现金", AssetType::Cash, 3000.0)).unwrap();
-        db.create_asset(&Asset::new("股票B", AssetType::Stock, 8000.0)).unwrap();
+        assert_eq!(
+            SettingsKey::from_key("report_email"),
+            Some(SettingsKey::ReportEmail)
+        );
+        assert_eq!(SettingsKey::from_key("window"), None);
+        for key in SettingsKey::ALL {
+            let name = serde_json::to_value(key).unwrap();
+            assert_eq!(name, key.as_str());
+        }
+    }
 
-        let stocks = db.list_assets_by_type(&AssetType::Stock).unwrap();
-        assert_eq!(stocks.len(), 2);
+    #[test]
+    fn test_snapshot_isolation() {
+        let mut db = Database::open_in_memory().unwrap();
+        let asset = Asset::new("活期", AssetType::BankDeposit, 10.0);
+        db.create_asset(&asset).unwrap();
 
-        let cash = db.list_assets_by_type(&AssetType::Cash).unwrap();
-        assert_eq!(cash.len(), 1);
+        let snapshot = db.snapshot();
+        assert!(Arc::ptr_eq(&db.store, &snapshot.store));
+
+        // 快照创建后的修改不影响快照
+        db.update_asset(&Asset {
+            value: 20.0,
+            ..asset.clone()
+        })
+        .unwrap();
+        db.add_transaction(&AssetTransaction::new(
+            asset.id,
+            TransactionType::Income,
+            20.0,
+            30.0,
+        ))
+        .unwrap();
+        assert_eq!(snapshot.get_asset(asset.id).unwrap().unwrap().value, 10.0);
+        let query = TransactionQuery::default();
+        assert!(snapshot
+            .get_transactions(asset.id, &query)
+            .unwrap()
+            .is_empty());
+        assert_eq!(snapshot.get_summary().unwrap().total_value, 10.0);
+        assert_eq!(db.get_transactions(asset.id, &query).unwrap().len(), 1);
+        assert_eq!(db.get_summary().unwrap().total_value, 20.0);
+        assert!(snapshot.taken_at() <= Utc::now());
+    }
+
+    #[test]
+    fn test_backup_and_migration_on_open() {
+        let dir = std::env::temp_dir().join(format!("asset-manager-db-{}", Uuid::new_v4()));
+        let path = dir.join("assets.json");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(&path, r#"{"assets": [], "transactions": [], "settings": {}}"#).unwrap();
+
+        // 打开旧格式文件时补上迁移
+        let mut db = Database::open(&path).unwrap();
+        assert_eq!(db.store.schema_version, SCHEMA_VERSION);
+        assert_eq!(migration::file_schema_version(&path).unwrap(), SCHEMA_VERSION);
+
+        let asset = Asset::new("活期", AssetType::BankDeposit, 10.0);
+        db.create_asset(&asset).unwrap();
+        let backup = db.backup_to(dir.join("backups")).unwrap();
+        assert_eq!((backup.assets, backup.transactions), (1, 0));
+        assert_eq!(backup.schema_version, SCHEMA_VERSION);
+
+        drop(db);
+        let restored = Database::open(&backup.path).unwrap();
+        assert_eq!(restored.get_asset(asset.id).unwrap().unwrap().name, "活期");
+
+        drop(restored);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_scheduled_backups() {
+        let dir = std::env::temp_dir().join(format!("asset-manager-db-{}", Uuid::new_v4()));
+        let backups = dir.join("backups");
+        let mut db = Database::open(dir.join("assets.json")).unwrap();
+        let asset = Asset::new("活期", AssetType::BankDeposit, 10.0);
+        db.create_asset(&asset).unwrap();
+        db.set_backup_settings(&BackupSettings {
+            keep: 2,
+            ..BackupSettings::default()
+        })
+        .unwrap();
+        assert!(db
+            .set_backup_settings(&BackupSettings {
+                keep: 0,
+                ..BackupSettings::default()
+            })
+            .is_err());
+
+        // 距最新一份备份不足间隔时跳过，超过保留份数时删除最旧的
+        let t0 = Utc.with_ymd_and_hms(2024, 1, 1, 3, 0, 0).unwrap();
+        assert!(db.scheduled_backup(&backups, t0).unwrap().is_some());
+        let hours = chrono::Duration::hours;
+        assert!(db
+            .scheduled_backup(&backups, t0 + hours(12))
+            .unwrap()
+            .is_none());
+        let second = db
+            .scheduled_backup(&backups, t0 + hours(24))
+            .unwrap()
+            .unwrap();
+        db.scheduled_backup(&backups, t0 + hours(48))
+            .unwrap()
+            .unwrap();
+        let listed = db.list_backups(&backups).unwrap();
+        assert_eq!(listed.len(), 2);
+        assert_eq!(listed[1].path, second.path);
+        assert_eq!(listed[1].assets, 1);
+
+        // 恢复前备份当前数据
+        db.delete_asset(asset.id).unwrap();
+        let before = db.restore_backup(&backups, &second.path).unwrap();
+        assert_eq!(before.assets, 0);
+        assert!(db.get_asset(asset.id).unwrap().is_some());
+        assert_eq!(db.list_backups(&backups).unwrap().len(), 3);
+        assert!(db
+            .restore_backup(&backups, dir.join("assets.json"))
+            .is_err());
+
+        drop(db);
+        let reopened = Database::open(dir.join("assets.json")).unwrap();
+        assert!(reopened.get_asset(asset.id).unwrap().is_some());
+        drop(reopened);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_sell_asset() {
+        let mut db = Database::open_in_memory().unwrap();
+        let stock = Asset::new("招商银行", AssetType::Stock, 2000.0);
+        let cash = Asset::new("证券账户现金", AssetType::Cash, 0.0);
+        db.create_asset(&stock).unwrap();
+        db.create_asset(&cash).unwrap();
+        db.add_transactions(&[
+            AssetTransaction::new(stock.id, TransactionType::Buy, 0.0, 1000.0)
+                .with_timestamp(Utc::now() - chrono::Duration::days(30)),
+            AssetTransaction::new(stock.id, TransactionType::ValueChange, 1000.0, 2000.0)
+                .with_timestamp(Utc::now() - chrono::Duration::days(1)),
+        ])
+        .unwrap();
+
+        let mut order = SellOrder {
+            asset_id: stock.id,
+            gross: 1000.0,
+            fee: 5.0,
+            tax: 1.0,
+            pay_to: Some(cash.id),
+            note: None,
+        };
+        let record = db.sell_asset(&order).unwrap();
+        assert_eq!(record.sale.delta(), -1000.0);
+        assert_eq!(record.charges.len(), 2);
+        assert_eq!(record.net_proceeds, 994.0);
+        assert_eq!(db.get_asset(stock.id).unwrap().unwrap().value, 1000.0);
+        // 收款资产收到卖出金额并扣除费用
+        assert_eq!(db.get_asset(cash.id).unwrap().unwrap().value, 994.0);
+
+        // 卖出款不在账内时费用只记录，不改变资产价值
+        order.pay_to = None;
+        order.gross = 500.0;
+        order.tax = 0.0;
+        let record = db.sell_asset(&order).unwrap();
+        assert_eq!(record.charges[0].delta(), 0.0);
+        assert_eq!(record.charges[0].asset_id, stock.id);
+        assert_eq!(db.get_asset(stock.id).unwrap().unwrap().value, 500.0);
+        order.gross = 600.0;
+        assert!(db.sell_asset(&order).is_err());
+
+        let range = DateRange::new(Utc::now() - chrono::Duration::days(7), Utc::now());
+        let report = db
+            .get_realized_gains(&range, CostMethod::WeightedAverage)
+            .unwrap();
+        assert_eq!(report.sales.len(), 2);
+        // 第一笔结转成本 500，扣除手续费后盈利 495
+        assert_eq!(report.sales[0].gain, 495.0);
+        assert_eq!((report.total_fees, report.total_taxes), (10.0, 1.0));
+    }
+
+    #[test]
+    fn test_import_trades() {
+        let mut db = Database::open_in_memory().unwrap();
+        let cash = Asset::new("证券账户现金", AssetType::Cash, 10000.0);
+        let held = Asset::new("招商银行", AssetType::Stock, 3000.0)
+            .with_metadata(serde_json::json!({ "shares": 100 }));
+        db.create_asset(&cash).unwrap();
+        db.create_asset(&held).unwrap();
+
+        let mut profile = TradeProfile::new("券商", "date", "symbol", "qty", "price");
+        profile.fee_column = Some("fee".to_string());
+        profile.cash_account = Some(cash.id);
+        profile.rules.push(crate::import::SymbolRule {
+            pattern: "600036".to_string(),
+            asset_id: held.id,
+        });
+        db.save_trade_profile(&profile).unwrap();
+        assert_eq!(db.list_trade_profiles().unwrap().len(), 1);
+
+        let content = "date,symbol,qty,price,fee\n\
+                       2024-03-01,AAPL,10,100,1\n\
+                       2024-03-02,AAPL,5,120,1\n\
+                       2024-03-03,600036,-40,35,2\n\
+                       2024-03-04,MSFT,-1,400,0\n\
+                       2024-03-05,AAPL,-20,130,1\n";
+        let parsed = profile.parse_csv(content);
+        assert_eq!(parsed.trades.len(), 5);
+        let batch = ImportBatch::new(ImportSource::Trades);
+        let result = db.import_trades(&profile, &parsed.trades, &batch).unwrap();
+        assert_eq!(result.imported, 3);
+        assert_eq!(result.created_assets.len(), 1);
+        // 没有持仓的卖出与超过持仓的卖出记入错误
+        assert_eq!(result.errors.len(), 2);
+
+        let apple = db.get_asset(result.created_assets[0]).unwrap().unwrap();
+        assert_eq!(apple.metadata["ticker"], "AAPL");
+        // 第二次买入前按成交价重估：10 股 × 120
+        assert_eq!(apple.value, 1800.0);
+        assert_eq!(apple.metadata["lots"].as_array().unwrap().len(), 2);
+        // 已有持仓按成交价重估到 3500 后卖出 40 股
+        let bank = db.get_asset(held.id).unwrap().unwrap();
+        assert_eq!(bank.metadata["shares"], 60.0);
+        assert_eq!(bank.value, 2100.0);
+        // 现金：-1000 - 600 + 1400，扣除三笔手续费
+        let cash_value = db.get_asset(cash.id).unwrap().unwrap().value;
+        assert_eq!(cash_value, 10000.0 - 1000.0 - 600.0 + 1400.0 - 4.0);
+
+        // 卖出的手续费关联到卖出交易
+        let range = DateRange::new(
+            Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2024, 12, 31, 0, 0, 0).unwrap(),
+        );
+        let gains = db
+            .get_realized_gains(&range, CostMethod::WeightedAverage)
+            .unwrap();
+        assert_eq!(gains.sales.len(), 1);
+        assert_eq!(gains.total_fees, 2.0);
+
+        // 再次导入同一批成交全部跳过，撤销导入移除新建的资产
+        let again = db
+            .import_trades(
+                &profile,
+                &parsed.trades,
+                &ImportBatch::new(ImportSource::Trades),
+            )
+            .unwrap();
+        assert_eq!((again.imported, again.duplicates), (0, 3));
+        db.undo_import(batch.id).unwrap();
+        assert!(db.get_asset(apple.id).unwrap().is_none());
+        assert_eq!(db.get_asset(cash.id).unwrap().unwrap().value, 10000.0);
+
+        db.delete_trade_profile(profile.id).unwrap();
+        assert!(db.get_trade_profile(profile.id).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_recalculate_cost_basis() {
+        let mut db = Database::open_in_memory().unwrap();
+        let fund = Asset::new("沪深300", AssetType::Fund, 0.0);
+        db.create_asset(&fund).unwrap();
+        let start = Utc::now() - chrono::Duration::days(30);
+        db.add_transactions(&[
+            AssetTransaction::new(fund.id, TransactionType::Buy, 0.0, 1000.0).with_timestamp(start),
+            AssetTransaction::new(fund.id, TransactionType::ValueChange, 1000.0, 2000.0)
+                .with_timestamp(start + chrono::Duration::days(1)),
+            AssetTransaction::new(fund.id, TransactionType::Sell, 2000.0, 1000.0)
+                .with_timestamp(start + chrono::Duration::days(2)),
+        ])
+        .unwrap();
+        // 手动改过的成本
+        let mut edited = db.get_asset(fund.id).unwrap().unwrap();
+        edited.cost_basis = Some(800.0);
+        db.update_asset(&edited).unwrap();
+
+        let report = db
+            .recalculate_cost_basis(fund.id, CostMethod::WeightedAverage)
+            .unwrap();
+        assert_eq!(report.previous, Some(800.0));
+        assert_eq!(report.cost_basis, 500.0);
+        assert_eq!(report.difference, -300.0);
+        assert_eq!(report.realized_gain, 500.0);
+        assert_eq!(
+            db.get_asset(fund.id).unwrap().unwrap().cost_basis,
+            Some(500.0)
+        );
+        assert!(db
+            .recalculate_cost_basis(Uuid::new_v4(), CostMethod::Fifo)
+            .is_err());
+    }
+
+    #[test]
+    fn test_corporate_actions() {
+        let mut db = Database::open_in_memory().unwrap();
+        let stock = Asset::new("苹果", AssetType::Stock, 2000.0)
+            .with_metadata(serde_json::json!({ "ticker": "AAPL", "shares": 10 }))
+            .with_cost_basis(1500.0);
+        let cash = Asset::new("证券账户现金", AssetType::Cash, 0.0);
+        db.create_asset(&stock).unwrap();
+        db.create_asset(&cash).unwrap();
+
+        // 拆股只调整数量，价值与成本不变
+        let split = db
+            .apply_corporate_action(stock.id, &CorporateAction::Split { from: 1, to: 2 })
+            .unwrap();
+        assert_eq!(split.delta(), 0.0);
+        let loaded = db.get_asset(stock.id).unwrap().unwrap();
+        assert_eq!(loaded.metadata["shares"], 20.0);
+        assert_eq!((loaded.value, loaded.cost_basis), (2000.0, Some(1500.0)));
+
+        // 分红记入现金账户，已录入的不再重复
+        let today = Utc::now().date_naive();
+        let events = [
+            DividendEvent {
+                date: today - chrono::Duration::days(400),
+                per_share: 1.0,
+            },
+            DividendEvent {
+                date: today,
+                per_share: 0.5,
+            },
+        ];
+        let recorded = db
+            .record_dividends(stock.id, &events, Some(cash.id))
+            .unwrap();
+        assert_eq!(recorded.len(), 1);
+        assert_eq!(recorded[0].asset_id, cash.id);
+        assert_eq!(db.get_asset(cash.id).unwrap().unwrap().value, 10.0);
+        assert!(db
+            .record_dividends(stock.id, &events, Some(cash.id))
+            .unwrap()
+            .is_empty());
+
+        assert!(db
+            .apply_corporate_action(cash.id, &CorporateAction::Split { from: 1, to: 2 })
+            .is_err());
+    }
+
+    #[test]
+    fn test_sip_plans() {
+        use crate::asset::Recurrence;
+
+        let mut db = Database::open_in_memory().unwrap();
+        let fund = Asset::new("沪深300指数基金", AssetType::Fund, 0.0)
+            .with_metadata(serde_json::json!({ "code": "110020" }));
+        let card = Asset::new("储蓄卡", AssetType::BankDeposit, 10000.0);
+        db.create_asset(&fund).unwrap();
+        db.create_asset(&card).unwrap();
+
+        let first = Utc.with_ymd_and_hms(2024, 1, 10, 0, 0, 0).unwrap();
+        let plan = SipPlan::new(fund.id, 1000.0, Recurrence::Monthly, first).with_pay_from(card.id);
+        db.add_sip_plan(&plan).unwrap();
+        assert_eq!(db.get_due_sip_plans(first).unwrap().len(), 1);
+
+        let installment = db
+            .record_sip_installment(plan.id, first, 1.0, 10.0)
+            .unwrap();
+        assert_eq!(installment.units, 990.0);
+        let second = Utc.with_ymd_and_hms(2024, 2, 10, 0, 0, 0).unwrap();
+        db.record_sip_installment(plan.id, second, 0.9, 0.0)
+            .unwrap();
+        assert!(db.get_due_sip_plans(second).unwrap().is_empty());
+
+        let loaded = db.get_asset(fund.id).unwrap().unwrap();
+        assert_eq!(loaded.value, 1990.0);
+        assert!((loaded.metadata["units"].as_f64().unwrap() - 2101.111).abs() < 1e-3);
+        assert_eq!(loaded.metadata["nav"], 0.9);
+        assert_eq!(db.get_asset(card.id).unwrap().unwrap().value, 8000.0);
+        let buys = db.get_transactions(fund.id, &Default::default()).unwrap();
+        assert!(buys.iter().any(|t| t.timestamp == first));
+
+        let report = db.get_sip_report(plan.id, second).unwrap();
+        assert_eq!(report.installments, 2);
+        assert_eq!(report.total_invested, 2000.0);
+        assert_eq!(report.total_fees, 10.0);
+        assert!(report.profit < 0.0);
+        assert!(report.irr.unwrap() < 0.0);
+
+        // 更新设置保留已执行的各期
+        let updated = db
+            .update_sip_plan(&SipPlan {
+                amount: 2000.0,
+                ..plan.clone()
+            })
+            .unwrap();
+        assert_eq!(updated.installments.len(), 2);
+        assert!(db
+            .add_sip_plan(&SipPlan::new(card.id, 100.0, Recurrence::Monthly, first))
+            .is_err());
+
+        db.delete_asset(card.id).unwrap();
+        assert!(db.list_sip_plans(Some(fund.id)).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_automation_rules() {
+        use crate::rules::{Comparison, RuleCondition, RuleEvent};
+
+        let mut db = Database::open_in_memory().unwrap();
+        let now = Utc::now();
+        let stock = Asset::new("招商银行", AssetType::Stock, 800.0);
+        db.create_asset(&stock).unwrap();
+
+        // 事件触发：新建股票时加标签并调用插件
+        let tag_rule = Rule::new(
+            "标记股票",
+            RuleTrigger::Event {
+                event: RuleEvent::AssetCreated,
+            },
+        )
+        .when(RuleCondition::AssetType {
+            asset_type: AssetType::Stock,
+        })
+        .then(RuleAction::AddTag { tag: "A股".into() })
+        .then(RuleAction::CallPlugin {
+            plugin: "prices".into(),
+            handler: "sync".into(),
+            args: serde_json::Value::Null,
+        });
+        db.save_rule(&tag_rule).unwrap();
+        assert!(db.save_rule(&Rule::new("空规则", RuleTrigger::Condition)).is_err());
+
+        let signal = RuleSignal::Event {
+            event: RuleEvent::AssetCreated,
+            asset_id: stock.id,
+        };
+        let runs = db.run_rules(signal, now).unwrap();
+        assert_eq!(runs.len(), 1);
+        assert_eq!(runs[0].plugin_calls[0].args["asset"]["name"], "招商银行");
+        assert_eq!(db.get_asset(stock.id).unwrap().unwrap().tags, vec!["A股"]);
+        assert!(db.run_rules(RuleSignal::Tick, now).unwrap().is_empty());
+
+        // 条件触发：跌破阈值时只通知一次
+        let alert = Rule::new("跌破", RuleTrigger::Condition)
+            .when(RuleCondition::AssetValue {
+                op: Comparison::Below,
+                value: 500.0,
+            })
+            .then(RuleAction::Notify {
+                title: "{asset} 跌破 500".into(),
+                message: None,
+            });
+        db.save_rule(&alert).unwrap();
+        assert!(db.run_rules(RuleSignal::Tick, now).unwrap().is_empty());
+        db.adjust_asset_value(stock.id, -400.0, TransactionType::ValueChange, None)
+            .unwrap();
+        assert_eq!(db.run_rules(RuleSignal::Tick, now).unwrap().len(), 1);
+        assert!(db.run_rules(RuleSignal::Tick, now).unwrap().is_empty());
+        let notifications = db.list_notifications(true).unwrap();
+        assert_eq!(notifications[0].title, "招商银行 跌破 500");
+        assert_eq!(notifications[0].kind, NotificationKind::Rule);
+
+        // 动作失败记录在规则上
+        let broken = Rule::new("整体加标签", RuleTrigger::Condition)
+            .then(RuleAction::AddTag { tag: "x".into() });
+        db.save_rule(&broken).unwrap();
+        let runs = db.run_rules(RuleSignal::Tick, now).unwrap();
+        assert!(runs[0].error.is_some());
+        let rules = db.list_rules().unwrap();
+        assert!(rules.iter().find(|r| r.id == broken.id).unwrap().last_error.is_some());
+
+        db.delete_rule(broken.id).unwrap();
+        assert_eq!(db.list_rules().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_tag_normalization() {
+        let mut db = Database::open_in_memory().unwrap();
+        let tags = |list: &[&str]| list.iter().map(|t| t.to_string()).collect::<Vec<_>>();
+
+        let fund = Asset::new("沪深300", AssetType::Fund, 1000.0).with_tags(tags(&["Index"]));
+        db.create_asset(&fund).unwrap();
+        let stock = Asset::new("招商银行", AssetType::Stock, 800.0)
+            .with_tags(tags(&[" index ", "A股", "a股"]));
+        db.create_asset(&stock).unwrap();
+        assert_eq!(
+            db.get_asset(stock.id).unwrap().unwrap().tags,
+            tags(&["Index", "A股"])
+        );
+
+        // 添加别名时替换已有资产上的标签
+        assert_eq!(db.set_tag_alias("A股", "CN Stocks").unwrap(), 1);
+        assert_eq!(
+            db.get_asset(stock.id).unwrap().unwrap().tags,
+            tags(&["Index", "CN Stocks"])
+        );
+        let moutai = Asset::new("贵州茅台", AssetType::Stock, 1500.0).with_tags(tags(&["a股"]));
+        let upsert = db.upsert_asset(&moutai).unwrap();
+        assert_eq!(upsert.asset.tags, tags(&["CN Stocks"]));
+        assert!(db.set_tag_alias("cn stocks", "A股").is_err());
+
+        db.remove_tag_alias("A股").unwrap();
+        assert!(db.list_tag_aliases().unwrap().is_empty());
+        assert!(matches!(
+            db.remove_tag_alias("A股"),
+            Err(StorageError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_display_styles() {
+        let mut db = Database::open_in_memory().unwrap();
+        let style = |color: &str| DisplayMeta {
+            color: Some(color.to_string()),
+            ..DisplayMeta::default()
+        };
+        let stock = Asset::new("招商银行", AssetType::Stock, 800.0).with_tags(vec!["A股".into()]);
+        db.create_asset(&stock).unwrap();
+
+        let stock_type = DisplayTarget::AssetType {
+            code: "Stock".into(),
+        };
+        db.set_display(&stock_type, &style("#336699")).unwrap();
+        let tag = DisplayTarget::Tag {
+            tag: " a股 ".into(),
+        };
+        db.set_display(&tag, &style("#e33")).unwrap();
+        db.set_display(
+            &DisplayTarget::Category {
+                category: TransactionType::Income,
+            },
+            &style("#0a0"),
+        )
+        .unwrap();
+        let catalog = db.get_display_catalog().unwrap();
+        assert_eq!(catalog.asset_types["stock"], style("#336699"));
+        assert_eq!(catalog.tags["A股"], style("#e33"));
+        assert_eq!(catalog.categories["income"], style("#0a0"));
+
+        let asset = db.get_asset(stock.id).unwrap().unwrap();
+        assert_eq!(db.resolve_display(&asset), style("#e33"));
+        db.set_display(&DisplayTarget::Asset { id: stock.id }, &style("#000"))
+            .unwrap();
+        let asset = db.get_asset(stock.id).unwrap().unwrap();
+        assert_eq!(asset.display, style("#000"));
+
+        // 标签改名后样式随之迁移
+        db.set_tag_alias("A股", "CN Stocks").unwrap();
+        assert_eq!(
+            db.get_display_catalog().unwrap().tags["CN Stocks"],
+            style("#e33")
+        );
+
+        assert!(db.set_display(&stock_type, &style("blue")).is_err());
+        db.set_display(&stock_type, &DisplayMeta::default())
+            .unwrap();
+        assert!(db.get_display_catalog().unwrap().asset_types.is_empty());
+        assert!(matches!(
+            db.set_display(&DisplayTarget::Asset { id: Uuid::new_v4() }, &style("#000")),
+            Err(StorageError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_currency_conversion() {
+        let mut db = Database::open_in_memory().unwrap();
+        let mut rates = HashMap::new();
+        rates.insert("EUR".to_string(), 8.0);
+        db.set_fx_settings(&FxSettings {
+            base_currency: Currency::CNY,
+            rates,
+        })
+        .unwrap();
+        let date = NaiveDate::from_ymd_opt(2024, 6, 1).unwrap();
+        db.upsert_fx_rates(&[FxRate::new(&Currency::USD, &Currency::CNY, date, 7.2)])
+            .unwrap();
+
+        let usd = Asset::new("美股账户", AssetType::Stock, 100.0).with_currency(Currency::USD);
+        db.create_asset(&usd).unwrap();
+        let cash = Asset::new("现金", AssetType::Cash, 500.0);
+        db.create_asset(&cash).unwrap();
+
+        let conversion = db.get_asset(usd.id).unwrap().unwrap().conversion.unwrap();
+        assert_eq!(conversion.currency, Currency::CNY);
+        assert!((conversion.converted_value - 720.0).abs() < 1e-9);
+        assert_eq!(conversion.rate_date, Some(date));
+        assert!(db.get_asset(cash.id).unwrap().unwrap().conversion.is_none());
+
+        // 指定显示货币时按交叉汇率折算；计算字段不写入存储
+        let mut asset = db.get_asset(usd.id).unwrap().unwrap();
+        asset.display_currency = Some(Currency::EUR);
+        db.update_asset(&asset).unwrap();
+        assert!(db.store.assets.get(usd.id).unwrap().conversion.is_none());
+        let conversion = db.get_asset(usd.id).unwrap().unwrap().conversion.unwrap();
+        assert!((conversion.rate - 0.9).abs() < 1e-9);
+        assert!((conversion.converted_value - 90.0).abs() < 1e-9);
+        assert_eq!(conversion.rate_date, Some(date));
+
+        // 缺少汇率时不折算
+        let gbp = Asset::new("英镑存款", AssetType::BankDeposit, 10.0).with_currency(Currency::GBP);
+        db.create_asset(&gbp).unwrap();
+        assert!(db.get_asset(gbp.id).unwrap().unwrap().conversion.is_none());
+    }
+
+    #[test]
+    fn test_milestones() {
+        let mut db = Database::open_in_memory().unwrap();
+        let now = Utc::now();
+        let mut cash = Asset::new("存款", AssetType::BankDeposit, 150_000.0);
+        cash.created_at = now - chrono::Duration::days(10);
+        db.create_asset(&cash).unwrap();
+        let mut loan = Asset::new("车贷", AssetType::Other("liability".into()), 0.0);
+        loan.created_at = now - chrono::Duration::days(10);
+        db.create_asset(&loan).unwrap();
+        db.add_transaction(
+            &AssetTransaction::new(loan.id, TransactionType::Buy, -20_000.0, 0.0)
+                .with_timestamp(now - chrono::Duration::days(5)),
+        )
+        .unwrap();
+
+        // 10 天前达成的净资产里程碑只记录，5 天前还清负债发送通知
+        let created = db.check_milestones(now).unwrap();
+        assert_eq!(created.len(), 1);
+        assert_eq!(created[0].kind, NotificationKind::Milestone);
+        let milestones = db.list_milestones().unwrap();
+        assert_eq!(milestones.len(), 3);
+        assert_eq!(milestones[2].title, "还清全部负债");
+        assert_eq!(
+            milestones[2].achieved_on,
+            (now - chrono::Duration::days(5)).date_naive()
+        );
+        assert!(db.check_milestones(now).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_check_ins() {
+        let mut db = Database::open_in_memory().unwrap();
+        db.create_asset(&Asset::new("存款", AssetType::BankDeposit, 1000.0))
+            .unwrap();
+        let date = |d: u32| NaiveDate::from_ymd_opt(2024, 8, d).unwrap();
+
+        let empty = CheckIn::new(date(1), " ");
+        assert!(db.save_check_in(&empty, false).is_err());
+        let crash = CheckIn::new(date(5), "大跌，没有恐慌卖出").with_mood(Mood::Bad);
+        let saved = db.save_check_in(&crash, true).unwrap();
+        assert_eq!(saved.snapshot.as_ref().unwrap().total_value, 1000.0);
+        let weekly = CheckIn::new(date(9), "本周复盘").with_period(CheckInPeriod::Weekly);
+        db.save_check_in(&weekly, false).unwrap();
+
+        // 修改时保留创建时间与快照
+        let mut edited = saved.clone();
+        edited.notes = "大跌，坚持定投".to_string();
+        db.save_check_in(&edited, false).unwrap();
+        let entries = db.list_check_ins(None, None).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].id, weekly.id);
+        assert_eq!(entries[1].notes, "大跌，坚持定投");
+        assert!(entries[1].snapshot.is_some());
+        assert_eq!(entries[1].created_at, crash.created_at);
+
+        assert_eq!(db.list_check_ins(None, Some(date(6))).unwrap().len(), 1);
+        db.delete_check_in(crash.id).unwrap();
+        let early = db.list_check_ins(Some(date(1)), Some(date(8))).unwrap();
+        assert!(early.is_empty());
+    }
+
+    #[test]
+    fn test_attachments() {
+        let dir = std::env::temp_dir().join(format!("asset-manager-db-{}", Uuid::new_v4()));
+        let files = dir.join("attachments");
+        fs::create_dir_all(&dir).unwrap();
+        let receipt = dir.join("receipt.pdf");
+        fs::write(&receipt, "receipt").unwrap();
+
+        let mut db = Database::open_in_memory().unwrap();
+        let house = Asset::new("房产", AssetType::RealEstate, 1_000_000.0);
+        db.create_asset(&house).unwrap();
+        assert!(db
+            .add_attachment(&files, Uuid::new_v4(), &receipt, None)
+            .is_err());
+
+        // 相同内容只存一份，删除最后一个引用时删除文件
+        let first = db
+            .add_attachment(&files, house.id, &receipt, Some("购房收据".into()))
+            .unwrap();
+        let second = db.add_attachment(&files, house.id, &receipt, None).unwrap();
+        assert_eq!(first.file_name, "receipt.pdf");
+        assert_eq!(first.hash, second.hash);
+        assert_eq!(first.hash.len(), attachment::HASH_LEN);
+        assert_eq!(db.list_attachments(house.id).unwrap().len(), 2);
+
+        // 指纹不是固定长度的小写十六进制时拒绝，读取数据时也拒绝
+        let mut forged = first.clone();
+        forged.hash = "../../../etc/passwd".to_string();
+        assert!(forged.path(&files).is_err());
+        let json = serde_json::to_string(&forged).unwrap();
+        assert!(serde_json::from_str::<Attachment>(&json).is_err());
+        db.remove_attachment(&files, first.id).unwrap();
+        assert!(second.path(&files).unwrap().exists());
+        db.remove_attachment(&files, second.id).unwrap();
+        assert!(!second.path(&files).unwrap().exists());
+
+        // 附件随资产移入回收站，永久删除后清理文件
+        let contract = db.add_attachment(&files, house.id, &receipt, None).unwrap();
+        db.delete_asset(house.id).unwrap();
+        assert!(db.list_attachments(house.id).unwrap().is_empty());
+        assert_eq!(db.prune_attachment_files(&files).unwrap(), 0);
+        db.restore_asset(house.id).unwrap();
+        assert_eq!(db.list_attachments(house.id).unwrap()[0].id, contract.id);
+        db.delete_asset_permanently(house.id).unwrap();
+        assert_eq!(db.prune_attachment_files(&files).unwrap(), 1);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_timeline() {
+        let mut db = Database::open_in_memory().unwrap();
+        let now = Utc::now();
+        let stock = Asset::new("招商银行", AssetType::Stock, 1000.0);
+        db.create_asset(&stock).unwrap();
+        db.adjust_asset_value(stock.id, 200.0, TransactionType::ValueChange, None)
+            .unwrap();
+        db.adjust_asset_value(stock.id, 50.0, TransactionType::Income, Some("分红".into()))
+            .unwrap();
+        let entry = CheckIn::new(now.date_naive(), "上涨了，继续持有");
+        db.save_check_in(&entry, false).unwrap();
+        db.add_notification(&Notification::new(NotificationKind::Info, "提醒"))
+            .unwrap();
+
+        let day = chrono::Duration::days(1);
+        let range = DateRange::new(now - day, now + day);
+        let events = db.get_timeline(&range, &TimelineFilter::default()).unwrap();
+        assert_eq!(events.len(), 4);
+        assert!(events.windows(2).all(|w| w[0].timestamp >= w[1].timestamp));
+        assert!(events.iter().any(|e| e.kind == TimelineKind::CheckIn));
+
+        let filter = TimelineFilter {
+            kinds: vec![TimelineKind::Transaction, TimelineKind::ValueChange],
+            asset_id: Some(stock.id),
+            limit: Some(1),
+        };
+        let events = db.get_timeline(&range, &filter).unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].kind, TimelineKind::Transaction);
+        assert_eq!(events[0].amount, Some(50.0));
+        assert_eq!(events[0].detail.as_deref(), Some("分红"));
+
+        let past = DateRange::new(now - day * 3, now - day * 2);
+        let events = db.get_timeline(&past, &TimelineFilter::default()).unwrap();
+        assert!(events.is_empty());
     }
 }
@@ -0,0 +1,239 @@
+//! 合并两个数据库：同一份数据在两台设备上分别使用后，把另一份的资产与交易并入当前数据库
+//!
+//! 资产按ID对应，双方都有且内容不同的资产视为冲突，按 `updated_at` 判断哪一方较新。
+//! 交易只加入当前数据库中没有的。删除不会合并：当前数据库中已删除的资产不会重新加入，
+//! 另一份中已删除的资产也不会从当前数据库删除。两个版本都保留时副本的ID由原ID推出，
+//! 重复合并不会产生多个副本，另一份中该资产的新交易记到副本上。
+
+use super::audit::{AuditEntity, AuditRecord, FieldChange, DEFAULT_ACTOR};
+use super::{Database, StorageError};
+use crate::asset::Asset;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use uuid::Uuid;
+
+/// 冲突的处理方式
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum MergeStrategy {
+    /// 保留修改时间较新的版本（相同时保留当前版本）
+    KeepNewest,
+    /// 两个版本都保留：另一份的版本作为副本加入
+    KeepBoth,
+    /// 逐个选择：只处理已选择的冲突，其余冲突列出并保持不变
+    Interactive(BTreeMap<Uuid, MergeChoice>),
+}
+
+/// 对一个冲突的选择
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MergeChoice {
+    KeepMine,
+    KeepTheirs,
+    KeepBoth,
+}
+
+impl MergeStrategy {
+    /// 对冲突的处理，逐个选择且尚未选择时为 None
+    pub fn choice(&self, conflict: &MergeConflict) -> Option<MergeChoice> {
+        match self {
+            MergeStrategy::KeepNewest if conflict.theirs_newer => Some(MergeChoice::KeepTheirs),
+            MergeStrategy::KeepNewest => Some(MergeChoice::KeepMine),
+            MergeStrategy::KeepBoth => Some(MergeChoice::KeepBoth),
+            MergeStrategy::Interactive(choices) => choices.get(&conflict.asset_id).copied(),
+        }
+    }
+}
+
+/// 双方都修改过的资产
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MergeConflict {
+    pub asset_id: Uuid,
+    /// 当前数据库中的版本
+    pub mine: Asset,
+    /// 另一份数据库中的版本
+    pub theirs: Asset,
+    /// 另一份的版本修改时间较新
+    pub theirs_newer: bool,
+    /// 不同的字段（从当前版本到另一份的版本）
+    pub changes: Vec<FieldChange>,
+}
+
+impl MergeConflict {
+    /// 比较同一资产的两个版本，除修改时间外内容相同时为 None
+    pub fn compare(mine: &Asset, theirs: &Asset) -> Result<Option<Self>, StorageError> {
+        let record = AuditRecord::compare(
+            DEFAULT_ACTOR,
+            AuditEntity::Asset,
+            mine.id,
+            Some(mine),
+            Some(theirs),
+            Utc::now(),
+        )?;
+        Ok(record.map(|record| Self {
+            asset_id: mine.id,
+            mine: mine.clone(),
+            theirs: theirs.clone(),
+            theirs_newer: theirs.updated_at > mine.updated_at,
+            changes: record.changes,
+        }))
+    }
+
+    /// 另一份版本的副本：ID由原ID推出（同一资产每次合并得到同一个ID），名称标注来源，
+    /// 清除须唯一的外部标识
+    pub fn theirs_copy(&self) -> Asset {
+        let mut copy = self.theirs.clone();
+        copy.id = copy_id(self.asset_id);
+        copy.name = format!("{} (合并)", self.theirs.name);
+        copy.external_id = None;
+        copy
+    }
+}
+
+/// 保留两个版本时另一份版本的副本ID
+pub fn copy_id(asset_id: Uuid) -> Uuid {
+    Uuid::new_v5(&asset_id, b"merge-copy")
+}
+
+/// 合并结果
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MergeReport {
+    /// 新加入的资产
+    pub added: usize,
+    /// 采用另一份版本的资产
+    pub updated: usize,
+    /// 作为副本加入的另一份版本
+    pub duplicated: usize,
+    /// 新加入的交易
+    pub transactions: usize,
+    /// 在当前数据库中已删除或所属资产不存在而跳过的记录数量
+    pub skipped: usize,
+    /// 未通过校验（如外部标识已被使用）而跳过的记录及原因
+    #[serde(default)]
+    pub errors: Vec<String>,
+    /// 尚未选择处理方式的冲突（仅逐个选择时）
+    pub conflicts: Vec<MergeConflict>,
+}
+
+/// 以只读方式打开另一个数据文件并合并到当前数据库（不迁移、不加锁、不写入另一个文件）
+pub fn merge_file(
+    db: &mut Database,
+    path: &Path,
+    strategy: MergeStrategy,
+) -> Result<MergeReport, StorageError> {
+    if !path.is_file() {
+        return Err(StorageError::NotFound(path.display().to_string()));
+    }
+    let same = db
+        .path()
+        .and_then(|current| current.canonicalize().ok())
+        .is_some_and(|current| path.canonicalize().ok() == Some(current));
+    if same {
+        return Err(StorageError::InvalidData(
+            "Cannot merge a database into itself".to_string(),
+        ));
+    }
+    let other = Database::open_read_only(path)?;
+    db.merge_from(&other, strategy)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asset::{AssetTransaction, AssetType, TransactionType};
+    use crate::storage::{SortBy, SortOrder, TransactionQuery};
+    use chrono::Duration;
+
+    #[test]
+    fn test_merge_from() {
+        let mut mine = Database::open_in_memory().unwrap();
+        let mut theirs = Database::open_in_memory().unwrap();
+        let card = Asset::new("储蓄卡", AssetType::BankDeposit, 1000.0);
+        mine.create_asset(&card).unwrap();
+        theirs.create_asset(&card).unwrap();
+
+        // 另一台设备上较晚修改了储蓄卡，并新建了现金与一笔交易
+        let mut edited = card.clone();
+        edited.value = 1200.0;
+        edited.updated_at = card.updated_at + Duration::minutes(5);
+        theirs.update_asset(&edited).unwrap();
+        let cash = Asset::new("现金", AssetType::Cash, 50.0);
+        theirs.create_asset(&cash).unwrap();
+        let txn = AssetTransaction::new(card.id, TransactionType::Income, 1000.0, 1200.0);
+        theirs.add_transaction(&txn).unwrap();
+
+        // 逐个选择：先列出冲突，不修改冲突的资产
+        let report = mine
+            .merge_from(&theirs, MergeStrategy::Interactive(BTreeMap::new()))
+            .unwrap();
+        assert_eq!((report.added, report.transactions), (1, 1));
+        assert_eq!(report.conflicts.len(), 1);
+        assert!(report.conflicts[0].theirs_newer);
+        assert_eq!(report.conflicts[0].changes[0].field, "value");
+        assert_eq!(mine.get_asset(card.id).unwrap().unwrap().value, 1000.0);
+
+        // 再次合并时已加入的记录不重复
+        let choices = BTreeMap::from([(card.id, MergeChoice::KeepTheirs)]);
+        let report = mine
+            .merge_from(&theirs, MergeStrategy::Interactive(choices))
+            .unwrap();
+        assert_eq!(
+            (report.added, report.updated, report.transactions),
+            (0, 1, 0)
+        );
+        assert!(report.conflicts.is_empty());
+        assert_eq!(mine.get_asset(card.id).unwrap().unwrap().value, 1200.0);
+
+        // 保留较新的版本；两者都保留时另一份的版本作为副本加入
+        let mut local = edited.clone();
+        local.value = 900.0;
+        local.updated_at = edited.updated_at + Duration::minutes(5);
+        mine.update_asset(&local).unwrap();
+        let report = mine.merge_from(&theirs, MergeStrategy::KeepNewest).unwrap();
+        assert_eq!(report.updated, 0);
+        assert_eq!(mine.get_asset(card.id).unwrap().unwrap().value, 900.0);
+        let later = AssetTransaction::new(card.id, TransactionType::Income, 1200.0, 1300.0);
+        theirs.add_transaction(&later).unwrap();
+        let report = mine.merge_from(&theirs, MergeStrategy::KeepBoth).unwrap();
+        assert_eq!((report.duplicated, report.transactions), (1, 1));
+        let copy = copy_id(card.id);
+        let query = TransactionQuery::default();
+        assert_eq!(mine.get_transactions(copy, &query).unwrap()[0].id, later.id);
+        assert_eq!(mine.get_transactions(card.id, &query).unwrap().len(), 1);
+
+        // 再次合并不会产生新的副本或交易
+        let report = mine.merge_from(&theirs, MergeStrategy::KeepBoth).unwrap();
+        assert_eq!((report.duplicated, report.transactions), (0, 0));
+        let assets = mine
+            .list_assets(SortBy::default(), SortOrder::default())
+            .unwrap();
+        assert_eq!(assets.len(), 3);
+
+        // 当前数据库中已删除的资产不会重新加入
+        mine.delete_asset(cash.id).unwrap();
+        let report = mine.merge_from(&theirs, MergeStrategy::KeepNewest).unwrap();
+        assert_eq!((report.added, report.skipped), (0, 1));
+    }
+
+    #[test]
+    fn test_merge_validates() {
+        let mut mine = Database::open_in_memory().unwrap();
+        let mut theirs = Database::open_in_memory().unwrap();
+        let mut card = Asset::new("储蓄卡", AssetType::BankDeposit, 1000.0);
+        card.external_id = Some("X".to_string());
+        mine.create_asset(&card).unwrap();
+
+        // 外部标识已被当前数据库中的资产使用，该资产与其交易都不加入
+        let mut other = Asset::new("工资卡", AssetType::BankDeposit, 500.0);
+        other.external_id = Some("X".to_string());
+        theirs.create_asset(&other).unwrap();
+        let txn = AssetTransaction::new(other.id, TransactionType::Income, 0.0, 500.0);
+        theirs.add_transaction(&txn).unwrap();
+        let report = mine.merge_from(&theirs, MergeStrategy::KeepNewest).unwrap();
+        assert_eq!((report.added, report.skipped), (0, 1));
+        assert_eq!(report.errors.len(), 1);
+        assert!(mine.get_asset(other.id).unwrap().is_none());
+    }
+}
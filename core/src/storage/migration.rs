@@ -0,0 +1,160 @@
+//! 数据格式迁移：主文件记录格式版本，应用升级后按版本依次转换
+//!
+//! 迁移直接处理主文件的 JSON（反序列化之前），全部完成后一次性替换主文件，中途失败时原文件
+//! 不变。日志中的资产与交易变更在打开时按新格式重放，迁移不应改变它们的结构。
+
+use super::StorageError;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs;
+use std::path::Path;
+
+/// 当前的数据格式版本
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// 主文件中记录格式版本的字段
+const VERSION_FIELD: &str = "schema_version";
+
+/// 一次格式迁移
+pub struct Migration {
+    /// 迁移后的版本
+    pub version: u32,
+    /// 说明（显示在进度中）
+    pub description: &'static str,
+    apply: fn(&mut Value) -> Result<(), StorageError>,
+}
+
+/// 按版本排列的全部迁移
+const MIGRATIONS: &[Migration] = &[Migration {
+    version: 1,
+    description: "Record data format version",
+    apply: |_| Ok(()),
+}];
+
+/// 迁移进度（每开始一步报告一次）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MigrationProgress {
+    /// 正在迁移到的版本
+    pub version: u32,
+    /// 迁移说明
+    pub description: String,
+    /// 当前步骤（从 1 开始）
+    pub step: usize,
+    /// 总步骤数
+    pub total: usize,
+}
+
+/// 读取主文件的格式版本（文件不存在或为空时视为当前版本）
+pub fn file_schema_version(path: impl AsRef<Path>) -> Result<u32, StorageError> {
+    Ok(read_store(path.as_ref())?.map_or(SCHEMA_VERSION, |value| version_of(&value)))
+}
+
+/// 主文件尚未执行的迁移
+pub fn pending_migrations(path: impl AsRef<Path>) -> Result<Vec<&'static Migration>, StorageError> {
+    let version = file_schema_version(path)?;
+    ensure_supported(version)?;
+    Ok(MIGRATIONS.iter().filter(|m| m.version > version).collect())
+}
+
+/// 依次执行主文件尚未执行的迁移，返回执行的步数
+pub fn migrate_file(
+    path: impl AsRef<Path>,
+    mut on_progress: impl FnMut(&MigrationProgress),
+) -> Result<usize, StorageError> {
+    let path = path.as_ref();
+    let Some(mut store) = read_store(path)? else {
+        return Ok(0);
+    };
+    let version = version_of(&store);
+    ensure_supported(version)?;
+    let pending: Vec<_> = MIGRATIONS.iter().filter(|m| m.version > version).collect();
+    for (i, migration) in pending.iter().enumerate() {
+        on_progress(&MigrationProgress {
+            version: migration.version,
+            description: migration.description.to_string(),
+            step: i + 1,
+            total: pending.len(),
+        });
+        (migration.apply)(&mut store)?;
+        let Value::Object(fields) = &mut store else {
+            return Err(StorageError::InvalidData(
+                "Data file is not a JSON object".to_string(),
+            ));
+        };
+        fields.insert(VERSION_FIELD.to_string(), migration.version.into());
+    }
+    if !pending.is_empty() {
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, serde_json::to_string_pretty(&store)?)?;
+        fs::rename(&tmp, path)?;
+    }
+    Ok(pending.len())
+}
+
+fn read_store(path: &Path) -> Result<Option<Value>, StorageError> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    if content.trim().is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(serde_json::from_str(&content)?))
+}
+
+/// 未记录版本的文件来自引入版本号之前
+pub(super) fn version_of(store: &Value) -> u32 {
+    store
+        .get(VERSION_FIELD)
+        .and_then(Value::as_u64)
+        .map_or(0, |v| v as u32)
+}
+
+/// 较新版本写入的文件可能包含本版本不认识的数据，拒绝打开以免保存时丢失
+fn ensure_supported(version: u32) -> Result<(), StorageError> {
+    if version > SCHEMA_VERSION {
+        return Err(StorageError::InvalidData(format!(
+            "Data file format {} is newer than supported format {}",
+            version, SCHEMA_VERSION
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    #[test]
+    fn test_migrate_file() {
+        let path =
+            std::env::temp_dir().join(format!("asset-manager-migrate-{}.json", Uuid::new_v4()));
+        fs::write(
+            &path,
+            r#"{"assets": [], "transactions": [], "settings": {}}"#,
+        )
+        .unwrap();
+        assert_eq!(file_schema_version(&path).unwrap(), 0);
+        assert_eq!(pending_migrations(&path).unwrap().len(), 1);
+
+        let mut steps = Vec::new();
+        assert_eq!(migrate_file(&path, |p| steps.push(p.clone())).unwrap(), 1);
+        assert_eq!(steps.len(), 1);
+        assert_eq!((steps[0].step, steps[0].total), (1, 1));
+        assert_eq!(file_schema_version(&path).unwrap(), SCHEMA_VERSION);
+        assert!(pending_migrations(&path).unwrap().is_empty());
+        assert_eq!(migrate_file(&path, |_| {}).unwrap(), 0);
+
+        fs::write(
+            &path,
+            format!(r#"{{"schema_version": {}}}"#, SCHEMA_VERSION + 1),
+        )
+        .unwrap();
+        assert!(pending_migrations(&path).is_err());
+        assert!(migrate_file(&path, |_| {}).is_err());
+
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -1,8 +1,499 @@
 //! 本地存储模块
 
+mod archive;
+mod audit;
+mod backup;
+mod journal;
 mod json;
+mod merge;
+mod migration;
+mod recovery;
+mod snapshot;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+mod sync;
+mod table;
+mod worker;
 
+pub use archive::{
+    export_archive, import_archive, Archive, ArchiveMode, ArchiveSummary, ARCHIVE_FORMAT,
+    ARCHIVE_VERSION,
+};
+pub use audit::{
+    AssetVersionDiff, AuditAction, AuditEntity, AuditOperation, AuditQuery,
+    AuditRecord, AuditSettings, FieldChange, AUDIT_SETTINGS, AUDIT_SETTING_KEY, DEFAULT_ACTOR,
+};
+pub use backup::{
+    BackupInfo, BackupSettings, BACKUP_CHECK_INTERVAL_SECS, BACKUP_SETTINGS, BACKUP_SETTING_KEY,
+};
 pub use json::Database;
+pub use merge::{merge_file, MergeChoice, MergeConflict, MergeReport, MergeStrategy};
+pub use migration::{
+    file_schema_version, migrate_file, pending_migrations, Migration, MigrationProgress,
+    SCHEMA_VERSION,
+};
+pub use recovery::{reset_data_file, restore_data_file, DataFileProblem, RecoveryInfo};
+pub use snapshot::Snapshot;
+pub use sync::{
+    AssetDifference, ConflictResolution, LockInfo, ReloadOutcome, StoreConflict, StoreVersion,
+    EXTERNAL_CHECK_INTERVAL_SECS, LOCK_HEARTBEAT_SECS,
+};
+pub use worker::AsyncDatabase;
+
+use crate::asset::{
+    Asset, AssetTransaction, AssetType, Currency, SchemaViolation, TransactionType,
+};
+use crate::settings::Settings;
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+use uuid::Uuid;
+
+/// 分页查询交易时的默认每页条数
+pub const DEFAULT_PAGE_SIZE: usize = 50;
+
+/// 保存设置的设置项键名
+pub const SAVE_SETTING_KEY: &str = "save";
+
+/// 保存设置项
+pub const SAVE_SETTINGS: Settings<SaveSettings> =
+    Settings::new(SAVE_SETTING_KEY, SaveSettings::default);
+
+/// 定时保存的默认间隔（秒）
+pub const DEFAULT_AUTOSAVE_INTERVAL_SECS: u64 = 60;
+
+/// 检查是否需要定时保存的间隔（秒）
+pub const AUTOSAVE_CHECK_INTERVAL_SECS: u64 = 5;
+
+/// 延迟保存的默认等待时间（毫秒）
+pub const DEFAULT_DEBOUNCE_MS: u64 = 500;
+
+/// 延迟保存模式下检查是否需要写入的间隔（毫秒）
+pub const DEBOUNCE_CHECK_INTERVAL_MS: u64 = 200;
+
+/// 保存方式
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SaveMode {
+    /// 每次修改后立即写入
+    #[default]
+    Immediate,
+    /// 修改只标记为未保存，按间隔定时写入
+    Interval,
+    /// 最后一次修改后等待片刻再写入，批量修改只写一次（连续修改超过定时保存间隔时也会写入）
+    Debounced,
+    /// 只在显式保存时写入
+    Manual,
+}
+
+/// 保存设置（适合慢速磁盘或云同步目录，避免频繁写文件）
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SaveSettings {
+    /// 保存方式
+    #[serde(default)]
+    pub mode: SaveMode,
+    /// 定时保存的间隔（秒）
+    #[serde(default = "default_autosave_interval")]
+    pub interval_secs: u64,
+    /// 延迟保存的等待时间（毫秒）
+    #[serde(default = "default_debounce")]
+    pub debounce_ms: u64,
+}
+
+fn default_autosave_interval() -> u64 {
+    DEFAULT_AUTOSAVE_INTERVAL_SECS
+}
+
+fn default_debounce() -> u64 {
+    DEFAULT_DEBOUNCE_MS
+}
+
+impl Default for SaveSettings {
+    fn default() -> Self {
+        Self {
+            mode: SaveMode::default(),
+            interval_secs: DEFAULT_AUTOSAVE_INTERVAL_SECS,
+            debounce_ms: DEFAULT_DEBOUNCE_MS,
+        }
+    }
+}
+
+/// 当前的保存状态
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SaveStatus {
+    /// 保存方式
+    pub mode: SaveMode,
+    /// 是否有未保存的修改
+    pub unsaved_changes: bool,
+    /// 最早一次未保存修改的时间
+    pub unsaved_since: Option<DateTime<Utc>>,
+    /// 本次运行中最近一次写入文件的时间
+    pub last_saved: Option<DateTime<Utc>>,
+    /// 数据文件被外部修改，等待用户处理冲突
+    pub conflict: bool,
+    /// 同时打开数据文件的其他实例
+    pub locked_by: Option<LockInfo>,
+    /// 以只读方式打开，修改会被拒绝
+    #[serde(default)]
+    pub read_only: bool,
+}
+
+/// 重建派生数据的结果
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RebuildReport {
+    /// 资产数
+    pub assets: usize,
+    /// 交易数
+    pub transactions: usize,
+    /// 去掉的重复记录数（同ID的资产或交易）
+    pub duplicates_removed: usize,
+    /// 复式账簿中为对齐资产价值补记的分录数
+    pub ledger_adjustments: usize,
+}
+
+/// 压缩数据文件的结果
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CompactReport {
+    /// 压缩前数据文件（包括日志）的大小（字节）
+    pub size_before: u64,
+    /// 压缩后数据文件的大小（字节）
+    pub size_after: u64,
+    /// 去掉的无主记录数（所属资产已不存在的估值与附件记录）
+    pub records_removed: usize,
+}
+
+/// 资产列表的排序字段
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SortBy {
+    /// 创建时间
+    #[default]
+    CreatedAt,
+    /// 最后修改时间
+    UpdatedAt,
+    /// 价值（原币金额）
+    Value,
+    /// 名称
+    Name,
+    /// 资产类型
+    Type,
+}
+
+/// 排序方向
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SortOrder {
+    Asc,
+    #[default]
+    Desc,
+}
+
+impl SortBy {
+    /// 对应的数据库列
+    pub fn column(&self) -> &'static str {
+        match self {
+            SortBy::CreatedAt => "created_at",
+            SortBy::UpdatedAt => "updated_at",
+            SortBy::Value => "value",
+            SortBy::Name => "name",
+            SortBy::Type => "asset_type",
+        }
+    }
+
+    /// 比较两个资产
+    pub fn compare(&self, a: &Asset, b: &Asset) -> Ordering {
+        match self {
+            SortBy::CreatedAt => a.created_at.cmp(&b.created_at),
+            SortBy::UpdatedAt => a.updated_at.cmp(&b.updated_at),
+            SortBy::Value => a.value.total_cmp(&b.value),
+            SortBy::Name => a.name.cmp(&b.name),
+            SortBy::Type => a.asset_type.as_str().cmp(b.asset_type.as_str()),
+        }
+    }
+}
+
+impl SortOrder {
+    /// SQL 排序关键字
+    pub fn keyword(&self) -> &'static str {
+        match self {
+            SortOrder::Asc => "ASC",
+            SortOrder::Desc => "DESC",
+        }
+    }
+}
+
+/// 按字段排序资产；排序稳定，值相同时保持原有顺序
+pub fn sort_assets(assets: &mut [Asset], sort_by: SortBy, order: SortOrder) {
+    assets.sort_by(|a, b| match order {
+        SortOrder::Asc => sort_by.compare(a, b),
+        SortOrder::Desc => sort_by.compare(b, a),
+    });
+}
+
+/// 资产筛选条件：设置的条件需同时满足，结果按创建时间倒序
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AssetFilter {
+    /// 资产类型
+    #[serde(default)]
+    pub asset_type: Option<AssetType>,
+    /// 货币
+    #[serde(default)]
+    pub currency: Option<Currency>,
+    /// 需包含的标签（全部包含）
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// 最低价值（含）
+    #[serde(default)]
+    pub min_value: Option<f64>,
+    /// 最高价值（含）
+    #[serde(default)]
+    pub max_value: Option<f64>,
+    /// 创建时间起始（含）
+    #[serde(default)]
+    pub created_since: Option<DateTime<Utc>>,
+    /// 创建时间截止（含）
+    #[serde(default)]
+    pub created_until: Option<DateTime<Utc>>,
+    /// 最后修改时间起始（含）
+    #[serde(default)]
+    pub updated_since: Option<DateTime<Utc>>,
+    /// 最后修改时间截止（含）
+    #[serde(default)]
+    pub updated_until: Option<DateTime<Utc>>,
+}
+
+impl AssetFilter {
+    /// 限定资产类型
+    pub fn with_type(mut self, asset_type: AssetType) -> Self {
+        self.asset_type = Some(asset_type);
+        self
+    }
+
+    /// 限定价值范围
+    pub fn with_value_range(mut self, min: Option<f64>, max: Option<f64>) -> Self {
+        self.min_value = min;
+        self.max_value = max;
+        self
+    }
+
+    /// 需包含标签
+    pub fn with_tag(mut self, tag: impl Into<String>) -> Self {
+        self.tags.push(tag.into());
+        self
+    }
+
+    /// 限定创建时间范围
+    pub fn with_created_range(
+        mut self,
+        since: Option<DateTime<Utc>>,
+        until: Option<DateTime<Utc>>,
+    ) -> Self {
+        self.created_since = since;
+        self.created_until = until;
+        self
+    }
+
+    /// 资产是否符合条件
+    pub fn matches(&self, asset: &Asset) -> bool {
+        self.asset_type
+            .as_ref()
+            .is_none_or(|t| t.as_str() == asset.asset_type.as_str())
+            && self.currency.as_ref().is_none_or(|c| &asset.currency == c)
+            && self.tags.iter().all(|tag| asset.tags.contains(tag))
+            && self.min_value.is_none_or(|min| asset.value >= min)
+            && self.max_value.is_none_or(|max| asset.value <= max)
+            && within(asset.created_at, self.created_since, self.created_until)
+            && within(asset.updated_at, self.updated_since, self.updated_until)
+    }
+}
+
+/// 时间是否在范围内（含两端，未设置的一端不限）
+fn within(t: DateTime<Utc>, since: Option<DateTime<Utc>>, until: Option<DateTime<Utc>>) -> bool {
+    since.is_none_or(|since| t >= since) && until.is_none_or(|until| t <= until)
+}
+
+/// `upsert_asset` 的结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AssetUpsert {
+    /// 保存后的资产
+    pub asset: Asset,
+    /// 是否为新建（否则为更新已有资产）
+    pub created: bool,
+}
+
+/// 批量写入中未写入的一项
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BulkError {
+    /// 在输入中的位置
+    pub index: usize,
+    pub id: Uuid,
+    pub error: String,
+}
+
+/// 批量写入的结果：出错的项被跳过，其余项一起写入
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct BulkResult {
+    /// 写入的数量
+    pub inserted: usize,
+    pub errors: Vec<BulkError>,
+}
+
+impl BulkResult {
+    fn push(&mut self, index: usize, id: Uuid, result: Result<(), StorageError>) {
+        match result {
+            Ok(()) => self.inserted += 1,
+            Err(e) => self.errors.push(BulkError {
+                index,
+                id,
+                error: e.to_string(),
+            }),
+        }
+    }
+}
+
+/// 交易查询条件：时间范围、类型与数量限制，结果按时间倒序
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TransactionQuery {
+    /// 起始时间（含）
+    #[serde(default)]
+    pub since: Option<DateTime<Utc>>,
+    /// 截止时间（含）
+    #[serde(default)]
+    pub until: Option<DateTime<Utc>>,
+    /// 交易类型
+    #[serde(default)]
+    pub transaction_type: Option<TransactionType>,
+    /// 最多返回的条数
+    #[serde(default)]
+    pub limit: Option<usize>,
+    /// 跳过的条数
+    #[serde(default)]
+    pub offset: usize,
+}
+
+impl TransactionQuery {
+    /// 限定时间范围
+    pub fn with_range(
+        mut self,
+        since: Option<DateTime<Utc>>,
+        until: Option<DateTime<Utc>>,
+    ) -> Self {
+        self.since = since;
+        self.until = until;
+        self
+    }
+
+    /// 限定交易类型
+    pub fn with_type(mut self, transaction_type: TransactionType) -> Self {
+        self.transaction_type = Some(transaction_type);
+        self
+    }
+
+    /// 限定返回条数
+    pub fn with_limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// 跳过前若干条
+    pub fn with_offset(mut self, offset: usize) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    /// 交易是否满足时间与类型条件
+    pub fn matches(&self, transaction: &AssetTransaction) -> bool {
+        self.since
+            .is_none_or(|since| transaction.timestamp >= since)
+            && self
+                .until
+                .is_none_or(|until| transaction.timestamp <= until)
+            && self
+                .transaction_type
+                .as_ref()
+                .is_none_or(|t| &transaction.transaction_type == t)
+    }
+}
+
+/// 交易分页游标：上一页最后一笔交易的时间与ID
+///
+/// 交易按 (时间, ID) 倒序排列，游标之后插入或删除其他交易不会导致重复或遗漏。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TransactionCursor {
+    pub timestamp: DateTime<Utc>,
+    pub id: Uuid,
+}
+
+impl TransactionCursor {
+    /// 指向某笔交易的游标
+    pub fn after(transaction: &AssetTransaction) -> Self {
+        Self {
+            timestamp: transaction.timestamp,
+            id: transaction.id,
+        }
+    }
+
+    /// 交易是否排在游标之后（即属于后续页）
+    pub fn precedes(&self, transaction: &AssetTransaction) -> bool {
+        (transaction.timestamp, transaction.id) < (self.timestamp, self.id)
+    }
+}
+
+impl fmt::Display for TransactionCursor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}_{}",
+            self.timestamp.to_rfc3339_opts(SecondsFormat::AutoSi, true),
+            self.id
+        )
+    }
+}
+
+impl FromStr for TransactionCursor {
+    type Err = StorageError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || StorageError::InvalidData(format!("Invalid cursor: {}", s));
+        let (timestamp, id) = s.split_once('_').ok_or_else(invalid)?;
+        Ok(Self {
+            timestamp: DateTime::parse_from_rfc3339(timestamp)
+                .map_err(|_| invalid())?
+                .with_timezone(&Utc),
+            id: Uuid::parse_str(id).map_err(|_| invalid())?,
+        })
+    }
+}
+
+/// 一页交易记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransactionPage {
+    /// 本页交易（按时间倒序）
+    pub transactions: Vec<AssetTransaction>,
+    /// 下一页的游标，为空表示没有更多
+    pub next_cursor: Option<String>,
+}
+
+impl TransactionPage {
+    /// 由多取一条的查询结果构造分页
+    pub fn from_rows(mut transactions: Vec<AssetTransaction>, limit: usize) -> Self {
+        let has_more = transactions.len() > limit;
+        transactions.truncate(limit);
+        let next_cursor = if has_more {
+            transactions
+                .last()
+                .map(|t| TransactionCursor::after(t).to_string())
+        } else {
+            None
+        };
+        Self {
+            transactions,
+            next_cursor,
+        }
+    }
+}
 
 /// 存储错误
 #[derive(Debug, thiserror::Error)]
@@ -15,4 +506,37 @@ pub enum StorageError {
     
     #[error("Not found: {0}")]
     NotFound(String),
+
+    #[error("Invalid data: {0}")]
+    InvalidData(String),
+
+    #[error("Period closed: {0}")]
+    PeriodClosed(String),
+
+    #[error("Invalid metadata: {}", join_violations(.0))]
+    InvalidMetadata(Vec<SchemaViolation>),
+
+    #[error("Data file was modified externally: {0}")]
+    Conflict(String),
+
+    #[error("Database thread has stopped")]
+    Stopped,
+
+    #[error("Database operation panicked")]
+    Panicked,
+
+    #[error("Data file is open read-only")]
+    ReadOnly,
+
+    #[cfg(feature = "sqlite")]
+    #[error("SQLite error: {0}")]
+    Sqlite(#[from] rusqlite::Error),
+}
+
+fn join_violations(violations: &[SchemaViolation]) -> String {
+    violations
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join("; ")
 }
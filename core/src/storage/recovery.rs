@@ -0,0 +1,167 @@
+//! 启动恢复：数据文件丢失或无法打开时，桌面端以内存数据库启动并进入恢复模式，
+//! 由用户选择从备份恢复、新建数据文件或指定其他位置的数据文件
+//!
+//! 恢复或新建前，原数据文件与日志改名为 `<文件名>.broken-<时间>` 留在原目录中，不会删除。
+
+use super::backup;
+use super::journal::Journal;
+use super::{BackupInfo, StorageError};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+/// 数据文件的问题
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DataFileProblem {
+    /// 数据文件不存在（如所在的移动硬盘未连接）
+    Missing,
+    /// 数据文件无法读取或内容损坏
+    Unreadable,
+}
+
+/// 恢复模式的信息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecoveryInfo {
+    pub db_path: String,
+    pub problem: DataFileProblem,
+    /// 打开失败的原因
+    pub message: String,
+    /// 可用于恢复的备份，按时间倒序
+    pub backups: Vec<BackupInfo>,
+}
+
+impl RecoveryInfo {
+    /// 数据文件打开失败后的恢复信息，列出备份目录中该数据文件的备份
+    pub fn new(db_path: &str, backups_dir: &str, error: &StorageError) -> Self {
+        let path = Path::new(db_path);
+        let problem = if path.exists() {
+            DataFileProblem::Unreadable
+        } else {
+            DataFileProblem::Missing
+        };
+        let backups = backup::list(Path::new(backups_dir), &backup::name_for(Some(path)))
+            .unwrap_or_else(|e| {
+                warn!("Failed to list backups in {}: {}", backups_dir, e);
+                Vec::new()
+            });
+        Self {
+            db_path: db_path.to_string(),
+            problem,
+            message: error.to_string(),
+            backups,
+        }
+    }
+}
+
+/// 用备份替换数据文件：备份在临时副本上迁移到当前格式后写入，返回改名保留的原文件
+pub fn restore_data_file(
+    db_path: &Path,
+    backups_dir: &Path,
+    backup_path: &Path,
+) -> Result<Option<PathBuf>, StorageError> {
+    let listed = backup::entries(backups_dir, &backup::name_for(Some(db_path)))?;
+    if !listed.iter().any(|(_, p)| p == backup_path) {
+        return Err(StorageError::NotFound(backup_path.display().to_string()));
+    }
+    let store = backup::read(backup_path)?;
+    let kept = set_aside(db_path)?;
+    if let Some(parent) = db_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let tmp = db_path.with_extension("tmp");
+    fs::write(&tmp, serde_json::to_string_pretty(&store)?)?;
+    fs::rename(&tmp, db_path)?;
+    info!("Restored {:?} from backup {:?}", db_path, backup_path);
+    Ok(kept)
+}
+
+/// 改名保留原数据文件（如有），之后打开时新建空的数据文件
+pub fn reset_data_file(db_path: &Path) -> Result<Option<PathBuf>, StorageError> {
+    set_aside(db_path)
+}
+
+/// 把数据文件与日志改名为 `<文件名>.broken-<时间>`，返回改名后的数据文件
+fn set_aside(db_path: &Path) -> Result<Option<PathBuf>, StorageError> {
+    let suffix = format!("broken-{}", Utc::now().format("%Y%m%d-%H%M%S"));
+    let journal = Journal::for_store(db_path);
+    let mut kept = None;
+    for path in [db_path, journal.path()] {
+        if !path.exists() {
+            continue;
+        }
+        // 同一秒内多次改名时加序号，不覆盖之前保留的文件
+        let mut target = path.as_os_str().to_owned();
+        target.push(format!(".{}", suffix));
+        let mut target = PathBuf::from(target);
+        for n in 2.. {
+            if !target.exists() {
+                break;
+            }
+            let mut numbered = path.as_os_str().to_owned();
+            numbered.push(format!(".{}-{}", suffix, n));
+            target = PathBuf::from(numbered);
+        }
+        fs::rename(path, &target)?;
+        warn!("Kept previous {:?} as {:?}", path, target);
+        kept.get_or_insert(target);
+    }
+    Ok(kept)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asset::{Asset, AssetType};
+    use crate::storage::Database;
+    use uuid::Uuid;
+
+    #[test]
+    fn test_recovery() {
+        let dir = std::env::temp_dir().join(format!("asset-manager-recovery-{}", Uuid::new_v4()));
+        let db_path = dir.join("assets.json");
+        let backups_dir = dir.join("backups");
+        let card = Asset::new("储蓄卡", AssetType::BankDeposit, 1000.0);
+        {
+            let mut db = Database::open(&db_path).unwrap();
+            db.create_asset(&card).unwrap();
+            db.save_now().unwrap();
+            db.backup_to(&backups_dir).unwrap();
+        }
+
+        // 数据文件损坏：列出可用的备份
+        fs::write(&db_path, "{ not json").unwrap();
+        let error = Database::open(&db_path).err().unwrap();
+        let info = RecoveryInfo::new(
+            &db_path.to_string_lossy(),
+            &backups_dir.to_string_lossy(),
+            &error,
+        );
+        assert_eq!(info.problem, DataFileProblem::Unreadable);
+        assert_eq!(info.backups.len(), 1);
+
+        // 从备份恢复，损坏的文件改名保留
+        let backup = PathBuf::from(&info.backups[0].path);
+        assert!(restore_data_file(&db_path, &backups_dir, &dir.join("other.json")).is_err());
+        let kept = restore_data_file(&db_path, &backups_dir, &backup)
+            .unwrap()
+            .unwrap();
+        assert_eq!(fs::read_to_string(&kept).unwrap(), "{ not json");
+        let db = Database::open(&db_path).unwrap();
+        assert_eq!(db.get_asset(card.id).unwrap().unwrap().name, "储蓄卡");
+        drop(db);
+
+        // 新建空的数据文件
+        assert!(reset_data_file(&db_path).unwrap().is_some());
+        let error = StorageError::NotFound(db_path.display().to_string());
+        let info = RecoveryInfo::new(&db_path.to_string_lossy(), "", &error);
+        assert_eq!(info.problem, DataFileProblem::Missing);
+        let db = Database::open(&db_path).unwrap();
+        assert!(db.get_asset(card.id).unwrap().is_none());
+        drop(db);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
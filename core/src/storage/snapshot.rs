@@ -0,0 +1,36 @@
+//! 只读快照：耗时的分析与导出在快照上进行，期间数据库可以继续修改
+//!
+//! JSON 后端的快照与数据库共享数据（`Arc`），创建时不复制；快照存在期间数据库被修改时
+//! 才复制一份（写时复制）。SQLite 后端在独立的只读连接上开启读事务。
+
+use super::Database;
+use chrono::{DateTime, Utc};
+use std::ops::Deref;
+
+/// 数据库在某一时刻的只读快照
+pub struct Snapshot<D = Database> {
+    taken_at: DateTime<Utc>,
+    db: D,
+}
+
+impl<D> Snapshot<D> {
+    pub(super) fn new(db: D) -> Self {
+        Self {
+            taken_at: Utc::now(),
+            db,
+        }
+    }
+
+    /// 创建快照的时间
+    pub fn taken_at(&self) -> DateTime<Utc> {
+        self.taken_at
+    }
+}
+
+impl<D> Deref for Snapshot<D> {
+    type Target = D;
+
+    fn deref(&self) -> &D {
+        &self.db
+    }
+}
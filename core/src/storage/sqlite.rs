@@ -1,19 +1,182 @@
 //! SQLite 数据库实现
 
-use super::StorageError;
-use crate::asset::{Asset, AssetSummary, AssetTransaction, AssetType, TransactionType};
-use chrono::{DateTime, Utc};
-use rusqlite::{params, Connection, OptionalExtension};
+use super::{
+    Archive, ArchiveMode, ArchiveSummary, AssetFilter, AssetUpsert, AuditEntity, AuditQuery, AuditRecord,
+    AuditSettings, BulkResult, CompactReport, Snapshot, SortBy, SortOrder, StorageError, TransactionCursor,
+    TransactionPage, TransactionQuery, AUDIT_SETTINGS, DEFAULT_ACTOR,
+};
+use crate::analytics::{end_of_day_values, DailyBalance, DailyBalances, DateRange};
+use crate::asset::{
+    Asset, AssetFormula, AssetSummary, AssetTransaction, AssetType, Currency, DisplayMeta,
+    MetadataPath, SaleCharge, TransactionStatus, TransactionType,
+};
+use crate::search::SEARCH_SETTINGS;
+use crate::settings::Settings;
+use chrono::{DateTime, NaiveDate, Utc};
+use rusqlite::{
+    params, params_from_iter, types::Value as SqlValue, Connection, OpenFlags, OptionalExtension,
+};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs;
 use std::path::Path;
 use tracing::info;
 use uuid::Uuid;
 
+/// 查询计划检查结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueryHealth {
+    /// 查询名称
+    pub name: String,
+    /// `EXPLAIN QUERY PLAN` 的输出
+    pub plan: Vec<String>,
+    /// 是否出现全表扫描（未使用索引）
+    pub full_scan: bool,
+}
+
+/// 标签及使用该标签的资产数量
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TagUsage {
+    pub name: String,
+    /// 使用该标签的资产数量（不含回收站中的资产）
+    pub asset_count: usize,
+}
+
+/// 修改记录中的时间格式（与触发器中的 `strftime` 一致，可按字符串比较）
+const AUDIT_TIME_FORMAT: &str = "%Y-%m-%dT%H:%M:%S%.3fZ";
+
+/// 触发器记入修改记录的各列：资产表与交易表
+const AUDITED_COLUMNS: [(&str, &str, &str); 2] = [
+    (
+        "assets",
+        "asset",
+        "'name', {r}.name, 'asset_type', {r}.asset_type, 'value', {r}.value, \
+         'currency', json({r}.currency), 'description', {r}.description, \
+         'tags', json({r}.tags), 'metadata', json({r}.metadata), \
+         'external_id', {r}.external_id, 'deleted_at', {r}.deleted_at",
+    ),
+    (
+        "transactions",
+        "transaction",
+        "'asset_id', {r}.asset_id, 'transaction_type', {r}.transaction_type, \
+         'amount_before', {r}.amount_before, 'amount_after', {r}.amount_after, \
+         'note', {r}.note, 'timestamp', {r}.timestamp, 'external_id', {r}.external_id",
+    ),
+];
+
+/// 流式读取资产时每次查询的行数
+const ASSET_PAGE_SIZE: usize = 256;
+
+/// 资产中没有单独列的字段，以 JSON 保存在 `extra` 列
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+struct AssetExtra {
+    formula: Option<AssetFormula>,
+    cost_basis: Option<f64>,
+    target_price: Option<f64>,
+    target_notified: bool,
+    import_batch: Option<Uuid>,
+    display: DisplayMeta,
+    display_currency: Option<Currency>,
+}
+
+impl AssetExtra {
+    fn of(asset: &Asset) -> Self {
+        Self {
+            formula: asset.formula.clone(),
+            cost_basis: asset.cost_basis,
+            target_price: asset.target_price,
+            target_notified: asset.target_notified,
+            import_batch: asset.import_batch,
+            display: asset.display.clone(),
+            display_currency: asset.display_currency.clone(),
+        }
+    }
+}
+
+/// 交易中没有单独列的字段，以 JSON 保存在 `extra` 列
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+struct TransactionExtra {
+    corrects: Option<Uuid>,
+    original_amount: Option<f64>,
+    original_currency: Option<Currency>,
+    fx_rate: Option<f64>,
+    status: TransactionStatus,
+    scheduled: bool,
+    import_hash: Option<String>,
+    import_batch: Option<Uuid>,
+    charge: Option<SaleCharge>,
+}
+
+impl TransactionExtra {
+    fn of(transaction: &AssetTransaction) -> Self {
+        Self {
+            corrects: transaction.corrects,
+            original_amount: transaction.original_amount,
+            original_currency: transaction.original_currency.clone(),
+            fx_rate: transaction.fx_rate,
+            status: transaction.status,
+            scheduled: transaction.scheduled,
+            import_hash: transaction.import_hash.clone(),
+            import_batch: transaction.import_batch,
+            charge: transaction.charge,
+        }
+    }
+}
+
+/// 解析 `extra` 列，为空或无法解析时取默认值
+fn parse_extra<T: DeserializeOwned + Default>(row: &rusqlite::Row) -> rusqlite::Result<T> {
+    Ok(row
+        .get::<_, Option<String>>("extra")?
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default())
+}
+
 /// SQLite 数据库
 pub struct Database {
     conn: Connection,
 }
 
+/// 逐页读取资产的迭代器（创建时间倒序），内存中最多保留一页
+///
+/// 按上一页最后一个资产的 (创建时间, ID) 继续查询，迭代期间新建或删除其他资产不会导致重复。
+pub struct AssetIter<'a> {
+    db: &'a Database,
+    page_size: usize,
+    page: std::vec::IntoIter<Asset>,
+    after: Option<(String, String)>,
+    done: bool,
+}
+
+impl Iterator for AssetIter<'_> {
+    type Item = Result<Asset, StorageError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(asset) = self.page.next() {
+            return Some(Ok(asset));
+        }
+        if self.done {
+            return None;
+        }
+        match self.db.asset_page(self.after.as_ref(), self.page_size) {
+            Ok(page) => {
+                self.done = page.len() < self.page_size;
+                self.after = page
+                    .last()
+                    .map(|a| (a.created_at.to_rfc3339(), a.id.to_string()));
+                self.page = page.into_iter();
+                self.page.next().map(Ok)
+            }
+            Err(e) => {
+                self.done = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
 impl Database {
     /// 打开或创建数据库
     pub fn open(path: impl AsRef<Path>) -> Result<Self, StorageError> {
@@ -25,6 +188,8 @@ impl Database {
         }
 
         let conn = Connection::open(path)?;
+        // WAL 模式下快照的读事务不阻塞写入
+        conn.pragma_update(None, "journal_mode", "WAL")?;
         let db = Self { conn };
         
         db.init_schema()?;
@@ -41,6 +206,18 @@ impl Database {
         Ok(db)
     }
 
+    /// 创建只读快照：在独立的只读连接上开启读事务，之后的查询都只看到此时的数据
+    pub fn snapshot(&self) -> Result<Snapshot<Self>, StorageError> {
+        let path = self.conn.path().filter(|p| !p.is_empty()).ok_or_else(|| {
+            StorageError::InvalidData("In-memory databases do not support snapshots".to_string())
+        })?;
+        let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+        // 读事务在第一次读取时才确定快照
+        conn.execute_batch("BEGIN")?;
+        conn.query_row("SELECT count(*) FROM sqlite_master", [], |_| Ok(()))?;
+        Ok(Snapshot::new(Self { conn }))
+    }
+
     /// 初始化数据库表结构
     fn init_schema(&self) -> Result<(), StorageError> {
         self.conn.execute_batch(
@@ -56,7 +233,8 @@ impl Database {
                 tags TEXT,
                 metadata TEXT,
                 created_at TEXT NOT NULL,
-                updated_at TEXT NOT NULL
+                updated_at TEXT NOT NULL,
+                external_id TEXT
             );
 
             -- 交易记录表
@@ -68,6 +246,16 @@ impl Database {
                 amount_after REAL NOT NULL,
                 note TEXT,
                 timestamp TEXT NOT NULL,
+                external_id TEXT,
+                FOREIGN KEY (asset_id) REFERENCES assets(id) ON DELETE CASCADE
+            );
+
+            -- 每日余额表（资产价值的日终快照，随资产与交易的修改更新）
+            CREATE TABLE IF NOT EXISTS daily_balances (
+                asset_id TEXT NOT NULL,
+                date TEXT NOT NULL,
+                value REAL NOT NULL,
+                PRIMARY KEY (asset_id, date),
                 FOREIGN KEY (asset_id) REFERENCES assets(id) ON DELETE CASCADE
             );
 
@@ -80,11 +268,182 @@ impl Database {
             -- 创建索引
             CREATE INDEX IF NOT EXISTS idx_assets_type ON assets(asset_type);
             CREATE INDEX IF NOT EXISTS idx_assets_created ON assets(created_at);
-            CREATE INDEX IF NOT EXISTS idx_transactions_asset ON transactions(asset_id);
             CREATE INDEX IF NOT EXISTS idx_transactions_time ON transactions(timestamp);
+            CREATE INDEX IF NOT EXISTS idx_transactions_asset_time
+                ON transactions(asset_id, timestamp);
+            CREATE INDEX IF NOT EXISTS idx_transactions_type_time
+                ON transactions(transaction_type, timestamp);
+            -- 已被 (asset_id, timestamp) 复合索引覆盖
+            DROP INDEX IF EXISTS idx_transactions_asset;
+            "#,
+        )?;
+
+        // 旧数据库补充外部标识、回收站与其余字段列（列已存在时忽略错误）
+        for sql in [
+            "ALTER TABLE assets ADD COLUMN external_id TEXT",
+            "ALTER TABLE transactions ADD COLUMN external_id TEXT",
+            "ALTER TABLE assets ADD COLUMN deleted_at TEXT",
+            "ALTER TABLE assets ADD COLUMN extra TEXT",
+            "ALTER TABLE transactions ADD COLUMN extra TEXT",
+        ] {
+            let _ = self.conn.execute(sql, []);
+        }
+        self.conn.execute_batch(
+            r#"
+            CREATE UNIQUE INDEX IF NOT EXISTS idx_assets_external_id ON assets(external_id);
+            CREATE UNIQUE INDEX IF NOT EXISTS idx_transactions_external_id
+                ON transactions(asset_id, external_id);
+            "#,
+        )?;
+
+        self.init_tag_index()?;
+        self.init_search_index()?;
+        self.init_audit_log()
+    }
+
+    /// 标签表：`tags` 保存标签名，`asset_tags` 关联资产与标签，由触发器按资产的 `tags` 列同步。
+    /// 资产的 `tags` 列仍保存完整的标签数组，读取资产时不需要联表
+    fn init_tag_index(&self) -> Result<(), StorageError> {
+        let exists: bool = self.conn.query_row(
+            "SELECT count(*) > 0 FROM sqlite_master WHERE name = 'asset_tags'",
+            [],
+            |row| row.get(0),
+        )?;
+        self.conn.execute_batch(
+            r#"
+            CREATE TABLE IF NOT EXISTS tags (
+                id INTEGER PRIMARY KEY,
+                name TEXT NOT NULL UNIQUE
+            );
+            CREATE TABLE IF NOT EXISTS asset_tags (
+                asset_id TEXT NOT NULL REFERENCES assets(id) ON DELETE CASCADE,
+                tag_id INTEGER NOT NULL REFERENCES tags(id) ON DELETE CASCADE,
+                PRIMARY KEY (asset_id, tag_id)
+            ) WITHOUT ROWID;
+            CREATE INDEX IF NOT EXISTS idx_asset_tags_tag ON asset_tags(tag_id);
+
+            CREATE TRIGGER IF NOT EXISTS assets_tags_insert AFTER INSERT ON assets BEGIN
+                INSERT OR IGNORE INTO tags(name) SELECT value FROM json_each(new.tags);
+                INSERT OR IGNORE INTO asset_tags(asset_id, tag_id)
+                SELECT new.id, tags.id FROM json_each(new.tags)
+                JOIN tags ON tags.name = json_each.value;
+            END;
+            CREATE TRIGGER IF NOT EXISTS assets_tags_update AFTER UPDATE OF tags ON assets BEGIN
+                DELETE FROM asset_tags WHERE asset_id = old.id;
+                INSERT OR IGNORE INTO tags(name) SELECT value FROM json_each(new.tags);
+                INSERT OR IGNORE INTO asset_tags(asset_id, tag_id)
+                SELECT new.id, tags.id FROM json_each(new.tags)
+                JOIN tags ON tags.name = json_each.value;
+                DELETE FROM tags WHERE id NOT IN (SELECT tag_id FROM asset_tags);
+            END;
+            CREATE TRIGGER IF NOT EXISTS assets_tags_delete AFTER DELETE ON assets BEGIN
+                DELETE FROM asset_tags WHERE asset_id = old.id;
+                DELETE FROM tags WHERE id NOT IN (SELECT tag_id FROM asset_tags);
+            END;
+            "#,
+        )?;
+        // 旧数据库首次建立时从资产的标签数组填充
+        if !exists {
+            self.conn.execute_batch(
+                r#"
+                INSERT OR IGNORE INTO tags(name)
+                SELECT DISTINCT json_each.value FROM assets, json_each(assets.tags);
+                INSERT OR IGNORE INTO asset_tags(asset_id, tag_id)
+                SELECT assets.id, tags.id FROM assets, json_each(assets.tags)
+                JOIN tags ON tags.name = json_each.value;
+                "#,
+            )?;
+        }
+        Ok(())
+    }
+
+    /// 修改记录：由触发器在资产与交易表的每次写入后记下修改前后的内容，
+    /// 操作者取自 `audit_context`（由 `set_actor` 设置）
+    fn init_audit_log(&self) -> Result<(), StorageError> {
+        self.conn.execute_batch(
+            r#"
+            CREATE TABLE IF NOT EXISTS audit_log (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                timestamp TEXT NOT NULL,
+                actor TEXT NOT NULL,
+                operation TEXT NOT NULL,
+                entity TEXT NOT NULL,
+                entity_id TEXT NOT NULL,
+                before TEXT,
+                after TEXT
+            );
+            CREATE INDEX IF NOT EXISTS idx_audit_log_entity ON audit_log(entity_id, timestamp);
+            CREATE INDEX IF NOT EXISTS idx_audit_log_time ON audit_log(timestamp);
+
+            CREATE TABLE IF NOT EXISTS audit_context (
+                id INTEGER PRIMARY KEY CHECK (id = 1),
+                actor TEXT NOT NULL
+            );
             "#,
         )?;
+        self.set_actor(DEFAULT_ACTOR)?;
 
+        let now = "strftime('%Y-%m-%dT%H:%M:%fZ', 'now')";
+        let actor = "(SELECT actor FROM audit_context WHERE id = 1)";
+        for (table, entity, columns) in AUDITED_COLUMNS {
+            let old = format!("json_object({})", columns.replace("{r}", "old"));
+            let new = format!("json_object({})", columns.replace("{r}", "new"));
+            self.conn.execute_batch(&format!(
+                r#"
+                CREATE TRIGGER IF NOT EXISTS {table}_audit_insert AFTER INSERT ON {table} BEGIN
+                    INSERT INTO audit_log (timestamp, actor, operation, entity, entity_id, after)
+                    VALUES ({now}, {actor}, 'create', '{entity}', new.id, {new});
+                END;
+                CREATE TRIGGER IF NOT EXISTS {table}_audit_update AFTER UPDATE ON {table} BEGIN
+                    INSERT INTO audit_log (timestamp, actor, operation, entity, entity_id, before, after)
+                    VALUES ({now}, {actor}, 'update', '{entity}', new.id, {old}, {new});
+                END;
+                CREATE TRIGGER IF NOT EXISTS {table}_audit_delete AFTER DELETE ON {table} BEGIN
+                    INSERT INTO audit_log (timestamp, actor, operation, entity, entity_id, before)
+                    VALUES ({now}, {actor}, 'delete', '{entity}', old.id, {old});
+                END;
+                "#
+            ))?;
+        }
+        Ok(())
+    }
+
+    /// 资产全文索引：FTS5 外部内容表，由触发器与资产表保持同步。
+    /// trigram 分词可匹配中文等无空格文本中的任意子串（至少 3 个字符）
+    fn init_search_index(&self) -> Result<(), StorageError> {
+        let exists: bool = self.conn.query_row(
+            "SELECT count(*) > 0 FROM sqlite_master WHERE name = 'assets_fts'",
+            [],
+            |row| row.get(0),
+        )?;
+        self.conn.execute_batch(
+            r#"
+            CREATE VIRTUAL TABLE IF NOT EXISTS assets_fts USING fts5(
+                name, description, tags,
+                content = 'assets', content_rowid = 'rowid', tokenize = 'trigram'
+            );
+
+            CREATE TRIGGER IF NOT EXISTS assets_fts_insert AFTER INSERT ON assets BEGIN
+                INSERT INTO assets_fts(rowid, name, description, tags)
+                VALUES (new.rowid, new.name, new.description, new.tags);
+            END;
+            CREATE TRIGGER IF NOT EXISTS assets_fts_delete AFTER DELETE ON assets BEGIN
+                INSERT INTO assets_fts(assets_fts, rowid, name, description, tags)
+                VALUES ('delete', old.rowid, old.name, old.description, old.tags);
+            END;
+            CREATE TRIGGER IF NOT EXISTS assets_fts_update AFTER UPDATE ON assets BEGIN
+                INSERT INTO assets_fts(assets_fts, rowid, name, description, tags)
+                VALUES ('delete', old.rowid, old.name, old.description, old.tags);
+                INSERT INTO assets_fts(rowid, name, description, tags)
+                VALUES (new.rowid, new.name, new.description, new.tags);
+            END;
+            "#,
+        )?;
+        // 旧数据库首次建立索引
+        if !exists {
+            self.conn
+                .execute("INSERT INTO assets_fts(assets_fts) VALUES ('rebuild')", [])?;
+        }
         Ok(())
     }
 
@@ -94,8 +453,8 @@ impl Database {
     pub fn create_asset(&self, asset: &Asset) -> Result<(), StorageError> {
         self.conn.execute(
             r#"
-            INSERT INTO assets (id, name, asset_type, value, currency, description, tags, metadata, created_at, updated_at)
-            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+            INSERT INTO assets (id, name, asset_type, value, currency, description, tags, metadata, created_at, updated_at, external_id, extra)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)
             "#,
             params![
                 asset.id.to_string(),
@@ -108,26 +467,66 @@ impl Database {
                 asset.metadata.to_string(),
                 asset.created_at.to_rfc3339(),
                 asset.updated_at.to_rfc3339(),
+                asset.external_id,
+                serde_json::to_string(&AssetExtra::of(asset))?,
             ],
         )?;
 
-        Ok(())
+        self.refresh_daily_balances(asset.id)
+    }
+
+    /// 在一个事务中批量创建资产，出错的项跳过，其余一起提交
+    pub fn create_assets_bulk(&self, assets: &[Asset]) -> Result<BulkResult, StorageError> {
+        let tx = self.conn.unchecked_transaction()?;
+        let mut result = BulkResult::default();
+        for (index, asset) in assets.iter().enumerate() {
+            let inserted = self.savepoint(|| self.create_asset(asset))?;
+            result.push(index, asset.id, inserted);
+        }
+        tx.commit()?;
+        Ok(result)
     }
 
-    /// 获取资产
+    /// 在保存点中执行一项写入，出错时只撤销该项；外层错误为保存点本身失败
+    fn savepoint(
+        &self,
+        write: impl FnOnce() -> Result<(), StorageError>,
+    ) -> Result<Result<(), StorageError>, StorageError> {
+        self.conn.execute_batch("SAVEPOINT bulk_item")?;
+        let result = write();
+        if result.is_err() {
+            self.conn.execute_batch("ROLLBACK TO bulk_item")?;
+        }
+        self.conn.execute_batch("RELEASE bulk_item")?;
+        Ok(result)
+    }
+
+    /// 获取资产（不含回收站中的资产）
     pub fn get_asset(&self, id: Uuid) -> Result<Option<Asset>, StorageError> {
-        let result = self.conn.query_row(
-            "SELECT * FROM assets WHERE id = ?1",
-            params![id.to_string()],
-            |row| self.row_to_asset(row),
-        ).optional()?;
+        let result = self
+            .conn
+            .query_row(
+                "SELECT * FROM assets WHERE id = ?1 AND deleted_at IS NULL",
+                params![id.to_string()],
+                |row| self.row_to_asset(row),
+            )
+            .optional()?;
 
         Ok(result)
     }
 
-    /// 获取所有资产
-    pub fn list_assets(&self) -> Result<Vec<Asset>, StorageError> {
-        let mut stmt = self.conn.prepare("SELECT * FROM assets ORDER BY created_at DESC")?;
+    /// 获取所有资产，按指定字段排序（默认创建时间倒序）
+    pub fn list_assets(
+        &self,
+        sort_by: SortBy,
+        order: SortOrder,
+    ) -> Result<Vec<Asset>, StorageError> {
+        // 列名与方向来自枚举，不含用户输入；值相同时按创建时间倒序
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT * FROM assets WHERE deleted_at IS NULL ORDER BY {} {}, created_at DESC",
+            sort_by.column(),
+            order.keyword()
+        ))?;
         
         let assets = stmt
             .query_map([], |row| self.row_to_asset(row))?
@@ -136,19 +535,183 @@ impl Database {
         Ok(assets)
     }
 
+    /// 逐页读取资产（创建时间倒序），不一次取出全部资产，供导出与报告处理大量资产
+    pub fn iter_assets(&self) -> AssetIter<'_> {
+        self.iter_assets_by(ASSET_PAGE_SIZE)
+    }
+
+    fn iter_assets_by(&self, page_size: usize) -> AssetIter<'_> {
+        AssetIter {
+            db: self,
+            page_size: page_size.max(1),
+            page: Vec::new().into_iter(),
+            after: None,
+            done: false,
+        }
+    }
+
+    /// 排在 (创建时间, ID) 之后的一页资产
+    fn asset_page(
+        &self,
+        after: Option<&(String, String)>,
+        limit: usize,
+    ) -> Result<Vec<Asset>, StorageError> {
+        let mut sql = "SELECT * FROM assets WHERE deleted_at IS NULL".to_string();
+        let mut args: Vec<SqlValue> = Vec::new();
+        if let Some((created_at, id)) = after {
+            sql.push_str(" AND (created_at < ?1 OR (created_at = ?1 AND id < ?2))");
+            args.push(SqlValue::Text(created_at.clone()));
+            args.push(SqlValue::Text(id.clone()));
+        }
+        sql.push_str(&format!(
+            " ORDER BY created_at DESC, id DESC LIMIT {}",
+            limit
+        ));
+        let mut stmt = self.conn.prepare(&sql)?;
+        let assets = stmt
+            .query_map(params_from_iter(args), |row| self.row_to_asset(row))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(assets)
+    }
+
     /// 按类型获取资产
     pub fn list_assets_by_type(&self, asset_type: &AssetType) -> Result<Vec<Asset>, StorageError> {
-        let mut stmt = self.conn.prepare(
-            "SELECT * FROM assets WHERE asset_type = ?1 ORDER BY created_at DESC"
-        )?;
-        
+        self.query_assets(&AssetFilter::default().with_type(asset_type.clone()))
+    }
+
+    /// 按类型、货币、标签、价值与时间范围筛选资产
+    pub fn query_assets(&self, filter: &AssetFilter) -> Result<Vec<Asset>, StorageError> {
+        let (sql, args) = Self::assets_sql(filter)?;
+        let mut stmt = self.conn.prepare(&sql)?;
         let assets = stmt
-            .query_map(params![asset_type.as_str()], |row| self.row_to_asset(row))?
+            .query_map(params_from_iter(args), |row| self.row_to_asset(row))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(assets)
+    }
+
+    /// 生成资产筛选语句
+    fn assets_sql(filter: &AssetFilter) -> Result<(String, Vec<SqlValue>), StorageError> {
+        let (conditions, args) = Self::asset_conditions(filter)?;
+        Ok((
+            format!(
+                "SELECT * FROM assets WHERE {} ORDER BY created_at DESC",
+                conditions
+            ),
+            args,
+        ))
+    }
+
+    /// 资产筛选的 WHERE 条件：标签通过 `asset_tags` 查找，时间按 RFC 3339 文本比较
+    fn asset_conditions(filter: &AssetFilter) -> Result<(String, Vec<SqlValue>), StorageError> {
+        let mut sql = String::from("deleted_at IS NULL");
+        let mut args = Vec::new();
+        if let Some(asset_type) = &filter.asset_type {
+            sql.push_str(" AND asset_type = ?");
+            args.push(SqlValue::Text(asset_type.as_str().to_string()));
+        }
+        if let Some(currency) = &filter.currency {
+            sql.push_str(" AND currency = ?");
+            args.push(SqlValue::Text(serde_json::to_string(currency)?));
+        }
+        for tag in &filter.tags {
+            sql.push_str(
+                " AND id IN (SELECT asset_tags.asset_id FROM tags \
+                 JOIN asset_tags ON asset_tags.tag_id = tags.id WHERE tags.name = ?)",
+            );
+            args.push(SqlValue::Text(tag.clone()));
+        }
+        if let Some(min) = filter.min_value {
+            sql.push_str(" AND value >= ?");
+            args.push(SqlValue::Real(min));
+        }
+        if let Some(max) = filter.max_value {
+            sql.push_str(" AND value <= ?");
+            args.push(SqlValue::Real(max));
+        }
+        for (column, op, time) in [
+            ("created_at", ">=", filter.created_since),
+            ("created_at", "<=", filter.created_until),
+            ("updated_at", ">=", filter.updated_since),
+            ("updated_at", "<=", filter.updated_until),
+        ] {
+            if let Some(time) = time {
+                sql.push_str(&format!(" AND {} {} ?", column, op));
+                args.push(SqlValue::Text(time.to_rfc3339()));
+            }
+        }
+        Ok((sql, args))
+    }
+
+    /// 按元数据字段筛选资产（使用 JSON1 的 json_extract）
+    pub fn query_assets_by_metadata(
+        &self,
+        json_path: &str,
+        value: &serde_json::Value,
+    ) -> Result<Vec<Asset>, StorageError> {
+        let path = MetadataPath::parse(json_path)
+            .map_err(StorageError::InvalidData)?
+            .to_string();
+        // json_extract 对 JSON null 与缺失字段都返回 NULL，因此 null 用 json_type 区分
+        let (condition, arg) = match value {
+            serde_json::Value::Null => (
+                "json_type(metadata, ?1) = ?2",
+                SqlValue::Text("null".to_string()),
+            ),
+            serde_json::Value::Bool(b) => (
+                "json_extract(metadata, ?1) = ?2",
+                SqlValue::Integer(*b as i64),
+            ),
+            serde_json::Value::Number(n) => (
+                "json_extract(metadata, ?1) = ?2",
+                SqlValue::Real(n.as_f64().unwrap_or_default()),
+            ),
+            serde_json::Value::String(s) => {
+                ("json_extract(metadata, ?1) = ?2", SqlValue::Text(s.clone()))
+            }
+            other => (
+                "json_extract(metadata, ?1) = json(?2)",
+                SqlValue::Text(other.to_string()),
+            ),
+        };
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT * FROM assets WHERE deleted_at IS NULL AND {} ORDER BY created_at DESC",
+            condition
+        ))?;
+
+        let assets = stmt
+            .query_map(params![path, arg], |row| self.row_to_asset(row))?
             .collect::<Result<Vec<_>, _>>()?;
 
         Ok(assets)
     }
 
+    /// 所有标签及使用数量，按名称排序；只有回收站中的资产使用的标签数量为 0
+    pub fn list_tags(&self) -> Result<Vec<TagUsage>, StorageError> {
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT tags.name, count(assets.id) FROM tags
+            JOIN asset_tags ON asset_tags.tag_id = tags.id
+            LEFT JOIN assets ON assets.id = asset_tags.asset_id AND assets.deleted_at IS NULL
+            GROUP BY tags.id
+            ORDER BY tags.name
+            "#,
+        )?;
+        let tags = stmt
+            .query_map([], |row| {
+                Ok(TagUsage {
+                    name: row.get(0)?,
+                    asset_count: row.get::<_, i64>(1)? as usize,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(tags)
+    }
+
+    /// 使用指定标签的资产
+    pub fn assets_with_tag(&self, tag: &str) -> Result<Vec<Asset>, StorageError> {
+        self.query_assets(&AssetFilter::default().with_tag(tag))
+    }
+
     /// 更新资产
     pub fn update_asset(&self, asset: &Asset) -> Result<(), StorageError> {
         let rows = self.conn.execute(
@@ -161,7 +724,9 @@ impl Database {
                 description = ?6,
                 tags = ?7,
                 metadata = ?8,
-                updated_at = ?9
+                updated_at = ?9,
+                external_id = ?10,
+                extra = ?11
             WHERE id = ?1
             "#,
             params![
@@ -174,6 +739,8 @@ impl Database {
                 serde_json::to_string(&asset.tags)?,
                 asset.metadata.to_string(),
                 asset.updated_at.to_rfc3339(),
+                asset.external_id,
+                serde_json::to_string(&AssetExtra::of(asset))?,
             ],
         )?;
 
@@ -181,15 +748,71 @@ impl Database {
             return Err(StorageError::NotFound(asset.id.to_string()));
         }
 
-        Ok(())
+        self.refresh_daily_balances(asset.id)
     }
 
-    /// 删除资产
+    /// 按外部标识获取资产
+    pub fn get_asset_by_external_id(&self, external_id: &str) -> Result<Option<Asset>, StorageError> {
+        let result = self.conn.query_row(
+            "SELECT * FROM assets WHERE external_id = ?1",
+            params![external_id],
+            |row| self.row_to_asset(row),
+        ).optional()?;
+
+        Ok(result)
+    }
+
+    /// 新建或更新资产（按外部标识匹配，未设置时按名称与类型匹配）
+    pub fn upsert_asset(&self, asset: &Asset) -> Result<AssetUpsert, StorageError> {
+        let existing = match asset.external_id.as_deref() {
+            Some(external_id) => self.get_asset_by_external_id(external_id)?,
+            None => self.conn.query_row(
+                "SELECT * FROM assets WHERE name = ?1 AND asset_type = ?2 LIMIT 1",
+                params![asset.name, asset.asset_type.as_str()],
+                |row| self.row_to_asset(row),
+            ).optional()?,
+        };
+
+        let Some(existing) = existing else {
+            self.create_asset(asset)?;
+            return Ok(AssetUpsert {
+                asset: asset.clone(),
+                created: true,
+            });
+        };
+        let mut updated = asset.clone();
+        updated.id = existing.id;
+        updated.created_at = existing.created_at;
+        updated.updated_at = Utc::now();
+        self.update_asset(&updated)?;
+        Ok(AssetUpsert {
+            asset: updated,
+            created: false,
+        })
+    }
+
+    /// 删除资产：移入回收站，交易与每日余额保留，可恢复
     pub fn delete_asset(&self, id: Uuid) -> Result<(), StorageError> {
+        let rows = self.conn.execute(
+            "UPDATE assets SET deleted_at = ?2 WHERE id = ?1 AND deleted_at IS NULL",
+            params![id.to_string(), Utc::now().to_rfc3339()],
+        )?;
+        if rows == 0 {
+            return Err(StorageError::NotFound(id.to_string()));
+        }
+        Ok(())
+    }
+
+    /// 永久删除资产（包括回收站中的）及其每日余额
+    pub fn delete_asset_permanently(&self, id: Uuid) -> Result<(), StorageError> {
         let rows = self.conn.execute(
             "DELETE FROM assets WHERE id = ?1",
             params![id.to_string()],
         )?;
+        self.conn.execute(
+            "DELETE FROM daily_balances WHERE asset_id = ?1",
+            params![id.to_string()],
+        )?;
 
         if rows == 0 {
             return Err(StorageError::NotFound(id.to_string()));
@@ -198,21 +821,87 @@ impl Database {
         Ok(())
     }
 
+    /// 回收站中的资产，最近删除的在前
+    pub fn list_trashed(&self) -> Result<Vec<Asset>, StorageError> {
+        let mut stmt = self.conn.prepare(
+            "SELECT * FROM assets WHERE deleted_at IS NOT NULL ORDER BY deleted_at DESC",
+        )?;
+        let assets = stmt
+            .query_map([], |row| self.row_to_asset(row))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(assets)
+    }
+
+    /// 从回收站恢复资产
+    pub fn restore_asset(&self, id: Uuid) -> Result<Asset, StorageError> {
+        let rows = self.conn.execute(
+            "UPDATE assets SET deleted_at = NULL WHERE id = ?1 AND deleted_at IS NOT NULL",
+            params![id.to_string()],
+        )?;
+        if rows == 0 {
+            return Err(StorageError::NotFound(id.to_string()));
+        }
+        self.get_asset(id)?
+            .ok_or_else(|| StorageError::NotFound(id.to_string()))
+    }
+
+    /// 永久删除 `older_than` 及之前移入回收站的资产，返回删除的数量
+    pub fn purge_trash(&self, older_than: DateTime<Utc>) -> Result<usize, StorageError> {
+        let cutoff = older_than.to_rfc3339();
+        self.conn.execute(
+            r#"
+            DELETE FROM daily_balances WHERE asset_id IN
+                (SELECT id FROM assets WHERE deleted_at IS NOT NULL AND deleted_at <= ?1)
+            "#,
+            params![cutoff],
+        )?;
+        let rows = self.conn.execute(
+            "DELETE FROM assets WHERE deleted_at IS NOT NULL AND deleted_at <= ?1",
+            params![cutoff],
+        )?;
+        Ok(rows)
+    }
+
     /// 搜索资产
     pub fn search_assets(&self, query: &str) -> Result<Vec<Asset>, StorageError> {
-        let pattern = format!("%{}%", query);
+        // 拼音与模糊匹配无法用 LIKE 表达，在内存中过滤
+        let settings = self.get_settings(SEARCH_SETTINGS)?;
+        let assets = self
+            .list_assets(SortBy::default(), SortOrder::default())?
+            .into_iter()
+            .filter(|a| {
+                settings.matches(query, &a.name)
+                    || a.description
+                        .as_ref()
+                        .is_some_and(|d| settings.matches(query, d))
+                    || a.tags.iter().any(|t| settings.matches(query, t))
+            })
+            .collect();
+
+        Ok(assets)
+    }
+
+    /// 按相关度搜索资产（FTS5 的 bm25 排序，名称权重最高，其次标签、描述）
+    ///
+    /// 不足 3 个字符的查询无法用 trigram 索引，退回到 `search_assets`
+    pub fn search_assets_ranked(&self, query: &str) -> Result<Vec<Asset>, StorageError> {
+        let query = query.trim();
+        if query.chars().count() < 3 {
+            return self.search_assets(query);
+        }
+        // 整体作为短语查询，双引号转义后不会被解析为 FTS5 语法
+        let phrase = format!("\"{}\"", query.replace('"', "\"\""));
         let mut stmt = self.conn.prepare(
             r#"
-            SELECT * FROM assets 
-            WHERE name LIKE ?1 OR description LIKE ?1 OR tags LIKE ?1
-            ORDER BY created_at DESC
-            "#
+            SELECT assets.* FROM assets_fts
+            JOIN assets ON assets.rowid = assets_fts.rowid
+            WHERE assets_fts MATCH ?1 AND assets.deleted_at IS NULL
+            ORDER BY bm25(assets_fts, 10.0, 1.0, 5.0), assets.created_at DESC
+            "#,
         )?;
-        
         let assets = stmt
-            .query_map(params![pattern], |row| self.row_to_asset(row))?
+            .query_map(params![phrase], |row| self.row_to_asset(row))?
             .collect::<Result<Vec<_>, _>>()?;
-
         Ok(assets)
     }
 
@@ -225,6 +914,7 @@ impl Database {
         let metadata_str: String = row.get("metadata")?;
         let created_str: String = row.get("created_at")?;
         let updated_str: String = row.get("updated_at")?;
+        let extra: AssetExtra = parse_extra(row)?;
 
         Ok(Asset {
             id: Uuid::parse_str(&id_str).unwrap_or_default(),
@@ -241,6 +931,19 @@ impl Database {
             updated_at: DateTime::parse_from_rfc3339(&updated_str)
                 .map(|dt| dt.with_timezone(&Utc))
                 .unwrap_or_else(|_| Utc::now()),
+            formula: extra.formula,
+            cost_basis: extra.cost_basis,
+            target_price: extra.target_price,
+            target_notified: extra.target_notified,
+            import_batch: extra.import_batch,
+            external_id: row.get("external_id")?,
+            display: extra.display,
+            display_currency: extra.display_currency,
+            deleted_at: row
+                .get::<_, Option<String>>("deleted_at")?
+                .and_then(|s| DateTime::parse_from_rfc3339(&s).ok())
+                .map(|dt| dt.with_timezone(&Utc)),
+            conversion: None,
         })
     }
 
@@ -263,21 +966,40 @@ impl Database {
 
     /// 获取资产统计摘要
     pub fn get_summary(&self) -> Result<AssetSummary, StorageError> {
-        let assets = self.list_assets()?;
-        
-        let mut summary = AssetSummary::default();
-        summary.asset_count = assets.len();
+        self.get_summary_filtered(&AssetFilter::default())
+    }
 
-        for asset in &assets {
-            summary.total_value += asset.value;
+    /// 按筛选条件（如创建时间范围、标签）统计摘要，在 SQL 中按类型与货币分组求和
+    pub fn get_summary_filtered(&self, filter: &AssetFilter) -> Result<AssetSummary, StorageError> {
+        let (conditions, args) = Self::asset_conditions(filter)?;
+        let mut summary = AssetSummary::default();
 
-            // 按类型统计
-            let type_key = asset.asset_type.as_str().to_string();
-            *summary.by_type.entry(type_key).or_insert(0.0) += asset.value;
+        // 按类型统计
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT asset_type, SUM(value), COUNT(*) FROM assets WHERE {} GROUP BY asset_type",
+            conditions
+        ))?;
+        let mut rows = stmt.query(params_from_iter(args.iter().cloned()))?;
+        while let Some(row) = rows.next()? {
+            let asset_type: String = row.get(0)?;
+            let value: f64 = row.get(1)?;
+            summary.total_value += value;
+            summary.asset_count += row.get::<_, i64>(2)? as usize;
+            let type_key = self.parse_asset_type(&asset_type).as_str().to_string();
+            *summary.by_type.entry(type_key).or_insert(0.0) += value;
+        }
 
-            // 按货币统计
-            let currency_key = format!("{:?}", asset.currency);
-            *summary.by_currency.entry(currency_key).or_insert(0.0) += asset.value;
+        // 按货币统计，键名与 JSON 存储一致
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT currency, SUM(value) FROM assets WHERE {} GROUP BY currency",
+            conditions
+        ))?;
+        let mut rows = stmt.query(params_from_iter(args))?;
+        while let Some(row) = rows.next()? {
+            let currency: String = row.get(0)?;
+            let currency: Currency = serde_json::from_str(&currency).unwrap_or_default();
+            let currency_key = format!("{:?}", currency);
+            *summary.by_currency.entry(currency_key).or_insert(0.0) += row.get::<_, f64>(1)?;
         }
 
         Ok(summary)
@@ -289,8 +1011,8 @@ impl Database {
     pub fn add_transaction(&self, transaction: &AssetTransaction) -> Result<(), StorageError> {
         self.conn.execute(
             r#"
-            INSERT INTO transactions (id, asset_id, transaction_type, amount_before, amount_after, note, timestamp)
-            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+            INSERT INTO transactions (id, asset_id, transaction_type, amount_before, amount_after, note, timestamp, external_id, extra)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
             "#,
             params![
                 transaction.id.to_string(),
@@ -300,42 +1022,281 @@ impl Database {
                 transaction.amount_after,
                 transaction.note,
                 transaction.timestamp.to_rfc3339(),
+                transaction.external_id,
+                serde_json::to_string(&TransactionExtra::of(transaction))?,
             ],
         )?;
 
-        Ok(())
+        self.refresh_daily_balances(transaction.asset_id)
+    }
+
+    /// 在一个事务中批量记录交易，出错的项跳过，其余一起提交
+    pub fn add_transactions_bulk(
+        &self,
+        transactions: &[AssetTransaction],
+    ) -> Result<BulkResult, StorageError> {
+        let tx = self.conn.unchecked_transaction()?;
+        let mut result = BulkResult::default();
+        for (index, transaction) in transactions.iter().enumerate() {
+            let inserted = self.savepoint(|| self.add_transaction(transaction))?;
+            result.push(index, transaction.id, inserted);
+        }
+        tx.commit()?;
+        Ok(result)
     }
 
     /// 获取资产的交易历史
-    pub fn get_transactions(&self, asset_id: Uuid) -> Result<Vec<AssetTransaction>, StorageError> {
-        let mut stmt = self.conn.prepare(
-            "SELECT * FROM transactions WHERE asset_id = ?1 ORDER BY timestamp DESC"
-        )?;
-        
+    pub fn get_transactions(
+        &self,
+        asset_id: Uuid,
+        query: &TransactionQuery,
+    ) -> Result<Vec<AssetTransaction>, StorageError> {
+        let (sql, args) = Self::transactions_sql(asset_id, query);
+        self.query_transactions(&sql, args)
+    }
+
+    /// 分页获取资产的交易（按时间倒序），`cursor` 为上一页返回的游标
+    pub fn get_transactions_page(
+        &self,
+        asset_id: Uuid,
+        cursor: Option<&str>,
+        limit: usize,
+    ) -> Result<TransactionPage, StorageError> {
+        let mut sql = String::from("SELECT * FROM transactions WHERE asset_id = ?");
+        let mut args = vec![SqlValue::Text(asset_id.to_string())];
+        if let Some(cursor) = cursor.map(str::parse::<TransactionCursor>).transpose()? {
+            sql.push_str(" AND (timestamp < ? OR (timestamp = ? AND id < ?))");
+            let timestamp = cursor.timestamp.to_rfc3339();
+            args.push(SqlValue::Text(timestamp.clone()));
+            args.push(SqlValue::Text(timestamp));
+            args.push(SqlValue::Text(cursor.id.to_string()));
+        }
+        sql.push_str(" ORDER BY timestamp DESC, id DESC LIMIT ?");
+        args.push(SqlValue::Integer(limit as i64 + 1));
+        let rows = self.query_transactions(&sql, args)?;
+        Ok(TransactionPage::from_rows(rows, limit))
+    }
+
+    /// 所有资产最近的交易（按时间倒序），供动态列表使用
+    pub fn list_recent_transactions(
+        &self,
+        limit: usize,
+    ) -> Result<Vec<AssetTransaction>, StorageError> {
+        self.query_transactions(
+            "SELECT * FROM transactions ORDER BY timestamp DESC, id DESC LIMIT ?",
+            vec![SqlValue::Integer(limit as i64)],
+        )
+    }
+
+    fn query_transactions(
+        &self,
+        sql: &str,
+        args: Vec<SqlValue>,
+    ) -> Result<Vec<AssetTransaction>, StorageError> {
+        let mut stmt = self.conn.prepare(sql)?;
+
         let transactions = stmt
-            .query_map(params![asset_id.to_string()], |row| {
-                let id_str: String = row.get("id")?;
-                let asset_id_str: String = row.get("asset_id")?;
-                let type_str: String = row.get("transaction_type")?;
-                let timestamp_str: String = row.get("timestamp")?;
-
-                Ok(AssetTransaction {
-                    id: Uuid::parse_str(&id_str).unwrap_or_default(),
-                    asset_id: Uuid::parse_str(&asset_id_str).unwrap_or_default(),
-                    transaction_type: Self::parse_transaction_type(&type_str),
-                    amount_before: row.get("amount_before")?,
-                    amount_after: row.get("amount_after")?,
-                    note: row.get("note")?,
-                    timestamp: DateTime::parse_from_rfc3339(&timestamp_str)
-                        .map(|dt| dt.with_timezone(&Utc))
-                        .unwrap_or_else(|_| Utc::now()),
-                })
-            })?
+            .query_map(params_from_iter(args), Self::row_to_transaction)?
             .collect::<Result<Vec<_>, _>>()?;
 
         Ok(transactions)
     }
 
+    fn row_to_transaction(row: &rusqlite::Row) -> rusqlite::Result<AssetTransaction> {
+        let id_str: String = row.get("id")?;
+        let asset_id_str: String = row.get("asset_id")?;
+        let type_str: String = row.get("transaction_type")?;
+        let timestamp_str: String = row.get("timestamp")?;
+        let extra: TransactionExtra = parse_extra(row)?;
+
+        Ok(AssetTransaction {
+            id: Uuid::parse_str(&id_str).unwrap_or_default(),
+            asset_id: Uuid::parse_str(&asset_id_str).unwrap_or_default(),
+            transaction_type: Self::parse_transaction_type(&type_str),
+            amount_before: row.get("amount_before")?,
+            amount_after: row.get("amount_after")?,
+            note: row.get("note")?,
+            timestamp: DateTime::parse_from_rfc3339(&timestamp_str)
+                .map(|dt| dt.with_timezone(&Utc))
+                .unwrap_or_else(|_| Utc::now()),
+            corrects: extra.corrects,
+            original_amount: extra.original_amount,
+            original_currency: extra.original_currency,
+            fx_rate: extra.fx_rate,
+            status: extra.status,
+            scheduled: extra.scheduled,
+            import_hash: extra.import_hash,
+            import_batch: extra.import_batch,
+            external_id: row.get("external_id")?,
+            charge: extra.charge,
+        })
+    }
+
+    /// 生成交易查询语句，条件顺序与 (asset_id, timestamp) 索引一致
+    fn transactions_sql(asset_id: Uuid, query: &TransactionQuery) -> (String, Vec<SqlValue>) {
+        let mut sql = String::from("SELECT * FROM transactions WHERE asset_id = ?");
+        let mut args = vec![SqlValue::Text(asset_id.to_string())];
+        if let Some(since) = query.since {
+            sql.push_str(" AND timestamp >= ?");
+            args.push(SqlValue::Text(since.to_rfc3339()));
+        }
+        if let Some(until) = query.until {
+            sql.push_str(" AND timestamp <= ?");
+            args.push(SqlValue::Text(until.to_rfc3339()));
+        }
+        if let Some(transaction_type) = &query.transaction_type {
+            sql.push_str(" AND transaction_type = ?");
+            args.push(SqlValue::Text(format!("{:?}", transaction_type)));
+        }
+        sql.push_str(" ORDER BY timestamp DESC LIMIT ? OFFSET ?");
+        // SQLite 中 LIMIT -1 表示不限
+        args.push(SqlValue::Integer(query.limit.map_or(-1, |l| l as i64)));
+        args.push(SqlValue::Integer(query.offset as i64));
+        (sql, args)
+    }
+
+    /// 用 `EXPLAIN QUERY PLAN` 检查常用查询是否走索引
+    pub fn query_health(&self) -> Result<Vec<QueryHealth>, StorageError> {
+        let now = Utc::now();
+        let checks = [
+            ("transactions_by_asset", TransactionQuery::default()),
+            (
+                "transactions_by_asset_range",
+                TransactionQuery::default()
+                    .with_range(Some(now), Some(now))
+                    .with_limit(50),
+            ),
+        ];
+
+        let mut results = Vec::new();
+        for (name, query) in checks {
+            let (sql, args) = Self::transactions_sql(Uuid::nil(), &query);
+            results.push(self.explain(name, &sql, args)?);
+        }
+        results.push(self.explain(
+            "transactions_by_type",
+            "SELECT * FROM transactions WHERE transaction_type = ? ORDER BY timestamp DESC",
+            vec![SqlValue::Text("Buy".to_string())],
+        )?);
+        let (sql, args) = Self::assets_sql(&AssetFilter::default().with_tag("tag"))?;
+        results.push(self.explain("assets_by_tag", &sql, args)?);
+        Ok(results)
+    }
+
+    /// 压缩数据库：删除不再使用的标签，`VACUUM` 回收空闲页并 `ANALYZE` 更新查询统计，
+    /// 返回压缩前后的大小
+    pub fn compact(&self) -> Result<CompactReport, StorageError> {
+        let size_before = self.file_size()?;
+        let records_removed = self.conn.execute(
+            "DELETE FROM tags WHERE id NOT IN (SELECT tag_id FROM asset_tags)",
+            [],
+        )?;
+        self.conn.execute_batch("VACUUM; ANALYZE;")?;
+        Ok(CompactReport {
+            size_before,
+            size_after: self.file_size()?,
+            records_removed,
+        })
+    }
+
+    /// 数据库占用的大小（页数乘以页大小）
+    fn file_size(&self) -> Result<u64, StorageError> {
+        let pages: i64 = self.conn.query_row("PRAGMA page_count", [], |row| row.get(0))?;
+        let page_size: i64 = self.conn.query_row("PRAGMA page_size", [], |row| row.get(0))?;
+        Ok((pages * page_size) as u64)
+    }
+
+    fn explain(
+        &self,
+        name: &str,
+        sql: &str,
+        args: Vec<SqlValue>,
+    ) -> Result<QueryHealth, StorageError> {
+        let mut stmt = self.conn.prepare(&format!("EXPLAIN QUERY PLAN {}", sql))?;
+        let plan = stmt
+            .query_map(params_from_iter(args), |row| row.get::<_, String>("detail"))?
+            .collect::<Result<Vec<_>, _>>()?;
+        // 有索引时输出 "SEARCH ... USING INDEX"，全表扫描为 "SCAN transactions"
+        let full_scan = plan
+            .iter()
+            .any(|detail| detail.starts_with("SCAN") && !detail.contains("USING"));
+        Ok(QueryHealth {
+            name: name.to_string(),
+            plan,
+            full_scan,
+        })
+    }
+
+    // ============ 每日余额 ============
+
+    /// 重算资产的日终价值
+    fn refresh_daily_balances(&self, asset_id: Uuid) -> Result<(), StorageError> {
+        let Some(asset) = self.get_asset(asset_id)? else {
+            return Ok(());
+        };
+        let transactions = self.get_transactions(asset_id, &TransactionQuery::default())?;
+
+        // 已在外层事务中（批量写入、导入存档）时由外层提交
+        let tx = if self.conn.is_autocommit() {
+            Some(self.conn.unchecked_transaction()?)
+        } else {
+            None
+        };
+        self.conn.execute(
+            "DELETE FROM daily_balances WHERE asset_id = ?1",
+            params![asset_id.to_string()],
+        )?;
+        for (date, value) in end_of_day_values(&asset, &transactions) {
+            self.conn.execute(
+                "INSERT INTO daily_balances (asset_id, date, value) VALUES (?1, ?2, ?3)",
+                params![asset_id.to_string(), date.to_string(), value],
+            )?;
+        }
+        if let Some(tx) = tx {
+            tx.commit()?;
+        }
+        Ok(())
+    }
+
+    /// 获取资产在时间范围内每天的日终价值
+    pub fn get_daily_balances(
+        &self,
+        asset_id: Uuid,
+        range: &DateRange,
+    ) -> Result<Vec<DailyBalance>, StorageError> {
+        // 范围开始前的最后一个日终值也要读出，用于沿用到范围内
+        let mut stmt = self.conn.prepare(
+            r#"
+            SELECT date, value FROM daily_balances
+            WHERE asset_id = ?1 AND date <= ?2 AND date >= COALESCE(
+                (SELECT MAX(date) FROM daily_balances WHERE asset_id = ?1 AND date < ?3), ?3)
+            ORDER BY date
+            "#,
+        )?;
+        let rows = stmt
+            .query_map(
+                params![
+                    asset_id.to_string(),
+                    range.end.date_naive().to_string(),
+                    range.start.date_naive().to_string(),
+                ],
+                |row| Ok((row.get::<_, String>(0)?, row.get::<_, f64>(1)?)),
+            )?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut balances = DailyBalances::default();
+        for (date, value) in rows {
+            if let Ok(date) = date.parse::<NaiveDate>() {
+                balances.insert(DailyBalance {
+                    asset_id,
+                    date,
+                    value,
+                });
+            }
+        }
+        Ok(balances.series(asset_id, range))
+    }
+
     fn parse_transaction_type(s: &str) -> TransactionType {
         match s {
             "Buy" => TransactionType::Buy,
@@ -348,6 +1309,115 @@ impl Database {
         }
     }
 
+    // ============ 修改记录 ============
+
+    /// 设置之后的修改记入修改记录时的操作者
+    pub fn set_actor(&self, actor: &str) -> Result<(), StorageError> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO audit_context (id, actor) VALUES (1, ?1)",
+            params![actor],
+        )?;
+        Ok(())
+    }
+
+    /// 查询资产与交易的修改记录，按时间倒序
+    pub fn query_audit_log(&self, query: &AuditQuery) -> Result<Vec<AuditRecord>, StorageError> {
+        let mut sql = String::from(
+            "SELECT timestamp, actor, operation, entity, entity_id, before, after \
+             FROM audit_log WHERE 1 = 1",
+        );
+        let mut args: Vec<SqlValue> = Vec::new();
+        let mut push = |clause: &str, value: SqlValue| {
+            args.push(value);
+            sql.push_str(&format!(" AND {} ?{}", clause, args.len()));
+        };
+        if let Some(entity) = query.entity {
+            push("entity =", SqlValue::Text(enum_text(&entity)?));
+        }
+        if let Some(id) = query.entity_id {
+            push("entity_id =", SqlValue::Text(id.to_string()));
+        }
+        if let Some(operation) = query.operation {
+            push("operation =", SqlValue::Text(enum_text(&operation)?));
+        }
+        if let Some(actor) = &query.actor {
+            push("actor =", SqlValue::Text(actor.clone()));
+        }
+        if let Some(since) = query.since {
+            push("timestamp >=", SqlValue::Text(since.format(AUDIT_TIME_FORMAT).to_string()));
+        }
+        if let Some(until) = query.until {
+            push("timestamp <=", SqlValue::Text(until.format(AUDIT_TIME_FORMAT).to_string()));
+        }
+        sql.push_str(" ORDER BY timestamp DESC, id DESC");
+        if let Some(limit) = query.limit {
+            sql.push_str(&format!(" LIMIT {}", limit));
+        }
+
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows = stmt
+            .query_map(params_from_iter(args), |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(3)?,
+                    row.get::<_, String>(4)?,
+                    row.get::<_, Option<String>>(5)?,
+                    row.get::<_, Option<String>>(6)?,
+                ))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut records = Vec::with_capacity(rows.len());
+        for (timestamp, actor, entity, entity_id, before, after) in rows {
+            let timestamp = DateTime::parse_from_rfc3339(&timestamp)
+                .map_err(|e| StorageError::InvalidData(e.to_string()))?
+                .with_timezone(&Utc);
+            let entity: AuditEntity = serde_json::from_value(entity.into())?;
+            let entity_id = Uuid::parse_str(&entity_id)
+                .map_err(|e| StorageError::InvalidData(e.to_string()))?;
+            let parse = |json: Option<String>| -> Result<Option<serde_json::Value>, StorageError> {
+                Ok(json.as_deref().map(serde_json::from_str).transpose()?)
+            };
+            let (before, after) = (parse(before)?, parse(after)?);
+            if let Some(record) = AuditRecord::compare(
+                &actor,
+                entity,
+                entity_id,
+                before.as_ref(),
+                after.as_ref(),
+                timestamp,
+            )? {
+                records.push(record);
+            }
+        }
+        Ok(records)
+    }
+
+    /// 获取修改记录设置
+    pub fn get_audit_settings(&self) -> Result<AuditSettings, StorageError> {
+        self.get_settings(AUDIT_SETTINGS)
+    }
+
+    /// 保存修改记录设置，并按新的保留天数清除旧记录
+    pub fn set_audit_settings(&self, settings: &AuditSettings) -> Result<(), StorageError> {
+        settings.validate().map_err(StorageError::InvalidData)?;
+        self.set_settings(AUDIT_SETTINGS, settings)?;
+        self.prune_audit_log(Utc::now())?;
+        Ok(())
+    }
+
+    /// 清除超过保留天数的修改记录，返回清除的数量
+    pub fn prune_audit_log(&self, now: DateTime<Utc>) -> Result<usize, StorageError> {
+        let Some(cutoff) = self.get_audit_settings()?.cutoff(now) else {
+            return Ok(0);
+        };
+        Ok(self.conn.execute(
+            "DELETE FROM audit_log WHERE timestamp < ?1",
+            params![cutoff.format(AUDIT_TIME_FORMAT).to_string()],
+        )?)
+    }
+
     // ============ 设置 ============
 
     /// 保存设置
@@ -368,11 +1438,112 @@ impl Database {
         ).optional()?;
         Ok(result)
     }
+
+    /// 读取类型化设置，未设置时为默认值
+    pub fn get_settings<T: DeserializeOwned>(
+        &self,
+        settings: Settings<T>,
+    ) -> Result<T, StorageError> {
+        let raw = self.get_setting(settings.key())?;
+        Ok(settings.parse(raw.as_deref())?)
+    }
+
+    /// 保存类型化设置
+    pub fn set_settings<T: Serialize>(
+        &self,
+        settings: Settings<T>,
+        value: &T,
+    ) -> Result<(), StorageError> {
+        self.set_setting(settings.key(), &serde_json::to_string(value)?)
+    }
+
+    // ============ 便携存档 ============
+
+    /// 导出资产、交易与设置（不含回收站）
+    pub fn to_archive(&self) -> Result<Archive, StorageError> {
+        let assets = self.list_assets(SortBy::default(), SortOrder::default())?;
+        let transactions = self.query_transactions(
+            "SELECT * FROM transactions WHERE asset_id IN \
+             (SELECT id FROM assets WHERE deleted_at IS NULL) ORDER BY timestamp",
+            Vec::new(),
+        )?;
+        let mut stmt = self.conn.prepare("SELECT key, value FROM settings")?;
+        let settings = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<BTreeMap<String, String>, _>>()?;
+        Ok(Archive::new(assets, transactions, settings))
+    }
+
+    /// 在一个事务中导入存档中的资产、交易与设置，规则与 JSON 存储相同
+    pub fn apply_archive(
+        &self,
+        archive: &Archive,
+        mode: ArchiveMode,
+    ) -> Result<ArchiveSummary, StorageError> {
+        let tx = self.conn.unchecked_transaction()?;
+        if mode == ArchiveMode::Replace {
+            self.conn.execute_batch(
+                r#"
+                DELETE FROM daily_balances WHERE asset_id IN
+                    (SELECT id FROM assets WHERE deleted_at IS NULL);
+                DELETE FROM assets WHERE deleted_at IS NULL;
+                DELETE FROM settings;
+                "#,
+            )?;
+        }
+
+        let exists = |table: &str, id: Uuid| -> Result<bool, StorageError> {
+            Ok(self
+                .conn
+                .query_row(
+                    &format!("SELECT 1 FROM {} WHERE id = ?1", table),
+                    params![id.to_string()],
+                    |_| Ok(()),
+                )
+                .optional()?
+                .is_some())
+        };
+        let mut summary = ArchiveSummary::default();
+        for asset in &archive.assets {
+            if exists("assets", asset.id)? {
+                summary.skipped += 1;
+                continue;
+            }
+            self.create_asset(asset)?;
+            summary.assets += 1;
+        }
+        for txn in &archive.transactions {
+            if exists("transactions", txn.id)? || self.get_asset(txn.asset_id)?.is_none() {
+                summary.skipped += 1;
+                continue;
+            }
+            self.add_transaction(txn)?;
+            summary.transactions += 1;
+        }
+        for (key, raw) in &archive.settings {
+            if mode == ArchiveMode::Merge && self.get_setting(key)?.is_some() {
+                continue;
+            }
+            self.set_setting(key, raw)?;
+            summary.settings += 1;
+        }
+        tx.commit()?;
+        Ok(summary)
+    }
+}
+
+/// 枚举序列化后的名称（如 `AuditOperation::Create` 为 `create`）
+fn enum_text<T: Serialize>(value: &T) -> Result<String, StorageError> {
+    match serde_json::to_value(value)? {
+        serde_json::Value::String(text) => Ok(text),
+        other => Err(StorageError::InvalidData(other.to_string())),
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::storage::AuditOperation;
 
     #[test]
     fn test_database_operations() {
@@ -388,7 +1559,7 @@ mod tests {
         assert_eq!(loaded.value, 10000.0);
 
         // 列出资产
-        let assets = db.list_assets().unwrap();
+        let assets = db.list_assets(SortBy::default(), SortOrder::default()).unwrap();
         assert_eq!(assets.len(), 1);
 
         // 获取摘要
@@ -396,4 +1567,273 @@ mod tests {
         assert_eq!(summary.total_value, 10000.0);
         assert_eq!(summary.asset_count, 1);
     }
+
+    #[test]
+    fn test_query_assets() {
+        let db = Database::open_in_memory().unwrap();
+        let stock = Asset::new("股票", AssetType::Stock, 5000.0).with_tags(vec!["长期".into()]);
+        db.create_asset(&stock).unwrap();
+        db.create_asset(&Asset::new("现金", AssetType::Cash, 3000.0))
+            .unwrap();
+
+        let found = db
+            .query_assets(&AssetFilter::default().with_tag("长期"))
+            .unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].id, stock.id);
+        let found = db
+            .query_assets(&AssetFilter::default().with_value_range(None, Some(4000.0)))
+            .unwrap();
+        assert_eq!(found[0].name, "现金");
+        assert_eq!(db.list_assets_by_type(&AssetType::Stock).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_transaction_history() {
+        let db = Database::open_in_memory().unwrap();
+        let bank = Asset::new("活期", AssetType::BankDeposit, 0.0);
+        let cash = Asset::new("现金", AssetType::Cash, 0.0);
+        db.create_asset(&bank).unwrap();
+        db.create_asset(&cash).unwrap();
+        let start = Utc::now() - chrono::Duration::days(10);
+        for day in 0..6 {
+            let asset_id = if day % 2 == 0 { bank.id } else { cash.id };
+            let txn = AssetTransaction::new(asset_id, TransactionType::Income, 0.0, 1.0)
+                .with_timestamp(start + chrono::Duration::days(day));
+            db.add_transaction(&txn).unwrap();
+        }
+
+        let query = TransactionQuery::default()
+            .with_range(Some(start + chrono::Duration::days(1)), None)
+            .with_offset(1)
+            .with_limit(5);
+        let page = db.get_transactions(bank.id, &query).unwrap();
+        assert_eq!(page.len(), 1);
+        assert_eq!(page[0].timestamp, start + chrono::Duration::days(2));
+
+        let feed = db.list_recent_transactions(3).unwrap();
+        let days: Vec<i64> = feed
+            .iter()
+            .map(|t| (t.timestamp - start).num_days())
+            .collect();
+        assert_eq!(days, [5, 4, 3]);
+        assert_eq!(feed[0].asset_id, cash.id);
+    }
+
+    #[test]
+    fn test_iter_assets() {
+        let db = Database::open_in_memory().unwrap();
+        let start = Utc::now() - chrono::Duration::days(10);
+        let mut ids = Vec::new();
+        for i in 0..5 {
+            let mut asset = Asset::new(format!("资产{}", i), AssetType::Cash, 100.0);
+            // 两个资产创建时间相同，按 ID 区分先后
+            asset.created_at = start + chrono::Duration::days(i.min(3));
+            db.create_asset(&asset).unwrap();
+            ids.push(asset.id);
+        }
+
+        let streamed: Vec<Uuid> = db.iter_assets_by(2).map(|a| a.unwrap().id).collect();
+        let listed: Vec<Uuid> = db
+            .list_assets(SortBy::default(), SortOrder::default())
+            .unwrap()
+            .into_iter()
+            .map(|a| a.id)
+            .collect();
+        assert_eq!(streamed.len(), 5);
+        assert_eq!(streamed[2..], listed[2..]);
+        assert!(ids.iter().all(|id| streamed.contains(id)));
+        assert_eq!(db.iter_assets().count(), 5);
+    }
+
+    #[test]
+    fn test_summary_aggregation() {
+        let db = Database::open_in_memory().unwrap();
+        let usd = Asset::new("美股", AssetType::Stock, 8000.0)
+            .with_currency(Currency::USD)
+            .with_tags(vec!["长期".into()]);
+        let mut old = Asset::new("股票A", AssetType::Stock, 5000.0).with_tags(vec!["长期".into()]);
+        old.created_at = Utc::now() - chrono::Duration::days(30);
+        db.create_asset(&usd).unwrap();
+        db.create_asset(&old).unwrap();
+        db.create_asset(&Asset::new("现金", AssetType::Cash, 3000.0))
+            .unwrap();
+
+        // 与 JSON 存储的摘要一致
+        let summary = db.get_summary().unwrap();
+        assert_eq!((summary.asset_count, summary.total_value), (3, 16000.0));
+        assert_eq!(summary.by_type["stock"], 13000.0);
+        assert_eq!(summary.by_type["cash"], 3000.0);
+        assert_eq!(summary.by_currency["CNY"], 8000.0);
+        assert_eq!(summary.by_currency["USD"], 8000.0);
+
+        let summary = db
+            .get_summary_filtered(&AssetFilter::default().with_tag("长期"))
+            .unwrap();
+        assert_eq!((summary.asset_count, summary.total_value), (2, 13000.0));
+        let recent = AssetFilter::default()
+            .with_created_range(Some(Utc::now() - chrono::Duration::days(1)), None);
+        assert_eq!(
+            db.get_summary_filtered(&recent).unwrap().total_value,
+            11000.0
+        );
+    }
+
+    #[test]
+    fn test_tag_index() {
+        let db = Database::open_in_memory().unwrap();
+        let mut stock = Asset::new("股票", AssetType::Stock, 5000.0)
+            .with_tags(vec!["长期".into(), "投资".into()]);
+        db.create_asset(&stock).unwrap();
+        let fund = Asset::new("基金", AssetType::Fund, 1000.0).with_tags(vec!["投资".into()]);
+        db.create_asset(&fund).unwrap();
+
+        let tags = db.list_tags().unwrap();
+        let counts: Vec<(&str, usize)> = tags
+            .iter()
+            .map(|t| (t.name.as_str(), t.asset_count))
+            .collect();
+        assert_eq!(counts, vec![("投资", 2), ("长期", 1)]);
+        assert_eq!(db.assets_with_tag("长期").unwrap()[0].id, stock.id);
+
+        // 修改标签后关联随之更新，不再使用的标签删除；资产仍带有完整的标签数组
+        stock.tags = vec!["投资".into()];
+        db.update_asset(&stock).unwrap();
+        assert!(db.assets_with_tag("长期").unwrap().is_empty());
+        assert_eq!(db.list_tags().unwrap().len(), 1);
+        assert_eq!(db.get_asset(stock.id).unwrap().unwrap().tags, vec!["投资"]);
+
+        // 回收站中的资产不计数
+        db.delete_asset(fund.id).unwrap();
+        assert_eq!(db.list_tags().unwrap()[0].asset_count, 1);
+        assert_eq!(db.assets_with_tag("投资").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_search_assets_ranked() {
+        let db = Database::open_in_memory().unwrap();
+        let described =
+            Asset::new("工资卡", AssetType::BankDeposit, 100.0).with_description("招商银行储蓄卡");
+        let named = Asset::new("招商银行", AssetType::BankDeposit, 200.0);
+        db.create_asset(&described).unwrap();
+        db.create_asset(&named).unwrap();
+
+        let found = db.search_assets_ranked("招商银").unwrap();
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0].id, named.id);
+
+        // 删除资产后索引随之更新
+        db.delete_asset(named.id).unwrap();
+        assert_eq!(db.search_assets_ranked("招商银").unwrap().len(), 1);
+        assert!(db.search_assets_ranked("\"不存在\"").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_trash() {
+        let db = Database::open_in_memory().unwrap();
+        let asset = Asset::new("旧账户", AssetType::BankDeposit, 100.0);
+        db.create_asset(&asset).unwrap();
+
+        db.delete_asset(asset.id).unwrap();
+        assert!(db.get_asset(asset.id).unwrap().is_none());
+        let trashed = db.list_trashed().unwrap();
+        assert_eq!(trashed.len(), 1);
+        assert!(trashed[0].deleted_at.is_some());
+
+        assert_eq!(db.restore_asset(asset.id).unwrap().name, "旧账户");
+        assert!(db.list_trashed().unwrap().is_empty());
+
+        db.delete_asset(asset.id).unwrap();
+        assert_eq!(db.purge_trash(Utc::now()).unwrap(), 1);
+        assert!(db.restore_asset(asset.id).is_err());
+    }
+
+    #[test]
+    fn test_archive() {
+        let db = Database::open_in_memory().unwrap();
+        // 没有单独列的字段同样写入存档
+        let mut asset = Asset::new("储蓄卡", AssetType::BankDeposit, 100.0);
+        asset.cost_basis = Some(80.0);
+        asset.display_currency = Some(Currency::USD);
+        asset.display.color = Some("#336699".to_string());
+        db.create_asset(&asset).unwrap();
+        let mut txn = AssetTransaction::new(asset.id, TransactionType::Income, 0.0, 100.0);
+        txn.original_amount = Some(14.0);
+        txn.original_currency = Some(Currency::USD);
+        txn.fx_rate = Some(7.1);
+        txn.import_hash = Some("abc".to_string());
+        db.add_transaction(&txn).unwrap();
+        db.set_setting("theme", "\"dark\"").unwrap();
+        let archive = db.to_archive().unwrap();
+        assert_eq!((archive.assets.len(), archive.transactions.len()), (1, 1));
+        let exported = &archive.assets[0];
+        assert_eq!(exported.cost_basis, Some(80.0));
+        assert_eq!(exported.display_currency, Some(Currency::USD));
+        assert_eq!(exported.display, asset.display);
+        let exported = &archive.transactions[0];
+        assert_eq!((exported.original_amount, exported.fx_rate), (Some(14.0), Some(7.1)));
+        assert_eq!(exported.import_hash.as_deref(), Some("abc"));
+
+        let other = Database::open_in_memory().unwrap();
+        let summary = other.apply_archive(&archive, ArchiveMode::Merge).unwrap();
+        assert_eq!((summary.assets, summary.transactions, summary.settings), (1, 1, 1));
+        let summary = other.apply_archive(&archive, ArchiveMode::Merge).unwrap();
+        assert_eq!(summary.skipped, 2);
+        let summary = other.apply_archive(&archive, ArchiveMode::Replace).unwrap();
+        assert_eq!((summary.assets, summary.skipped), (1, 0));
+    }
+
+    #[test]
+    fn test_bulk_insert() {
+        let db = Database::open_in_memory().unwrap();
+        let existing = Asset::new("现金", AssetType::Cash, 10.0);
+        db.create_asset(&existing).unwrap();
+        let card = Asset::new("储蓄卡", AssetType::BankDeposit, 100.0);
+        let result = db
+            .create_assets_bulk(&[card.clone(), existing.clone()])
+            .unwrap();
+        assert_eq!(result.inserted, 1);
+        assert_eq!(
+            (result.errors[0].index, result.errors[0].id),
+            (1, existing.id)
+        );
+
+        let txn = AssetTransaction::new(card.id, TransactionType::Income, 0.0, 100.0);
+        let result = db.add_transactions_bulk(&[txn.clone(), txn]).unwrap();
+        assert_eq!((result.inserted, result.errors.len()), (1, 1));
+        let query = TransactionQuery::default();
+        assert_eq!(db.get_transactions(card.id, &query).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_audit_log() {
+        let db = Database::open_in_memory().unwrap();
+        let mut asset = Asset::new("存款", AssetType::BankDeposit, 100.0);
+        db.create_asset(&asset).unwrap();
+        db.set_actor("import").unwrap();
+        asset.value = 200.0;
+        db.update_asset(&asset).unwrap();
+
+        let records = db
+            .query_audit_log(&AuditQuery::for_entity(AuditEntity::Asset, asset.id))
+            .unwrap();
+        assert!(records.len() >= 2);
+        assert_eq!(records[0].actor, "import");
+        let update = records
+            .iter()
+            .find(|r| r.operation == AuditOperation::Update)
+            .unwrap();
+        assert!(update.changes.iter().any(|c| c.field == "value"));
+
+        let later = Utc::now() + chrono::Duration::days(400);
+        assert!(db.prune_audit_log(later).unwrap() >= 2);
+    }
+
+    #[test]
+    fn test_query_health() {
+        let db = Database::open_in_memory().unwrap();
+        for check in db.query_health().unwrap() {
+            assert!(!check.full_scan, "{}: {:?}", check.name, check.plan);
+        }
+    }
 }
@@ -0,0 +1,349 @@
+//! 云同步目录的安全措施：锁文件与外部修改检测
+//!
+//! 数据文件放在 Dropbox、OneDrive 等同步目录时，多台设备同时写入会产生冲突副本。
+//! 打开时在数据文件旁写入锁文件并定期刷新心跳，其他实例可据此提示文件正被使用（锁只是
+//! 提示性的，不阻止打开）。每次写入前检查主文件与日志是否被外部修改，被修改时不覆盖，
+//! 而是把磁盘上的版本与内存中的版本一起交给用户选择。没有未保存的修改时则定期检查并直接重新加载。
+
+use crate::asset::{Asset, AssetTransaction};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::io::{self, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use uuid::Uuid;
+
+/// 刷新锁文件心跳的间隔（秒）
+pub const LOCK_HEARTBEAT_SECS: u64 = 60;
+
+/// 心跳超过该时长（秒）未刷新的锁视为失效
+pub const LOCK_STALE_SECS: i64 = 300;
+
+/// 检查数据文件是否被外部修改的间隔（秒）
+pub const EXTERNAL_CHECK_INTERVAL_SECS: u64 = 2;
+
+/// 锁文件内容：持有数据文件的实例
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct LockInfo {
+    /// 实例ID（每次打开生成）
+    pub instance_id: Uuid,
+    /// 主机名
+    pub host: String,
+    /// 进程ID
+    pub pid: u32,
+    /// 最近一次心跳
+    pub heartbeat: DateTime<Utc>,
+}
+
+impl LockInfo {
+    fn current() -> Self {
+        let host = std::env::var("HOSTNAME")
+            .or_else(|_| std::env::var("COMPUTERNAME"))
+            .unwrap_or_else(|_| "unknown".to_string());
+        Self {
+            instance_id: Uuid::new_v4(),
+            host,
+            pid: std::process::id(),
+            heartbeat: Utc::now(),
+        }
+    }
+
+    /// 心跳是否已过期
+    pub fn is_stale(&self, now: DateTime<Utc>) -> bool {
+        now - self.heartbeat > Duration::seconds(LOCK_STALE_SECS)
+    }
+}
+
+/// 获取锁的结果
+#[derive(Debug)]
+pub enum LockAttempt {
+    /// 已获取
+    Acquired(StoreLock),
+    /// 另一实例持有未过期的锁
+    Held(LockInfo),
+}
+
+/// 本实例持有的锁文件，释放时删除
+#[derive(Debug)]
+pub struct StoreLock {
+    path: PathBuf,
+    info: LockInfo,
+}
+
+impl StoreLock {
+    /// 数据文件的锁文件（同目录下的 `<文件名>.lock`）
+    fn path_for(store_path: &Path) -> PathBuf {
+        let mut path = store_path.as_os_str().to_owned();
+        path.push(".lock");
+        PathBuf::from(path)
+    }
+
+    /// 获取锁：没有锁文件或锁已过期时写入本实例的信息
+    pub fn acquire(store_path: &Path) -> io::Result<LockAttempt> {
+        let path = Self::path_for(store_path);
+        if let Some(holder) = read_lock(&path)? {
+            if !holder.is_stale(Utc::now()) {
+                return Ok(LockAttempt::Held(holder));
+            }
+        }
+        let lock = Self {
+            path,
+            info: LockInfo::current(),
+        };
+        lock.write()?;
+        Ok(LockAttempt::Acquired(lock))
+    }
+
+    /// 刷新心跳；锁已被其他实例接管时返回其信息
+    pub fn refresh(&mut self) -> io::Result<Option<LockInfo>> {
+        if let Some(holder) = read_lock(&self.path)? {
+            if holder.instance_id != self.info.instance_id {
+                return Ok(Some(holder));
+            }
+        }
+        self.info.heartbeat = Utc::now();
+        self.write()?;
+        Ok(None)
+    }
+
+    fn write(&self) -> io::Result<()> {
+        let content = serde_json::to_vec(&self.info).map_err(io::Error::other)?;
+        OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(&self.path)?
+            .write_all(&content)
+    }
+}
+
+impl Drop for StoreLock {
+    fn drop(&mut self) {
+        let ours = read_lock(&self.path)
+            .ok()
+            .flatten()
+            .is_some_and(|holder| holder.instance_id == self.info.instance_id);
+        if ours {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+/// 读取锁文件，不存在或无法解析时为 None
+fn read_lock(path: &Path) -> io::Result<Option<LockInfo>> {
+    match fs::read(path) {
+        Ok(content) => Ok(serde_json::from_slice(&content).ok()),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// 文件在本实例最近一次读写后的状态
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileState {
+    len: u64,
+    modified: Option<SystemTime>,
+    hash: u64,
+}
+
+impl FileState {
+    /// 读取文件状态，文件不存在时为 None
+    pub fn read(path: &Path) -> io::Result<Option<Self>> {
+        match fs::read(path) {
+            Ok(content) => Self::written(path, &content).map(Some),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// 刚写入 `content` 后的文件状态
+    pub fn written(path: &Path, content: &[u8]) -> io::Result<Self> {
+        let metadata = fs::metadata(path)?;
+        Ok(Self {
+            len: metadata.len(),
+            modified: metadata.modified().ok(),
+            hash: hash_bytes(content),
+        })
+    }
+
+    /// 文件是否与记录的状态不同（大小与修改时间都未变时不读取内容）
+    pub fn changed(recorded: Option<&Self>, path: &Path) -> io::Result<bool> {
+        let metadata = match fs::metadata(path) {
+            Ok(metadata) => metadata,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(recorded.is_some()),
+            Err(e) => return Err(e),
+        };
+        let Some(recorded) = recorded else {
+            return Ok(true);
+        };
+        if metadata.len() == recorded.len && metadata.modified().ok() == recorded.modified {
+            return Ok(false);
+        }
+        // 同步工具可能只更新修改时间，以内容为准
+        Ok(hash_bytes(&fs::read(path)?) != recorded.hash)
+    }
+}
+
+fn hash_bytes(content: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// 检查外部修改的结果
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ReloadOutcome {
+    /// 文件未被修改
+    Unchanged,
+    /// 已重新加载磁盘上的版本
+    Reloaded,
+    /// 本实例有未保存的修改，记为冲突等待用户处理
+    Conflict,
+}
+
+/// 冲突的处理方式
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictResolution {
+    /// 保留内存中的版本，覆盖磁盘上的修改
+    KeepMine,
+    /// 采用磁盘上的版本，放弃本实例未保存的修改
+    KeepTheirs,
+}
+
+/// 冲突一方的数据概况
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoreVersion {
+    /// 资产数
+    pub asset_count: usize,
+    /// 交易数
+    pub transaction_count: usize,
+    /// 最近一次资产更新或交易的时间
+    pub last_change: Option<DateTime<Utc>>,
+}
+
+impl StoreVersion {
+    fn new(assets: &[Asset], transactions: &[AssetTransaction]) -> Self {
+        let last_change = assets
+            .iter()
+            .map(|a| a.updated_at)
+            .chain(transactions.iter().map(|t| t.timestamp))
+            .max();
+        Self {
+            asset_count: assets.len(),
+            transaction_count: transactions.len(),
+            last_change,
+        }
+    }
+}
+
+/// 两个版本中不同的资产（某一方没有时为空）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AssetDifference {
+    pub asset_id: Uuid,
+    pub mine: Option<Asset>,
+    pub theirs: Option<Asset>,
+}
+
+/// 数据文件被外部修改时的冲突
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoreConflict {
+    /// 发现冲突的时间
+    pub detected_at: DateTime<Utc>,
+    /// 内存中的版本
+    pub mine: StoreVersion,
+    /// 磁盘上的版本
+    pub theirs: StoreVersion,
+    /// 内容不同的资产
+    pub differences: Vec<AssetDifference>,
+}
+
+impl StoreConflict {
+    /// 比较两个版本
+    pub fn compare(
+        detected_at: DateTime<Utc>,
+        mine: (&[Asset], &[AssetTransaction]),
+        theirs: (&[Asset], &[AssetTransaction]),
+    ) -> Self {
+        let same =
+            |a: &Asset, b: &Asset| serde_json::to_value(a).ok() == serde_json::to_value(b).ok();
+        let mut differences: Vec<AssetDifference> = mine
+            .0
+            .iter()
+            .filter_map(|asset| {
+                let other = theirs.0.iter().find(|a| a.id == asset.id);
+                if other.is_some_and(|other| same(asset, other)) {
+                    return None;
+                }
+                Some(AssetDifference {
+                    asset_id: asset.id,
+                    mine: Some(asset.clone()),
+                    theirs: other.cloned(),
+                })
+            })
+            .collect();
+        differences.extend(
+            theirs
+                .0
+                .iter()
+                .filter(|asset| !mine.0.iter().any(|a| a.id == asset.id))
+                .map(|asset| AssetDifference {
+                    asset_id: asset.id,
+                    mine: None,
+                    theirs: Some(asset.clone()),
+                }),
+        );
+        Self {
+            detected_at,
+            mine: StoreVersion::new(mine.0, mine.1),
+            theirs: StoreVersion::new(theirs.0, theirs.1),
+            differences,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asset::AssetType;
+
+    #[test]
+    fn test_lock_and_file_state() {
+        let dir = std::env::temp_dir().join(format!("asset-manager-sync-{}", Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("assets.json");
+
+        let LockAttempt::Acquired(mut lock) = StoreLock::acquire(&path).unwrap() else {
+            panic!("lock should be free");
+        };
+        assert!(matches!(
+            StoreLock::acquire(&path).unwrap(),
+            LockAttempt::Held(_)
+        ));
+        assert_eq!(lock.refresh().unwrap(), None);
+        drop(lock);
+        assert!(!StoreLock::path_for(&path).exists());
+
+        fs::write(&path, b"one").unwrap();
+        let state = FileState::read(&path).unwrap();
+        assert!(!FileState::changed(state.as_ref(), &path).unwrap());
+        fs::write(&path, b"two!").unwrap();
+        assert!(FileState::changed(state.as_ref(), &path).unwrap());
+        assert!(FileState::changed(None, &path).unwrap());
+
+        let asset = Asset::new("活期", AssetType::BankDeposit, 1.0);
+        let mut changed = asset.clone();
+        changed.value = 2.0;
+        let mine = [asset];
+        let conflict = StoreConflict::compare(Utc::now(), (&mine, &[]), (&[changed], &[]));
+        assert_eq!(conflict.differences.len(), 1);
+        assert_eq!(conflict.differences[0].theirs.as_ref().unwrap().value, 2.0);
+        let same = StoreConflict::compare(Utc::now(), (&mine, &[]), (&mine, &[]));
+        assert!(same.differences.is_empty());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
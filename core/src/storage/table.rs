@@ -0,0 +1,409 @@
+//! 带索引的内存表：按 ID 的哈希索引与按时间的有序索引
+//!
+//! 行按插入顺序存放（替换不改变位置），序列化为普通数组，与原有的文件格式一致。
+//! 表会记录自上次取出以来变更过的行，供追加日志使用；并保留这些行修改前的内容，供修改记录使用。
+
+use super::journal::Change;
+use crate::asset::{Asset, AssetTransaction};
+use crate::valuation::Valuation;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::ops::{Bound, Deref, RangeBounds};
+use uuid::Uuid;
+
+/// 可存入表的记录
+pub trait Record: Clone {
+    /// 记录ID
+    fn id(&self) -> Uuid;
+    /// 时间索引使用的时间
+    fn time(&self) -> DateTime<Utc>;
+}
+
+impl Record for Asset {
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    /// 资产按创建时间索引
+    fn time(&self) -> DateTime<Utc> {
+        self.created_at
+    }
+}
+
+impl Record for AssetTransaction {
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    /// 交易按交易时间索引
+    fn time(&self) -> DateTime<Utc> {
+        self.timestamp
+    }
+}
+
+impl Record for Valuation {
+    fn id(&self) -> Uuid {
+        self.id
+    }
+
+    /// 估值按记录时间索引
+    fn time(&self) -> DateTime<Utc> {
+        self.recorded_at
+    }
+}
+
+/// 时间索引的键，时间相同时按插入顺序（序号倒序存放，倒序遍历时先插入的在前）
+type TimeKey = (DateTime<Utc>, Reverse<u64>);
+
+/// 带索引的记录表
+#[derive(Debug, Clone)]
+pub struct Table<T> {
+    rows: Vec<T>,
+    /// 与 `rows` 一一对应的时间索引键
+    keys: Vec<TimeKey>,
+    by_id: HashMap<Uuid, usize>,
+    by_time: BTreeMap<TimeKey, Uuid>,
+    next_seq: u64,
+    /// 自上次取出变更以来写入过的行
+    changed: HashSet<Uuid>,
+    /// 自上次取出变更以来删除过的行
+    removed: HashSet<Uuid>,
+    /// 自上次取出以来修改过的行修改前的内容（新插入的行为 None）
+    originals: HashMap<Uuid, Option<T>>,
+}
+
+impl<T: Record> Table<T> {
+    /// 创建空表
+    pub fn new() -> Self {
+        Self {
+            rows: Vec::new(),
+            keys: Vec::new(),
+            by_id: HashMap::new(),
+            by_time: BTreeMap::new(),
+            next_seq: 0,
+            changed: HashSet::new(),
+            removed: HashSet::new(),
+            originals: HashMap::new(),
+        }
+    }
+
+    /// 按ID获取行
+    pub fn get(&self, id: Uuid) -> Option<&T> {
+        self.by_id.get(&id).map(|&pos| &self.rows[pos])
+    }
+
+    /// 是否包含该ID
+    pub fn contains(&self, id: Uuid) -> bool {
+        self.by_id.contains_key(&id)
+    }
+
+    /// 插入新行，或替换同ID的行（保持原位置），返回被替换的行
+    pub fn insert(&mut self, row: T) -> Option<T> {
+        let id = row.id();
+        self.remember(id);
+        self.changed.insert(id);
+        match self.by_id.get(&id) {
+            Some(&pos) => {
+                let previous = std::mem::replace(&mut self.rows[pos], row);
+                self.reindex_time(pos);
+                Some(previous)
+            }
+            None => {
+                let key = (row.time(), Reverse(self.next_seq));
+                self.next_seq += 1;
+                self.by_id.insert(id, self.rows.len());
+                self.by_time.insert(key, id);
+                self.keys.push(key);
+                self.rows.push(row);
+                None
+            }
+        }
+    }
+
+    /// 修改一行并返回闭包的结果，ID 不存在时返回 None（不得修改行的ID）
+    pub fn update<R>(&mut self, id: Uuid, f: impl FnOnce(&mut T) -> R) -> Option<R> {
+        let pos = *self.by_id.get(&id)?;
+        self.remember(id);
+        let result = f(&mut self.rows[pos]);
+        debug_assert_eq!(self.rows[pos].id(), id, "row id must not change");
+        self.reindex_time(pos);
+        self.changed.insert(id);
+        Some(result)
+    }
+
+    /// 删除行
+    pub fn remove(&mut self, id: Uuid) -> Option<T> {
+        let pos = *self.by_id.get(&id)?;
+        self.remember(id);
+        self.by_id.remove(&id);
+        self.by_time.remove(&self.keys.remove(pos));
+        for p in self.by_id.values_mut().filter(|p| **p > pos) {
+            *p -= 1;
+        }
+        self.mark_removed(id);
+        Some(self.rows.remove(pos))
+    }
+
+    /// 删除满足条件的行，按原顺序返回被删除的行
+    pub fn remove_where(&mut self, mut f: impl FnMut(&T) -> bool) -> Vec<T> {
+        let mut removed = Vec::new();
+        let rows = std::mem::take(&mut self.rows);
+        let keys = std::mem::take(&mut self.keys);
+        for (row, key) in rows.into_iter().zip(keys) {
+            if f(&row) {
+                self.originals
+                    .entry(row.id())
+                    .or_insert_with(|| Some(row.clone()));
+                self.by_time.remove(&key);
+                removed.push(row);
+            } else {
+                self.rows.push(row);
+                self.keys.push(key);
+            }
+        }
+        if !removed.is_empty() {
+            self.by_id = self
+                .rows
+                .iter()
+                .enumerate()
+                .map(|(pos, row)| (row.id(), pos))
+                .collect();
+            for row in &removed {
+                self.mark_removed(row.id());
+            }
+        }
+        removed
+    }
+
+    /// 按时间倒序遍历时间范围内的行（时间相同时按插入顺序）
+    pub fn newest_first(
+        &self,
+        range: impl RangeBounds<DateTime<Utc>>,
+    ) -> impl Iterator<Item = &T> + '_ {
+        // 同一时间下序号越大键越小
+        let start = match range.start_bound() {
+            Bound::Included(t) => Bound::Included((*t, Reverse(u64::MAX))),
+            Bound::Excluded(t) => Bound::Excluded((*t, Reverse(0))),
+            Bound::Unbounded => Bound::Unbounded,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(t) => Bound::Included((*t, Reverse(0))),
+            Bound::Excluded(t) => Bound::Excluded((*t, Reverse(u64::MAX))),
+            Bound::Unbounded => Bound::Unbounded,
+        };
+        self.by_time
+            .range((start, end))
+            .rev()
+            .map(|(_, id)| &self.rows[self.by_id[id]])
+    }
+
+    /// 取出自上次调用以来的变更：先删除，再按行的位置写入（未取出的修改前内容一并丢弃）
+    pub fn take_changes(&mut self) -> Vec<Change<T>> {
+        self.originals.clear();
+        let mut changes: Vec<Change<T>> = self
+            .removed
+            .drain()
+            .map(|id| Change::Delete { id })
+            .collect();
+        let mut positions: Vec<usize> = self
+            .changed
+            .drain()
+            .filter_map(|id| self.by_id.get(&id).copied())
+            .collect();
+        positions.sort_unstable();
+        changes.extend(positions.into_iter().map(|pos| Change::Put {
+            row: Box::new(self.rows[pos].clone()),
+        }));
+        changes
+    }
+
+    /// 取出自上次调用以来修改过的行修改前与当前的内容（新插入的行修改前为 None，删除的行当前为 None）
+    pub fn take_originals(&mut self) -> Vec<(Option<T>, Option<T>)> {
+        let originals = std::mem::take(&mut self.originals);
+        originals
+            .into_iter()
+            .map(|(id, before)| (before, self.get(id).cloned()))
+            .collect()
+    }
+
+    /// 丢弃记录的变更
+    pub fn clear_changes(&mut self) {
+        self.changed.clear();
+        self.removed.clear();
+        self.originals.clear();
+    }
+
+    /// 按现有的行重建索引（同ID的重复行合并为一行，取最后一行的内容），保留变更记录，返回去掉的重复行数
+    pub fn rebuild_indexes(&mut self) -> usize {
+        let rows = std::mem::take(&mut self.rows);
+        let count = rows.len();
+        let changed = std::mem::take(&mut self.changed);
+        let removed = std::mem::take(&mut self.removed);
+        let originals = std::mem::take(&mut self.originals);
+        *self = rows.into_iter().collect();
+        self.changed = changed;
+        self.removed = removed;
+        self.originals = originals;
+        count - self.rows.len()
+    }
+
+    /// 应用一条变更
+    pub fn apply(&mut self, change: Change<T>) {
+        match change {
+            Change::Put { row } => {
+                self.insert(*row);
+            }
+            Change::Delete { id } => {
+                self.remove(id);
+            }
+        }
+    }
+
+    /// 行第一次修改前记下其内容
+    fn remember(&mut self, id: Uuid) {
+        if !self.originals.contains_key(&id) {
+            let original = self.get(id).cloned();
+            self.originals.insert(id, original);
+        }
+    }
+
+    fn mark_removed(&mut self, id: Uuid) {
+        self.changed.remove(&id);
+        self.removed.insert(id);
+    }
+
+    /// 行的时间变化后更新时间索引
+    fn reindex_time(&mut self, pos: usize) {
+        let key = (self.rows[pos].time(), self.keys[pos].1);
+        if key != self.keys[pos] {
+            let id = self
+                .by_time
+                .remove(&self.keys[pos])
+                .expect("time index out of sync");
+            self.by_time.insert(key, id);
+            self.keys[pos] = key;
+        }
+    }
+}
+
+impl<T: Record> Default for Table<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Deref for Table<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        &self.rows
+    }
+}
+
+impl<'a, T> IntoIterator for &'a Table<T> {
+    type Item = &'a T;
+    type IntoIter = std::slice::Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.rows.iter()
+    }
+}
+
+impl<T: Record> FromIterator<T> for Table<T> {
+    /// 由已有数据构建，不记为变更
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut table = Self::new();
+        for row in iter {
+            table.insert(row);
+        }
+        table.clear_changes();
+        table
+    }
+}
+
+impl<T: Serialize> Serialize for Table<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.rows.serialize(serializer)
+    }
+}
+
+impl<'de, T: Record + Deserialize<'de>> Deserialize<'de> for Table<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(Vec::<T>::deserialize(deserializer)?.into_iter().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asset::TransactionType;
+    use chrono::{Duration, TimeZone};
+
+    #[test]
+    fn test_table_indexes() {
+        let t0 = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let asset_id = Uuid::new_v4();
+        let txn = |days: i64| {
+            AssetTransaction::new(asset_id, TransactionType::Income, 0.0, 1.0)
+                .with_timestamp(t0 + Duration::days(days))
+        };
+        let (a, b, c, d) = (txn(2), txn(0), txn(2), txn(1));
+        let mut table: Table<AssetTransaction> = [a.clone(), b.clone(), c.clone(), d.clone()]
+            .into_iter()
+            .collect();
+        assert!(table.take_changes().is_empty());
+
+        // 倒序遍历，同一时间按插入顺序
+        let ids: Vec<Uuid> = table.newest_first(..).map(|t| t.id).collect();
+        assert_eq!(ids, vec![a.id, c.id, d.id, b.id]);
+        let ids: Vec<Uuid> = table
+            .newest_first(t0 + Duration::days(1)..t0 + Duration::days(2))
+            .map(|t| t.id)
+            .collect();
+        assert_eq!(ids, vec![d.id]);
+
+        // 修改时间后索引随之更新，位置不变
+        table.update(b.id, |t| t.timestamp = t0 + Duration::days(3));
+        assert_eq!(table.newest_first(..).next().unwrap().id, b.id);
+        assert_eq!(table[1].id, b.id);
+
+        // 重建索引合并重复行，保留变更记录（下面仍能取出 b 的修改）
+        table.rows.push(c.clone());
+        table.keys.push(table.keys[2]);
+        assert_eq!(table.rebuild_indexes(), 1);
+        assert_eq!(table.len(), 4);
+        assert_eq!(table.newest_first(..).next().unwrap().id, b.id);
+
+        assert_eq!(table.remove(a.id).unwrap().id, a.id);
+        assert_eq!(table.get(c.id).unwrap().id, c.id);
+        let removed = table.remove_where(|t| t.id == d.id);
+        assert_eq!(removed.len(), 1);
+        assert_eq!(table.len(), 2);
+        assert!(!table.contains(d.id));
+
+        // 修改前的内容：b 被修改，a 与 d 被删除
+        let originals = table.take_originals();
+        assert_eq!(originals.len(), 3);
+        assert_eq!(
+            originals
+                .iter()
+                .filter(|(_, after)| after.is_none())
+                .count(),
+            2
+        );
+        assert!(originals
+            .iter()
+            .any(|(before, after)| before.as_ref().unwrap().timestamp == t0
+                && after.as_ref().is_some_and(|t| t.id == b.id)));
+
+        let changes = table.take_changes();
+        let deletes = changes
+            .iter()
+            .filter(|c| matches!(c, Change::Delete { .. }))
+            .count();
+        assert_eq!((changes.len(), deletes), (3, 2));
+        assert!(matches!(&changes[2], Change::Put { row } if row.id == b.id));
+    }
+}
@@ -0,0 +1,177 @@
+//! 异步数据库：在专用线程中持有数据库，异步代码通过通道提交操作
+//!
+//! 操作按提交顺序在数据库线程上依次执行，调用方等待结果时不占用异步运行时的线程。数据库
+//! 也可以与同步代码共用（`shared`），每个操作执行期间持有互斥锁。所有句柄释放后线程退出，
+//! 关闭数据库时写入未保存的修改。
+
+use super::{BulkResult, Database, Snapshot, SortBy, SortOrder, StorageError, TransactionQuery};
+use crate::asset::{Asset, AssetTransaction};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use tokio::sync::oneshot;
+use uuid::Uuid;
+
+/// 在数据库线程上执行的操作
+type Job = Box<dyn FnOnce(&Mutex<Database>) + Send>;
+
+/// 异步数据库句柄（可克隆，各句柄共享同一个数据库线程）
+#[derive(Clone)]
+pub struct AsyncDatabase {
+    sender: mpsc::Sender<Job>,
+}
+
+impl AsyncDatabase {
+    /// 启动数据库线程并移交数据库
+    pub fn spawn(db: Database) -> Result<Self, StorageError> {
+        Self::shared(Arc::new(Mutex::new(db)))
+    }
+
+    /// 启动数据库线程，与持有同一互斥锁的同步代码共用数据库
+    pub fn shared(db: Arc<Mutex<Database>>) -> Result<Self, StorageError> {
+        let (sender, receiver) = mpsc::channel::<Job>();
+        thread::Builder::new()
+            .name("database".to_string())
+            .spawn(move || {
+                for job in receiver {
+                    job(&db);
+                }
+            })?;
+        Ok(Self { sender })
+    }
+
+    /// 在数据库线程上执行操作并等待结果
+    pub async fn call<T, F>(&self, f: F) -> Result<T, StorageError>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Database) -> Result<T, StorageError> + Send + 'static,
+    {
+        let (tx, rx) = oneshot::channel();
+        self.sender
+            .send(Box::new(move |db: &Mutex<Database>| {
+                let result = match db.lock() {
+                    // 操作中 panic 只让这次调用失败，数据库线程继续执行之后的操作
+                    Ok(mut db) => panic::catch_unwind(AssertUnwindSafe(|| f(&mut db)))
+                        .unwrap_or(Err(StorageError::Panicked)),
+                    Err(_) => Err(StorageError::Panicked),
+                };
+                let _ = tx.send(result);
+            }))
+            .map_err(|_| StorageError::Stopped)?;
+        rx.await.map_err(|_| StorageError::Stopped)?
+    }
+
+    /// 只读快照，可在其他线程中查询
+    pub async fn snapshot(&self) -> Result<Snapshot, StorageError> {
+        self.call(|db| Ok(db.snapshot())).await
+    }
+
+    // ============ 资产 ============
+
+    /// 创建资产
+    pub async fn create_asset(&self, asset: Asset) -> Result<(), StorageError> {
+        self.call(move |db| db.create_asset(&asset)).await
+    }
+
+    /// 批量创建资产，出错的项跳过
+    pub async fn create_assets_bulk(&self, assets: Vec<Asset>) -> Result<BulkResult, StorageError> {
+        self.call(move |db| db.create_assets_bulk(&assets)).await
+    }
+
+    /// 获取资产
+    pub async fn get_asset(&self, id: Uuid) -> Result<Option<Asset>, StorageError> {
+        self.call(move |db| db.get_asset(id)).await
+    }
+
+    /// 获取所有资产，按指定字段排序
+    pub async fn list_assets(
+        &self,
+        sort_by: SortBy,
+        order: SortOrder,
+    ) -> Result<Vec<Asset>, StorageError> {
+        self.call(move |db| db.list_assets(sort_by, order)).await
+    }
+
+    /// 更新资产
+    pub async fn update_asset(&self, asset: Asset) -> Result<(), StorageError> {
+        self.call(move |db| db.update_asset(&asset)).await
+    }
+
+    /// 删除资产（移入回收站）
+    pub async fn delete_asset(&self, id: Uuid) -> Result<(), StorageError> {
+        self.call(move |db| db.delete_asset(id)).await
+    }
+
+    // ============ 交易记录 ============
+
+    /// 记录交易
+    pub async fn add_transaction(&self, transaction: AssetTransaction) -> Result<(), StorageError> {
+        self.call(move |db| db.add_transaction(&transaction)).await
+    }
+
+    /// 批量记录交易，出错的项跳过
+    pub async fn add_transactions_bulk(
+        &self,
+        transactions: Vec<AssetTransaction>,
+    ) -> Result<BulkResult, StorageError> {
+        self.call(move |db| db.add_transactions_bulk(&transactions))
+            .await
+    }
+
+    /// 获取资产的交易历史
+    pub async fn get_transactions(
+        &self,
+        asset_id: Uuid,
+        query: TransactionQuery,
+    ) -> Result<Vec<AssetTransaction>, StorageError> {
+        self.call(move |db| db.get_transactions(asset_id, &query))
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asset::{AssetType, TransactionType};
+
+    #[tokio::test]
+    async fn test_async_database() {
+        let db = AsyncDatabase::spawn(Database::open_in_memory().unwrap()).unwrap();
+        let card = Asset::new("储蓄卡", AssetType::BankDeposit, 1000.0);
+        db.create_asset(card.clone()).await.unwrap();
+
+        // 多个句柄并发提交，按提交顺序执行
+        let tasks: Vec<_> = (0..10)
+            .map(|i| {
+                let db = db.clone();
+                let txn = AssetTransaction::new(card.id, TransactionType::Income, 0.0, i as f64);
+                tokio::spawn(async move { db.add_transaction(txn).await })
+            })
+            .collect();
+        for task in tasks {
+            task.await.unwrap().unwrap();
+        }
+        let txns = db
+            .get_transactions(card.id, TransactionQuery::default())
+            .await
+            .unwrap();
+        assert_eq!(txns.len(), 10);
+
+        let snapshot = db.snapshot().await.unwrap();
+        assert_eq!(snapshot.get_asset(card.id).unwrap().unwrap().name, "储蓄卡");
+        assert!(db.delete_asset(Uuid::new_v4()).await.is_err());
+
+        // 操作中 panic 时该调用返回错误，之后的调用不受影响
+        let result = db
+            .call(|_| -> Result<(), StorageError> { panic!("boom") })
+            .await;
+        assert!(matches!(result, Err(StorageError::Panicked)));
+        assert_eq!(db.get_asset(card.id).await.unwrap().unwrap().name, "储蓄卡");
+
+        // 与同步代码共用数据库
+        let shared = Arc::new(Mutex::new(Database::open_in_memory().unwrap()));
+        let db = AsyncDatabase::shared(shared.clone()).unwrap();
+        shared.lock().unwrap().create_asset(&card).unwrap();
+        assert!(db.get_asset(card.id).await.unwrap().is_some());
+    }
+}
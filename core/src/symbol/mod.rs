@@ -0,0 +1,168 @@
+//! 证券代码查询：为股票资产的代码与交易所提供自动补全
+//!
+//! 先在内置的常用证券表中按代码、名称与拼音查找，可选地再合并在线数据源的结果。
+
+mod provider;
+
+pub use provider::*;
+
+use crate::search::SearchSettings;
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+use tracing::warn;
+
+/// 默认返回的条数
+pub const DEFAULT_LOOKUP_LIMIT: usize = 10;
+
+/// 内置证券表（`代码,交易所,名称`，`#` 开头为注释）
+const BUNDLED_TICKERS: &str = include_str!("tickers.csv");
+
+/// 证券代码
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SymbolInfo {
+    /// 代码（如 `AAPL`、`600519`）
+    pub symbol: String,
+    /// 交易所
+    pub exchange: String,
+    /// 名称
+    pub name: String,
+}
+
+/// 内置证券表
+pub fn bundled() -> &'static [SymbolInfo] {
+    static TICKERS: OnceLock<Vec<SymbolInfo>> = OnceLock::new();
+    TICKERS.get_or_init(|| {
+        BUNDLED_TICKERS
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .filter_map(|line| {
+                let mut fields = line.splitn(3, ',');
+                Some(SymbolInfo {
+                    symbol: fields.next()?.to_string(),
+                    exchange: fields.next()?.to_string(),
+                    name: fields.next()?.to_string(),
+                })
+            })
+            .collect()
+    })
+}
+
+/// 匹配程度，越小越靠前：代码相同、代码前缀、名称包含、名称拼音
+fn rank(query: &str, info: &SymbolInfo) -> Option<u8> {
+    let symbol = info.symbol.to_uppercase();
+    let upper = query.to_uppercase();
+    if symbol == upper {
+        Some(0)
+    } else if symbol.starts_with(&upper) {
+        Some(1)
+    } else if info.name.to_lowercase().contains(&query.to_lowercase()) {
+        Some(2)
+    } else {
+        let pinyin = SearchSettings {
+            fuzzy: false,
+            pinyin: true,
+        };
+        pinyin.matches(query, &info.name).then_some(3)
+    }
+}
+
+/// 在内置证券表中查找
+pub fn lookup_bundled(query: &str, limit: usize) -> Vec<SymbolInfo> {
+    let query = query.trim();
+    if query.is_empty() {
+        return Vec::new();
+    }
+    let mut found: Vec<(u8, &SymbolInfo)> = bundled()
+        .iter()
+        .filter_map(|info| rank(query, info).map(|r| (r, info)))
+        .collect();
+    found.sort_by(|a, b| a.0.cmp(&b.0).then_with(|| a.1.symbol.cmp(&b.1.symbol)));
+    found
+        .into_iter()
+        .take(limit)
+        .map(|(_, info)| info.clone())
+        .collect()
+}
+
+/// 查找证券代码：内置结果在前，再合并在线数据源的结果（去重）；在线查询失败时只返回内置结果
+pub fn lookup_symbol(
+    query: &str,
+    provider: Option<&dyn SymbolProvider>,
+    limit: usize,
+) -> Vec<SymbolInfo> {
+    let query = query.trim();
+    let mut found = lookup_bundled(query, limit);
+    let Some(provider) = provider else {
+        return found;
+    };
+    if query.is_empty() || found.len() >= limit {
+        return found;
+    }
+    match provider.search(query) {
+        Ok(live) => {
+            for info in live {
+                let duplicate = found.iter().any(|f| {
+                    f.symbol.eq_ignore_ascii_case(&info.symbol)
+                        && f.exchange.eq_ignore_ascii_case(&info.exchange)
+                });
+                if !duplicate && found.len() < limit {
+                    found.push(info);
+                }
+            }
+        }
+        Err(e) => warn!("Symbol lookup via {} failed: {}", provider.name(), e),
+    }
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct FixedProvider(Result<Vec<SymbolInfo>, String>);
+
+    impl SymbolProvider for FixedProvider {
+        fn name(&self) -> &str {
+            "fixed"
+        }
+
+        fn search(&self, _query: &str) -> Result<Vec<SymbolInfo>, SymbolError> {
+            self.0.clone().map_err(SymbolError::Request)
+        }
+    }
+
+    fn info(symbol: &str, exchange: &str, name: &str) -> SymbolInfo {
+        SymbolInfo {
+            symbol: symbol.into(),
+            exchange: exchange.into(),
+            name: name.into(),
+        }
+    }
+
+    #[test]
+    fn test_lookup_symbol() {
+        assert!(bundled().len() > 50);
+        let found = lookup_bundled("aapl", DEFAULT_LOOKUP_LIMIT);
+        assert_eq!(found[0], info("AAPL", "NASDAQ", "Apple Inc."));
+        // 代码相同的排在代码前缀之前
+        let found = lookup_bundled("goog", DEFAULT_LOOKUP_LIMIT);
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0].symbol, "GOOG");
+        assert_eq!(lookup_bundled("60051", 5)[0].name, "贵州茅台");
+        assert_eq!(lookup_bundled("gzmt", 5)[0].symbol, "600519");
+        assert_eq!(lookup_bundled("腾讯", 5)[0].symbol, "0700");
+        assert!(lookup_bundled("  ", 5).is_empty());
+
+        let live = FixedProvider(Ok(vec![
+            info("AAPL", "nasdaq", "Apple Inc."),
+            info("AAPL.MX", "Mexico", "Apple Inc."),
+        ]));
+        let found = lookup_symbol("AAPL", Some(&live), DEFAULT_LOOKUP_LIMIT);
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[1].symbol, "AAPL.MX");
+
+        let offline = FixedProvider(Err("offline".into()));
+        assert_eq!(lookup_symbol("AAPL", Some(&offline), 5).len(), 1);
+    }
+}
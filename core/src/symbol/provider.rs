@@ -0,0 +1,265 @@
+//! 在线证券代码与分红数据源
+
+use super::SymbolInfo;
+use crate::asset::DividendEvent;
+use chrono::{DateTime, Datelike, NaiveDate, Utc};
+
+/// 证券代码数据源错误
+#[derive(Debug, thiserror::Error)]
+pub enum SymbolError {
+    #[error("Request failed: {0}")]
+    Request(String),
+
+    #[error("Invalid response: {0}")]
+    InvalidResponse(String),
+}
+
+/// 在线证券代码数据源
+pub trait SymbolProvider: Send + Sync {
+    /// 数据源名称
+    fn name(&self) -> &str;
+
+    /// 按代码或名称搜索证券
+    fn search(&self, query: &str) -> Result<Vec<SymbolInfo>, SymbolError>;
+}
+
+/// 分红数据源
+pub trait DividendProvider: Send + Sync {
+    /// 数据源名称
+    fn name(&self) -> &str;
+
+    /// 获取 `since`（含）以来的分红
+    fn dividends(&self, symbol: &str, since: NaiveDate) -> Result<Vec<DividendEvent>, SymbolError>;
+}
+
+/// Yahoo Finance 搜索与行情接口（无需密钥）
+pub struct YahooSymbolProvider {
+    base_url: String,
+}
+
+impl YahooSymbolProvider {
+    pub const DEFAULT_URL: &'static str = "https://query2.finance.yahoo.com";
+
+    /// 使用默认地址
+    pub fn new() -> Self {
+        Self::with_base_url(Self::DEFAULT_URL)
+    }
+
+    /// 使用自定义地址（如代理）
+    pub fn with_base_url(base_url: impl Into<String>) -> Self {
+        Self {
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+        }
+    }
+}
+
+impl Default for YahooSymbolProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SymbolProvider for YahooSymbolProvider {
+    fn name(&self) -> &str {
+        "yahoo"
+    }
+
+    fn search(&self, query: &str) -> Result<Vec<SymbolInfo>, SymbolError> {
+        let url = format!("{}/v1/finance/search", self.base_url);
+        let body = ureq::get(&url)
+            .query("q", query)
+            .query("newsCount", "0")
+            .call()
+            .map_err(|e| SymbolError::Request(e.to_string()))?
+            .into_string()
+            .map_err(|e| SymbolError::Request(e.to_string()))?;
+        parse_yahoo(&body)
+    }
+}
+
+impl DividendProvider for YahooSymbolProvider {
+    fn name(&self) -> &str {
+        "yahoo"
+    }
+
+    fn dividends(&self, symbol: &str, since: NaiveDate) -> Result<Vec<DividendEvent>, SymbolError> {
+        let url = format!("{}/v8/finance/chart/{}", self.base_url, symbol);
+        let period1 = since.and_time(chrono::NaiveTime::MIN).and_utc().timestamp();
+        let body = ureq::get(&url)
+            .query("period1", &period1.to_string())
+            .query("period2", &Utc::now().timestamp().to_string())
+            .query("interval", "1d")
+            .query("events", "div")
+            .call()
+            .map_err(|e| SymbolError::Request(e.to_string()))?
+            .into_string()
+            .map_err(|e| SymbolError::Request(e.to_string()))?;
+        parse_yahoo_dividends(&body)
+    }
+}
+
+/// 模拟证券数据源：不联网，搜索结果与分红都由查询内容确定地生成，用于开发、测试与离线演示
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MockSymbolProvider;
+
+impl MockSymbolProvider {
+    /// 模拟的每股季度分红
+    pub const DIVIDEND_PER_SHARE: f64 = 0.25;
+}
+
+impl SymbolProvider for MockSymbolProvider {
+    fn name(&self) -> &str {
+        "mock"
+    }
+
+    fn search(&self, query: &str) -> Result<Vec<SymbolInfo>, SymbolError> {
+        let symbol = query.trim().to_uppercase();
+        if symbol.is_empty() {
+            return Ok(Vec::new());
+        }
+        Ok(vec![SymbolInfo {
+            name: format!("{} (mock)", symbol),
+            symbol,
+            exchange: "MOCK".to_string(),
+        }])
+    }
+}
+
+impl DividendProvider for MockSymbolProvider {
+    fn name(&self) -> &str {
+        "mock"
+    }
+
+    /// 每季度末月 15 日分红一次，截至今天
+    fn dividends(
+        &self,
+        _symbol: &str,
+        since: NaiveDate,
+    ) -> Result<Vec<DividendEvent>, SymbolError> {
+        let today = Utc::now().date_naive();
+        let mut events = Vec::new();
+        for year in since.year()..=today.year() {
+            for month in [3, 6, 9, 12] {
+                let Some(date) = NaiveDate::from_ymd_opt(year, month, 15) else {
+                    continue;
+                };
+                if date >= since && date <= today {
+                    events.push(DividendEvent {
+                        date,
+                        per_share: Self::DIVIDEND_PER_SHARE,
+                    });
+                }
+            }
+        }
+        Ok(events)
+    }
+}
+
+/// 解析 `{"quotes": [{"symbol": "AAPL", "exchDisp": "NASDAQ", "longname": "Apple Inc."}]}` 格式的响应
+fn parse_yahoo(body: &str) -> Result<Vec<SymbolInfo>, SymbolError> {
+    let json: serde_json::Value =
+        serde_json::from_str(body).map_err(|e| SymbolError::InvalidResponse(e.to_string()))?;
+    let quotes = json
+        .get("quotes")
+        .and_then(|q| q.as_array())
+        .ok_or_else(|| SymbolError::InvalidResponse("missing quotes".to_string()))?;
+
+    let field = |quote: &serde_json::Value, keys: &[&str]| {
+        keys.iter()
+            .find_map(|key| quote.get(*key).and_then(|v| v.as_str()))
+            .map(str::to_string)
+    };
+    Ok(quotes
+        .iter()
+        .filter_map(|quote| {
+            Some(SymbolInfo {
+                symbol: field(quote, &["symbol"])?,
+                exchange: field(quote, &["exchDisp", "exchange"]).unwrap_or_default(),
+                name: field(quote, &["longname", "shortname"]).unwrap_or_default(),
+            })
+        })
+        .collect())
+}
+
+/// 解析 `{"chart": {"result": [{"events": {"dividends": {"<时间戳>": {"amount": 0.24, "date": <时间戳>}}}}]}}`，
+/// 没有分红时不含 `events`
+fn parse_yahoo_dividends(body: &str) -> Result<Vec<DividendEvent>, SymbolError> {
+    let json: serde_json::Value =
+        serde_json::from_str(body).map_err(|e| SymbolError::InvalidResponse(e.to_string()))?;
+    let result = json
+        .pointer("/chart/result/0")
+        .ok_or_else(|| SymbolError::InvalidResponse("missing chart result".to_string()))?;
+    let Some(dividends) = result
+        .pointer("/events/dividends")
+        .and_then(|d| d.as_object())
+    else {
+        return Ok(Vec::new());
+    };
+
+    let mut events = Vec::with_capacity(dividends.len());
+    for dividend in dividends.values() {
+        let date = dividend
+            .get("date")
+            .and_then(|d| d.as_i64())
+            .and_then(|ts| DateTime::from_timestamp(ts, 0))
+            .ok_or_else(|| SymbolError::InvalidResponse("invalid dividend date".to_string()))?;
+        if let Some(per_share) = dividend.get("amount").and_then(|a| a.as_f64()) {
+            events.push(DividendEvent {
+                date: date.date_naive(),
+                per_share,
+            });
+        }
+    }
+    events.sort_by_key(|e| e.date);
+    Ok(events)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_yahoo() {
+        let body = r#"{"quotes":[{"exchange":"NMS","shortname":"Apple Inc.","symbol":"AAPL","exchDisp":"NASDAQ"},{"exchange":"HKG","longname":"Tencent Holdings Limited","symbol":"0700.HK"},{"index":"news"}]}"#;
+        let found = parse_yahoo(body).unwrap();
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0].exchange, "NASDAQ");
+        assert_eq!(found[0].name, "Apple Inc.");
+        assert_eq!(found[1].exchange, "HKG");
+        assert!(parse_yahoo("{}").is_err());
+    }
+
+    #[test]
+    fn test_parse_yahoo_dividends() {
+        let body = r#"{"chart":{"result":[{"events":{"dividends":{"1707489000":{"amount":0.24,"date":1707489000},"1699540200":{"amount":0.24,"date":1699540200}}}}],"error":null}}"#;
+        let events = parse_yahoo_dividends(body).unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(
+            events[0].date,
+            NaiveDate::from_ymd_opt(2023, 11, 9).unwrap()
+        );
+        assert_eq!(events[1].per_share, 0.24);
+        assert!(parse_yahoo_dividends(r#"{"chart":{"result":[{}]}}"#)
+            .unwrap()
+            .is_empty());
+        assert!(parse_yahoo_dividends(r#"{"chart":{"result":null}}"#).is_err());
+    }
+
+    #[test]
+    fn test_mock_provider() {
+        let found = MockSymbolProvider.search(" aapl ").unwrap();
+        assert_eq!(
+            (found[0].symbol.as_str(), found[0].exchange.as_str()),
+            ("AAPL", "MOCK")
+        );
+        assert!(MockSymbolProvider.search("").unwrap().is_empty());
+
+        let since = NaiveDate::from_ymd_opt(2023, 4, 1).unwrap();
+        let events = MockSymbolProvider.dividends("AAPL", since).unwrap();
+        assert_eq!(
+            events[0].date,
+            NaiveDate::from_ymd_opt(2023, 6, 15).unwrap()
+        );
+        assert!(events.len() >= 3);
+    }
+}
@@ -0,0 +1,179 @@
+//! 标签规范化：写入与导入时统一标签写法，避免同一个标签出现多种写法
+//!
+//! 去掉首尾空白并合并连续空白，按别名表替换（如 "A股" → "CN Stocks"）。启用大小写折叠时，
+//! 只有大小写不同的标签视为同一个，统一为别名目标或已有资产使用的写法。
+
+use crate::settings::Settings;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// 设置项键名
+pub const TAG_SETTING_KEY: &str = "tags";
+
+/// 标签设置项
+pub const TAG_SETTINGS: Settings<TagSettings> =
+    Settings::new(TAG_SETTING_KEY, TagSettings::default);
+
+/// 标签设置
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct TagSettings {
+    /// 是否不区分大小写
+    #[serde(default = "default_case_fold")]
+    pub case_fold: bool,
+    /// 别名 → 标准标签
+    #[serde(default)]
+    pub aliases: BTreeMap<String, String>,
+}
+
+fn default_case_fold() -> bool {
+    true
+}
+
+impl Default for TagSettings {
+    fn default() -> Self {
+        Self {
+            case_fold: default_case_fold(),
+            aliases: BTreeMap::new(),
+        }
+    }
+}
+
+/// 去掉首尾空白并合并连续空白
+pub fn clean_tag(tag: &str) -> String {
+    tag.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// 比较用的键
+fn fold(tag: &str, case_fold: bool) -> String {
+    if case_fold {
+        tag.to_lowercase()
+    } else {
+        tag.to_string()
+    }
+}
+
+impl TagSettings {
+    fn key(&self, tag: &str) -> String {
+        fold(tag, self.case_fold)
+    }
+
+    /// 别名对应的标准标签
+    pub fn resolve_alias(&self, tag: &str) -> Option<&str> {
+        let key = self.key(&clean_tag(tag));
+        self.aliases
+            .iter()
+            .find(|(alias, _)| self.key(alias) == key)
+            .map(|(_, target)| target.as_str())
+    }
+
+    /// 添加或修改别名；指向该别名的其他别名改为直接指向新的标准标签
+    pub fn set_alias(&mut self, alias: &str, tag: &str) -> Result<(), String> {
+        let (alias, tag) = (clean_tag(alias), clean_tag(tag));
+        if alias.is_empty() || tag.is_empty() {
+            return Err("Alias and tag are required".to_string());
+        }
+        if self.key(&alias) == self.key(&tag) {
+            return Err(format!("Tag {} cannot be an alias of itself", tag));
+        }
+        let tag = self.resolve_alias(&tag).map_or(tag, str::to_string);
+        if self.key(&alias) == self.key(&tag) {
+            return Err(format!("Alias {} would form a cycle", alias));
+        }
+        let (alias_key, case_fold) = (self.key(&alias), self.case_fold);
+        self.aliases
+            .retain(|existing, _| fold(existing, case_fold) != alias_key);
+        for target in self.aliases.values_mut() {
+            if fold(target, case_fold) == alias_key {
+                target.clone_from(&tag);
+            }
+        }
+        self.aliases.insert(alias, tag);
+        Ok(())
+    }
+
+    /// 删除别名，返回是否存在
+    pub fn remove_alias(&mut self, alias: &str) -> bool {
+        let (key, case_fold) = (self.key(&clean_tag(alias)), self.case_fold);
+        let before = self.aliases.len();
+        self.aliases
+            .retain(|existing, _| fold(existing, case_fold) != key);
+        self.aliases.len() != before
+    }
+
+    /// 规范化一组标签（去重并保持顺序），`known` 为已有资产使用的标签
+    pub fn normalize<'a>(
+        &self,
+        tags: &[String],
+        known: impl IntoIterator<Item = &'a str>,
+    ) -> Vec<String> {
+        // 大小写折叠时统一为别名目标或最先出现的已有写法
+        let mut spellings: HashMap<String, String> = HashMap::new();
+        if self.case_fold {
+            let aliases = self.aliases.values().map(|tag| clean_tag(tag));
+            for tag in aliases.chain(known.into_iter().map(clean_tag)) {
+                spellings.entry(self.key(&tag)).or_insert(tag);
+            }
+        }
+
+        let mut seen = HashSet::new();
+        let mut normalized = Vec::new();
+        for tag in tags {
+            let tag = clean_tag(tag);
+            if tag.is_empty() {
+                continue;
+            }
+            let tag = self.resolve_alias(&tag).map_or(tag, clean_tag);
+            let key = self.key(&tag);
+            let tag = spellings.get(&key).cloned().unwrap_or(tag);
+            if seen.insert(key) {
+                normalized.push(tag);
+            }
+        }
+        normalized
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tags(list: &[&str]) -> Vec<String> {
+        list.iter().map(|t| t.to_string()).collect()
+    }
+
+    #[test]
+    fn test_normalize_tags() {
+        let mut settings = TagSettings::default();
+        settings.set_alias("A股", "CN Stocks").unwrap();
+        assert_eq!(settings.resolve_alias(" a股 "), Some("CN Stocks"));
+
+        let normalized = settings.normalize(
+            &tags(&["  Tech ", "tech", "a股", "", "cn  stocks", "Long  Term"]),
+            ["Long term"],
+        );
+        assert_eq!(normalized, tags(&["Tech", "CN Stocks", "Long term"]));
+
+        settings.case_fold = false;
+        assert_eq!(
+            settings.normalize(&tags(&["Tech", "tech"]), []),
+            tags(&["Tech", "tech"])
+        );
+    }
+
+    #[test]
+    fn test_tag_aliases() {
+        let mut settings = TagSettings::default();
+        assert!(settings.set_alias("股票", "股票").is_err());
+        settings.set_alias("沪深", "A股").unwrap();
+        // 目标是别名时指向其标准标签，已有别名随之更新
+        settings.set_alias("A股", "CN Stocks").unwrap();
+        assert_eq!(settings.resolve_alias("沪深"), Some("CN Stocks"));
+        settings.set_alias("cn", "沪深").unwrap();
+        assert_eq!(settings.resolve_alias("cn"), Some("CN Stocks"));
+        assert!(settings.set_alias("CN Stocks", "a股").is_err());
+
+        assert!(settings.remove_alias("沪深"));
+        assert!(!settings.remove_alias("沪深"));
+        assert_eq!(settings.aliases.len(), 2);
+    }
+}
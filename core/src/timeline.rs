@@ -0,0 +1,73 @@
+//! 时间线：交易、价值变动、日记、通知、导入与里程碑合并成的动态
+
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// 时间线条目类型
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum TimelineKind {
+    /// 交易（价值变动以外）
+    Transaction,
+    /// 价值变动（如股价涨跌）
+    ValueChange,
+    /// 财务日记
+    CheckIn,
+    /// 通知（提醒、告警等）
+    Alert,
+    /// 导入批次
+    Import,
+    /// 里程碑
+    Milestone,
+}
+
+/// 时间线条目
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimelineEvent {
+    /// 类型
+    pub kind: TimelineKind,
+    /// 时间（只有日期的条目为当日零点）
+    pub timestamp: DateTime<Utc>,
+    /// 标题
+    pub title: String,
+    /// 详情
+    pub detail: Option<String>,
+    /// 金额（交易与价值变动的变动额）
+    pub amount: Option<f64>,
+    /// 关联的资产ID
+    pub asset_id: Option<Uuid>,
+    /// 来源对象的ID（交易、日记、通知等）
+    pub source_id: Uuid,
+}
+
+/// 只有日期的条目在时间线上的时间
+pub fn start_of_day(date: NaiveDate) -> DateTime<Utc> {
+    date.and_time(chrono::NaiveTime::MIN).and_utc()
+}
+
+/// 时间线筛选条件
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TimelineFilter {
+    /// 包含的类型（为空表示全部）
+    #[serde(default)]
+    pub kinds: Vec<TimelineKind>,
+    /// 只看与该资产相关的条目
+    #[serde(default)]
+    pub asset_id: Option<Uuid>,
+    /// 最多返回的条数（从最新的开始）
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+impl TimelineFilter {
+    /// 是否包含该类型
+    pub fn includes(&self, kind: TimelineKind) -> bool {
+        self.kinds.is_empty() || self.kinds.contains(&kind)
+    }
+
+    /// 是否保留该条目
+    pub fn accepts(&self, event: &TimelineEvent) -> bool {
+        self.includes(event.kind) && self.asset_id.is_none_or(|id| event.asset_id == Some(id))
+    }
+}
@@ -0,0 +1,305 @@
+//! 本地使用统计：各命令的调用次数与最近使用的命令，用于在应用内给出提示
+//!
+//! 统计只写入配置文件旁的 `usage.json`，不会发送到任何地方；只记录命令名称，不记录参数。
+
+use crate::storage::StorageError;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// 统计文件名
+pub const USAGE_FILE: &str = "usage.json";
+
+/// 保留的最近命令数量
+pub const RECENT_COMMANDS: usize = 20;
+
+/// 两次写入统计文件之间的最短间隔
+pub const FLUSH_INTERVAL_SECS: i64 = 60;
+
+/// 使用次数达到此值后才给出提示，避免刚开始使用时打扰
+pub const TIPS_AFTER_COMMANDS: u64 = 50;
+
+/// 可统计的功能
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum Feature {
+    Budget,
+    Import,
+    Reminders,
+    Rules,
+    Sip,
+    Reports,
+    CheckIn,
+}
+
+impl Feature {
+    pub const ALL: [Feature; 7] = [
+        Feature::Budget,
+        Feature::Import,
+        Feature::Reminders,
+        Feature::Rules,
+        Feature::Sip,
+        Feature::Reports,
+        Feature::CheckIn,
+    ];
+
+    /// 表示使用了该功能的命令
+    pub fn commands(&self) -> &'static [&'static str] {
+        match self {
+            Feature::Budget => &["create_envelope", "allocate_to_envelope"],
+            Feature::Import => &["import_statement_file", "import_gnucash", "import_edits"],
+            Feature::Reminders => &["create_reminder"],
+            Feature::Rules => &["save_rule"],
+            Feature::Sip => &["create_sip_plan"],
+            Feature::Reports => &["get_monthly_report", "render_report"],
+            Feature::CheckIn => &["save_check_in"],
+        }
+    }
+
+    /// 从未使用该功能时的提示
+    pub fn tip(&self) -> &'static str {
+        match self {
+            Feature::Budget => "你还没有设置过预算，可以用信封预算为每月开支分配额度",
+            Feature::Import => "可以导入银行对账单，自动记录交易，不必逐笔录入",
+            Feature::Reminders => "可以为到期的存款、账单等设置提醒",
+            Feature::Rules => "可以设置自动化规则，在资产变化时自动打标签或发出通知",
+            Feature::Sip => "基金定投可以自动记录每期份额并计算定投收益率",
+            Feature::Reports => "月度报告汇总每月的收支与资产变化",
+            Feature::CheckIn => "可以写财务日记，记录当时的心情与想法",
+        }
+    }
+}
+
+/// 一个命令的使用情况
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CommandCount {
+    pub count: u64,
+    pub last_used: DateTime<Utc>,
+}
+
+/// 最近使用的一个命令
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CommandUse {
+    pub command: String,
+    pub at: DateTime<Utc>,
+}
+
+/// 使用统计
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UsageStats {
+    /// 开始统计的时间
+    #[serde(default)]
+    pub since: Option<DateTime<Utc>>,
+    /// 各命令的使用情况
+    #[serde(default)]
+    pub commands: BTreeMap<String, CommandCount>,
+    /// 最近使用的命令，最近的在前
+    #[serde(default)]
+    pub recent: Vec<CommandUse>,
+}
+
+/// 一个功能的使用情况
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FeatureUsage {
+    pub feature: Feature,
+    pub count: u64,
+    pub last_used: Option<DateTime<Utc>>,
+}
+
+/// 提示
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct UsageTip {
+    pub feature: Feature,
+    pub message: String,
+}
+
+/// 使用统计汇总
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageSummary {
+    pub since: Option<DateTime<Utc>>,
+    /// 命令调用总次数
+    pub total: u64,
+    pub features: Vec<FeatureUsage>,
+    pub recent: Vec<CommandUse>,
+    pub tips: Vec<UsageTip>,
+}
+
+impl UsageStats {
+    /// 记入一次命令调用
+    pub fn record(&mut self, command: &str, now: DateTime<Utc>) {
+        self.since.get_or_insert(now);
+        let entry = self
+            .commands
+            .entry(command.to_string())
+            .or_insert(CommandCount {
+                count: 0,
+                last_used: now,
+            });
+        entry.count += 1;
+        entry.last_used = now;
+        self.recent.insert(
+            0,
+            CommandUse {
+                command: command.to_string(),
+                at: now,
+            },
+        );
+        self.recent.truncate(RECENT_COMMANDS);
+    }
+
+    /// 功能的使用情况
+    pub fn feature(&self, feature: Feature) -> FeatureUsage {
+        let used = feature
+            .commands()
+            .iter()
+            .filter_map(|command| self.commands.get(*command));
+        FeatureUsage {
+            feature,
+            count: used.clone().map(|c| c.count).sum(),
+            last_used: used.map(|c| c.last_used).max(),
+        }
+    }
+
+    /// 汇总各功能的使用情况，并为从未使用的功能给出提示
+    pub fn summary(&self) -> UsageSummary {
+        let total = self.commands.values().map(|c| c.count).sum();
+        let features: Vec<FeatureUsage> = Feature::ALL.iter().map(|f| self.feature(*f)).collect();
+        let tips = if total < TIPS_AFTER_COMMANDS {
+            Vec::new()
+        } else {
+            features
+                .iter()
+                .filter(|f| f.count == 0)
+                .map(|f| UsageTip {
+                    feature: f.feature,
+                    message: f.feature.tip().to_string(),
+                })
+                .collect()
+        };
+        UsageSummary {
+            since: self.since,
+            total,
+            features,
+            recent: self.recent.clone(),
+            tips,
+        }
+    }
+}
+
+/// 使用统计及其文件，修改后至多每分钟写入一次
+#[derive(Debug, Default)]
+pub struct UsageTracker {
+    /// 统计文件（为空时只保存在内存中）
+    path: Option<PathBuf>,
+    stats: UsageStats,
+    dirty: bool,
+    last_flush: Option<DateTime<Utc>>,
+}
+
+impl UsageTracker {
+    /// 读取统计文件，不存在或无法解析时从头统计
+    pub fn load(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let stats = match std::fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+                tracing::warn!("Failed to parse usage stats {:?}: {}", path, e);
+                UsageStats::default()
+            }),
+            Err(_) => UsageStats::default(),
+        };
+        Self {
+            path: Some(path),
+            stats,
+            ..Self::default()
+        }
+    }
+
+    /// 统计文件路径
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    pub fn stats(&self) -> &UsageStats {
+        &self.stats
+    }
+
+    /// 记入一次命令调用，距上次写入已超过间隔时写入文件
+    pub fn record(&mut self, command: &str, now: DateTime<Utc>) {
+        self.stats.record(command, now);
+        self.dirty = true;
+        let due = self
+            .last_flush
+            .is_none_or(|at| now - at >= Duration::seconds(FLUSH_INTERVAL_SECS));
+        if due {
+            self.last_flush = Some(now);
+            if let Err(e) = self.flush() {
+                tracing::warn!("Failed to save usage stats: {}", e);
+            }
+        }
+    }
+
+    /// 写入未保存的统计（先写临时文件再替换）
+    pub fn flush(&mut self) -> Result<(), StorageError> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if !self.dirty {
+            return Ok(());
+        }
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, serde_json::to_string(&self.stats)?)?;
+        std::fs::rename(&tmp, path)?;
+        self.dirty = false;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    #[test]
+    fn test_usage_stats() {
+        let now = Utc::now();
+        let mut stats = UsageStats::default();
+        stats.record("create_envelope", now);
+        for i in 0..TIPS_AFTER_COMMANDS {
+            stats.record("get_assets", now + Duration::seconds(i as i64));
+        }
+        assert_eq!(stats.since, Some(now));
+        assert_eq!(stats.recent.len(), RECENT_COMMANDS);
+        assert_eq!(stats.recent[0].command, "get_assets");
+
+        let summary = stats.summary();
+        assert_eq!(summary.total, TIPS_AFTER_COMMANDS + 1);
+        assert_eq!(summary.features[0].count, 1);
+        assert!(summary.tips.iter().all(|t| t.feature != Feature::Budget));
+        assert!(summary.tips.iter().any(|t| t.feature == Feature::Import));
+
+        // 刚开始使用时不给出提示
+        assert!(UsageStats::default().summary().tips.is_empty());
+    }
+
+    #[test]
+    fn test_usage_tracker() {
+        let dir = std::env::temp_dir().join(format!("asset-manager-usage-{}", Uuid::new_v4()));
+        let path = dir.join(USAGE_FILE);
+        let now = Utc::now();
+
+        // 第一次记录立即写入，间隔内的修改等到下次写入
+        let mut tracker = UsageTracker::load(&path);
+        tracker.record("get_assets", now);
+        tracker.record("save_rule", now + Duration::seconds(1));
+        assert_eq!(UsageTracker::load(&path).stats().commands.len(), 1);
+        tracker.flush().unwrap();
+        let reloaded = UsageTracker::load(&path);
+        assert_eq!(reloaded.stats().feature(Feature::Rules).count, 1);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,30 @@
+//! 估值历史：资产价值每次变化时记下的快照，供界面绘制价值走势图
+//!
+//! 创建资产、修改价值或记录交易后，价值与最近一次记录不同时追加一条估值。
+//! 与由交易还原的价值序列不同，直接修改价值（未记录交易）的变化也会保留下来。
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// 一次估值记录
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Valuation {
+    pub id: Uuid,
+    pub asset_id: Uuid,
+    /// 当时的资产价值
+    pub value: f64,
+    /// 记录时间
+    pub recorded_at: DateTime<Utc>,
+}
+
+impl Valuation {
+    pub fn new(asset_id: Uuid, value: f64, recorded_at: DateTime<Utc>) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            asset_id,
+            value,
+            recorded_at,
+        }
+    }
+}
@@ -0,0 +1,63 @@
+//! Asset Manager 命令行工具：不启动界面运行插件，供 cron / 任务计划程序定时调用
+//!
+//! 用法：`asset-manager-cli [--portable] plugin run <插件名> <处理函数> [JSON 参数]`
+
+use asset_manager_core::portable::{AppPaths, PORTABLE_ARG};
+use asset_manager_core::PluginManager;
+use std::process::ExitCode;
+use tracing_subscriber::{fmt, prelude::*, EnvFilter};
+
+const USAGE: &str = "Usage: asset-manager-cli [--portable] plugin run <name> <handler> [json]";
+
+fn main() -> ExitCode {
+    // 日志写到标准错误，标准输出只输出结果
+    tracing_subscriber::registry()
+        .with(fmt::layer().with_writer(std::io::stderr))
+        .with(EnvFilter::from_default_env())
+        .init();
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let paths = AppPaths::detect(args.iter().cloned());
+    let args: Vec<&str> = args
+        .iter()
+        .map(String::as_str)
+        .filter(|arg| *arg != PORTABLE_ARG)
+        .collect();
+
+    match args.as_slice() {
+        ["plugin", "run", name, handler, rest @ ..] if rest.len() <= 1 => {
+            match run_plugin(&paths, name, handler, rest.first().copied()) {
+                Ok(output) => {
+                    println!("{}", output);
+                    ExitCode::SUCCESS
+                }
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    ExitCode::FAILURE
+                }
+            }
+        }
+        _ => {
+            eprintln!("{}", USAGE);
+            ExitCode::from(2)
+        }
+    }
+}
+
+/// 加载插件目录中的插件并调用处理函数，返回格式化的 JSON 结果
+fn run_plugin(
+    paths: &AppPaths,
+    name: &str,
+    handler: &str,
+    args: Option<&str>,
+) -> anyhow::Result<String> {
+    let args = match args {
+        Some(json) => serde_json::from_str(json)?,
+        None => serde_json::Value::Null,
+    };
+    let config = paths.load_config();
+    let mut plugin_manager = PluginManager::new(&config.plugins_dir);
+    plugin_manager.load_all()?;
+    let result = plugin_manager.run_handler(name, handler, &args)?;
+    Ok(serde_json::to_string_pretty(&result)?)
+}
@@ -2,11 +2,70 @@
 
 use crate::AppState;
 use asset_manager_core::{
-    asset::{Asset, AssetSummary, AssetType, Currency},
-    plugin::PluginEvent,
+    analytics::{
+        Attribution, ChartSeries, ChartSource, DailyBalance, DateRange, DownsampleMethod, GroupBy,
+        InterpolationMethod, Interval, SeriesPoint,
+    },
+    asset::{
+        holding_symbol, Asset, AssetFormula, AssetPerformance, AssetRelation, AssetSummary,
+        AssetTransaction, AssetType, ClosedPeriod, CorporateAction, CostBasisReport, CostMethod,
+        Currency, DisplayCatalog, DisplayMeta, DisplayTarget, Envelope, EnvelopeSummary,
+        ProjectedBalance, RealizedGainsReport, Recurrence, RelatedAsset, RelationGraph,
+        RelationKind, Reminder, SaleRecord, SchemaViolation, SellOrder, SipInstallment, SipPlan,
+        SipReport, TransactionStatus, TransactionTemplate, TransactionType,
+    },
+    assumptions::Assumptions,
+    attachment::{self, Attachment},
+    binary,
+    bulk_edit::{self, EditFilter, EditPreview},
+    checkin::CheckIn,
+    deep_link::DeepLink,
+    email::{ReportEmail, ReportEmailSettings, SmtpSettings},
+    first_run::{self, FirstRunChoices, FirstRunState},
+    format::{FormatRequest, FormatSettings},
+    fx::{
+        self, ExchangeRateProvider, FrankfurterProvider, FxExposure, FxGainLossReport, FxRate,
+        FxSettings, MockExchangeRateProvider,
+    },
+    gnucash::{self, GnuCashImport},
+    import::{
+        self, ImportBatchStats, ImportResult, ImporterInfo, MappingProfile, StatementFile,
+        StatementSniff, TradeImport, TradeProfile, UndoImport, WatchFolder,
+    },
+    ledger::{AccountKind, LedgerAccount, LedgerEntry, TrialBalance},
+    milestone::Milestone,
+    notification::Notification,
+    plugin::{PluginEvent, PluginHealth, PluginInfo, PluginManager, PluginMetrics},
+    precision::PrecisionSettings,
+    profile::ProfileInfo,
+    quick_entry::{QuickEntryDraft, QuickEntryParser},
+    report::{MonthlyReport, ReportParams, ReportRenderer},
+    rules::{Rule, RuleEvent, RuleSignal},
+    search::SearchSettings,
+    secrets::SMTP_PASSWORD,
+    split::{Debt, ParticipantBalance, Settlement, Share, SharedExpense},
+    storage::{
+        self, sort_assets, ArchiveMode, ArchiveSummary, AssetFilter, AssetUpsert, AssetVersionDiff,
+        AuditQuery, AuditRecord, AuditSettings, BackupInfo, BackupSettings, BulkResult,
+        ConflictResolution, MergeReport, MergeStrategy, MigrationProgress, RebuildReport,
+        RecoveryInfo, SaveMode, SaveSettings, SaveStatus, Snapshot, SortBy, SortOrder,
+        StorageError, StoreConflict, TransactionPage, TransactionQuery, DEFAULT_PAGE_SIZE,
+    },
+    symbol::{
+        self, DividendProvider, MockSymbolProvider, SymbolInfo, SymbolProvider, YahooSymbolProvider,
+    },
+    tags::TagSettings,
+    timeline::{TimelineEvent, TimelineFilter},
+    usage::UsageSummary,
+    valuation::Valuation,
+    AppConfig, Database,
 };
+use chrono::{DateTime, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use tauri::ipc::Response;
 use tauri::State;
+use tauri_plugin_updater::UpdaterExt;
 use uuid::Uuid;
 
 /// 创建资产的请求参数
@@ -18,6 +77,17 @@ pub struct CreateAssetRequest {
     pub currency: Option<String>,
     pub description: Option<String>,
     pub tags: Option<Vec<String>>,
+    /// 设置后创建为由公式计算的虚拟资产
+    pub formula: Option<AssetFormula>,
+    pub cost_basis: Option<f64>,
+    pub target_price: Option<f64>,
+    pub external_id: Option<String>,
+    /// 自定义字段，按资产类型的 Schema 校验
+    pub metadata: Option<serde_json::Value>,
+    /// 显示样式
+    pub display: Option<DisplayMeta>,
+    /// 同时显示的货币（默认本位币）
+    pub display_currency: Option<String>,
 }
 
 /// 更新资产的请求参数
@@ -28,6 +98,101 @@ pub struct UpdateAssetRequest {
     pub value: Option<f64>,
     pub description: Option<String>,
     pub tags: Option<Vec<String>>,
+    pub formula: Option<AssetFormula>,
+    pub cost_basis: Option<f64>,
+    pub target_price: Option<f64>,
+    pub external_id: Option<String>,
+    pub metadata: Option<serde_json::Value>,
+    pub display: Option<DisplayMeta>,
+    /// 同时显示的货币，空字符串表示恢复为本位币
+    pub display_currency: Option<String>,
+}
+
+/// 创建提醒的请求参数
+#[derive(Debug, Deserialize)]
+pub struct CreateReminderRequest {
+    pub title: String,
+    pub recurrence: Recurrence,
+    pub first_due: DateTime<Utc>,
+    pub asset_id: Option<String>,
+}
+
+/// 创建定投计划的请求参数
+#[derive(Debug, Deserialize)]
+pub struct CreateSipPlanRequest {
+    pub asset_id: String,
+    pub pay_from: Option<String>,
+    pub amount: f64,
+    pub recurrence: Recurrence,
+    pub first_run: DateTime<Utc>,
+    pub end: Option<DateTime<Utc>>,
+}
+
+/// 添加资产关系的请求参数
+#[derive(Debug, Deserialize)]
+pub struct AddRelationRequest {
+    pub from_id: String,
+    pub to_id: String,
+    pub kind: RelationKind,
+    pub note: Option<String>,
+}
+
+/// 添加更正分录的请求参数
+#[derive(Debug, Deserialize)]
+pub struct AddCorrectionRequest {
+    pub transaction_id: String,
+    /// 原交易本应记录的变动金额
+    pub corrected_delta: f64,
+    pub note: Option<String>,
+}
+
+/// 记录外币交易的请求参数
+#[derive(Debug, Deserialize)]
+pub struct AddForeignTransactionRequest {
+    pub asset_id: String,
+    /// 带符号的原币金额（支出为负）
+    pub original_amount: f64,
+    pub original_currency: String,
+    /// 1 单位原币折合多少资产币种
+    pub fx_rate: f64,
+    pub transaction_type: TransactionType,
+    pub note: Option<String>,
+}
+
+/// 添加计划交易的请求参数
+#[derive(Debug, Deserialize)]
+pub struct AddScheduledTransactionRequest {
+    pub asset_id: String,
+    /// 变动金额（支出为负）
+    pub delta: f64,
+    pub transaction_type: TransactionType,
+    /// 计划日期（须晚于当前时间）
+    pub date: DateTime<Utc>,
+    pub note: Option<String>,
+}
+
+/// 记录共同支出的请求参数
+///
+/// 指定 `shares` 时按给定份额分摊，否则由 `participants` 平均分摊。
+#[derive(Debug, Deserialize)]
+pub struct AddSharedExpenseRequest {
+    pub description: String,
+    pub payer: String,
+    pub amount: f64,
+    pub participants: Option<Vec<String>>,
+    pub shares: Option<Vec<Share>>,
+    pub date: Option<DateTime<Utc>>,
+}
+
+/// 创建交易模板的请求参数
+#[derive(Debug, Deserialize)]
+pub struct CreateTemplateRequest {
+    pub name: String,
+    pub from_asset_id: Option<String>,
+    pub to_asset_id: Option<String>,
+    pub amount: Option<f64>,
+    pub amount_prompt: Option<String>,
+    pub note: Option<String>,
 }
 
 /// 插件信息响应
@@ -38,15 +203,43 @@ pub struct PluginInfoResponse {
     pub author: Option<String>,
     pub description: Option<String>,
     pub enabled: bool,
+    /// 声明的权限
+    pub permissions: Vec<String>,
+    /// 自加载以来的执行统计
+    pub metrics: PluginMetrics,
+    /// 平均耗时（毫秒）
+    pub average_ms: f64,
+    pub health: PluginHealth,
+}
+
+impl PluginInfoResponse {
+    fn new(info: &PluginInfo, metrics: PluginMetrics) -> Self {
+        Self {
+            name: info.name.clone(),
+            version: info.version.clone(),
+            author: info.author.clone(),
+            description: info.description.clone(),
+            enabled: info.enabled,
+            permissions: info.permissions.clone(),
+            average_ms: metrics.average_ms(),
+            health: metrics.health(),
+            metrics,
+        }
+    }
 }
 
 // ============ 资产命令 ============
 
-/// 获取所有资产
+/// 获取所有资产，可按价值、名称、修改时间或类型排序（默认创建时间倒序）
 #[tauri::command]
-pub fn get_assets(state: State<'_, AppState>) -> Result<Vec<Asset>, String> {
+pub fn get_assets(
+    state: State<'_, AppState>,
+    sort_by: Option<SortBy>,
+    sort_order: Option<SortOrder>,
+) -> Result<Vec<Asset>, String> {
     let db = state.db.lock().map_err(|e| e.to_string())?;
-    db.list_assets().map_err(|e| e.to_string())
+    db.list_assets(sort_by.unwrap_or_default(), sort_order.unwrap_or_default())
+        .map_err(|e| e.to_string())
 }
 
 /// 获取单个资产
@@ -64,37 +257,95 @@ pub fn create_asset(
     state: State<'_, AppState>,
     request: CreateAssetRequest,
 ) -> Result<Asset, String> {
-    let asset_type = parse_asset_type(&request.asset_type);
-    let currency = request
-        .currency
-        .as_ref()
-        .map(|c| parse_currency(c))
-        .unwrap_or_default();
+    let asset = asset_from_request(request);
 
-    let mut asset = Asset::new(request.name, asset_type, request.value)
-        .with_currency(currency);
+    // 保存到数据库（返回规范化标签后的资产）
+    let asset = {
+        let mut db = state.db.lock().map_err(|e| e.to_string())?;
+        db.create_asset(&asset).map_err(|e| e.to_string())?;
+        db.get_asset(asset.id)
+            .map_err(|e| e.to_string())?
+            .ok_or("Asset not found")?
+    };
 
-    if let Some(desc) = request.description {
-        asset = asset.with_description(desc);
+    // 触发插件事件
+    {
+        let pm = state.plugin_manager.lock().map_err(|e| e.to_string())?;
+        pm.broadcast_event(&PluginEvent::AssetCreated(asset.clone()));
     }
+    run_rules(&state, asset_event(RuleEvent::AssetCreated, asset.id));
 
-    if let Some(tags) = request.tags {
-        asset = asset.with_tags(tags);
-    }
+    Ok(asset)
+}
 
-    // 保存到数据库
-    {
+/// 批量创建资产（一次保存），返回出错的项
+#[tauri::command]
+pub fn create_assets_bulk(
+    state: State<'_, AppState>,
+    requests: Vec<CreateAssetRequest>,
+) -> Result<BulkResult, String> {
+    let assets: Vec<Asset> = requests.into_iter().map(asset_from_request).collect();
+
+    let (result, created) = {
         let mut db = state.db.lock().map_err(|e| e.to_string())?;
-        db.create_asset(&asset).map_err(|e| e.to_string())?;
+        let result = db.create_assets_bulk(&assets).map_err(|e| e.to_string())?;
+        let mut created = Vec::new();
+        for (index, asset) in assets.iter().enumerate() {
+            if result.errors.iter().any(|e| e.index == index) {
+                continue;
+            }
+            if let Some(asset) = db.get_asset(asset.id).map_err(|e| e.to_string())? {
+                created.push(asset);
+            }
+        }
+        (result, created)
+    };
+
+    // 触发插件事件
+    {
+        let pm = state.plugin_manager.lock().map_err(|e| e.to_string())?;
+        for asset in &created {
+            pm.broadcast_event(&PluginEvent::AssetCreated(asset.clone()));
+        }
+    }
+    for asset in &created {
+        run_rules(&state, asset_event(RuleEvent::AssetCreated, asset.id));
     }
 
+    Ok(result)
+}
+
+/// 新建或更新资产（按外部标识匹配，未设置时按名称与类型匹配）
+#[tauri::command]
+pub fn upsert_asset(
+    state: State<'_, AppState>,
+    request: CreateAssetRequest,
+) -> Result<AssetUpsert, String> {
+    let asset = asset_from_request(request);
+
+    let upsert = {
+        let mut db = state.db.lock().map_err(|e| e.to_string())?;
+        db.upsert_asset(&asset).map_err(|e| e.to_string())?
+    };
+
     // 触发插件事件
     {
         let pm = state.plugin_manager.lock().map_err(|e| e.to_string())?;
-        pm.broadcast_event(&PluginEvent::AssetCreated(asset.clone()));
+        let event = if upsert.created {
+            PluginEvent::AssetCreated(upsert.asset.clone())
+        } else {
+            PluginEvent::AssetUpdated(upsert.asset.clone())
+        };
+        pm.broadcast_event(&event);
     }
+    let event = if upsert.created {
+        RuleEvent::AssetCreated
+    } else {
+        RuleEvent::AssetUpdated
+    };
+    run_rules(&state, asset_event(event, upsert.asset.id));
 
-    Ok(asset)
+    Ok(upsert)
 }
 
 /// 更新资产
@@ -124,8 +375,35 @@ pub fn update_asset(
     if let Some(tags) = request.tags {
         asset.tags = tags;
     }
+    if let Some(formula) = request.formula {
+        asset.formula = Some(formula);
+    }
+    if let Some(cost_basis) = request.cost_basis {
+        asset.cost_basis = Some(cost_basis);
+    }
+    if let Some(target_price) = request.target_price {
+        asset.target_price = Some(target_price);
+    }
+    if let Some(external_id) = request.external_id {
+        asset.external_id = Some(external_id);
+    }
+    if let Some(metadata) = request.metadata {
+        asset.metadata = metadata;
+    }
+    if let Some(display) = request.display {
+        asset.display = display;
+    }
+    if let Some(currency) = request.display_currency {
+        asset.display_currency = Some(currency)
+            .filter(|c| !c.trim().is_empty())
+            .map(|c| Currency::from_code(&c));
+    }
 
     db.update_asset(&asset).map_err(|e| e.to_string())?;
+    let asset = db
+        .get_asset(uuid)
+        .map_err(|e| e.to_string())?
+        .ok_or("Asset not found")?;
 
     // 触发插件事件
     drop(db);
@@ -133,10 +411,52 @@ pub fn update_asset(
         let pm = state.plugin_manager.lock().map_err(|e| e.to_string())?;
         pm.broadcast_event(&PluginEvent::AssetUpdated(asset.clone()));
     }
+    run_rules(&state, asset_event(RuleEvent::AssetUpdated, asset.id));
 
     Ok(asset)
 }
 
+/// 按外部标识获取资产
+#[tauri::command]
+pub fn get_asset_by_external_id(
+    state: State<'_, AppState>,
+    external_id: String,
+) -> Result<Option<Asset>, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    db.get_asset_by_external_id(&external_id)
+        .map_err(|e| e.to_string())
+}
+
+/// 按类型、货币、标签、价值与时间范围筛选资产，可指定排序
+#[tauri::command]
+pub fn query_assets(
+    state: State<'_, AppState>,
+    filter: AssetFilter,
+    sort_by: Option<SortBy>,
+    sort_order: Option<SortOrder>,
+) -> Result<Vec<Asset>, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let mut assets = db.query_assets(&filter).map_err(|e| e.to_string())?;
+    sort_assets(
+        &mut assets,
+        sort_by.unwrap_or_default(),
+        sort_order.unwrap_or_default(),
+    );
+    Ok(assets)
+}
+
+/// 按元数据字段筛选资产（如 `$.broker` 等于 "IBKR"）
+#[tauri::command]
+pub fn query_assets_by_metadata(
+    state: State<'_, AppState>,
+    json_path: String,
+    value: serde_json::Value,
+) -> Result<Vec<Asset>, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    db.query_assets_by_metadata(&json_path, &value)
+        .map_err(|e| e.to_string())
+}
+
 /// 删除资产
 #[tauri::command]
 pub fn delete_asset(state: State<'_, AppState>, id: String) -> Result<(), String> {
@@ -152,10 +472,122 @@ pub fn delete_asset(state: State<'_, AppState>, id: String) -> Result<(), String
         let pm = state.plugin_manager.lock().map_err(|e| e.to_string())?;
         pm.broadcast_event(&PluginEvent::AssetDeleted(uuid));
     }
+    run_rules(&state, asset_event(RuleEvent::AssetDeleted, uuid));
+
+    Ok(())
+}
+
+/// 永久删除资产（包括回收站中的资产）
+#[tauri::command]
+pub fn delete_asset_permanently(state: State<'_, AppState>, id: String) -> Result<(), String> {
+    let uuid = Uuid::parse_str(&id).map_err(|e| e.to_string())?;
+
+    let attachments_dir = attachments_dir(&state)?;
+    let was_live = {
+        let mut db = state.db.lock().map_err(|e| e.to_string())?;
+        let was_live = db.get_asset(uuid).map_err(|e| e.to_string())?.is_some();
+        db.delete_asset_permanently(uuid)
+            .map_err(|e| e.to_string())?;
+        prune_attachment_files(&db, &attachments_dir);
+        was_live
+    };
+
+    // 回收站中的资产删除时已触发过事件
+    if was_live {
+        {
+            let pm = state.plugin_manager.lock().map_err(|e| e.to_string())?;
+            pm.broadcast_event(&PluginEvent::AssetDeleted(uuid));
+        }
+        run_rules(&state, asset_event(RuleEvent::AssetDeleted, uuid));
+    }
 
     Ok(())
 }
 
+/// 获取回收站中的资产
+#[tauri::command]
+pub fn list_trashed(state: State<'_, AppState>) -> Result<Vec<Asset>, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    db.list_trashed().map_err(|e| e.to_string())
+}
+
+/// 从回收站恢复资产
+#[tauri::command]
+pub fn restore_asset(state: State<'_, AppState>, id: String) -> Result<Asset, String> {
+    let uuid = Uuid::parse_str(&id).map_err(|e| e.to_string())?;
+
+    let asset = {
+        let mut db = state.db.lock().map_err(|e| e.to_string())?;
+        db.restore_asset(uuid).map_err(|e| e.to_string())?
+    };
+
+    // 触发插件事件
+    {
+        let pm = state.plugin_manager.lock().map_err(|e| e.to_string())?;
+        pm.broadcast_event(&PluginEvent::AssetCreated(asset.clone()));
+    }
+    run_rules(&state, asset_event(RuleEvent::AssetCreated, asset.id));
+
+    Ok(asset)
+}
+
+/// 永久删除指定时间及之前移入回收站的资产，返回删除的数量
+#[tauri::command]
+pub fn purge_trash(state: State<'_, AppState>, older_than: DateTime<Utc>) -> Result<usize, String> {
+    let attachments_dir = attachments_dir(&state)?;
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+    let purged = db.purge_trash(older_than).map_err(|e| e.to_string())?;
+    prune_attachment_files(&db, &attachments_dir);
+    Ok(purged)
+}
+
+/// 获取资产的修改历史（各次修改的字段变化）
+#[tauri::command]
+pub fn get_asset_history_diff(
+    state: State<'_, AppState>,
+    asset_id: String,
+) -> Result<Vec<AssetVersionDiff>, String> {
+    let uuid = Uuid::parse_str(&asset_id).map_err(|e| e.to_string())?;
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    db.get_asset_history_diff(uuid).map_err(|e| e.to_string())
+}
+
+/// 查询资产与交易的修改记录（按时间倒序）
+#[tauri::command]
+pub fn query_audit_log(
+    state: State<'_, AppState>,
+    query: Option<AuditQuery>,
+) -> Result<Vec<AuditRecord>, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    db.query_audit_log(&query.unwrap_or_default())
+        .map_err(|e| e.to_string())
+}
+
+/// 获取修改记录设置
+#[tauri::command]
+pub fn get_audit_settings(state: State<'_, AppState>) -> Result<AuditSettings, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    db.get_audit_settings().map_err(|e| e.to_string())
+}
+
+/// 保存修改记录设置（保留天数缩短时立即清除旧记录）
+#[tauri::command]
+pub fn set_audit_settings(
+    state: State<'_, AppState>,
+    settings: AuditSettings,
+) -> Result<(), String> {
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+    db.set_audit_settings(&settings).map_err(|e| e.to_string())
+}
+
+/// 获取各资产的浮动盈亏与目标进度
+#[tauri::command]
+pub async fn get_asset_performance(
+    state: State<'_, AppState>,
+) -> Result<Vec<AssetPerformance>, String> {
+    on_snapshot(&state, |db| db.get_asset_performance()).await
+}
+
 /// 搜索资产
 #[tauri::command]
 pub fn search_assets(state: State<'_, AppState>, query: String) -> Result<Vec<Asset>, String> {
@@ -163,92 +595,2690 @@ pub fn search_assets(state: State<'_, AppState>, query: String) -> Result<Vec<As
     db.search_assets(&query).map_err(|e| e.to_string())
 }
 
-/// 获取资产摘要
+/// 按相关度搜索资产（名称匹配优先）
 #[tauri::command]
-pub fn get_summary(state: State<'_, AppState>) -> Result<AssetSummary, String> {
+pub fn search_assets_ranked(
+    state: State<'_, AppState>,
+    query: String,
+) -> Result<Vec<Asset>, String> {
     let db = state.db.lock().map_err(|e| e.to_string())?;
-    db.get_summary().map_err(|e| e.to_string())
+    db.search_assets_ranked(&query).map_err(|e| e.to_string())
 }
 
-// ============ 插件命令 ============
+/// 获取搜索设置
+#[tauri::command]
+pub fn get_search_settings(state: State<'_, AppState>) -> Result<SearchSettings, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    db.get_search_settings().map_err(|e| e.to_string())
+}
 
-/// 获取插件列表
+/// 保存搜索设置（模糊匹配与拼音匹配）
 #[tauri::command]
-pub fn get_plugins(state: State<'_, AppState>) -> Result<Vec<PluginInfoResponse>, String> {
+pub fn set_search_settings(
+    state: State<'_, AppState>,
+    settings: SearchSettings,
+) -> Result<(), String> {
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+    db.set_search_settings(&settings).map_err(|e| e.to_string())
+}
+
+/// 获取利率环境假设
+#[tauri::command]
+pub fn get_assumptions(state: State<'_, AppState>) -> Result<Assumptions, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    db.get_assumptions().map_err(|e| e.to_string())
+}
+
+/// 保存利率环境假设（存款利率、房贷利率与预期通胀率）
+#[tauri::command]
+pub fn set_assumptions(state: State<'_, AppState>, assumptions: Assumptions) -> Result<(), String> {
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+    db.set_assumptions(&assumptions).map_err(|e| e.to_string())
+}
+
+/// 获取资产摘要（按精度设置舍入，并合并插件指标），可只统计符合筛选条件的资产
+#[tauri::command]
+pub fn get_summary(
+    state: State<'_, AppState>,
+    include_pending: Option<bool>,
+    filter: Option<AssetFilter>,
+) -> Result<AssetSummary, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let summary = db
+        .get_summary_filtered(&filter.unwrap_or_default(), include_pending.unwrap_or(true))
+        .map_err(|e| e.to_string())?;
+    let precision = db.get_precision_settings().map_err(|e| e.to_string())?;
+    let mut summary = precision.round_summary(&summary);
+    drop(db);
+
+    // 合并插件提供的附加指标
     let pm = state.plugin_manager.lock().map_err(|e| e.to_string())?;
-    
-    let plugins = pm
-        .list_plugins()
-        .iter()
-        .map(|p| PluginInfoResponse {
-            name: p.name.clone(),
-            version: p.version.clone(),
-            author: p.author.clone(),
-            description: p.description.clone(),
-            enabled: p.enabled,
-        })
-        .collect();
+    pm.contribute_summary(&mut summary);
+    Ok(summary)
+}
 
-    Ok(plugins)
+/// 获取资产价值的插值序列（供图表使用）
+#[tauri::command]
+pub fn get_interpolated_series(
+    state: State<'_, AppState>,
+    asset_id: String,
+    range: DateRange,
+    interval: Interval,
+    method: Option<InterpolationMethod>,
+) -> Result<Vec<SeriesPoint>, String> {
+    let uuid = Uuid::parse_str(&asset_id).map_err(|e| e.to_string())?;
+    let db = snapshot(&state)?;
+    db.get_interpolated_series(uuid, &range, interval, method.unwrap_or_default())
+        .map_err(|e| e.to_string())
 }
 
-/// 重新加载插件
+/// 获取资产每天的日终价值（供历史图表使用）
 #[tauri::command]
-pub fn reload_plugins(state: State<'_, AppState>) -> Result<Vec<PluginInfoResponse>, String> {
-    let mut pm = state.plugin_manager.lock().map_err(|e| e.to_string())?;
-    
-    let loaded = pm.load_all().map_err(|e| e.to_string())?;
-    
-    let plugins = loaded
-        .iter()
-        .map(|p| PluginInfoResponse {
-            name: p.name.clone(),
-            version: p.version.clone(),
-            author: p.author.clone(),
-            description: p.description.clone(),
-            enabled: p.enabled,
-        })
-        .collect();
+pub fn get_daily_balances(
+    state: State<'_, AppState>,
+    asset_id: String,
+    range: DateRange,
+) -> Result<Vec<DailyBalance>, String> {
+    let uuid = Uuid::parse_str(&asset_id).map_err(|e| e.to_string())?;
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    db.get_daily_balances(uuid, &range)
+        .map_err(|e| e.to_string())
+}
 
-    Ok(plugins)
+/// 获取资产在时间范围内的估值历史（供价值走势图使用）
+#[tauri::command]
+pub fn get_valuation_history(
+    state: State<'_, AppState>,
+    asset_id: String,
+    range: DateRange,
+) -> Result<Vec<Valuation>, String> {
+    let uuid = Uuid::parse_str(&asset_id).map_err(|e| e.to_string())?;
+    let db = snapshot(&state)?;
+    db.get_valuation_history(uuid, &range)
+        .map_err(|e| e.to_string())
 }
 
-/// 设置插件启用状态
+/// 获取收益归因（资金投入/取出与市场收益）
 #[tauri::command]
-pub fn set_plugin_enabled(
+pub fn get_attribution(
     state: State<'_, AppState>,
-    name: String,
-    enabled: bool,
-) -> Result<(), String> {
-    let mut pm = state.plugin_manager.lock().map_err(|e| e.to_string())?;
-    pm.set_plugin_enabled(&name, enabled).map_err(|e| e.to_string())
+    range: DateRange,
+    group_by: GroupBy,
+) -> Result<Attribution, String> {
+    let db = snapshot(&state)?;
+    db.get_attribution(&range, group_by)
+        .map_err(|e| e.to_string())
 }
 
-// ============ 辅助函数 ============
+/// 获取时间线：交易、价值变动、日记、通知、导入与里程碑按时间倒序合并
+#[tauri::command]
+pub fn get_timeline(
+    state: State<'_, AppState>,
+    range: DateRange,
+    filter: Option<TimelineFilter>,
+) -> Result<Vec<TimelineEvent>, String> {
+    let db = snapshot(&state)?;
+    db.get_timeline(&range, &filter.unwrap_or_default())
+        .map_err(|e| e.to_string())
+}
+
+/// 获取外汇敞口
+#[tauri::command]
+pub fn get_fx_exposure(state: State<'_, AppState>) -> Result<FxExposure, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    db.get_fx_exposure().map_err(|e| e.to_string())
+}
+
+/// 获取本位币与汇率设置
+#[tauri::command]
+pub fn get_fx_settings(state: State<'_, AppState>) -> Result<FxSettings, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    db.get_fx_settings().map_err(|e| e.to_string())
+}
+
+/// 保存本位币与汇率设置
+#[tauri::command]
+pub fn set_fx_settings(state: State<'_, AppState>, settings: FxSettings) -> Result<(), String> {
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+    db.set_fx_settings(&settings).map_err(|e| e.to_string())
+}
+
+/// 获取某货币的历史汇率
+#[tauri::command]
+pub fn get_fx_rates(state: State<'_, AppState>, currency: Currency) -> Result<Vec<FxRate>, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    db.list_fx_rates(&currency).map_err(|e| e.to_string())
+}
+
+/// 手动录入历史汇率
+#[tauri::command]
+pub fn add_fx_rates(state: State<'_, AppState>, rates: Vec<FxRate>) -> Result<usize, String> {
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+    db.upsert_fx_rates(&rates).map_err(|e| e.to_string())
+}
+
+/// 从汇率数据源回填资产所用货币的历史汇率，返回写入条数
+#[tauri::command]
+pub async fn backfill_fx_rates(
+    state: State<'_, AppState>,
+    start: NaiveDate,
+    end: Option<NaiveDate>,
+) -> Result<usize, String> {
+    let end = end.unwrap_or_else(|| Utc::now().date_naive());
+    let (currencies, base) = {
+        let db = state.db.lock().map_err(|e| e.to_string())?;
+        let mut currencies: Vec<Currency> = Vec::new();
+        for asset in db.iter_assets().map_err(|e| e.to_string())? {
+            if !currencies.contains(&asset.currency) {
+                currencies.push(asset.currency);
+            }
+        }
+        let base = db
+            .get_fx_settings()
+            .map_err(|e| e.to_string())?
+            .base_currency;
+        (currencies, base)
+    };
+
+    // 网络请求期间不持有数据库锁
+    let provider = exchange_rate_provider(&state)?;
+    let rates = tauri::async_runtime::spawn_blocking(move || {
+        fx::backfill(provider.as_ref(), &currencies, &base, start, end)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())?;
+
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+    db.upsert_fx_rates(&rates).map_err(|e| e.to_string())
+}
+
+/// 查找证券代码（用于股票资产代码与交易所的自动补全），`live` 为真时合并在线查询结果
+#[tauri::command]
+pub async fn lookup_symbol(
+    state: State<'_, AppState>,
+    query: String,
+    live: Option<bool>,
+    limit: Option<usize>,
+) -> Result<Vec<SymbolInfo>, String> {
+    let limit = limit.unwrap_or(symbol::DEFAULT_LOOKUP_LIMIT);
+    if !live.unwrap_or(false) {
+        return Ok(symbol::lookup_bundled(&query, limit));
+    }
+    let provider = symbol_provider(&state)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        symbol::lookup_symbol(&query, Some(provider.as_ref()), limit)
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
+
+/// 获取以本位币计价的净资产序列（按当时汇率折算）
+#[tauri::command]
+pub fn get_net_worth_series(
+    state: State<'_, AppState>,
+    range: DateRange,
+    interval: Interval,
+) -> Result<Vec<SeriesPoint>, String> {
+    let db = snapshot(&state)?;
+    db.get_net_worth_series(&range, interval)
+        .map_err(|e| e.to_string())
+}
+
+/// 获取降采样到目标点数的图表序列（默认 LTTB），多年的每日数据也只返回目标点数以内的点
+#[tauri::command]
+pub fn get_chart_series(
+    state: State<'_, AppState>,
+    source: ChartSource,
+    range: DateRange,
+    target_points: usize,
+    method: Option<DownsampleMethod>,
+) -> Result<ChartSeries, String> {
+    let db = snapshot(&state)?;
+    db.get_chart_series(&source, &range, target_points, method.unwrap_or_default())
+        .map_err(|e| e.to_string())
+}
+
+/// 获取舍入与显示精度设置
+#[tauri::command]
+pub fn get_precision_settings(state: State<'_, AppState>) -> Result<PrecisionSettings, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    db.get_precision_settings().map_err(|e| e.to_string())
+}
+
+/// 保存舍入与显示精度设置
+#[tauri::command]
+pub fn set_precision_settings(
+    state: State<'_, AppState>,
+    settings: PrecisionSettings,
+) -> Result<(), String> {
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+    db.set_precision_settings(&settings)
+        .map_err(|e| e.to_string())
+}
+
+/// 获取数字与日期格式设置
+#[tauri::command]
+pub fn get_format_settings(state: State<'_, AppState>) -> Result<FormatSettings, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    db.get_format_settings().map_err(|e| e.to_string())
+}
+
+/// 保存数字与日期格式设置
+#[tauri::command]
+pub fn set_format_settings(
+    state: State<'_, AppState>,
+    settings: FormatSettings,
+) -> Result<(), String> {
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+    db.set_format_settings(&settings).map_err(|e| e.to_string())
+}
+
+/// 按当前语言、格式与精度设置批量格式化数值与日期，与报告中的显示一致
+#[tauri::command]
+pub fn format_values(
+    state: State<'_, AppState>,
+    requests: Vec<FormatRequest>,
+) -> Result<Vec<String>, String> {
+    let formatter = {
+        let db = state.db.lock().map_err(|e| e.to_string())?;
+        db.formatter().map_err(|e| e.to_string())?
+    };
+    Ok(requests.iter().map(|r| formatter.format(r)).collect())
+}
+
+/// 获取标签设置
+#[tauri::command]
+pub fn get_tag_settings(state: State<'_, AppState>) -> Result<TagSettings, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    db.get_tag_settings().map_err(|e| e.to_string())
+}
+
+/// 保存标签设置，返回标签有变化的资产数
+#[tauri::command]
+pub fn set_tag_settings(
+    state: State<'_, AppState>,
+    settings: TagSettings,
+) -> Result<usize, String> {
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+    db.set_tag_settings(&settings).map_err(|e| e.to_string())
+}
+
+/// 获取标签别名
+#[tauri::command]
+pub fn get_tag_aliases(state: State<'_, AppState>) -> Result<BTreeMap<String, String>, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    db.list_tag_aliases().map_err(|e| e.to_string())
+}
+
+/// 添加或修改标签别名，返回标签有变化的资产数
+#[tauri::command]
+pub fn set_tag_alias(
+    state: State<'_, AppState>,
+    alias: String,
+    tag: String,
+) -> Result<usize, String> {
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+    db.set_tag_alias(&alias, &tag).map_err(|e| e.to_string())
+}
+
+/// 删除标签别名
+#[tauri::command]
+pub fn remove_tag_alias(state: State<'_, AppState>, alias: String) -> Result<(), String> {
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+    db.remove_tag_alias(&alias).map_err(|e| e.to_string())
+}
+
+/// 获取资产类型、分类与标签的显示样式
+#[tauri::command]
+pub fn get_display_catalog(state: State<'_, AppState>) -> Result<DisplayCatalog, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    db.get_display_catalog().map_err(|e| e.to_string())
+}
+
+/// 设置显示样式（样式为空时清除）
+#[tauri::command]
+pub fn set_display(
+    state: State<'_, AppState>,
+    target: DisplayTarget,
+    display: DisplayMeta,
+) -> Result<(), String> {
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+    db.set_display(&target, &display).map_err(|e| e.to_string())
+}
+
+/// 获取保存设置
+#[tauri::command]
+pub fn get_save_settings(state: State<'_, AppState>) -> Result<SaveSettings, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    db.get_save_settings().map_err(|e| e.to_string())
+}
+
+/// 保存保存设置（同时写入未保存的修改）
+#[tauri::command]
+pub fn set_save_settings(
+    state: State<'_, AppState>,
+    settings: SaveSettings,
+) -> Result<(), String> {
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+    db.set_save_settings(&settings).map_err(|e| e.to_string())
+}
+
+/// 获取保存状态（是否有未保存的修改）
+#[tauri::command]
+pub fn get_save_status(state: State<'_, AppState>) -> Result<SaveStatus, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    Ok(db.save_status())
+}
+
+/// 立即写入所有未保存的修改
+#[tauri::command]
+pub fn save_now(state: State<'_, AppState>) -> Result<SaveStatus, String> {
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+    db.save_now().map_err(|e| e.to_string())?;
+    Ok(db.save_status())
+}
+
+/// 从资产与交易重建索引、每日余额与账簿余额
+#[tauri::command]
+pub async fn rebuild_derived_data(state: State<'_, AppState>) -> Result<RebuildReport, String> {
+    on_db_thread(&state, |db| db.rebuild_derived_data()).await
+}
+
+/// 压缩数据文件并清理不再使用的附件文件，返回压缩前后的大小
+#[tauri::command]
+pub async fn compact_database(state: State<'_, AppState>) -> Result<CompactReport, String> {
+    let attachments_dir = attachments_dir(&state)?;
+    on_db_thread(&state, move |db| {
+        let report = db.compact()?;
+        prune_attachment_files(db, &attachments_dir);
+        Ok(report)
+    })
+    .await
+}
+
+/// 获取数据文件被外部修改（如同步目录中另一台设备写入）后的冲突，包含两个版本的差异
+#[tauri::command]
+pub fn get_store_conflict(state: State<'_, AppState>) -> Result<Option<StoreConflict>, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    Ok(db.store_conflict())
+}
+
+/// 解决数据文件冲突
+#[tauri::command]
+pub fn resolve_store_conflict(
+    state: State<'_, AppState>,
+    resolution: ConflictResolution,
+) -> Result<SaveStatus, String> {
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+    db.resolve_store_conflict(resolution)
+        .map_err(|e| e.to_string())?;
+    Ok(db.save_status())
+}
+
+/// 解析快速录入文本，返回待确认的交易草稿
+#[tauri::command]
+pub fn parse_quick_entry(text: String) -> Result<Option<QuickEntryDraft>, String> {
+    Ok(QuickEntryParser::new().parse(&text))
+}
+
+// ============ 批量编辑命令 ============
+
+/// 导出用于表格编辑的 CSV，返回导出的资产数量
+#[tauri::command]
+pub fn export_for_edit(
+    state: State<'_, AppState>,
+    path: String,
+    filter: Option<EditFilter>,
+) -> Result<usize, String> {
+    let db = snapshot(&state)?;
+    let content =
+        bulk_edit::export_for_edit(&db, &filter.unwrap_or_default()).map_err(|e| e.to_string())?;
+    std::fs::write(&path, &content).map_err(|e| e.to_string())?;
+    Ok(content.lines().count().saturating_sub(1))
+}
+
+/// 预览表格中的修改
+#[tauri::command]
+pub fn preview_edits(state: State<'_, AppState>, path: String) -> Result<EditPreview, String> {
+    let content = std::fs::read_to_string(&path).map_err(|e| e.to_string())?;
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    bulk_edit::preview_edits(&db, &content).map_err(|e| e.to_string())
+}
+
+/// 导回表格中的修改
+#[tauri::command]
+pub fn import_edits(state: State<'_, AppState>, path: String) -> Result<EditPreview, String> {
+    let content = std::fs::read_to_string(&path).map_err(|e| e.to_string())?;
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+    bulk_edit::apply_edits(&mut db, &content).map_err(|e| e.to_string())
+}
+
+// ============ 交易记录命令 ============
+
+/// 获取资产的交易历史（可限定时间范围、类型与条数）
+#[tauri::command]
+pub fn get_transactions(
+    state: State<'_, AppState>,
+    asset_id: String,
+    query: Option<TransactionQuery>,
+) -> Result<Vec<AssetTransaction>, String> {
+    let uuid = Uuid::parse_str(&asset_id).map_err(|e| e.to_string())?;
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    db.get_transactions(uuid, &query.unwrap_or_default())
+        .map_err(|e| e.to_string())
+}
+
+/// 分页获取资产的交易，`cursor` 为上一页返回的 `next_cursor`
+#[tauri::command]
+pub fn get_transactions_page(
+    state: State<'_, AppState>,
+    asset_id: String,
+    cursor: Option<String>,
+    limit: Option<usize>,
+) -> Result<TransactionPage, String> {
+    let uuid = Uuid::parse_str(&asset_id).map_err(|e| e.to_string())?;
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    db.get_transactions_page(uuid, cursor.as_deref(), limit.unwrap_or(DEFAULT_PAGE_SIZE))
+        .map_err(|e| e.to_string())
+}
+
+/// 获取所有资产最近的交易，用于动态列表
+#[tauri::command]
+pub fn list_recent_transactions(
+    state: State<'_, AppState>,
+    limit: Option<usize>,
+) -> Result<Vec<AssetTransaction>, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    db.list_recent_transactions(limit.unwrap_or(DEFAULT_PAGE_SIZE))
+        .map_err(|e| e.to_string())
+}
+
+/// 按清算状态筛选交易
+#[tauri::command]
+pub fn get_transactions_by_status(
+    state: State<'_, AppState>,
+    status: TransactionStatus,
+    asset_id: Option<String>,
+) -> Result<Vec<AssetTransaction>, String> {
+    let asset_id = asset_id
+        .map(|id| Uuid::parse_str(&id))
+        .transpose()
+        .map_err(|e| e.to_string())?;
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    db.get_transactions_by_status(status, asset_id)
+        .map_err(|e| e.to_string())
+}
+
+/// 更改交易的清算状态（待入账/已入账/已对账）
+#[tauri::command]
+pub fn set_transaction_status(
+    state: State<'_, AppState>,
+    id: String,
+    status: TransactionStatus,
+) -> Result<AssetTransaction, String> {
+    let uuid = Uuid::parse_str(&id).map_err(|e| e.to_string())?;
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+    db.set_transaction_status(uuid, status)
+        .map_err(|e| e.to_string())
+}
+
+/// 修改交易记录
+#[tauri::command]
+pub fn update_transaction(
+    state: State<'_, AppState>,
+    transaction: AssetTransaction,
+) -> Result<(), String> {
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+    db.update_transaction(&transaction)
+        .map_err(|e| e.to_string())
+}
+
+/// 删除交易记录
+#[tauri::command]
+pub fn delete_transaction(state: State<'_, AppState>, id: String) -> Result<(), String> {
+    let uuid = Uuid::parse_str(&id).map_err(|e| e.to_string())?;
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+    db.delete_transaction(uuid).map_err(|e| e.to_string())
+}
+
+/// 为交易添加更正分录
+#[tauri::command]
+pub fn add_correction(
+    state: State<'_, AppState>,
+    request: AddCorrectionRequest,
+) -> Result<AssetTransaction, String> {
+    let uuid = Uuid::parse_str(&request.transaction_id).map_err(|e| e.to_string())?;
+    let transaction = {
+        let mut db = state.db.lock().map_err(|e| e.to_string())?;
+        db.add_correction(uuid, request.corrected_delta, request.note)
+            .map_err(|e| e.to_string())?
+    };
+    run_rules(&state, asset_event(RuleEvent::TransactionAdded, transaction.asset_id));
+    Ok(transaction)
+}
+
+/// 记录币种与资产不同的交易
+#[tauri::command]
+pub fn add_foreign_transaction(
+    state: State<'_, AppState>,
+    request: AddForeignTransactionRequest,
+) -> Result<AssetTransaction, String> {
+    let uuid = Uuid::parse_str(&request.asset_id).map_err(|e| e.to_string())?;
+    let currency = Currency::from_code(&request.original_currency);
+    let transaction = {
+        let mut db = state.db.lock().map_err(|e| e.to_string())?;
+        db.add_foreign_transaction(
+            uuid,
+            request.original_amount,
+            currency,
+            request.fx_rate,
+            request.transaction_type,
+            request.note,
+        )
+        .map_err(|e| e.to_string())?
+    };
+    run_rules(&state, asset_event(RuleEvent::TransactionAdded, transaction.asset_id));
+    Ok(transaction)
+}
+
+/// 对持仓执行公司行动（拆股、代码变更、分红），返回记录的说明交易
+#[tauri::command]
+pub fn apply_corporate_action(
+    state: State<'_, AppState>,
+    asset_id: String,
+    action: CorporateAction,
+) -> Result<AssetTransaction, String> {
+    let uuid = Uuid::parse_str(&asset_id).map_err(|e| e.to_string())?;
+    let transaction = {
+        let mut db = state.db.lock().map_err(|e| e.to_string())?;
+        db.apply_corporate_action(uuid, &action)
+            .map_err(|e| e.to_string())?
+    };
+    run_rules(&state, asset_event(RuleEvent::TransactionAdded, transaction.asset_id));
+    Ok(transaction)
+}
+
+/// 从行情数据源录入持有期间的分红，返回新录入的交易
+#[tauri::command]
+pub async fn sync_dividends(
+    state: State<'_, AppState>,
+    asset_id: String,
+    pay_to: Option<String>,
+) -> Result<Vec<AssetTransaction>, String> {
+    let uuid = Uuid::parse_str(&asset_id).map_err(|e| e.to_string())?;
+    let pay_to = parse_optional_uuid(pay_to.as_deref())?;
+    let (symbol, since) = {
+        let db = state.db.lock().map_err(|e| e.to_string())?;
+        let asset = db
+            .get_asset(uuid)
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| format!("Asset not found: {}", asset_id))?;
+        let symbol = holding_symbol(&asset)
+            .ok_or_else(|| format!("{} has no symbol", asset.name))?
+            .to_string();
+        (symbol, asset.created_at.date_naive())
+    };
+
+    // 网络请求期间不持有数据库锁
+    let provider = dividend_provider(&state)?;
+    let events = tauri::async_runtime::spawn_blocking(move || provider.dividends(&symbol, since))
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())?;
+
+    let recorded = {
+        let mut db = state.db.lock().map_err(|e| e.to_string())?;
+        db.record_dividends(uuid, &events, pay_to)
+            .map_err(|e| e.to_string())?
+    };
+    for transaction in &recorded {
+        run_rules(&state, asset_event(RuleEvent::TransactionAdded, transaction.asset_id));
+    }
+    Ok(recorded)
+}
+
+/// 部分卖出持仓，手续费与税费记为关联到卖出交易的支出，返回记录的交易与实际到账金额
+#[tauri::command]
+pub fn sell_asset(state: State<'_, AppState>, order: SellOrder) -> Result<SaleRecord, String> {
+    let record = {
+        let mut db = state.db.lock().map_err(|e| e.to_string())?;
+        db.sell_asset(&order).map_err(|e| e.to_string())?
+    };
+    run_rules(
+        &state,
+        asset_event(RuleEvent::TransactionAdded, order.asset_id),
+    );
+    if let Some(pay_to) = order.pay_to {
+        run_rules(&state, asset_event(RuleEvent::TransactionAdded, pay_to));
+    }
+    Ok(record)
+}
+
+/// 获取时间范围内的已实现盈亏与手续费、税费汇总（未指定方法时按加权平均结转成本）
+#[tauri::command]
+pub async fn get_realized_gains(
+    state: State<'_, AppState>,
+    range: DateRange,
+    method: Option<CostMethod>,
+) -> Result<RealizedGainsReport, String> {
+    on_snapshot(&state, move |db| {
+        db.get_realized_gains(&range, method.unwrap_or_default())
+    })
+    .await
+}
+
+/// 按买入/卖出历史重算资产的买入成本，未指定方法时按加权平均，返回各步的成本变动
+#[tauri::command]
+pub fn recalculate_cost_basis(
+    state: State<'_, AppState>,
+    asset_id: String,
+    method: Option<CostMethod>,
+) -> Result<CostBasisReport, String> {
+    let uuid = Uuid::parse_str(&asset_id).map_err(|e| e.to_string())?;
+    let report = {
+        let mut db = state.db.lock().map_err(|e| e.to_string())?;
+        db.recalculate_cost_basis(uuid, method.unwrap_or_default())
+            .map_err(|e| e.to_string())?
+    };
+    if report.previous != Some(report.cost_basis) {
+        run_rules(&state, asset_event(RuleEvent::AssetUpdated, uuid));
+    }
+    Ok(report)
+}
+
+// ============ 基金定投命令 ============
+
+/// 创建定投计划
+#[tauri::command]
+pub fn create_sip_plan(
+    state: State<'_, AppState>,
+    request: CreateSipPlanRequest,
+) -> Result<SipPlan, String> {
+    let asset_id = Uuid::parse_str(&request.asset_id).map_err(|e| e.to_string())?;
+    let mut plan = SipPlan::new(
+        asset_id,
+        request.amount,
+        request.recurrence,
+        request.first_run,
+    );
+    plan.pay_from = parse_optional_uuid(request.pay_from.as_deref())?;
+    plan.end = request.end;
+
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+    db.add_sip_plan(&plan).map_err(|e| e.to_string())?;
+    Ok(plan)
+}
+
+/// 更新定投计划（金额、周期、扣款资产、启停等）
+#[tauri::command]
+pub fn update_sip_plan(state: State<'_, AppState>, plan: SipPlan) -> Result<SipPlan, String> {
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+    db.update_sip_plan(&plan).map_err(|e| e.to_string())
+}
+
+/// 获取定投计划（可按基金筛选）
+#[tauri::command]
+pub fn get_sip_plans(
+    state: State<'_, AppState>,
+    asset_id: Option<String>,
+) -> Result<Vec<SipPlan>, String> {
+    let asset_id = parse_optional_uuid(asset_id.as_deref())?;
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    db.list_sip_plans(asset_id).map_err(|e| e.to_string())
+}
+
+/// 删除定投计划
+#[tauri::command]
+pub fn delete_sip_plan(state: State<'_, AppState>, id: String) -> Result<(), String> {
+    let uuid = Uuid::parse_str(&id).map_err(|e| e.to_string())?;
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+    db.delete_sip_plan(uuid).map_err(|e| e.to_string())
+}
+
+/// 记录一期定投（默认为今天、无手续费）
+#[tauri::command]
+pub fn record_sip_installment(
+    state: State<'_, AppState>,
+    plan_id: String,
+    nav: f64,
+    fee: Option<f64>,
+    date: Option<DateTime<Utc>>,
+) -> Result<SipInstallment, String> {
+    let uuid = Uuid::parse_str(&plan_id).map_err(|e| e.to_string())?;
+    let (installment, asset_id) = {
+        let mut db = state.db.lock().map_err(|e| e.to_string())?;
+        let installment = db
+            .record_sip_installment(uuid, date.unwrap_or_else(Utc::now), nav, fee.unwrap_or(0.0))
+            .map_err(|e| e.to_string())?;
+        let asset_id = db
+            .list_sip_plans(None)
+            .map_err(|e| e.to_string())?
+            .into_iter()
+            .find(|p| p.id == uuid)
+            .map(|p| p.asset_id);
+        (installment, asset_id)
+    };
+    if let Some(asset_id) = asset_id {
+        run_rules(&state, asset_event(RuleEvent::TransactionAdded, asset_id));
+    }
+    Ok(installment)
+}
+
+/// 获取定投报告（累计投入、平均成本、收益与年化内部收益率）
+#[tauri::command]
+pub fn get_sip_report(state: State<'_, AppState>, plan_id: String) -> Result<SipReport, String> {
+    let uuid = Uuid::parse_str(&plan_id).map_err(|e| e.to_string())?;
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    db.get_sip_report(uuid, Utc::now())
+        .map_err(|e| e.to_string())
+}
+
+/// 获取外币交易的汇兑损益
+#[tauri::command]
+pub async fn get_fx_gain_loss(
+    state: State<'_, AppState>,
+    range: Option<DateRange>,
+) -> Result<FxGainLossReport, String> {
+    on_snapshot(&state, move |db| db.get_fx_gain_loss(range.as_ref())).await
+}
+
+/// 添加未来日期的计划交易
+#[tauri::command]
+pub fn add_scheduled_transaction(
+    state: State<'_, AppState>,
+    request: AddScheduledTransactionRequest,
+) -> Result<AssetTransaction, String> {
+    let uuid = Uuid::parse_str(&request.asset_id).map_err(|e| e.to_string())?;
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+    db.add_scheduled_transaction(
+        uuid,
+        request.delta,
+        request.transaction_type,
+        request.date,
+        request.note,
+    )
+    .map_err(|e| e.to_string())
+}
+
+/// 获取即将到期的计划交易（如待付账单）
+#[tauri::command]
+pub fn get_upcoming_transactions(
+    state: State<'_, AppState>,
+    until: Option<DateTime<Utc>>,
+) -> Result<Vec<AssetTransaction>, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    db.get_upcoming_transactions(until)
+        .map_err(|e| e.to_string())
+}
+
+/// 获取各资产截至某时间的预计余额
+#[tauri::command]
+pub fn get_projected_balances(
+    state: State<'_, AppState>,
+    until: DateTime<Utc>,
+) -> Result<Vec<ProjectedBalance>, String> {
+    let db = snapshot(&state)?;
+    db.get_projected_balances(until).map_err(|e| e.to_string())
+}
+
+/// 将已到期的计划交易入账
+#[tauri::command]
+pub fn post_due_transactions(state: State<'_, AppState>) -> Result<Vec<AssetTransaction>, String> {
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+    db.post_due_transactions(Utc::now())
+        .map_err(|e| e.to_string())
+}
+
+// ============ 信封预算命令 ============
+
+/// 在现金或存款资产内创建信封
+#[tauri::command]
+pub fn create_envelope(
+    state: State<'_, AppState>,
+    asset_id: String,
+    name: String,
+) -> Result<Envelope, String> {
+    let uuid = Uuid::parse_str(&asset_id).map_err(|e| e.to_string())?;
+    let envelope = Envelope::new(uuid, name);
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+    db.create_envelope(&envelope).map_err(|e| e.to_string())?;
+    Ok(envelope)
+}
+
+/// 获取资产的信封分配情况
+#[tauri::command]
+pub fn get_envelopes(
+    state: State<'_, AppState>,
+    asset_id: String,
+) -> Result<EnvelopeSummary, String> {
+    let uuid = Uuid::parse_str(&asset_id).map_err(|e| e.to_string())?;
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    db.get_envelopes(uuid).map_err(|e| e.to_string())
+}
+
+/// 从未分配金额向信封分配（负数表示退回）
+#[tauri::command]
+pub fn allocate_to_envelope(
+    state: State<'_, AppState>,
+    id: String,
+    amount: f64,
+) -> Result<Envelope, String> {
+    let uuid = Uuid::parse_str(&id).map_err(|e| e.to_string())?;
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+    db.allocate_to_envelope(uuid, amount)
+        .map_err(|e| e.to_string())
+}
+
+/// 在信封之间移动金额
+#[tauri::command]
+pub fn move_between_envelopes(
+    state: State<'_, AppState>,
+    from_id: String,
+    to_id: String,
+    amount: f64,
+) -> Result<(), String> {
+    let from = Uuid::parse_str(&from_id).map_err(|e| e.to_string())?;
+    let to = Uuid::parse_str(&to_id).map_err(|e| e.to_string())?;
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+    db.move_between_envelopes(from, to, amount)
+        .map_err(|e| e.to_string())
+}
+
+/// 删除信封
+#[tauri::command]
+pub fn delete_envelope(state: State<'_, AppState>, id: String) -> Result<(), String> {
+    let uuid = Uuid::parse_str(&id).map_err(|e| e.to_string())?;
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+    db.delete_envelope(uuid).map_err(|e| e.to_string())
+}
+
+// ============ 分摊账单命令 ============
+
+/// 记录共同支出
+#[tauri::command]
+pub fn add_shared_expense(
+    state: State<'_, AppState>,
+    request: AddSharedExpenseRequest,
+) -> Result<SharedExpense, String> {
+    let mut expense = SharedExpense::new(request.description, request.payer, request.amount);
+    if let Some(shares) = request.shares {
+        expense.shares = shares;
+    } else if let Some(participants) = request.participants {
+        let names: Vec<&str> = participants.iter().map(|p| p.as_str()).collect();
+        expense = expense.split_evenly(&names);
+    }
+    if let Some(date) = request.date {
+        expense.date = date;
+    }
+
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+    db.add_shared_expense(&expense).map_err(|e| e.to_string())?;
+    Ok(expense)
+}
+
+/// 获取所有共同支出
+#[tauri::command]
+pub fn get_shared_expenses(state: State<'_, AppState>) -> Result<Vec<SharedExpense>, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    db.list_shared_expenses().map_err(|e| e.to_string())
+}
+
+/// 删除共同支出
+#[tauri::command]
+pub fn delete_shared_expense(state: State<'_, AppState>, id: String) -> Result<(), String> {
+    let uuid = Uuid::parse_str(&id).map_err(|e| e.to_string())?;
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+    db.delete_shared_expense(uuid).map_err(|e| e.to_string())
+}
+
+/// 获取各参与者的净余额
+#[tauri::command]
+pub fn get_split_balances(state: State<'_, AppState>) -> Result<Vec<ParticipantBalance>, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    db.get_split_balances().map_err(|e| e.to_string())
+}
+
+/// 获取结清欠款的建议还款方案
+#[tauri::command]
+pub fn get_suggested_settlements(state: State<'_, AppState>) -> Result<Vec<Debt>, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    db.get_suggested_settlements().map_err(|e| e.to_string())
+}
+
+/// 获取结算记录
+#[tauri::command]
+pub fn get_settlements(state: State<'_, AppState>) -> Result<Vec<Settlement>, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    db.list_settlements().map_err(|e| e.to_string())
+}
+
+/// 记录一次还款，本人参与时可同时记入资产
+#[tauri::command]
+pub fn record_settlement(
+    state: State<'_, AppState>,
+    from: String,
+    to: String,
+    amount: f64,
+    asset_id: Option<String>,
+) -> Result<Settlement, String> {
+    let asset_id = asset_id
+        .map(|id| Uuid::parse_str(&id))
+        .transpose()
+        .map_err(|e| e.to_string())?;
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+    db.record_settlement(Settlement::new(from, to, amount), asset_id)
+        .map_err(|e| e.to_string())
+}
+
+// ============ 复式记账命令 ============
+
+/// 是否已启用复式记账
+#[tauri::command]
+pub fn is_double_entry_enabled(state: State<'_, AppState>) -> Result<bool, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    Ok(db.is_double_entry_enabled())
+}
+
+/// 启用复式记账（由现有记录迁移），返回试算平衡表
+#[tauri::command]
+pub fn enable_double_entry(state: State<'_, AppState>) -> Result<TrialBalance, String> {
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+    db.enable_double_entry().map_err(|e| e.to_string())
+}
+
+/// 停用复式记账
+#[tauri::command]
+pub fn disable_double_entry(state: State<'_, AppState>) -> Result<(), String> {
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+    db.disable_double_entry().map_err(|e| e.to_string())
+}
+
+/// 获取账户列表
+#[tauri::command]
+pub fn get_ledger_accounts(state: State<'_, AppState>) -> Result<Vec<LedgerAccount>, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    db.list_ledger_accounts().map_err(|e| e.to_string())
+}
+
+/// 添加账户
+#[tauri::command]
+pub fn add_ledger_account(
+    state: State<'_, AppState>,
+    name: String,
+    kind: AccountKind,
+) -> Result<LedgerAccount, String> {
+    let account = LedgerAccount::new(name, kind);
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+    db.add_ledger_account(&account).map_err(|e| e.to_string())?;
+    Ok(account)
+}
+
+/// 获取分录
+#[tauri::command]
+pub fn get_ledger_entries(state: State<'_, AppState>) -> Result<Vec<LedgerEntry>, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    db.list_ledger_entries().map_err(|e| e.to_string())
+}
+
+/// 记一笔分录（必须借贷平衡）
+#[tauri::command]
+pub fn post_ledger_entry(state: State<'_, AppState>, entry: LedgerEntry) -> Result<(), String> {
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+    db.post_ledger_entry(&entry).map_err(|e| e.to_string())
+}
+
+/// 获取试算平衡表
+#[tauri::command]
+pub fn get_trial_balance(state: State<'_, AppState>) -> Result<TrialBalance, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    db.get_trial_balance().map_err(|e| e.to_string())
+}
+
+// ============ 会计期间命令 ============
+
+/// 结账某月
+#[tauri::command]
+pub fn close_period(
+    state: State<'_, AppState>,
+    year: i32,
+    month: u32,
+) -> Result<ClosedPeriod, String> {
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+    db.close_period(year, month).map_err(|e| e.to_string())
+}
+
+/// 解锁已结账的期间
+#[tauri::command]
+pub fn reopen_period(state: State<'_, AppState>, year: i32, month: u32) -> Result<(), String> {
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+    db.reopen_period(year, month).map_err(|e| e.to_string())
+}
+
+/// 获取已结账的期间
+#[tauri::command]
+pub fn get_closed_periods(state: State<'_, AppState>) -> Result<Vec<ClosedPeriod>, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    db.list_closed_periods().map_err(|e| e.to_string())
+}
+
+// ============ GnuCash 导入导出命令 ============
+
+/// 导入 GnuCash XML 账簿
+#[tauri::command]
+pub async fn import_gnucash(
+    state: State<'_, AppState>,
+    path: String,
+) -> Result<GnuCashImport, String> {
+    let bytes = std::fs::read(&path).map_err(|e| e.to_string())?;
+    let content = gnucash::read_book(&bytes).map_err(|e| e.to_string())?;
+    on_db_thread(&state, move |db| gnucash::import_book(db, &content)).await
+}
+
+/// 导出为 GnuCash XML 账簿
+#[tauri::command]
+pub async fn export_gnucash(state: State<'_, AppState>, path: String) -> Result<(), String> {
+    on_snapshot(&state, move |db| {
+        let content = gnucash::export_book(db)?;
+        Ok(std::fs::write(&path, content)?)
+    })
+    .await
+}
+
+// ============ 对账单导入命令 ============
+
+/// 获取所有映射配置
+#[tauri::command]
+pub fn get_import_profiles(state: State<'_, AppState>) -> Result<Vec<MappingProfile>, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    db.list_import_profiles().map_err(|e| e.to_string())
+}
+
+/// 新建或更新映射配置
+#[tauri::command]
+pub fn save_import_profile(
+    state: State<'_, AppState>,
+    profile: MappingProfile,
+) -> Result<MappingProfile, String> {
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+    db.save_import_profile(&profile)
+        .map_err(|e| e.to_string())?;
+    Ok(profile)
+}
+
+/// 删除映射配置
+#[tauri::command]
+pub fn delete_import_profile(state: State<'_, AppState>, id: String) -> Result<(), String> {
+    let uuid = Uuid::parse_str(&id).map_err(|e| e.to_string())?;
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+    db.delete_import_profile(uuid).map_err(|e| e.to_string())
+}
+
+/// 获取可用的导入器（内置格式与插件提供的导入器）
+#[tauri::command]
+pub fn list_importers(state: State<'_, AppState>) -> Result<Vec<ImporterInfo>, String> {
+    let pm = state.plugin_manager.lock().map_err(|e| e.to_string())?;
+    Ok(import::list_importers(&pm))
+}
+
+/// 按映射配置解析对账单文件但不导入，用于导入前预览
+#[tauri::command]
+pub fn preview_statement_file(
+    state: State<'_, AppState>,
+    path: String,
+    profile_id: String,
+) -> Result<StatementFile, String> {
+    let profile = import_profile(&state, &profile_id)?;
+    read_statement_file(&state, &path, &profile)
+}
+
+/// 按映射配置导入对账单文件，内置格式以外的文件交给插件导入器解析
+#[tauri::command]
+pub async fn import_statement_file(
+    state: State<'_, AppState>,
+    path: String,
+    profile_id: String,
+) -> Result<ImportResult, String> {
+    let profile = import_profile(&state, &profile_id)?;
+    let file = read_statement_file(&state, &path, &profile)?;
+    on_db_thread(&state, move |db| import::commit_file(db, file, &profile)).await
+}
+
+/// 获取导入批次及统计
+#[tauri::command]
+pub fn list_import_batches(state: State<'_, AppState>) -> Result<Vec<ImportBatchStats>, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    db.list_import_batches().map_err(|e| e.to_string())
+}
+
+/// 撤销一次导入
+#[tauri::command]
+pub fn undo_import(state: State<'_, AppState>, batch_id: String) -> Result<UndoImport, String> {
+    let uuid = Uuid::parse_str(&batch_id).map_err(|e| e.to_string())?;
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+    db.undo_import(uuid).map_err(|e| e.to_string())
+}
+
+/// 识别对账单文件的编码与分隔符，并预览前几行
+#[tauri::command]
+pub fn sniff_statement_file(
+    path: String,
+    encoding: Option<String>,
+) -> Result<StatementSniff, String> {
+    import::sniff_file(std::path::Path::new(&path), encoding.as_deref()).map_err(|e| e.to_string())
+}
+
+/// 获取所有监视目录
+#[tauri::command]
+pub fn get_watch_folders(state: State<'_, AppState>) -> Result<Vec<WatchFolder>, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    db.list_watch_folders().map_err(|e| e.to_string())
+}
+
+/// 添加监视目录
+#[tauri::command]
+pub fn add_watch_folder(
+    state: State<'_, AppState>,
+    path: String,
+    profile_id: String,
+    archive_dir: Option<String>,
+) -> Result<WatchFolder, String> {
+    let uuid = Uuid::parse_str(&profile_id).map_err(|e| e.to_string())?;
+    let mut folder = WatchFolder::new(path, uuid);
+    folder.archive_dir = archive_dir.map(Into::into);
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+    db.add_watch_folder(&folder).map_err(|e| e.to_string())?;
+    Ok(folder)
+}
+
+/// 删除监视目录
+#[tauri::command]
+pub fn delete_watch_folder(state: State<'_, AppState>, id: String) -> Result<(), String> {
+    let uuid = Uuid::parse_str(&id).map_err(|e| e.to_string())?;
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+    db.delete_watch_folder(uuid).map_err(|e| e.to_string())
+}
+
+/// 立即扫描监视目录
+#[tauri::command]
+pub fn scan_watch_folders(state: State<'_, AppState>) -> Result<Vec<ImportResult>, String> {
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+    import::scan_watch_folders(&mut db).map_err(|e| e.to_string())
+}
+
+// ============ 成交单导入命令 ============
+
+/// 获取所有成交单映射配置
+#[tauri::command]
+pub fn get_trade_profiles(state: State<'_, AppState>) -> Result<Vec<TradeProfile>, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    db.list_trade_profiles().map_err(|e| e.to_string())
+}
+
+/// 新建或更新成交单映射配置
+#[tauri::command]
+pub fn save_trade_profile(
+    state: State<'_, AppState>,
+    profile: TradeProfile,
+) -> Result<TradeProfile, String> {
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+    db.save_trade_profile(&profile).map_err(|e| e.to_string())?;
+    Ok(profile)
+}
+
+/// 删除成交单映射配置
+#[tauri::command]
+pub fn delete_trade_profile(state: State<'_, AppState>, id: String) -> Result<(), String> {
+    let uuid = Uuid::parse_str(&id).map_err(|e| e.to_string())?;
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+    db.delete_trade_profile(uuid).map_err(|e| e.to_string())
+}
+
+/// 按成交单映射配置导入券商成交单文件
+#[tauri::command]
+pub async fn import_trade_file(
+    state: State<'_, AppState>,
+    path: String,
+    profile_id: String,
+) -> Result<TradeImport, String> {
+    let uuid = Uuid::parse_str(&profile_id).map_err(|e| e.to_string())?;
+    let profile = {
+        let db = state.db.lock().map_err(|e| e.to_string())?;
+        db.get_trade_profile(uuid)
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| "Trade profile not found".to_string())?
+    };
+    let trade_profile = profile.clone();
+    let outcome = on_db_thread(&state, move |db| {
+        import::import_trade_file(db, std::path::Path::new(&path), &trade_profile)
+    })
+    .await?;
+    let cash_account = profile.cash_account.filter(|_| outcome.imported > 0);
+    for &asset_id in outcome.updated_assets.iter().chain(&cash_account) {
+        run_rules(&state, asset_event(RuleEvent::TransactionAdded, asset_id));
+    }
+    Ok(outcome)
+}
+
+// ============ 邮件报告命令 ============
+
+/// 邮件设置（不含密码）
+#[derive(Debug, Serialize)]
+pub struct EmailSettings {
+    pub smtp: SmtpSettings,
+    /// 是否已保存 SMTP 密码
+    pub has_password: bool,
+    pub report: ReportEmailSettings,
+}
+
+/// 获取邮件设置
+#[tauri::command]
+pub fn get_email_settings(state: State<'_, AppState>) -> Result<EmailSettings, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    Ok(EmailSettings {
+        smtp: db.get_smtp_settings().map_err(|e| e.to_string())?,
+        has_password: db.has_secret(SMTP_PASSWORD),
+        report: db.get_report_email_settings().map_err(|e| e.to_string())?,
+    })
+}
+
+/// 保存邮件设置；`password` 为空表示保持不变，空字符串表示清除
+#[tauri::command]
+pub fn set_email_settings(
+    state: State<'_, AppState>,
+    smtp: SmtpSettings,
+    report: ReportEmailSettings,
+    password: Option<String>,
+) -> Result<(), String> {
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+    db.set_smtp_settings(&smtp).map_err(|e| e.to_string())?;
+    db.set_report_email_settings(&report)
+        .map_err(|e| e.to_string())?;
+    if let Some(password) = password {
+        db.set_secret(SMTP_PASSWORD, &password)
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// 生成某月的月度报告
+#[tauri::command]
+pub async fn get_monthly_report(
+    state: State<'_, AppState>,
+    year: i32,
+    month: u32,
+) -> Result<MonthlyReport, String> {
+    on_snapshot(&state, move |db| db.get_monthly_report(year, month)).await
+}
+
+/// 立即发送某月的月度报告，未指定收件人时使用设置中的收件人
+#[tauri::command]
+pub async fn send_report_email(
+    state: State<'_, AppState>,
+    year: i32,
+    month: u32,
+    recipients: Option<Vec<String>>,
+) -> Result<(), String> {
+    let email = {
+        let db = snapshot(&state)?;
+        let recipients = match recipients {
+            Some(recipients) => recipients,
+            None => {
+                db.get_report_email_settings()
+                    .map_err(|e| e.to_string())?
+                    .recipients
+            }
+        };
+        ReportEmail::prepare(&db, year, month, recipients).map_err(|e| e.to_string())?
+    };
+
+    // 网络请求期间不持有数据库锁
+    tauri::async_runtime::spawn_blocking(move || email.send())
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+/// 获取可用的报告模板（内置模板与模板目录中的自定义模板）
+#[tauri::command]
+pub fn get_report_templates(state: State<'_, AppState>) -> Result<Vec<String>, String> {
+    let config = state.config.read().map_err(|e| e.to_string())?;
+    Ok(ReportRenderer::with_templates_dir(&config.templates_dir).template_names())
+}
+
+/// 使用模板渲染报告，返回可打印或转换为 PDF 的 HTML
+#[tauri::command]
+pub fn render_report(
+    state: State<'_, AppState>,
+    template: String,
+    params: Option<ReportParams>,
+) -> Result<String, String> {
+    let (context, formatter) = {
+        let db = snapshot(&state)?;
+        let context = db
+            .report_context(&params.unwrap_or_default())
+            .map_err(|e| e.to_string())?;
+        (context, db.formatter().map_err(|e| e.to_string())?)
+    };
+    let config = state.config.read().map_err(|e| e.to_string())?;
+    ReportRenderer::with_templates_dir(&config.templates_dir)
+        .with_formatter(formatter)
+        .render(&template, &context)
+        .map_err(|e| e.to_string())
+}
+
+// ============ 资产关系命令 ============
+
+/// 添加资产关系
+#[tauri::command]
+pub fn add_asset_relation(
+    state: State<'_, AppState>,
+    request: AddRelationRequest,
+) -> Result<AssetRelation, String> {
+    let from_id = Uuid::parse_str(&request.from_id).map_err(|e| e.to_string())?;
+    let to_id = Uuid::parse_str(&request.to_id).map_err(|e| e.to_string())?;
+
+    let mut relation = AssetRelation::new(from_id, to_id, request.kind);
+    if let Some(note) = request.note {
+        relation = relation.with_note(note);
+    }
+
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+    db.add_relation(&relation).map_err(|e| e.to_string())?;
+    Ok(relation)
+}
+
+/// 删除资产关系
+#[tauri::command]
+pub fn remove_asset_relation(state: State<'_, AppState>, id: String) -> Result<(), String> {
+    let uuid = Uuid::parse_str(&id).map_err(|e| e.to_string())?;
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+    db.remove_relation(uuid).map_err(|e| e.to_string())
+}
+
+/// 获取关联资产
+#[tauri::command]
+pub fn get_related_assets(
+    state: State<'_, AppState>,
+    id: String,
+) -> Result<Vec<RelatedAsset>, String> {
+    let uuid = Uuid::parse_str(&id).map_err(|e| e.to_string())?;
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    db.get_related_assets(uuid).map_err(|e| e.to_string())
+}
+
+/// 导出资产关系图
+#[tauri::command]
+pub fn get_relation_graph(state: State<'_, AppState>) -> Result<RelationGraph, String> {
+    let db = snapshot(&state)?;
+    db.export_relation_graph().map_err(|e| e.to_string())
+}
+
+// ============ 交易模板命令 ============
+
+/// 获取所有交易模板
+#[tauri::command]
+pub fn get_templates(state: State<'_, AppState>) -> Result<Vec<TransactionTemplate>, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    db.list_templates().map_err(|e| e.to_string())
+}
+
+/// 创建交易模板
+#[tauri::command]
+pub fn create_template(
+    state: State<'_, AppState>,
+    request: CreateTemplateRequest,
+) -> Result<TransactionTemplate, String> {
+    let from_id = parse_optional_uuid(request.from_asset_id.as_deref())?;
+    let to_id = parse_optional_uuid(request.to_asset_id.as_deref())?;
+
+    let mut template = TransactionTemplate::new(request.name, from_id, to_id);
+    if let Some(amount) = request.amount {
+        template = template.with_amount(amount);
+    }
+    if let Some(prompt) = request.amount_prompt {
+        template = template.with_amount_prompt(prompt);
+    }
+    if let Some(note) = request.note {
+        template = template.with_note(note);
+    }
+
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+    db.add_template(&template).map_err(|e| e.to_string())?;
+    Ok(template)
+}
+
+/// 删除交易模板
+#[tauri::command]
+pub fn delete_template(state: State<'_, AppState>, id: String) -> Result<(), String> {
+    let uuid = Uuid::parse_str(&id).map_err(|e| e.to_string())?;
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+    db.delete_template(uuid).map_err(|e| e.to_string())
+}
+
+/// 应用交易模板
+#[tauri::command]
+pub fn apply_template(
+    state: State<'_, AppState>,
+    id: String,
+    amount: Option<f64>,
+) -> Result<Vec<AssetTransaction>, String> {
+    let uuid = Uuid::parse_str(&id).map_err(|e| e.to_string())?;
+
+    let (transactions, updated) = {
+        let mut db = state.db.lock().map_err(|e| e.to_string())?;
+        let transactions = db.apply_template(uuid, amount).map_err(|e| e.to_string())?;
+        let mut updated = Vec::new();
+        for txn in &transactions {
+            if let Some(asset) = db.get_asset(txn.asset_id).map_err(|e| e.to_string())? {
+                updated.push(asset);
+            }
+        }
+        (transactions, updated)
+    };
+
+    // 触发插件事件
+    {
+        let pm = state.plugin_manager.lock().map_err(|e| e.to_string())?;
+        for asset in updated {
+            pm.broadcast_event(&PluginEvent::AssetUpdated(asset));
+        }
+    }
+    for txn in &transactions {
+        run_rules(&state, asset_event(RuleEvent::TransactionAdded, txn.asset_id));
+    }
+
+    Ok(transactions)
+}
+
+// ============ 自动化规则命令 ============
+
+/// 获取所有自动化规则
+#[tauri::command]
+pub fn get_rules(state: State<'_, AppState>) -> Result<Vec<Rule>, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    db.list_rules().map_err(|e| e.to_string())
+}
+
+/// 新建或修改自动化规则
+#[tauri::command]
+pub fn save_rule(state: State<'_, AppState>, rule: Rule) -> Result<Rule, String> {
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+    db.save_rule(&rule).map_err(|e| e.to_string())?;
+    Ok(rule)
+}
+
+/// 删除自动化规则
+#[tauri::command]
+pub fn delete_rule(state: State<'_, AppState>, id: String) -> Result<(), String> {
+    let uuid = Uuid::parse_str(&id).map_err(|e| e.to_string())?;
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+    db.delete_rule(uuid).map_err(|e| e.to_string())
+}
+
+// ============ 提醒与通知命令 ============
+
+/// 获取所有提醒
+#[tauri::command]
+pub fn get_reminders(state: State<'_, AppState>) -> Result<Vec<Reminder>, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    db.list_reminders().map_err(|e| e.to_string())
+}
+
+/// 创建提醒
+#[tauri::command]
+pub fn create_reminder(
+    state: State<'_, AppState>,
+    request: CreateReminderRequest,
+) -> Result<Reminder, String> {
+    let mut reminder = Reminder::new(request.title, request.recurrence, request.first_due);
+
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+
+    if let Some(asset_id) = request.asset_id {
+        let uuid = Uuid::parse_str(&asset_id).map_err(|e| e.to_string())?;
+        db.get_asset(uuid)
+            .map_err(|e| e.to_string())?
+            .ok_or("Asset not found")?;
+        reminder = reminder.for_asset(uuid);
+    }
+
+    db.add_reminder(&reminder).map_err(|e| e.to_string())?;
+    Ok(reminder)
+}
+
+/// 完成提醒
+#[tauri::command]
+pub fn complete_reminder(state: State<'_, AppState>, id: String) -> Result<Reminder, String> {
+    let uuid = Uuid::parse_str(&id).map_err(|e| e.to_string())?;
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+    db.complete_reminder(uuid, Utc::now()).map_err(|e| e.to_string())
+}
+
+/// 推迟提醒
+#[tauri::command]
+pub fn snooze_reminder(
+    state: State<'_, AppState>,
+    id: String,
+    until: DateTime<Utc>,
+) -> Result<Reminder, String> {
+    let uuid = Uuid::parse_str(&id).map_err(|e| e.to_string())?;
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+    db.snooze_reminder(uuid, until).map_err(|e| e.to_string())
+}
+
+/// 删除提醒
+#[tauri::command]
+pub fn delete_reminder(state: State<'_, AppState>, id: String) -> Result<(), String> {
+    let uuid = Uuid::parse_str(&id).map_err(|e| e.to_string())?;
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+    db.delete_reminder(uuid).map_err(|e| e.to_string())
+}
+
+/// 获取通知（会先为到期提醒、达到目标价的资产和新达成的里程碑生成通知）
+#[tauri::command]
+pub fn get_notifications(
+    state: State<'_, AppState>,
+    unread_only: Option<bool>,
+) -> Result<Vec<Notification>, String> {
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+    db.check_reminders(Utc::now()).map_err(|e| e.to_string())?;
+    db.check_targets().map_err(|e| e.to_string())?;
+    db.check_milestones(Utc::now())
+        .map_err(|e| e.to_string())?;
+    db.list_notifications(unread_only.unwrap_or(false))
+        .map_err(|e| e.to_string())
+}
+
+/// 标记通知为已读
+#[tauri::command]
+pub fn mark_notification_read(state: State<'_, AppState>, id: String) -> Result<(), String> {
+    let uuid = Uuid::parse_str(&id).map_err(|e| e.to_string())?;
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+    db.mark_notification_read(uuid).map_err(|e| e.to_string())
+}
+
+/// 获取已达成的净资产里程碑（会先检测新达成的里程碑）
+#[tauri::command]
+pub fn get_milestones(state: State<'_, AppState>) -> Result<Vec<Milestone>, String> {
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+    db.check_milestones(Utc::now())
+        .map_err(|e| e.to_string())?;
+    db.list_milestones().map_err(|e| e.to_string())
+}
+
+// ============ 财务日记命令 ============
+
+/// 获取日期范围内的日记条目
+#[tauri::command]
+pub fn get_check_ins(
+    state: State<'_, AppState>,
+    start: Option<NaiveDate>,
+    end: Option<NaiveDate>,
+) -> Result<Vec<CheckIn>, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    db.list_check_ins(start, end).map_err(|e| e.to_string())
+}
+
+/// 保存日记条目（新建或修改），可附带当前的资产快照
+#[tauri::command]
+pub fn save_check_in(
+    state: State<'_, AppState>,
+    entry: CheckIn,
+    capture_snapshot: Option<bool>,
+) -> Result<CheckIn, String> {
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+    db.save_check_in(&entry, capture_snapshot.unwrap_or(false))
+        .map_err(|e| e.to_string())
+}
+
+/// 删除日记条目
+#[tauri::command]
+pub fn delete_check_in(state: State<'_, AppState>, id: String) -> Result<(), String> {
+    let uuid = Uuid::parse_str(&id).map_err(|e| e.to_string())?;
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+    db.delete_check_in(uuid).map_err(|e| e.to_string())
+}
+
+// ============ 附件命令 ============
+
+/// 附件及其文件位置
+#[derive(Debug, Serialize)]
+pub struct AttachmentResponse {
+    #[serde(flatten)]
+    pub attachment: Attachment,
+    /// 附件文件的完整路径（用于打开或预览）
+    pub path: String,
+}
+
+impl AttachmentResponse {
+    fn new(attachment: Attachment, dir: &std::path::Path) -> Result<Self, String> {
+        let path = attachment.path(dir).map_err(|e| e.to_string())?;
+        Ok(Self {
+            path: path.to_string_lossy().into_owned(),
+            attachment,
+        })
+    }
+}
+
+/// 为资产添加附件（收据、合同、照片等），文件复制到数据文件旁的附件目录
+#[tauri::command]
+pub fn add_attachment(
+    state: State<'_, AppState>,
+    asset_id: String,
+    path: String,
+    note: Option<String>,
+) -> Result<AttachmentResponse, String> {
+    let uuid = Uuid::parse_str(&asset_id).map_err(|e| e.to_string())?;
+    let dir = attachments_dir(&state)?;
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+    let attachment = db
+        .add_attachment(&dir, uuid, &path, note)
+        .map_err(|e| e.to_string())?;
+    AttachmentResponse::new(attachment, &dir)
+}
+
+/// 获取资产的附件
+#[tauri::command]
+pub fn list_attachments(
+    state: State<'_, AppState>,
+    asset_id: String,
+) -> Result<Vec<AttachmentResponse>, String> {
+    let uuid = Uuid::parse_str(&asset_id).map_err(|e| e.to_string())?;
+    let dir = attachments_dir(&state)?;
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let attachments = db.list_attachments(uuid).map_err(|e| e.to_string())?;
+    attachments
+        .into_iter()
+        .map(|attachment| AttachmentResponse::new(attachment, &dir))
+        .collect()
+}
+
+/// 删除附件
+#[tauri::command]
+pub fn remove_attachment(state: State<'_, AppState>, id: String) -> Result<(), String> {
+    let uuid = Uuid::parse_str(&id).map_err(|e| e.to_string())?;
+    let dir = attachments_dir(&state)?;
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+    db.remove_attachment(&dir, uuid)
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+// ============ 插件命令 ============
+
+/// 获取插件列表
+#[tauri::command]
+pub fn get_plugins(state: State<'_, AppState>) -> Result<Vec<PluginInfoResponse>, String> {
+    let pm = state.plugin_manager.lock().map_err(|e| e.to_string())?;
+    
+    let plugins = pm
+        .list_plugins()
+        .iter()
+        .map(|p| PluginInfoResponse::new(p, pm.metrics(&p.name)))
+        .collect();
+
+    Ok(plugins)
+}
+
+/// 重新加载插件
+#[tauri::command]
+pub fn reload_plugins(state: State<'_, AppState>) -> Result<Vec<PluginInfoResponse>, String> {
+    if state.launch.safe_mode {
+        return Err("Plugins are disabled in safe mode".to_string());
+    }
+    let mut pm = state.plugin_manager.lock().map_err(|e| e.to_string())?;
+    
+    let loaded = pm.load_all().map_err(|e| e.to_string())?;
+    refresh_metadata_schemas(&state, &pm)?;
+    
+    let plugins = loaded
+        .iter()
+        .map(|p| PluginInfoResponse::new(p, pm.metrics(&p.name)))
+        .collect();
+
+    Ok(plugins)
+}
+
+/// 设置插件启用状态
+#[tauri::command]
+pub fn set_plugin_enabled(
+    state: State<'_, AppState>,
+    name: String,
+    enabled: bool,
+) -> Result<(), String> {
+    let mut pm = state.plugin_manager.lock().map_err(|e| e.to_string())?;
+    pm.set_plugin_enabled(&name, enabled)
+        .map_err(|e| e.to_string())?;
+    refresh_metadata_schemas(&state, &pm)
+}
+
+/// 获取各资产类型的元数据 Schema（按类型代码）
+#[tauri::command]
+pub fn get_metadata_schemas(
+    state: State<'_, AppState>,
+) -> Result<HashMap<String, serde_json::Value>, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    Ok(db.metadata_schemas().clone())
+}
+
+/// 按资产类型的 Schema 校验元数据，返回所有错误（为空表示通过）
+#[tauri::command]
+pub fn validate_asset_metadata(
+    state: State<'_, AppState>,
+    asset_type: String,
+    metadata: serde_json::Value,
+) -> Result<Vec<SchemaViolation>, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    Ok(db.validate_metadata(&AssetType::parse(&asset_type), &metadata))
+}
+
+// ============ 二进制接口命令 ============
+
+/// IPC 能力，前端据此决定是否使用二进制接口
+#[derive(Debug, Serialize)]
+pub struct IpcCapabilities {
+    /// 支持的二进制格式
+    pub binary_formats: Vec<String>,
+}
+
+/// 获取 IPC 能力
+#[tauri::command]
+pub fn get_ipc_capabilities() -> IpcCapabilities {
+    IpcCapabilities {
+        binary_formats: vec![binary::BINARY_FORMAT.to_string()],
+    }
+}
+
+/// 获取所有资产（MessagePack 编码）
+#[tauri::command]
+pub fn get_assets_binary(state: State<'_, AppState>) -> Result<Response, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let assets = db
+        .list_assets(SortBy::default(), SortOrder::default())
+        .map_err(|e| e.to_string())?;
+    binary_response(&assets)
+}
+
+/// 获取资产的交易历史（MessagePack 编码）
+#[tauri::command]
+pub fn get_transactions_binary(
+    state: State<'_, AppState>,
+    asset_id: String,
+    query: Option<TransactionQuery>,
+) -> Result<Response, String> {
+    let uuid = Uuid::parse_str(&asset_id).map_err(|e| e.to_string())?;
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let transactions = db
+        .get_transactions(uuid, &query.unwrap_or_default())
+        .map_err(|e| e.to_string())?;
+    binary_response(&transactions)
+}
+
+/// 获取资产每天的日终价值（MessagePack 编码）
+#[tauri::command]
+pub fn get_daily_balances_binary(
+    state: State<'_, AppState>,
+    asset_id: String,
+    range: DateRange,
+) -> Result<Response, String> {
+    let uuid = Uuid::parse_str(&asset_id).map_err(|e| e.to_string())?;
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let balances = db
+        .get_daily_balances(uuid, &range)
+        .map_err(|e| e.to_string())?;
+    binary_response(&balances)
+}
+
+// ============ 备份命令 ============
+
+/// 获取定时备份设置
+#[tauri::command]
+pub fn get_backup_settings(state: State<'_, AppState>) -> Result<BackupSettings, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    db.get_backup_settings().map_err(|e| e.to_string())
+}
+
+/// 保存定时备份设置
+#[tauri::command]
+pub fn set_backup_settings(
+    state: State<'_, AppState>,
+    settings: BackupSettings,
+) -> Result<(), String> {
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+    db.set_backup_settings(&settings).map_err(|e| e.to_string())
+}
+
+/// 列出备份目录中的备份（最新的在前）
+#[tauri::command]
+pub fn list_backups(state: State<'_, AppState>) -> Result<Vec<BackupInfo>, String> {
+    let backups_dir = backups_dir(&state)?;
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    db.list_backups(&backups_dir).map_err(|e| e.to_string())
+}
+
+/// 从备份恢复，返回恢复前数据的备份，并通知窗口刷新
+#[tauri::command]
+pub fn restore_backup(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    path: String,
+) -> Result<BackupInfo, String> {
+    let backups_dir = backups_dir(&state)?;
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+    let previous = db
+        .restore_backup(&backups_dir, &path)
+        .map_err(|e| e.to_string())?;
+    crate::emit(&app, "database://reloaded", ());
+    Ok(previous)
+}
+
+// ============ 便携存档命令 ============
+
+/// 导出便携存档（资产、交易、设置与插件文件），用于迁移到其他设备
+#[tauri::command]
+pub async fn export_archive(
+    state: State<'_, AppState>,
+    path: String,
+) -> Result<ArchiveSummary, String> {
+    let plugins_dir = plugins_dir(&state)?;
+    on_snapshot(&state, move |db| {
+        storage::export_archive(
+            db,
+            std::path::Path::new(&path),
+            Some(std::path::Path::new(&plugins_dir)),
+        )
+    })
+    .await
+}
+
+/// 导入便携存档并通知窗口刷新；`include_plugins` 为 true 时才安装存档中的插件文件
+/// （插件会被执行，默认不安装），写入了插件文件时重新加载插件
+#[tauri::command]
+pub async fn import_archive(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    path: String,
+    mode: ArchiveMode,
+    include_plugins: Option<bool>,
+) -> Result<ArchiveSummary, String> {
+    let plugins_dir = if include_plugins.unwrap_or(false) {
+        Some(std::path::PathBuf::from(plugins_dir(&state)?))
+    } else {
+        None
+    };
+    let summary = on_db_thread(&state, move |db| {
+        storage::import_archive(
+            db,
+            std::path::Path::new(&path),
+            mode,
+            plugins_dir.as_deref(),
+        )
+    })
+    .await?;
+    if summary.plugin_files > 0 && !state.launch.safe_mode {
+        let mut pm = state.plugin_manager.lock().map_err(|e| e.to_string())?;
+        pm.load_all().map_err(|e| e.to_string())?;
+        refresh_metadata_schemas(&state, &pm)?;
+    }
+    crate::emit(&app, "database://reloaded", ());
+    Ok(summary)
+}
+
+/// 合并另一个数据文件，逐个选择时返回尚未处理的冲突
+#[tauri::command]
+pub async fn merge_database(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    path: String,
+    strategy: MergeStrategy,
+) -> Result<MergeReport, String> {
+    let report = on_db_thread(&state, move |db| {
+        storage::merge_file(db, std::path::Path::new(&path), strategy)
+    })
+    .await?;
+    crate::emit(&app, "database://reloaded", ());
+    Ok(report)
+}
+
+// ============ 配置命令 ============
+
+/// 获取当前配置
+#[tauri::command]
+pub fn get_config(state: State<'_, AppState>) -> Result<AppConfig, String> {
+    Ok(state.config.read().map_err(|e| e.to_string())?.clone())
+}
+
+/// 应用新配置（数据文件、插件目录等），无需重启
+#[tauri::command]
+pub fn apply_config(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    config: AppConfig,
+) -> Result<(), String> {
+    state.apply_config(&app, config)
+}
+
+/// 获取首次运行状态
+#[tauri::command]
+pub fn get_first_run_state(state: State<'_, AppState>) -> FirstRunState {
+    FirstRunState::detect(&state.paths)
+}
+
+/// 完成首次运行设置：创建数据文件与设置并切换到新位置
+#[tauri::command]
+pub fn complete_first_run(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    choices: FirstRunChoices,
+) -> Result<AppConfig, String> {
+    if !FirstRunState::detect(&state.paths).first_run {
+        return Err("Setup has already been completed".to_string());
+    }
+    let config = first_run::complete(&state.paths, &choices).map_err(|e| e.to_string())?;
+    state.apply_config(&app, config.clone())?;
+    Ok(config)
+}
+
+// ============ 更新命令 ============
+
+/// 可用的新版本
+#[derive(Debug, Serialize)]
+pub struct UpdateInfo {
+    pub version: String,
+    pub current_version: String,
+    pub notes: Option<String>,
+    pub date: Option<String>,
+}
+
+/// 下载新版本的进度
+#[derive(Debug, Clone, Serialize)]
+pub struct UpdateProgress {
+    /// 已下载的字节数
+    pub downloaded: u64,
+    /// 总字节数（服务器未提供时为空）
+    pub total: Option<u64>,
+}
+
+/// 启动时的数据迁移状态，迁移完成前界面显示进度而不加载数据
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct MigrationStatus {
+    /// 是否正在迁移
+    pub running: bool,
+    /// 最近开始的一步
+    pub progress: Option<MigrationProgress>,
+    /// 迁移失败的原因
+    pub error: Option<String>,
+}
+
+/// 启动流程中的步骤
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum StartupStep {
+    OpenDatabase,
+    Migrations,
+    Settings,
+    Schedulers,
+    AppStarted,
+}
+
+/// 启动流程中出错的一步
+#[derive(Debug, Clone, Serialize)]
+pub struct StartupError {
+    pub step: StartupStep,
+    pub message: String,
+}
+
+/// 启动流程的状态：出错的步骤报告给界面，不中止启动
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct StartupStatus {
+    /// 是否已完成（数据迁移在后台继续进行）
+    pub finished: bool,
+    pub errors: Vec<StartupError>,
+    /// 数据文件丢失或无法打开时进入恢复模式，处理前使用内存数据库
+    pub recovery: Option<RecoveryInfo>,
+}
+
+/// 检查是否有新版本
+#[tauri::command]
+pub async fn check_for_update(app: tauri::AppHandle) -> Result<Option<UpdateInfo>, String> {
+    let update = app
+        .updater()
+        .map_err(|e| e.to_string())?
+        .check()
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(update.map(|update| UpdateInfo {
+        version: update.version,
+        current_version: update.current_version,
+        notes: update.body,
+        date: update.date.map(|date| date.to_string()),
+    }))
+}
+
+/// 安装新版本并重启：先备份数据并校验，备份失败时不更新；重启后在界面加载前执行数据迁移
+#[tauri::command]
+pub async fn install_update(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let update = app
+        .updater()
+        .map_err(|e| e.to_string())?
+        .check()
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "No update available".to_string())?;
+
+    let backup = backup_before_update(&state)?;
+    crate::emit(&app, "update://backup-created", backup);
+
+    let mut downloaded = 0u64;
+    update
+        .download_and_install(
+            |chunk, total| {
+                downloaded += chunk as u64;
+                crate::emit(&app, "update://progress", UpdateProgress { downloaded, total });
+            },
+            || crate::emit(&app, "update://downloaded", ()),
+        )
+        .await
+        .map_err(|e| e.to_string())?;
+    app.restart()
+}
+
+/// 获取启动时的数据迁移状态
+#[tauri::command]
+pub fn get_migration_status(state: State<'_, AppState>) -> Result<MigrationStatus, String> {
+    Ok(state.migration.lock().map_err(|e| e.to_string())?.clone())
+}
+
+/// 获取启动流程的状态与出错的步骤
+#[tauri::command]
+pub fn get_startup_status(state: State<'_, AppState>) -> Result<StartupStatus, String> {
+    Ok(state.startup.lock().map_err(|e| e.to_string())?.clone())
+}
+
+/// 取走启动时打开的链接（窗口加载后调用，之后的链接通过 `deep-link://navigate` 事件通知）
+#[tauri::command]
+pub fn take_deep_link(state: State<'_, AppState>) -> Result<Option<DeepLink>, String> {
+    Ok(state.pending_link.lock().map_err(|e| e.to_string())?.take())
+}
+
+/// 启动时的命令行参数（如是否为安全模式）
+#[tauri::command]
+pub fn get_launch_args(state: State<'_, AppState>) -> crate::LaunchArgs {
+    state.launch.clone()
+}
+
+// ============ 档案命令 ============
+
+/// 档案列表与当前使用的档案
+#[derive(Debug, Serialize)]
+pub struct ProfilesResponse {
+    pub profiles: Vec<ProfileInfo>,
+    /// 当前数据文件所属的档案（使用配置中的数据文件时为空）
+    pub active: Option<String>,
+}
+
+/// 获取档案列表
+#[tauri::command]
+pub fn list_profiles(state: State<'_, AppState>) -> Result<ProfilesResponse, String> {
+    let profiles = state.paths.profiles();
+    let config = state.config.read().map_err(|e| e.to_string())?;
+    Ok(ProfilesResponse {
+        profiles: profiles.list().map_err(|e| e.to_string())?,
+        active: profiles.find(std::path::Path::new(&config.db_path)),
+    })
+}
+
+/// 新建档案及其空的数据文件
+#[tauri::command]
+pub fn create_profile(state: State<'_, AppState>, name: String) -> Result<ProfileInfo, String> {
+    state
+        .paths
+        .profiles()
+        .create(name.trim())
+        .map_err(|e| e.to_string())
+}
+
+/// 切换到档案的数据文件（为空时回到配置中的数据文件），只用于本次运行
+#[tauri::command]
+pub fn switch_profile(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    name: Option<String>,
+) -> Result<(), String> {
+    let profiles = state.paths.profiles();
+    let mut config = state.config.read().map_err(|e| e.to_string())?.clone();
+    let (db_path, backups_dir) = match &name {
+        Some(name) => {
+            let info = profiles
+                .info(name)
+                .map_err(|e| e.to_string())?
+                .ok_or_else(|| format!("Profile not found: {}", name))?;
+            let backups_dir = profiles.backups_dir(name).map_err(|e| e.to_string())?;
+            (info.db_path, backups_dir.to_string_lossy().into_owned())
+        }
+        None => {
+            let file = state.paths.load_config();
+            (file.db_path, file.backups_dir)
+        }
+    };
+    config.db_path = db_path;
+    config.backups_dir = backups_dir;
+    state.apply_config(&app, config)?;
+    tracing::info!("Switched to profile {:?}", name);
+    crate::emit(&app, "profile://changed", &name);
+    Ok(())
+}
+
+// ============ 只读查看命令 ============
+
+/// 以只读方式打开其他数据文件（如备份或共享的文件）查看，修改会被拒绝；配置不变
+#[tauri::command]
+pub fn open_read_only_file(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    path: String,
+) -> Result<(), String> {
+    if state.in_recovery()? {
+        return Err("Resolve the data file problem first".to_string());
+    }
+    if state.launch.safe_mode {
+        return Err("Data file cannot be switched in safe mode".to_string());
+    }
+    let db = Database::open_read_only(&path).map_err(|e| e.to_string())?;
+    let schemas = state
+        .plugin_manager
+        .lock()
+        .map_err(|e| e.to_string())?
+        .metadata_schemas();
+    {
+        let mut current = state.db.lock().map_err(|e| e.to_string())?;
+        state.replace_database(&app, &mut current, db)?;
+        current.set_plugin_metadata_schemas(schemas);
+    }
+    tracing::info!("Opened {} read-only", path);
+    crate::emit(&app, "database://reloaded", ());
+    Ok(())
+}
+
+/// 结束只读查看，重新打开配置中的数据文件
+#[tauri::command]
+pub fn close_read_only_file(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    if !state.db.lock().map_err(|e| e.to_string())?.is_read_only() {
+        return Err("No file is open read-only".to_string());
+    }
+    let config = state.config.read().map_err(|e| e.to_string())?.clone();
+    state.apply_config(&app, config)
+}
+
+// ============ 启动恢复命令 ============
+
+/// 恢复模式：用备份替换数据文件并打开，原文件改名保留
+#[tauri::command]
+pub fn recover_from_backup(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    path: String,
+) -> Result<(), String> {
+    let config = recovery_config(&state)?;
+    storage::restore_data_file(
+        std::path::Path::new(&config.db_path),
+        std::path::Path::new(&config.backups_dir),
+        std::path::Path::new(&path),
+    )
+    .map_err(|e| e.to_string())?;
+    finish_recovery(&app, &state, config)
+}
+
+/// 恢复模式：新建空的数据文件并打开，原文件（如有）改名保留
+#[tauri::command]
+pub fn recover_with_new_file(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let config = recovery_config(&state)?;
+    storage::reset_data_file(std::path::Path::new(&config.db_path)).map_err(|e| e.to_string())?;
+    finish_recovery(&app, &state, config)
+}
+
+/// 恢复模式：打开其他位置已有的数据文件（如移动后的文件），并记入配置
+#[tauri::command]
+pub fn recover_with_file(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    path: String,
+) -> Result<(), String> {
+    let mut config = recovery_config(&state)?;
+    if !std::path::Path::new(&path).is_file() {
+        return Err(format!("Data file not found: {}", path));
+    }
+    config.db_path = path.clone();
+    // 便携模式下数据文件限定在程序旁的目录中
+    if state.paths.enforce(config.clone()).db_path != path {
+        return Err("Portable mode only uses data files next to the program".to_string());
+    }
+    finish_recovery(&app, &state, config)
+}
+
+/// 获取本地使用统计及功能提示（统计只保存在本机）
+#[tauri::command]
+pub fn get_usage_stats(state: State<'_, AppState>) -> Result<UsageSummary, String> {
+    let usage = state.usage.lock().map_err(|e| e.to_string())?;
+    Ok(usage.stats().summary())
+}
+
+// ============ 辅助函数 ============
+
+fn binary_response<T: Serialize>(value: &T) -> Result<Response, String> {
+    binary::to_msgpack(value)
+        .map(Response::new)
+        .map_err(|e| e.to_string())
+}
+
+fn parse_optional_uuid(s: Option<&str>) -> Result<Option<Uuid>, String> {
+    s.map(|id| Uuid::parse_str(id).map_err(|e| e.to_string()))
+        .transpose()
+}
+
+/// 恢复模式下的当前配置，不在恢复模式时拒绝
+fn recovery_config(state: &AppState) -> Result<AppConfig, String> {
+    if !state.in_recovery()? {
+        return Err("Not in recovery mode".to_string());
+    }
+    Ok(state.config.read().map_err(|e| e.to_string())?.clone())
+}
+
+/// 退出恢复模式并打开数据文件，失败时保持恢复模式
+fn finish_recovery(
+    app: &tauri::AppHandle,
+    state: &AppState,
+    config: AppConfig,
+) -> Result<(), String> {
+    let recovery = state
+        .startup
+        .lock()
+        .map_err(|e| e.to_string())?
+        .recovery
+        .take();
+    if let Err(e) = state.apply_config(app, config) {
+        state.startup.lock().map_err(|e| e.to_string())?.recovery = recovery;
+        return Err(e);
+    }
+    tracing::info!("Recovered from data file problem");
+    crate::emit(app, "startup://recovered", ());
+    Ok(())
+}
+
+/// 数据文件旁的附件目录
+fn attachments_dir(state: &AppState) -> Result<std::path::PathBuf, String> {
+    let config = state.config.read().map_err(|e| e.to_string())?;
+    Ok(attachment::attachments_dir(std::path::Path::new(
+        &config.db_path,
+    )))
+}
+
+/// 永久删除资产后清理不再被引用的附件文件，失败时只记录日志
+fn prune_attachment_files(db: &Database, dir: &std::path::Path) {
+    match db.prune_attachment_files(dir) {
+        Ok(0) => {}
+        Ok(removed) => tracing::info!("Removed {} unused attachment files", removed),
+        Err(e) => tracing::warn!("Failed to clean up attachment files: {}", e),
+    }
+}
+
+/// 配置中的备份目录
+fn backups_dir(state: &AppState) -> Result<String, String> {
+    let config = state.config.read().map_err(|e| e.to_string())?;
+    Ok(config.backups_dir.clone())
+}
+
+fn plugins_dir(state: &AppState) -> Result<String, String> {
+    let config = state.config.read().map_err(|e| e.to_string())?;
+    Ok(config.plugins_dir.clone())
+}
+
+/// 更新前写入未保存的修改（手动保存模式下须由用户先保存），再备份到备份目录并校验
+fn backup_before_update(state: &AppState) -> Result<BackupInfo, String> {
+    let backups_dir = backups_dir(state)?;
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+    if db.has_unsaved_changes() {
+        if db.save_status().mode == SaveMode::Manual {
+            return Err("Save changes before updating".to_string());
+        }
+        db.save_now().map_err(|e| e.to_string())?;
+    }
+    db.backup_to(&backups_dir).map_err(|e| e.to_string())
+}
+
+/// 是否使用模拟数据源
+fn use_mock_providers(state: &AppState) -> Result<bool, String> {
+    Ok(state
+        .config
+        .read()
+        .map_err(|e| e.to_string())?
+        .mock_providers)
+}
+
+/// 汇率数据源（配置为模拟数据源时不联网）
+fn exchange_rate_provider(state: &AppState) -> Result<Box<dyn ExchangeRateProvider>, String> {
+    Ok(if use_mock_providers(state)? {
+        Box::new(MockExchangeRateProvider)
+    } else {
+        Box::new(FrankfurterProvider::new())
+    })
+}
+
+/// 证券代码数据源（配置为模拟数据源时不联网）
+fn symbol_provider(state: &AppState) -> Result<Box<dyn SymbolProvider>, String> {
+    Ok(if use_mock_providers(state)? {
+        Box::new(MockSymbolProvider)
+    } else {
+        Box::new(YahooSymbolProvider::new())
+    })
+}
+
+/// 分红数据源（配置为模拟数据源时不联网）
+fn dividend_provider(state: &AppState) -> Result<Box<dyn DividendProvider>, String> {
+    Ok(if use_mock_providers(state)? {
+        Box::new(MockSymbolProvider)
+    } else {
+        Box::new(YahooSymbolProvider::new())
+    })
+}
+
+fn asset_event(event: RuleEvent, asset_id: Uuid) -> RuleSignal {
+    RuleSignal::Event { event, asset_id }
+}
+
+/// 按信号执行自动化规则，再执行规则中的插件调用；失败只记录日志，不影响触发它的命令
+pub(crate) fn run_rules(state: &AppState, signal: RuleSignal) {
+    let runs = match state.db.lock() {
+        Ok(mut db) => db.run_rules(signal, Utc::now()),
+        Err(_) => return,
+    };
+    let runs = match runs {
+        Ok(runs) => runs,
+        Err(e) => {
+            tracing::warn!("Failed to run rules: {}", e);
+            return;
+        }
+    };
+    let Ok(pm) = state.plugin_manager.lock() else {
+        return;
+    };
+    for call in runs.iter().flat_map(|run| &run.plugin_calls) {
+        if let Err(e) = pm.run_handler(&call.plugin, &call.handler, &call.args) {
+            tracing::warn!("Rule plugin call {}.{} failed: {}", call.plugin, call.handler, e);
+        }
+    }
+}
+
+/// 取得快照后立即释放数据库锁，耗时的分析与导出期间其他命令仍可修改数据
+fn snapshot(state: &AppState) -> Result<Snapshot, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    Ok(db.snapshot())
+}
+
+/// 在数据库线程上执行耗时的修改，等待期间不占用界面线程
+async fn on_db_thread<T, F>(state: &AppState, f: F) -> Result<T, String>
+where
+    T: Send + 'static,
+    F: FnOnce(&mut Database) -> Result<T, StorageError> + Send + 'static,
+{
+    state.async_db.call(f).await.map_err(|e| e.to_string())
+}
+
+/// 在后台线程中基于快照执行耗时的查询与导出，期间其他命令仍可修改数据
+async fn on_snapshot<T, F>(state: &AppState, f: F) -> Result<T, String>
+where
+    T: Send + 'static,
+    F: FnOnce(&Database) -> Result<T, StorageError> + Send + 'static,
+{
+    let snapshot = state.async_db.snapshot().await.map_err(|e| e.to_string())?;
+    tauri::async_runtime::spawn_blocking(move || f(&snapshot))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+fn refresh_metadata_schemas(state: &AppState, pm: &PluginManager) -> Result<(), String> {
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+    db.set_plugin_metadata_schemas(pm.metadata_schemas());
+    Ok(())
+}
+
+fn asset_from_request(request: CreateAssetRequest) -> Asset {
+    let asset_type = AssetType::parse(&request.asset_type);
+    let currency = request
+        .currency
+        .as_ref()
+        .map(|c| Currency::from_code(c))
+        .unwrap_or_default();
+
+    let mut asset = Asset::new(request.name, asset_type, request.value)
+        .with_currency(currency);
+
+    if let Some(desc) = request.description {
+        asset = asset.with_description(desc);
+    }
+
+    if let Some(tags) = request.tags {
+        asset = asset.with_tags(tags);
+    }
+
+    if let Some(formula) = request.formula {
+        asset = asset.with_formula(formula);
+    }
+
+    if let Some(cost_basis) = request.cost_basis {
+        asset = asset.with_cost_basis(cost_basis);
+    }
+
+    if let Some(target_price) = request.target_price {
+        asset = asset.with_target_price(target_price);
+    }
+
+    if let Some(external_id) = request.external_id {
+        asset = asset.with_external_id(external_id);
+    }
+
+    if let Some(metadata) = request.metadata {
+        asset = asset.with_metadata(metadata);
+    }
+
+    if let Some(display) = request.display {
+        asset = asset.with_display(display);
+    }
+
+    if let Some(currency) = request.display_currency {
+        asset = asset.with_display_currency(Currency::from_code(&currency));
+    }
+
+    asset
+}
+
+fn import_profile(state: &AppState, id: &str) -> Result<MappingProfile, String> {
+    let uuid = Uuid::parse_str(id).map_err(|e| e.to_string())?;
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    db.get_import_profile(uuid)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "Mapping profile not found".to_string())
+}
+
+/// 解析对账单文件（插件导入器执行时只持有插件管理器，不持有数据库）
+fn read_statement_file(
+    state: &AppState,
+    path: &str,
+    profile: &MappingProfile,
+) -> Result<StatementFile, String> {
+    let pm = state.plugin_manager.lock().map_err(|e| e.to_string())?;
+    import::read_file(std::path::Path::new(path), profile, Some(&pm)).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::de::DeserializeOwned;
+    use serde_json::json;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use tauri::test::{mock_builder, mock_context, noop_assets, MockRuntime};
+    use tauri::{App, Manager};
+
+    /// 记录收到的事件的模拟插件
+    const RECORDER_PLUGIN: &str = r#"
+        local plugin = { name = "recorder", version = "0.0.0" }
+        local events = {}
+        function plugin.on_asset_created(asset) table.insert(events, "created") end
+        function plugin.on_asset_updated(asset) table.insert(events, "updated") end
+        function plugin.on_asset_deleted(id) table.insert(events, "deleted") end
+        function plugin.on_app_closing() table.insert(events, "closing") end
+        function plugin.events() return events end
+        return plugin
+    "#;
+
+    /// 不启动窗口的应用：内存数据库与模拟插件
+    fn mock_app() -> (App<MockRuntime>, PathBuf) {
+        let dir = std::env::temp_dir().join(format!("asset-manager-app-{}", Uuid::new_v4()));
+        let plugin_dir = dir.join("plugins").join("recorder");
+        fs::create_dir_all(&plugin_dir).unwrap();
+        fs::write(plugin_dir.join("init.lua"), RECORDER_PLUGIN).unwrap();
+
+        let app = mock_builder()
+            .manage(AppState::for_tests(&dir))
+            .build(mock_context(noop_assets()))
+            .unwrap();
+        (app, dir)
+    }
+
+    /// 按前端传参的方式构造请求
+    fn request<T: DeserializeOwned>(value: serde_json::Value) -> T {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn recorded_events(state: &AppState) -> serde_json::Value {
+        state
+            .plugin_manager
+            .lock()
+            .unwrap()
+            .run_handler("recorder", "events", &serde_json::Value::Null)
+            .unwrap()
+    }
+
+    fn cleanup(dir: &Path) {
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_asset_commands() {
+        let (app, dir) = mock_app();
+        assert_eq!(get_plugins(app.state()).unwrap().len(), 1);
+
+        let asset = create_asset(
+            app.state(),
+            request(json!({ "name": "储蓄卡", "asset_type": "bank_deposit", "value": 1000.0 })),
+        )
+        .unwrap();
+        let updated = update_asset(
+            app.state(),
+            request(json!({ "id": asset.id.to_string(), "value": 1500.0 })),
+        )
+        .unwrap();
+        assert_eq!(updated.value, 1500.0);
+        assert_eq!(get_assets(app.state(), None, None).unwrap().len(), 1);
+
+        delete_asset(app.state(), asset.id.to_string()).unwrap();
+        assert!(get_assets(app.state(), None, None).unwrap().is_empty());
+        assert_eq!(
+            recorded_events(&app.state()),
+            json!(["created", "updated", "deleted"])
+        );
+        cleanup(&dir);
+    }
+
+    #[test]
+    fn test_shutdown() {
+        let (app, dir) = mock_app();
+        create_asset(
+            app.state(),
+            request(json!({ "name": "现金", "asset_type": "cash", "value": 1.0 })),
+        )
+        .unwrap();
+
+        // 关闭流程只执行一次
+        crate::shutdown(app.handle());
+        crate::shutdown(app.handle());
+        let state = app.state::<AppState>();
+        assert!(state.is_closing());
+        assert_eq!(recorded_events(&state), json!(["created", "closing"]));
+        cleanup(&dir);
+    }
+
+    #[test]
+    fn test_command_validation() {
+        let (app, dir) = mock_app();
+        assert!(delete_asset(app.state(), "not-a-uuid".into()).is_err());
+        assert!(update_asset(
+            app.state(),
+            request(json!({ "id": Uuid::new_v4().to_string(), "name": "不存在" })),
+        )
+        .is_err());
+        assert!(set_assumptions(
+            app.state(),
+            Assumptions {
+                inflation: 2.0,
+                ..Default::default()
+            },
+        )
+        .is_err());
+        // 失败的命令不修改数据
+        assert!(get_assets(app.state(), None, None).unwrap().is_empty());
+        cleanup(&dir);
+    }
+
+    #[test]
+    fn test_concurrent_commands() {
+        let (app, dir) = mock_app();
+        let state = app.state::<AppState>();
+        std::thread::scope(|s| {
+            for t in 0..4 {
+                let state = state.clone();
+                s.spawn(move || {
+                    for i in 0..10 {
+                        create_asset(
+                            state.clone(),
+                            request(json!({
+                                "name": format!("资产 {}-{}", t, i),
+                                "asset_type": "cash",
+                                "value": 1.0,
+                            })),
+                        )
+                        .unwrap();
+                    }
+                });
+            }
+        });
+        assert_eq!(get_assets(app.state(), None, None).unwrap().len(), 40);
+        assert_eq!(
+            get_summary(app.state(), None, None).unwrap().total_value,
+            40.0
+        );
 
-fn parse_asset_type(s: &str) -> AssetType {
-    match s.to_lowercase().as_str() {
-        "cash" => AssetType::Cash,
-        "bank_deposit" | "bank" => AssetType::BankDeposit,
-        "stock" => AssetType::Stock,
-        "fund" => AssetType::Fund,
-        "bond" => AssetType::Bond,
-        "real_estate" | "property" => AssetType::RealEstate,
-        "vehicle" | "car" => AssetType::Vehicle,
-        "crypto" | "cryptocurrency" => AssetType::Crypto,
-        "precious_metal" | "gold" | "silver" => AssetType::PreciousMetal,
-        other => AssetType::Other(other.to_string()),
-    }
-}
-
-fn parse_currency(s: &str) -> Currency {
-    match s.to_uppercase().as_str() {
-        "CNY" | "RMB" => Currency::CNY,
-        "USD" => Currency::USD,
-        "EUR" => Currency::EUR,
-        "GBP" => Currency::GBP,
-        "JPY" => Currency::JPY,
-        "HKD" => Currency::HKD,
-        other => Currency::Other(other.to_string()),
+        // 锁被持有者的 panic 毒化后命令返回错误，不会跟着 panic
+        let _ = std::thread::scope(|s| {
+            s.spawn(|| {
+                let _db = state.db.lock().unwrap();
+                panic!("poison the database lock");
+            })
+            .join()
+        });
+        assert!(get_assets(app.state(), None, None).is_err());
+        cleanup(&dir);
     }
 }
@@ -4,16 +4,250 @@
 
 mod commands;
 
-use asset_manager_core::{AppConfig, Database, PluginManager};
-use std::sync::Mutex;
+use asset_manager_core::deep_link::{self, DeepLink};
+use asset_manager_core::first_run::FirstRunState;
+use asset_manager_core::plugin::{
+    AppContext, PluginEvent, SharedAppContext, APP_CLOSING_TIMEOUT_SECS,
+};
+use asset_manager_core::portable::{AppPaths, PORTABLE_ARG};
+use asset_manager_core::storage::{
+    self, AsyncDatabase, RecoveryInfo, ReloadOutcome, SaveMode, StorageError,
+    AUTOSAVE_CHECK_INTERVAL_SECS, BACKUP_CHECK_INTERVAL_SECS, EXTERNAL_CHECK_INTERVAL_SECS,
+    LOCK_HEARTBEAT_SECS,
+};
+use commands::{MigrationStatus, StartupError, StartupStatus, StartupStep};
+use asset_manager_core::rules::{RuleSignal, RULES_CHECK_INTERVAL_SECS};
+use asset_manager_core::usage::UsageTracker;
+use asset_manager_core::{email, import, AppConfig, Database, PluginManager};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Mutex, RwLock};
+use std::time::Duration;
+use tauri::{Emitter, Manager};
 use tracing::info;
 use tracing_subscriber::{fmt, prelude::*, EnvFilter};
 
 /// 应用程序状态
 pub struct AppState {
-    pub db: Mutex<Database>,
+    pub db: Arc<Mutex<Database>>,
+    /// 在数据库线程上执行耗时操作（导入、合并、存档、分析），与 `db` 共用同一个数据库
+    pub async_db: AsyncDatabase,
     pub plugin_manager: Mutex<PluginManager>,
-    pub config: AppConfig,
+    /// 插件读取的设置与本位币，随设置变化更新
+    pub app_context: SharedAppContext,
+    pub config: RwLock<AppConfig>,
+    /// 配置文件位置与便携模式
+    pub paths: AppPaths,
+    pub migration: Mutex<MigrationStatus>,
+    /// 启动流程的状态
+    pub startup: Mutex<StartupStatus>,
+    /// 本地使用统计
+    pub usage: Mutex<UsageTracker>,
+    /// 已开始关闭，后台任务不再开始新的工作
+    pub closing: AtomicBool,
+    /// 启动时的命令行参数
+    pub launch: LaunchArgs,
+    /// 启动时打开的链接，窗口加载后取走
+    pub pending_link: Mutex<Option<DeepLink>>,
+}
+
+/// 指定本次使用的数据文件（不写入配置文件）：`--db <路径>`
+const DB_ARG: &str = "--db";
+
+/// 使用命名档案：`--profile <档案名>`
+const PROFILE_ARG: &str = "--profile";
+
+/// 安全模式：只读打开数据文件，不加载插件、不启动定时任务、不使用自定义配置，用于排查崩溃原因
+const SAFE_MODE_ARG: &str = "--safe-mode";
+
+/// 桌面端的命令行参数，供快捷方式与技术支持控制启动方式
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize)]
+pub struct LaunchArgs {
+    /// 本次使用的数据文件
+    pub db: Option<String>,
+    /// 档案名
+    pub profile: Option<String>,
+    /// 是否为安全模式
+    pub safe_mode: bool,
+}
+
+impl LaunchArgs {
+    /// 解析命令行参数（值可写在下一个参数或 `=` 之后），无法识别的参数记录日志后忽略
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Self {
+        let mut parsed = Self::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let (name, inline) = match arg.split_once('=') {
+                Some((name, value)) => (name, Some(value.to_string())),
+                None => (arg.as_str(), None),
+            };
+            match name {
+                DB_ARG | PROFILE_ARG => {
+                    let value = inline
+                        .or_else(|| args.next())
+                        .filter(|v| !v.is_empty() && !v.starts_with("--"));
+                    let Some(value) = value else {
+                        tracing::warn!("Missing value for {}", name);
+                        continue;
+                    };
+                    if name == DB_ARG {
+                        parsed.db = Some(value);
+                    } else {
+                        parsed.profile = Some(value);
+                    }
+                }
+                SAFE_MODE_ARG => parsed.safe_mode = true,
+                PORTABLE_ARG => {}
+                // 链接由深度链接插件处理
+                _ if deep_link::is_deep_link(&arg) => {}
+                _ => tracing::warn!("Ignoring unknown argument {}", arg),
+            }
+        }
+        parsed
+    }
+}
+
+impl AppState {
+    /// 是否已开始关闭
+    pub fn is_closing(&self) -> bool {
+        self.closing.load(Ordering::SeqCst)
+    }
+
+    /// 是否处于恢复模式（数据文件丢失或无法打开）
+    pub fn in_recovery(&self) -> Result<bool, String> {
+        let startup = self.startup.lock().map_err(|e| e.to_string())?;
+        Ok(startup.recovery.is_some())
+    }
+
+    /// 换用另一个数据库：先写入当前数据库未保存的修改（手动保存模式下拒绝），并转发新数据库的设置变化
+    pub fn replace_database(
+        &self,
+        app: &tauri::AppHandle,
+        current_db: &mut Database,
+        mut db: Database,
+    ) -> Result<(), String> {
+        if current_db.has_unsaved_changes() {
+            if current_db.save_status().mode == SaveMode::Manual {
+                return Err("Save changes before switching the data file".to_string());
+            }
+            current_db.save_now().map_err(|e| e.to_string())?;
+        }
+        forward_settings_changes(app, &mut db, &self.app_context);
+        *self.app_context.write().map_err(|e| e.to_string())? =
+            AppContext::from_database(&db).map_err(|e| e.to_string())?;
+        current_db.release_lock();
+        *current_db = db;
+        Ok(())
+    }
+
+    /// 应用并保存新配置：数据文件或插件目录变化时重新打开，无需重启
+    ///
+    /// 替换时等待进行中的命令释放锁；已取得的快照仍使用原来的数据。
+    /// 手动保存模式下有未保存的修改时拒绝切换数据文件。便携模式下路径限定在程序旁的目录中。
+    pub fn apply_config(&self, app: &tauri::AppHandle, config: AppConfig) -> Result<(), String> {
+        // 恢复模式下只能通过恢复命令打开数据文件，避免在丢失的数据文件位置新建空文件
+        if self.in_recovery()? {
+            return Err("Resolve the data file problem first".to_string());
+        }
+        if self.launch.safe_mode {
+            return Err("Settings cannot be changed in safe mode".to_string());
+        }
+        let config = self.paths.enforce(config);
+        let current = self.config.read().map_err(|e| e.to_string())?.clone();
+        // 首次运行尚未选择位置时使用的是内存数据库，查看其他文件时以只读方式打开
+        let reopen = {
+            let db = self.db.lock().map_err(|e| e.to_string())?;
+            db.path().is_none() || db.is_read_only()
+        };
+        let db_changed = reopen || config.db_path != current.db_path;
+        let plugins_changed = config.plugins_dir != current.plugins_dir;
+
+        // 先打开新的数据文件，失败时保持原配置
+        let mut db = if db_changed {
+            Some(open_database(&config.db_path).map_err(|e| e.to_string())?)
+        } else {
+            None
+        };
+        if plugins_changed {
+            let plugin_manager = load_plugins(
+                &config.plugins_dir,
+                &self.app_context,
+                self.launch.safe_mode,
+            );
+            *self.plugin_manager.lock().map_err(|e| e.to_string())? = plugin_manager;
+            info!("Plugins reloaded from {}", config.plugins_dir);
+        }
+        let schemas = self
+            .plugin_manager
+            .lock()
+            .map_err(|e| e.to_string())?
+            .metadata_schemas();
+
+        {
+            let mut current_db = self.db.lock().map_err(|e| e.to_string())?;
+            if let Some(db) = db.take() {
+                self.replace_database(app, &mut current_db, db)?;
+                info!("Switched data file to {}", config.db_path);
+            }
+            current_db.set_plugin_metadata_schemas(schemas);
+        }
+
+        // 命令行指定的数据文件与运行时切换到的档案只用于本次运行，配置文件中保留原来的数据文件
+        let mut saved = config.clone();
+        let profiles = self.paths.profiles();
+        let profile = profiles.find(std::path::Path::new(&config.db_path));
+        if self.launch.db.as_deref() == Some(config.db_path.as_str()) || profile.is_some() {
+            if let Ok(Some(file)) = AppConfig::load(&self.paths.config_file) {
+                saved.db_path = file.db_path;
+                // 档案的备份目录同样不写入配置
+                let profile_backups = profile.and_then(|name| profiles.backups_dir(&name).ok());
+                if profile_backups.as_deref() == Some(std::path::Path::new(&config.backups_dir)) {
+                    saved.backups_dir = file.backups_dir;
+                }
+            }
+        }
+        if let Err(e) = saved.save(&self.paths.config_file) {
+            tracing::warn!("Failed to save config: {}", e);
+        }
+        *self.config.write().map_err(|e| e.to_string())? = config.clone();
+        emit(app, "config://changed", &config);
+        if db_changed {
+            emit(app, "database://reloaded", ());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+impl AppState {
+    /// 测试用状态：内存数据库与模拟数据源，以便携模式把配置与插件目录放在 `dir` 中
+    /// （插件目录可放入记录事件的模拟插件），无需启动窗口或联网即可调用命令
+    pub fn for_tests(dir: &std::path::Path) -> Self {
+        let paths = AppPaths::portable(dir);
+        let config = AppConfig {
+            mock_providers: true,
+            ..paths.default_config()
+        };
+        let app_context = SharedAppContext::default();
+        let plugin_manager = load_plugins(&config.plugins_dir, &app_context, false);
+        let mut db = Database::open_in_memory().expect("Failed to open database");
+        db.set_plugin_metadata_schemas(plugin_manager.metadata_schemas());
+        forward_plugin_settings(&mut db, &app_context);
+        let db = Arc::new(Mutex::new(db));
+        Self {
+            async_db: AsyncDatabase::shared(db.clone()).expect("Failed to start database thread"),
+            db,
+            plugin_manager: Mutex::new(plugin_manager),
+            app_context,
+            config: RwLock::new(config),
+            paths,
+            migration: Mutex::default(),
+            startup: Mutex::default(),
+            usage: Mutex::default(),
+            closing: AtomicBool::default(),
+            launch: LaunchArgs::default(),
+            pending_link: Mutex::default(),
+        }
+    }
 }
 
 fn main() {
@@ -25,23 +259,111 @@ fn main() {
 
     info!("Starting Asset Manager...");
 
-    // 加载配置
-    let config = AppConfig::default();
+    // 确定文件位置（便携模式下都在程序旁，使用档案时在档案目录中）并加载配置
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let mut launch = LaunchArgs::parse(args.iter().cloned());
+    let mut paths = AppPaths::detect(args);
+    if let Some(root) = &paths.portable_root {
+        info!("Portable mode, data in {:?}", root);
+    }
+    if let Some(profile) = launch.profile.clone() {
+        match paths.clone().with_profile(&profile) {
+            Ok(profile_paths) => {
+                info!("Using profile {}", profile);
+                paths = profile_paths;
+            }
+            Err(e) => {
+                tracing::warn!("{}", e);
+                launch.profile = None;
+            }
+        }
+    }
+    let mut config = paths.load_config();
+    if let Some(db_path) = launch.db.take() {
+        config = paths.enforce(AppConfig { db_path, ..config });
+        info!("Using data file {} for this session", config.db_path);
+        launch.db = Some(config.db_path.clone());
+    }
+    if launch.safe_mode {
+        // 只保留数据文件与备份的位置，其他配置使用默认值
+        info!(
+            "Safe mode, opening {} read-only without plugins",
+            config.db_path
+        );
+        config = AppConfig {
+            db_path: config.db_path,
+            backups_dir: config.backups_dir,
+            ..paths.default_config()
+        };
+    }
 
-    // 初始化 JSON 存储
-    let db = Database::open(&config.db_path).expect("Failed to open database");
+    // 初始化 JSON 存储（首次运行时先使用内存数据库，等待用户选择数据位置；
+    // 升级后需要迁移时也先使用内存数据库，迁移完成后再打开）
+    // 数据文件丢失或无法打开时改用内存数据库并进入恢复模式，由用户在窗口中选择处理方式
+    // 安全模式下只读打开，不迁移也不进入恢复模式
+    let mut startup = StartupStatus::default();
+    let first_run = launch.db.is_none() && FirstRunState::detect(&paths).first_run;
+    let migrating = !first_run
+        && !launch.safe_mode
+        && storage::pending_migrations(&config.db_path).is_ok_and(|pending| !pending.is_empty());
+    let opened = if first_run {
+        info!("First run, waiting for data location");
+        Database::open_in_memory()
+    } else if migrating {
+        info!("Data file needs migration");
+        Database::open_in_memory()
+    } else if launch.safe_mode {
+        Database::open_read_only(&config.db_path)
+    } else if !std::path::Path::new(&config.db_path).exists() {
+        Err(StorageError::NotFound(config.db_path.clone()))
+    } else {
+        open_database(&config.db_path)
+    };
+    let mut db = opened.unwrap_or_else(|e| {
+        tracing::error!("Failed to open database {}: {}", config.db_path, e);
+        startup.errors.push(StartupError {
+            step: StartupStep::OpenDatabase,
+            message: e.to_string(),
+        });
+        if !launch.safe_mode {
+            startup.recovery = Some(RecoveryInfo::new(&config.db_path, &config.backups_dir, &e));
+        }
+        Database::open_in_memory().expect("Failed to open in-memory database")
+    });
 
     // 初始化插件管理器
-    let mut plugin_manager = PluginManager::new(&config.plugins_dir);
-    if let Err(e) = plugin_manager.load_all() {
-        tracing::warn!("Failed to load plugins: {}", e);
-    }
+    let app_context = AppContext::from_database(&db).unwrap_or_else(|e| {
+        tracing::error!("Failed to read settings: {}", e);
+        startup.errors.push(StartupError {
+            step: StartupStep::Settings,
+            message: e.to_string(),
+        });
+        AppContext::default()
+    });
+    let app_context = Arc::new(RwLock::new(app_context));
+    let plugin_manager = load_plugins(&config.plugins_dir, &app_context, launch.safe_mode);
+
+    db.set_plugin_metadata_schemas(plugin_manager.metadata_schemas());
 
     // 构建应用状态
+    let usage = UsageTracker::load(paths.usage_file());
+    let db = Arc::new(Mutex::new(db));
     let state = AppState {
-        db: Mutex::new(db),
+        async_db: AsyncDatabase::shared(db.clone()).expect("Failed to start database thread"),
+        db,
         plugin_manager: Mutex::new(plugin_manager),
-        config,
+        app_context,
+        config: RwLock::new(config),
+        paths,
+        migration: Mutex::new(MigrationStatus {
+            running: migrating,
+            ..MigrationStatus::default()
+        }),
+        startup: Mutex::new(startup),
+        usage: Mutex::new(usage),
+        closing: AtomicBool::default(),
+        launch,
+        pending_link: Mutex::default(),
     };
 
     // 启动 Tauri 应用
@@ -49,19 +371,673 @@ fn main() {
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_fs::init())
+        .plugin(tauri_plugin_updater::Builder::new().build())
+        .plugin(tauri_plugin_deep_link::init())
         .manage(state)
-        .invoke_handler(tauri::generate_handler![
+        .setup(move |app| {
+            run_startup(app.handle(), migrating);
+            watch_deep_links(app.handle());
+            Ok(())
+        })
+        .invoke_handler(track_usage(tauri::generate_handler![
             commands::get_assets,
             commands::get_asset,
             commands::create_asset,
+            commands::create_assets_bulk,
+            commands::upsert_asset,
             commands::update_asset,
+            commands::get_asset_by_external_id,
+            commands::query_assets,
+            commands::query_assets_by_metadata,
             commands::delete_asset,
+            commands::delete_asset_permanently,
+            commands::list_trashed,
+            commands::restore_asset,
+            commands::purge_trash,
+            commands::get_asset_history_diff,
+            commands::query_audit_log,
+            commands::get_audit_settings,
+            commands::set_audit_settings,
             commands::search_assets,
+            commands::search_assets_ranked,
+            commands::get_search_settings,
+            commands::set_search_settings,
+            commands::get_assumptions,
+            commands::set_assumptions,
+            commands::get_asset_performance,
             commands::get_summary,
+            commands::get_interpolated_series,
+            commands::get_daily_balances,
+            commands::get_valuation_history,
+            commands::get_attribution,
+            commands::get_timeline,
+            commands::get_fx_exposure,
+            commands::get_fx_settings,
+            commands::set_fx_settings,
+            commands::get_fx_rates,
+            commands::add_fx_rates,
+            commands::backfill_fx_rates,
+            commands::lookup_symbol,
+            commands::get_net_worth_series,
+            commands::get_chart_series,
+            commands::get_precision_settings,
+            commands::set_precision_settings,
+            commands::get_format_settings,
+            commands::set_format_settings,
+            commands::format_values,
+            commands::get_tag_settings,
+            commands::set_tag_settings,
+            commands::get_tag_aliases,
+            commands::set_tag_alias,
+            commands::remove_tag_alias,
+            commands::get_display_catalog,
+            commands::set_display,
+            commands::get_save_settings,
+            commands::set_save_settings,
+            commands::get_save_status,
+            commands::save_now,
+            commands::rebuild_derived_data,
+            commands::compact_database,
+            commands::get_store_conflict,
+            commands::resolve_store_conflict,
+            commands::parse_quick_entry,
+            commands::export_for_edit,
+            commands::preview_edits,
+            commands::import_edits,
+            commands::get_transactions,
+            commands::get_transactions_page,
+            commands::list_recent_transactions,
+            commands::get_transactions_by_status,
+            commands::set_transaction_status,
+            commands::update_transaction,
+            commands::delete_transaction,
+            commands::add_correction,
+            commands::add_foreign_transaction,
+            commands::apply_corporate_action,
+            commands::sync_dividends,
+            commands::sell_asset,
+            commands::get_realized_gains,
+            commands::recalculate_cost_basis,
+            commands::create_sip_plan,
+            commands::update_sip_plan,
+            commands::get_sip_plans,
+            commands::delete_sip_plan,
+            commands::record_sip_installment,
+            commands::get_sip_report,
+            commands::get_fx_gain_loss,
+            commands::add_scheduled_transaction,
+            commands::get_upcoming_transactions,
+            commands::get_projected_balances,
+            commands::post_due_transactions,
+            commands::create_envelope,
+            commands::get_envelopes,
+            commands::allocate_to_envelope,
+            commands::move_between_envelopes,
+            commands::delete_envelope,
+            commands::add_shared_expense,
+            commands::get_shared_expenses,
+            commands::delete_shared_expense,
+            commands::get_split_balances,
+            commands::get_suggested_settlements,
+            commands::get_settlements,
+            commands::record_settlement,
+            commands::is_double_entry_enabled,
+            commands::enable_double_entry,
+            commands::disable_double_entry,
+            commands::get_ledger_accounts,
+            commands::add_ledger_account,
+            commands::get_ledger_entries,
+            commands::post_ledger_entry,
+            commands::get_trial_balance,
+            commands::close_period,
+            commands::reopen_period,
+            commands::get_closed_periods,
+            commands::import_gnucash,
+            commands::export_gnucash,
+            commands::get_import_profiles,
+            commands::save_import_profile,
+            commands::delete_import_profile,
+            commands::list_importers,
+            commands::preview_statement_file,
+            commands::import_statement_file,
+            commands::sniff_statement_file,
+            commands::list_import_batches,
+            commands::undo_import,
+            commands::get_watch_folders,
+            commands::add_watch_folder,
+            commands::delete_watch_folder,
+            commands::scan_watch_folders,
+            commands::get_trade_profiles,
+            commands::save_trade_profile,
+            commands::delete_trade_profile,
+            commands::import_trade_file,
+            commands::get_email_settings,
+            commands::set_email_settings,
+            commands::get_monthly_report,
+            commands::send_report_email,
+            commands::get_report_templates,
+            commands::render_report,
+            commands::add_asset_relation,
+            commands::remove_asset_relation,
+            commands::get_related_assets,
+            commands::get_relation_graph,
+            commands::get_templates,
+            commands::create_template,
+            commands::delete_template,
+            commands::apply_template,
+            commands::get_rules,
+            commands::save_rule,
+            commands::delete_rule,
+            commands::get_reminders,
+            commands::create_reminder,
+            commands::complete_reminder,
+            commands::snooze_reminder,
+            commands::delete_reminder,
+            commands::get_notifications,
+            commands::mark_notification_read,
+            commands::get_milestones,
+            commands::get_check_ins,
+            commands::save_check_in,
+            commands::delete_check_in,
+            commands::add_attachment,
+            commands::list_attachments,
+            commands::remove_attachment,
             commands::get_plugins,
             commands::reload_plugins,
             commands::set_plugin_enabled,
-        ])
-        .run(tauri::generate_context!())
-        .expect("Error running tauri application");
+            commands::get_ipc_capabilities,
+            commands::get_assets_binary,
+            commands::get_transactions_binary,
+            commands::get_daily_balances_binary,
+            commands::get_metadata_schemas,
+            commands::validate_asset_metadata,
+            commands::get_config,
+            commands::apply_config,
+            commands::get_first_run_state,
+            commands::complete_first_run,
+            commands::check_for_update,
+            commands::get_backup_settings,
+            commands::set_backup_settings,
+            commands::list_backups,
+            commands::restore_backup,
+            commands::export_archive,
+            commands::import_archive,
+            commands::merge_database,
+            commands::install_update,
+            commands::get_migration_status,
+            commands::get_startup_status,
+            commands::get_launch_args,
+            commands::take_deep_link,
+            commands::list_profiles,
+            commands::create_profile,
+            commands::switch_profile,
+            commands::open_read_only_file,
+            commands::close_read_only_file,
+            commands::recover_from_backup,
+            commands::recover_with_new_file,
+            commands::recover_with_file,
+            commands::get_usage_stats,
+        ]))
+        .build(tauri::generate_context!())
+        .expect("Error building tauri application")
+        .run(|app, event| {
+            if let tauri::RunEvent::ExitRequested { .. } | tauri::RunEvent::Exit = event {
+                shutdown(app);
+            }
+        });
+}
+
+/// 启动流程：转发设置变化、数据迁移（在后台进行）、启动后台任务、通知插件应用已启动
+///
+/// 出错的步骤记入启动状态并通知窗口，不中止启动。安全模式下不启动后台任务。
+fn run_startup(app: &tauri::AppHandle, migrating: bool) {
+    let state = app.state::<AppState>();
+    match state.db.lock() {
+        Ok(mut db) => forward_settings_changes(app, &mut db, &state.app_context),
+        Err(e) => startup_error(app, StartupStep::Settings, e.to_string()),
+    }
+    if migrating {
+        start_task(app, "migrations", run_migrations);
+    }
+
+    if !state.launch.safe_mode {
+        start_task(app, "import-watch", watch_import_folders);
+        start_task(app, "monthly-reports", send_monthly_reports);
+        start_task(app, "autosave", autosave);
+        start_task(app, "backups", scheduled_backups);
+        start_task(app, "store-lock", keep_store_lock);
+        start_task(app, "data-file-watch", watch_data_file);
+        start_task(app, "rules", evaluate_rules);
+    }
+
+    match state.plugin_manager.lock() {
+        Ok(pm) => pm.broadcast_event(&PluginEvent::AppStarted),
+        Err(e) => startup_error(app, StartupStep::AppStarted, e.to_string()),
+    }
+
+    let status = match state.startup.lock() {
+        Ok(mut status) => {
+            status.finished = true;
+            status.clone()
+        }
+        Err(_) => return,
+    };
+    info!("Startup finished with {} errors", status.errors.len());
+    emit(app, "startup://finished", status);
+}
+
+/// 处理 `assetmgr://` 链接：启动时的链接留给窗口加载后取走，运行中打开的链接直接通知窗口
+fn watch_deep_links(app: &tauri::AppHandle) {
+    use tauri_plugin_deep_link::DeepLinkExt;
+
+    // 未安装时（开发模式）在 Windows 与 Linux 上注册链接协议
+    #[cfg(all(debug_assertions, any(windows, target_os = "linux")))]
+    if let Err(e) = app.deep_link().register_all() {
+        tracing::warn!("Failed to register link scheme: {}", e);
+    }
+    match app.deep_link().get_current() {
+        Ok(urls) => {
+            for url in urls.unwrap_or_default() {
+                open_deep_link(app, url.as_str(), true);
+            }
+        }
+        Err(e) => tracing::warn!("Failed to read launch link: {}", e),
+    }
+    let handle = app.clone();
+    app.deep_link().on_open_url(move |event| {
+        for url in event.urls() {
+            open_deep_link(&handle, url.as_str(), false);
+        }
+    });
+}
+
+/// 解析链接并通过 `deep-link://navigate` 通知窗口跳转，不合法的链接发送 `deep-link://invalid`
+fn open_deep_link(app: &tauri::AppHandle, url: &str, at_launch: bool) {
+    match DeepLink::parse(url) {
+        Ok(link) => {
+            info!("Opening link {}", url);
+            if at_launch {
+                if let Ok(mut pending) = app.state::<AppState>().pending_link.lock() {
+                    *pending = Some(link.clone());
+                }
+            }
+            emit(app, "deep-link://navigate", link);
+        }
+        Err(e) => {
+            tracing::warn!("Ignoring link {}: {}", url, e);
+            emit(app, "deep-link://invalid", e);
+        }
+    }
+}
+
+/// 在新线程中运行后台任务
+fn start_task(app: &tauri::AppHandle, name: &str, task: fn(tauri::AppHandle)) {
+    let handle = app.clone();
+    let started = std::thread::Builder::new()
+        .name(name.to_string())
+        .spawn(move || task(handle));
+    if let Err(e) = started {
+        startup_error(app, StartupStep::Schedulers, format!("{}: {}", name, e));
+    }
+}
+
+/// 记入启动流程中出错的一步，并通知窗口
+fn startup_error(app: &tauri::AppHandle, step: StartupStep, message: String) {
+    tracing::warn!("Startup step {:?} failed: {}", step, message);
+    let error = StartupError { step, message };
+    if let Ok(mut status) = app.state::<AppState>().startup.lock() {
+        status.errors.push(error.clone());
+    }
+    emit(app, "startup://error", error);
+}
+
+/// 调用命令前记入本地使用统计（只记录命令名称）
+fn track_usage(
+    handler: impl Fn(tauri::ipc::Invoke) -> bool + Send + Sync + 'static,
+) -> impl Fn(tauri::ipc::Invoke) -> bool + Send + Sync + 'static {
+    move |invoke| {
+        let state = invoke.message.webview_ref().state::<AppState>();
+        if let Ok(mut usage) = state.usage.lock() {
+            usage.record(invoke.message.command(), chrono::Utc::now());
+        }
+        handler(invoke)
+    }
+}
+
+/// 升级后执行数据迁移并报告进度，完成后打开数据文件，界面随后加载数据
+fn run_migrations(app: tauri::AppHandle) {
+    let state = app.state::<AppState>();
+    let Ok(config) = state.config.read().map(|config| config.clone()) else {
+        return;
+    };
+    let result = storage::migrate_file(&config.db_path, |progress| {
+        info!("Migrating data file: {}", progress.description);
+        if let Ok(mut status) = state.migration.lock() {
+            status.progress = Some(progress.clone());
+        }
+        emit(&app, "migration://progress", progress.clone());
+    })
+    .map_err(|e| e.to_string())
+    .and_then(|_| state.apply_config(&app, config));
+
+    let Ok(mut status) = state.migration.lock() else {
+        return;
+    };
+    status.running = false;
+    match result {
+        Ok(()) => emit(&app, "migration://finished", ()),
+        Err(e) => {
+            tracing::warn!("Data migration failed: {}", e);
+            status.error = Some(e.clone());
+            startup_error(&app, StartupStep::Migrations, e.clone());
+            emit(&app, "migration://failed", e);
+        }
+    }
+}
+
+/// 定时保存与延迟保存模式下定期写入未保存的修改
+fn autosave(app: tauri::AppHandle) {
+    let mut wait = Duration::from_secs(AUTOSAVE_CHECK_INTERVAL_SECS);
+    loop {
+        std::thread::sleep(wait);
+        let state = app.state::<AppState>();
+        if state.is_closing() {
+            break;
+        }
+        let Ok(mut db) = state.db.lock() else {
+            break;
+        };
+        if let Err(e) = db.autosave(chrono::Utc::now()) {
+            tracing::warn!("Autosave failed: {}", e);
+        }
+        wait = db.autosave_check_interval();
+    }
+}
+
+/// 按备份设置定时备份到备份目录，并清理旧备份
+fn scheduled_backups(app: tauri::AppHandle) {
+    loop {
+        let state = app.state::<AppState>();
+        if state.is_closing() {
+            break;
+        }
+        let Ok(backups_dir) = state.config.read().map(|c| c.backups_dir.clone()) else {
+            break;
+        };
+        let Ok(db) = state.db.lock() else {
+            break;
+        };
+        // 首次运行、迁移与恢复模式下使用的内存数据库不备份，以免轮换掉数据文件的备份
+        let result = match db.path() {
+            Some(_) => db.scheduled_backup(&backups_dir, chrono::Utc::now()),
+            None => Ok(None),
+        };
+        match result {
+            Ok(Some(backup)) => info!("Scheduled backup written to {}", backup.path),
+            Ok(None) => {}
+            Err(e) => tracing::warn!("Scheduled backup failed: {}", e),
+        }
+        drop(db);
+        std::thread::sleep(Duration::from_secs(BACKUP_CHECK_INTERVAL_SECS));
+    }
+}
+
+/// 定期刷新数据文件锁的心跳
+fn keep_store_lock(app: tauri::AppHandle) {
+    loop {
+        std::thread::sleep(Duration::from_secs(LOCK_HEARTBEAT_SECS));
+        let state = app.state::<AppState>();
+        if state.is_closing() {
+            break;
+        }
+        let Ok(mut db) = state.db.lock() else {
+            break;
+        };
+        if let Err(e) = db.refresh_lock() {
+            tracing::warn!("Failed to refresh data file lock: {}", e);
+        }
+    }
+}
+
+/// 打开数据文件，并入账已到期的计划交易
+fn open_database(path: &str) -> Result<Database, StorageError> {
+    let mut db = Database::open(path)?;
+    if let Err(e) = db.post_due_transactions(chrono::Utc::now()) {
+        tracing::warn!("Failed to post scheduled transactions: {}", e);
+    }
+    Ok(db)
+}
+
+/// 创建插件管理器并加载插件目录中的插件（安全模式下不加载）
+fn load_plugins(
+    plugins_dir: &str,
+    app_context: &SharedAppContext,
+    safe_mode: bool,
+) -> PluginManager {
+    let mut plugin_manager =
+        PluginManager::new(plugins_dir).with_app_context(Arc::clone(app_context));
+    if safe_mode {
+        return plugin_manager;
+    }
+    if let Err(e) = plugin_manager.load_all() {
+        tracing::warn!("Failed to load plugins: {}", e);
+    }
+    plugin_manager
+}
+
+/// 设置变化时通知窗口刷新与插件，并更新插件读取的设置
+fn forward_settings_changes(
+    app: &tauri::AppHandle,
+    db: &mut Database,
+    app_context: &SharedAppContext,
+) {
+    // 先更新插件读取的设置，插件收到通知时即可读到新值
+    forward_plugin_settings(db, app_context);
+    let app = app.clone();
+    db.subscribe_all_settings(move |key, _| {
+        emit(&app, "settings://changed", key);
+        // 回调时持有数据库，在新线程中通知插件，避免与先锁插件管理器的命令死锁
+        let (handle, key) = (app.clone(), key.to_string());
+        std::thread::spawn(move || {
+            if let Ok(pm) = handle.state::<AppState>().plugin_manager.lock() {
+                pm.broadcast_event(&PluginEvent::SettingChanged(key));
+            }
+        });
+    });
+}
+
+/// 设置变化时更新插件读取的设置（回调中不访问数据库，避免死锁）
+fn forward_plugin_settings(db: &mut Database, app_context: &SharedAppContext) {
+    let app_context = Arc::clone(app_context);
+    db.subscribe_all_settings(move |key, raw| {
+        if let Ok(mut context) = app_context.write() {
+            context.update(key, raw);
+        }
+    });
+}
+
+fn emit(app: &tauri::AppHandle, event: &str, payload: impl serde::Serialize + Clone) {
+    if let Err(e) = app.emit(event, payload) {
+        tracing::warn!("Failed to emit {}: {}", event, e);
+    }
+}
+
+/// 数据文件被外部修改（命令行编辑或其他设备同步）时重新加载，并通知窗口刷新
+fn watch_data_file(app: tauri::AppHandle) {
+    loop {
+        std::thread::sleep(Duration::from_secs(EXTERNAL_CHECK_INTERVAL_SECS));
+        let outcome = {
+            let state = app.state::<AppState>();
+            if state.is_closing() {
+                break;
+            }
+            let Ok(mut db) = state.db.lock() else {
+                break;
+            };
+            db.reload_if_changed()
+        };
+        let event = match outcome {
+            Ok(ReloadOutcome::Unchanged) => continue,
+            Ok(ReloadOutcome::Reloaded) => "database://reloaded",
+            Ok(ReloadOutcome::Conflict) => "database://conflict",
+            Err(e) => {
+                tracing::warn!("Failed to reload data file: {}", e);
+                continue;
+            }
+        };
+        info!("Data file changed externally: {}", event);
+        emit(&app, event, ());
+    }
+}
+
+/// 关闭应用（只执行一次）：停止后台任务，通知插件，等待进行中的命令与任务释放数据库后
+/// 写入未保存的修改并释放锁
+fn shutdown<R: tauri::Runtime>(app: &tauri::AppHandle<R>) {
+    let state = app.state::<AppState>();
+    if state.closing.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    info!("Shutting down...");
+    notify_plugins_closing(app);
+    save_on_exit(app);
+}
+
+/// 向插件广播应用关闭事件，至多等待 `APP_CLOSING_TIMEOUT_SECS` 秒，超时后不再等待
+fn notify_plugins_closing<R: tauri::Runtime>(app: &tauri::AppHandle<R>) {
+    let (done, finished) = mpsc::channel();
+    let handle = app.clone();
+    std::thread::spawn(move || {
+        if let Ok(pm) = handle.state::<AppState>().plugin_manager.lock() {
+            pm.broadcast_event(&PluginEvent::AppClosing);
+        }
+        let _ = done.send(());
+    });
+    let timeout = Duration::from_secs(APP_CLOSING_TIMEOUT_SECS);
+    if finished.recv_timeout(timeout).is_err() {
+        tracing::warn!(
+            "Plugins did not finish on_app_closing within {}s",
+            APP_CLOSING_TIMEOUT_SECS
+        );
+    }
+}
+
+/// 退出时写入定时保存模式下未保存的修改（手动保存模式由用户决定），并释放数据文件锁
+fn save_on_exit<R: tauri::Runtime>(app: &tauri::AppHandle<R>) {
+    let state = app.state::<AppState>();
+    if let Ok(mut usage) = state.usage.lock() {
+        if let Err(e) = usage.flush() {
+            tracing::warn!("Failed to save usage stats: {}", e);
+        }
+    }
+    let Ok(mut db) = state.db.lock() else {
+        return;
+    };
+    if db.has_unsaved_changes() {
+        if db.save_status().mode == SaveMode::Manual {
+            tracing::warn!("Exiting with unsaved changes");
+        } else if let Err(e) = db.save_now() {
+            tracing::warn!("Failed to save on exit: {}", e);
+        }
+    }
+    db.release_lock();
+}
+
+/// 定期执行到期的定时规则与条件规则
+fn evaluate_rules(app: tauri::AppHandle) {
+    loop {
+        std::thread::sleep(Duration::from_secs(RULES_CHECK_INTERVAL_SECS));
+        let state = app.state::<AppState>();
+        if state.is_closing() {
+            break;
+        }
+        commands::run_rules(&state, RuleSignal::Tick);
+    }
+}
+
+/// 定期扫描监视目录，自动导入放入的对账单
+fn watch_import_folders(app: tauri::AppHandle) {
+    loop {
+        std::thread::sleep(Duration::from_secs(import::WATCH_INTERVAL_SECS));
+        let state = app.state::<AppState>();
+        if state.is_closing() {
+            break;
+        }
+        let Ok(mut db) = state.db.lock() else {
+            break;
+        };
+        if let Err(e) = import::scan_watch_folders(&mut db) {
+            tracing::warn!("Failed to scan watch folders: {}", e);
+        }
+    }
+}
+
+/// 定期检查并发送到期的月度报告邮件
+fn send_monthly_reports(app: tauri::AppHandle) {
+    loop {
+        let state = app.state::<AppState>();
+        if state.is_closing() {
+            break;
+        }
+        let snapshot = match state.db.lock() {
+            Ok(db) => db.snapshot(),
+            Err(_) => break,
+        };
+        let due = email::ReportEmail::due(&snapshot, chrono::Utc::now());
+        match due {
+            Ok(Some(report_email)) => match report_email.send() {
+                Ok(()) => {
+                    info!("Sent monthly report {}", report_email.report.label());
+                    if let Ok(mut db) = state.db.lock() {
+                        let report = &report_email.report;
+                        if let Err(e) = db.mark_report_sent(report.year, report.month) {
+                            tracing::warn!("Failed to record sent report: {}", e);
+                        }
+                    }
+                }
+                Err(e) => tracing::warn!("Failed to send monthly report: {}", e),
+            },
+            Ok(None) => {}
+            Err(e) => tracing::warn!("Failed to prepare monthly report: {}", e),
+        }
+        std::thread::sleep(Duration::from_secs(email::REPORT_CHECK_INTERVAL_SECS));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> LaunchArgs {
+        LaunchArgs::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn test_launch_args() {
+        assert_eq!(parse(&[]), LaunchArgs::default());
+        let args = [
+            "--portable",
+            "--db",
+            "D:/a.json",
+            "--profile=work",
+            "--safe-mode",
+        ];
+        assert_eq!(
+            parse(&args),
+            LaunchArgs {
+                db: Some("D:/a.json".to_string()),
+                profile: Some("work".to_string()),
+                safe_mode: true,
+            }
+        );
+        // 缺少值时忽略该参数，不吞掉下一个参数
+        let args = parse(&[
+            "--db",
+            "--safe-mode",
+            "--unknown",
+            "assetmgr://add?amount=1",
+        ]);
+        assert_eq!(args.db, None);
+        assert!(!args.safe_mode);
+        assert!(parse(&["--profile="]).profile.is_none());
+    }
 }